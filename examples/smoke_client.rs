@@ -0,0 +1,207 @@
+//! Smoke test for a running deployment: connect, list the tools, call each one with a known-good
+//! input and print a PASS/FAIL table. Exits with status 1 if any call failed.
+//!
+//! ```bash
+//! # Streamable HTTP (defaults to http://127.0.0.1:8001/mcp)
+//! cargo run --example smoke_client -- https://engine.example.com/mcp
+//!
+//! # stdio: the client starts the server itself
+//! cargo run --example smoke_client -- stdio ./target/release/stdio_server
+//! ```
+//!
+//! Arguments that name configured entries (fee codes, heir classes, currencies) are asked from the
+//! server through completion, so the calls fit the deployment's configuration.
+
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use rmcp::model::{CallToolRequestParams, CallToolResult};
+use rmcp::service::RunningService;
+use rmcp::transport::StreamableHttpClientTransport;
+use rmcp::{RoleClient, ServiceExt};
+use serde_json::{json, Value};
+
+const DEFAULT_URL: &str = "http://127.0.0.1:8001/mcp";
+
+/// Longest failure detail printed in the table
+const MAX_DETAIL: usize = 100;
+
+/// Tools not called: their results depend on what the server has seen since it started, or they
+/// change its configuration or stored data
+const NOT_EXERCISED: &[&str] = &[
+    "get_usage_report",
+    "get_recent_schema_failures",
+    "upsert_profile",
+    "verify_store_integrity",
+    "purge_expired_data",
+    "export_subject_data",
+    "erase_subject_data",
+];
+
+/// A configured entry asked from the server: (tool, argument, fallback)
+struct Configured(&'static str, &'static str, &'static str);
+
+/// Known-good arguments per tool. `Configured` values are filled in by completion.
+fn golden_inputs() -> Vec<(&'static str, Value)> {
+    vec![
+        ("calc_penalty", json!({"days_late": 12, "rate_per_day": 150, "cap": 1000, "interest_rate": 0.05})),
+        ("calc_tax", json!({"income": 90000})),
+        ("check_voting", json!({"eligible_voters": 100, "turnout": 70, "yes_votes": 50, "proposal_type": "general"})),
+        ("validate_vote_record", json!({"eligible_voters": 100, "turnout": 70, "yes_votes": 40, "no_votes": 30})),
+        ("distribute_waterfall", json!({"cash_available": 1000, "senior_debt": 600, "junior_debt": 300})),
+        ("check_covenants", json!({"total_debt": 3000, "ebitda": 1000, "interest_expense": 200})),
+        ("calc_npv", json!({"cash_flows": [-1000, 300, 400, 500], "discount_rate": 0.08})),
+        ("calc_irr", json!({"cash_flows": [-1000, 300, 400, 500]})),
+        ("check_escrow_release", json!({"escrow_amount": 10000, "as_of_date": "2024-06-01", "milestones": [{"name": "Design", "completed": true, "release_percentage": 0.4}]})),
+        ("check_housing_grant", json!({"ami": 80000, "household_size": 5, "income": 45000, "has_other_subsidy": false})),
+        ("calc_capital_gains", json!({"acquisition_cost": 10000, "sale_price": 15000, "holding_period_days": 400})),
+        ("calc_inheritance_tax", json!({"beneficiaries": [{"name": "Ana", "relationship": "{relationship}", "amount": 250000}]})),
+        ("calc_social_contributions", json!({"gross_salary": 5000})),
+        ("calc_property_tax", json!({"assessed_value": 300000, "prior_year_value": 250000, "homestead": true})),
+        ("calc_transfer_tax", json!({"price": 350000})),
+        ("calc_fee", json!({"fee_code": "{fee_code}", "value": 1200})),
+        ("index_amount", json!({"amount": 1000})),
+        ("convert_currency", json!({"amount": 100, "from_currency": "{from_currency}"})),
+        ("get_intake_schema", json!({"program": "housing_grant"})),
+        ("prescreen", json!({"program": "housing_grant", "inputs": {"has_other_subsidy": true}})),
+        ("list_tools_by_category", json!({"category": "finance"})),
+        ("describe_capabilities", json!({})),
+        ("diff_profiles", json!({"before": {"ENGINE_AMT_RATE": {"value": "0.15"}}, "after": {"ENGINE_AMT_RATE": {"value": "0.2"}}})),
+        ("check_appeal_deadline", json!({"decision_date": "2026-03-02", "filing_date": "2026-03-30"})),
+        ("get_required_documents", json!({"program": "housing_grant", "situation": ["self_employed"]})),
+        ("check_voting_bulk", json!({"proposal_type": "general", "precincts": [
+            {"name": "North", "eligible_voters": 60, "turnout": 45, "yes_votes": 30},
+            {"name": "South", "eligible_voters": 40, "turnout": 20, "yes_votes": 12},
+        ]})),
+        ("project_quorum", json!({"eligible_voters": 200, "turnout": 90, "yes_votes": 50, "hours_elapsed": 6, "hours_remaining": 2})),
+        ("stress_config", json!({})),
+        ("evaluate_ranges", json!({"tool": "check_housing_grant", "arguments": {"ami": 80000, "household_size": 5, "income": {"min": 50000, "max": 56000}, "has_other_subsidy": false}})),
+        ("simulate_uncertainty", json!({"tool": "check_housing_grant", "arguments": {"ami": 80000, "household_size": 5, "income": {"distribution": "normal", "mean": 52000, "sd": 2000}, "has_other_subsidy": false}, "draws": 200})),
+    ]
+}
+
+/// Placeholders in the golden inputs and where their values come from
+const CONFIGURED: &[Configured] = &[
+    Configured("calc_inheritance_tax", "relationship", "child"),
+    Configured("calc_fee", "fee_code", "COURT_FILING"),
+    Configured("convert_currency", "from_currency", "USD"),
+];
+
+enum Outcome {
+    Pass,
+    Fail(String),
+    Skip(&'static str),
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let passed = match args.first().map(String::as_str) {
+        Some("stdio") => {
+            let command = args.get(1).context("usage: smoke_client stdio <server command> [args...]")?;
+            let mut child = tokio::process::Command::new(command)
+                .args(&args[2..])
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .kill_on_drop(true)
+                .spawn()
+                .with_context(|| format!("Cannot start {}", command))?;
+            let stdout = child.stdout.take().context("server stdout")?;
+            let stdin = child.stdin.take().context("server stdin")?;
+            // The child is killed when dropped, so keep it for the whole run
+            let client = ().serve((stdout, stdin)).await?;
+            println!("Connected to {} over stdio", command);
+            run(client, Some(child)).await?
+        }
+        url => {
+            let url = url.unwrap_or(DEFAULT_URL);
+            let client = ().serve(StreamableHttpClientTransport::from_uri(url)).await?;
+            println!("Connected to {}", url);
+            run(client, None).await?
+        }
+    };
+    std::process::exit(if passed { 0 } else { 1 });
+}
+
+/// List and call the tools, print the table and return whether every call passed
+async fn run(client: RunningService<RoleClient, ()>, _child: Option<tokio::process::Child>) -> anyhow::Result<bool> {
+    if let Some(server) = client.peer_info() {
+        println!("Server: {} {}", server.server_info.name, server.server_info.version);
+    }
+    let tools = client.list_all_tools().await.context("tools/list failed")?;
+    println!("{} tools listed\n", tools.len());
+
+    let mut values = Vec::new();
+    for Configured(tool, argument, fallback) in CONFIGURED {
+        let value = client
+            .complete_prompt_simple(*tool, *argument, "")
+            .await
+            .ok()
+            .and_then(|values| values.into_iter().next())
+            .unwrap_or_else(|| fallback.to_string());
+        values.push((format!("\"{{{}}}\"", argument), format!("\"{}\"", value)));
+    }
+
+    let mut inputs = golden_inputs();
+    let mut rows = Vec::new();
+    for tool in &tools {
+        let name = tool.name.to_string();
+        let Some(position) = inputs.iter().position(|(tool, _)| *tool == name) else {
+            let reason = if NOT_EXERCISED.contains(&name.as_str()) { "depends on server state" } else { "no golden input" };
+            rows.push((name, Outcome::Skip(reason), Duration::ZERO));
+            continue;
+        };
+        let (_, arguments) = inputs.remove(position);
+        let arguments = values
+            .iter()
+            .fold(arguments.to_string(), |text, (placeholder, value)| text.replace(placeholder, value));
+        let start = Instant::now();
+        let outcome = call(&client, &name, &arguments).await;
+        rows.push((name, outcome, start.elapsed()));
+    }
+    // Golden inputs left over belong to tools the server did not list
+    for (tool, _) in inputs {
+        rows.push((tool.to_string(), Outcome::Fail("not listed by the server".to_string()), Duration::ZERO));
+    }
+    client.cancel().await?;
+
+    let width = rows.iter().map(|(tool, _, _)| tool.len()).max().unwrap_or(0).max("TOOL".len());
+    println!("{:<width$}  RESULT  {:>8}  DETAIL", "TOOL", "MS");
+    for (tool, outcome, elapsed) in &rows {
+        let (result, detail) = match outcome {
+            Outcome::Pass => ("PASS", String::new()),
+            Outcome::Fail(reason) => ("FAIL", reason.chars().take(MAX_DETAIL).collect()),
+            Outcome::Skip(reason) => ("SKIP", reason.to_string()),
+        };
+        println!("{:<width$}  {:<6}  {:>8}  {}", tool, result, elapsed.as_millis(), detail);
+    }
+    let failed = rows.iter().filter(|(_, outcome, _)| matches!(outcome, Outcome::Fail(_))).count();
+    let passed = rows.iter().filter(|(_, outcome, _)| matches!(outcome, Outcome::Pass)).count();
+    println!("\n{} passed, {} failed, {} skipped", passed, failed, rows.len() - passed - failed);
+    Ok(failed == 0)
+}
+
+async fn call(client: &RunningService<RoleClient, ()>, tool: &str, arguments: &str) -> Outcome {
+    // Built from JSON so the request does not depend on the params struct's constructor
+    let request = json!({ "name": tool, "arguments": serde_json::from_str::<Value>(arguments).unwrap_or_default() });
+    let request: CallToolRequestParams = match serde_json::from_value(request) {
+        Ok(request) => request,
+        Err(e) => return Outcome::Fail(format!("cannot build request: {}", e)),
+    };
+    match client.call_tool(request).await {
+        Ok(result) if result.is_error.unwrap_or(false) => Outcome::Fail(first_text(&result)),
+        Ok(_) => Outcome::Pass,
+        Err(e) => Outcome::Fail(e.to_string()),
+    }
+}
+
+fn first_text(result: &CallToolResult) -> String {
+    result
+        .content
+        .first()
+        .and_then(|content| content.raw.as_text())
+        .map(|text| text.text.replace('\n', " "))
+        .unwrap_or_else(|| "error result".to_string())
+}
@@ -0,0 +1,436 @@
+//! In-process usage analytics for tool calls, reported by the `get_usage_report` tool.
+//!
+//! [`track`] wraps every route of a tool router so each call records, per tool, the JSON type
+//! each parameter arrived as (string or native number, boolean, ...) and the errors it produced:
+//! protocol errors such as missing fields, error results, and validation errors in the payload.
+//! Error messages are grouped with quoted values blanked out, so "Cannot parse 'abc'" and
+//! "Cannot parse 'xyz'" count as one error. The data shows where agents misuse the API.
+//!
+//! Counts are kept in memory since process start; `ENGINE_USAGE_ANALYTICS=false` disables them.
+//! With the `embedded-store` feature they are also written to its database and continue from
+//! there after a restart. A `usage` retention period (see [`super::retention`]) clears them once
+//! counting began longer ago than the period.
+//!
+//! Calls whose arguments do not deserialize into the tool's parameters are also sampled into a
+//! ring buffer of the most recent `ENGINE_SCHEMA_FAILURE_SAMPLES` failures (default 50, `0`
+//! disables), reported by `get_recent_schema_failures`. A sample keeps the argument shape only:
+//! field names with JSON types in place of values, and the error with values blanked out.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{LazyLock, Mutex};
+
+use chrono::{DateTime, Utc};
+use futures::FutureExt;
+use rmcp::handler::server::router::tool::{ToolRoute, ToolRouter};
+use rmcp::handler::server::tool::ToolCallContext;
+use rmcp::model::{CallToolResult, JsonObject};
+use rmcp::ErrorData as McpError;
+use serde_json::Value;
+
+use super::clock;
+
+/// Distinct parameter names or error messages kept per tool; the rest are counted as "(other)"
+const MAX_DISTINCT_KEYS: usize = 100;
+
+const OTHER_KEY: &str = "(other)";
+
+/// Longest grouped error message kept, in characters
+const MAX_MESSAGE_CHARS: usize = 200;
+
+/// Counts for one tool
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ToolUsage {
+    pub calls: u64,
+    /// Calls that failed with a protocol error or returned an error result
+    pub failed_calls: u64,
+    /// Parameter name -> JSON type name -> count
+    pub parameter_types: BTreeMap<String, BTreeMap<String, u64>>,
+    /// Grouped error message -> count
+    pub errors: BTreeMap<String, u64>,
+}
+
+/// Usage counts since `since`, keyed by tool name
+#[derive(Debug, Clone, PartialEq)]
+pub struct UsageStats {
+    pub since: DateTime<Utc>,
+    pub tools: BTreeMap<String, ToolUsage>,
+}
+
+impl Default for UsageStats {
+    fn default() -> Self {
+        Self {
+            since: clock::now(),
+            tools: BTreeMap::new(),
+        }
+    }
+}
+
+impl UsageStats {
+    fn record_arguments(&mut self, tool: &str, arguments: Option<&JsonObject>) {
+        let usage = self.tools.entry(tool.to_string()).or_default();
+        usage.calls += 1;
+        for (name, value) in arguments.into_iter().flatten() {
+            let types = bounded_entry(&mut usage.parameter_types, name);
+            *types.entry(json_type(value).to_string()).or_default() += 1;
+        }
+    }
+
+    fn record_outcome(&mut self, tool: &str, result: &Result<CallToolResult, McpError>) {
+        let usage = self.tools.entry(tool.to_string()).or_default();
+        let messages = match result {
+            Err(error) => {
+                usage.failed_calls += 1;
+                vec![error.message.to_string()]
+            }
+            Ok(result) => {
+                let text = result
+                    .content
+                    .first()
+                    .and_then(|content| content.raw.as_text())
+                    .map(|text| text.text.as_str())
+                    .unwrap_or_default();
+                if result.is_error.unwrap_or(false) {
+                    usage.failed_calls += 1;
+                    vec![error_result_message(text)]
+                } else {
+                    payload_errors(text)
+                }
+            }
+        };
+        for message in messages {
+            *bounded_entry(&mut usage.errors, &group_message(&message)) += 1;
+        }
+    }
+
+    /// Clear the counts if counting began before `cutoff`, counting again from `now`; returns
+    /// the number of tools whose counts were cleared
+    fn expire(&mut self, cutoff: DateTime<Utc>, now: DateTime<Utc>) -> usize {
+        if self.since >= cutoff {
+            return 0;
+        }
+        let cleared = self.tools.len();
+        *self = UsageStats { since: now, tools: BTreeMap::new() };
+        cleared
+    }
+}
+
+static USAGE: LazyLock<Option<Mutex<UsageStats>>> = LazyLock::new(|| {
+    let enabled = std::env::var("ENGINE_USAGE_ANALYTICS")
+        .map(|value| !matches!(value.trim().to_ascii_lowercase().as_str(), "false" | "0" | "off" | "no"))
+        .unwrap_or(true);
+    enabled.then(|| {
+        #[cfg(feature = "embedded-store")]
+        let stats = super::embedded_store::load_usage().unwrap_or_default();
+        #[cfg(not(feature = "embedded-store"))]
+        let stats = UsageStats::default();
+        Mutex::new(stats)
+    })
+});
+
+fn with_usage<T>(update: impl FnOnce(&mut UsageStats) -> T) -> Option<T> {
+    USAGE
+        .as_ref()
+        .map(|usage| update(&mut usage.lock().unwrap_or_else(|poisoned| poisoned.into_inner())))
+}
+
+/// Write the counts of `tool` to the embedded store from a copy taken under the lock, so other
+/// calls do not wait on the database
+#[cfg(feature = "embedded-store")]
+fn save_usage(tool: &str) {
+    let counted = USAGE.as_ref().and_then(|usage| {
+        let usage = usage.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        Some((usage.tools.get(tool)?.clone(), usage.since))
+    });
+    if let Some((counts, since)) = counted {
+        super::embedded_store::save_usage(tool, &counts, since);
+    }
+}
+
+/// Wrap every route of `router` so its calls are recorded
+pub fn track<S: Send + Sync + 'static>(router: ToolRouter<S>) -> ToolRouter<S> {
+    let mut tracked = ToolRouter::new();
+    for route in router {
+        let call = route.call.clone();
+        tracked.add_route(ToolRoute::new_dyn(route.attr, move |context: ToolCallContext<'_, S>| {
+            let tool = context.name.to_string();
+            with_usage(|usage| usage.record_arguments(&tool, context.arguments.as_ref()));
+            let future = call(context);
+            async move {
+                let result = future.await;
+                with_usage(|usage| usage.record_outcome(&tool, &result));
+                #[cfg(feature = "embedded-store")]
+                save_usage(&tool);
+                result
+            }
+            .boxed()
+        }));
+    }
+    tracked
+}
+
+/// Arguments of one call that failed to deserialize, with values redacted
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaFailure {
+    pub tool: String,
+    pub at: DateTime<Utc>,
+    /// Deserialization error with quoted values and numbers blanked out
+    pub error: String,
+    /// Arguments with every value replaced by its JSON type name
+    pub shape: Value,
+}
+
+/// Array items and nesting levels kept in a redacted argument shape
+const MAX_SHAPE_ITEMS: usize = 3;
+const MAX_SHAPE_DEPTH: usize = 8;
+
+struct SchemaFailures {
+    capacity: usize,
+    samples: Mutex<VecDeque<SchemaFailure>>,
+}
+
+static SCHEMA_FAILURES: LazyLock<Option<SchemaFailures>> = LazyLock::new(|| {
+    let capacity = match std::env::var("ENGINE_SCHEMA_FAILURE_SAMPLES") {
+        Ok(value) => value.trim().parse::<usize>().unwrap_or_else(|e| {
+            tracing::error!("Invalid ENGINE_SCHEMA_FAILURE_SAMPLES '{}': {}; keeping 50", value, e);
+            50
+        }),
+        Err(_) => 50,
+    };
+    (capacity > 0).then(|| SchemaFailures { capacity, samples: Mutex::new(VecDeque::with_capacity(capacity)) })
+});
+
+/// Sample a call whose arguments did not deserialize into the tool's parameters
+pub fn record_schema_failure(tool: &str, arguments: &JsonObject, error: &str) {
+    let Some(failures) = SCHEMA_FAILURES.as_ref() else {
+        return;
+    };
+    let failure = SchemaFailure {
+        tool: tool.to_string(),
+        at: clock::now(),
+        error: redact_message(error),
+        shape: shape(&Value::Object(arguments.clone()), 0),
+    };
+    let mut samples = failures.samples.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if samples.len() == failures.capacity {
+        samples.pop_front();
+    }
+    samples.push_back(failure);
+}
+
+/// Recorded schema failures, oldest first, or None when sampling is disabled
+pub fn schema_failures() -> Option<Vec<SchemaFailure>> {
+    SCHEMA_FAILURES.as_ref().map(|failures| {
+        failures.samples.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).iter().cloned().collect()
+    })
+}
+
+/// `value` with field names kept and every other value replaced by its JSON type name
+fn shape(value: &Value, depth: usize) -> Value {
+    match value {
+        Value::Object(fields) if depth < MAX_SHAPE_DEPTH => Value::Object(
+            fields
+                .iter()
+                .take(MAX_DISTINCT_KEYS)
+                .map(|(name, value)| (name.clone(), shape(value, depth + 1)))
+                .collect(),
+        ),
+        Value::Array(items) if depth < MAX_SHAPE_DEPTH => {
+            Value::Array(items.iter().take(MAX_SHAPE_ITEMS).map(|item| shape(item, depth + 1)).collect())
+        }
+        other => Value::String(json_type(other).to_string()),
+    }
+}
+
+/// Clear the usage counts if counting began before `cutoff`: how many tools' counts were cleared
+/// in memory, and in the embedded store in builds with it
+pub fn purge_before(cutoff: DateTime<Utc>, now: DateTime<Utc>) -> (usize, Option<usize>) {
+    let cleared = with_usage(|usage| usage.expire(cutoff, now)).unwrap_or(0);
+    // Counting restarts from `now` in both, so the store skips counts copied before the purge
+    #[cfg(feature = "embedded-store")]
+    let cleared_stored = super::embedded_store::purge_usage(cutoff, now);
+    #[cfg(not(feature = "embedded-store"))]
+    let cleared_stored = None;
+    (cleared, cleared_stored)
+}
+
+/// Copy of the counts so far, or None when analytics are disabled
+pub fn snapshot() -> Option<UsageStats> {
+    USAGE
+        .as_ref()
+        .map(|usage| usage.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone())
+}
+
+/// Entry for `key`, or the shared "(other)" entry once the map holds too many distinct keys
+fn bounded_entry<'a, V: Default>(map: &'a mut BTreeMap<String, V>, key: &str) -> &'a mut V {
+    let key = if map.contains_key(key) || map.len() < MAX_DISTINCT_KEYS {
+        key
+    } else {
+        OTHER_KEY
+    };
+    map.entry(key.to_string()).or_default()
+}
+
+fn json_type(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Message of an error result; structured results (such as needs_input) carry it in `message`
+fn error_result_message(text: &str) -> String {
+    serde_json::from_str::<Value>(text)
+        .ok()
+        .and_then(|value| value.get("message").and_then(Value::as_str).map(str::to_string))
+        .unwrap_or_else(|| text.to_string())
+}
+
+/// Validation errors listed in a successful response, versioned or legacy
+fn payload_errors(text: &str) -> Vec<String> {
+    let Ok(value) = serde_json::from_str::<Value>(text) else {
+        return Vec::new();
+    };
+    value
+        .get("payload")
+        .unwrap_or(&value)
+        .get("errors")
+        .and_then(Value::as_array)
+        .map(|errors| errors.iter().filter_map(Value::as_str).map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+/// Blank out quoted values and numbers so errors differing only in user input group together
+fn group_message(message: &str) -> String {
+    blank_values(message, &['\'', '`'])
+}
+
+/// Blank out values quoted with ' or " and numbers, keeping `field` names, so a deserialization
+/// error can be kept without the client's data
+fn redact_message(message: &str) -> String {
+    blank_values(message, &['\'', '"'])
+}
+
+fn blank_values(message: &str, quotes: &[char]) -> String {
+    let mut grouped = String::with_capacity(message.len());
+    let mut quote: Option<char> = None;
+    let mut previous_digit = false;
+    for c in message.chars() {
+        match quote {
+            Some(open) if c == open => {
+                grouped.push('…');
+                grouped.push(c);
+                quote = None;
+            }
+            Some(_) => {}
+            None if quotes.contains(&c) => {
+                grouped.push(c);
+                quote = Some(c);
+            }
+            None if c.is_ascii_digit() || (previous_digit && matches!(c, '.' | ',')) => {
+                if !previous_digit {
+                    grouped.push('#');
+                }
+                previous_digit = true;
+                continue;
+            }
+            None => grouped.push(c),
+        }
+        previous_digit = false;
+    }
+    if quote.is_some() {
+        grouped.push('…');
+    }
+    grouped.chars().take(MAX_MESSAGE_CHARS).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::model::Content;
+    use serde_json::json;
+
+    #[test]
+    fn test_group_message() {
+        assert_eq!(
+            group_message("Invalid income parameter: Cannot parse 'lots' as a number"),
+            "Invalid income parameter: Cannot parse '…' as a number"
+        );
+        assert_eq!(
+            group_message("Turnout (120) cannot exceed eligible voters (100.5)"),
+            "Turnout (#) cannot exceed eligible voters (#)"
+        );
+        assert_eq!(group_message("missing field `income`"), "missing field `…`");
+    }
+
+    #[test]
+    fn test_record_shapes_and_errors() {
+        let mut stats = UsageStats::default();
+        let arguments = json!({ "income": "45,000", "loss_carryforward": 1000 });
+        stats.record_arguments("calc_tax", arguments.as_object());
+        let arguments = json!({ "income": 52000 });
+        stats.record_arguments("calc_tax", arguments.as_object());
+
+        stats.record_outcome(
+            "calc_tax",
+            &Ok(CallToolResult::error(vec![Content::text("Invalid income parameter: Cannot parse 'x' as a number")])),
+        );
+        stats.record_outcome(
+            "calc_tax",
+            &Ok(CallToolResult::success(vec![Content::text(
+                json!({ "schema_version": 2, "payload": { "errors": ["Income must be below 1000000"] } }).to_string(),
+            )])),
+        );
+        stats.record_outcome("calc_tax", &Err(McpError::invalid_params("missing field `income`", None)));
+
+        let usage = &stats.tools["calc_tax"];
+        assert_eq!(usage.calls, 2);
+        assert_eq!(usage.failed_calls, 2);
+        assert_eq!(usage.parameter_types["income"], BTreeMap::from([("string".to_string(), 1), ("number".to_string(), 1)]));
+        assert_eq!(usage.parameter_types["loss_carryforward"]["number"], 1);
+        assert_eq!(usage.errors["Income must be below #"], 1);
+        assert_eq!(usage.errors["missing field `…`"], 1);
+        assert_eq!(usage.errors.len(), 3);
+
+        // Counts expire as a whole once counting began before the cutoff
+        let started = stats.since;
+        assert_eq!(stats.expire(started, started + chrono::TimeDelta::days(1)), 0);
+        let now = started + chrono::TimeDelta::days(40);
+        assert_eq!(stats.expire(now - chrono::TimeDelta::days(30), now), 1);
+        assert_eq!((stats.since, stats.tools.len()), (now, 0));
+    }
+
+    #[test]
+    fn test_schema_failure_redaction() {
+        let arguments = json!({
+            "income": "lots",
+            "brackets": [{ "up_to": 10000, "rate": 0.1 }, 2, 3, 4],
+            "context": { "case_id": "CASE-1" },
+        });
+        assert_eq!(
+            shape(&arguments, 0),
+            json!({
+                "income": "string",
+                "brackets": [{ "up_to": "number", "rate": "number" }, "number", "number"],
+                "context": { "case_id": "string" },
+            })
+        );
+        assert_eq!(
+            redact_message("invalid type: string \"lots\", expected i32 at line 1 column 12 in `turnout`"),
+            "invalid type: string \"…\", expected i# at line # column # in `turnout`"
+        );
+    }
+
+    #[test]
+    fn test_bounded_entry_overflows_to_other() {
+        let mut map: BTreeMap<String, u64> = BTreeMap::new();
+        for i in 0..=MAX_DISTINCT_KEYS {
+            *bounded_entry(&mut map, &format!("field_{}", i)) += 1;
+        }
+        assert_eq!(map.len(), MAX_DISTINCT_KEYS + 1);
+        assert_eq!(map[OTHER_KEY], 1);
+    }
+}
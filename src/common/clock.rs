@@ -0,0 +1,62 @@
+//! Time source for date-based features.
+//!
+//! Everything that reads the current time for a result goes through [`now`] or [`today`]: the
+//! default `as_of_date` of escrow checks, the `generated_at` timestamp of response envelopes and
+//! the timestamps in usage analytics. Durations (request timers, queue timeouts) keep measuring
+//! real time.
+//!
+//! `ENGINE_FROZEN_TIME` freezes the clock at an RFC 3339 timestamp (`2024-06-01T12:00:00Z`) or a
+//! date (`2024-06-01`, midnight UTC), so integration tests and replayed calls give the same
+//! results on any day.
+
+use std::sync::LazyLock;
+
+use chrono::{DateTime, NaiveDate, Utc};
+
+static FROZEN: LazyLock<Option<DateTime<Utc>>> = LazyLock::new(|| {
+    let value = std::env::var("ENGINE_FROZEN_TIME").ok().filter(|value| !value.trim().is_empty())?;
+    match parse(&value) {
+        Ok(time) => {
+            tracing::info!("Clock frozen at {} (ENGINE_FROZEN_TIME)", time.to_rfc3339());
+            Some(time)
+        }
+        Err(e) => {
+            tracing::error!("Invalid ENGINE_FROZEN_TIME '{}': {}; using the system clock", value, e);
+            None
+        }
+    }
+});
+
+/// Parse an RFC 3339 timestamp, or a YYYY-MM-DD date at midnight UTC
+fn parse(value: &str) -> Result<DateTime<Utc>, String> {
+    let value = value.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map(|date| date.and_time(chrono::NaiveTime::MIN).and_utc())
+        .map_err(|_| "expected an RFC 3339 timestamp or a YYYY-MM-DD date".to_string())
+}
+
+/// The current time, or the frozen time
+pub fn now() -> DateTime<Utc> {
+    FROZEN.unwrap_or_else(Utc::now)
+}
+
+/// Today's date (UTC), or the frozen date
+pub fn today() -> NaiveDate {
+    now().date_naive()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_frozen_time() {
+        assert_eq!(parse("2024-06-01T12:30:00+02:00").unwrap().to_rfc3339(), "2024-06-01T10:30:00+00:00");
+        assert_eq!(parse(" 2024-06-01 ").unwrap().to_rfc3339(), "2024-06-01T00:00:00+00:00");
+        assert!(parse("yesterday").is_err());
+        assert!(parse("2024-13-01").is_err());
+    }
+}
@@ -1,21 +1,58 @@
-use serde::{Deserialize, Deserializer, Serialize, de};
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Deserializer, Serialize, de, de::DeserializeOwned};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
 use std::env;
 use std::fmt;
-use std::sync::LazyLock;
-
-use super::metrics::{increment_requests, increment_errors, RequestTimer};
-
+use std::sync::{Arc, LazyLock, OnceLock, PoisonError, RwLock, RwLockReadGuard};
+
+use super::explanation_graph::{self, GraphFormat};
+use super::explanations::{self, explain, Audience};
+use super::export::{self, ExportFormat, ExportedFile};
+use super::metrics::{increment_requests, increment_errors, param_size, RequestTimer};
+use super::analytics::{self, SchemaFailure, ToolUsage, UsageStats};
+use super::clock;
+use super::concurrency;
+use super::fixtures;
+use super::invariants::Invariants;
+use super::json_format;
+use super::preflight;
+use super::log_sampling;
+use super::reference;
+use super::reminders;
+use super::resources;
+use super::result_store;
+use super::retention;
+use super::sampling;
+
+use minijinja::context;
+use sha2::{Digest, Sha256};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use futures::future::BoxFuture;
 use rmcp::{
     ServerHandler,
-    handler::server::router::tool::ToolRouter,
+    handler::server::router::tool::{ToolRoute, ToolRouter},
+    handler::server::tool::ToolCallContext,
     handler::server::wrapper::Parameters,
-    model::{Implementation, ServerCapabilities, ServerInfo, CallToolResult, Content},
+    model::{
+        Implementation, ServerCapabilities, ServerInfo, CallToolResult, Content, JsonObject, Meta, Tool, ToolAnnotations,
+        CompleteRequestParams, CompleteResult, CompletionInfo, Reference, InitializeRequestParams,
+        ListResourceTemplatesResult, PaginatedRequestParams, ReadResourceRequestParams, ReadResourceResult,
+        SetLevelRequestMethod, SetLevelRequestParams,
+    },
+    service::{RequestContext as ServiceContext, RoleServer},
     ErrorData as McpError,
-    schemars, tool, tool_handler, tool_router
+    schemars, tool, tool_handler
 };
 
 // =================== CONFIGURATION ===================
 
+/// Deprecated env-only settings, replaced by `ENGINE_TAX_BRACKETS` and `ENGINE_SURCHARGES`
+const LEGACY_TAX_BRACKET_VARS: [&str; 2] = ["ENGINE_DEFAULT_THRESHOLDS", "ENGINE_DEFAULT_RATES"];
+const LEGACY_SURCHARGE_VARS: [&str; 2] = ["ENGINE_DEFAULT_SURCHARGE_THRESHOLD", "ENGINE_DEFAULT_SURCHARGE_RATE"];
+
 #[derive(Debug, Clone)]
 pub struct EngineConfig {
     // Penalty calculation defaults
@@ -26,1392 +63,14328 @@ pub struct EngineConfig {
     // Tax calculation defaults
     pub default_thresholds: Vec<f64>,
     pub default_rates: Vec<f64>,
-    pub default_surcharge_threshold: f64,
-    pub default_surcharge_rate: f64,
+    pub default_cliffs: Vec<bool>,
+    pub surcharges: Vec<SurchargeRule>,
+    pub surcharge_base: SurchargeBase,
+    pub tax_schedule_change: Option<ScheduleChange>,
+    pub minimum_tax: Option<MinimumTaxConfig>,
+
+    // Covenant check defaults
+    pub default_max_leverage: f64,
+    pub default_min_interest_coverage: f64,
+
+    // Capital gains defaults
+    pub capital_gains_short_term_rate: f64,
+    pub capital_gains_long_term_rate: f64,
+    pub capital_gains_long_term_days: i32,
+    pub default_capital_gains_exemption: f64,
+
+    // Inheritance and gift tax classes, keyed by relationship
+    pub inheritance_classes: BTreeMap<String, InheritanceClass>,
+
+    // Social contribution types with ceilings and employer/employee rates
+    pub social_contributions: Vec<SocialContribution>,
+
+    // Property tax defaults
+    pub default_property_millage: f64,
+    pub property_homestead_exemption: f64,
+    pub property_assessment_cap: f64,
+
+    // Transfer tax schedule
+    pub transfer_tax_thresholds: Vec<f64>,
+    pub transfer_tax_rates: Vec<f64>,
+    pub transfer_tax_cliffs: Vec<bool>,
+    pub transfer_tax_method: TransferTaxMethod,
+
+    // Housing grant: how the income limit scales with the household
+    pub housing_equivalence_scale: EquivalenceScale,
+
+    // Appeal window after a decision, and the public holidays that are not business days
+    pub appeal_window_days: i32,
+    pub appeal_business_days: bool,
+    pub public_holidays: BTreeSet<NaiveDate>,
+
+    // Document checklists by program
+    pub required_documents: BTreeMap<String, Vec<RequiredDocument>>,
+
+    // Fee schedules by fee code
+    pub fee_schedules: BTreeMap<String, Vec<FeeComponent>>,
+
+    // Exchange rates against the base currency
+    pub fx: FxTable,
+
+    // Reject lenient number shorthand such as "600k"
+    pub strict_parsing: bool,
 }
 
-impl EngineConfig {
-    pub fn from_env() -> Self {
-        Self {
-            default_rate_per_day: env::var("ENGINE_DEFAULT_RATE_PER_DAY")
-                .ok()
-                .and_then(|s| s.parse().ok())
-                .unwrap_or(100.0),  // From LyFin-Compliance-Annex.md: "100 per day"
-                
-            default_cap: env::var("ENGINE_DEFAULT_CAP")
-                .ok()
-                .and_then(|s| s.parse().ok())
-                .unwrap_or(1000.0),  // From LyFin-Compliance-Annex.md: "Maximum Cap: 1000"
-                
-            default_interest_rate: env::var("ENGINE_DEFAULT_INTEREST_RATE")
-                .ok()
-                .and_then(|s| s.parse().ok())
-                .unwrap_or(0.05),  // From LyFin-Compliance-Annex.md: "5 percent annual"
-                
-            default_thresholds: env::var("ENGINE_DEFAULT_THRESHOLDS")
-                .ok()
-                .and_then(|s| Self::parse_vec_f64(&s))
-                .unwrap_or_else(|| vec![10000.0]),  // From 2025_61-FR.md: "First bracket: 10% on income up to 10000"
-                
-            default_rates: env::var("ENGINE_DEFAULT_RATES")
-                .ok()
-                .and_then(|s| Self::parse_vec_f64(&s))
-                .unwrap_or_else(|| vec![0.10, 0.20]),  // From 2025_61-FR.md: "10% up to 10000", "20% exceeding 10000"
-                
-            default_surcharge_threshold: env::var("ENGINE_DEFAULT_SURCHARGE_THRESHOLD")
-                .ok()
-                .and_then(|s| s.parse().ok())
-                .unwrap_or(5000.0),  // From 2025_61-FR.md: "Where the tax calculated... exceeds 5000"
-                
-            default_surcharge_rate: env::var("ENGINE_DEFAULT_SURCHARGE_RATE")
-                .ok()
-                .and_then(|s| s.parse().ok())
-                .unwrap_or(0.02),  // From 2025_61-FR.md: "a surcharge of 2% of the total tax liability"
+/// One progressive tax bracket in `ENGINE_TAX_BRACKETS` / `ENGINE_TAX_BRACKETS_FILE`.
+/// The last bracket omits `up_to` and applies to all remaining income. A `cliff` bracket taxes
+/// the whole amount at its rate once the amount exceeds the previous bracket's `up_to`, instead
+/// of only the part within the bracket. An `indexed` bracket's `up_to` is given in base-year
+/// money and inflation-adjusted to the target year of the price index.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TaxBracketConfig {
+    #[serde(default)]
+    up_to: Option<f64>,
+    rate: f64,
+    #[serde(default)]
+    cliff: bool,
+    #[serde(default)]
+    indexed: bool,
+}
+
+/// Structured configuration from the `env_var` (inline JSON) or `path_var` (path to a JSON file)
+/// setting read through `var`, in that order, validated by `parse`. When neither is set, or the configuration is invalid, `default`
+/// is used instead; invalid configuration is reported at load, naming the variable and `fallback`.
+fn load_json_config<T>(
+    var: &dyn Fn(&str) -> Option<String>,
+    env_var: &str,
+    path_var: &str,
+    parse: impl FnOnce(&str) -> Result<T, String>,
+    fallback: &str,
+    default: impl FnOnce() -> T,
+) -> T {
+    let structured = match (var(env_var), var(path_var)) {
+        (Some(json), _) => Some((env_var, Ok(json))),
+        (None, Some(path)) => Some((
+            path_var,
+            std::fs::read_to_string(&path).map_err(|e| format!("cannot read '{}': {}", path, e)),
+        )),
+        _ => None,
+    };
+    if let Some((source, json)) = structured {
+        match json.and_then(|json| parse(&json)) {
+            Ok(config) => return config,
+            Err(e) => tracing::error!("Invalid {}: {}; {}", source, e, fallback),
         }
     }
-    
-    fn parse_vec_f64(s: &str) -> Option<Vec<f64>> {
-        let parsed: Result<Vec<f64>, _> = s
-            .split(',')
-            .map(|part| part.trim().parse::<f64>())
-            .collect();
-        parsed.ok()
-    }
+    default()
 }
 
-static CONFIG: LazyLock<EngineConfig> = LazyLock::new(EngineConfig::from_env);
+/// Settings read by `EngineConfig` and `PriceIndex`, which a profile may set
+const CONFIG_VARS: [&str; 48] = [
+    "ENGINE_DEFAULT_RATE_PER_DAY", "ENGINE_DEFAULT_CAP", "ENGINE_DEFAULT_INTEREST_RATE",
+    "ENGINE_TAX_BRACKETS", "ENGINE_TAX_BRACKETS_FILE", "ENGINE_DEFAULT_THRESHOLDS", "ENGINE_DEFAULT_RATES",
+    "ENGINE_SURCHARGES", "ENGINE_SURCHARGES_FILE", "ENGINE_DEFAULT_SURCHARGE_THRESHOLD", "ENGINE_DEFAULT_SURCHARGE_RATE",
+    "ENGINE_SURCHARGE_BASE", "ENGINE_TAX_CHANGE_DATE", "ENGINE_TAX_BRACKETS_AFTER_CHANGE",
+    "ENGINE_AMT_RATE", "ENGINE_AMT_EXEMPTION",
+    "ENGINE_DEFAULT_MAX_LEVERAGE", "ENGINE_DEFAULT_MIN_INTEREST_COVERAGE",
+    "ENGINE_CAPITAL_GAINS_SHORT_TERM_RATE", "ENGINE_CAPITAL_GAINS_LONG_TERM_RATE",
+    "ENGINE_CAPITAL_GAINS_LONG_TERM_DAYS", "ENGINE_CAPITAL_GAINS_ANNUAL_EXEMPTION",
+    "ENGINE_INHERITANCE_CLASSES", "ENGINE_INHERITANCE_CLASSES_FILE",
+    "ENGINE_SOCIAL_CONTRIBUTIONS", "ENGINE_SOCIAL_CONTRIBUTIONS_FILE",
+    "ENGINE_DEFAULT_PROPERTY_MILLAGE", "ENGINE_PROPERTY_HOMESTEAD_EXEMPTION", "ENGINE_PROPERTY_ASSESSMENT_CAP",
+    "ENGINE_TRANSFER_TAX_BRACKETS", "ENGINE_TRANSFER_TAX_BRACKETS_FILE", "ENGINE_TRANSFER_TAX_METHOD",
+    "ENGINE_HOUSING_EQUIVALENCE_SCALE",
+    "ENGINE_APPEAL_WINDOW_DAYS", "ENGINE_APPEAL_BUSINESS_DAYS", "ENGINE_PUBLIC_HOLIDAYS",
+    "ENGINE_REQUIRED_DOCUMENTS", "ENGINE_REQUIRED_DOCUMENTS_FILE",
+    "ENGINE_FEE_SCHEDULES", "ENGINE_FEE_SCHEDULES_FILE",
+    "ENGINE_BASE_CURRENCY", "ENGINE_FX_RATES", "ENGINE_FX_RATES_FILE",
+    "ENGINE_STRICT_PARSING",
+    "ENGINE_PRICE_INDEX", "ENGINE_PRICE_INDEX_FILE", "ENGINE_INDEX_BASE_YEAR", "ENGINE_INDEX_TARGET_YEAR",
+];
+
+/// Settings holding a bracket list, compared bracket by bracket by `diff_profiles`
+const BRACKET_SETTINGS: [&str; 3] = ["ENGINE_TAX_BRACKETS", "ENGINE_TAX_BRACKETS_AFTER_CHANGE", "ENGINE_TRANSFER_TAX_BRACKETS"];
+
+/// Placeholder for the citation of a profile setting written by `--migrate-config`
+const PROFILE_SOURCE: &str = "<regulation and section this value comes from>";
+
+/// One setting of the profile in `ENGINE_PROFILE` / `ENGINE_PROFILE_FILE`, with where its value
+/// comes from and anything to review
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ProfileSetting {
+    #[schemars(description = "Value of the setting: a string as it would be set in the environment, or JSON for JSON settings such as brackets")]
+    pub value: serde_json::Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Optional regulation and section the value comes from")]
+    pub source: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Optional note for reviewers")]
+    pub note: Option<String>,
+}
 
-// =================== PARSING UTILITIES ===================
+/// Settings of the profile in `ENGINE_PROFILE` (inline JSON) or `ENGINE_PROFILE_FILE` (path to JSON)
+static PROFILE: LazyLock<BTreeMap<String, String>> = LazyLock::new(|| {
+    load_json_config(
+        &|name| env::var(name).ok(),
+        "ENGINE_PROFILE",
+        "ENGINE_PROFILE_FILE",
+        EngineConfig::parse_profile,
+        "ignoring the profile",
+        BTreeMap::new,
+    )
+});
+
+/// Setting `name` from the environment, or else from the profile
+fn config_var(name: &str) -> Option<String> {
+    env::var(name).ok().or_else(|| PROFILE.get(name).cloned())
+}
 
-/// Sanitize user input for safe inclusion in error messages
-/// Prevents JSON injection, XSS, log injection, and other attacks
-fn sanitize_for_error_message(input: &str) -> String {
-    // Limit length to prevent DoS and overly verbose errors
-    let truncated = if input.len() > 50 { 
-        format!("{}...", &input[..47])
-    } else { 
-        input.to_string() 
-    };
-    
-    // Replace dangerous and non-printable characters
-    truncated
-        .chars()
-        .map(|c| match c {
-            // Replace line breaks and control chars that could break JSON/logs
-            '\n' | '\r' | '\t' => ' ',
-            // Replace quote chars that could break JSON structure  
-            '"' | '\'' | '`' => '?',
-            // Replace backslashes that could escape JSON
-            '\\' => '?',
-            // Replace HTML/script chars for XSS prevention
-            '<' | '>' => '?',
-            // Keep normal printable ASCII and space
-            c if c.is_ascii_graphic() || c == ' ' => c,
-            // Replace any other non-printable or unicode control chars
-            _ => '?'
-        })
-        .collect()
+/// Thresholds, rates and per-bracket cliff flags of a validated bracket list
+type BracketSchedule = (Vec<f64>, Vec<f64>, Vec<bool>);
+
+/// Exemption and progressive brackets for one relationship class of inheritance and gift tax
+#[derive(Debug, Clone, PartialEq)]
+pub struct InheritanceClass {
+    pub exemption: f64,
+    pub thresholds: Vec<f64>,
+    pub rates: Vec<f64>,
+    pub cliffs: Vec<bool>,
 }
 
-/// Validate input length and format for security
-fn validate_input_security(input: &str, field_name: &str) -> Result<(), String> {
-    // Check maximum length to prevent DoS
-    if input.len() > 100 {
-        return Err(format!("Invalid {}: input too long (max 100 characters)", field_name));
-    }
-    
-    // Check for null bytes (can cause issues in some contexts)
-    if input.contains('\0') {
-        return Err(format!("Invalid {}: input contains null bytes", field_name));
-    }
-    
-    // Check for excessive control characters (potential log injection)
-    let control_char_count = input.chars().filter(|c| c.is_control()).count();
-    if control_char_count > 2 {  // Allow a couple for legitimate formatting
-        return Err(format!("Invalid {}: input contains too many control characters", field_name));
-    }
-    
-    Ok(())
+/// One relationship class in `ENGINE_INHERITANCE_CLASSES` / `ENGINE_INHERITANCE_CLASSES_FILE`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct InheritanceClassConfig {
+    exemption: f64,
+    brackets: Vec<TaxBracketConfig>,
 }
 
-/// Parse a string to f64, handling various formats with security validation
-fn parse_f64_from_string(s: &str) -> Result<f64, String> {
-    let trimmed = s.trim();
-    
-    // Security validation first
-    if let Err(e) = validate_input_security(trimmed, "number") {
-        return Err(e);
-    }
-    
-    // Handle empty strings
-    if trimmed.is_empty() {
-        return Err("Empty string cannot be parsed as number".to_string());
-    }
-    
-    // Sanitize input for error messages
-    let sanitized = sanitize_for_error_message(trimmed);
-    
-    // Remove common formatting characters
-    let cleaned = trimmed
-        .replace(',', "")  // Remove thousands separators
-        .replace('$', "")  // Remove dollar, euro, pound, etc. signs
-        .replace('€', "")  // Remove euro signs
-        .replace('£', "")  // Remove pound signs
-        .replace('¥', "")  // Remove yen signs
-        .replace('%', ""); // Remove percentage signs
-    
-    match cleaned.parse::<f64>() {
-        Ok(value) => {
-            if value.is_infinite() || value.is_nan() {
-                Err(format!("Invalid number: '{}'", sanitized))
-            } else {
-                Ok(value)
-            }
-        },
-        Err(_) => Err(format!("Cannot parse '{}' as a number", sanitized))
-    }
+/// One social contribution type in `ENGINE_SOCIAL_CONTRIBUTIONS` / `ENGINE_SOCIAL_CONTRIBUTIONS_FILE`.
+/// Rates apply to salary up to the annual ceiling; omit the ceiling for an uncapped contribution.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SocialContribution {
+    pub name: String,
+    #[serde(default)]
+    pub ceiling: Option<f64>,
+    pub employee_rate: f64,
+    pub employer_rate: f64,
 }
 
-/// Parse a string to i32, handling various formats with security validation
-fn parse_i32_from_string(s: &str) -> Result<i32, String> {
-    let trimmed = s.trim();
-    
-    // Security validation first
-    if let Err(e) = validate_input_security(trimmed, "integer") {
-        return Err(e);
-    }
-    
-    // Handle empty strings
-    if trimmed.is_empty() {
-        return Err("Empty string cannot be parsed as integer".to_string());
-    }
-    
-    // Sanitize input for error messages
-    let sanitized = sanitize_for_error_message(trimmed);
-    
-    // Remove common formatting characters
-    let cleaned = trimmed.replace(',', ""); // Remove thousands separators
-    
-    match cleaned.parse::<i32>() {
-        Ok(value) => Ok(value),
-        Err(_) => Err(format!("Cannot parse '{}' as an integer", sanitized))
-    }
+/// One component of a fee in `ENGINE_FEE_SCHEDULES` / `ENGINE_FEE_SCHEDULES_FILE`:
+/// `fixed` + `rate` × (value above `above`), capped at `cap` when set.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FeeComponent {
+    pub name: String,
+    #[serde(default)]
+    pub fixed: f64,
+    #[serde(default)]
+    pub rate: f64,
+    #[serde(default)]
+    pub above: f64,
+    #[serde(default)]
+    pub cap: Option<f64>,
 }
 
-/// Parse a string to bool, handling various formats with security validation
-fn parse_bool_from_string(s: &str) -> Result<bool, String> {
-    let trimmed = s.trim();
-    
-    // Security validation first
-    if let Err(e) = validate_input_security(trimmed, "boolean") {
-        return Err(e);
-    }
-    
-    // Handle empty strings
-    if trimmed.is_empty() {
-        return Err("Empty string cannot be parsed as boolean".to_string());
+/// One document of a program checklist in `ENGINE_REQUIRED_DOCUMENTS` /
+/// `ENGINE_REQUIRED_DOCUMENTS_FILE`: required of every applicant, or only when the applicant's
+/// situation includes the flag `when`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RequiredDocument {
+    pub document: String,
+    #[serde(default)]
+    pub when: Option<String>,
+}
+
+/// One exchange rate in `ENGINE_FX_RATES` / `ENGINE_FX_RATES_FILE`: units of the base currency
+/// per unit of `currency`, as quoted on `date` (YYYY-MM-DD)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FxRateConfig {
+    date: String,
+    currency: String,
+    rate: f64,
+}
+
+/// Date-stamped exchange rates against the base currency amounts and thresholds are expressed in
+#[derive(Debug, Clone, PartialEq)]
+pub struct FxTable {
+    pub base_currency: String,
+    pub rates: BTreeMap<String, BTreeMap<NaiveDate, f64>>,
+}
+
+/// Rate applied to convert between two currencies. `quote_date` is the older of the quotes the
+/// rate was derived from, or None when neither currency needed a quote.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FxConversion {
+    pub rate: f64,
+    pub quote_date: Option<NaiveDate>,
+}
+
+impl FxTable {
+    /// Units of the base currency per unit of `currency`, using the latest quote on or before
+    /// `on` (or the latest quote when `on` is None)
+    fn base_rate(&self, currency: &str, on: Option<NaiveDate>) -> Result<(f64, Option<NaiveDate>), String> {
+        if currency == self.base_currency {
+            return Ok((1.0, None));
+        }
+        let quotes = self.rates.get(currency).ok_or_else(|| {
+            let mut known: Vec<&str> = self.rates.keys().map(String::as_str).collect();
+            known.insert(0, &self.base_currency);
+            format!("no exchange rate for '{}' (expected one of: {})", sanitize_for_error_message(currency), known.join(", "))
+        })?;
+        let quote = match on {
+            Some(date) => quotes.range(..=date).next_back(),
+            None => quotes.iter().next_back(),
+        };
+        match (quote, on) {
+            (Some((date, rate)), _) => Ok((*rate, Some(*date))),
+            (None, Some(date)) => Err(format!("no {} exchange rate on or before {}", currency, date)),
+            (None, None) => Err(format!("no exchange rate for '{}'", currency)),
+        }
     }
-    
-    // Sanitize input for error messages
-    let sanitized = sanitize_for_error_message(trimmed);
-    
-    // Parse various boolean representations (case-insensitive)
-    match trimmed.to_lowercase().as_str() {
-        "true" | "t" | "yes" | "y" | "1" | "on" => Ok(true),
-        "false" | "f" | "no" | "n" | "0" | "off" => Ok(false),
-        _ => Err(format!("Cannot parse '{}' as a boolean (expected: true/false, yes/no, 1/0, etc.)", sanitized))
+
+    /// Rate to convert `from` into `to`, crossing through the base currency
+    pub fn conversion(&self, from: &str, to: &str, on: Option<NaiveDate>) -> Result<FxConversion, String> {
+        let (from_rate, from_date) = self.base_rate(from, on)?;
+        let (to_rate, to_date) = self.base_rate(to, on)?;
+        let quote_date = match (from_date, to_date) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (date, None) | (None, date) => date,
+        };
+        Ok(FxConversion { rate: from_rate / to_rate, quote_date })
     }
 }
 
-// =================== CUSTOM DESERIALIZERS ===================
+/// A second bracket schedule that replaces the default one part-way through the tax year.
+/// Income is taxed under each schedule in proportion to the days it was in force.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScheduleChange {
+    pub effective_date: NaiveDate,
+    pub thresholds: Vec<f64>,
+    pub rates: Vec<f64>,
+    pub cliffs: Vec<bool>,
+}
 
-/// Custom deserializer that accepts both f64 numbers and strings, then parses them
-fn deserialize_flexible_f64<'de, D>(deserializer: D) -> Result<String, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    struct FlexibleF64Visitor;
+/// Alternative minimum tax: a flat rate on adjusted income above an exemption, owed instead of
+/// the regular tax when it is higher
+#[derive(Debug, Clone, PartialEq)]
+pub struct MinimumTaxConfig {
+    pub rate: f64,
+    pub exemption: f64,
+}
 
-    impl<'de> de::Visitor<'de> for FlexibleF64Visitor {
-        type Value = String;
+/// What the calc_tax surcharge is levied on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SurchargeBase {
+    /// Surcharge rate × total tax, once the tax exceeds the surcharge threshold
+    #[default]
+    Tax,
+    /// Surcharge rate × the part of taxable income above the surcharge threshold
+    Income,
+}
 
-        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-            formatter.write_str("a number or a string representing a number")
+impl SurchargeBase {
+    /// Parse `ENGINE_SURCHARGE_BASE` or the `surcharge_base` tool parameter
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "tax" => Ok(SurchargeBase::Tax),
+            "income" => Ok(SurchargeBase::Income),
+            _ => Err("must be 'tax' or 'income'".to_string()),
         }
+    }
+}
 
-        fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E>
-        where
-            E: de::Error,
-        {
-            Ok(value.to_string())
-        }
+/// How calc_transfer_tax applies its bracket schedule
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TransferTaxMethod {
+    /// The whole amount is taxed at the rate of the slice it falls in
+    #[default]
+    Slice,
+    /// Each bracket's rate applies only to the part of the amount within it
+    Marginal,
+}
 
-        fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
-        where
-            E: de::Error,
-        {
-            Ok(value.to_string())
+impl TransferTaxMethod {
+    /// Parse `ENGINE_TRANSFER_TAX_METHOD` or the `method` tool parameter
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "slice" => Ok(TransferTaxMethod::Slice),
+            "marginal" => Ok(TransferTaxMethod::Marginal),
+            _ => Err("must be 'slice' or 'marginal'".to_string()),
         }
+    }
+}
 
-        fn visit_f64<E>(self, value: f64) -> Result<Self::Value, E>
-        where
-            E: de::Error,
-        {
-            Ok(value.to_string())
-        }
+impl fmt::Display for TransferTaxMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TransferTaxMethod::Slice => "slice",
+            TransferTaxMethod::Marginal => "marginal",
+        })
+    }
+}
 
-        fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
-        where
-            E: de::Error,
-        {
-            Ok(value.to_string())
+/// How check_housing_grant scales the income limit to the size of the household
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EquivalenceScale {
+    /// 60% of AMI, raised by 10% for households of more than 4 people
+    #[default]
+    Size,
+    /// OECD-modified scale: 1 for the first adult, 0.5 for each further adult, 0.3 per child
+    OecdModified,
+    /// Original OECD scale: 1 for the first adult, 0.7 for each further adult, 0.5 per child
+    Oecd,
+    /// Square root of the number of people
+    SquareRoot,
+}
+
+impl EquivalenceScale {
+    /// Parse `ENGINE_HOUSING_EQUIVALENCE_SCALE`
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.trim().to_ascii_lowercase().replace('-', "_").as_str() {
+            "size" => Ok(EquivalenceScale::Size),
+            "oecd_modified" => Ok(EquivalenceScale::OecdModified),
+            "oecd" => Ok(EquivalenceScale::Oecd),
+            "square_root" => Ok(EquivalenceScale::SquareRoot),
+            _ => Err("must be 'size', 'oecd_modified', 'oecd' or 'square_root'".to_string()),
         }
+    }
 
-        fn visit_string<E>(self, value: String) -> Result<Self::Value, E>
-        where
-            E: de::Error,
-        {
-            Ok(value)
+    /// Whether the scale weighs children differently from adults
+    pub fn counts_children(self) -> bool {
+        matches!(self, EquivalenceScale::OecdModified | EquivalenceScale::Oecd)
+    }
+
+    /// Size of a household in adult equivalents; None for the size rule, which has none
+    pub fn adult_equivalents(self, household_size: i32, children: i32) -> Option<f64> {
+        let adults = f64::from(household_size - children);
+        let children = f64::from(children);
+        match self {
+            EquivalenceScale::Size => None,
+            EquivalenceScale::OecdModified => Some(1.0 + 0.5 * (adults - 1.0) + 0.3 * children),
+            EquivalenceScale::Oecd => Some(1.0 + 0.7 * (adults - 1.0) + 0.5 * children),
+            EquivalenceScale::SquareRoot => Some(f64::from(household_size).sqrt()),
         }
     }
+}
 
-    deserializer.deserialize_any(FlexibleF64Visitor)
+impl fmt::Display for EquivalenceScale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            EquivalenceScale::Size => "size",
+            EquivalenceScale::OecdModified => "oecd_modified",
+            EquivalenceScale::Oecd => "oecd",
+            EquivalenceScale::SquareRoot => "square_root",
+        })
+    }
 }
 
-/// Custom deserializer that accepts both i32 numbers and strings, then parses them
-fn deserialize_flexible_i32<'de, D>(deserializer: D) -> Result<String, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    struct FlexibleI32Visitor;
+/// One surcharge in `ENGINE_SURCHARGES` / `ENGINE_SURCHARGES_FILE`. Surcharges are applied in
+/// order; a tax-based surcharge is levied on the tax including any earlier surcharges. Rules
+/// without a base use the request's `surcharge_base` or `ENGINE_SURCHARGE_BASE`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SurchargeRule {
+    pub name: String,
+    pub threshold: f64,
+    pub rate: f64,
+    #[serde(default)]
+    pub base: Option<SurchargeBase>,
+}
 
-    impl<'de> de::Visitor<'de> for FlexibleI32Visitor {
-        type Value = String;
+/// A bracket schedule with the tax owed at each threshold precomputed, so the tax on an amount
+/// is one binary search instead of a walk through every bracket below it
+#[derive(Debug, Clone, PartialEq)]
+struct BracketTable {
+    thresholds: Vec<f64>,
+    rates: Vec<f64>,
+    cliffs: Vec<bool>,
+    /// Tax owed on an amount equal to each threshold
+    cumulative: Vec<f64>,
+    ascending: bool,
+}
 
-        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-            formatter.write_str("an integer or a string representing an integer")
+impl BracketTable {
+    fn new(thresholds: Vec<f64>, rates: Vec<f64>, cliffs: Vec<bool>) -> Self {
+        let mut cumulative = Vec::with_capacity(thresholds.len());
+        let mut tax = 0.0;
+        let mut lower = 0.0;
+        // A schedule with too few rates is reported by calc_tax before any amount is looked up
+        for (i, (&upper, &rate)) in thresholds.iter().zip(&rates).enumerate() {
+            tax = if CompatibilityEngine::is_cliff(&cliffs, i) { upper * rate } else { tax + (upper - lower) * rate };
+            cumulative.push(tax);
+            lower = upper;
         }
+        let ascending = thresholds.windows(2).all(|pair| pair[1] > pair[0]);
+        Self { thresholds, rates, cliffs, cumulative, ascending }
+    }
 
-        fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E>
-        where
-            E: de::Error,
-        {
-            Ok(value.to_string())
-        }
+    /// Whether there is one rate per bracket, the top one included
+    fn complete(&self) -> bool {
+        self.rates.len() == self.thresholds.len() + 1
+    }
 
-        fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
-        where
-            E: de::Error,
-        {
-            Ok(value.to_string())
-        }
+    /// Index of the bracket an amount falls in; an amount equal to a threshold tops the bracket below
+    fn bracket(&self, amount: f64) -> usize {
+        self.thresholds.partition_point(|&threshold| threshold < amount)
+    }
 
-        fn visit_f64<E>(self, value: f64) -> Result<Self::Value, E>
-        where
-            E: de::Error,
-        {
-            // Convert float to int if it's a whole number
-            if value.fract() == 0.0 {
-                Ok((value as i64).to_string())
-            } else {
-                Err(E::custom(format!("Expected integer, got float: {}", value)))
-            }
-        }
+    /// Lower bound of bracket `index`
+    fn lower(&self, index: usize) -> f64 {
+        if index == 0 { 0.0 } else { self.thresholds[index - 1] }
+    }
 
-        fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
-        where
-            E: de::Error,
-        {
-            Ok(value.to_string())
+    /// Tax on an amount: the tax owed at the bracket's lower bound plus its rate on the rest, or for
+    /// a cliff bracket its rate on the whole amount
+    fn tax(&self, amount: f64) -> f64 {
+        if amount <= 0.0 {
+            return 0.0;
         }
-
-        fn visit_string<E>(self, value: String) -> Result<Self::Value, E>
-        where
-            E: de::Error,
-        {
-            Ok(value)
+        let index = self.bracket(amount);
+        let rate = self.rates[index];
+        if CompatibilityEngine::is_cliff(&self.cliffs, index) {
+            return amount * rate;
         }
+        let below = if index == 0 { 0.0 } else { self.cumulative[index - 1] };
+        below + (amount - self.lower(index)) * rate
     }
+}
 
-    deserializer.deserialize_any(FlexibleI32Visitor)
+/// Bracket schedules and surcharge settings applied by calc_tax
+#[derive(Debug, Clone)]
+struct TaxRules {
+    brackets: Arc<BracketTable>,
+    /// Date a second schedule takes over part-way through the year, and its brackets
+    schedule_change: Option<(NaiveDate, Arc<BracketTable>)>,
+    minimum_tax: Option<MinimumTaxConfig>,
+    surcharges: Vec<SurchargeRule>,
+    surcharge_base: SurchargeBase,
 }
 
-/// Custom deserializer that accepts both booleans and strings, then parses them
-fn deserialize_flexible_bool<'de, D>(deserializer: D) -> Result<String, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    struct FlexibleBoolVisitor;
+impl EngineConfig {
+    /// Configuration from the settings read through `var`, with indexed thresholds adjusted by `index`
+    fn from_vars(var: &dyn Fn(&str) -> Option<String>, index: &PriceIndex) -> Self {
+        let (default_thresholds, default_rates, default_cliffs) = Self::tax_brackets_from_vars(var, index);
+        let (transfer_tax_thresholds, transfer_tax_rates, transfer_tax_cliffs) = Self::transfer_tax_brackets_from_vars(var, index);
 
-    impl<'de> de::Visitor<'de> for FlexibleBoolVisitor {
-        type Value = String;
+        Self {
+            default_rate_per_day: var("ENGINE_DEFAULT_RATE_PER_DAY")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(100.0),  // From LyFin-Compliance-Annex.md: "100 per day"
+                
+            default_cap: var("ENGINE_DEFAULT_CAP")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(1000.0),  // From LyFin-Compliance-Annex.md: "Maximum Cap: 1000"
+                
+            default_interest_rate: var("ENGINE_DEFAULT_INTEREST_RATE")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(0.05),  // From LyFin-Compliance-Annex.md: "5 percent annual"
+                
+            default_thresholds,
+            default_rates,
+            default_cliffs,
 
-        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-            formatter.write_str("a boolean or a string representing a boolean")
+            surcharges: Self::surcharges_from_vars(var),
+
+            surcharge_base: var("ENGINE_SURCHARGE_BASE")
+                .map(|s| SurchargeBase::parse(&s).unwrap_or_else(|e| {
+                    tracing::error!("Invalid ENGINE_SURCHARGE_BASE '{}': {}; surcharge levied on tax", s, e);
+                    SurchargeBase::Tax
+                }))
+                .unwrap_or_default(),  // From 2025_61-FR.md: "a surcharge of 2% of the total tax liability"
+
+            tax_schedule_change: Self::tax_schedule_change_from_vars(var, index),
+            minimum_tax: Self::minimum_tax_from_vars(var),
+
+            default_max_leverage: var("ENGINE_DEFAULT_MAX_LEVERAGE")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(4.0),  // Typical maintenance covenant: total debt ≤ 4.0× EBITDA
+
+            default_min_interest_coverage: var("ENGINE_DEFAULT_MIN_INTEREST_COVERAGE")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(2.0),  // Typical maintenance covenant: EBITDA ≥ 2.0× interest expense
+
+            capital_gains_short_term_rate: var("ENGINE_CAPITAL_GAINS_SHORT_TERM_RATE")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(0.20),  // Short-term gains taxed like income at the top bracket rate
+
+            capital_gains_long_term_rate: var("ENGINE_CAPITAL_GAINS_LONG_TERM_RATE")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(0.10),  // Preferential rate for assets held long term
+
+            capital_gains_long_term_days: var("ENGINE_CAPITAL_GAINS_LONG_TERM_DAYS")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(365),  // Long term once held for at least one year
+
+            default_capital_gains_exemption: var("ENGINE_CAPITAL_GAINS_ANNUAL_EXEMPTION")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(1000.0),  // Annual tax-free allowance on gains
+
+            inheritance_classes: Self::inheritance_classes_from_vars(var, index),
+
+            social_contributions: Self::social_contributions_from_vars(var),
+
+            default_property_millage: var("ENGINE_DEFAULT_PROPERTY_MILLAGE")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(15.0),  // Mills: tax per 1000 of taxable value
+
+            property_homestead_exemption: var("ENGINE_PROPERTY_HOMESTEAD_EXEMPTION")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(25000.0),  // Deducted from the value of an owner-occupied primary residence
+
+            property_assessment_cap: var("ENGINE_PROPERTY_ASSESSMENT_CAP")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(0.03),  // Maximum year-over-year increase in taxable value
+
+            transfer_tax_thresholds,
+            transfer_tax_rates,
+            transfer_tax_cliffs,
+
+            transfer_tax_method: var("ENGINE_TRANSFER_TAX_METHOD")
+                .map(|s| TransferTaxMethod::parse(&s).unwrap_or_else(|e| {
+                    tracing::error!("Invalid ENGINE_TRANSFER_TAX_METHOD '{}': {}; using slice computation", s, e);
+                    TransferTaxMethod::Slice
+                }))
+                .unwrap_or_default(),  // Transfer taxes are commonly levied on the whole price once a threshold is crossed
+
+            housing_equivalence_scale: var("ENGINE_HOUSING_EQUIVALENCE_SCALE")
+                .map(|s| EquivalenceScale::parse(&s).unwrap_or_else(|e| {
+                    tracing::error!("Invalid ENGINE_HOUSING_EQUIVALENCE_SCALE '{}': {}; using the household size rule", s, e);
+                    EquivalenceScale::Size
+                }))
+                .unwrap_or_default(),  // The Act raises the limit by 10% for households of more than 4
+
+            appeal_window_days: var("ENGINE_APPEAL_WINDOW_DAYS")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(30),  // Common statutory period for administrative appeals
+
+            appeal_business_days: var("ENGINE_APPEAL_BUSINESS_DAYS")
+                .map(|s| parse_bool_from_string(&s).unwrap_or_else(|e| {
+                    tracing::error!("Invalid ENGINE_APPEAL_BUSINESS_DAYS '{}': {}; counting calendar days", s, e);
+                    false
+                }))
+                .unwrap_or(false),  // Calendar days, with a deadline on a non-business day moved forward
+
+            public_holidays: Self::public_holidays_from_vars(var),
+
+            required_documents: Self::required_documents_from_vars(var),
+
+            fee_schedules: Self::fee_schedules_from_vars(var),
+
+            fx: Self::fx_table_from_vars(var),
+
+            strict_parsing: var("ENGINE_STRICT_PARSING")
+                .map(|s| parse_bool_from_string(&s).unwrap_or_else(|e| {
+                    tracing::error!("Invalid ENGINE_STRICT_PARSING '{}': {}; strict parsing disabled", s, e);
+                    false
+                }))
+                .unwrap_or(false),
         }
+    }
+    
+    /// Tax brackets from `ENGINE_TAX_BRACKETS` (inline JSON), `ENGINE_TAX_BRACKETS_FILE` (path to
+    /// JSON), or the legacy `ENGINE_DEFAULT_THRESHOLDS` / `ENGINE_DEFAULT_RATES` lists, in that order.
+    /// Invalid configuration is reported at load and the next source is used instead.
+    fn tax_brackets_from_vars(var: &dyn Fn(&str) -> Option<String>, index: &PriceIndex) -> BracketSchedule {
+        load_json_config(
+            var,
+            "ENGINE_TAX_BRACKETS",
+            "ENGINE_TAX_BRACKETS_FILE",
+            |json| Self::parse_tax_brackets(json, index),
+            "falling back to legacy tax bracket settings",
+            || Self::legacy_tax_bracket_schedule(var),
+        )
+    }
 
-        fn visit_bool<E>(self, value: bool) -> Result<Self::Value, E>
-        where
-            E: de::Error,
-        {
-            Ok(if value { "true".to_string() } else { "false".to_string() })
+    /// Tax brackets from the deprecated `ENGINE_DEFAULT_THRESHOLDS` / `ENGINE_DEFAULT_RATES` lists.
+    fn legacy_tax_bracket_schedule(var: &dyn Fn(&str) -> Option<String>) -> BracketSchedule {
+        let legacy = Self::set_vars(&LEGACY_TAX_BRACKET_VARS, var);
+        if !legacy.is_empty() {
+            tracing::info!(
+                "{} are deprecated; run with --migrate-config to generate a profile with the equivalent ENGINE_TAX_BRACKETS",
+                legacy.join(" and ")
+            );
+        }
+        let (thresholds, rates) = Self::legacy_tax_brackets(var);
+        if rates.len() != thresholds.len() + 1 {
+            tracing::error!(
+                "ENGINE_DEFAULT_RATES has {} rates for {} ENGINE_DEFAULT_THRESHOLDS (expected {}); calc_tax will reject requests",
+                rates.len(), thresholds.len(), thresholds.len() + 1
+            );
         }
+        let cliffs = vec![false; rates.len()];
+        (thresholds, rates, cliffs)
+    }
 
-        fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
-        where
-            E: de::Error,
-        {
-            Ok(value.to_string())
+    /// The legacy `ENGINE_DEFAULT_THRESHOLDS` / `ENGINE_DEFAULT_RATES` lists, read through `var`
+    fn legacy_tax_brackets(var: impl Fn(&str) -> Option<String>) -> (Vec<f64>, Vec<f64>) {
+        let thresholds = var("ENGINE_DEFAULT_THRESHOLDS")
+            .and_then(|s| Self::parse_vec_f64(&s))
+            .unwrap_or_else(|| vec![10000.0]);  // From 2025_61-FR.md: "First bracket: 10% on income up to 10000"
+        let rates = var("ENGINE_DEFAULT_RATES")
+            .and_then(|s| Self::parse_vec_f64(&s))
+            .unwrap_or_else(|| vec![0.10, 0.20]);  // From 2025_61-FR.md: "10% up to 10000", "20% exceeding 10000"
+        (thresholds, rates)
+    }
+
+    fn transfer_tax_brackets_from_vars(var: &dyn Fn(&str) -> Option<String>, index: &PriceIndex) -> BracketSchedule {
+        load_json_config(
+            var,
+            "ENGINE_TRANSFER_TAX_BRACKETS",
+            "ENGINE_TRANSFER_TAX_BRACKETS_FILE",
+            |json| Self::parse_tax_brackets(json, index),
+            "using built-in transfer tax brackets",
+            Self::builtin_transfer_tax_brackets,
+        )
+    }
+
+    /// The built-in transfer tax brackets
+    fn builtin_transfer_tax_brackets() -> BracketSchedule {
+        // Illustrative schedule: 2% up to 250000, 5% up to 925000, 10% above
+        (vec![250000.0, 925000.0], vec![0.02, 0.05, 0.10], vec![false; 3])
+    }
+
+    /// Parse and validate a JSON bracket list such as
+    /// `[{"up_to": 10000, "rate": 0.10}, {"rate": 0.20, "cliff": true}]` into thresholds, rates
+    /// and cliff flags.
+    fn parse_tax_brackets(json: &str, index: &PriceIndex) -> Result<BracketSchedule, String> {
+        let brackets: Vec<TaxBracketConfig> =
+            serde_json::from_str(json).map_err(|e| format!("invalid JSON: {}", e))?;
+        Self::validate_tax_brackets(&brackets, index)
+    }
+
+    /// Validate a bracket list and split it into thresholds, rates and cliff flags
+    fn validate_tax_brackets(brackets: &[TaxBracketConfig], index: &PriceIndex) -> Result<BracketSchedule, String> {
+        if brackets.is_empty() {
+            return Err("at least one bracket is required".to_string());
         }
 
-        fn visit_string<E>(self, value: String) -> Result<Self::Value, E>
-        where
-            E: de::Error,
-        {
-            Ok(value)
+        let mut thresholds = Vec::with_capacity(brackets.len() - 1);
+        let mut rates = Vec::with_capacity(brackets.len());
+        for (i, bracket) in brackets.iter().enumerate() {
+            let is_last = i == brackets.len() - 1;
+            match (bracket.up_to, is_last) {
+                (Some(_), true) => return Err(format!("bracket {} is the top bracket and must not set up_to", i + 1)),
+                (None, false) => return Err(format!("bracket {} must set up_to (only the top bracket may omit it)", i + 1)),
+                (Some(up_to), false) => {
+                    if !up_to.is_finite() || up_to <= 0.0 {
+                        return Err(format!("bracket {} up_to must be a positive number", i + 1));
+                    }
+                    let up_to = if bracket.indexed {
+                        index
+                            .index_to_target(up_to)
+                            .map_err(|e| format!("bracket {} is indexed but {}", i + 1, e))?
+                    } else {
+                        up_to
+                    };
+                    if thresholds.last().is_some_and(|previous| up_to <= *previous) {
+                        return Err(format!("bracket {} up_to must be greater than the previous bracket's", i + 1));
+                    }
+                    thresholds.push(up_to);
+                }
+                (None, true) if bracket.indexed => {
+                    return Err(format!("bracket {} is the top bracket and has no up_to to index", i + 1));
+                }
+                (None, true) => {}
+            }
+            if !(0.0..=1.0).contains(&bracket.rate) {
+                return Err(format!("bracket {} rate must be a decimal between 0 and 1", i + 1));
+            }
+            rates.push(bracket.rate);
         }
+        let cliffs = brackets.iter().map(|bracket| bracket.cliff).collect();
+        Ok((thresholds, rates, cliffs))
     }
 
-    deserializer.deserialize_any(FlexibleBoolVisitor)
-}
-
-// =================== DATA STRUCTURES ===================
+    /// Inheritance classes from `ENGINE_INHERITANCE_CLASSES` (inline JSON) or
+    /// `ENGINE_INHERITANCE_CLASSES_FILE` (path to JSON), falling back to the built-in classes.
+    fn inheritance_classes_from_vars(var: &dyn Fn(&str) -> Option<String>, index: &PriceIndex) -> BTreeMap<String, InheritanceClass> {
+        load_json_config(
+            var,
+            "ENGINE_INHERITANCE_CLASSES",
+            "ENGINE_INHERITANCE_CLASSES_FILE",
+            |json| Self::parse_inheritance_classes(json, index),
+            "using built-in inheritance classes",
+            Self::builtin_inheritance_classes,
+        )
+    }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
-pub struct CalcPenaltyParams {
-    #[serde(deserialize_with = "deserialize_flexible_f64")]
-    #[schemars(description = "Number of days late")]
-    pub days_late: String,
-    /// Optional. If not provided, uses configured default (e.g. ENGINE_DEFAULT_RATE_PER_DAY).
-    #[serde(default)]
-    #[schemars(description = "Optional rate per day; uses default if omitted")]
-    pub rate_per_day: Option<String>,
-    /// Optional. If not provided, uses configured default (e.g. ENGINE_DEFAULT_CAP).
-    #[serde(default)]
-    #[schemars(description = "Optional cap; uses default if omitted")]
-    pub cap: Option<String>,
-    /// Optional. If not provided, uses configured default (e.g. ENGINE_DEFAULT_INTEREST_RATE).
-    #[serde(default)]
-    #[schemars(description = "Optional interest rate; uses default if omitted")]
-    pub interest_rate: Option<String>,
-}
+    /// The built-in inheritance classes
+    fn builtin_inheritance_classes() -> BTreeMap<String, InheritanceClass> {
+        // Illustrative class-based schedule: close family has high exemptions and lower rates
+        let family_rates = vec![0.07, 0.11, 0.15, 0.19];
+        let distant_rates = vec![0.15, 0.20, 0.25, 0.30];
+        let thresholds = vec![75000.0, 300000.0, 600000.0];
+        BTreeMap::from([
+            ("spouse".to_string(), InheritanceClass { exemption: 500000.0, thresholds: thresholds.clone(), rates: family_rates.clone(), cliffs: vec![false; 4] }),
+            ("child".to_string(), InheritanceClass { exemption: 400000.0, thresholds: thresholds.clone(), rates: family_rates, cliffs: vec![false; 4] }),
+            ("sibling".to_string(), InheritanceClass { exemption: 20000.0, thresholds: thresholds.clone(), rates: distant_rates, cliffs: vec![false; 4] }),
+            ("other".to_string(), InheritanceClass { exemption: 20000.0, thresholds: vec![600000.0], rates: vec![0.30, 0.50], cliffs: vec![false; 2] }),
+        ])
+    }
 
-impl Default for CalcPenaltyParams {
-    fn default() -> Self {
-        Self {
-            days_late: String::new(),
-            rate_per_day: None,
-            cap: None,
-            interest_rate: None,
+    /// Parse and validate a JSON object of relationship classes such as
+    /// `{"child": {"exemption": 400000, "brackets": [{"up_to": 75000, "rate": 0.07}, {"rate": 0.19}]}}`.
+    fn parse_inheritance_classes(json: &str, index: &PriceIndex) -> Result<BTreeMap<String, InheritanceClass>, String> {
+        let classes: BTreeMap<String, InheritanceClassConfig> =
+            serde_json::from_str(json).map_err(|e| format!("invalid JSON: {}", e))?;
+        if classes.is_empty() {
+            return Err("at least one relationship class is required".to_string());
         }
+        classes
+            .into_iter()
+            .map(|(relationship, class)| {
+                if !class.exemption.is_finite() || class.exemption < 0.0 {
+                    return Err(format!("class '{}' exemption must not be negative", relationship));
+                }
+                let (thresholds, rates, cliffs) = Self::validate_tax_brackets(&class.brackets, index)
+                    .map_err(|e| format!("class '{}': {}", relationship, e))?;
+                let relationship = relationship.trim().to_ascii_lowercase();
+                Ok((relationship, InheritanceClass { exemption: class.exemption, thresholds, rates, cliffs }))
+            })
+            .collect()
     }
-}
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
-pub struct CalcTaxParams {
-    #[serde(deserialize_with = "deserialize_flexible_f64")]
-    #[schemars(description = "Total income")]
-    pub income: String,
-}
+    /// Social contribution types from `ENGINE_SOCIAL_CONTRIBUTIONS` (inline JSON) or
+    /// `ENGINE_SOCIAL_CONTRIBUTIONS_FILE` (path to JSON), falling back to the built-in types.
+    fn social_contributions_from_vars(var: &dyn Fn(&str) -> Option<String>) -> Vec<SocialContribution> {
+        load_json_config(
+            var,
+            "ENGINE_SOCIAL_CONTRIBUTIONS",
+            "ENGINE_SOCIAL_CONTRIBUTIONS_FILE",
+            Self::parse_social_contributions,
+            "using built-in social contributions",
+            Self::builtin_social_contributions,
+        )
+    }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
-pub struct CheckVotingParams {
-    #[serde(deserialize_with = "deserialize_flexible_i32")]
-    #[schemars(description = "Total number of eligible voters")]
-    pub eligible_voters: String,
-    #[serde(deserialize_with = "deserialize_flexible_i32")]
-    #[schemars(description = "Actual turnout (number of people who voted)")]
-    pub turnout: String,
-    #[serde(deserialize_with = "deserialize_flexible_i32")]
-    #[schemars(description = "Number of yes votes")]
-    pub yes_votes: String,
-    #[schemars(description = "Type of proposal: 'general' or 'amendment'")]
-    pub proposal_type: String,
-}
+    /// The built-in social contributions
+    fn builtin_social_contributions() -> Vec<SocialContribution> {
+        // Illustrative payroll schedule split evenly between employer and employee
+        let contribution = |name: &str, ceiling: f64, rate: f64| SocialContribution {
+            name: name.to_string(),
+            ceiling: Some(ceiling),
+            employee_rate: rate,
+            employer_rate: rate,
+        };
+        vec![
+            contribution("pension", 90000.0, 0.093),
+            contribution("health", 62000.0, 0.073),
+            contribution("unemployment", 90000.0, 0.013),
+        ]
+    }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
-pub struct DistributeWaterfallParams {
-    #[serde(deserialize_with = "deserialize_flexible_f64")]
-    #[schemars(description = "Total cash available for distribution")]
-    pub cash_available: String,
-    #[serde(deserialize_with = "deserialize_flexible_f64")]
-    #[schemars(description = "Senior debt amount")]
-    pub senior_debt: String,
-    #[serde(deserialize_with = "deserialize_flexible_f64")]
-    #[schemars(description = "Junior debt amount")]
-    pub junior_debt: String,
-}
+    /// Parse and validate a JSON contribution list such as
+    /// `[{"name": "pension", "ceiling": 90000, "employee_rate": 0.093, "employer_rate": 0.093}]`.
+    fn parse_social_contributions(json: &str) -> Result<Vec<SocialContribution>, String> {
+        let contributions: Vec<SocialContribution> =
+            serde_json::from_str(json).map_err(|e| format!("invalid JSON: {}", e))?;
+        if contributions.is_empty() {
+            return Err("at least one contribution type is required".to_string());
+        }
+        for (i, contribution) in contributions.iter().enumerate() {
+            if contribution.name.trim().is_empty() {
+                return Err(format!("contribution {} must have a name", i + 1));
+            }
+            if contributions[..i].iter().any(|previous| previous.name == contribution.name) {
+                return Err(format!("contribution '{}' is listed more than once", contribution.name));
+            }
+            if contribution.ceiling.is_some_and(|ceiling| !ceiling.is_finite() || ceiling <= 0.0) {
+                return Err(format!("contribution '{}' ceiling must be a positive number", contribution.name));
+            }
+            for rate in [contribution.employee_rate, contribution.employer_rate] {
+                if !(0.0..=1.0).contains(&rate) {
+                    return Err(format!("contribution '{}' rates must be decimals between 0 and 1", contribution.name));
+                }
+            }
+        }
+        Ok(contributions)
+    }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
-pub struct DistributeWaterfallResult {
-    #[schemars(description = "Amount allocated to senior debt")]
-    pub senior: f64,
-    #[schemars(description = "Amount allocated to junior debt")]
-    pub junior: f64,
-    #[schemars(description = "Amount allocated to equity")]
-    pub equity: f64,
-}
+    /// Fee schedules from `ENGINE_FEE_SCHEDULES` (inline JSON) or `ENGINE_FEE_SCHEDULES_FILE`
+    /// (path to JSON), falling back to the built-in schedules.
+    fn fee_schedules_from_vars(var: &dyn Fn(&str) -> Option<String>) -> BTreeMap<String, Vec<FeeComponent>> {
+        load_json_config(
+            var,
+            "ENGINE_FEE_SCHEDULES",
+            "ENGINE_FEE_SCHEDULES_FILE",
+            Self::parse_fee_schedules,
+            "using built-in fee schedules",
+            Self::builtin_fee_schedules,
+        )
+    }
 
-// Response structures with explanations
-#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
-pub struct CalcPenaltyResponse {
-    #[schemars(description = "Calculated penalty amount")]
-    pub penalty: f64,
-    #[schemars(description = "Explanation of calculation steps")]
-    pub explanation: String,
-    #[schemars(description = "Any errors in input validation")]
-    pub errors: Vec<String>,
-    #[schemars(description = "Warnings or additional information")]
-    pub warnings: Vec<String>,
-}
+    /// The built-in fee schedules
+    fn builtin_fee_schedules() -> BTreeMap<String, Vec<FeeComponent>> {
+        // Illustrative court and registry fees
+        let component = |name: &str, fixed: f64, rate: f64, above: f64, cap: Option<f64>| FeeComponent {
+            name: name.to_string(),
+            fixed,
+            rate,
+            above,
+            cap,
+        };
+        BTreeMap::from([
+            ("court_filing".to_string(), vec![component("filing", 50.0, 0.01, 10000.0, Some(5000.0))]),
+            ("land_registration".to_string(), vec![
+                component("registration", 100.0, 0.0, 0.0, None),
+                component("stamp", 0.0, 0.005, 0.0, Some(1000.0)),
+            ]),
+        ])
+    }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
-pub struct CalcTaxResponse {
-    #[schemars(description = "Calculated tax amount")]
-    pub tax: f64,
-    #[schemars(description = "Explanation of calculation steps")]
-    pub explanation: String,
-    #[schemars(description = "Any errors in input validation")]
-    pub errors: Vec<String>,
-    #[schemars(description = "Warnings or additional information")]
-    pub warnings: Vec<String>,
-}
+    /// Document checklists from `ENGINE_REQUIRED_DOCUMENTS` (inline JSON) or
+    /// `ENGINE_REQUIRED_DOCUMENTS_FILE` (path to JSON), falling back to the built-in checklists.
+    fn required_documents_from_vars(var: &dyn Fn(&str) -> Option<String>) -> BTreeMap<String, Vec<RequiredDocument>> {
+        load_json_config(
+            var,
+            "ENGINE_REQUIRED_DOCUMENTS",
+            "ENGINE_REQUIRED_DOCUMENTS_FILE",
+            Self::parse_required_documents,
+            "using built-in document checklists",
+            Self::builtin_required_documents,
+        )
+    }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
-pub struct CheckVotingResponse {
-    #[schemars(description = "Whether the proposal passes")]
-    pub passes: bool,
-    #[schemars(description = "Explanation of voting calculation")]
-    pub explanation: String,
-    #[schemars(description = "Any errors in input validation")]
-    pub errors: Vec<String>,
-    #[schemars(description = "Warnings or additional information")]
-    pub warnings: Vec<String>,
-}
+    /// The built-in document checklists
+    fn builtin_required_documents() -> BTreeMap<String, Vec<RequiredDocument>> {
+        // The housing grant documents the Act asks for, and the records of a vote
+        let document = |document: &str, when: Option<&str>| RequiredDocument {
+            document: document.to_string(),
+            when: when.map(str::to_string),
+        };
+        BTreeMap::from([
+            ("housing_grant".to_string(), vec![
+                document("Proof of identity of the applicant", None),
+                document("Proof of income of every adult household member for the last 12 months", None),
+                document("Signed declaration that no other housing subsidy is received", None),
+                document("Tax returns for the last 2 years and a current profit and loss statement", Some("self_employed")),
+                document("Proof of the composition of the household (birth certificates or custody orders)", Some("large_household")),
+                document("Residence permit or other proof of lawful residence", Some("non_resident")),
+                document("Certificate of income earned abroad", Some("non_resident")),
+            ]),
+            ("voting".to_string(), vec![
+                document("Register of eligible voters", None),
+                document("Minutes of the vote with the counts of votes cast and yes votes", None),
+                document("Current text of the provision being amended", Some("amendment")),
+            ]),
+        ])
+    }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
-pub struct DistributeWaterfallResponse {
-    #[schemars(description = "Distribution results")]
-    pub distribution: DistributeWaterfallResult,
-    #[schemars(description = "Explanation of waterfall distribution")]
-    pub explanation: String,
-    #[schemars(description = "Any errors in input validation")]
-    pub errors: Vec<String>,
-    #[schemars(description = "Warnings or additional information")]
-    pub warnings: Vec<String>,
-}
+    /// Parse and validate a JSON object of document checklists such as
+    /// `{"housing_grant": [{"document": "Proof of income"}, {"document": "Tax returns", "when": "self_employed"}]}`.
+    fn parse_required_documents(json: &str) -> Result<BTreeMap<String, Vec<RequiredDocument>>, String> {
+        let checklists: BTreeMap<String, Vec<RequiredDocument>> =
+            serde_json::from_str(json).map_err(|e| format!("invalid JSON: {}", e))?;
+        if checklists.is_empty() {
+            return Err("at least one program is required".to_string());
+        }
+        checklists
+            .into_iter()
+            .map(|(program, documents)| {
+                if documents.is_empty() {
+                    return Err(format!("program '{}' must list at least one document", program));
+                }
+                let mut documents = documents;
+                for (i, item) in documents.iter_mut().enumerate() {
+                    if item.document.trim().is_empty() {
+                        return Err(format!("program '{}' document {} must have a text", program, i + 1));
+                    }
+                    item.when = item.when.as_deref().map(situation_flag);
+                    if item.when.as_deref() == Some("") {
+                        return Err(format!("program '{}' document {} has an empty 'when'", program, i + 1));
+                    }
+                }
+                Ok((program.trim().to_ascii_lowercase(), documents))
+            })
+            .collect()
+    }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
-pub struct CheckHousingGrantResponse {
-    #[schemars(description = "Whether eligible for housing grant")]
-    pub eligible: bool,
-    #[schemars(description = "Explanation of eligibility calculation")]
-    pub explanation: String,
-    #[schemars(description = "Any errors in input validation")]
-    pub errors: Vec<String>,
-    #[schemars(description = "Additional requirements or warnings")]
-    pub additional_requirements: Vec<String>,
-}
+    /// Parse and validate a JSON object of fee schedules such as
+    /// `{"court_filing": [{"name": "filing", "fixed": 50, "rate": 0.01, "above": 10000, "cap": 5000}]}`.
+    fn parse_fee_schedules(json: &str) -> Result<BTreeMap<String, Vec<FeeComponent>>, String> {
+        let schedules: BTreeMap<String, Vec<FeeComponent>> =
+            serde_json::from_str(json).map_err(|e| format!("invalid JSON: {}", e))?;
+        if schedules.is_empty() {
+            return Err("at least one fee code is required".to_string());
+        }
+        schedules
+            .into_iter()
+            .map(|(code, components)| {
+                if components.is_empty() {
+                    return Err(format!("fee '{}' must have at least one component", code));
+                }
+                for (i, component) in components.iter().enumerate() {
+                    if component.name.trim().is_empty() {
+                        return Err(format!("fee '{}' component {} must have a name", code, i + 1));
+                    }
+                    if components[..i].iter().any(|previous| previous.name == component.name) {
+                        return Err(format!("fee '{}' component '{}' is listed more than once", code, component.name));
+                    }
+                    if !component.fixed.is_finite() || component.fixed < 0.0 || !component.above.is_finite() || component.above < 0.0 {
+                        return Err(format!("fee '{}' component '{}' fixed and above must not be negative", code, component.name));
+                    }
+                    if !(0.0..=1.0).contains(&component.rate) {
+                        return Err(format!("fee '{}' component '{}' rate must be a decimal between 0 and 1", code, component.name));
+                    }
+                    if component.cap.is_some_and(|cap| !cap.is_finite() || cap <= 0.0) {
+                        return Err(format!("fee '{}' component '{}' cap must be a positive number", code, component.name));
+                    }
+                }
+                Ok((code.trim().to_ascii_lowercase(), components))
+            })
+            .collect()
+    }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
-pub struct CheckHousingGrantParams {
-    #[serde(deserialize_with = "deserialize_flexible_f64")]
-    #[schemars(description = "Area Median Income (AMI)")]
-    pub ami: String,
-    #[serde(deserialize_with = "deserialize_flexible_i32")]
-    #[schemars(description = "Household size")]
-    pub household_size: String,
-    #[serde(deserialize_with = "deserialize_flexible_f64")]
-    #[schemars(description = "Household income")]
-    pub income: String,
-    #[serde(deserialize_with = "deserialize_flexible_bool")]
-    #[schemars(description = "Whether the household has another subsidy (true/false, yes/no, 1/0)")]
-    pub has_other_subsidy: String,
-}
+    /// Public holidays from `ENGINE_PUBLIC_HOLIDAYS` (comma-separated YYYY-MM-DD dates); invalid
+    /// dates are logged and skipped
+    fn public_holidays_from_vars(var: &dyn Fn(&str) -> Option<String>) -> BTreeSet<NaiveDate> {
+        let Some(value) = var("ENGINE_PUBLIC_HOLIDAYS") else {
+            return BTreeSet::new();
+        };
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|date| !date.is_empty())
+            .filter_map(|date| {
+                NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                    .inspect_err(|_| tracing::error!("Invalid ENGINE_PUBLIC_HOLIDAYS date '{}' (expected YYYY-MM-DD); skipped", date))
+                    .ok()
+            })
+            .collect()
+    }
 
-// =================== COMPATIBILITY ENGINE ===================
+    /// Exchange rates from `ENGINE_FX_RATES` (inline JSON) or `ENGINE_FX_RATES_FILE` (path to
+    /// JSON) against `ENGINE_BASE_CURRENCY` (default EUR), falling back to the built-in rates.
+    fn fx_table_from_vars(var: &dyn Fn(&str) -> Option<String>) -> FxTable {
+        let base_currency = var("ENGINE_BASE_CURRENCY")
+            .map(|s| s.trim().to_ascii_uppercase())
+            .filter(|s| Self::is_currency_code(s))
+            .unwrap_or_else(|| "EUR".to_string());
+        load_json_config(
+            var,
+            "ENGINE_FX_RATES",
+            "ENGINE_FX_RATES_FILE",
+            |json| Self::parse_fx_rates(json, &base_currency),
+            "using built-in exchange rates",
+            || Self::builtin_fx_table(&base_currency),
+        )
+    }
 
-#[derive(Debug, Clone)]
-pub struct CompatibilityEngine {
-    tool_router: ToolRouter<Self>,
-}
+    /// The built-in exchange rates, which are quoted against the euro
+    fn builtin_fx_table(base_currency: &str) -> FxTable {
+        if base_currency != "EUR" {
+            tracing::error!("ENGINE_BASE_CURRENCY is {} but no ENGINE_FX_RATES are configured; only {} amounts can be converted", base_currency, base_currency);
+            return FxTable { base_currency: base_currency.to_string(), rates: BTreeMap::new() };
+        }
 
-impl CompatibilityEngine {
-    /// Calculate penalty with cap and interest
-    fn calc_penalty_internal(
-        days_late: f64, 
-        rate_per_day: f64, 
-        cap: f64, 
-        interest_rate: f64
-    ) -> CalcPenaltyResponse {
-        let mut errors = Vec::new();
-        let mut warnings = Vec::new();
-        let mut explanation_parts = Vec::new();
-        
-        // Validation
-        if days_late < 0.0 {
-            errors.push("Days late cannot be negative".to_string());
-        }
-        if rate_per_day < 0.0 {
-            errors.push("Rate per day cannot be negative".to_string());
-        }
-        if cap < 0.0 {
-            errors.push("Cap cannot be negative".to_string());
-        }
-        if interest_rate < 0.0 {
-            errors.push("Interest rate cannot be negative".to_string());
-        }
-        
-        if !errors.is_empty() {
-            return CalcPenaltyResponse {
-                penalty: 0.0,
-                explanation: "Calculation failed due to invalid inputs".to_string(),
-                errors,
-                warnings,
-            };
-        }
-        
-        // Calculate base penalty
-        let base_penalty = days_late * rate_per_day;
-        explanation_parts.push(format!("Base penalty: {} days × {} = {:.2}", days_late, rate_per_day, base_penalty));
-        
-        // Apply cap
-        let penalty = base_penalty.min(cap);
-        if base_penalty > cap {
-            explanation_parts.push(format!("Applied cap on base penalty: {:.2} capped at {:.2}", base_penalty, cap));
-            warnings.push(format!("Base penalty {:.2} exceeded cap of {:.2}", base_penalty, cap));
-        } else {
-            explanation_parts.push(format!("No cap applied on base penalty ({:.2} ≤ {:.2})", base_penalty, cap));
-        }
-        
-        // Calculate interest
-        let interest = penalty * interest_rate;
-        explanation_parts.push(format!("Interest: {:.2} × {:.1}% = {:.2}", penalty, interest_rate * 100.0, interest));
-        
-        let final_penalty = penalty + interest;
-        explanation_parts.push(format!("Final penalty: {:.2} + {:.2} = {:.2}", penalty, interest, final_penalty));
-        
-        if interest_rate > 0.1 {
-            warnings.push(format!("High interest rate: {:.1}%", interest_rate * 100.0));
-        }
-        
-        CalcPenaltyResponse {
-            penalty: final_penalty,
-            explanation: explanation_parts.join(". "),
-            errors,
-            warnings,
-        }
+        // Illustrative reference rates against the euro
+        let date = NaiveDate::from_ymd_opt(2025, 1, 2).unwrap_or_default();
+        let quotes = [("USD", 0.9650), ("GBP", 1.2050), ("CHF", 1.0620), ("JPY", 0.0061)];
+        let rates = quotes
+            .into_iter()
+            .map(|(currency, rate)| (currency.to_string(), BTreeMap::from([(date, rate)])))
+            .collect();
+        FxTable { base_currency: base_currency.to_string(), rates }
     }
 
-    /// Calculate progressive tax with surcharge
-    fn calc_tax_internal(
-        income: f64,
-        thresholds: Vec<f64>,
-        rates: Vec<f64>,
-        surcharge_threshold: f64,
-        surcharge_rate: f64,
-    ) -> CalcTaxResponse {
-        let mut errors = Vec::new();
-        let mut warnings = Vec::new();
-        let mut explanation_parts = Vec::new();
-        
-        // Validation
-        if income < 0.0 {
-            errors.push("Income cannot be negative".to_string());
-        }
-        if rates.len() != thresholds.len() + 1 {
-            errors.push(format!("Invalid bracket configuration: {} rates for {} thresholds (should be {} rates)", 
-                rates.len(), thresholds.len(), thresholds.len() + 1));
+    /// Parse and validate a JSON list of exchange rates such as
+    /// `[{"date": "2025-01-02", "currency": "USD", "rate": 0.965}]` against the base currency.
+    fn parse_fx_rates(json: &str, base_currency: &str) -> Result<FxTable, String> {
+        let quotes: Vec<FxRateConfig> =
+            serde_json::from_str(json).map_err(|e| format!("invalid JSON: {}", e))?;
+        if quotes.is_empty() {
+            return Err("at least one exchange rate is required".to_string());
         }
-        if surcharge_threshold < 0.0 {
-            errors.push("Surcharge threshold cannot be negative".to_string());
+        let mut rates: BTreeMap<String, BTreeMap<NaiveDate, f64>> = BTreeMap::new();
+        for (i, quote) in quotes.iter().enumerate() {
+            let currency = quote.currency.trim().to_ascii_uppercase();
+            if !Self::is_currency_code(&currency) {
+                return Err(format!("rate {} currency must be a three-letter code", i + 1));
+            }
+            if currency == base_currency {
+                return Err(format!("rate {} is for the base currency {}", i + 1, base_currency));
+            }
+            let date = NaiveDate::parse_from_str(quote.date.trim(), "%Y-%m-%d")
+                .map_err(|_| format!("rate {} date '{}' must be YYYY-MM-DD", i + 1, quote.date.trim()))?;
+            if !quote.rate.is_finite() || quote.rate <= 0.0 {
+                return Err(format!("rate {} must be a positive number", i + 1));
+            }
+            if rates.entry(currency.clone()).or_default().insert(date, quote.rate).is_some() {
+                return Err(format!("{} has more than one rate on {}", currency, date));
+            }
         }
-        if surcharge_rate < 0.0 {
-            errors.push("Surcharge rate cannot be negative".to_string());
+        Ok(FxTable { base_currency: base_currency.to_string(), rates })
+    }
+
+    fn is_currency_code(code: &str) -> bool {
+        code.len() == 3 && code.chars().all(|c| c.is_ascii_uppercase())
+    }
+
+    /// Surcharges from `ENGINE_SURCHARGES` (inline JSON) or `ENGINE_SURCHARGES_FILE` (path to JSON),
+    /// falling back to the single legacy `ENGINE_DEFAULT_SURCHARGE_THRESHOLD` / `ENGINE_DEFAULT_SURCHARGE_RATE` pair.
+    fn surcharges_from_vars(var: &dyn Fn(&str) -> Option<String>) -> Vec<SurchargeRule> {
+        load_json_config(
+            var,
+            "ENGINE_SURCHARGES",
+            "ENGINE_SURCHARGES_FILE",
+            Self::parse_surcharges,
+            "falling back to legacy surcharge settings",
+            || Self::legacy_surcharges(var),
+        )
+    }
+
+    /// The single surcharge from the deprecated `ENGINE_DEFAULT_SURCHARGE_THRESHOLD` / `ENGINE_DEFAULT_SURCHARGE_RATE` pair.
+    fn legacy_surcharges(var: &dyn Fn(&str) -> Option<String>) -> Vec<SurchargeRule> {
+        let legacy = Self::set_vars(&LEGACY_SURCHARGE_VARS, var);
+        if !legacy.is_empty() {
+            tracing::info!(
+                "{} are deprecated; run with --migrate-config to generate a profile with the equivalent ENGINE_SURCHARGES",
+                legacy.join(" and ")
+            );
         }
-        
-        // Check if thresholds are sorted
-        for i in 1..thresholds.len() {
-            if thresholds[i] <= thresholds[i-1] {
-                errors.push("Tax thresholds must be in ascending order".to_string());
-                break;
-            }
+        vec![Self::legacy_surcharge(var)]
+    }
+
+    /// The surcharge of the legacy `ENGINE_DEFAULT_SURCHARGE_THRESHOLD` / `ENGINE_DEFAULT_SURCHARGE_RATE` pair
+    fn legacy_surcharge(var: impl Fn(&str) -> Option<String>) -> SurchargeRule {
+        SurchargeRule {
+            name: "Surcharge".to_string(),
+            threshold: var("ENGINE_DEFAULT_SURCHARGE_THRESHOLD")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(5000.0),  // From 2025_61-FR.md: "Where the tax calculated... exceeds 5000"
+            rate: var("ENGINE_DEFAULT_SURCHARGE_RATE")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(0.02),  // From 2025_61-FR.md: "a surcharge of 2% of the total tax liability"
+            base: None,
         }
-        
-        if !errors.is_empty() {
-            return CalcTaxResponse {
-                tax: 0.0,
-                explanation: "Tax calculation failed due to invalid inputs".to_string(),
-                errors,
-                warnings,
+    }
+
+    /// The variables of `names` that are set
+    fn set_vars(names: &[&'static str], var: impl Fn(&str) -> Option<String>) -> Vec<&'static str> {
+        names.iter().copied().filter(|name| var(name).is_some()).collect()
+    }
+
+    /// The current settings as a profile, for `--migrate-config`
+    pub fn migrate_config() -> String {
+        Self::profile_from_vars(&config_var)
+    }
+
+    /// A profile of the settings read through `var`. JSON settings are written as JSON, and the
+    /// deprecated bracket and surcharge settings are replaced by the equivalent `ENGINE_TAX_BRACKETS`
+    /// and `ENGINE_SURCHARGES` when those are unset. Loading the profile gives the same configuration.
+    fn profile_from_vars(var: &dyn Fn(&str) -> Option<String>) -> String {
+        let mut profile: BTreeMap<&str, ProfileSetting> = BTreeMap::new();
+        for name in CONFIG_VARS {
+            let Some(raw) = var(name) else {
+                continue;
             };
+            let value = match serde_json::from_str::<serde_json::Value>(&raw) {
+                Ok(json) if json.is_array() || json.is_object() => json,
+                _ => serde_json::Value::String(raw),
+            };
+            profile.insert(name, ProfileSetting { value, source: Some(PROFILE_SOURCE.to_string()), note: None });
         }
 
-        let mut tax = 0.0;
-        let mut remaining_income = income;
-        explanation_parts.push(format!("Starting income: {:.2}", income));
-        
-        // Apply progressive brackets
-        for (i, &threshold) in thresholds.iter().enumerate() {
-            if remaining_income <= 0.0 {
-                break;
+        let mut migrate = |legacy: &[&'static str], structured: &'static str, value: Result<serde_json::Value, String>| {
+            let legacy = Self::set_vars(legacy, var);
+            if legacy.is_empty() {
+                return;
             }
-            
-            let prev_threshold = if i == 0 { 0.0 } else { thresholds[i - 1] };
-            let bracket_size = threshold - prev_threshold;
-            let taxable_in_bracket = if remaining_income > bracket_size {
-                bracket_size
+            let file = format!("{}_FILE", structured);
+            let note = if var(structured).is_some() || var(&file).is_some() {
+                format!("Deprecated; only used while {} or {} is invalid", structured, file)
             } else {
-                remaining_income
+                match value {
+                    Ok(value) => {
+                        let replaced: Vec<String> = legacy
+                            .iter()
+                            .filter_map(|name| var(name).map(|raw| format!("{}={}", name, raw.trim())))
+                            .collect();
+                        for name in &legacy {
+                            profile.remove(name);
+                        }
+                        let note = format!("Replaces the deprecated {}", replaced.join(" and "));
+                        profile.insert(structured, ProfileSetting { value, source: Some(PROFILE_SOURCE.to_string()), note: Some(note) });
+                        return;
+                    }
+                    Err(e) => format!("Deprecated; cannot be replaced by {}: {}", structured, e),
+                }
             };
-            
-            let bracket_tax = taxable_in_bracket * rates[i];
-            tax += bracket_tax;
-            remaining_income -= taxable_in_bracket;
-            
-            explanation_parts.push(format!(
-                "Bracket {} ({:.0}-{:.0}): {:.2} × {:.1}% = {:.2}", 
-                i + 1, prev_threshold, threshold, taxable_in_bracket, rates[i] * 100.0, bracket_tax
-            ));
-        }
-        
-        // Apply highest bracket rate to remaining income
-        if remaining_income > 0.0 {
-            let highest_rate = rates[rates.len() - 1];
-            let highest_bracket_tax = remaining_income * highest_rate;
-            tax += highest_bracket_tax;
-            
-            let prev_threshold = if thresholds.is_empty() { 0.0 } else { thresholds[thresholds.len() - 1] };
-            explanation_parts.push(format!(
-                "Highest bracket ({:.0}+): {:.2} × {:.1}% = {:.2}", 
-                prev_threshold, remaining_income, highest_rate * 100.0, highest_bracket_tax
-            ));
-        }
-        
-        explanation_parts.push(format!("Subtotal tax: {:.2}", tax));
-        
-        // Apply surcharge if tax exceeds threshold
-        if tax > surcharge_threshold {
-            let surcharge = tax * surcharge_rate;
-            tax += surcharge;
-            explanation_parts.push(format!(
-                "Surcharge applied (tax {:.2} > {:.2}): {:.2} × {:.1}% = {:.2}", 
-                tax - surcharge, surcharge_threshold, tax - surcharge, surcharge_rate * 100.0, surcharge
-            ));
-            explanation_parts.push(format!("Final tax with surcharge: {:.2}", tax));
+            for name in legacy {
+                if let Some(setting) = profile.get_mut(name) {
+                    setting.note = Some(note.clone());
+                }
+            }
+        };
+
+        let (thresholds, rates) = Self::legacy_tax_brackets(var);
+        let brackets = if rates.len() == thresholds.len() + 1 {
+            let mut brackets: Vec<serde_json::Value> = thresholds
+                .iter()
+                .zip(&rates)
+                .map(|(up_to, rate)| serde_json::json!({ "up_to": up_to, "rate": rate }))
+                .collect();
+            brackets.push(serde_json::json!({ "rate": rates[thresholds.len()] }));
+            let brackets = serde_json::Value::Array(brackets);
+            Self::parse_tax_brackets(&json_format::canonical(&brackets), &PriceIndex::from_vars(var)).map(|_| brackets)
         } else {
-            explanation_parts.push(format!("No surcharge (tax {:.2} ≤ {:.2})", tax, surcharge_threshold));
-        }
-        
-        if surcharge_rate > 0.05 {
-            warnings.push(format!("High surcharge rate: {:.1}%", surcharge_rate * 100.0));
-        }
-        
-        CalcTaxResponse {
-            tax,
-            explanation: explanation_parts.join(". "),
-            errors,
-            warnings,
-        }
+            Err(format!(
+                "{} rates for {} thresholds (expected {})",
+                rates.len(), thresholds.len(), thresholds.len() + 1
+            ))
+        };
+        migrate(&LEGACY_TAX_BRACKET_VARS, "ENGINE_TAX_BRACKETS", brackets);
+
+        let surcharge = Self::legacy_surcharge(var);
+        let surcharges = serde_json::json!([{ "name": surcharge.name, "threshold": surcharge.threshold, "rate": surcharge.rate }]);
+        migrate(
+            &LEGACY_SURCHARGE_VARS,
+            "ENGINE_SURCHARGES",
+            Self::parse_surcharges(&json_format::canonical(&surcharges)).map(|_| surcharges),
+        );
+
+        serde_json::to_string_pretty(&profile).unwrap_or_default() + "\n"
     }
 
-    /// Check if voting proposal passes
-    fn check_voting_internal(
-        eligible_voters: i32,
-        turnout: i32,
-        yes_votes: i32,
-        proposal_type: &str,
-    ) -> CheckVotingResponse {
-        let mut errors = Vec::new();
-        let mut warnings = Vec::new();
-        let mut explanation_parts = Vec::new();
-        
-        // Validation
-        if eligible_voters <= 0 {
-            errors.push("Eligible voters must be positive".to_string());
-        }
-        if turnout < 0 {
-            errors.push("Turnout cannot be negative".to_string());
-        }
-        if yes_votes < 0 {
-            errors.push("Yes votes cannot be negative".to_string());
-        }
-        if turnout > eligible_voters {
-            errors.push("Turnout cannot exceed eligible voters".to_string());
+    /// Parse a profile: a JSON object of settings by name, such as
+    /// `{"ENGINE_DEFAULT_CAP": {"value": "2000", "source": "Annex 2"}}`. A value that is not a
+    /// string is read as its JSON text.
+    fn parse_profile(json: &str) -> Result<BTreeMap<String, String>, String> {
+        let profile: BTreeMap<String, ProfileSetting> =
+            serde_json::from_str(json).map_err(|e| format!("invalid JSON: {}", e))?;
+        Self::profile_settings(&profile)
+    }
+
+    /// Settings of a profile as they would be read from the environment
+    fn profile_settings(profile: &BTreeMap<String, ProfileSetting>) -> Result<BTreeMap<String, String>, String> {
+        profile
+            .iter()
+            .map(|(name, setting)| {
+                if !CONFIG_VARS.contains(&name.as_str()) {
+                    return Err(format!("unknown setting '{}'", sanitize_for_error_message(name)));
+                }
+                let value = match &setting.value {
+                    serde_json::Value::String(value) => value.clone(),
+                    value => json_format::canonical(value),
+                };
+                Ok((name.clone(), value))
+            })
+            .collect()
+    }
+
+    /// Save `profile` to `path` in the format `--migrate-config` prints, replacing the file in one step
+    fn save_profile(path: &str, profile: &BTreeMap<String, ProfileSetting>) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(profile).map_err(|e| e.to_string())? + "\n";
+        let temp = format!("{}.tmp", path);
+        std::fs::write(&temp, json)
+            .and_then(|_| std::fs::rename(&temp, path))
+            .map_err(|e| format!("cannot write '{}': {}", path, e))
+    }
+
+    /// Parse and validate a JSON surcharge list such as
+    /// `[{"name": "Surcharge", "threshold": 5000, "rate": 0.02, "base": "tax"}]`. An empty list disables surcharges.
+    fn parse_surcharges(json: &str) -> Result<Vec<SurchargeRule>, String> {
+        let surcharges: Vec<SurchargeRule> =
+            serde_json::from_str(json).map_err(|e| format!("invalid JSON: {}", e))?;
+        for (i, rule) in surcharges.iter().enumerate() {
+            if rule.name.trim().is_empty() {
+                return Err(format!("surcharge {} must have a name", i + 1));
+            }
+            if !rule.threshold.is_finite() || rule.threshold < 0.0 {
+                return Err(format!("surcharge '{}' threshold must not be negative", rule.name));
+            }
+            if !(0.0..=1.0).contains(&rule.rate) {
+                return Err(format!("surcharge '{}' rate must be a decimal between 0 and 1", rule.name));
+            }
         }
-        if yes_votes > turnout {
-            errors.push("Yes votes cannot exceed turnout".to_string());
+        Ok(surcharges)
+    }
+
+    /// Mid-year schedule change from `ENGINE_TAX_CHANGE_DATE` and `ENGINE_TAX_BRACKETS_AFTER_CHANGE`.
+    /// Both must be set; otherwise the default schedule applies to the whole year.
+    fn tax_schedule_change_from_vars(var: &dyn Fn(&str) -> Option<String>, index: &PriceIndex) -> Option<ScheduleChange> {
+        match (var("ENGINE_TAX_CHANGE_DATE"), var("ENGINE_TAX_BRACKETS_AFTER_CHANGE")) {
+            (Some(date), Some(json)) => match Self::parse_schedule_change(&date, &json, index) {
+                Ok(change) => Some(change),
+                Err(e) => {
+                    tracing::error!("Invalid mid-year tax schedule change: {}; using a single schedule", e);
+                    None
+                }
+            },
+            (None, None) => None,
+            _ => {
+                tracing::error!(
+                    "ENGINE_TAX_CHANGE_DATE and ENGINE_TAX_BRACKETS_AFTER_CHANGE must be set together; using a single schedule"
+                );
+                None
+            }
         }
-        if !matches!(proposal_type, "general" | "amendment") {
-            errors.push(format!("Invalid proposal type '{}' (must be 'general' or 'amendment')", proposal_type));
+    }
+
+    /// Parse the effective date (YYYY-MM-DD) and bracket list of a mid-year schedule change
+    fn parse_schedule_change(date: &str, brackets_json: &str, index: &PriceIndex) -> Result<ScheduleChange, String> {
+        let effective_date = NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+            .map_err(|_| format!("effective date '{}' must be YYYY-MM-DD", date.trim()))?;
+        if effective_date.ordinal() == 1 {
+            return Err("effective date must fall after January 1 of the tax year".to_string());
         }
-        
-        if !errors.is_empty() {
-            return CheckVotingResponse {
-                passes: false,
-                explanation: "Voting check failed due to invalid inputs".to_string(),
-                errors,
-                warnings,
-            };
+        let (thresholds, rates, cliffs) = Self::parse_tax_brackets(brackets_json, index)?;
+        Ok(ScheduleChange { effective_date, thresholds, rates, cliffs })
+    }
+
+    /// Alternative minimum tax from `ENGINE_AMT_RATE` and `ENGINE_AMT_EXEMPTION` (default 0).
+    /// Disabled unless a rate is set.
+    fn minimum_tax_from_vars(var: &dyn Fn(&str) -> Option<String>) -> Option<MinimumTaxConfig> {
+        let rate = var("ENGINE_AMT_RATE")?;
+        let exemption = var("ENGINE_AMT_EXEMPTION");
+        match Self::parse_minimum_tax(&rate, exemption.as_deref()) {
+            Ok(config) => Some(config),
+            Err(e) => {
+                tracing::error!("Invalid alternative minimum tax settings: {}; AMT disabled", e);
+                None
+            }
         }
-        
-        // Check minimum turnout (60%)
-        let turnout_percentage = turnout as f64 / eligible_voters as f64;
-        explanation_parts.push(format!(
-            "Turnout: {} out of {} eligible voters ({:.1}%)", 
-            turnout, eligible_voters, turnout_percentage * 100.0
-        ));
-        
-        if turnout_percentage < 0.60 {
-            explanation_parts.push("Turnout requirement: ≥60% - FAILED".to_string());
-            explanation_parts.push("Proposal fails due to insufficient turnout".to_string());
-            
-            return CheckVotingResponse {
-                passes: false,
-                explanation: explanation_parts.join(". "),
-                errors,
-                warnings,
-            };
-        } else {
-            explanation_parts.push("Turnout requirement: ≥60% - PASSED".to_string());
+    }
+
+    fn parse_minimum_tax(rate: &str, exemption: Option<&str>) -> Result<MinimumTaxConfig, String> {
+        let rate: f64 = rate.trim().parse().map_err(|_| format!("ENGINE_AMT_RATE '{}' is not a number", rate.trim()))?;
+        if !(0.0..=1.0).contains(&rate) {
+            return Err("ENGINE_AMT_RATE must be a decimal between 0 and 1".to_string());
         }
-        
-        // Check voting threshold based on proposal type
-        let yes_percentage = yes_votes as f64 / turnout as f64;
-        explanation_parts.push(format!(
-            "Yes votes: {} out of {} ({:.1}%)", 
-            yes_votes, turnout, yes_percentage * 100.0
-        ));
-        
-        let passes = match proposal_type {
-            "general" => {
-                let required = 50.0;
-                explanation_parts.push(format!("General proposal requirement: >{}%", required));
-                let passes = yes_percentage > 0.50;
-                explanation_parts.push(format!(
-                    "Vote threshold: {:.1}% > {}% - {}", 
-                    yes_percentage * 100.0, required, if passes { "PASSED" } else { "FAILED" }
-                ));
-                passes
-            },
-            "amendment" => {
-                let required = 66.7;
-                explanation_parts.push(format!("Amendment requirement: ≥{:.1}%", required));
-                let passes = yes_percentage >= 2.0 / 3.0;
-                explanation_parts.push(format!(
-                    "Vote threshold: {:.1}% ≥ {:.1}% - {}", 
-                    yes_percentage * 100.0, required, if passes { "PASSED" } else { "FAILED" }
-                ));
-                passes
-            },
-            _ => false,
+        let exemption = match exemption {
+            Some(value) => value
+                .trim()
+                .parse::<f64>()
+                .map_err(|_| format!("ENGINE_AMT_EXEMPTION '{}' is not a number", value.trim()))?,
+            None => 0.0,
         };
-        
-        explanation_parts.push(format!("Final result: Proposal {}", if passes { "PASSES" } else { "FAILS" }));
-        
-        if turnout_percentage < 0.70 {
-            warnings.push("Low turnout (below 70%)".to_string());
-        }
-        if turnout > 0 && yes_votes == 0 {
-            warnings.push("No yes votes recorded".to_string());
-        }
-        
-        CheckVotingResponse {
-            passes,
-            explanation: explanation_parts.join(". "),
-            errors,
-            warnings,
+        if !exemption.is_finite() || exemption < 0.0 {
+            return Err("ENGINE_AMT_EXEMPTION must not be negative".to_string());
         }
+        Ok(MinimumTaxConfig { rate, exemption })
     }
 
-    /// Distribute cash in waterfall structure
-    fn distribute_waterfall_internal(
-        cash_available: f64,
-        senior_debt: f64,
-        junior_debt: f64,
-    ) -> DistributeWaterfallResponse {
-        let mut errors = Vec::new();
-        let mut warnings = Vec::new();
-        let mut explanation_parts = Vec::new();
-        
-        // Validation
-        if cash_available < 0.0 {
-            errors.push("Cash available cannot be negative".to_string());
-        }
-        if senior_debt < 0.0 {
-            errors.push("Senior debt cannot be negative".to_string());
+    /// Tax rules currently in force for calc_tax
+    fn tax_rules(&self) -> TaxRules {
+        TaxRules {
+            brackets: Arc::new(BracketTable::new(
+                self.default_thresholds.clone(),
+                self.default_rates.clone(),
+                self.default_cliffs.clone(),
+            )),
+            schedule_change: self.tax_schedule_change.as_ref().map(|change| {
+                let brackets = BracketTable::new(change.thresholds.clone(), change.rates.clone(), change.cliffs.clone());
+                (change.effective_date, Arc::new(brackets))
+            }),
+            minimum_tax: self.minimum_tax.clone(),
+            surcharges: self.surcharges.clone(),
+            surcharge_base: self.surcharge_base,
+        }
+    }
+
+    fn parse_vec_f64(s: &str) -> Option<Vec<f64>> {
+        let parsed: Result<Vec<f64>, _> = s
+            .split(',')
+            .map(|part| part.trim().parse::<f64>())
+            .collect();
+        parsed.ok()
+    }
+}
+
+/// Price index table used by index_amount and to inflation-adjust indexed thresholds
+#[derive(Debug, Clone, PartialEq)]
+pub struct PriceIndex {
+    /// Index value per year
+    pub values: BTreeMap<i32, f64>,
+    /// Year in which configured indexed thresholds are expressed
+    pub base_year: i32,
+    /// Year indexed thresholds are adjusted to
+    pub target_year: i32,
+}
+
+impl PriceIndex {
+    /// Price index from `ENGINE_PRICE_INDEX` (inline JSON) or `ENGINE_PRICE_INDEX_FILE` (path to
+    /// JSON), with `ENGINE_INDEX_BASE_YEAR` (default 2020) and `ENGINE_INDEX_TARGET_YEAR` (default
+    /// the latest year in the table), falling back to the built-in table.
+    fn from_vars(var: &dyn Fn(&str) -> Option<String>) -> Self {
+        let base_year = var("ENGINE_INDEX_BASE_YEAR");
+        let target_year = var("ENGINE_INDEX_TARGET_YEAR");
+        load_json_config(
+            var,
+            "ENGINE_PRICE_INDEX",
+            "ENGINE_PRICE_INDEX_FILE",
+            |json| Self::parse(json, base_year.as_deref(), target_year.as_deref()),
+            "using built-in price index",
+            || Self::builtin(base_year.as_deref(), target_year.as_deref()),
+        )
+    }
+
+    /// The built-in price index, indexed between the configured years
+    fn builtin(base_year: Option<&str>, target_year: Option<&str>) -> Self {
+        // Illustrative annual consumer price index, 2020 = 100
+        let values = BTreeMap::from([
+            (2015, 91.58), (2016, 92.73), (2017, 94.71), (2018, 97.02), (2019, 98.78),
+            (2020, 100.0), (2021, 104.70), (2022, 113.08), (2023, 117.73), (2024, 121.20),
+        ]);
+        Self::with_years(values.clone(), base_year, target_year).unwrap_or_else(|e| {
+            tracing::error!("Invalid price index years: {}; indexing from 2020 to 2024", e);
+            Self { values, base_year: 2020, target_year: 2024 }
+        })
+    }
+
+    /// Parse and validate a JSON object of index values by year such as `{"2020": 100, "2024": 121.2}`
+    fn parse(json: &str, base_year: Option<&str>, target_year: Option<&str>) -> Result<Self, String> {
+        let values: BTreeMap<i32, f64> =
+            serde_json::from_str(json).map_err(|e| format!("invalid JSON: {}", e))?;
+        if values.is_empty() {
+            return Err("at least one year is required".to_string());
+        }
+        if let Some((year, _)) = values.iter().find(|(_, value)| !value.is_finite() || **value <= 0.0) {
+            return Err(format!("index value for {} must be a positive number", year));
+        }
+        Self::with_years(values, base_year, target_year)
+    }
+
+    fn with_years(values: BTreeMap<i32, f64>, base_year: Option<&str>, target_year: Option<&str>) -> Result<Self, String> {
+        let year = |raw: Option<&str>, name: &str, default: i32| match raw {
+            Some(raw) => raw.trim().parse::<i32>().map_err(|_| format!("{} '{}' is not a year", name, raw.trim())),
+            None => Ok(default),
+        };
+        let latest = values.keys().next_back().copied().unwrap_or(2020);
+        let index = Self {
+            base_year: year(base_year, "ENGINE_INDEX_BASE_YEAR", 2020)?,
+            target_year: year(target_year, "ENGINE_INDEX_TARGET_YEAR", latest)?,
+            values,
+        };
+        index.value(index.base_year)?;
+        index.value(index.target_year)?;
+        Ok(index)
+    }
+
+    /// Index value for a year
+    pub fn value(&self, year: i32) -> Result<f64, String> {
+        self.values.get(&year).copied().ok_or_else(|| {
+            let years: Vec<String> = self.values.keys().map(|year| year.to_string()).collect();
+            format!("no price index value for {} (available: {})", year, years.join(", "))
+        })
+    }
+
+    /// Adjust a base-year amount to the target year
+    fn index_to_target(&self, amount: f64) -> Result<f64, String> {
+        Ok(amount * self.value(self.target_year)? / self.value(self.base_year)?)
+    }
+}
+
+/// Configuration and price index in force, with their version. Replaced as a whole by upsert_profile.
+struct LoadedRules {
+    config: Arc<EngineConfig>,
+    price_index: Arc<PriceIndex>,
+    /// calc_tax rules with their bracket tables built once for the configuration
+    tax_rules: Arc<TaxRules>,
+    version: String,
+}
+
+impl LoadedRules {
+    /// Price index and configuration from the settings read through `var`
+    fn from_vars(var: &dyn Fn(&str) -> Option<String>) -> Self {
+        let price_index = PriceIndex::from_vars(var);
+        let config = EngineConfig::from_vars(var, &price_index);
+        Self::new(config, price_index)
+    }
+
+    fn new(config: EngineConfig, price_index: PriceIndex) -> Self {
+        // Fingerprint of the effective configuration (rates, brackets, schedules, price index), so the
+        // input hash changes when the same inputs would be computed differently
+        let digest = Sha256::digest(format!("{:?}{:?}", config, price_index));
+        Self {
+            tax_rules: Arc::new(config.tax_rules()),
+            config: Arc::new(config),
+            price_index: Arc::new(price_index),
+            version: format!("{:x}", digest)[..16].to_string(),
+        }
+    }
+}
+
+static RULES: LazyLock<RwLock<LoadedRules>> =
+    LazyLock::new(|| RwLock::new(LoadedRules::from_vars(&config_var)));
+
+fn loaded_rules() -> RwLockReadGuard<'static, LoadedRules> {
+    RULES.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// The configuration in force
+fn config() -> Arc<EngineConfig> {
+    loaded_rules().config.clone()
+}
+
+/// The price index in force
+fn price_index() -> Arc<PriceIndex> {
+    loaded_rules().price_index.clone()
+}
+
+/// calc_tax rules of the configuration in force
+fn tax_rules() -> Arc<TaxRules> {
+    loaded_rules().tax_rules.clone()
+}
+
+/// Version of the configuration in force
+fn config_version() -> String {
+    loaded_rules().version.clone()
+}
+
+/// Days per year when penalty interest accrues to a valuation date (actual/365)
+const INTEREST_DAY_BASIS: f64 = 365.0;
+
+/// Extreme inputs of `stress_config`: the highest income, the brackets of the wide table and
+/// the most days late, with the time above which a check is reported as slow
+const STRESS_MAX_INCOME: f64 = 1e12;
+const STRESS_BRACKETS: usize = 100;
+const STRESS_DAYS_LATE: i64 = 10_000;
+const STRESS_SLOW_MS: f64 = 1000.0;
+/// Failing cases listed per stress check
+const MAX_STRESS_FAILURES: usize = 5;
+
+/// Maximum number of precincts accepted in a bulk voting check
+const MAX_PRECINCTS: usize = 1000;
+
+/// Maximum number of periods accepted in a cash flow series
+const MAX_CASH_FLOWS: usize = 1000;
+/// IRR bisection search bounds, tolerance on NPV and iteration limit
+const IRR_LOWER_BOUND: f64 = -0.99;
+const IRR_UPPER_BOUND: f64 = 10.0;
+const IRR_TOLERANCE: f64 = 1e-7;
+const IRR_MAX_ITERATIONS: u32 = 200;
+
+// =================== PARSING UTILITIES ===================
+
+/// Sanitize user input for safe inclusion in error messages
+/// Prevents JSON injection, XSS, log injection, and other attacks
+fn sanitize_for_error_message(input: &str) -> String {
+    // Limit length to prevent DoS and overly verbose errors
+    let truncated = if input.len() > 50 { 
+        format!("{}...", &input[..47])
+    } else { 
+        input.to_string() 
+    };
+    
+    // Replace dangerous and non-printable characters
+    truncated
+        .chars()
+        .map(|c| match c {
+            // Replace line breaks and control chars that could break JSON/logs
+            '\n' | '\r' | '\t' => ' ',
+            // Replace quote chars that could break JSON structure  
+            '"' | '\'' | '`' => '?',
+            // Replace backslashes that could escape JSON
+            '\\' => '?',
+            // Replace HTML/script chars for XSS prevention
+            '<' | '>' => '?',
+            // Keep normal printable ASCII and space
+            c if c.is_ascii_graphic() || c == ' ' => c,
+            // Replace any other non-printable or unicode control chars
+            _ => '?'
+        })
+        .collect()
+}
+
+/// Validate input length and format for security
+fn validate_input_security(input: &str, field_name: &str) -> Result<(), String> {
+    // Check maximum length to prevent DoS
+    if input.len() > 100 {
+        return Err(format!("Invalid {}: input too long (max 100 characters)", field_name));
+    }
+    
+    // Check for null bytes (can cause issues in some contexts)
+    if input.contains('\0') {
+        return Err(format!("Invalid {}: input contains null bytes", field_name));
+    }
+    
+    // Check for excessive control characters (potential log injection)
+    let control_char_count = input.chars().filter(|c| c.is_control()).count();
+    if control_char_count > 2 {  // Allow a couple for legitimate formatting
+        return Err(format!("Invalid {}: input contains too many control characters", field_name));
+    }
+    
+    Ok(())
+}
+
+/// Parse a string to f64, handling various formats with security validation
+fn parse_f64_from_string(s: &str) -> Result<f64, String> {
+    parse_f64_with_strictness(s, config().strict_parsing)
+}
+
+/// Remove digit-group underscores ("1_000_000"); None if an underscore is not between two digits
+fn strip_digit_underscores(s: &str) -> Option<String> {
+    let chars: Vec<char> = s.chars().collect();
+    let misplaced = chars.iter().enumerate().any(|(i, c)| {
+        *c == '_'
+            && !(i > 0
+                && chars[i - 1].is_ascii_digit()
+                && chars.get(i + 1).is_some_and(|next| next.is_ascii_digit()))
+    });
+    (!misplaced).then(|| s.replace('_', ""))
+}
+
+/// Split a magnitude shorthand suffix ("600k", "8.5m", "2B") into the number and its multiplier
+fn magnitude_suffix(s: &str) -> Option<(&str, f64)> {
+    let multiplier = match s.chars().last()? {
+        'k' | 'K' => 1e3,
+        'm' | 'M' => 1e6,
+        'b' | 'B' => 1e9,
+        _ => return None,
+    };
+    let number = &s[..s.len() - 1];
+    number.ends_with(|c: char| c.is_ascii_digit() || c == '.').then_some((number, multiplier))
+}
+
+/// Parse a string to f64; strict parsing rejects magnitude suffixes
+fn parse_f64_with_strictness(s: &str, strict: bool) -> Result<f64, String> {
+    let trimmed = s.trim();
+    
+    // Security validation first
+    if let Err(e) = validate_input_security(trimmed, "number") {
+        return Err(e);
+    }
+    
+    // Handle empty strings
+    if trimmed.is_empty() {
+        return Err("Empty string cannot be parsed as number".to_string());
+    }
+    
+    // Sanitize input for error messages
+    let sanitized = sanitize_for_error_message(trimmed);
+    
+    // Remove common formatting characters
+    let cleaned = trimmed
+        .replace(',', "")  // Remove thousands separators
+        .replace('$', "")  // Remove dollar, euro, pound, etc. signs
+        .replace('€', "")  // Remove euro signs
+        .replace('£', "")  // Remove pound signs
+        .replace('¥', "")  // Remove yen signs
+        .replace('%', ""); // Remove percentage signs
+    let Some(cleaned) = strip_digit_underscores(&cleaned) else {
+        return Err(format!("Cannot parse '{}' as a number (underscores must separate digits)", sanitized));
+    };
+
+    // Expand magnitude shorthand such as "15M" unless strict parsing is enabled
+    let (number, multiplier) = match magnitude_suffix(&cleaned) {
+        Some(_) if strict => {
+            return Err(format!(
+                "Cannot parse '{}' as a number (magnitude suffixes are disabled by strict parsing)", sanitized
+            ));
+        }
+        Some(split) => split,
+        None => (cleaned.as_str(), 1.0),
+    };
+    
+    match number.parse::<f64>() {
+        Ok(value) => {
+            let value = value * multiplier;
+            if value.is_infinite() || value.is_nan() {
+                Err(format!("Invalid number: '{}'", sanitized))
+            } else {
+                Ok(value)
+            }
+        },
+        Err(_) => Err(format!("Cannot parse '{}' as a number", sanitized))
+    }
+}
+
+/// Parse a string to i32, handling various formats with security validation
+fn parse_i32_from_string(s: &str) -> Result<i32, String> {
+    let trimmed = s.trim();
+    
+    // Security validation first
+    if let Err(e) = validate_input_security(trimmed, "integer") {
+        return Err(e);
+    }
+    
+    // Handle empty strings
+    if trimmed.is_empty() {
+        return Err("Empty string cannot be parsed as integer".to_string());
+    }
+    
+    // Sanitize input for error messages
+    let sanitized = sanitize_for_error_message(trimmed);
+    
+    // Remove common formatting characters
+    let cleaned = trimmed.replace(',', ""); // Remove thousands separators
+    let Some(cleaned) = strip_digit_underscores(&cleaned) else {
+        return Err(format!("Cannot parse '{}' as an integer (underscores must separate digits)", sanitized));
+    };
+    
+    match cleaned.parse::<i32>() {
+        Ok(value) => Ok(value),
+        // Scientific notation ("1e3") is accepted when it denotes a whole number in range
+        Err(_) if cleaned.contains(['e', 'E']) => match cleaned.parse::<f64>() {
+            Ok(value) if value.fract() == 0.0 && value >= i32::MIN as f64 && value <= i32::MAX as f64 => Ok(value as i32),
+            Ok(_) => Err(format!("'{}' is not a whole number in the integer range", sanitized)),
+            Err(_) => Err(format!("Cannot parse '{}' as an integer", sanitized)),
+        },
+        Err(_) => Err(format!("Cannot parse '{}' as an integer", sanitized))
+    }
+}
+
+/// Parse a string to bool, handling various formats with security validation
+fn parse_bool_from_string(s: &str) -> Result<bool, String> {
+    let trimmed = s.trim();
+    
+    // Security validation first
+    if let Err(e) = validate_input_security(trimmed, "boolean") {
+        return Err(e);
+    }
+    
+    // Handle empty strings
+    if trimmed.is_empty() {
+        return Err("Empty string cannot be parsed as boolean".to_string());
+    }
+    
+    // Sanitize input for error messages
+    let sanitized = sanitize_for_error_message(trimmed);
+    
+    // Parse various boolean representations (case-insensitive)
+    match trimmed.to_lowercase().as_str() {
+        "true" | "t" | "yes" | "y" | "1" | "on" => Ok(true),
+        "false" | "f" | "no" | "n" | "0" | "off" => Ok(false),
+        _ => Err(format!("Cannot parse '{}' as a boolean (expected: true/false, yes/no, 1/0, etc.)", sanitized))
+    }
+}
+
+/// Normalize a situation flag: lower case, with spaces and hyphens as underscores
+fn situation_flag(flag: &str) -> String {
+    flag.trim().to_ascii_lowercase().replace([' ', '-'], "_")
+}
+
+/// Parse an ISO 8601 calendar date (YYYY-MM-DD) with security validation
+fn parse_date_from_string(s: &str) -> Result<NaiveDate, String> {
+    let trimmed = s.trim();
+
+    // Security validation first
+    validate_input_security(trimmed, "date")?;
+
+    // Handle empty strings
+    if trimmed.is_empty() {
+        return Err("Empty string cannot be parsed as date".to_string());
+    }
+
+    // Sanitize input for error messages
+    let sanitized = sanitize_for_error_message(trimmed);
+
+    NaiveDate::parse_from_str(trimmed, "%Y-%m-%d")
+        .map_err(|_| format!("Cannot parse '{}' as a date (expected YYYY-MM-DD)", sanitized))
+}
+
+/// Unit a numeric parameter is expressed in, checked against unit annotations like "12 days"
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FieldUnit {
+    /// Money per year in the base currency
+    AnnualMoney,
+    /// A one-off amount of money in the base currency
+    Money,
+    Days,
+    /// Number of people or votes
+    Count,
+}
+
+impl FieldUnit {
+    /// Expected unit of a parameter, ignoring list indexes and parent fields
+    /// (`beneficiaries[0].amount` is an amount)
+    fn of(field: &str) -> Option<FieldUnit> {
+        let name = field.rsplit('.').next().unwrap_or(field);
+        let name = name.split('[').next().unwrap_or(name);
+        Some(match name {
+            "income" | "gross_salary" | "ami" | "ebitda" | "interest_expense" => FieldUnit::AnnualMoney,
+            "acquisition_cost" | "already_released" | "amount" | "amt_adjustments" | "annual_exemption"
+            | "assessed_value" | "cap" | "cash_available" | "cash_flows" | "escrow_amount" | "junior_debt"
+            | "senior_debt" | "total_debt" | "loss_carryforward" | "price" | "prior_gifts"
+            | "prior_year_value" | "sale_price" | "value" => FieldUnit::Money,
+            "days_late" | "holding_period_days" => FieldUnit::Days,
+            "eligible_voters" | "turnout" | "yes_votes" | "no_votes" | "abstain_votes" | "invalid_votes"
+            | "household_size" => FieldUnit::Count,
+            _ => return None,
+        })
+    }
+}
+
+/// Time period named in a unit annotation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Period {
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+impl Period {
+    fn parse(word: &str) -> Option<Period> {
+        Some(match word {
+            "day" | "days" | "daily" | "d" => Period::Day,
+            "week" | "weeks" | "weekly" | "wk" | "wks" => Period::Week,
+            "month" | "months" | "monthly" | "mo" | "mos" => Period::Month,
+            "year" | "years" | "yearly" | "annual" | "annually" | "yr" | "yrs" | "pa" | "p.a." => Period::Year,
+            _ => return None,
+        })
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Period::Day => "day",
+            Period::Week => "week",
+            Period::Month => "month",
+            Period::Year => "year",
+        }
+    }
+}
+
+/// Split a trailing unit annotation ("32000 EUR/year", "12 days") from the number it follows
+fn split_unit_annotation(raw: &str) -> (&str, Option<&str>) {
+    let trimmed = raw.trim();
+    match trimmed.find(|c: char| c.is_whitespace() || c == '/') {
+        Some(i) if trimmed[i..].chars().any(char::is_alphabetic) => (trimmed[..i].trim(), Some(trimmed[i..].trim())),
+        _ => (trimmed, None),
+    }
+}
+
+/// Check a unit annotation against the field's expected unit.
+///
+/// Returns the factor converting the annotated value into the field's unit and, when it is not 1,
+/// a description of the conversion. Annotations that cannot be converted are rejected.
+fn unit_conversion(field: &str, unit: &str) -> Result<(f64, Option<String>), String> {
+    let shown = sanitize_for_error_message(unit);
+    let Some(expected) = FieldUnit::of(field) else {
+        return Err(format!("{} does not take a unit (got '{}')", field, shown));
+    };
+    let mut currency = None;
+    let mut period = None;
+    let mut count = false;
+    for word in unit.split(|c: char| c.is_whitespace() || c == '/').filter(|word| !word.is_empty()) {
+        let lower = word.to_ascii_lowercase();
+        if lower == "per" || lower == "a" {
+            continue;
+        }
+        if let Some(parsed) = Period::parse(&lower) {
+            period = Some(parsed);
+        } else if matches!(lower.as_str(), "people" | "persons" | "person" | "members" | "votes" | "vote" | "voters" | "voter" | "ballots") {
+            count = true;
+        } else if word.len() == 3 && word.chars().all(|c| c.is_ascii_alphabetic()) {
+            currency = Some(word.to_ascii_uppercase());
+        } else {
+            return Err(format!("Unknown unit '{}'", shown));
+        }
+    }
+
+    let base_currency = &config().fx.base_currency;
+    if let Some(currency) = &currency {
+        if !matches!(expected, FieldUnit::Money | FieldUnit::AnnualMoney) {
+            return Err(format!("{} is not an amount of money (got '{}')", field, shown));
+        }
+        if currency != base_currency {
+            return Err(format!(
+                "{} must be in {}, not {}; convert it first (see convert_currency)",
+                field, base_currency, currency
+            ));
+        }
+    }
+    if count && expected != FieldUnit::Count {
+        return Err(format!("{} is not a count of people or votes (got '{}')", field, shown));
+    }
+
+    match (expected, period) {
+        (FieldUnit::Days, None) => Err(format!("{} is a number of days (got '{}')", field, shown)),
+        (_, None) => Ok((1.0, None)),
+        (FieldUnit::AnnualMoney, Some(Period::Year)) | (FieldUnit::Days, Some(Period::Day)) => Ok((1.0, None)),
+        (FieldUnit::AnnualMoney, Some(Period::Month)) => Ok((12.0, Some("monthly amount × 12".to_string()))),
+        (FieldUnit::AnnualMoney, Some(Period::Week)) => Ok((52.0, Some("weekly amount × 52".to_string()))),
+        (FieldUnit::Days, Some(Period::Week)) => Ok((7.0, Some("weeks × 7".to_string()))),
+        (FieldUnit::Days, _) => Err(format!("{} is a number of days (got '{}')", field, shown)),
+        (FieldUnit::AnnualMoney, Some(period)) => Err(format!(
+            "{} is a yearly amount; a per-{} amount cannot be converted reliably", field, period.name()
+        )),
+        (_, Some(period)) => Err(format!("{} is not a per-{} amount (got '{}')", field, period.name(), shown)),
+    }
+}
+
+// =================== CUSTOM DESERIALIZERS ===================
+
+/// Custom deserializer that accepts both f64 numbers and strings, then parses them
+fn deserialize_flexible_f64<'de, D>(deserializer: D) -> Result<FlexibleF64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct FlexibleF64Visitor;
+
+    impl<'de> de::Visitor<'de> for FlexibleF64Visitor {
+        type Value = FlexibleF64;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a number or a string representing a number")
+        }
+
+        fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            Ok(FlexibleF64(Ok(value as f64), Reading::default()))
+        }
+
+        fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            Ok(FlexibleF64(Ok(value as f64), Reading::default()))
+        }
+
+        fn visit_f64<E>(self, value: f64) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            Ok(FlexibleF64(Ok(value), Reading::default()))
+        }
+
+        fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            Ok(FlexibleF64::from(value))
+        }
+    }
+
+    deserializer.deserialize_any(FlexibleF64Visitor)
+}
+
+/// Custom deserializer that accepts both i32 numbers and strings, then parses them
+fn deserialize_flexible_i32<'de, D>(deserializer: D) -> Result<FlexibleI32, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct FlexibleI32Visitor;
+
+    impl<'de> de::Visitor<'de> for FlexibleI32Visitor {
+        type Value = FlexibleI32;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("an integer or a string representing an integer")
+        }
+
+        fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            let text = value.to_string();
+            match i32::try_from(value) {
+                Ok(value) => Ok(FlexibleI32(Ok(value), Reading::default())),
+                Err(_) => Ok(FlexibleI32(
+                    Err(format!("Cannot parse '{}' as an integer", text)),
+                    Reading { text: Some(text), ..Reading::default() },
+                )),
+            }
+        }
+
+        fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            self.visit_i64(i64::try_from(value).unwrap_or(i64::MAX))
+        }
+
+        fn visit_f64<E>(self, value: f64) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            // Convert float to int if it's a whole number
+            if value.fract() == 0.0 {
+                self.visit_i64(value as i64)
+            } else {
+                Err(E::custom(format!("Expected integer, got float: {}", value)))
+            }
+        }
+
+        fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            Ok(FlexibleI32::from(value))
+        }
+    }
+
+    deserializer.deserialize_any(FlexibleI32Visitor)
+}
+
+/// Custom deserializer that accepts both booleans and strings, then parses them
+fn deserialize_flexible_bool<'de, D>(deserializer: D) -> Result<FlexibleBool, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct FlexibleBoolVisitor;
+
+    impl<'de> de::Visitor<'de> for FlexibleBoolVisitor {
+        type Value = FlexibleBool;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a boolean or a string representing a boolean")
+        }
+
+        fn visit_bool<E>(self, value: bool) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            Ok(FlexibleBool(Ok(value), Reading::default()))
+        }
+
+        fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            Ok(FlexibleBool::from(value))
+        }
+    }
+
+    deserializer.deserialize_any(FlexibleBoolVisitor)
+}
+
+/// Custom deserializer for number lists: accepts a JSON array of numbers/strings, or a string
+/// containing a JSON array (some clients stringify arrays)
+fn deserialize_flexible_f64_list<'de, D>(deserializer: D) -> Result<Vec<FlexibleF64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum FlexibleList {
+        List(Vec<FlexibleF64>),
+        Text(String),
+    }
+
+    match FlexibleList::deserialize(deserializer)? {
+        FlexibleList::List(values) => Ok(values),
+        FlexibleList::Text(text) => serde_json::from_str::<Vec<FlexibleF64>>(&text)
+            .map_err(|_| de::Error::custom("expected an array of numbers or a string containing a JSON array")),
+    }
+}
+
+// =================== FLEXIBLE PARAMETER TYPES ===================
+//
+// Numeric and boolean parameters arrive as JSON numbers/booleans or as text ("45,000", "yes").
+// The wrappers accept exactly those JSON types and advertise them in the tool schemas. They parse
+// the value while the arguments are deserialized and keep the outcome: an unreadable value is
+// reported by the tool that reads it, under the field name, like any other invalid parameter.
+// Each also keeps a `Reading` of how lenient parsing read its text, which `AssumptionLog` records
+// as assumptions under the field name, converting any unit annotation to the field's unit.
+
+/// How a flexible parameter was read from text
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Reading {
+    /// The text the value was read from; None for a native JSON value
+    text: Option<String>,
+    /// Assumption code and message for each lenient reading ("45,000" read as 45000)
+    notes: Vec<(&'static str, String)>,
+    /// Unit annotation split off the text, checked against the field's unit when the value is read
+    unit: Option<UnitAnnotation>,
+}
+
+/// A trailing unit annotation ("3,000 per month") and where its conversion note belongs
+#[derive(Debug, Clone, PartialEq)]
+struct UnitAnnotation {
+    /// The annotated text and the number before the unit
+    raw: String,
+    number: String,
+    unit: String,
+    /// Number of notes recorded before the unit was split off
+    position: usize,
+}
+
+impl Reading {
+    fn from_text(text: &str) -> Self {
+        Self { text: Some(text.to_string()), ..Self::default() }
+    }
+
+    fn note(&mut self, code: &'static str, message: String) {
+        self.notes.push((code, message));
+    }
+
+    /// Replace a `$result:<id>.<field>` reference with the referenced value, recording the substitution
+    fn resolve_reference<'a>(&mut self, raw: &'a str) -> Result<Cow<'a, str>, String> {
+        match result_store::resolve(raw) {
+            None => Ok(Cow::Borrowed(raw)),
+            Some(resolved) => {
+                let value = resolved?;
+                self.note("result_reference", format!(
+                    "'{}' resolved to {}", sanitize_for_error_message(raw.trim()), value
+                ));
+                Ok(Cow::Owned(value))
+            }
+        }
+    }
+
+    /// Split off a unit annotation, keeping it to be checked against the field; returns the number text
+    fn split_unit<'a>(&mut self, raw: &'a str) -> &'a str {
+        // Anything that is not "<number> <unit>" is left for the number parser to report
+        match split_unit_annotation(raw) {
+            (number, Some(unit)) if parse_f64_from_string(number).is_ok() => {
+                self.unit = Some(UnitAnnotation {
+                    raw: raw.to_string(),
+                    number: number.to_string(),
+                    unit: unit.to_string(),
+                    position: self.notes.len(),
+                });
+                number
+            }
+            _ => raw,
+        }
+    }
+
+    /// Parse number text, noting any formatting characters that were ignored
+    fn parse_f64(&mut self, text: &str) -> Result<f64, String> {
+        let raw = &*self.resolve_reference(text)?;
+        let raw = self.split_unit(raw);
+        let value = parse_f64_from_string(raw)?;
+        let shown = sanitize_for_error_message(raw.trim());
+        if raw.contains('%') {
+            self.note("percent_sign_stripped", format!(
+                "'{}' read as {} (percent sign ignored; rates are decimals, so {}% may have been meant as {})",
+                shown, value, value, value / 100.0
+            ));
+        }
+        if raw.contains(['$', '€', '£', '¥']) {
+            self.note("currency_symbol_stripped", format!(
+                "'{}' read as {} (currency symbol ignored)", shown, value
+            ));
+        }
+        if raw.contains(',') {
+            self.note_commas(raw, &shown, &value.to_string());
+        }
+        if magnitude_suffix(raw.trim()).is_some() {
+            self.note("magnitude_suffix_expanded", format!(
+                "'{}' read as {} (k = thousand, M = million, B = billion)", shown, value
+            ));
+        }
+        Ok(value)
+    }
+
+    /// Parse integer text, noting any thousands separators that were ignored
+    fn parse_i32(&mut self, text: &str) -> Result<i32, String> {
+        let raw = &*self.resolve_reference(text)?;
+        let raw = self.split_unit(raw);
+        let value = parse_i32_from_string(raw)?;
+        if raw.contains(',') {
+            let shown = sanitize_for_error_message(raw.trim());
+            self.note_commas(raw, &shown, &value.to_string());
+        }
+        Ok(value)
+    }
+
+    /// Parse boolean text, noting non-canonical spellings such as 'yes' or '1'
+    fn parse_bool(&mut self, text: &str) -> Result<bool, String> {
+        let raw = &*self.resolve_reference(text)?;
+        let value = parse_bool_from_string(raw)?;
+        let normalized = raw.trim().to_lowercase();
+        if normalized != "true" && normalized != "false" {
+            self.note("boolean_interpreted", format!(
+                "'{}' read as {}", sanitize_for_error_message(raw.trim()), value
+            ));
+        }
+        Ok(value)
+    }
+
+    fn note_commas(&mut self, raw: &str, shown: &str, value: &str) {
+        // Commas are always treated as thousands separators; flag groups that look like decimals
+        let ambiguous = raw
+            .trim()
+            .split(',')
+            .skip(1)
+            .any(|group| group.chars().take_while(|c| c.is_ascii_digit()).count() != 3);
+        let message = if ambiguous {
+            format!("'{}' read as {} (commas treated as thousands separators, not decimal separators)", shown, value)
+        } else {
+            format!("'{}' read as {} (thousands separators removed)", shown, value)
+        };
+        self.note("comma_removed", message);
+    }
+}
+
+macro_rules! flexible_param {
+    ($name:ident, $value:ty, $deserialize:ident, $parse:ident, $json_type:literal) => {
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                $deserialize(deserializer)
+            }
+        }
+
+        /// Serialized as it arrived: the text, or the native JSON value
+        impl Serialize for $name {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                match (&self.1.text, &self.0) {
+                    (Some(text), _) => serializer.serialize_str(text),
+                    (None, Ok(value)) => value.serialize(serializer),
+                    (None, Err(_)) => serializer.serialize_none(),
+                }
+            }
+        }
+
+        impl schemars::JsonSchema for $name {
+            fn inline_schema() -> bool {
+                true
+            }
+
+            fn schema_name() -> Cow<'static, str> {
+                stringify!($name).into()
+            }
+
+            fn json_schema(_generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
+                schemars::json_schema!({ "type": [$json_type, "string"] })
+            }
+        }
+
+        /// Parsed as if the text had arrived as a JSON string
+        impl From<&str> for $name {
+            fn from(text: &str) -> Self {
+                let mut reading = Reading::from_text(text);
+                let value = reading.$parse(text);
+                $name(value, reading)
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(text: String) -> Self {
+                Self::from(text.as_str())
+            }
+        }
+
+        impl From<$value> for $name {
+            fn from(value: $value) -> Self {
+                $name(Ok(value), Reading::default())
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self::from("")
+            }
+        }
+    };
+}
+
+/// Number parameter: a JSON number or numeric text
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlexibleF64(Result<f64, String>, Reading);
+
+/// Integer parameter: a JSON integer (or whole float) or integer text
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlexibleI32(Result<i32, String>, Reading);
+
+/// Boolean parameter: a JSON boolean or text such as "yes" or "false"
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlexibleBool(Result<bool, String>, Reading);
+
+flexible_param!(FlexibleF64, f64, deserialize_flexible_f64, parse_f64, "number");
+flexible_param!(FlexibleI32, i32, deserialize_flexible_i32, parse_i32, "integer");
+flexible_param!(FlexibleBool, bool, deserialize_flexible_bool, parse_bool, "boolean");
+
+// =================== DATA STRUCTURES ===================
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct CalcPenaltyParams {
+    #[schemars(description = "Number of days late")]
+    pub days_late: FlexibleF64,
+    /// Optional. If not provided, uses configured default (e.g. ENGINE_DEFAULT_RATE_PER_DAY).
+    #[serde(default)]
+    #[schemars(description = "Optional rate per day; uses default if omitted")]
+    pub rate_per_day: Option<FlexibleF64>,
+    /// Optional. If not provided, uses configured default (e.g. ENGINE_DEFAULT_CAP).
+    #[serde(default)]
+    #[schemars(description = "Optional cap; uses default if omitted")]
+    pub cap: Option<FlexibleF64>,
+    /// Optional. If not provided, uses configured default (e.g. ENGINE_DEFAULT_INTEREST_RATE).
+    #[serde(default)]
+    #[schemars(description = "Optional interest rate; uses default if omitted")]
+    pub interest_rate: Option<FlexibleF64>,
+    /// Optional. Defaults to days_late days before the valuation date.
+    #[serde(default)]
+    #[schemars(description = "Optional date payment was due (YYYY-MM-DD), from which interest accrues; defaults to days_late days before valuation_date")]
+    pub due_date: Option<String>,
+    /// Optional. Without it (and without due_date), interest is a one-off charge.
+    #[serde(default)]
+    #[schemars(description = "Optional date to value the liability at (YYYY-MM-DD). With it or due_date, interest_rate is an annual rate accrued from due_date to this date (actual/365); defaults to today when only due_date is given. Omit both for a one-off interest charge")]
+    pub valuation_date: Option<String>,
+    /// Optional. Defaults to the current response schema version.
+    #[serde(default)]
+    #[schemars(description = "Optional response schema version: 2 (default, versioned envelope) or 1 (legacy bare payload)")]
+    pub response_version: Option<FlexibleI32>,
+    /// Optional. Defaults to expert.
+    #[serde(default)]
+    #[schemars(description = "Optional explanation audience: 'expert' (default, technical step-by-step) or 'citizen' (plain-language narrative for the public)")]
+    pub audience: Option<String>,
+    /// Optional. Echoed back in the response envelope.
+    #[serde(default)]
+    #[schemars(description = "Optional request context (case_id, requester, channel) echoed back in the response envelope and attached to log records")]
+    pub context: Option<RequestContext>,
+    /// Optional. Defaults to the whole payload.
+    #[serde(default)]
+    #[schemars(description = "Optional list of payload fields to return, e.g. ['tax', 'errors']; nested fields as 'distribution.senior'. Omit for the whole payload")]
+    pub fields: Option<Vec<String>>,
+    /// Optional. Defaults to false.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set true, after confirming with the user, to accept inputs beyond the configured soft limits (such as days_late above 3650 or income above 10M); otherwise such calls return needs_confirmation")]
+    pub acknowledge_warnings: Option<FlexibleBool>,
+    /// Optional. No graph by default.
+    #[serde(default)]
+    #[schemars(description = "Optional 'mermaid' or 'dot' to also return the calculation steps as a graph in the envelope's explanation_graph (inputs, rules and values as nodes, data flow as edges), for drawing an audit trail")]
+    pub explanation_graph: Option<String>,
+    /// Optional. Defaults to ENGINE_NUMBER_LOCALE, or plain numbers.
+    #[serde(default)]
+    #[schemars(description = "Optional locale such as 'de-DE' or 'en-US' for the numbers in explanations (decimal mark, digit grouping, currency symbol); the default writes 1234.56")]
+    pub locale: Option<String>,
+    /// Optional. Defaults to true.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set false in batch and simulation runs to skip building the explanation; it is then returned empty and the call is faster")]
+    pub include_explanations: Option<FlexibleBool>,
+}
+
+impl Default for CalcPenaltyParams {
+    fn default() -> Self {
+        Self {
+            days_late: FlexibleF64::default(),
+            rate_per_day: None,
+            cap: None,
+            interest_rate: None,
+            due_date: None,
+            valuation_date: None,
+            response_version: None,
+            audience: None,
+            context: None,
+            fields: None,
+            acknowledge_warnings: None,
+            explanation_graph: None,
+            locale: None,
+            include_explanations: None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct CalcTaxParams {
+    #[schemars(description = "Total income; zero or negative income (a loss) yields no tax")]
+    pub income: FlexibleF64,
+    /// Optional. Defaults to 0 (no losses brought forward).
+    #[serde(default)]
+    #[schemars(description = "Optional losses carried forward from prior years, deducted from income before brackets apply")]
+    pub loss_carryforward: Option<FlexibleF64>,
+    /// Optional. Defaults to 0; only used when an alternative minimum tax is configured.
+    #[serde(default)]
+    #[schemars(description = "Optional AMT adjustments (preference items) added back to taxable income for the alternative minimum tax, when configured")]
+    pub amt_adjustments: Option<FlexibleF64>,
+    /// Optional. Defaults to the configured base (ENGINE_SURCHARGE_BASE, default tax).
+    #[serde(default)]
+    #[schemars(description = "Optional surcharge base for surcharges that do not set their own: 'tax' (surcharge on total tax above the threshold) or 'income' (surcharge on income above the threshold); uses the configured default if omitted")]
+    pub surcharge_base: Option<String>,
+    /// Optional. Defaults to false.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set true to add the per-bracket breakdown (the brackets list and one explanation step per bracket), which takes time in the number of brackets below the income. Without it the tax is looked up in the bracket table and explained in one step")]
+    pub include_brackets: Option<FlexibleBool>,
+    /// Optional. Defaults to the current response schema version.
+    #[serde(default)]
+    #[schemars(description = "Optional response schema version: 2 (default, versioned envelope) or 1 (legacy bare payload)")]
+    pub response_version: Option<FlexibleI32>,
+    /// Optional. Defaults to expert.
+    #[serde(default)]
+    #[schemars(description = "Optional explanation audience: 'expert' (default, technical step-by-step) or 'citizen' (plain-language narrative for the public)")]
+    pub audience: Option<String>,
+    /// Optional. Echoed back in the response envelope.
+    #[serde(default)]
+    #[schemars(description = "Optional request context (case_id, requester, channel) echoed back in the response envelope and attached to log records")]
+    pub context: Option<RequestContext>,
+    /// Optional. Defaults to the whole payload.
+    #[serde(default)]
+    #[schemars(description = "Optional list of payload fields to return, e.g. ['tax', 'errors']; nested fields as 'distribution.senior'. Omit for the whole payload")]
+    pub fields: Option<Vec<String>>,
+    /// Optional. Defaults to false.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set true, after confirming with the user, to accept inputs beyond the configured soft limits (such as days_late above 3650 or income above 10M); otherwise such calls return needs_confirmation")]
+    pub acknowledge_warnings: Option<FlexibleBool>,
+    /// Optional. No graph by default.
+    #[serde(default)]
+    #[schemars(description = "Optional 'mermaid' or 'dot' to also return the calculation steps as a graph in the envelope's explanation_graph (inputs, rules and values as nodes, data flow as edges), for drawing an audit trail")]
+    pub explanation_graph: Option<String>,
+    /// Optional. Defaults to ENGINE_NUMBER_LOCALE, or plain numbers.
+    #[serde(default)]
+    #[schemars(description = "Optional locale such as 'de-DE' or 'en-US' for the numbers in explanations (decimal mark, digit grouping, currency symbol); the default writes 1234.56")]
+    pub locale: Option<String>,
+    /// Optional. Defaults to true.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set false in batch and simulation runs to skip building the explanation; it is then returned empty and the call is faster")]
+    pub include_explanations: Option<FlexibleBool>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct CheckVotingParams {
+    #[schemars(description = "Total number of eligible voters")]
+    pub eligible_voters: FlexibleI32,
+    #[schemars(description = "Actual turnout (number of people who voted)")]
+    pub turnout: FlexibleI32,
+    #[schemars(description = "Number of yes votes")]
+    pub yes_votes: FlexibleI32,
+    #[schemars(description = "Type of proposal: 'general' or 'amendment'")]
+    pub proposal_type: String,
+    /// Optional. Defaults to the current response schema version.
+    #[serde(default)]
+    #[schemars(description = "Optional response schema version: 2 (default, versioned envelope) or 1 (legacy bare payload)")]
+    pub response_version: Option<FlexibleI32>,
+    /// Optional. Defaults to expert.
+    #[serde(default)]
+    #[schemars(description = "Optional explanation audience: 'expert' (default, technical step-by-step) or 'citizen' (plain-language narrative for the public)")]
+    pub audience: Option<String>,
+    /// Optional. Echoed back in the response envelope.
+    #[serde(default)]
+    #[schemars(description = "Optional request context (case_id, requester, channel) echoed back in the response envelope and attached to log records")]
+    pub context: Option<RequestContext>,
+    /// Optional. Defaults to the whole payload.
+    #[serde(default)]
+    #[schemars(description = "Optional list of payload fields to return, e.g. ['tax', 'errors']; nested fields as 'distribution.senior'. Omit for the whole payload")]
+    pub fields: Option<Vec<String>>,
+    /// Optional. Defaults to false.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set true, after confirming with the user, to accept inputs beyond the configured soft limits (such as days_late above 3650 or income above 10M); otherwise such calls return needs_confirmation")]
+    pub acknowledge_warnings: Option<FlexibleBool>,
+    /// Optional. No graph by default.
+    #[serde(default)]
+    #[schemars(description = "Optional 'mermaid' or 'dot' to also return the calculation steps as a graph in the envelope's explanation_graph (inputs, rules and values as nodes, data flow as edges), for drawing an audit trail")]
+    pub explanation_graph: Option<String>,
+    /// Optional. Defaults to ENGINE_NUMBER_LOCALE, or plain numbers.
+    #[serde(default)]
+    #[schemars(description = "Optional locale such as 'de-DE' or 'en-US' for the numbers in explanations (decimal mark, digit grouping, currency symbol); the default writes 1234.56")]
+    pub locale: Option<String>,
+    /// Optional. Defaults to true.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set false in batch and simulation runs to skip building the explanation; it is then returned empty and the call is faster")]
+    pub include_explanations: Option<FlexibleBool>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct ValidateVoteRecordParams {
+    #[schemars(description = "Total number of eligible voters")]
+    pub eligible_voters: FlexibleI32,
+    #[schemars(description = "Reported turnout (number of ballots cast)")]
+    pub turnout: FlexibleI32,
+    #[schemars(description = "Number of yes votes")]
+    pub yes_votes: FlexibleI32,
+    #[schemars(description = "Number of no votes")]
+    pub no_votes: FlexibleI32,
+    /// Optional. Treated as 0 when omitted.
+    #[serde(default)]
+    #[schemars(description = "Optional number of abstentions; treated as 0 if omitted")]
+    pub abstain_votes: Option<FlexibleI32>,
+    /// Optional. Treated as 0 when omitted.
+    #[serde(default)]
+    #[schemars(description = "Optional number of invalid or spoiled ballots; treated as 0 if omitted")]
+    pub invalid_votes: Option<FlexibleI32>,
+    /// Optional. Defaults to the current response schema version.
+    #[serde(default)]
+    #[schemars(description = "Optional response schema version: 2 (default, versioned envelope) or 1 (legacy bare payload)")]
+    pub response_version: Option<FlexibleI32>,
+    /// Optional. Defaults to expert.
+    #[serde(default)]
+    #[schemars(description = "Optional explanation audience: 'expert' (default, technical step-by-step) or 'citizen' (plain-language narrative for the public)")]
+    pub audience: Option<String>,
+    /// Optional. Echoed back in the response envelope.
+    #[serde(default)]
+    #[schemars(description = "Optional request context (case_id, requester, channel) echoed back in the response envelope and attached to log records")]
+    pub context: Option<RequestContext>,
+    /// Optional. Defaults to the whole payload.
+    #[serde(default)]
+    #[schemars(description = "Optional list of payload fields to return, e.g. ['tax', 'errors']; nested fields as 'distribution.senior'. Omit for the whole payload")]
+    pub fields: Option<Vec<String>>,
+    /// Optional. Defaults to false.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set true, after confirming with the user, to accept inputs beyond the configured soft limits (such as days_late above 3650 or income above 10M); otherwise such calls return needs_confirmation")]
+    pub acknowledge_warnings: Option<FlexibleBool>,
+    /// Optional. No graph by default.
+    #[serde(default)]
+    #[schemars(description = "Optional 'mermaid' or 'dot' to also return the calculation steps as a graph in the envelope's explanation_graph (inputs, rules and values as nodes, data flow as edges), for drawing an audit trail")]
+    pub explanation_graph: Option<String>,
+    /// Optional. Defaults to ENGINE_NUMBER_LOCALE, or plain numbers.
+    #[serde(default)]
+    #[schemars(description = "Optional locale such as 'de-DE' or 'en-US' for the numbers in explanations (decimal mark, digit grouping, currency symbol); the default writes 1234.56")]
+    pub locale: Option<String>,
+    /// Optional. Defaults to true.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set false in batch and simulation runs to skip building the explanation; it is then returned empty and the call is faster")]
+    pub include_explanations: Option<FlexibleBool>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct DistributeWaterfallParams {
+    #[schemars(description = "Total cash available for distribution")]
+    pub cash_available: FlexibleF64,
+    #[schemars(description = "Senior debt amount")]
+    pub senior_debt: FlexibleF64,
+    #[schemars(description = "Junior debt amount")]
+    pub junior_debt: FlexibleF64,
+    /// Optional. Defaults to the current response schema version.
+    #[serde(default)]
+    #[schemars(description = "Optional response schema version: 2 (default, versioned envelope) or 1 (legacy bare payload)")]
+    pub response_version: Option<FlexibleI32>,
+    /// Optional. Defaults to expert.
+    #[serde(default)]
+    #[schemars(description = "Optional explanation audience: 'expert' (default, technical step-by-step) or 'citizen' (plain-language narrative for the public)")]
+    pub audience: Option<String>,
+    /// Optional. Echoed back in the response envelope.
+    #[serde(default)]
+    #[schemars(description = "Optional request context (case_id, requester, channel) echoed back in the response envelope and attached to log records")]
+    pub context: Option<RequestContext>,
+    /// Optional. Defaults to the whole payload.
+    #[serde(default)]
+    #[schemars(description = "Optional list of payload fields to return, e.g. ['tax', 'errors']; nested fields as 'distribution.senior'. Omit for the whole payload")]
+    pub fields: Option<Vec<String>>,
+    /// Optional. Defaults to false.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set true, after confirming with the user, to accept inputs beyond the configured soft limits (such as days_late above 3650 or income above 10M); otherwise such calls return needs_confirmation")]
+    pub acknowledge_warnings: Option<FlexibleBool>,
+    /// Optional. No graph by default.
+    #[serde(default)]
+    #[schemars(description = "Optional 'mermaid' or 'dot' to also return the calculation steps as a graph in the envelope's explanation_graph (inputs, rules and values as nodes, data flow as edges), for drawing an audit trail")]
+    pub explanation_graph: Option<String>,
+    /// Optional. Defaults to ENGINE_NUMBER_LOCALE, or plain numbers.
+    #[serde(default)]
+    #[schemars(description = "Optional locale such as 'de-DE' or 'en-US' for the numbers in explanations (decimal mark, digit grouping, currency symbol); the default writes 1234.56")]
+    pub locale: Option<String>,
+    /// Optional. Defaults to true.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set false in batch and simulation runs to skip building the explanation; it is then returned empty and the call is faster")]
+    pub include_explanations: Option<FlexibleBool>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct CheckCovenantsParams {
+    #[schemars(description = "Total debt outstanding")]
+    pub total_debt: FlexibleF64,
+    #[schemars(description = "EBITDA for the test period")]
+    pub ebitda: FlexibleF64,
+    #[schemars(description = "Interest expense for the test period")]
+    pub interest_expense: FlexibleF64,
+    /// Optional. If not provided, uses configured default (e.g. ENGINE_DEFAULT_MAX_LEVERAGE).
+    #[serde(default)]
+    #[schemars(description = "Optional maximum leverage (debt / EBITDA); uses default if omitted")]
+    pub max_leverage: Option<FlexibleF64>,
+    /// Optional. If not provided, uses configured default (e.g. ENGINE_DEFAULT_MIN_INTEREST_COVERAGE).
+    #[serde(default)]
+    #[schemars(description = "Optional minimum interest coverage (EBITDA / interest); uses default if omitted")]
+    pub min_interest_coverage: Option<FlexibleF64>,
+    /// Optional. Defaults to the current response schema version.
+    #[serde(default)]
+    #[schemars(description = "Optional response schema version: 2 (default, versioned envelope) or 1 (legacy bare payload)")]
+    pub response_version: Option<FlexibleI32>,
+    /// Optional. Defaults to expert.
+    #[serde(default)]
+    #[schemars(description = "Optional explanation audience: 'expert' (default, technical step-by-step) or 'citizen' (plain-language narrative for the public)")]
+    pub audience: Option<String>,
+    /// Optional. Echoed back in the response envelope.
+    #[serde(default)]
+    #[schemars(description = "Optional request context (case_id, requester, channel) echoed back in the response envelope and attached to log records")]
+    pub context: Option<RequestContext>,
+    /// Optional. Defaults to the whole payload.
+    #[serde(default)]
+    #[schemars(description = "Optional list of payload fields to return, e.g. ['tax', 'errors']; nested fields as 'distribution.senior'. Omit for the whole payload")]
+    pub fields: Option<Vec<String>>,
+    /// Optional. Defaults to false.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set true, after confirming with the user, to accept inputs beyond the configured soft limits (such as days_late above 3650 or income above 10M); otherwise such calls return needs_confirmation")]
+    pub acknowledge_warnings: Option<FlexibleBool>,
+    /// Optional. No graph by default.
+    #[serde(default)]
+    #[schemars(description = "Optional 'mermaid' or 'dot' to also return the calculation steps as a graph in the envelope's explanation_graph (inputs, rules and values as nodes, data flow as edges), for drawing an audit trail")]
+    pub explanation_graph: Option<String>,
+    /// Optional. Defaults to ENGINE_NUMBER_LOCALE, or plain numbers.
+    #[serde(default)]
+    #[schemars(description = "Optional locale such as 'de-DE' or 'en-US' for the numbers in explanations (decimal mark, digit grouping, currency symbol); the default writes 1234.56")]
+    pub locale: Option<String>,
+    /// Optional. Defaults to true.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set false in batch and simulation runs to skip building the explanation; it is then returned empty and the call is faster")]
+    pub include_explanations: Option<FlexibleBool>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct CalcNpvParams {
+    #[serde(deserialize_with = "deserialize_flexible_f64_list")]
+    #[schemars(description = "Cash flows per period, starting at period 0 (undiscounted); outflows negative")]
+    pub cash_flows: Vec<FlexibleF64>,
+    #[schemars(description = "Discount rate per period (decimal, e.g. 0.08 for 8%)")]
+    pub discount_rate: FlexibleF64,
+    /// Optional. Defaults to the current response schema version.
+    #[serde(default)]
+    #[schemars(description = "Optional response schema version: 2 (default, versioned envelope) or 1 (legacy bare payload)")]
+    pub response_version: Option<FlexibleI32>,
+    /// Optional. Defaults to expert.
+    #[serde(default)]
+    #[schemars(description = "Optional explanation audience: 'expert' (default, technical step-by-step) or 'citizen' (plain-language narrative for the public)")]
+    pub audience: Option<String>,
+    /// Optional. Echoed back in the response envelope.
+    #[serde(default)]
+    #[schemars(description = "Optional request context (case_id, requester, channel) echoed back in the response envelope and attached to log records")]
+    pub context: Option<RequestContext>,
+    /// Optional. Defaults to the whole payload.
+    #[serde(default)]
+    #[schemars(description = "Optional list of payload fields to return, e.g. ['tax', 'errors']; nested fields as 'distribution.senior'. Omit for the whole payload")]
+    pub fields: Option<Vec<String>>,
+    /// Optional. Defaults to false.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set true, after confirming with the user, to accept inputs beyond the configured soft limits (such as days_late above 3650 or income above 10M); otherwise such calls return needs_confirmation")]
+    pub acknowledge_warnings: Option<FlexibleBool>,
+    /// Optional. No graph by default.
+    #[serde(default)]
+    #[schemars(description = "Optional 'mermaid' or 'dot' to also return the calculation steps as a graph in the envelope's explanation_graph (inputs, rules and values as nodes, data flow as edges), for drawing an audit trail")]
+    pub explanation_graph: Option<String>,
+    /// Optional. Defaults to ENGINE_NUMBER_LOCALE, or plain numbers.
+    #[serde(default)]
+    #[schemars(description = "Optional locale such as 'de-DE' or 'en-US' for the numbers in explanations (decimal mark, digit grouping, currency symbol); the default writes 1234.56")]
+    pub locale: Option<String>,
+    /// Optional. Defaults to true.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set false in batch and simulation runs to skip building the explanation; it is then returned empty and the call is faster")]
+    pub include_explanations: Option<FlexibleBool>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct CalcIrrParams {
+    #[serde(deserialize_with = "deserialize_flexible_f64_list")]
+    #[schemars(description = "Cash flows per period, starting at period 0; must contain at least one sign change")]
+    pub cash_flows: Vec<FlexibleF64>,
+    /// Optional. Defaults to the current response schema version.
+    #[serde(default)]
+    #[schemars(description = "Optional response schema version: 2 (default, versioned envelope) or 1 (legacy bare payload)")]
+    pub response_version: Option<FlexibleI32>,
+    /// Optional. Defaults to expert.
+    #[serde(default)]
+    #[schemars(description = "Optional explanation audience: 'expert' (default, technical step-by-step) or 'citizen' (plain-language narrative for the public)")]
+    pub audience: Option<String>,
+    /// Optional. Echoed back in the response envelope.
+    #[serde(default)]
+    #[schemars(description = "Optional request context (case_id, requester, channel) echoed back in the response envelope and attached to log records")]
+    pub context: Option<RequestContext>,
+    /// Optional. Defaults to the whole payload.
+    #[serde(default)]
+    #[schemars(description = "Optional list of payload fields to return, e.g. ['tax', 'errors']; nested fields as 'distribution.senior'. Omit for the whole payload")]
+    pub fields: Option<Vec<String>>,
+    /// Optional. Defaults to false.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set true, after confirming with the user, to accept inputs beyond the configured soft limits (such as days_late above 3650 or income above 10M); otherwise such calls return needs_confirmation")]
+    pub acknowledge_warnings: Option<FlexibleBool>,
+    /// Optional. No graph by default.
+    #[serde(default)]
+    #[schemars(description = "Optional 'mermaid' or 'dot' to also return the calculation steps as a graph in the envelope's explanation_graph (inputs, rules and values as nodes, data flow as edges), for drawing an audit trail")]
+    pub explanation_graph: Option<String>,
+    /// Optional. Defaults to ENGINE_NUMBER_LOCALE, or plain numbers.
+    #[serde(default)]
+    #[schemars(description = "Optional locale such as 'de-DE' or 'en-US' for the numbers in explanations (decimal mark, digit grouping, currency symbol); the default writes 1234.56")]
+    pub locale: Option<String>,
+    /// Optional. Defaults to true.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set false in batch and simulation runs to skip building the explanation; it is then returned empty and the call is faster")]
+    pub include_explanations: Option<FlexibleBool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct EscrowMilestone {
+    #[schemars(description = "Milestone name")]
+    pub name: String,
+    #[schemars(description = "Whether the milestone has been completed (true/false, yes/no, 1/0)")]
+    pub completed: FlexibleBool,
+    #[schemars(description = "Share of the escrow released on completion (decimal, e.g. 0.25 for 25%)")]
+    pub release_percentage: FlexibleF64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct CheckEscrowReleaseParams {
+    #[schemars(description = "Total amount held in escrow")]
+    pub escrow_amount: FlexibleF64,
+    #[schemars(description = "Release schedule: milestones with completion flag and release percentage")]
+    pub milestones: Vec<EscrowMilestone>,
+    /// Optional. When set, nothing is released before this date.
+    #[serde(default)]
+    #[schemars(description = "Optional earliest release date (YYYY-MM-DD)")]
+    pub release_date: Option<String>,
+    /// Optional. Defaults to today's date.
+    #[serde(default)]
+    #[schemars(description = "Optional evaluation date (YYYY-MM-DD); defaults to today")]
+    pub as_of_date: Option<String>,
+    /// Optional. Treated as false when omitted.
+    #[serde(default)]
+    #[schemars(description = "Optional flag indicating an open dispute, which blocks any release; defaults to false")]
+    pub dispute_pending: Option<FlexibleBool>,
+    /// Optional. Treated as 0 when omitted.
+    #[serde(default)]
+    #[schemars(description = "Optional amount already released from escrow; defaults to 0")]
+    pub already_released: Option<FlexibleF64>,
+    /// Optional. Defaults to the current response schema version.
+    #[serde(default)]
+    #[schemars(description = "Optional response schema version: 2 (default, versioned envelope) or 1 (legacy bare payload)")]
+    pub response_version: Option<FlexibleI32>,
+    /// Optional. Defaults to expert.
+    #[serde(default)]
+    #[schemars(description = "Optional explanation audience: 'expert' (default, technical step-by-step) or 'citizen' (plain-language narrative for the public)")]
+    pub audience: Option<String>,
+    /// Optional. Echoed back in the response envelope.
+    #[serde(default)]
+    #[schemars(description = "Optional request context (case_id, requester, channel) echoed back in the response envelope and attached to log records")]
+    pub context: Option<RequestContext>,
+    /// Optional. Defaults to the whole payload.
+    #[serde(default)]
+    #[schemars(description = "Optional list of payload fields to return, e.g. ['tax', 'errors']; nested fields as 'distribution.senior'. Omit for the whole payload")]
+    pub fields: Option<Vec<String>>,
+    /// Optional. Defaults to false.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set true, after confirming with the user, to accept inputs beyond the configured soft limits (such as days_late above 3650 or income above 10M); otherwise such calls return needs_confirmation")]
+    pub acknowledge_warnings: Option<FlexibleBool>,
+    /// Optional. No graph by default.
+    #[serde(default)]
+    #[schemars(description = "Optional 'mermaid' or 'dot' to also return the calculation steps as a graph in the envelope's explanation_graph (inputs, rules and values as nodes, data flow as edges), for drawing an audit trail")]
+    pub explanation_graph: Option<String>,
+    /// Optional. Defaults to ENGINE_NUMBER_LOCALE, or plain numbers.
+    #[serde(default)]
+    #[schemars(description = "Optional locale such as 'de-DE' or 'en-US' for the numbers in explanations (decimal mark, digit grouping, currency symbol); the default writes 1234.56")]
+    pub locale: Option<String>,
+    /// Optional. Defaults to true.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set false in batch and simulation runs to skip building the explanation; it is then returned empty and the call is faster")]
+    pub include_explanations: Option<FlexibleBool>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct DistributeWaterfallResult {
+    #[schemars(description = "Amount allocated to senior debt")]
+    pub senior: f64,
+    #[schemars(description = "Amount allocated to junior debt")]
+    pub junior: f64,
+    #[schemars(description = "Amount allocated to equity")]
+    pub equity: f64,
+    #[serde(default)]
+    #[schemars(description = "Senior debt left unpaid: senior_debt − senior")]
+    pub senior_shortfall: f64,
+    #[serde(default)]
+    #[schemars(description = "Junior debt left unpaid: junior_debt − junior")]
+    pub junior_shortfall: f64,
+    #[serde(default)]
+    #[schemars(description = "Share of senior debt paid (decimal); 1 when there is no senior debt")]
+    pub senior_coverage: f64,
+    #[serde(default)]
+    #[schemars(description = "Share of junior debt paid (decimal); 1 when there is no junior debt")]
+    pub junior_coverage: f64,
+}
+
+/// A lenient-parsing or defaulting decision made while reading tool parameters
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct Assumption {
+    #[schemars(description = "Machine-readable assumption code (e.g. 'percent_sign_stripped', 'comma_removed', 'default_applied')")]
+    pub code: String,
+    #[schemars(description = "Parameter the assumption applies to")]
+    pub field: String,
+    #[schemars(description = "Human-readable description of what was assumed")]
+    pub message: String,
+}
+
+// Response structures with explanations
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct CalcPenaltyResponse {
+    #[schemars(description = "Calculated penalty amount")]
+    pub penalty: f64,
+    #[serde(default)]
+    #[schemars(description = "Penalty before the cap: days_late × rate_per_day")]
+    pub base_penalty: f64,
+    #[serde(default)]
+    #[schemars(description = "Whether the base penalty exceeded the cap and was reduced to it")]
+    pub cap_applied: bool,
+    #[serde(default)]
+    #[schemars(description = "Penalty after the cap, before interest")]
+    pub capped_penalty: f64,
+    #[serde(default)]
+    #[schemars(description = "Interest on the capped penalty; penalty = capped_penalty + interest_amount")]
+    pub interest_amount: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Present when interest accrued to a valuation date: the date interest accrued from")]
+    pub due_date: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Present when interest accrued to a valuation date: the date the liability is valued at")]
+    pub valuation_date: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Present when interest accrued to a valuation date: days from due_date to valuation_date")]
+    pub interest_days: Option<i64>,
+    #[schemars(description = "Explanation of calculation steps")]
+    pub explanation: String,
+    #[serde(default)]
+    #[schemars(description = "Assumptions made while reading the parameters (lenient parsing, defaults applied)")]
+    pub assumptions: Vec<Assumption>,
+    #[schemars(description = "Any errors in input validation")]
+    pub errors: Vec<String>,
+    #[schemars(description = "Warnings or additional information")]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct CalcTaxResponse {
+    #[schemars(description = "Calculated tax amount")]
+    pub tax: f64,
+    #[serde(default)]
+    #[schemars(description = "Income left after deducting loss carryforward; the brackets apply to this amount")]
+    pub taxable_income: f64,
+    #[serde(default)]
+    #[schemars(description = "Loss carryforward still available for future years, including any loss from this year")]
+    pub remaining_carryforward: f64,
+    #[serde(default)]
+    #[schemars(description = "Surcharge base for surcharges that do not set their own: 'tax' (tax liability) or 'income' (income above the surcharge threshold)")]
+    pub surcharge_base: SurchargeBase,
+    #[serde(default)]
+    #[schemars(description = "Each tax bracket used, in order: its range, rate, the income taxed in it and the tax")]
+    pub brackets: Vec<BracketResult>,
+    #[serde(default)]
+    #[schemars(description = "Total of the surcharges and the tax they were added to")]
+    pub surcharge: SurchargeTotal,
+    #[serde(default)]
+    #[schemars(description = "Each surcharge in the order applied, with its base and amount")]
+    pub surcharges: Vec<SurchargeLine>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Present when the bracket schedule changed mid-year: the tax under each schedule and the days each was in force")]
+    pub proration: Option<TaxProration>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Present when an alternative minimum tax is configured: both computations and which one was owed")]
+    pub minimum_tax: Option<MinimumTaxBreakdown>,
+    #[schemars(description = "Explanation of calculation steps")]
+    pub explanation: String,
+    #[serde(default)]
+    #[schemars(description = "Assumptions made while reading the parameters (lenient parsing, defaults applied)")]
+    pub assumptions: Vec<Assumption>,
+    #[schemars(description = "Any errors in input validation")]
+    pub errors: Vec<String>,
+    #[schemars(description = "Warnings or additional information")]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct BracketResult {
+    #[schemars(description = "Lower bound of the bracket")]
+    pub lower: f64,
+    #[schemars(description = "Upper bound of the bracket; null for the top bracket")]
+    pub upper: Option<f64>,
+    #[schemars(description = "Bracket rate (decimal)")]
+    pub rate: f64,
+    #[schemars(description = "Income taxed at this rate")]
+    pub taxable: f64,
+    #[schemars(description = "Tax from this bracket")]
+    pub tax: f64,
+    #[serde(default)]
+    #[schemars(description = "Cliff bracket: the rate applies to all income up to its top, and its tax replaces the tax of the brackets below")]
+    pub cliff: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Present when the schedule changed mid-year: 'before_change' or 'after_change'")]
+    pub period: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct SurchargeTotal {
+    #[schemars(description = "Whether any surcharge applied")]
+    pub applied: bool,
+    #[schemars(description = "Tax from the brackets, before surcharges")]
+    pub subtotal: f64,
+    #[schemars(description = "Sum of all surcharges; regular tax = subtotal + amount")]
+    pub amount: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct SurchargeLine {
+    #[schemars(description = "Surcharge name")]
+    pub name: String,
+    #[schemars(description = "What the surcharge was levied on: 'tax' or 'income'")]
+    pub base: SurchargeBase,
+    #[schemars(description = "Threshold the base must exceed")]
+    pub threshold: f64,
+    #[schemars(description = "Surcharge rate (decimal)")]
+    pub rate: f64,
+    #[schemars(description = "Whether the base exceeded the threshold")]
+    pub applied: bool,
+    #[schemars(description = "Surcharge amount added to the tax")]
+    pub amount: f64,
+}
+
+/// Regular tax and its parts, before any alternative minimum tax
+#[derive(Debug, Default)]
+struct RegularTax {
+    tax: f64,
+    proration: Option<TaxProration>,
+    brackets: Vec<BracketResult>,
+    surcharge: SurchargeTotal,
+    surcharges: Vec<SurchargeLine>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct TaxProration {
+    #[schemars(description = "Date the second schedule took effect (YYYY-MM-DD)")]
+    pub effective_date: String,
+    #[schemars(description = "Days of the tax year before the change")]
+    pub days_before_change: i64,
+    #[schemars(description = "Days of the tax year from the change onwards")]
+    pub days_after_change: i64,
+    #[schemars(description = "Full-year tax under the schedule in force before the change")]
+    pub tax_before_change: f64,
+    #[schemars(description = "Full-year tax under the schedule in force from the change")]
+    pub tax_after_change: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct MinimumTaxBreakdown {
+    #[schemars(description = "Taxable income plus AMT adjustments")]
+    pub adjusted_income: f64,
+    #[schemars(description = "Exemption deducted from adjusted income")]
+    pub exemption: f64,
+    #[schemars(description = "Flat minimum tax rate (decimal)")]
+    pub rate: f64,
+    #[schemars(description = "Alternative minimum tax: (adjusted income - exemption) × rate")]
+    pub minimum_tax: f64,
+    #[schemars(description = "Regular tax from brackets and surcharges")]
+    pub regular_tax: f64,
+    #[schemars(description = "True when the minimum tax exceeded the regular tax and was owed instead")]
+    pub applied: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct CheckVotingResponse {
+    #[schemars(description = "Whether the proposal passes")]
+    pub passes: bool,
+    #[serde(default)]
+    #[schemars(description = "Turnout as a share of eligible voters (decimal)")]
+    pub turnout_percentage: f64,
+    #[serde(default)]
+    #[schemars(description = "Yes votes as a share of turnout (decimal); 0 when nobody voted")]
+    pub approval_percentage: f64,
+    #[serde(default)]
+    #[schemars(description = "Minimum turnout share required (decimal, inclusive)")]
+    pub required_turnout: f64,
+    #[serde(default)]
+    #[schemars(description = "Approval share required (decimal): more than 0.5 for general proposals, at least 2/3 for amendments")]
+    pub required_approval: f64,
+    #[schemars(description = "Explanation of voting calculation")]
+    pub explanation: String,
+    #[serde(default)]
+    #[schemars(description = "Assumptions made while reading the parameters (lenient parsing, defaults applied)")]
+    pub assumptions: Vec<Assumption>,
+    #[schemars(description = "Any errors in input validation")]
+    pub errors: Vec<String>,
+    #[schemars(description = "Warnings or additional information")]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct VoteRecordInconsistency {
+    #[schemars(description = "Machine-readable inconsistency code (e.g. 'tally_mismatch', 'turnout_exceeds_eligible', 'negative_count')")]
+    pub code: String,
+    #[schemars(description = "Fields involved in the inconsistency")]
+    pub fields: Vec<String>,
+    #[schemars(description = "Human-readable description of the inconsistency")]
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct ValidateVoteRecordResponse {
+    #[schemars(description = "Whether the vote record is internally consistent")]
+    pub valid: bool,
+    #[schemars(description = "Sum of yes, no, abstain and invalid ballots")]
+    pub tallied_ballots: i64,
+    #[schemars(description = "All inconsistencies found in the record")]
+    pub inconsistencies: Vec<VoteRecordInconsistency>,
+    #[schemars(description = "Explanation of the checks performed")]
+    pub explanation: String,
+    #[serde(default)]
+    #[schemars(description = "Assumptions made while reading the parameters (lenient parsing, defaults applied)")]
+    pub assumptions: Vec<Assumption>,
+    #[schemars(description = "Any errors in input validation")]
+    pub errors: Vec<String>,
+    #[schemars(description = "Warnings or additional information")]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct DistributeWaterfallResponse {
+    #[schemars(description = "Distribution results")]
+    pub distribution: DistributeWaterfallResult,
+    #[schemars(description = "Explanation of waterfall distribution")]
+    pub explanation: String,
+    #[serde(default)]
+    #[schemars(description = "Assumptions made while reading the parameters (lenient parsing, defaults applied)")]
+    pub assumptions: Vec<Assumption>,
+    #[schemars(description = "Any errors in input validation")]
+    pub errors: Vec<String>,
+    #[schemars(description = "Warnings or additional information")]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct CovenantResult {
+    #[schemars(description = "Covenant name ('leverage' or 'interest_coverage')")]
+    pub name: String,
+    #[schemars(description = "Computed ratio; null when undefined (e.g. zero interest expense)")]
+    pub ratio: Option<f64>,
+    #[schemars(description = "Covenant threshold")]
+    pub threshold: f64,
+    #[schemars(description = "Required comparison of ratio against threshold ('<=' or '>=')")]
+    pub comparison: String,
+    #[schemars(description = "Whether the covenant is satisfied")]
+    pub passes: bool,
+    #[schemars(description = "Distance to the threshold in ratio turns (negative when breached); null when the ratio is undefined")]
+    pub headroom: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct CheckCovenantsResponse {
+    #[schemars(description = "Whether all covenants are satisfied")]
+    pub all_pass: bool,
+    #[schemars(description = "Per-covenant results")]
+    pub covenants: Vec<CovenantResult>,
+    #[schemars(description = "Explanation of covenant calculations")]
+    pub explanation: String,
+    #[serde(default)]
+    #[schemars(description = "Assumptions made while reading the parameters (lenient parsing, defaults applied)")]
+    pub assumptions: Vec<Assumption>,
+    #[schemars(description = "Any errors in input validation")]
+    pub errors: Vec<String>,
+    #[schemars(description = "Warnings or additional information")]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct CalcNpvResponse {
+    #[schemars(description = "Net present value of the cash flows")]
+    pub npv: f64,
+    #[schemars(description = "Present value of each period's cash flow")]
+    pub present_values: Vec<f64>,
+    #[schemars(description = "Explanation of discounting steps")]
+    pub explanation: String,
+    #[serde(default)]
+    #[schemars(description = "Assumptions made while reading the parameters (lenient parsing, defaults applied)")]
+    pub assumptions: Vec<Assumption>,
+    #[schemars(description = "Any errors in input validation")]
+    pub errors: Vec<String>,
+    #[schemars(description = "Warnings or additional information")]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct CalcIrrResponse {
+    #[schemars(description = "Internal rate of return per period (decimal)")]
+    pub irr: f64,
+    #[schemars(description = "Number of bisection iterations performed")]
+    pub iterations: u32,
+    #[schemars(description = "NPV of the cash flows evaluated at the returned IRR (solver residual)")]
+    pub npv_at_irr: f64,
+    #[schemars(description = "Explanation including solver diagnostics")]
+    pub explanation: String,
+    #[serde(default)]
+    #[schemars(description = "Assumptions made while reading the parameters (lenient parsing, defaults applied)")]
+    pub assumptions: Vec<Assumption>,
+    #[schemars(description = "Any errors in input validation")]
+    pub errors: Vec<String>,
+    #[schemars(description = "Warnings or additional information")]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct EscrowMilestoneResult {
+    #[schemars(description = "Milestone name")]
+    pub name: String,
+    #[schemars(description = "Whether the milestone has been completed")]
+    pub completed: bool,
+    #[schemars(description = "Share of the escrow attached to this milestone (decimal)")]
+    pub release_percentage: f64,
+    #[schemars(description = "Amount attributable to this milestone")]
+    pub amount: f64,
+    #[schemars(description = "Whether this milestone's share is released")]
+    pub released: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct CheckEscrowReleaseResponse {
+    #[schemars(description = "Amount to release now (net of amounts already released)")]
+    pub release_amount: f64,
+    #[schemars(description = "Cumulative share of the escrow released including this release (decimal)")]
+    pub released_percentage: f64,
+    #[schemars(description = "Amount remaining in escrow after this release")]
+    pub remaining_in_escrow: f64,
+    #[schemars(description = "Per-milestone release status")]
+    pub milestones: Vec<EscrowMilestoneResult>,
+    #[schemars(description = "Conditions that blocked all or part of the release")]
+    pub blocking_conditions: Vec<String>,
+    #[schemars(description = "Explanation of release evaluation")]
+    pub explanation: String,
+    #[serde(default)]
+    #[schemars(description = "Assumptions made while reading the parameters (lenient parsing, defaults applied)")]
+    pub assumptions: Vec<Assumption>,
+    #[schemars(description = "Any errors in input validation")]
+    pub errors: Vec<String>,
+    #[schemars(description = "Warnings or additional information")]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct CheckHousingGrantResponse {
+    #[schemars(description = "Whether eligible for housing grant")]
+    pub eligible: bool,
+    #[serde(default)]
+    #[schemars(description = "Income limit for the household, in the base currency")]
+    pub threshold: f64,
+    #[serde(default)]
+    #[schemars(description = "Share of AMI the income limit is set at (decimal): 0.60, or 0.66 for households of more than 4 under the size rule")]
+    pub ami_percentage_used: f64,
+    #[serde(default)]
+    #[schemars(description = "How the income limit scales with the household (ENGINE_HOUSING_EQUIVALENCE_SCALE): 'size' (10% higher for more than 4 people), 'oecd_modified', 'oecd' or 'square_root'")]
+    pub equivalence_scale: EquivalenceScale,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Household size in adult equivalents under the equivalence scale; absent under the size rule")]
+    pub adult_equivalents: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Income per adult equivalent; absent under the size rule")]
+    pub equivalized_income: Option<f64>,
+    #[serde(default)]
+    #[schemars(description = "Threshold minus income: headroom when positive, amount over the limit when negative")]
+    pub income_margin: f64,
+    #[serde(default)]
+    #[schemars(description = "Reasons the household is not eligible; empty when eligible or when the inputs are invalid")]
+    pub blocking_reasons: Vec<String>,
+    #[schemars(description = "Explanation of eligibility calculation")]
+    pub explanation: String,
+    #[serde(default)]
+    #[schemars(description = "Assumptions made while reading the parameters (lenient parsing, defaults applied)")]
+    pub assumptions: Vec<Assumption>,
+    #[schemars(description = "Any errors in input validation")]
+    pub errors: Vec<String>,
+    #[schemars(description = "Additional requirements or warnings")]
+    pub additional_requirements: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct CheckHousingGrantParams {
+    #[schemars(description = "Area Median Income (AMI)")]
+    pub ami: FlexibleF64,
+    #[schemars(description = "Household size")]
+    pub household_size: FlexibleI32,
+    #[schemars(description = "Household income")]
+    pub income: FlexibleF64,
+    #[schemars(description = "Whether the household has another subsidy (true/false, yes/no, 1/0)")]
+    pub has_other_subsidy: FlexibleBool,
+    /// Optional. Defaults to 0; only weighed by the OECD equivalence scales.
+    #[serde(default)]
+    #[schemars(description = "Optional number of household members under 14, included in household_size; counts for less than an adult under the OECD equivalence scales (default 0)")]
+    pub children: Option<FlexibleI32>,
+    /// Optional. Defaults to the base currency (ENGINE_BASE_CURRENCY).
+    #[serde(default)]
+    #[schemars(description = "Optional currency code of the income (e.g. 'USD'); converted to the base currency at the latest configured rate before the AMI threshold applies")]
+    pub income_currency: Option<String>,
+    /// Optional. Defaults to the current response schema version.
+    #[serde(default)]
+    #[schemars(description = "Optional response schema version: 2 (default, versioned envelope) or 1 (legacy bare payload)")]
+    pub response_version: Option<FlexibleI32>,
+    /// Optional. Defaults to expert.
+    #[serde(default)]
+    #[schemars(description = "Optional explanation audience: 'expert' (default, technical step-by-step) or 'citizen' (plain-language narrative for the public)")]
+    pub audience: Option<String>,
+    /// Optional. Echoed back in the response envelope.
+    #[serde(default)]
+    #[schemars(description = "Optional request context (case_id, requester, channel) echoed back in the response envelope and attached to log records")]
+    pub context: Option<RequestContext>,
+    /// Optional. Defaults to the whole payload.
+    #[serde(default)]
+    #[schemars(description = "Optional list of payload fields to return, e.g. ['tax', 'errors']; nested fields as 'distribution.senior'. Omit for the whole payload")]
+    pub fields: Option<Vec<String>>,
+    /// Optional. Defaults to false.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set true, after confirming with the user, to accept inputs beyond the configured soft limits (such as days_late above 3650 or income above 10M); otherwise such calls return needs_confirmation")]
+    pub acknowledge_warnings: Option<FlexibleBool>,
+    /// Optional. No graph by default.
+    #[serde(default)]
+    #[schemars(description = "Optional 'mermaid' or 'dot' to also return the calculation steps as a graph in the envelope's explanation_graph (inputs, rules and values as nodes, data flow as edges), for drawing an audit trail")]
+    pub explanation_graph: Option<String>,
+    /// Optional. Defaults to ENGINE_NUMBER_LOCALE, or plain numbers.
+    #[serde(default)]
+    #[schemars(description = "Optional locale such as 'de-DE' or 'en-US' for the numbers in explanations (decimal mark, digit grouping, currency symbol); the default writes 1234.56")]
+    pub locale: Option<String>,
+    /// Optional. Defaults to true.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set false in batch and simulation runs to skip building the explanation; it is then returned empty and the call is faster")]
+    pub include_explanations: Option<FlexibleBool>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct CalcCapitalGainsParams {
+    #[schemars(description = "Acquisition cost of the asset, including purchase expenses")]
+    pub acquisition_cost: FlexibleF64,
+    #[schemars(description = "Sale price of the asset, net of selling expenses")]
+    pub sale_price: FlexibleF64,
+    #[schemars(description = "Holding period in days between acquisition and sale")]
+    pub holding_period_days: FlexibleI32,
+    /// Optional. If not provided, uses configured default (e.g. ENGINE_CAPITAL_GAINS_ANNUAL_EXEMPTION).
+    #[serde(default)]
+    #[schemars(description = "Optional annual exemption still available to offset this gain; uses default if omitted")]
+    pub annual_exemption: Option<FlexibleF64>,
+    /// Optional. Defaults to the current response schema version.
+    #[serde(default)]
+    #[schemars(description = "Optional response schema version: 2 (default, versioned envelope) or 1 (legacy bare payload)")]
+    pub response_version: Option<FlexibleI32>,
+    /// Optional. Defaults to expert.
+    #[serde(default)]
+    #[schemars(description = "Optional explanation audience: 'expert' (default, technical step-by-step) or 'citizen' (plain-language narrative for the public)")]
+    pub audience: Option<String>,
+    /// Optional. Echoed back in the response envelope.
+    #[serde(default)]
+    #[schemars(description = "Optional request context (case_id, requester, channel) echoed back in the response envelope and attached to log records")]
+    pub context: Option<RequestContext>,
+    /// Optional. Defaults to the whole payload.
+    #[serde(default)]
+    #[schemars(description = "Optional list of payload fields to return, e.g. ['tax', 'errors']; nested fields as 'distribution.senior'. Omit for the whole payload")]
+    pub fields: Option<Vec<String>>,
+    /// Optional. Defaults to false.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set true, after confirming with the user, to accept inputs beyond the configured soft limits (such as days_late above 3650 or income above 10M); otherwise such calls return needs_confirmation")]
+    pub acknowledge_warnings: Option<FlexibleBool>,
+    /// Optional. No graph by default.
+    #[serde(default)]
+    #[schemars(description = "Optional 'mermaid' or 'dot' to also return the calculation steps as a graph in the envelope's explanation_graph (inputs, rules and values as nodes, data flow as edges), for drawing an audit trail")]
+    pub explanation_graph: Option<String>,
+    /// Optional. Defaults to ENGINE_NUMBER_LOCALE, or plain numbers.
+    #[serde(default)]
+    #[schemars(description = "Optional locale such as 'de-DE' or 'en-US' for the numbers in explanations (decimal mark, digit grouping, currency symbol); the default writes 1234.56")]
+    pub locale: Option<String>,
+    /// Optional. Defaults to true.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set false in batch and simulation runs to skip building the explanation; it is then returned empty and the call is faster")]
+    pub include_explanations: Option<FlexibleBool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct CalcCapitalGainsResponse {
+    #[schemars(description = "Gain (positive) or loss (negative): sale price - acquisition cost")]
+    pub gain: f64,
+    #[schemars(description = "Whether the holding period qualifies for the long-term rate")]
+    pub long_term: bool,
+    #[schemars(description = "Applicable rate (decimal)")]
+    pub rate: f64,
+    #[schemars(description = "Part of the annual exemption used against the gain")]
+    pub exemption_applied: f64,
+    #[schemars(description = "Gain remaining after the exemption")]
+    pub taxable_gain: f64,
+    #[schemars(description = "Capital gains tax: taxable gain × rate")]
+    pub tax: f64,
+    #[schemars(description = "Explanation of calculation steps")]
+    pub explanation: String,
+    #[serde(default)]
+    #[schemars(description = "Assumptions made while reading the parameters (lenient parsing, defaults applied)")]
+    pub assumptions: Vec<Assumption>,
+    #[schemars(description = "Any errors in input validation")]
+    pub errors: Vec<String>,
+    #[schemars(description = "Warnings or additional information")]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct InheritanceBeneficiary {
+    #[schemars(description = "Beneficiary name")]
+    pub name: String,
+    #[schemars(description = "Relationship class to the deceased or donor (e.g. 'spouse', 'child', 'sibling', 'other')")]
+    pub relationship: String,
+    #[schemars(description = "Amount received by this beneficiary")]
+    pub amount: FlexibleF64,
+    /// Optional. Treated as 0 when omitted.
+    #[serde(default)]
+    #[schemars(description = "Optional earlier gifts from the same person, which use up the exemption first; defaults to 0")]
+    pub prior_gifts: Option<FlexibleF64>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct CalcInheritanceTaxParams {
+    #[schemars(description = "Beneficiaries with relationship class and amount received")]
+    pub beneficiaries: Vec<InheritanceBeneficiary>,
+    /// Optional. Defaults to the current response schema version.
+    #[serde(default)]
+    #[schemars(description = "Optional response schema version: 2 (default, versioned envelope) or 1 (legacy bare payload)")]
+    pub response_version: Option<FlexibleI32>,
+    /// Optional. Defaults to expert.
+    #[serde(default)]
+    #[schemars(description = "Optional explanation audience: 'expert' (default, technical step-by-step) or 'citizen' (plain-language narrative for the public)")]
+    pub audience: Option<String>,
+    /// Optional. Echoed back in the response envelope.
+    #[serde(default)]
+    #[schemars(description = "Optional request context (case_id, requester, channel) echoed back in the response envelope and attached to log records")]
+    pub context: Option<RequestContext>,
+    /// Optional. Defaults to the whole payload.
+    #[serde(default)]
+    #[schemars(description = "Optional list of payload fields to return, e.g. ['tax', 'errors']; nested fields as 'distribution.senior'. Omit for the whole payload")]
+    pub fields: Option<Vec<String>>,
+    /// Optional. Defaults to false.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set true, after confirming with the user, to accept inputs beyond the configured soft limits (such as days_late above 3650 or income above 10M); otherwise such calls return needs_confirmation")]
+    pub acknowledge_warnings: Option<FlexibleBool>,
+    /// Optional. No graph by default.
+    #[serde(default)]
+    #[schemars(description = "Optional 'mermaid' or 'dot' to also return the calculation steps as a graph in the envelope's explanation_graph (inputs, rules and values as nodes, data flow as edges), for drawing an audit trail")]
+    pub explanation_graph: Option<String>,
+    /// Optional. Defaults to ENGINE_NUMBER_LOCALE, or plain numbers.
+    #[serde(default)]
+    #[schemars(description = "Optional locale such as 'de-DE' or 'en-US' for the numbers in explanations (decimal mark, digit grouping, currency symbol); the default writes 1234.56")]
+    pub locale: Option<String>,
+    /// Optional. Defaults to true.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set false in batch and simulation runs to skip building the explanation; it is then returned empty and the call is faster")]
+    pub include_explanations: Option<FlexibleBool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct BeneficiaryTax {
+    #[schemars(description = "Beneficiary name")]
+    pub name: String,
+    #[schemars(description = "Relationship class applied")]
+    pub relationship: String,
+    #[schemars(description = "Amount received")]
+    pub amount: f64,
+    #[schemars(description = "Exemption applied after any prior gifts")]
+    pub exemption_applied: f64,
+    #[schemars(description = "Amount taxed after the exemption")]
+    pub taxable_amount: f64,
+    #[schemars(description = "Tax owed by this beneficiary")]
+    pub tax: f64,
+    #[schemars(description = "Tax as a share of the amount received (decimal)")]
+    pub effective_rate: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct CalcInheritanceTaxResponse {
+    #[schemars(description = "Tax per beneficiary")]
+    pub beneficiaries: Vec<BeneficiaryTax>,
+    #[schemars(description = "Total tax across all beneficiaries")]
+    pub total_tax: f64,
+    #[schemars(description = "Explanation of calculation steps")]
+    pub explanation: String,
+    #[serde(default)]
+    #[schemars(description = "Assumptions made while reading the parameters (lenient parsing, defaults applied)")]
+    pub assumptions: Vec<Assumption>,
+    #[schemars(description = "Any errors in input validation")]
+    pub errors: Vec<String>,
+    #[schemars(description = "Warnings or additional information")]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct CalcSocialContributionsParams {
+    #[schemars(description = "Annual gross salary")]
+    pub gross_salary: FlexibleF64,
+    /// Optional. Defaults to the current response schema version.
+    #[serde(default)]
+    #[schemars(description = "Optional response schema version: 2 (default, versioned envelope) or 1 (legacy bare payload)")]
+    pub response_version: Option<FlexibleI32>,
+    /// Optional. Defaults to expert.
+    #[serde(default)]
+    #[schemars(description = "Optional explanation audience: 'expert' (default, technical step-by-step) or 'citizen' (plain-language narrative for the public)")]
+    pub audience: Option<String>,
+    /// Optional. Echoed back in the response envelope.
+    #[serde(default)]
+    #[schemars(description = "Optional request context (case_id, requester, channel) echoed back in the response envelope and attached to log records")]
+    pub context: Option<RequestContext>,
+    /// Optional. Defaults to the whole payload.
+    #[serde(default)]
+    #[schemars(description = "Optional list of payload fields to return, e.g. ['tax', 'errors']; nested fields as 'distribution.senior'. Omit for the whole payload")]
+    pub fields: Option<Vec<String>>,
+    /// Optional. Defaults to false.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set true, after confirming with the user, to accept inputs beyond the configured soft limits (such as days_late above 3650 or income above 10M); otherwise such calls return needs_confirmation")]
+    pub acknowledge_warnings: Option<FlexibleBool>,
+    /// Optional. No graph by default.
+    #[serde(default)]
+    #[schemars(description = "Optional 'mermaid' or 'dot' to also return the calculation steps as a graph in the envelope's explanation_graph (inputs, rules and values as nodes, data flow as edges), for drawing an audit trail")]
+    pub explanation_graph: Option<String>,
+    /// Optional. Defaults to ENGINE_NUMBER_LOCALE, or plain numbers.
+    #[serde(default)]
+    #[schemars(description = "Optional locale such as 'de-DE' or 'en-US' for the numbers in explanations (decimal mark, digit grouping, currency symbol); the default writes 1234.56")]
+    pub locale: Option<String>,
+    /// Optional. Defaults to true.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set false in batch and simulation runs to skip building the explanation; it is then returned empty and the call is faster")]
+    pub include_explanations: Option<FlexibleBool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct ContributionLine {
+    #[schemars(description = "Contribution type (e.g. 'pension', 'health', 'unemployment')")]
+    pub name: String,
+    #[schemars(description = "Annual contribution ceiling, if any")]
+    pub ceiling: Option<f64>,
+    #[schemars(description = "Salary subject to this contribution: gross salary capped at the ceiling")]
+    pub base: f64,
+    #[schemars(description = "Whether the salary exceeded the ceiling")]
+    pub capped: bool,
+    #[schemars(description = "Employee contribution rate (decimal)")]
+    pub employee_rate: f64,
+    #[schemars(description = "Employer contribution rate (decimal)")]
+    pub employer_rate: f64,
+    #[schemars(description = "Employee contribution: base × employee rate")]
+    pub employee_amount: f64,
+    #[schemars(description = "Employer contribution: base × employer rate")]
+    pub employer_amount: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct CalcSocialContributionsResponse {
+    #[schemars(description = "Per-type contribution breakdown")]
+    pub contributions: Vec<ContributionLine>,
+    #[schemars(description = "Total employee contributions")]
+    pub total_employee: f64,
+    #[schemars(description = "Total employer contributions")]
+    pub total_employer: f64,
+    #[schemars(description = "Gross salary minus employee contributions")]
+    pub net_salary: f64,
+    #[schemars(description = "Explanation of calculation steps")]
+    pub explanation: String,
+    #[serde(default)]
+    #[schemars(description = "Assumptions made while reading the parameters (lenient parsing, defaults applied)")]
+    pub assumptions: Vec<Assumption>,
+    #[schemars(description = "Any errors in input validation")]
+    pub errors: Vec<String>,
+    #[schemars(description = "Warnings or additional information")]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct CalcPropertyTaxParams {
+    #[schemars(description = "Assessed value of the property for this year")]
+    pub assessed_value: FlexibleF64,
+    /// Optional. Without it no year-over-year cap is applied.
+    #[serde(default)]
+    #[schemars(description = "Optional taxable value from the prior year; the increase over it is capped (ENGINE_PROPERTY_ASSESSMENT_CAP)")]
+    pub prior_year_value: Option<FlexibleF64>,
+    /// Optional. Treated as false when omitted.
+    #[serde(default)]
+    #[schemars(description = "Optional flag for an owner-occupied primary residence, which receives the homestead exemption; defaults to false")]
+    pub homestead: Option<FlexibleBool>,
+    /// Optional. If not provided, uses configured default (e.g. ENGINE_DEFAULT_PROPERTY_MILLAGE).
+    #[serde(default)]
+    #[schemars(description = "Optional millage rate in mills (tax per 1000 of taxable value); uses default if omitted")]
+    pub millage: Option<FlexibleF64>,
+    /// Optional. Defaults to the current response schema version.
+    #[serde(default)]
+    #[schemars(description = "Optional response schema version: 2 (default, versioned envelope) or 1 (legacy bare payload)")]
+    pub response_version: Option<FlexibleI32>,
+    /// Optional. Defaults to expert.
+    #[serde(default)]
+    #[schemars(description = "Optional explanation audience: 'expert' (default, technical step-by-step) or 'citizen' (plain-language narrative for the public)")]
+    pub audience: Option<String>,
+    /// Optional. Echoed back in the response envelope.
+    #[serde(default)]
+    #[schemars(description = "Optional request context (case_id, requester, channel) echoed back in the response envelope and attached to log records")]
+    pub context: Option<RequestContext>,
+    /// Optional. Defaults to the whole payload.
+    #[serde(default)]
+    #[schemars(description = "Optional list of payload fields to return, e.g. ['tax', 'errors']; nested fields as 'distribution.senior'. Omit for the whole payload")]
+    pub fields: Option<Vec<String>>,
+    /// Optional. Defaults to false.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set true, after confirming with the user, to accept inputs beyond the configured soft limits (such as days_late above 3650 or income above 10M); otherwise such calls return needs_confirmation")]
+    pub acknowledge_warnings: Option<FlexibleBool>,
+    /// Optional. No graph by default.
+    #[serde(default)]
+    #[schemars(description = "Optional 'mermaid' or 'dot' to also return the calculation steps as a graph in the envelope's explanation_graph (inputs, rules and values as nodes, data flow as edges), for drawing an audit trail")]
+    pub explanation_graph: Option<String>,
+    /// Optional. Defaults to ENGINE_NUMBER_LOCALE, or plain numbers.
+    #[serde(default)]
+    #[schemars(description = "Optional locale such as 'de-DE' or 'en-US' for the numbers in explanations (decimal mark, digit grouping, currency symbol); the default writes 1234.56")]
+    pub locale: Option<String>,
+    /// Optional. Defaults to true.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set false in batch and simulation runs to skip building the explanation; it is then returned empty and the call is faster")]
+    pub include_explanations: Option<FlexibleBool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct CalcPropertyTaxResponse {
+    #[schemars(description = "Assessed value as submitted")]
+    pub assessed_value: f64,
+    #[schemars(description = "Value after the year-over-year increase cap")]
+    pub capped_value: f64,
+    #[schemars(description = "Whether the year-over-year cap limited the value")]
+    pub increase_capped: bool,
+    #[schemars(description = "Homestead exemption deducted")]
+    pub exemption_applied: f64,
+    #[schemars(description = "Taxable value: capped value - exemption")]
+    pub taxable_value: f64,
+    #[schemars(description = "Millage rate applied (mills)")]
+    pub millage: f64,
+    #[schemars(description = "Property tax: taxable value × millage / 1000")]
+    pub tax: f64,
+    #[schemars(description = "Explanation of calculation steps")]
+    pub explanation: String,
+    #[serde(default)]
+    #[schemars(description = "Assumptions made while reading the parameters (lenient parsing, defaults applied)")]
+    pub assumptions: Vec<Assumption>,
+    #[schemars(description = "Any errors in input validation")]
+    pub errors: Vec<String>,
+    #[schemars(description = "Warnings or additional information")]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct CalcTransferTaxParams {
+    #[schemars(description = "Price or value of the property being transferred")]
+    pub price: FlexibleF64,
+    /// Optional. Defaults to the configured method (ENGINE_TRANSFER_TAX_METHOD, default slice).
+    #[serde(default)]
+    #[schemars(description = "Optional computation method: 'slice' (whole price taxed at the rate of the slice it falls in) or 'marginal' (each rate applies to the part of the price within its bracket); uses the configured default if omitted")]
+    pub method: Option<String>,
+    /// Optional. Defaults to the current response schema version.
+    #[serde(default)]
+    #[schemars(description = "Optional response schema version: 2 (default, versioned envelope) or 1 (legacy bare payload)")]
+    pub response_version: Option<FlexibleI32>,
+    /// Optional. Defaults to expert.
+    #[serde(default)]
+    #[schemars(description = "Optional explanation audience: 'expert' (default, technical step-by-step) or 'citizen' (plain-language narrative for the public)")]
+    pub audience: Option<String>,
+    /// Optional. Echoed back in the response envelope.
+    #[serde(default)]
+    #[schemars(description = "Optional request context (case_id, requester, channel) echoed back in the response envelope and attached to log records")]
+    pub context: Option<RequestContext>,
+    /// Optional. Defaults to the whole payload.
+    #[serde(default)]
+    #[schemars(description = "Optional list of payload fields to return, e.g. ['tax', 'errors']; nested fields as 'distribution.senior'. Omit for the whole payload")]
+    pub fields: Option<Vec<String>>,
+    /// Optional. Defaults to false.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set true, after confirming with the user, to accept inputs beyond the configured soft limits (such as days_late above 3650 or income above 10M); otherwise such calls return needs_confirmation")]
+    pub acknowledge_warnings: Option<FlexibleBool>,
+    /// Optional. No graph by default.
+    #[serde(default)]
+    #[schemars(description = "Optional 'mermaid' or 'dot' to also return the calculation steps as a graph in the envelope's explanation_graph (inputs, rules and values as nodes, data flow as edges), for drawing an audit trail")]
+    pub explanation_graph: Option<String>,
+    /// Optional. Defaults to ENGINE_NUMBER_LOCALE, or plain numbers.
+    #[serde(default)]
+    #[schemars(description = "Optional locale such as 'de-DE' or 'en-US' for the numbers in explanations (decimal mark, digit grouping, currency symbol); the default writes 1234.56")]
+    pub locale: Option<String>,
+    /// Optional. Defaults to true.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set false in batch and simulation runs to skip building the explanation; it is then returned empty and the call is faster")]
+    pub include_explanations: Option<FlexibleBool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct CalcTransferTaxResponse {
+    #[schemars(description = "Price as submitted")]
+    pub price: f64,
+    #[schemars(description = "Computation method applied")]
+    pub method: TransferTaxMethod,
+    #[schemars(description = "Rate of the bracket the price falls in")]
+    pub rate: f64,
+    #[schemars(description = "Transfer tax due")]
+    pub tax: f64,
+    #[schemars(description = "Tax as a fraction of the price")]
+    pub effective_rate: f64,
+    #[schemars(description = "Explanation of calculation steps")]
+    pub explanation: String,
+    #[serde(default)]
+    #[schemars(description = "Assumptions made while reading the parameters (lenient parsing, defaults applied)")]
+    pub assumptions: Vec<Assumption>,
+    #[schemars(description = "Any errors in input validation")]
+    pub errors: Vec<String>,
+    #[schemars(description = "Warnings or additional information")]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct CalcFeeParams {
+    #[schemars(description = "Fee code from the configured fee schedules (e.g. 'court_filing', 'land_registration')")]
+    pub fee_code: String,
+    #[schemars(description = "Value the fee is based on (claim amount, property value, etc.)")]
+    pub value: FlexibleF64,
+    /// Optional. Defaults to the current response schema version.
+    #[serde(default)]
+    #[schemars(description = "Optional response schema version: 2 (default, versioned envelope) or 1 (legacy bare payload)")]
+    pub response_version: Option<FlexibleI32>,
+    /// Optional. Defaults to expert.
+    #[serde(default)]
+    #[schemars(description = "Optional explanation audience: 'expert' (default, technical step-by-step) or 'citizen' (plain-language narrative for the public)")]
+    pub audience: Option<String>,
+    /// Optional. Echoed back in the response envelope.
+    #[serde(default)]
+    #[schemars(description = "Optional request context (case_id, requester, channel) echoed back in the response envelope and attached to log records")]
+    pub context: Option<RequestContext>,
+    /// Optional. Defaults to the whole payload.
+    #[serde(default)]
+    #[schemars(description = "Optional list of payload fields to return, e.g. ['tax', 'errors']; nested fields as 'distribution.senior'. Omit for the whole payload")]
+    pub fields: Option<Vec<String>>,
+    /// Optional. Defaults to false.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set true, after confirming with the user, to accept inputs beyond the configured soft limits (such as days_late above 3650 or income above 10M); otherwise such calls return needs_confirmation")]
+    pub acknowledge_warnings: Option<FlexibleBool>,
+    /// Optional. No graph by default.
+    #[serde(default)]
+    #[schemars(description = "Optional 'mermaid' or 'dot' to also return the calculation steps as a graph in the envelope's explanation_graph (inputs, rules and values as nodes, data flow as edges), for drawing an audit trail")]
+    pub explanation_graph: Option<String>,
+    /// Optional. Defaults to ENGINE_NUMBER_LOCALE, or plain numbers.
+    #[serde(default)]
+    #[schemars(description = "Optional locale such as 'de-DE' or 'en-US' for the numbers in explanations (decimal mark, digit grouping, currency symbol); the default writes 1234.56")]
+    pub locale: Option<String>,
+    /// Optional. Defaults to true.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set false in batch and simulation runs to skip building the explanation; it is then returned empty and the call is faster")]
+    pub include_explanations: Option<FlexibleBool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct FeeLine {
+    #[schemars(description = "Component name")]
+    pub name: String,
+    #[schemars(description = "Fixed amount")]
+    pub fixed: f64,
+    #[schemars(description = "Percentage rate (decimal) on the value above the threshold")]
+    pub rate: f64,
+    #[schemars(description = "Threshold above which the rate applies")]
+    pub above: f64,
+    #[schemars(description = "Maximum amount for this component, if any")]
+    pub cap: Option<f64>,
+    #[schemars(description = "Component amount: fixed + rate × value above threshold, capped")]
+    pub amount: f64,
+    #[schemars(description = "Whether the cap limited this component")]
+    pub capped: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct CalcFeeResponse {
+    #[schemars(description = "Fee code applied")]
+    pub fee_code: String,
+    #[schemars(description = "Per-component breakdown")]
+    pub components: Vec<FeeLine>,
+    #[schemars(description = "Total fee: sum of components")]
+    pub fee: f64,
+    #[schemars(description = "Explanation of calculation steps")]
+    pub explanation: String,
+    #[serde(default)]
+    #[schemars(description = "Assumptions made while reading the parameters (lenient parsing, defaults applied)")]
+    pub assumptions: Vec<Assumption>,
+    #[schemars(description = "Any errors in input validation")]
+    pub errors: Vec<String>,
+    #[schemars(description = "Warnings or additional information")]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct IndexAmountParams {
+    #[schemars(description = "Monetary amount to adjust")]
+    pub amount: FlexibleF64,
+    /// Optional. If not provided, uses the configured base year (ENGINE_INDEX_BASE_YEAR).
+    #[serde(default)]
+    #[schemars(description = "Optional year the amount is expressed in; uses the index base year if omitted")]
+    pub from_year: Option<FlexibleI32>,
+    /// Optional. If not provided, uses the configured target year (ENGINE_INDEX_TARGET_YEAR).
+    #[serde(default)]
+    #[schemars(description = "Optional year to adjust the amount to; uses the index target year (latest by default) if omitted")]
+    pub to_year: Option<FlexibleI32>,
+    /// Optional. Defaults to the current response schema version.
+    #[serde(default)]
+    #[schemars(description = "Optional response schema version: 2 (default, versioned envelope) or 1 (legacy bare payload)")]
+    pub response_version: Option<FlexibleI32>,
+    /// Optional. Defaults to expert.
+    #[serde(default)]
+    #[schemars(description = "Optional explanation audience: 'expert' (default, technical step-by-step) or 'citizen' (plain-language narrative for the public)")]
+    pub audience: Option<String>,
+    /// Optional. Echoed back in the response envelope.
+    #[serde(default)]
+    #[schemars(description = "Optional request context (case_id, requester, channel) echoed back in the response envelope and attached to log records")]
+    pub context: Option<RequestContext>,
+    /// Optional. Defaults to the whole payload.
+    #[serde(default)]
+    #[schemars(description = "Optional list of payload fields to return, e.g. ['tax', 'errors']; nested fields as 'distribution.senior'. Omit for the whole payload")]
+    pub fields: Option<Vec<String>>,
+    /// Optional. Defaults to false.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set true, after confirming with the user, to accept inputs beyond the configured soft limits (such as days_late above 3650 or income above 10M); otherwise such calls return needs_confirmation")]
+    pub acknowledge_warnings: Option<FlexibleBool>,
+    /// Optional. No graph by default.
+    #[serde(default)]
+    #[schemars(description = "Optional 'mermaid' or 'dot' to also return the calculation steps as a graph in the envelope's explanation_graph (inputs, rules and values as nodes, data flow as edges), for drawing an audit trail")]
+    pub explanation_graph: Option<String>,
+    /// Optional. Defaults to ENGINE_NUMBER_LOCALE, or plain numbers.
+    #[serde(default)]
+    #[schemars(description = "Optional locale such as 'de-DE' or 'en-US' for the numbers in explanations (decimal mark, digit grouping, currency symbol); the default writes 1234.56")]
+    pub locale: Option<String>,
+    /// Optional. Defaults to true.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set false in batch and simulation runs to skip building the explanation; it is then returned empty and the call is faster")]
+    pub include_explanations: Option<FlexibleBool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct IndexAmountResponse {
+    #[schemars(description = "Amount as submitted")]
+    pub amount: f64,
+    #[schemars(description = "Year the amount is expressed in")]
+    pub from_year: i32,
+    #[schemars(description = "Year the amount is adjusted to")]
+    pub to_year: i32,
+    #[schemars(description = "Price index value for from_year")]
+    pub from_index: f64,
+    #[schemars(description = "Price index value for to_year")]
+    pub to_index: f64,
+    #[schemars(description = "Adjustment factor: to_index / from_index")]
+    pub factor: f64,
+    #[schemars(description = "Inflation-adjusted amount: amount × factor")]
+    pub indexed_amount: f64,
+    #[schemars(description = "Explanation of calculation steps")]
+    pub explanation: String,
+    #[serde(default)]
+    #[schemars(description = "Assumptions made while reading the parameters (lenient parsing, defaults applied)")]
+    pub assumptions: Vec<Assumption>,
+    #[schemars(description = "Any errors in input validation")]
+    pub errors: Vec<String>,
+    #[schemars(description = "Warnings or additional information")]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct ConvertCurrencyParams {
+    #[schemars(description = "Amount to convert")]
+    pub amount: FlexibleF64,
+    #[schemars(description = "Currency code of the amount (e.g. 'USD')")]
+    pub from_currency: String,
+    /// Optional. Defaults to the base currency (ENGINE_BASE_CURRENCY).
+    #[serde(default)]
+    #[schemars(description = "Optional currency code to convert to; uses the base currency if omitted")]
+    pub to_currency: Option<String>,
+    /// Optional. Defaults to the latest configured rate.
+    #[serde(default)]
+    #[schemars(description = "Optional date (YYYY-MM-DD); uses the latest rate on or before it, or the latest rate if omitted")]
+    pub date: Option<String>,
+    /// Optional. Defaults to the current response schema version.
+    #[serde(default)]
+    #[schemars(description = "Optional response schema version: 2 (default, versioned envelope) or 1 (legacy bare payload)")]
+    pub response_version: Option<FlexibleI32>,
+    /// Optional. Defaults to expert.
+    #[serde(default)]
+    #[schemars(description = "Optional explanation audience: 'expert' (default, technical step-by-step) or 'citizen' (plain-language narrative for the public)")]
+    pub audience: Option<String>,
+    /// Optional. Echoed back in the response envelope.
+    #[serde(default)]
+    #[schemars(description = "Optional request context (case_id, requester, channel) echoed back in the response envelope and attached to log records")]
+    pub context: Option<RequestContext>,
+    /// Optional. Defaults to the whole payload.
+    #[serde(default)]
+    #[schemars(description = "Optional list of payload fields to return, e.g. ['tax', 'errors']; nested fields as 'distribution.senior'. Omit for the whole payload")]
+    pub fields: Option<Vec<String>>,
+    /// Optional. Defaults to false.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set true, after confirming with the user, to accept inputs beyond the configured soft limits (such as days_late above 3650 or income above 10M); otherwise such calls return needs_confirmation")]
+    pub acknowledge_warnings: Option<FlexibleBool>,
+    /// Optional. No graph by default.
+    #[serde(default)]
+    #[schemars(description = "Optional 'mermaid' or 'dot' to also return the calculation steps as a graph in the envelope's explanation_graph (inputs, rules and values as nodes, data flow as edges), for drawing an audit trail")]
+    pub explanation_graph: Option<String>,
+    /// Optional. Defaults to ENGINE_NUMBER_LOCALE, or plain numbers.
+    #[serde(default)]
+    #[schemars(description = "Optional locale such as 'de-DE' or 'en-US' for the numbers in explanations (decimal mark, digit grouping, currency symbol); the default writes 1234.56")]
+    pub locale: Option<String>,
+    /// Optional. Defaults to true.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set false in batch and simulation runs to skip building the explanation; it is then returned empty and the call is faster")]
+    pub include_explanations: Option<FlexibleBool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct ConvertCurrencyResponse {
+    #[schemars(description = "Amount as submitted")]
+    pub amount: f64,
+    #[schemars(description = "Currency converted from")]
+    pub from_currency: String,
+    #[schemars(description = "Currency converted to")]
+    pub to_currency: String,
+    #[schemars(description = "Units of to_currency per unit of from_currency")]
+    pub rate: f64,
+    #[schemars(description = "Date of the exchange rate quote used (the older one for cross rates); null when no quote was needed")]
+    pub rate_date: Option<String>,
+    #[schemars(description = "Converted amount: amount × rate")]
+    pub converted_amount: f64,
+    #[schemars(description = "Explanation of calculation steps")]
+    pub explanation: String,
+    #[serde(default)]
+    #[schemars(description = "Assumptions made while reading the parameters (lenient parsing, defaults applied)")]
+    pub assumptions: Vec<Assumption>,
+    #[schemars(description = "Any errors in input validation")]
+    pub errors: Vec<String>,
+    #[schemars(description = "Warnings or additional information")]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct GetIntakeSchemaParams {
+    #[schemars(description = "Program to build the intake form for: 'housing_grant' or 'voting'")]
+    pub program: String,
+    /// Optional. Defaults to the current response schema version.
+    #[serde(default)]
+    #[schemars(description = "Optional response schema version: 2 (default, versioned envelope) or 1 (legacy bare payload)")]
+    pub response_version: Option<FlexibleI32>,
+    /// Optional. Defaults to expert.
+    #[serde(default)]
+    #[schemars(description = "Optional explanation audience: 'expert' (default, technical step-by-step) or 'citizen' (plain-language narrative for the public)")]
+    pub audience: Option<String>,
+    /// Optional. Echoed back in the response envelope.
+    #[serde(default)]
+    #[schemars(description = "Optional request context (case_id, requester, channel) echoed back in the response envelope and attached to log records")]
+    pub context: Option<RequestContext>,
+    /// Optional. Defaults to the whole payload.
+    #[serde(default)]
+    #[schemars(description = "Optional list of payload fields to return, e.g. ['tax', 'errors']; nested fields as 'distribution.senior'. Omit for the whole payload")]
+    pub fields: Option<Vec<String>>,
+    /// Optional. Defaults to false.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set true, after confirming with the user, to accept inputs beyond the configured soft limits (such as days_late above 3650 or income above 10M); otherwise such calls return needs_confirmation")]
+    pub acknowledge_warnings: Option<FlexibleBool>,
+    /// Optional. No graph by default.
+    #[serde(default)]
+    #[schemars(description = "Optional 'mermaid' or 'dot' to also return the calculation steps as a graph in the envelope's explanation_graph (inputs, rules and values as nodes, data flow as edges), for drawing an audit trail")]
+    pub explanation_graph: Option<String>,
+    /// Optional. Defaults to ENGINE_NUMBER_LOCALE, or plain numbers.
+    #[serde(default)]
+    #[schemars(description = "Optional locale such as 'de-DE' or 'en-US' for the numbers in explanations (decimal mark, digit grouping, currency symbol); the default writes 1234.56")]
+    pub locale: Option<String>,
+    /// Optional. Defaults to true.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set false in batch and simulation runs to skip building the explanation; it is then returned empty and the call is faster")]
+    pub include_explanations: Option<FlexibleBool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct IntakeField {
+    #[schemars(description = "Parameter name to submit the value under")]
+    pub name: String,
+    #[schemars(description = "Input type: 'number', 'integer', 'boolean' or 'string'")]
+    pub field_type: String,
+    #[schemars(description = "Whether the engine requires this field")]
+    pub required: bool,
+    #[schemars(description = "Help text from the parameter description")]
+    pub help: String,
+    #[schemars(description = "Validation hints derived from the program's rules")]
+    pub validation: Vec<String>,
+    #[schemars(description = "Allowed values for choice fields; empty for free input")]
+    pub options: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct GetIntakeSchemaResponse {
+    #[schemars(description = "Program the form is for")]
+    pub program: String,
+    #[schemars(description = "Tool to submit the completed form to")]
+    pub tool: String,
+    #[schemars(description = "Form fields in submission order")]
+    pub fields: Vec<IntakeField>,
+    #[serde(default)]
+    #[schemars(description = "How the program tool derives its result from the fields")]
+    pub logic: String,
+    #[schemars(description = "Explanation of calculation steps")]
+    pub explanation: String,
+    #[serde(default)]
+    #[schemars(description = "Assumptions made while reading the parameters (lenient parsing, defaults applied)")]
+    pub assumptions: Vec<Assumption>,
+    #[schemars(description = "Any errors in input validation")]
+    pub errors: Vec<String>,
+    #[schemars(description = "Warnings or additional information")]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct PrescreenParams {
+    #[schemars(description = "Program to pre-screen for: 'housing_grant' or 'voting'")]
+    pub program: String,
+    /// Optional. Any subset of the program tool's parameters; see get_intake_schema for the fields.
+    #[serde(default)]
+    #[schemars(description = "Whatever subset of the program's parameters is known so far, as an object of field name to value (e.g. {\"income\": 45000, \"has_other_subsidy\": false})")]
+    pub inputs: BTreeMap<String, serde_json::Value>,
+    /// Optional. Defaults to the current response schema version.
+    #[serde(default)]
+    #[schemars(description = "Optional response schema version: 2 (default, versioned envelope) or 1 (legacy bare payload)")]
+    pub response_version: Option<FlexibleI32>,
+    /// Optional. Defaults to expert.
+    #[serde(default)]
+    #[schemars(description = "Optional explanation audience: 'expert' (default, technical step-by-step) or 'citizen' (plain-language narrative for the public)")]
+    pub audience: Option<String>,
+    /// Optional. Echoed back in the response envelope.
+    #[serde(default)]
+    #[schemars(description = "Optional request context (case_id, requester, channel) echoed back in the response envelope and attached to log records")]
+    pub context: Option<RequestContext>,
+    /// Optional. Defaults to the whole payload.
+    #[serde(default)]
+    #[schemars(description = "Optional list of payload fields to return, e.g. ['tax', 'errors']; nested fields as 'distribution.senior'. Omit for the whole payload")]
+    pub fields: Option<Vec<String>>,
+    /// Optional. Defaults to false.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set true, after confirming with the user, to accept inputs beyond the configured soft limits (such as days_late above 3650 or income above 10M); otherwise such calls return needs_confirmation")]
+    pub acknowledge_warnings: Option<FlexibleBool>,
+    /// Optional. No graph by default.
+    #[serde(default)]
+    #[schemars(description = "Optional 'mermaid' or 'dot' to also return the calculation steps as a graph in the envelope's explanation_graph (inputs, rules and values as nodes, data flow as edges), for drawing an audit trail")]
+    pub explanation_graph: Option<String>,
+    /// Optional. Defaults to ENGINE_NUMBER_LOCALE, or plain numbers.
+    #[serde(default)]
+    #[schemars(description = "Optional locale such as 'de-DE' or 'en-US' for the numbers in explanations (decimal mark, digit grouping, currency symbol); the default writes 1234.56")]
+    pub locale: Option<String>,
+    /// Optional. Defaults to true.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set false in batch and simulation runs to skip building the explanation; it is then returned empty and the call is faster")]
+    pub include_explanations: Option<FlexibleBool>,
+}
+
+/// Outcome of pre-screening on partial inputs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PrescreenVerdict {
+    /// Every rule passes with the inputs given
+    Eligible,
+    /// At least one rule fails whatever the missing inputs turn out to be
+    Ineligible,
+    /// No rule fails yet, but some need inputs that were not given
+    Undetermined,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct PrescreenRule {
+    #[schemars(description = "Rule name")]
+    pub rule: String,
+    #[schemars(description = "Rule status: 'passed', 'failed' or 'undetermined'")]
+    pub status: String,
+    #[schemars(description = "Why the rule failed, if it did")]
+    pub detail: Option<String>,
+    #[schemars(description = "Inputs needed to decide the rule, if undetermined")]
+    pub missing: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct PrescreenResponse {
+    #[schemars(description = "Program pre-screened")]
+    pub program: String,
+    #[schemars(description = "Tool that makes the full determination")]
+    pub tool: String,
+    #[schemars(description = "Verdict on the inputs given: 'eligible', 'ineligible' or 'undetermined'")]
+    pub verdict: PrescreenVerdict,
+    #[schemars(description = "Inputs still needed to reach a verdict; empty unless undetermined")]
+    pub missing: Vec<String>,
+    #[schemars(description = "Per-rule outcome")]
+    pub rules: Vec<PrescreenRule>,
+    #[schemars(description = "Explanation of calculation steps")]
+    pub explanation: String,
+    #[serde(default)]
+    #[schemars(description = "Assumptions made while reading the parameters (lenient parsing, defaults applied)")]
+    pub assumptions: Vec<Assumption>,
+    #[schemars(description = "Any errors in input validation")]
+    pub errors: Vec<String>,
+    #[schemars(description = "Warnings or additional information")]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct GetUsageReportParams {
+    /// Optional. Defaults to all tools.
+    #[serde(default)]
+    #[schemars(description = "Optional tool name to report on; all tools if omitted")]
+    pub tool: Option<String>,
+    /// Optional. Defaults to 10.
+    #[serde(default)]
+    #[schemars(description = "Optional number of most frequent errors to list (default 10)")]
+    pub limit: Option<FlexibleI32>,
+    /// Optional. Defaults to the current response schema version.
+    #[serde(default)]
+    #[schemars(description = "Optional response schema version: 2 (default, versioned envelope) or 1 (legacy bare payload)")]
+    pub response_version: Option<FlexibleI32>,
+    /// Optional. Defaults to expert.
+    #[serde(default)]
+    #[schemars(description = "Optional explanation audience: 'expert' (default, technical step-by-step) or 'citizen' (plain-language narrative for the public)")]
+    pub audience: Option<String>,
+    /// Optional. Echoed back in the response envelope.
+    #[serde(default)]
+    #[schemars(description = "Optional request context (case_id, requester, channel) echoed back in the response envelope and attached to log records")]
+    pub context: Option<RequestContext>,
+    /// Optional. Defaults to the whole payload.
+    #[serde(default)]
+    #[schemars(description = "Optional list of payload fields to return, e.g. ['tax', 'errors']; nested fields as 'distribution.senior'. Omit for the whole payload")]
+    pub fields: Option<Vec<String>>,
+    /// Optional. Defaults to false.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set true, after confirming with the user, to accept inputs beyond the configured soft limits (such as days_late above 3650 or income above 10M); otherwise such calls return needs_confirmation")]
+    pub acknowledge_warnings: Option<FlexibleBool>,
+    /// Optional. No graph by default.
+    #[serde(default)]
+    #[schemars(description = "Optional 'mermaid' or 'dot' to also return the calculation steps as a graph in the envelope's explanation_graph (inputs, rules and values as nodes, data flow as edges), for drawing an audit trail")]
+    pub explanation_graph: Option<String>,
+    /// Optional. Defaults to ENGINE_NUMBER_LOCALE, or plain numbers.
+    #[serde(default)]
+    #[schemars(description = "Optional locale such as 'de-DE' or 'en-US' for the numbers in explanations (decimal mark, digit grouping, currency symbol); the default writes 1234.56")]
+    pub locale: Option<String>,
+    /// Optional. Defaults to true.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set false in batch and simulation runs to skip building the explanation; it is then returned empty and the call is faster")]
+    pub include_explanations: Option<FlexibleBool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct ParameterUsage {
+    #[schemars(description = "Parameter name as sent by clients")]
+    pub name: String,
+    #[schemars(description = "Number of calls per JSON type the parameter arrived as (string, number, boolean, array, object, null)")]
+    pub types: BTreeMap<String, u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct ToolUsageReport {
+    #[schemars(description = "Tool name")]
+    pub tool: String,
+    #[schemars(description = "Number of calls")]
+    pub calls: u64,
+    #[schemars(description = "Calls that failed with a protocol error or returned an error result")]
+    pub failed_calls: u64,
+    #[schemars(description = "Parameter shapes seen for the tool")]
+    pub parameters: Vec<ParameterUsage>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct ErrorUsage {
+    #[schemars(description = "Tool that reported the error")]
+    pub tool: String,
+    #[schemars(description = "Error message with quoted values and numbers blanked out")]
+    pub message: String,
+    #[schemars(description = "Number of occurrences")]
+    pub count: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct GetUsageReportResponse {
+    #[schemars(description = "Start of the reporting period (server start), RFC 3339")]
+    pub since: String,
+    #[schemars(description = "Total number of tool calls in the period")]
+    pub total_calls: u64,
+    #[schemars(description = "Per-tool call counts and parameter shapes, most called first")]
+    pub tools: Vec<ToolUsageReport>,
+    #[schemars(description = "Most frequent errors across the reported tools")]
+    pub top_errors: Vec<ErrorUsage>,
+    #[schemars(description = "Explanation of calculation steps")]
+    pub explanation: String,
+    #[serde(default)]
+    #[schemars(description = "Assumptions made while reading the parameters (lenient parsing, defaults applied)")]
+    pub assumptions: Vec<Assumption>,
+    #[schemars(description = "Any errors in input validation")]
+    pub errors: Vec<String>,
+    #[schemars(description = "Warnings or additional information")]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct ListToolsByCategoryParams {
+    /// Optional. Defaults to all categories.
+    #[serde(default)]
+    #[schemars(description = "Optional category to list: 'finance', 'tax', 'eligibility', 'governance' or 'admin'; all categories if omitted")]
+    pub category: Option<String>,
+    /// Optional. Defaults to the current response schema version.
+    #[serde(default)]
+    #[schemars(description = "Optional response schema version: 2 (default, versioned envelope) or 1 (legacy bare payload)")]
+    pub response_version: Option<FlexibleI32>,
+    /// Optional. Defaults to expert.
+    #[serde(default)]
+    #[schemars(description = "Optional explanation audience: 'expert' (default, technical step-by-step) or 'citizen' (plain-language narrative for the public)")]
+    pub audience: Option<String>,
+    /// Optional. Echoed back in the response envelope.
+    #[serde(default)]
+    #[schemars(description = "Optional request context (case_id, requester, channel) echoed back in the response envelope and attached to log records")]
+    pub context: Option<RequestContext>,
+    /// Optional. Defaults to the whole payload.
+    #[serde(default)]
+    #[schemars(description = "Optional list of payload fields to return, e.g. ['tax', 'errors']; nested fields as 'distribution.senior'. Omit for the whole payload")]
+    pub fields: Option<Vec<String>>,
+    /// Optional. Defaults to false.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set true, after confirming with the user, to accept inputs beyond the configured soft limits (such as days_late above 3650 or income above 10M); otherwise such calls return needs_confirmation")]
+    pub acknowledge_warnings: Option<FlexibleBool>,
+    /// Optional. No graph by default.
+    #[serde(default)]
+    #[schemars(description = "Optional 'mermaid' or 'dot' to also return the calculation steps as a graph in the envelope's explanation_graph (inputs, rules and values as nodes, data flow as edges), for drawing an audit trail")]
+    pub explanation_graph: Option<String>,
+    /// Optional. Defaults to ENGINE_NUMBER_LOCALE, or plain numbers.
+    #[serde(default)]
+    #[schemars(description = "Optional locale such as 'de-DE' or 'en-US' for the numbers in explanations (decimal mark, digit grouping, currency symbol); the default writes 1234.56")]
+    pub locale: Option<String>,
+    /// Optional. Defaults to true.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set false in batch and simulation runs to skip building the explanation; it is then returned empty and the call is faster")]
+    pub include_explanations: Option<FlexibleBool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct ToolSummary {
+    #[schemars(description = "Tool name")]
+    pub name: String,
+    #[schemars(description = "Human-readable tool title")]
+    pub title: String,
+    #[schemars(description = "One-line description of how the tool derives its result")]
+    pub logic: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct ToolCategoryListing {
+    #[schemars(description = "Category name")]
+    pub category: ToolCategory,
+    #[schemars(description = "Tools in the category, in registration order")]
+    pub tools: Vec<ToolSummary>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct ListToolsByCategoryResponse {
+    #[schemars(description = "Categories with their tools")]
+    pub categories: Vec<ToolCategoryListing>,
+    #[schemars(description = "Number of tools listed")]
+    pub total_tools: usize,
+    #[schemars(description = "Explanation of calculation steps")]
+    pub explanation: String,
+    #[serde(default)]
+    #[schemars(description = "Assumptions made while reading the parameters (lenient parsing, defaults applied)")]
+    pub assumptions: Vec<Assumption>,
+    #[schemars(description = "Any errors in input validation")]
+    pub errors: Vec<String>,
+    #[schemars(description = "Warnings or additional information")]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct DescribeCapabilitiesParams {
+    /// Optional. Defaults to the current response schema version.
+    #[serde(default)]
+    #[schemars(description = "Optional response schema version: 2 (default, versioned envelope) or 1 (legacy bare payload)")]
+    pub response_version: Option<FlexibleI32>,
+    /// Optional. Defaults to expert.
+    #[serde(default)]
+    #[schemars(description = "Optional explanation audience: 'expert' (default, technical step-by-step) or 'citizen' (plain-language narrative for the public)")]
+    pub audience: Option<String>,
+    /// Optional. Echoed back in the response envelope.
+    #[serde(default)]
+    #[schemars(description = "Optional request context (case_id, requester, channel) echoed back in the response envelope and attached to log records")]
+    pub context: Option<RequestContext>,
+    /// Optional. Defaults to the whole payload.
+    #[serde(default)]
+    #[schemars(description = "Optional list of payload fields to return, e.g. ['tax', 'errors']; nested fields as 'distribution.senior'. Omit for the whole payload")]
+    pub fields: Option<Vec<String>>,
+    /// Optional. Defaults to false.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set true, after confirming with the user, to accept inputs beyond the configured soft limits (such as days_late above 3650 or income above 10M); otherwise such calls return needs_confirmation")]
+    pub acknowledge_warnings: Option<FlexibleBool>,
+    /// Optional. No graph by default.
+    #[serde(default)]
+    #[schemars(description = "Optional 'mermaid' or 'dot' to also return the calculation steps as a graph in the envelope's explanation_graph (inputs, rules and values as nodes, data flow as edges), for drawing an audit trail")]
+    pub explanation_graph: Option<String>,
+    /// Optional. Defaults to ENGINE_NUMBER_LOCALE, or plain numbers.
+    #[serde(default)]
+    #[schemars(description = "Optional locale such as 'de-DE' or 'en-US' for the numbers in explanations (decimal mark, digit grouping, currency symbol); the default writes 1234.56")]
+    pub locale: Option<String>,
+    /// Optional. Defaults to true.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set false in batch and simulation runs to skip building the explanation; it is then returned empty and the call is faster")]
+    pub include_explanations: Option<FlexibleBool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct DescribeCapabilitiesResponse {
+    #[schemars(description = "Whether the client has completed initialize in this session")]
+    pub negotiated: bool,
+    #[schemars(description = "Negotiated client profile; defaults (JSON text, inline results) when not negotiated")]
+    pub client: Option<ClientProfile>,
+    #[schemars(description = "Whether results carry structuredContent next to the JSON text block")]
+    pub structured_content: bool,
+    #[schemars(description = "Whether results are returned as resource links instead of inline")]
+    pub resource_links: bool,
+    #[schemars(description = "Whether results get a plain-language summary from the client's model (ENGINE_SAMPLING_SUMMARY and client sampling support)")]
+    pub sampling_summary: bool,
+    #[schemars(description = "Explanation of calculation steps")]
+    pub explanation: String,
+    #[serde(default)]
+    #[schemars(description = "Assumptions made while reading the parameters (lenient parsing, defaults applied)")]
+    pub assumptions: Vec<Assumption>,
+    #[schemars(description = "Any errors in input validation")]
+    pub errors: Vec<String>,
+    #[schemars(description = "Warnings or additional information")]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct GetRecentSchemaFailuresParams {
+    /// Optional. Defaults to all tools.
+    #[serde(default)]
+    #[schemars(description = "Optional tool name to report on; all tools if omitted")]
+    pub tool: Option<String>,
+    /// Optional. Defaults to 20.
+    #[serde(default)]
+    #[schemars(description = "Optional number of most recent failures to list (default 20)")]
+    pub limit: Option<FlexibleI32>,
+    /// Optional. Defaults to the current response schema version.
+    #[serde(default)]
+    #[schemars(description = "Optional response schema version: 2 (default, versioned envelope) or 1 (legacy bare payload)")]
+    pub response_version: Option<FlexibleI32>,
+    /// Optional. Defaults to expert.
+    #[serde(default)]
+    #[schemars(description = "Optional explanation audience: 'expert' (default, technical step-by-step) or 'citizen' (plain-language narrative for the public)")]
+    pub audience: Option<String>,
+    /// Optional. Echoed back in the response envelope.
+    #[serde(default)]
+    #[schemars(description = "Optional request context (case_id, requester, channel) echoed back in the response envelope and attached to log records")]
+    pub context: Option<RequestContext>,
+    /// Optional. Defaults to the whole payload.
+    #[serde(default)]
+    #[schemars(description = "Optional list of payload fields to return, e.g. ['tax', 'errors']; nested fields as 'distribution.senior'. Omit for the whole payload")]
+    pub fields: Option<Vec<String>>,
+    /// Optional. Defaults to false.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set true, after confirming with the user, to accept inputs beyond the configured soft limits (such as days_late above 3650 or income above 10M); otherwise such calls return needs_confirmation")]
+    pub acknowledge_warnings: Option<FlexibleBool>,
+    /// Optional. No graph by default.
+    #[serde(default)]
+    #[schemars(description = "Optional 'mermaid' or 'dot' to also return the calculation steps as a graph in the envelope's explanation_graph (inputs, rules and values as nodes, data flow as edges), for drawing an audit trail")]
+    pub explanation_graph: Option<String>,
+    /// Optional. Defaults to ENGINE_NUMBER_LOCALE, or plain numbers.
+    #[serde(default)]
+    #[schemars(description = "Optional locale such as 'de-DE' or 'en-US' for the numbers in explanations (decimal mark, digit grouping, currency symbol); the default writes 1234.56")]
+    pub locale: Option<String>,
+    /// Optional. Defaults to true.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set false in batch and simulation runs to skip building the explanation; it is then returned empty and the call is faster")]
+    pub include_explanations: Option<FlexibleBool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct SchemaFailureReport {
+    #[schemars(description = "Tool that rejected the arguments")]
+    pub tool: String,
+    #[schemars(description = "When the call arrived, RFC 3339")]
+    pub at: String,
+    #[schemars(description = "Deserialization error with quoted values and numbers blanked out")]
+    pub error: String,
+    #[schemars(description = "Arguments as sent, with each value replaced by its JSON type name")]
+    pub shape: serde_json::Value,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct GetRecentSchemaFailuresResponse {
+    #[schemars(description = "Most recent failures first")]
+    pub failures: Vec<SchemaFailureReport>,
+    #[schemars(description = "Failures held in the buffer for the selected tools")]
+    pub total_recorded: usize,
+    #[schemars(description = "Explanation of calculation steps")]
+    pub explanation: String,
+    #[serde(default)]
+    #[schemars(description = "Assumptions made while reading the parameters (lenient parsing, defaults applied)")]
+    pub assumptions: Vec<Assumption>,
+    #[schemars(description = "Any errors in input validation")]
+    pub errors: Vec<String>,
+    #[schemars(description = "Warnings or additional information")]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct UpsertProfileParams {
+    /// Required. Replaces the whole profile in force.
+    #[schemars(description = "Full profile as printed by --migrate-config: settings by name (such as ENGINE_TAX_BRACKETS or ENGINE_DEFAULT_CAP), each with its value and optionally its source and a note. Replaces the whole profile in force; settings left out take their defaults")]
+    pub profile: BTreeMap<String, ProfileSetting>,
+    /// Optional. Defaults to the current response schema version.
+    #[serde(default)]
+    #[schemars(description = "Optional response schema version: 2 (default, versioned envelope) or 1 (legacy bare payload)")]
+    pub response_version: Option<FlexibleI32>,
+    /// Optional. Defaults to expert.
+    #[serde(default)]
+    #[schemars(description = "Optional explanation audience: 'expert' (default, technical step-by-step) or 'citizen' (plain-language narrative for the public)")]
+    pub audience: Option<String>,
+    /// Optional. Echoed back in the response envelope.
+    #[serde(default)]
+    #[schemars(description = "Optional request context (case_id, requester, channel) echoed back in the response envelope and attached to log records")]
+    pub context: Option<RequestContext>,
+    /// Optional. Defaults to the whole payload.
+    #[serde(default)]
+    #[schemars(description = "Optional list of payload fields to return, e.g. ['tax', 'errors']; nested fields as 'distribution.senior'. Omit for the whole payload")]
+    pub fields: Option<Vec<String>>,
+    /// Optional. Defaults to false.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set true, after confirming with the user, to accept inputs beyond the configured soft limits (such as days_late above 3650 or income above 10M); otherwise such calls return needs_confirmation")]
+    pub acknowledge_warnings: Option<FlexibleBool>,
+    /// Optional. No graph by default.
+    #[serde(default)]
+    #[schemars(description = "Optional 'mermaid' or 'dot' to also return the calculation steps as a graph in the envelope's explanation_graph (inputs, rules and values as nodes, data flow as edges), for drawing an audit trail")]
+    pub explanation_graph: Option<String>,
+    /// Optional. Defaults to ENGINE_NUMBER_LOCALE, or plain numbers.
+    #[serde(default)]
+    #[schemars(description = "Optional locale such as 'de-DE' or 'en-US' for the numbers in explanations (decimal mark, digit grouping, currency symbol); the default writes 1234.56")]
+    pub locale: Option<String>,
+    /// Optional. Defaults to true.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set false in batch and simulation runs to skip building the explanation; it is then returned empty and the call is faster")]
+    pub include_explanations: Option<FlexibleBool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct UpsertProfileResponse {
+    #[schemars(description = "Whether the profile was saved and put in force")]
+    pub applied: bool,
+    #[schemars(description = "Number of settings in the profile")]
+    pub settings: usize,
+    #[schemars(description = "Configuration version before the call")]
+    pub previous_version: String,
+    #[schemars(description = "Configuration version in force after the call; it changes whenever the effective rules change")]
+    pub config_version: String,
+    #[schemars(description = "Settings of the profile overridden by environment variables, which take precedence")]
+    pub overridden: Vec<String>,
+    #[schemars(description = "Explanation of calculation steps")]
+    pub explanation: String,
+    #[serde(default)]
+    #[schemars(description = "Assumptions made while reading the parameters (lenient parsing, defaults applied)")]
+    pub assumptions: Vec<Assumption>,
+    #[schemars(description = "Any errors in input validation")]
+    pub errors: Vec<String>,
+    #[schemars(description = "Warnings or additional information")]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct DiffProfilesParams {
+    /// Required. The profile in force or under review.
+    #[schemars(description = "Profile before the change, as printed by --migrate-config: settings by name, each with its value and optionally its source and a note")]
+    pub before: BTreeMap<String, ProfileSetting>,
+    /// Required. The proposed profile.
+    #[schemars(description = "Profile after the change, in the same format as before")]
+    pub after: BTreeMap<String, ProfileSetting>,
+    /// Optional. Defaults to the current response schema version.
+    #[serde(default)]
+    #[schemars(description = "Optional response schema version: 2 (default, versioned envelope) or 1 (legacy bare payload)")]
+    pub response_version: Option<FlexibleI32>,
+    /// Optional. Defaults to expert.
+    #[serde(default)]
+    #[schemars(description = "Optional explanation audience: 'expert' (default, technical step-by-step) or 'citizen' (plain-language narrative for the public)")]
+    pub audience: Option<String>,
+    /// Optional. Echoed back in the response envelope.
+    #[serde(default)]
+    #[schemars(description = "Optional request context (case_id, requester, channel) echoed back in the response envelope and attached to log records")]
+    pub context: Option<RequestContext>,
+    /// Optional. Defaults to the whole payload.
+    #[serde(default)]
+    #[schemars(description = "Optional list of payload fields to return, e.g. ['tax', 'errors']; nested fields as 'distribution.senior'. Omit for the whole payload")]
+    pub fields: Option<Vec<String>>,
+    /// Optional. Defaults to false.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set true, after confirming with the user, to accept inputs beyond the configured soft limits (such as days_late above 3650 or income above 10M); otherwise such calls return needs_confirmation")]
+    pub acknowledge_warnings: Option<FlexibleBool>,
+    /// Optional. No graph by default.
+    #[serde(default)]
+    #[schemars(description = "Optional 'mermaid' or 'dot' to also return the calculation steps as a graph in the envelope's explanation_graph (inputs, rules and values as nodes, data flow as edges), for drawing an audit trail")]
+    pub explanation_graph: Option<String>,
+    /// Optional. Defaults to ENGINE_NUMBER_LOCALE, or plain numbers.
+    #[serde(default)]
+    #[schemars(description = "Optional locale such as 'de-DE' or 'en-US' for the numbers in explanations (decimal mark, digit grouping, currency symbol); the default writes 1234.56")]
+    pub locale: Option<String>,
+    /// Optional. Defaults to true.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set false in batch and simulation runs to skip building the explanation; it is then returned empty and the call is faster")]
+    pub include_explanations: Option<FlexibleBool>,
+}
+
+/// One bracket that differs between two bracket lists, numbered from the lowest
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct BracketChange {
+    #[schemars(description = "Bracket number, 1 for the lowest")]
+    pub bracket: usize,
+    #[schemars(description = "'added', 'removed' or 'changed'")]
+    pub change: String,
+    #[schemars(description = "Upper limit before the change; null for the top bracket or an added bracket")]
+    pub before_up_to: Option<f64>,
+    #[schemars(description = "Upper limit after the change; null for the top bracket or a removed bracket")]
+    pub after_up_to: Option<f64>,
+    #[schemars(description = "Rate before the change; null for an added bracket")]
+    pub before_rate: Option<f64>,
+    #[schemars(description = "Rate after the change; null for a removed bracket")]
+    pub after_rate: Option<f64>,
+    #[schemars(description = "Whether the bracket was a cliff bracket before the change")]
+    pub before_cliff: Option<bool>,
+    #[schemars(description = "Whether the bracket is a cliff bracket after the change")]
+    pub after_cliff: Option<bool>,
+    #[schemars(description = "The change in words")]
+    pub summary: String,
+}
+
+/// One setting that differs between two profiles
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct SettingChange {
+    #[schemars(description = "Setting name, such as ENGINE_TAX_BRACKETS")]
+    pub setting: String,
+    #[schemars(description = "'added', 'removed' or 'changed'")]
+    pub change: String,
+    #[schemars(description = "Value before the change, as read from the environment; null if added")]
+    pub before: Option<String>,
+    #[schemars(description = "Value after the change, as read from the environment; null if removed")]
+    pub after: Option<String>,
+    #[schemars(description = "For bracket lists, the brackets that differ")]
+    pub brackets: Vec<BracketChange>,
+    #[schemars(description = "The change in words")]
+    pub summary: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct DiffProfilesResponse {
+    #[schemars(description = "Settings that differ, by name")]
+    pub changes: Vec<SettingChange>,
+    #[schemars(description = "Number of settings present in both profiles with the same value")]
+    pub unchanged: usize,
+    #[schemars(description = "Explanation of calculation steps")]
+    pub explanation: String,
+    #[serde(default)]
+    #[schemars(description = "Assumptions made while reading the parameters (lenient parsing, defaults applied)")]
+    pub assumptions: Vec<Assumption>,
+    #[schemars(description = "Any errors in input validation")]
+    pub errors: Vec<String>,
+    #[schemars(description = "Warnings or additional information")]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct EvaluateRangesParams {
+    #[schemars(description = "Tool to evaluate, e.g. 'check_housing_grant' or 'calc_tax'; any calculation outside the admin category")]
+    pub tool: String,
+    #[schemars(description = "The tool's arguments, with each uncertain number given as a range object, e.g. {\"income\": {\"min\": 38000, \"max\": 42000}, \"ami\": 80000}")]
+    pub arguments: BTreeMap<String, serde_json::Value>,
+    /// Optional. No export by default.
+    #[serde(default)]
+    #[schemars(description = "Optional 'parquet' to also write one row per call made (varied arguments and result fields) to a Parquet file on the server for analytics tools; its path is returned in export")]
+    pub export: Option<String>,
+    /// Optional. Defaults to the current response schema version.
+    #[serde(default)]
+    #[schemars(description = "Optional response schema version: 2 (default, versioned envelope) or 1 (legacy bare payload)")]
+    pub response_version: Option<FlexibleI32>,
+    /// Optional. Defaults to expert.
+    #[serde(default)]
+    #[schemars(description = "Optional explanation audience: 'expert' (default, technical step-by-step) or 'citizen' (plain-language narrative for the public)")]
+    pub audience: Option<String>,
+    /// Optional. Echoed back in the response envelope.
+    #[serde(default)]
+    #[schemars(description = "Optional request context (case_id, requester, channel) echoed back in the response envelope and attached to log records")]
+    pub context: Option<RequestContext>,
+    /// Optional. Defaults to the whole payload.
+    #[serde(default)]
+    #[schemars(description = "Optional list of payload fields to return, e.g. ['tax', 'errors']; nested fields as 'distribution.senior'. Omit for the whole payload")]
+    pub fields: Option<Vec<String>>,
+    /// Optional. Defaults to false.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set true, after confirming with the user, to accept inputs beyond the configured soft limits (such as days_late above 3650 or income above 10M); otherwise such calls return needs_confirmation")]
+    pub acknowledge_warnings: Option<FlexibleBool>,
+    /// Optional. No graph by default.
+    #[serde(default)]
+    #[schemars(description = "Optional 'mermaid' or 'dot' to also return the calculation steps as a graph in the envelope's explanation_graph (inputs, rules and values as nodes, data flow as edges), for drawing an audit trail")]
+    pub explanation_graph: Option<String>,
+    /// Optional. Defaults to ENGINE_NUMBER_LOCALE, or plain numbers.
+    #[serde(default)]
+    #[schemars(description = "Optional locale such as 'de-DE' or 'en-US' for the numbers in explanations (decimal mark, digit grouping, currency symbol); the default writes 1234.56")]
+    pub locale: Option<String>,
+    /// Optional. Defaults to true.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set false in batch and simulation runs to skip building the explanation; it is then returned empty and the call is faster")]
+    pub include_explanations: Option<FlexibleBool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct InputRange {
+    #[schemars(description = "Argument given as a range")]
+    pub parameter: String,
+    #[schemars(description = "Lower end of the range")]
+    pub min: f64,
+    #[schemars(description = "Upper end of the range")]
+    pub max: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct OutcomeRange {
+    #[schemars(description = "Result field of the evaluated tool; nested fields as 'distribution.senior'")]
+    pub field: String,
+    #[schemars(description = "Lowest value over the range ends (false for a yes/no field that is false anywhere)")]
+    pub min: serde_json::Value,
+    #[schemars(description = "Highest value over the range ends (true for a yes/no field that is true anywhere)")]
+    pub max: serde_json::Value,
+    #[schemars(description = "Whether the field takes different values within the ranges")]
+    pub varies: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct EvaluateRangesResponse {
+    #[schemars(description = "Tool evaluated")]
+    pub tool: String,
+    #[schemars(description = "Arguments given as ranges")]
+    pub ranges: Vec<InputRange>,
+    #[schemars(description = "Number of calls made: one per combination of range ends")]
+    pub evaluations: usize,
+    #[schemars(description = "Range of every numeric and yes/no result field")]
+    pub outcomes: Vec<OutcomeRange>,
+    #[schemars(description = "Yes/no result fields that flip within the ranges (e.g. 'eligible'); empty if every decision holds across them")]
+    pub flips: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Export file written when export was requested")]
+    pub export: Option<ExportedFile>,
+    #[schemars(description = "Explanation of calculation steps")]
+    pub explanation: String,
+    #[serde(default)]
+    #[schemars(description = "Assumptions made while reading the parameters (lenient parsing, defaults applied)")]
+    pub assumptions: Vec<Assumption>,
+    #[schemars(description = "Any errors in input validation")]
+    pub errors: Vec<String>,
+    #[schemars(description = "Warnings or additional information")]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct SimulateUncertaintyParams {
+    #[schemars(description = "Tool to simulate, e.g. 'check_housing_grant' or 'calc_tax'; any calculation outside the admin category")]
+    pub tool: String,
+    #[schemars(description = "The tool's arguments, with each uncertain number given as a distribution: {\"distribution\": \"normal\", \"mean\": 40000, \"sd\": 2000} or {\"distribution\": \"uniform\", \"min\": 38000, \"max\": 42000}")]
+    pub arguments: BTreeMap<String, serde_json::Value>,
+    #[serde(default)]
+    #[schemars(description = "Optional number of draws (default 1000, at most 10000)")]
+    pub draws: Option<FlexibleI32>,
+    #[serde(default)]
+    #[schemars(description = "Optional random seed (default 1); the same seed and inputs give the same draws")]
+    pub seed: Option<FlexibleI32>,
+    /// Optional. No export by default.
+    #[serde(default)]
+    #[schemars(description = "Optional 'parquet' to also write one row per call made (varied arguments and result fields) to a Parquet file on the server for analytics tools; its path is returned in export")]
+    pub export: Option<String>,
+    /// Optional. Defaults to the current response schema version.
+    #[serde(default)]
+    #[schemars(description = "Optional response schema version: 2 (default, versioned envelope) or 1 (legacy bare payload)")]
+    pub response_version: Option<FlexibleI32>,
+    /// Optional. Defaults to expert.
+    #[serde(default)]
+    #[schemars(description = "Optional explanation audience: 'expert' (default, technical step-by-step) or 'citizen' (plain-language narrative for the public)")]
+    pub audience: Option<String>,
+    /// Optional. Echoed back in the response envelope.
+    #[serde(default)]
+    #[schemars(description = "Optional request context (case_id, requester, channel) echoed back in the response envelope and attached to log records")]
+    pub context: Option<RequestContext>,
+    /// Optional. Defaults to the whole payload.
+    #[serde(default)]
+    #[schemars(description = "Optional list of payload fields to return, e.g. ['tax', 'errors']; nested fields as 'distribution.senior'. Omit for the whole payload")]
+    pub fields: Option<Vec<String>>,
+    /// Optional. Defaults to false.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set true, after confirming with the user, to accept inputs beyond the configured soft limits (such as days_late above 3650 or income above 10M); otherwise such calls return needs_confirmation")]
+    pub acknowledge_warnings: Option<FlexibleBool>,
+    /// Optional. No graph by default.
+    #[serde(default)]
+    #[schemars(description = "Optional 'mermaid' or 'dot' to also return the calculation steps as a graph in the envelope's explanation_graph (inputs, rules and values as nodes, data flow as edges), for drawing an audit trail")]
+    pub explanation_graph: Option<String>,
+    /// Optional. Defaults to ENGINE_NUMBER_LOCALE, or plain numbers.
+    #[serde(default)]
+    #[schemars(description = "Optional locale such as 'de-DE' or 'en-US' for the numbers in explanations (decimal mark, digit grouping, currency symbol); the default writes 1234.56")]
+    pub locale: Option<String>,
+    /// Optional. Defaults to true.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set false in batch and simulation runs to skip building the explanation; it is then returned empty and the call is faster")]
+    pub include_explanations: Option<FlexibleBool>,
+}
+
+/// Distribution an uncertain input is drawn from
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+#[serde(tag = "distribution", rename_all = "lowercase")]
+pub enum Distribution {
+    /// Normal distribution with mean and standard deviation
+    Normal { mean: f64, sd: f64 },
+    /// Every value from min to max equally likely
+    Uniform { min: f64, max: f64 },
+}
+
+impl Distribution {
+    /// Read a distribution of `kind`, getting each of its parameters from `number`
+    fn parse(kind: &str, mut number: impl FnMut(&str) -> Result<f64, String>) -> Result<Self, String> {
+        match kind.trim().to_ascii_lowercase().as_str() {
+            "normal" => Ok(Distribution::Normal { mean: number("mean")?, sd: number("sd")? }),
+            "uniform" => Ok(Distribution::Uniform { min: number("min")?, max: number("max")? }),
+            other => Err(format!("unknown distribution '{}'; expected normal or uniform", sanitize_for_error_message(other))),
+        }
+    }
+
+    fn check(&self) -> Result<(), String> {
+        match *self {
+            Distribution::Normal { sd, .. } if sd < 0.0 => Err(format!("sd {} must not be negative", sd)),
+            Distribution::Uniform { min, max } if min > max => Err(format!("min {} is above max {}", min, max)),
+            _ => Ok(()),
+        }
+    }
+
+    fn sample(&self, rng: &mut StdRng) -> f64 {
+        match *self {
+            Distribution::Normal { mean, sd } => {
+                // Box-Muller transform; 1 - u keeps the logarithm finite
+                let u: f64 = 1.0 - rng.random::<f64>();
+                let v: f64 = rng.random();
+                mean + sd * (-2.0 * u.ln()).sqrt() * (std::f64::consts::TAU * v).cos()
+            }
+            Distribution::Uniform { min, max } => min + (max - min) * rng.random::<f64>(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct InputDistribution {
+    #[schemars(description = "Argument drawn from a distribution")]
+    pub parameter: String,
+    #[serde(flatten)]
+    pub distribution: Distribution,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct OutcomeDistribution {
+    #[schemars(description = "Numeric result field of the simulated tool; nested fields as 'distribution.senior'")]
+    pub field: String,
+    #[schemars(description = "Average over the draws")]
+    pub mean: f64,
+    #[schemars(description = "5th percentile")]
+    pub p5: f64,
+    #[schemars(description = "25th percentile")]
+    pub p25: f64,
+    #[schemars(description = "Median")]
+    pub p50: f64,
+    #[schemars(description = "75th percentile")]
+    pub p75: f64,
+    #[schemars(description = "95th percentile")]
+    pub p95: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct OutcomeProbability {
+    #[schemars(description = "Yes/no result field of the simulated tool, e.g. 'eligible'")]
+    pub field: String,
+    #[schemars(description = "Share of draws in which the field is true (0 to 1)")]
+    pub probability: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct SimulateUncertaintyResponse {
+    #[schemars(description = "Tool simulated")]
+    pub tool: String,
+    #[schemars(description = "Arguments drawn from distributions")]
+    pub inputs: Vec<InputDistribution>,
+    #[schemars(description = "Number of draws")]
+    pub draws: usize,
+    #[schemars(description = "Random seed used")]
+    pub seed: u64,
+    #[schemars(description = "Percentiles of every numeric result field")]
+    pub outcomes: Vec<OutcomeDistribution>,
+    #[schemars(description = "Probability of every yes/no result field being true, e.g. the probability of eligibility")]
+    pub probabilities: Vec<OutcomeProbability>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Export file written when export was requested")]
+    pub export: Option<ExportedFile>,
+    #[schemars(description = "Explanation of calculation steps")]
+    pub explanation: String,
+    #[serde(default)]
+    #[schemars(description = "Assumptions made while reading the parameters (lenient parsing, defaults applied)")]
+    pub assumptions: Vec<Assumption>,
+    #[schemars(description = "Any errors in input validation")]
+    pub errors: Vec<String>,
+    #[schemars(description = "Warnings or additional information")]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct CheckAppealDeadlineParams {
+    #[schemars(description = "Date the decision was issued (YYYY-MM-DD)")]
+    pub decision_date: String,
+    /// Optional. Defaults to today's date.
+    #[serde(default)]
+    #[schemars(description = "Optional date the appeal was or will be filed (YYYY-MM-DD); defaults to today")]
+    pub filing_date: Option<String>,
+    /// Optional. Defaults to ENGINE_APPEAL_WINDOW_DAYS.
+    #[serde(default)]
+    #[schemars(description = "Optional length of the appeal window in days; uses the statutory default if omitted")]
+    pub window_days: Option<FlexibleI32>,
+    /// Optional. Defaults to ENGINE_APPEAL_BUSINESS_DAYS.
+    #[serde(default)]
+    #[schemars(description = "Optional. true to count the window in business days (weekends and public holidays skipped), false to count calendar days with a deadline on a weekend or holiday moved to the next business day; uses the statutory default if omitted")]
+    pub business_days: Option<FlexibleBool>,
+    /// Optional. Defaults to the current response schema version.
+    #[serde(default)]
+    #[schemars(description = "Optional response schema version: 2 (default, versioned envelope) or 1 (legacy bare payload)")]
+    pub response_version: Option<FlexibleI32>,
+    /// Optional. Defaults to expert.
+    #[serde(default)]
+    #[schemars(description = "Optional explanation audience: 'expert' (default, technical step-by-step) or 'citizen' (plain-language narrative for the public)")]
+    pub audience: Option<String>,
+    /// Optional. Echoed back in the response envelope.
+    #[serde(default)]
+    #[schemars(description = "Optional request context (case_id, requester, channel) echoed back in the response envelope and attached to log records")]
+    pub context: Option<RequestContext>,
+    /// Optional. Defaults to the whole payload.
+    #[serde(default)]
+    #[schemars(description = "Optional list of payload fields to return, e.g. ['tax', 'errors']; nested fields as 'distribution.senior'. Omit for the whole payload")]
+    pub fields: Option<Vec<String>>,
+    /// Optional. Defaults to false.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set true, after confirming with the user, to accept inputs beyond the configured soft limits (such as days_late above 3650 or income above 10M); otherwise such calls return needs_confirmation")]
+    pub acknowledge_warnings: Option<FlexibleBool>,
+    /// Optional. No graph by default.
+    #[serde(default)]
+    #[schemars(description = "Optional 'mermaid' or 'dot' to also return the calculation steps as a graph in the envelope's explanation_graph (inputs, rules and values as nodes, data flow as edges), for drawing an audit trail")]
+    pub explanation_graph: Option<String>,
+    /// Optional. Defaults to ENGINE_NUMBER_LOCALE, or plain numbers.
+    #[serde(default)]
+    #[schemars(description = "Optional locale such as 'de-DE' or 'en-US' for the numbers in explanations (decimal mark, digit grouping, currency symbol); the default writes 1234.56")]
+    pub locale: Option<String>,
+    /// Optional. Defaults to true.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set false in batch and simulation runs to skip building the explanation; it is then returned empty and the call is faster")]
+    pub include_explanations: Option<FlexibleBool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct CheckAppealDeadlineResponse {
+    #[schemars(description = "Whether the appeal is filed on or before the deadline")]
+    pub within_window: bool,
+    #[schemars(description = "Last day to file the appeal (YYYY-MM-DD)")]
+    pub deadline: String,
+    #[schemars(description = "Calendar days from the filing date to the deadline; 0 when overdue")]
+    pub days_remaining: i64,
+    #[schemars(description = "Calendar days from the deadline to the filing date; 0 when in time")]
+    pub days_overdue: i64,
+    #[schemars(description = "Length of the appeal window in days")]
+    pub window_days: i32,
+    #[schemars(description = "Whether the window was counted in business days")]
+    pub business_days: bool,
+    #[schemars(description = "Whether a calendar-day deadline fell on a weekend or public holiday and moved to the next business day")]
+    pub deadline_extended: bool,
+    #[schemars(description = "Explanation of calculation steps")]
+    pub explanation: String,
+    #[serde(default)]
+    #[schemars(description = "Assumptions made while reading the parameters (lenient parsing, defaults applied)")]
+    pub assumptions: Vec<Assumption>,
+    #[schemars(description = "Any errors in input validation")]
+    pub errors: Vec<String>,
+    #[schemars(description = "Warnings or additional information")]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct GetRequiredDocumentsParams {
+    #[schemars(description = "Program to list the documents for, e.g. 'housing_grant'")]
+    pub program: String,
+    /// Optional. Defaults to no special situation.
+    #[serde(default)]
+    #[schemars(description = "Optional situation flags of the applicant that call for extra documents, e.g. ['self_employed', 'large_household', 'non_resident']")]
+    pub situation: Option<Vec<String>>,
+    /// Optional. Defaults to the current response schema version.
+    #[serde(default)]
+    #[schemars(description = "Optional response schema version: 2 (default, versioned envelope) or 1 (legacy bare payload)")]
+    pub response_version: Option<FlexibleI32>,
+    /// Optional. Defaults to expert.
+    #[serde(default)]
+    #[schemars(description = "Optional explanation audience: 'expert' (default, technical step-by-step) or 'citizen' (plain-language narrative for the public)")]
+    pub audience: Option<String>,
+    /// Optional. Echoed back in the response envelope.
+    #[serde(default)]
+    #[schemars(description = "Optional request context (case_id, requester, channel) echoed back in the response envelope and attached to log records")]
+    pub context: Option<RequestContext>,
+    /// Optional. Defaults to the whole payload.
+    #[serde(default)]
+    #[schemars(description = "Optional list of payload fields to return, e.g. ['tax', 'errors']; nested fields as 'distribution.senior'. Omit for the whole payload")]
+    pub fields: Option<Vec<String>>,
+    /// Optional. Defaults to false.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set true, after confirming with the user, to accept inputs beyond the configured soft limits (such as days_late above 3650 or income above 10M); otherwise such calls return needs_confirmation")]
+    pub acknowledge_warnings: Option<FlexibleBool>,
+    /// Optional. No graph by default.
+    #[serde(default)]
+    #[schemars(description = "Optional 'mermaid' or 'dot' to also return the calculation steps as a graph in the envelope's explanation_graph (inputs, rules and values as nodes, data flow as edges), for drawing an audit trail")]
+    pub explanation_graph: Option<String>,
+    /// Optional. Defaults to ENGINE_NUMBER_LOCALE, or plain numbers.
+    #[serde(default)]
+    #[schemars(description = "Optional locale such as 'de-DE' or 'en-US' for the numbers in explanations (decimal mark, digit grouping, currency symbol); the default writes 1234.56")]
+    pub locale: Option<String>,
+    /// Optional. Defaults to true.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set false in batch and simulation runs to skip building the explanation; it is then returned empty and the call is faster")]
+    pub include_explanations: Option<FlexibleBool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct DocumentRequirement {
+    #[schemars(description = "Document to provide")]
+    pub document: String,
+    #[schemars(description = "Situation flag that calls for the document; absent when every applicant needs it")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub situation: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct GetRequiredDocumentsResponse {
+    #[schemars(description = "Program the documents are for")]
+    pub program: String,
+    #[schemars(description = "Documents the applicant must provide: those for every applicant first, then those for their situation")]
+    pub documents: Vec<DocumentRequirement>,
+    #[schemars(description = "Situation flags that add documents for this program")]
+    pub known_situations: Vec<String>,
+    #[schemars(description = "Explanation of calculation steps")]
+    pub explanation: String,
+    #[serde(default)]
+    #[schemars(description = "Assumptions made while reading the parameters (lenient parsing, defaults applied)")]
+    pub assumptions: Vec<Assumption>,
+    #[schemars(description = "Any errors in input validation")]
+    pub errors: Vec<String>,
+    #[schemars(description = "Warnings or additional information")]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct PrecinctTally {
+    #[schemars(description = "Precinct or site name")]
+    pub name: String,
+    #[schemars(description = "Eligible voters in the precinct")]
+    pub eligible_voters: FlexibleI32,
+    #[schemars(description = "Number of people who voted in the precinct")]
+    pub turnout: FlexibleI32,
+    #[schemars(description = "Yes votes in the precinct")]
+    pub yes_votes: FlexibleI32,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct CheckVotingBulkParams {
+    #[schemars(description = "Tallies per precinct: name, eligible_voters, turnout and yes_votes")]
+    pub precincts: Vec<PrecinctTally>,
+    #[schemars(description = "Type of proposal: 'general' or 'amendment'")]
+    pub proposal_type: String,
+    /// Optional. Defaults to the current response schema version.
+    #[serde(default)]
+    #[schemars(description = "Optional response schema version: 2 (default, versioned envelope) or 1 (legacy bare payload)")]
+    pub response_version: Option<FlexibleI32>,
+    /// Optional. Defaults to expert.
+    #[serde(default)]
+    #[schemars(description = "Optional explanation audience: 'expert' (default, technical step-by-step) or 'citizen' (plain-language narrative for the public)")]
+    pub audience: Option<String>,
+    /// Optional. Echoed back in the response envelope.
+    #[serde(default)]
+    #[schemars(description = "Optional request context (case_id, requester, channel) echoed back in the response envelope and attached to log records")]
+    pub context: Option<RequestContext>,
+    /// Optional. Defaults to the whole payload.
+    #[serde(default)]
+    #[schemars(description = "Optional list of payload fields to return, e.g. ['tax', 'errors']; nested fields as 'distribution.senior'. Omit for the whole payload")]
+    pub fields: Option<Vec<String>>,
+    /// Optional. Defaults to false.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set true, after confirming with the user, to accept inputs beyond the configured soft limits (such as days_late above 3650 or income above 10M); otherwise such calls return needs_confirmation")]
+    pub acknowledge_warnings: Option<FlexibleBool>,
+    /// Optional. No graph by default.
+    #[serde(default)]
+    #[schemars(description = "Optional 'mermaid' or 'dot' to also return the calculation steps as a graph in the envelope's explanation_graph (inputs, rules and values as nodes, data flow as edges), for drawing an audit trail")]
+    pub explanation_graph: Option<String>,
+    /// Optional. Defaults to ENGINE_NUMBER_LOCALE, or plain numbers.
+    #[serde(default)]
+    #[schemars(description = "Optional locale such as 'de-DE' or 'en-US' for the numbers in explanations (decimal mark, digit grouping, currency symbol); the default writes 1234.56")]
+    pub locale: Option<String>,
+    /// Optional. Defaults to true.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set false in batch and simulation runs to skip building the explanation; it is then returned empty and the call is faster")]
+    pub include_explanations: Option<FlexibleBool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct PrecinctResult {
+    #[schemars(description = "Precinct name")]
+    pub name: String,
+    #[schemars(description = "Eligible voters in the precinct")]
+    pub eligible_voters: i32,
+    #[schemars(description = "Number of people who voted in the precinct")]
+    pub turnout: i32,
+    #[schemars(description = "Yes votes in the precinct")]
+    pub yes_votes: i32,
+    #[schemars(description = "Precinct turnout as a share of its eligible voters (decimal)")]
+    pub turnout_percentage: f64,
+    #[schemars(description = "Yes votes as a share of the precinct turnout (decimal); 0 when nobody voted")]
+    pub approval_percentage: f64,
+    #[schemars(description = "Whether the precinct turnout alone meets the required turnout")]
+    pub met_quorum: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct CheckVotingBulkResponse {
+    #[schemars(description = "Whether the proposal passes on the totals over all precincts")]
+    pub passes: bool,
+    #[schemars(description = "Eligible voters over all precincts")]
+    pub eligible_voters: i32,
+    #[schemars(description = "Turnout over all precincts")]
+    pub turnout: i32,
+    #[schemars(description = "Yes votes over all precincts")]
+    pub yes_votes: i32,
+    #[schemars(description = "Total turnout as a share of total eligible voters (decimal)")]
+    pub turnout_percentage: f64,
+    #[schemars(description = "Total yes votes as a share of total turnout (decimal); 0 when nobody voted")]
+    pub approval_percentage: f64,
+    #[schemars(description = "Minimum turnout share required (decimal, inclusive), overall and for a precinct's quorum")]
+    pub required_turnout: f64,
+    #[schemars(description = "Approval share required (decimal): more than 0.5 for general proposals, at least 2/3 for amendments")]
+    pub required_approval: f64,
+    #[schemars(description = "Breakdown per precinct, in the order given")]
+    pub precincts: Vec<PrecinctResult>,
+    #[schemars(description = "Number of precincts whose own turnout meets the required turnout")]
+    pub precincts_meeting_quorum: usize,
+    #[schemars(description = "Explanation of calculation steps")]
+    pub explanation: String,
+    #[serde(default)]
+    #[schemars(description = "Assumptions made while reading the parameters (lenient parsing, defaults applied)")]
+    pub assumptions: Vec<Assumption>,
+    #[schemars(description = "Any errors in input validation")]
+    pub errors: Vec<String>,
+    #[schemars(description = "Warnings or additional information")]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct ProjectQuorumParams {
+    #[schemars(description = "Total number of eligible voters")]
+    pub eligible_voters: FlexibleI32,
+    #[schemars(description = "Number of people who have voted so far")]
+    pub turnout: FlexibleI32,
+    /// Optional. Defaults to 0.
+    #[serde(default)]
+    #[schemars(description = "Optional yes votes counted so far (default 0)")]
+    pub yes_votes: Option<FlexibleI32>,
+    /// Optional. Needed with hours_remaining for a projection.
+    #[serde(default)]
+    #[schemars(description = "Optional hours the vote has been open; with hours_remaining, projects the final turnout at the rate so far")]
+    pub hours_elapsed: Option<FlexibleF64>,
+    /// Optional. Needed with hours_elapsed for a projection.
+    #[serde(default)]
+    #[schemars(description = "Optional hours until the vote closes; with hours_elapsed, projects the final turnout at the rate so far")]
+    pub hours_remaining: Option<FlexibleF64>,
+    /// Optional. Defaults to the current response schema version.
+    #[serde(default)]
+    #[schemars(description = "Optional response schema version: 2 (default, versioned envelope) or 1 (legacy bare payload)")]
+    pub response_version: Option<FlexibleI32>,
+    /// Optional. Defaults to expert.
+    #[serde(default)]
+    #[schemars(description = "Optional explanation audience: 'expert' (default, technical step-by-step) or 'citizen' (plain-language narrative for the public)")]
+    pub audience: Option<String>,
+    /// Optional. Echoed back in the response envelope.
+    #[serde(default)]
+    #[schemars(description = "Optional request context (case_id, requester, channel) echoed back in the response envelope and attached to log records")]
+    pub context: Option<RequestContext>,
+    /// Optional. Defaults to the whole payload.
+    #[serde(default)]
+    #[schemars(description = "Optional list of payload fields to return, e.g. ['tax', 'errors']; nested fields as 'distribution.senior'. Omit for the whole payload")]
+    pub fields: Option<Vec<String>>,
+    /// Optional. Defaults to false.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set true, after confirming with the user, to accept inputs beyond the configured soft limits (such as days_late above 3650 or income above 10M); otherwise such calls return needs_confirmation")]
+    pub acknowledge_warnings: Option<FlexibleBool>,
+    /// Optional. No graph by default.
+    #[serde(default)]
+    #[schemars(description = "Optional 'mermaid' or 'dot' to also return the calculation steps as a graph in the envelope's explanation_graph (inputs, rules and values as nodes, data flow as edges), for drawing an audit trail")]
+    pub explanation_graph: Option<String>,
+    /// Optional. Defaults to ENGINE_NUMBER_LOCALE, or plain numbers.
+    #[serde(default)]
+    #[schemars(description = "Optional locale such as 'de-DE' or 'en-US' for the numbers in explanations (decimal mark, digit grouping, currency symbol); the default writes 1234.56")]
+    pub locale: Option<String>,
+    /// Optional. Defaults to true.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set false in batch and simulation runs to skip building the explanation; it is then returned empty and the call is faster")]
+    pub include_explanations: Option<FlexibleBool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct ProposalThreshold {
+    #[schemars(description = "Proposal type: 'general' or 'amendment'")]
+    pub proposal_type: String,
+    #[schemars(description = "Approval share required (decimal): more than 0.5 for general proposals, at least 2/3 for amendments")]
+    pub required_approval: f64,
+    #[schemars(description = "Fewest yes votes that pass this proposal type at turnout_basis")]
+    pub min_yes_votes: i32,
+    #[schemars(description = "Yes votes still needed beyond those counted so far")]
+    pub additional_yes_votes: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct ProjectQuorumResponse {
+    #[schemars(description = "Whether the turnout so far already meets quorum")]
+    pub quorum_met: bool,
+    #[schemars(description = "Minimum turnout share required (decimal, inclusive)")]
+    pub required_turnout: f64,
+    #[schemars(description = "Fewest votes that meet quorum")]
+    pub quorum_votes: i32,
+    #[schemars(description = "Votes still needed to reach quorum; 0 once it is met")]
+    pub additional_votes_needed: i32,
+    #[schemars(description = "Eligible voters who have not voted yet")]
+    pub outstanding_voters: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Present with hours_elapsed and hours_remaining: votes per hour so far")]
+    pub turnout_rate_per_hour: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Present with hours_elapsed and hours_remaining: final turnout if the rate so far holds, rounded down and at most eligible_voters")]
+    pub projected_turnout: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Present with hours_elapsed and hours_remaining: whether the projected turnout meets quorum")]
+    pub projected_quorum_met: Option<bool>,
+    #[schemars(description = "Turnout the yes vote minimums assume: the projected turnout (or the turnout so far), raised to quorum_votes if below it")]
+    pub turnout_basis: i32,
+    #[schemars(description = "Yes votes needed to pass, per proposal type")]
+    pub thresholds: Vec<ProposalThreshold>,
+    #[schemars(description = "Explanation of calculation steps")]
+    pub explanation: String,
+    #[serde(default)]
+    #[schemars(description = "Assumptions made while reading the parameters (lenient parsing, defaults applied)")]
+    pub assumptions: Vec<Assumption>,
+    #[schemars(description = "Any errors in input validation")]
+    pub errors: Vec<String>,
+    #[schemars(description = "Warnings or additional information")]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct StressConfigParams {
+    /// Optional. Defaults to the loaded tax brackets.
+    #[serde(default)]
+    #[schemars(description = "Optional candidate tax bracket table to vet, as JSON text in the ENGINE_TAX_BRACKETS format, e.g. '[{\"up_to\": 10000, \"rate\": 0.1}, {\"rate\": 0.3}]'; the loaded brackets if omitted")]
+    pub tax_brackets: Option<String>,
+    /// Optional. Defaults to the current response schema version.
+    #[serde(default)]
+    #[schemars(description = "Optional response schema version: 2 (default, versioned envelope) or 1 (legacy bare payload)")]
+    pub response_version: Option<FlexibleI32>,
+    /// Optional. Defaults to expert.
+    #[serde(default)]
+    #[schemars(description = "Optional explanation audience: 'expert' (default, technical step-by-step) or 'citizen' (plain-language narrative for the public)")]
+    pub audience: Option<String>,
+    /// Optional. Echoed back in the response envelope.
+    #[serde(default)]
+    #[schemars(description = "Optional request context (case_id, requester, channel) echoed back in the response envelope and attached to log records")]
+    pub context: Option<RequestContext>,
+    /// Optional. Defaults to the whole payload.
+    #[serde(default)]
+    #[schemars(description = "Optional list of payload fields to return, e.g. ['tax', 'errors']; nested fields as 'distribution.senior'. Omit for the whole payload")]
+    pub fields: Option<Vec<String>>,
+    /// Optional. Defaults to false.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set true, after confirming with the user, to accept inputs beyond the configured soft limits (such as days_late above 3650 or income above 10M); otherwise such calls return needs_confirmation")]
+    pub acknowledge_warnings: Option<FlexibleBool>,
+    /// Optional. No graph by default.
+    #[serde(default)]
+    #[schemars(description = "Optional 'mermaid' or 'dot' to also return the calculation steps as a graph in the envelope's explanation_graph (inputs, rules and values as nodes, data flow as edges), for drawing an audit trail")]
+    pub explanation_graph: Option<String>,
+    /// Optional. Defaults to ENGINE_NUMBER_LOCALE, or plain numbers.
+    #[serde(default)]
+    #[schemars(description = "Optional locale such as 'de-DE' or 'en-US' for the numbers in explanations (decimal mark, digit grouping, currency symbol); the default writes 1234.56")]
+    pub locale: Option<String>,
+    /// Optional. Defaults to true.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set false in batch and simulation runs to skip building the explanation; it is then returned empty and the call is faster")]
+    pub include_explanations: Option<FlexibleBool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct StressCheck {
+    #[schemars(description = "What was stressed: 'tax_brackets', 'tax_brackets_100' or 'penalty_10000_days'")]
+    pub name: String,
+    #[schemars(description = "Whether every case gave a finite result within its bounds that agrees with the reference implementation")]
+    pub passed: bool,
+    #[schemars(description = "Number of inputs computed")]
+    pub cases: usize,
+    #[schemars(description = "Time taken for all cases, in milliseconds")]
+    pub elapsed_ms: f64,
+    #[schemars(description = "Largest absolute difference from the integer-cent reference implementation over the cases it covers")]
+    pub max_deviation: f64,
+    #[schemars(description = "The first failing cases, if any")]
+    pub failures: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct StressConfigResponse {
+    #[schemars(description = "Whether every stress check passed")]
+    pub passed: bool,
+    #[schemars(description = "Tax brackets stressed: 'loaded configuration' or 'tax_brackets parameter'")]
+    pub source: String,
+    #[schemars(description = "Number of tax brackets in the stressed table")]
+    pub brackets: usize,
+    #[schemars(description = "Result per stress check")]
+    pub checks: Vec<StressCheck>,
+    #[schemars(description = "Explanation of calculation steps")]
+    pub explanation: String,
+    #[serde(default)]
+    #[schemars(description = "Assumptions made while reading the parameters (lenient parsing, defaults applied)")]
+    pub assumptions: Vec<Assumption>,
+    #[schemars(description = "Any errors in input validation")]
+    pub errors: Vec<String>,
+    #[schemars(description = "Warnings or additional information")]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct VerifyStoreIntegrityParams {
+    /// Optional. Treated as false when omitted.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set true when rotating store keys: results whose data keys are wrapped with an older key are re-wrapped with the current key, and unencrypted results are encrypted; defaults to false (check only)")]
+    pub rewrap: Option<FlexibleBool>,
+    /// Optional. Defaults to the current response schema version.
+    #[serde(default)]
+    #[schemars(description = "Optional response schema version: 2 (default, versioned envelope) or 1 (legacy bare payload)")]
+    pub response_version: Option<FlexibleI32>,
+    /// Optional. Defaults to expert.
+    #[serde(default)]
+    #[schemars(description = "Optional explanation audience: 'expert' (default, technical step-by-step) or 'citizen' (plain-language narrative for the public)")]
+    pub audience: Option<String>,
+    /// Optional. Echoed back in the response envelope.
+    #[serde(default)]
+    #[schemars(description = "Optional request context (case_id, requester, channel) echoed back in the response envelope and attached to log records")]
+    pub context: Option<RequestContext>,
+    /// Optional. Defaults to the whole payload.
+    #[serde(default)]
+    #[schemars(description = "Optional list of payload fields to return, e.g. ['tax', 'errors']; nested fields as 'distribution.senior'. Omit for the whole payload")]
+    pub fields: Option<Vec<String>>,
+    /// Optional. Defaults to false.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set true, after confirming with the user, to accept inputs beyond the configured soft limits (such as days_late above 3650 or income above 10M); otherwise such calls return needs_confirmation")]
+    pub acknowledge_warnings: Option<FlexibleBool>,
+    /// Optional. No graph by default.
+    #[serde(default)]
+    #[schemars(description = "Optional 'mermaid' or 'dot' to also return the calculation steps as a graph in the envelope's explanation_graph (inputs, rules and values as nodes, data flow as edges), for drawing an audit trail")]
+    pub explanation_graph: Option<String>,
+    /// Optional. Defaults to ENGINE_NUMBER_LOCALE, or plain numbers.
+    #[serde(default)]
+    #[schemars(description = "Optional locale such as 'de-DE' or 'en-US' for the numbers in explanations (decimal mark, digit grouping, currency symbol); the default writes 1234.56")]
+    pub locale: Option<String>,
+    /// Optional. Defaults to true.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set false in batch and simulation runs to skip building the explanation; it is then returned empty and the call is faster")]
+    pub include_explanations: Option<FlexibleBool>,
+}
+
+/// What an integrity check of the embedded store found
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StoreIntegrity {
+    pub schema_version: i64,
+    pub database_check: String,
+    pub encryption: bool,
+    pub current_key: Option<String>,
+    pub records: usize,
+    pub plaintext_records: usize,
+    pub records_by_key: BTreeMap<String, usize>,
+    pub failed_records: usize,
+    pub failures: Vec<String>,
+    pub rewrapped: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct VerifyStoreIntegrityResponse {
+    #[schemars(description = "Whether the database check passed and every stored result decrypts and authenticates")]
+    pub intact: bool,
+    #[schemars(description = "Schema version of the database")]
+    pub schema_version: i64,
+    #[schemars(description = "Result of SQLite's integrity check: 'ok' or the problems found")]
+    pub database_check: String,
+    #[schemars(description = "Whether store keys are configured, so new results are stored encrypted")]
+    pub encryption: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Id of the key that wraps the data keys of new results")]
+    pub current_key: Option<String>,
+    #[schemars(description = "Number of stored results")]
+    pub records: usize,
+    #[schemars(description = "Number of results stored unencrypted")]
+    pub plaintext_records: usize,
+    #[schemars(description = "Number of encrypted results per key id their data key is wrapped with")]
+    pub records_by_key: BTreeMap<String, usize>,
+    #[schemars(description = "Number of results that do not decrypt or authenticate")]
+    pub failed_records: usize,
+    #[schemars(description = "The first failing results, with the reason")]
+    pub failures: Vec<String>,
+    #[schemars(description = "Number of results re-wrapped with the current key or encrypted by this call")]
+    pub rewrapped: usize,
+    #[schemars(description = "Explanation of calculation steps")]
+    pub explanation: String,
+    #[serde(default)]
+    #[schemars(description = "Assumptions made while reading the parameters (lenient parsing, defaults applied)")]
+    pub assumptions: Vec<Assumption>,
+    #[schemars(description = "Any errors in input validation")]
+    pub errors: Vec<String>,
+    #[schemars(description = "Warnings or additional information")]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct PurgeExpiredDataParams {
+    /// Optional. Defaults to the current response schema version.
+    #[serde(default)]
+    #[schemars(description = "Optional response schema version: 2 (default, versioned envelope) or 1 (legacy bare payload)")]
+    pub response_version: Option<FlexibleI32>,
+    /// Optional. Defaults to expert.
+    #[serde(default)]
+    #[schemars(description = "Optional explanation audience: 'expert' (default, technical step-by-step) or 'citizen' (plain-language narrative for the public)")]
+    pub audience: Option<String>,
+    /// Optional. Echoed back in the response envelope.
+    #[serde(default)]
+    #[schemars(description = "Optional request context (case_id, requester, channel) echoed back in the response envelope and attached to log records")]
+    pub context: Option<RequestContext>,
+    /// Optional. Defaults to the whole payload.
+    #[serde(default)]
+    #[schemars(description = "Optional list of payload fields to return, e.g. ['tax', 'errors']; nested fields as 'distribution.senior'. Omit for the whole payload")]
+    pub fields: Option<Vec<String>>,
+    /// Optional. Defaults to false.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set true, after confirming with the user, to accept inputs beyond the configured soft limits (such as days_late above 3650 or income above 10M); otherwise such calls return needs_confirmation")]
+    pub acknowledge_warnings: Option<FlexibleBool>,
+    /// Optional. No graph by default.
+    #[serde(default)]
+    #[schemars(description = "Optional 'mermaid' or 'dot' to also return the calculation steps as a graph in the envelope's explanation_graph (inputs, rules and values as nodes, data flow as edges), for drawing an audit trail")]
+    pub explanation_graph: Option<String>,
+    /// Optional. Defaults to ENGINE_NUMBER_LOCALE, or plain numbers.
+    #[serde(default)]
+    #[schemars(description = "Optional locale such as 'de-DE' or 'en-US' for the numbers in explanations (decimal mark, digit grouping, currency symbol); the default writes 1234.56")]
+    pub locale: Option<String>,
+    /// Optional. Defaults to true.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set false in batch and simulation runs to skip building the explanation; it is then returned empty and the call is faster")]
+    pub include_explanations: Option<FlexibleBool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct PurgedCategory {
+    #[schemars(description = "Data category: 'results' (stored $result payloads) or 'usage' (usage counts)")]
+    pub category: String,
+    #[schemars(description = "Retention period of the category in days, from ENGINE_RETENTION_DAYS")]
+    pub retention_days: u32,
+    #[schemars(description = "RFC 3339 time before which data of the category has expired")]
+    pub cutoff: String,
+    #[schemars(description = "Deleted from memory: results, or tools whose usage counts were cleared")]
+    pub deleted: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "The same deleted from the embedded store, in builds with it")]
+    pub deleted_stored: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct PurgeExpiredDataResponse {
+    #[schemars(description = "What was purged, per category with a retention period")]
+    pub purged: Vec<PurgedCategory>,
+    #[schemars(description = "Categories without a retention period, whose data is kept")]
+    pub kept: Vec<String>,
+    #[schemars(description = "Explanation of calculation steps")]
+    pub explanation: String,
+    #[serde(default)]
+    #[schemars(description = "Assumptions made while reading the parameters (lenient parsing, defaults applied)")]
+    pub assumptions: Vec<Assumption>,
+    #[schemars(description = "Any errors in input validation")]
+    pub errors: Vec<String>,
+    #[schemars(description = "Warnings or additional information")]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct ExportSubjectDataParams {
+    #[schemars(description = "Case identifier whose stored data to export, as given in context.case_id of the original calls (max 100 characters)")]
+    pub case_id: String,
+    /// Optional. Defaults to the current response schema version.
+    #[serde(default)]
+    #[schemars(description = "Optional response schema version: 2 (default, versioned envelope) or 1 (legacy bare payload)")]
+    pub response_version: Option<FlexibleI32>,
+    /// Optional. Defaults to expert.
+    #[serde(default)]
+    #[schemars(description = "Optional explanation audience: 'expert' (default, technical step-by-step) or 'citizen' (plain-language narrative for the public)")]
+    pub audience: Option<String>,
+    /// Optional. Echoed back in the response envelope.
+    #[serde(default)]
+    #[schemars(description = "Optional request context (case_id, requester, channel) echoed back in the response envelope and attached to log records")]
+    pub context: Option<RequestContext>,
+    /// Optional. Defaults to the whole payload.
+    #[serde(default)]
+    #[schemars(description = "Optional list of payload fields to return, e.g. ['tax', 'errors']; nested fields as 'distribution.senior'. Omit for the whole payload")]
+    pub fields: Option<Vec<String>>,
+    /// Optional. Defaults to false.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set true, after confirming with the user, to accept inputs beyond the configured soft limits (such as days_late above 3650 or income above 10M); otherwise such calls return needs_confirmation")]
+    pub acknowledge_warnings: Option<FlexibleBool>,
+    /// Optional. No graph by default.
+    #[serde(default)]
+    #[schemars(description = "Optional 'mermaid' or 'dot' to also return the calculation steps as a graph in the envelope's explanation_graph (inputs, rules and values as nodes, data flow as edges), for drawing an audit trail")]
+    pub explanation_graph: Option<String>,
+    /// Optional. Defaults to ENGINE_NUMBER_LOCALE, or plain numbers.
+    #[serde(default)]
+    #[schemars(description = "Optional locale such as 'de-DE' or 'en-US' for the numbers in explanations (decimal mark, digit grouping, currency symbol); the default writes 1234.56")]
+    pub locale: Option<String>,
+    /// Optional. Defaults to true.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set false in batch and simulation runs to skip building the explanation; it is then returned empty and the call is faster")]
+    pub include_explanations: Option<FlexibleBool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct SubjectRecord {
+    #[schemars(description = "Id the result is referenced by ($result:<id>.<field>)")]
+    pub result_id: String,
+    #[schemars(description = "Tool whose response the result is")]
+    pub tool: String,
+    #[schemars(description = "RFC 3339 time the result was stored")]
+    pub stored_at: String,
+    #[schemars(description = "The stored response payload")]
+    pub payload: serde_json::Value,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct ExportSubjectDataResponse {
+    #[schemars(description = "Case identifier the records belong to")]
+    pub case_id: String,
+    #[schemars(description = "Stored results of the case, oldest first")]
+    pub records: Vec<SubjectRecord>,
+    #[schemars(description = "Stored results of the case that could not be read (e.g. their store key is not configured), with the reason")]
+    pub unreadable: Vec<String>,
+    #[schemars(description = "Stores searched: 'memory', and 'embedded_store' in builds with it")]
+    pub stores_searched: Vec<String>,
+    #[schemars(description = "Explanation of calculation steps")]
+    pub explanation: String,
+    #[serde(default)]
+    #[schemars(description = "Assumptions made while reading the parameters (lenient parsing, defaults applied)")]
+    pub assumptions: Vec<Assumption>,
+    #[schemars(description = "Any errors in input validation")]
+    pub errors: Vec<String>,
+    #[schemars(description = "Warnings or additional information")]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct EraseSubjectDataParams {
+    #[schemars(description = "Case identifier whose stored data to erase, as given in context.case_id of the original calls (max 100 characters)")]
+    pub case_id: String,
+    /// Optional. Defaults to the current response schema version.
+    #[serde(default)]
+    #[schemars(description = "Optional response schema version: 2 (default, versioned envelope) or 1 (legacy bare payload)")]
+    pub response_version: Option<FlexibleI32>,
+    /// Optional. Defaults to expert.
+    #[serde(default)]
+    #[schemars(description = "Optional explanation audience: 'expert' (default, technical step-by-step) or 'citizen' (plain-language narrative for the public)")]
+    pub audience: Option<String>,
+    /// Optional. Echoed back in the response envelope.
+    #[serde(default)]
+    #[schemars(description = "Optional request context (case_id, requester, channel) echoed back in the response envelope and attached to log records")]
+    pub context: Option<RequestContext>,
+    /// Optional. Defaults to the whole payload.
+    #[serde(default)]
+    #[schemars(description = "Optional list of payload fields to return, e.g. ['tax', 'errors']; nested fields as 'distribution.senior'. Omit for the whole payload")]
+    pub fields: Option<Vec<String>>,
+    /// Optional. Defaults to false.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set true, after confirming with the user, to accept inputs beyond the configured soft limits (such as days_late above 3650 or income above 10M); otherwise such calls return needs_confirmation")]
+    pub acknowledge_warnings: Option<FlexibleBool>,
+    /// Optional. No graph by default.
+    #[serde(default)]
+    #[schemars(description = "Optional 'mermaid' or 'dot' to also return the calculation steps as a graph in the envelope's explanation_graph (inputs, rules and values as nodes, data flow as edges), for drawing an audit trail")]
+    pub explanation_graph: Option<String>,
+    /// Optional. Defaults to ENGINE_NUMBER_LOCALE, or plain numbers.
+    #[serde(default)]
+    #[schemars(description = "Optional locale such as 'de-DE' or 'en-US' for the numbers in explanations (decimal mark, digit grouping, currency symbol); the default writes 1234.56")]
+    pub locale: Option<String>,
+    /// Optional. Defaults to true.
+    #[serde(default)]
+    #[schemars(description = "Optional. Set false in batch and simulation runs to skip building the explanation; it is then returned empty and the call is faster")]
+    pub include_explanations: Option<FlexibleBool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct ErasureReceipt {
+    #[schemars(description = "Id of this erasure, also written to the server log")]
+    pub receipt_id: String,
+    #[schemars(description = "RFC 3339 time of the erasure")]
+    pub erased_at: String,
+    #[schemars(description = "HMAC-SHA256 of the case identifier under the store's secret case hash key, which the log record carries instead of the identifier")]
+    pub case_hash: String,
+    #[schemars(description = "Number of distinct stored results erased")]
+    pub erased: usize,
+    #[schemars(description = "Results deleted from memory")]
+    pub erased_in_memory: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Results deleted from the embedded store, in builds with it")]
+    pub erased_stored: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Of those, encrypted results whose wrapped data keys were destroyed with them (crypto-shredded)")]
+    pub shredded: Option<usize>,
+    #[schemars(description = "Ids of the erased results")]
+    pub result_ids: Vec<String>,
+    #[schemars(description = "SHA-256 of the erased result ids, one per line in order, to match the receipt with the log record")]
+    pub result_ids_digest: String,
+    #[schemars(description = "Whether every store searched was erased; false when the embedded store could not be")]
+    pub complete: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct EraseSubjectDataResponse {
+    #[schemars(description = "Receipt of the erasure")]
+    pub receipt: ErasureReceipt,
+    #[schemars(description = "Stores searched: 'memory', and 'embedded_store' in builds with it")]
+    pub stores_searched: Vec<String>,
+    #[schemars(description = "Explanation of calculation steps")]
+    pub explanation: String,
+    #[serde(default)]
+    #[schemars(description = "Assumptions made while reading the parameters (lenient parsing, defaults applied)")]
+    pub assumptions: Vec<Assumption>,
+    #[schemars(description = "Any errors in input validation")]
+    pub errors: Vec<String>,
+    #[schemars(description = "Warnings or additional information")]
+    pub warnings: Vec<String>,
+}
+
+// =================== RESPONSE ENVELOPE ===================
+
+/// Current response schema version. Version 1 is the legacy bare payload without an envelope.
+pub const RESPONSE_SCHEMA_VERSION: u32 = 2;
+const SUPPORTED_RESPONSE_VERSIONS: [u32; 2] = [1, 2];
+
+/// Versioned wrapper around every tool response payload
+#[derive(Debug, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct ResponseEnvelope<T> {
+    #[schemars(description = "Response schema version")]
+    pub schema_version: u32,
+    #[schemars(description = "Name of the tool that produced the payload")]
+    pub tool: String,
+    #[schemars(description = "RFC 3339 timestamp of when the response was generated")]
+    pub generated_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Id for referencing this result's fields in later calls as $result:<id>.<field>")]
+    pub result_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Request context echoed back from the call, if one was provided")]
+    pub context: Option<RequestContext>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "SHA-256 of the parsed inputs and the engine configuration; equal hashes mean the results were computed from identical inputs")]
+    pub input_hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Calculation steps as a Mermaid or DOT graph, if explanation_graph was requested")]
+    pub explanation_graph: Option<String>,
+    #[schemars(description = "Tool-specific response payload")]
+    pub payload: T,
+}
+
+/// Caller-supplied business metadata, echoed back and attached to log records
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct RequestContext {
+    #[serde(default)]
+    #[schemars(description = "Business case identifier (max 100 characters)")]
+    pub case_id: Option<String>,
+    #[serde(default)]
+    #[schemars(description = "Person or system requesting the calculation (max 100 characters)")]
+    pub requester: Option<String>,
+    #[serde(default)]
+    #[schemars(description = "Channel the request came through, e.g. 'web', 'phone', 'chatbot' (max 100 characters)")]
+    pub channel: Option<String>,
+    #[serde(default)]
+    #[schemars(description = "Where the parameter values came from: 'user_provided', 'llm_inferred' or 'document_extracted'. Eligibility values inferred by an LLM that sit exactly on a threshold must be confirmed with the user")]
+    pub input_source: Option<InputSource>,
+}
+
+/// Provenance of a call's parameter values
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum InputSource {
+    /// Stated by the user
+    UserProvided,
+    /// Inferred or estimated by the calling model
+    LlmInferred,
+    /// Read from a document
+    DocumentExtracted,
+}
+
+impl fmt::Display for InputSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            InputSource::UserProvided => "user_provided",
+            InputSource::LlmInferred => "llm_inferred",
+            InputSource::DocumentExtracted => "document_extracted",
+        })
+    }
+}
+
+/// Errors for LLM-inferred values that sit exactly on a decision threshold.
+///
+/// A small inference error there flips the outcome, so the caller must confirm each value with
+/// the user and retry. Each check is (label, value, threshold).
+fn threshold_confirmations(context: Option<&RequestContext>, checks: &[(&str, f64, f64)]) -> Vec<String> {
+    if context.and_then(|context| context.input_source) != Some(InputSource::LlmInferred) {
+        return Vec::new();
+    }
+    checks
+        .iter()
+        .filter(|(_, value, threshold)| (value - threshold).abs() <= 1e-9 * threshold.abs().max(1.0))
+        .map(|(label, value, threshold)| format!(
+            "{} ({}) sits exactly on the threshold of {}; confirm the value with the user and retry with input_source 'user_provided'",
+            label, value, threshold
+        ))
+        .collect()
+}
+
+/// Validate the optional request context; every field is subject to the usual input limits
+fn parse_request_context(value: Option<&RequestContext>) -> Result<Option<RequestContext>, String> {
+    let Some(context) = value else {
+        return Ok(None);
+    };
+    for (field, value) in [
+        ("case_id", &context.case_id),
+        ("requester", &context.requester),
+        ("channel", &context.channel),
+    ] {
+        if let Some(value) = value {
+            validate_input_security(value, &format!("context.{}", field))?;
+            if value.chars().any(char::is_control) {
+                return Err(format!("Invalid context.{}: control characters are not allowed", field));
+            }
+        }
+    }
+    Ok(Some(context.clone()))
+}
+
+/// Validate the case identifier of a subject data request; it must match `context.case_id` of
+/// the original calls exactly, so it is subject to the same limits and not trimmed
+fn parse_case_id(raw: &str) -> Result<String, String> {
+    validate_input_security(raw, "case_id").map_err(|e| invalid_parameter("case_id", e))?;
+    if raw.chars().any(char::is_control) {
+        return Err(invalid_parameter("case_id", "control characters are not allowed"));
+    }
+    Ok(raw.to_string())
+}
+
+/// Stores searched for the data of a case
+fn subject_stores(searched_stored: bool) -> Vec<String> {
+    let mut stores = vec!["memory".to_string()];
+    if searched_stored {
+        stores.push("embedded_store".to_string());
+    }
+    stores
+}
+
+/// Span carrying the tool name and request context so log records can be traced to a case
+fn request_span(tool: &'static str, context: Option<&RequestContext>) -> tracing::Span {
+    let field = |value: Option<&String>| value.map(|v| sanitize_for_error_message(v)).unwrap_or_default();
+    tracing::info_span!(
+        "tool_call",
+        tool,
+        case_id = %field(context.and_then(|c| c.case_id.as_ref())),
+        requester = %field(context.and_then(|c| c.requester.as_ref())),
+        channel = %field(context.and_then(|c| c.channel.as_ref())),
+        input_source = %context.and_then(|c| c.input_source).map(|s| s.to_string()).unwrap_or_default(),
+        input_hash = tracing::field::Empty,
+    )
+}
+
+/// Resolve the requested response schema version, defaulting to the current one
+fn parse_response_version(value: Option<&FlexibleI32>) -> Result<u32, String> {
+    let Some(s) = value else {
+        return Ok(RESPONSE_SCHEMA_VERSION);
+    };
+    let version = AssumptionLog::default().read_i32("response_version", s)?;
+    match u32::try_from(version) {
+        Ok(version) if SUPPORTED_RESPONSE_VERSIONS.contains(&version) => Ok(version),
+        _ => Err(format!(
+            "Unsupported response version {} (supported: {})",
+            version,
+            SUPPORTED_RESPONSE_VERSIONS.map(|v| v.to_string()).join(", ")
+        )),
+    }
+}
+
+/// Validate the optional `fields` projection: payload field names, dotted for nested fields
+fn parse_fields(value: Option<&[String]>) -> Result<Option<Vec<String>>, String> {
+    let Some(fields) = value else {
+        return Ok(None);
+    };
+    if fields.is_empty() {
+        return Err("list at least one field, or omit the parameter for the whole payload".to_string());
+    }
+    fields
+        .iter()
+        .map(|field| {
+            validate_input_security(field, "fields")?;
+            let field = field.trim();
+            if field.split('.').any(str::is_empty) {
+                return Err(format!("'{}' is not a field name", sanitize_for_error_message(field)));
+            }
+            Ok(field.to_string())
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(Some)
+}
+
+/// Keep only the requested fields of a payload, with nested fields inside their parent objects
+fn project_fields(payload: &serde_json::Value, fields: &[String]) -> Result<serde_json::Value, String> {
+    let mut projected = serde_json::Map::new();
+    for field in fields {
+        let mut source = payload;
+        let mut target = &mut projected;
+        let mut names = field.split('.').peekable();
+        while let Some(name) = names.next() {
+            let Some(object) = source.as_object() else {
+                return Err(format!("'{}' has no nested fields", sanitize_for_error_message(field)));
+            };
+            let Some(value) = object.get(name) else {
+                return Err(format!(
+                    "unknown field '{}' (available: {})",
+                    sanitize_for_error_message(field),
+                    object.keys().map(String::as_str).collect::<Vec<_>>().join(", ")
+                ));
+            };
+            if names.peek().is_none() {
+                target.insert(name.to_string(), value.clone());
+            } else {
+                source = value;
+                target = target
+                    .entry(name)
+                    .or_insert_with(|| serde_json::Value::Object(serde_json::Map::new()))
+                    .as_object_mut()
+                    .expect("parents of projected fields are objects");
+            }
+        }
+    }
+    Ok(serde_json::Value::Object(projected))
+}
+
+/// A payload as sent: whole, or projected to the requested fields
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Shown<'a, T> {
+    Whole(&'a T),
+    Projected(serde_json::Value),
+}
+
+/// Tools whose responses are not stored for reference: they hold, or account for, the stored data
+/// of a case, which must not be stored again
+const NOT_STORED: &[&str] = &["export_subject_data", "erase_subject_data"];
+
+/// Serialize a tool payload in the requested response schema version and configured format
+///
+/// The request context, input hash, explanation graph and result id are echoed in the envelope;
+/// legacy version 1 payloads have nowhere to carry them, so they are not stored for reference
+/// either. With a `fields` projection only those fields are sent, but the whole payload is stored
+/// for reference, under the context's case id. The responses of [`NOT_STORED`] tools are not.
+fn render_response<T: Serialize>(
+    tool: &str,
+    response_version: u32,
+    context: Option<RequestContext>,
+    input_hash: String,
+    explanation_graph: Option<String>,
+    fields: Option<&[String]>,
+    payload: &T,
+) -> Result<String, String> {
+    let serialization_error = |e: serde_json::Error| format!("Error serializing response: {}", e);
+    let value = serde_json::to_value(payload).map_err(serialization_error)?;
+    let shown = match fields {
+        Some(fields) => Shown::Projected(project_fields(&value, fields).map_err(|e| invalid_parameter("fields", e))?),
+        None => Shown::Whole(payload),
+    };
+    match response_version {
+        1 => json_format::to_string(&shown),
+        _ => json_format::to_string(&ResponseEnvelope {
+            schema_version: response_version,
+            tool: tool.to_string(),
+            generated_at: clock::now().to_rfc3339(),
+            result_id: match NOT_STORED.contains(&tool) {
+                true => None,
+                false => result_store::store(tool, context.as_ref().and_then(|context| context.case_id.as_deref()), value),
+            },
+            context,
+            input_hash: Some(input_hash),
+            explanation_graph,
+            payload: shown,
+        }),
+    }
+    .map_err(serialization_error)
+}
+
+// =================== INPUT HASH ===================
+
+/// Parameters that shape the response but not the result, left out of the input hash
+const PRESENTATION_PARAMS: [&str; 9] = [
+    "response_version",
+    "audience",
+    "context",
+    "fields",
+    "acknowledge_warnings",
+    "explanation_graph",
+    "locale",
+    "include_explanations",
+    "include_brackets",
+];
+
+/// Put a parsed value at a parameter path such as `cash_flows[2]` or `milestones[0].completed`
+fn set_parsed(arguments: &mut serde_json::Value, path: &str, parsed: serde_json::Value) {
+    let mut slot = arguments;
+    for segment in path.split('.') {
+        let (name, index) = match segment.split_once('[') {
+            Some((name, rest)) => (name, rest.trim_end_matches(']').parse::<usize>().ok()),
+            None => (segment, None),
+        };
+        let Some(object) = slot.as_object_mut() else {
+            return;
+        };
+        slot = object.entry(name).or_insert(serde_json::Value::Null);
+        if let Some(index) = index {
+            slot = match slot.get_mut(index) {
+                Some(item) => item,
+                None => return,
+            };
+        }
+    }
+    *slot = parsed;
+}
+
+/// SHA-256 (hex) of the canonical JSON of the tool, the configuration version and the inputs.
+///
+/// Inputs are the arguments without presentation parameters, with numbers and booleans replaced
+/// by their parsed values (defaults included), so `"45,000"` and `45000` hash the same.
+fn input_hash<P: Serialize>(tool: &str, params: &P, parsed: &BTreeMap<String, serde_json::Value>) -> String {
+    let mut inputs = serde_json::to_value(params).unwrap_or_default();
+    if let Some(object) = inputs.as_object_mut() {
+        object.retain(|name, value| !PRESENTATION_PARAMS.contains(&name.as_str()) && !value.is_null());
+    }
+    for (path, value) in parsed {
+        set_parsed(&mut inputs, path, value.clone());
+    }
+    let canonical = json_format::canonical(&serde_json::json!({
+        "tool": tool,
+        "config_version": config_version(),
+        "inputs": inputs,
+    }));
+    format!("{:x}", Sha256::digest(canonical))
+}
+
+// =================== MISSING INPUT ===================
+
+/// Values an agent sends when it does not know a required parameter yet
+const PLACEHOLDER_VALUES: [&str; 6] = ["?", "unknown", "n/a", "na", "tbd", "none"];
+
+/// Input schema of every tool, looked up when a call arrives with blank required parameters
+static INPUT_SCHEMAS: LazyLock<BTreeMap<String, JsonObject>> = LazyLock::new(|| {
+    calculators()
+        .map(|calculator| (calculator.name(), calculator.input_schema().as_ref().clone()))
+        .collect()
+});
+
+/// Structured reply asking the client to collect missing parameters and retry the call
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct NeedsInputResponse {
+    /// Always "needs_input"
+    pub status: String,
+    pub tool: String,
+    pub message: String,
+    /// Names of the parameters still needed
+    pub missing: Vec<String>,
+    /// Object schema of the missing parameters, usable as an elicitation `requestedSchema`
+    pub requested_schema: serde_json::Value,
+}
+
+/// Check a tool's required string parameters for blank or placeholder values.
+///
+/// Returns the `needs_input` result to send instead of failing on the first unparseable value.
+fn needs_input<P: Serialize>(tool: &str, params: &P) -> Option<CallToolResult> {
+    let schema = INPUT_SCHEMAS.get(tool)?;
+    let values = serde_json::to_value(params).ok()?;
+    let properties = schema.get("properties").and_then(serde_json::Value::as_object)?;
+    let missing: Vec<&str> = schema
+        .get("required")
+        .and_then(serde_json::Value::as_array)?
+        .iter()
+        .filter_map(serde_json::Value::as_str)
+        .filter(|name| match values.get(*name) {
+            Some(serde_json::Value::String(text)) => {
+                let text = text.trim().to_ascii_lowercase();
+                text.is_empty() || PLACEHOLDER_VALUES.contains(&text.as_str())
+            }
+            Some(serde_json::Value::Null) | None => true,
+            Some(_) => false,
+        })
+        .collect();
+    if missing.is_empty() {
+        return None;
+    }
+
+    let requested: serde_json::Map<String, serde_json::Value> = missing
+        .iter()
+        .filter_map(|name| properties.get(*name).map(|property| (name.to_string(), property.clone())))
+        .collect();
+    let response = NeedsInputResponse {
+        status: "needs_input".to_string(),
+        tool: tool.to_string(),
+        message: format!(
+            "Missing required parameters: {}. Ask the user for them and call {} again",
+            missing.join(", "),
+            tool
+        ),
+        missing: missing.iter().map(|name| name.to_string()).collect(),
+        requested_schema: serde_json::json!({
+            "type": "object",
+            "properties": requested,
+            "required": missing,
+        }),
+    };
+    let text = json_format::to_string(&response).ok()?;
+    increment_errors();
+    Some(CallToolResult::error(vec![Content::text(text)]))
+}
+
+// =================== SOFT LIMITS ===================
+
+/// Inputs beyond which a call needs `acknowledge_warnings`: parameter name -> largest magnitude
+/// accepted without confirmation
+static SOFT_LIMITS: LazyLock<BTreeMap<String, f64>> = LazyLock::new(|| {
+    let defaults = BTreeMap::from([("days_late".to_string(), 3650.0), ("income".to_string(), 10_000_000.0)]);
+    let Ok(json) = env::var("ENGINE_SOFT_LIMITS") else {
+        return defaults;
+    };
+    parse_soft_limits(&json).unwrap_or_else(|e| {
+        tracing::error!("Invalid ENGINE_SOFT_LIMITS: {}; using days_late 3650 and income 10000000", e);
+        defaults
+    })
+});
+
+fn parse_soft_limits(json: &str) -> Result<BTreeMap<String, f64>, String> {
+    let limits: BTreeMap<String, f64> =
+        serde_json::from_str(json).map_err(|e| format!("expected an object of parameter limits: {}", e))?;
+    match limits.iter().find(|(_, limit)| !limit.is_finite() || **limit <= 0.0) {
+        Some((parameter, limit)) => Err(format!("limit for '{}' must be a positive number, got {}", parameter, limit)),
+        None => Ok(limits),
+    }
+}
+
+/// An input beyond its soft limit
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SoftLimitWarning {
+    pub parameter: String,
+    pub value: f64,
+    pub limit: f64,
+}
+
+impl fmt::Display for SoftLimitWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} = {} is beyond the soft limit of {}", self.parameter, self.value, self.limit)
+    }
+}
+
+/// Parsed inputs beyond their soft limit. A limit set for a name applies to every input of that
+/// name, including fields of list items such as `beneficiaries[0].amount`.
+fn soft_limit_warnings(inputs: &BTreeMap<String, serde_json::Value>) -> Vec<SoftLimitWarning> {
+    inputs
+        .iter()
+        .filter_map(|(path, value)| {
+            let name = path.rsplit(['.', ']']).next().unwrap_or(path);
+            let limit = *SOFT_LIMITS.get(path).or_else(|| SOFT_LIMITS.get(name))?;
+            let value = value.as_f64()?;
+            (value.abs() > limit).then(|| SoftLimitWarning { parameter: path.clone(), value, limit })
+        })
+        .collect()
+}
+
+/// Structured reply asking the client to confirm unusual inputs and retry the call
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct NeedsConfirmationResponse {
+    /// Always "needs_confirmation"
+    pub status: String,
+    pub tool: String,
+    pub message: String,
+    /// Inputs beyond their soft limit
+    pub warnings: Vec<SoftLimitWarning>,
+}
+
+fn needs_confirmation(tool: &str, warnings: Vec<SoftLimitWarning>) -> Result<CallToolResult, McpError> {
+    let response = NeedsConfirmationResponse {
+        status: "needs_confirmation".to_string(),
+        tool: tool.to_string(),
+        message: format!(
+            "Unusual inputs: {}. Confirm them with the user, then call {} again with acknowledge_warnings: true",
+            warnings.iter().map(SoftLimitWarning::to_string).collect::<Vec<_>>().join("; "),
+            tool
+        ),
+        warnings,
+    };
+    match json_format::to_string(&response) {
+        Ok(text) => error_result(text),
+        Err(e) => error_result(format!("Error serializing response: {}", e)),
+    }
+}
+
+// =================== ASSUMPTIONS ===================
+
+/// Collects the assumptions made while parsing a tool's parameters
+#[derive(Debug, Default)]
+struct AssumptionLog(Vec<Assumption>);
+
+impl AssumptionLog {
+    fn push(&mut self, code: &str, field: &str, message: String) {
+        self.0.push(Assumption {
+            code: code.to_string(),
+            field: field.to_string(),
+            message,
+        });
+    }
+
+    /// Record how a parameter's text was read, checking any unit annotation against the field;
+    /// returns the factor converting the value into the field's unit
+    fn record(&mut self, field: &str, reading: &Reading) -> Result<f64, String> {
+        let position = reading.unit.as_ref().map_or(reading.notes.len(), |unit| unit.position);
+        let (before_unit, after_unit) = reading.notes.split_at(position);
+        for (code, message) in before_unit {
+            self.push(code, field, message.clone());
+        }
+        let mut factor = 1.0;
+        if let Some(unit) = &reading.unit {
+            let shown = sanitize_for_error_message(unit.raw.trim());
+            let (unit_factor, conversion) = unit_conversion(field, &unit.unit)
+                .map_err(|e| format!("Cannot parse '{}': {}", shown, e))?;
+            if let Some(conversion) = conversion {
+                self.push("unit_converted", field, format!(
+                    "'{}' converted to the field's unit ({})", shown, conversion
+                ));
+            }
+            factor = unit_factor;
+        }
+        for (code, message) in after_unit {
+            self.push(code, field, message.clone());
+        }
+        Ok(factor)
+    }
+
+    /// Read a number parameter, recording how its text was read
+    fn read_f64(&mut self, field: &str, param: &FlexibleF64) -> Result<f64, String> {
+        let factor = self.record(field, &param.1)?;
+        Ok(param.0.clone()? * factor)
+    }
+
+    /// Read an integer parameter, recording how its text was read
+    fn read_i32(&mut self, field: &str, param: &FlexibleI32) -> Result<i32, String> {
+        let factor = self.record(field, &param.1)?;
+        let value = param.0.clone()?;
+        match &param.1.unit {
+            None => Ok(value),
+            Some(unit) => value.checked_mul(factor as i32).ok_or_else(|| {
+                format!("'{}' is too large after unit conversion", sanitize_for_error_message(&unit.number))
+            }),
+        }
+    }
+
+    /// Read a boolean parameter, recording how its text was read
+    fn read_bool(&mut self, field: &str, param: &FlexibleBool) -> Result<bool, String> {
+        self.record(field, &param.1)?;
+        param.0.clone()
+    }
+
+    /// Record that an omitted optional parameter took its default value
+    fn defaulted<T: fmt::Display>(&mut self, field: &str, value: T) -> T {
+        self.push("default_applied", field, format!("{} not provided; assumed {}", field, value));
+        value
+    }
+
+    /// Record that an unparseable optional parameter fell back to its default value
+    fn invalid_defaulted<T: fmt::Display>(&mut self, field: &str, value: T) -> T {
+        self.push("invalid_value_defaulted", field, format!("{} could not be parsed; assumed default {}", field, value));
+        value
+    }
+
+    fn into_vec(self) -> Vec<Assumption> {
+        self.0
+    }
+}
+
+// =================== TOOL CALL PLUMBING ===================
+
+/// Parameters every tool accepts alongside its own
+trait CommonParams: Serialize {
+    fn response_version(&self) -> Option<&FlexibleI32>;
+    fn audience(&self) -> Option<&String>;
+    fn context(&self) -> Option<&RequestContext>;
+    fn fields(&self) -> Option<&[String]>;
+    fn acknowledge_warnings(&self) -> Option<&FlexibleBool>;
+    fn explanation_graph(&self) -> Option<&String>;
+    fn locale(&self) -> Option<&String>;
+    fn include_explanations(&self) -> Option<&FlexibleBool>;
+}
+
+/// Payload fields the shared plumbing fills in and inspects
+trait ToolPayload: Serialize {
+    fn errors(&self) -> &[String];
+    fn set_assumptions(&mut self, assumptions: Vec<Assumption>);
+    fn clear_explanation(&mut self);
+}
+
+macro_rules! tool_types {
+    ($($params:ty => $payload:ty),* $(,)?) => {$(
+        impl CommonParams for $params {
+            fn response_version(&self) -> Option<&FlexibleI32> {
+                self.response_version.as_ref()
+            }
+
+            fn audience(&self) -> Option<&String> {
+                self.audience.as_ref()
+            }
+
+            fn context(&self) -> Option<&RequestContext> {
+                self.context.as_ref()
+            }
+
+            fn fields(&self) -> Option<&[String]> {
+                self.fields.as_deref()
+            }
+
+            fn acknowledge_warnings(&self) -> Option<&FlexibleBool> {
+                self.acknowledge_warnings.as_ref()
+            }
+
+            fn explanation_graph(&self) -> Option<&String> {
+                self.explanation_graph.as_ref()
+            }
+
+            fn locale(&self) -> Option<&String> {
+                self.locale.as_ref()
+            }
+
+            fn include_explanations(&self) -> Option<&FlexibleBool> {
+                self.include_explanations.as_ref()
+            }
+        }
+
+        impl ToolPayload for $payload {
+            fn errors(&self) -> &[String] {
+                &self.errors
+            }
+
+            fn set_assumptions(&mut self, assumptions: Vec<Assumption>) {
+                self.assumptions = assumptions;
+            }
+
+            fn clear_explanation(&mut self) {
+                self.explanation.clear();
+            }
+        }
+    )*};
+}
+
+tool_types! {
+    CalcPenaltyParams => CalcPenaltyResponse,
+    CalcTaxParams => CalcTaxResponse,
+    CheckVotingParams => CheckVotingResponse,
+    ValidateVoteRecordParams => ValidateVoteRecordResponse,
+    DistributeWaterfallParams => DistributeWaterfallResponse,
+    CheckCovenantsParams => CheckCovenantsResponse,
+    CalcNpvParams => CalcNpvResponse,
+    CalcIrrParams => CalcIrrResponse,
+    CheckEscrowReleaseParams => CheckEscrowReleaseResponse,
+    CheckHousingGrantParams => CheckHousingGrantResponse,
+    CalcCapitalGainsParams => CalcCapitalGainsResponse,
+    CalcInheritanceTaxParams => CalcInheritanceTaxResponse,
+    CalcSocialContributionsParams => CalcSocialContributionsResponse,
+    CalcPropertyTaxParams => CalcPropertyTaxResponse,
+    CalcTransferTaxParams => CalcTransferTaxResponse,
+    CalcFeeParams => CalcFeeResponse,
+    IndexAmountParams => IndexAmountResponse,
+    ConvertCurrencyParams => ConvertCurrencyResponse,
+    GetIntakeSchemaParams => GetIntakeSchemaResponse,
+    PrescreenParams => PrescreenResponse,
+    GetUsageReportParams => GetUsageReportResponse,
+    ListToolsByCategoryParams => ListToolsByCategoryResponse,
+    DescribeCapabilitiesParams => DescribeCapabilitiesResponse,
+    GetRecentSchemaFailuresParams => GetRecentSchemaFailuresResponse,
+    UpsertProfileParams => UpsertProfileResponse,
+    DiffProfilesParams => DiffProfilesResponse,
+    EvaluateRangesParams => EvaluateRangesResponse,
+    SimulateUncertaintyParams => SimulateUncertaintyResponse,
+    CheckAppealDeadlineParams => CheckAppealDeadlineResponse,
+    GetRequiredDocumentsParams => GetRequiredDocumentsResponse,
+    CheckVotingBulkParams => CheckVotingBulkResponse,
+    ProjectQuorumParams => ProjectQuorumResponse,
+    StressConfigParams => StressConfigResponse,
+    VerifyStoreIntegrityParams => VerifyStoreIntegrityResponse,
+    PurgeExpiredDataParams => PurgeExpiredDataResponse,
+    ExportSubjectDataParams => ExportSubjectDataResponse,
+    EraseSubjectDataParams => EraseSubjectDataResponse,
+}
+
+/// How a tool reports the errors listed in its payload
+#[derive(Debug, Clone, Copy)]
+enum PayloadErrors {
+    /// As a "Validation errors: ..." error result
+    Validation,
+    /// As a "Calculation errors: ..." error result
+    Calculation,
+    /// Inside a successful response, for tools whose findings are the errors
+    Reported,
+}
+
+/// Parsed common parameters and the assumptions made so far, handed to a tool body
+struct ToolCall {
+    audience: Audience,
+    context: Option<RequestContext>,
+    assumptions: AssumptionLog,
+    /// Content sent after the rendered response when the call succeeds
+    notes: Vec<Content>,
+    /// Parsed parameter values by path, for the input hash
+    inputs: BTreeMap<String, serde_json::Value>,
+}
+
+/// Error message for a parameter that could not be read
+fn invalid_parameter(field: &str, error: impl fmt::Display) -> String {
+    format!("Invalid {} parameter: {}", field, error)
+}
+
+impl ToolCall {
+    /// Keep a parsed or defaulted value for the input hash
+    fn parsed<T: Serialize>(&mut self, field: &str, value: T) -> T {
+        self.inputs.insert(field.to_string(), serde_json::to_value(&value).unwrap_or_default());
+        value
+    }
+
+    fn f64(&mut self, field: &str, raw: &FlexibleF64) -> Result<f64, String> {
+        let value = self.assumptions.read_f64(field, raw).map_err(|e| invalid_parameter(field, e))?;
+        Ok(self.parsed(field, value))
+    }
+
+    fn i32(&mut self, field: &str, raw: &FlexibleI32) -> Result<i32, String> {
+        let value = self.assumptions.read_i32(field, raw).map_err(|e| invalid_parameter(field, e))?;
+        Ok(self.parsed(field, value))
+    }
+
+    fn bool(&mut self, field: &str, raw: &FlexibleBool) -> Result<bool, String> {
+        let value = self.assumptions.read_bool(field, raw).map_err(|e| invalid_parameter(field, e))?;
+        Ok(self.parsed(field, value))
+    }
+
+    /// Optional number; an omitted value takes `default`, recorded as an assumption
+    fn f64_or(&mut self, field: &str, raw: Option<&FlexibleF64>, default: f64) -> Result<f64, String> {
+        match raw {
+            None => {
+                let value = self.assumptions.defaulted(field, default);
+                Ok(self.parsed(field, value))
+            }
+            Some(raw) => self.f64(field, raw),
+        }
+    }
+
+    /// Optional integer; an omitted value takes `default`, recorded as an assumption
+    fn i32_or(&mut self, field: &str, raw: Option<&FlexibleI32>, default: i32) -> Result<i32, String> {
+        match raw {
+            None => {
+                let value = self.assumptions.defaulted(field, default);
+                Ok(self.parsed(field, value))
+            }
+            Some(raw) => self.i32(field, raw),
+        }
+    }
+
+    /// Optional boolean; an omitted value takes `default`, recorded as an assumption
+    fn bool_or(&mut self, field: &str, raw: Option<&FlexibleBool>, default: bool) -> Result<bool, String> {
+        match raw {
+            None => {
+                let value = self.assumptions.defaulted(field, default);
+                Ok(self.parsed(field, value))
+            }
+            Some(raw) => self.bool(field, raw),
+        }
+    }
+
+    /// Optional number without a default
+    fn optional_f64(&mut self, field: &str, raw: Option<&FlexibleF64>) -> Result<Option<f64>, String> {
+        raw.map(|raw| self.f64(field, raw)).transpose()
+    }
+}
+
+fn error_result(message: String) -> Result<CallToolResult, McpError> {
+    increment_errors();
+    Ok(CallToolResult::error(vec![Content::text(message)]))
+}
+
+/// Error result for a call the tool rejected, logged subject to burst sampling
+fn rejected(tool: &'static str, message: String) -> Result<CallToolResult, McpError> {
+    if let Some(suppressed) = log_sampling::admit(tool) {
+        tracing::info!(tool, suppressed, "tool call rejected: {}", message);
+    }
+    error_result(message)
+}
+
+/// Run a tool call through the plumbing every tool shares.
+///
+/// Opens the request span and timer, refuses admin tools unless the admin role is enabled,
+/// answers blank required parameters with `needs_input`, reads the common parameters and runs
+/// `body`. An `Err` from the body (usually from
+/// [`invalid_parameter`]) becomes an error result, as do payload errors unless the tool reports
+/// them itself. Anything else is rendered in the requested response version.
+fn run_tool<P: CommonParams, R: ToolPayload>(
+    tool: &'static str,
+    payload_errors: PayloadErrors,
+    params: &P,
+    body: impl FnOnce(&mut ToolCall) -> Result<R, String>,
+) -> Result<CallToolResult, McpError> {
+    let _span = request_span(tool, params.context()).entered();
+    let _timer = RequestTimer::new(tool, param_size(params));
+    increment_requests();
+
+    if !*ADMIN_TOOLS && ADMIN_ONLY_TOOLS.iter().any(|name| name == tool) {
+        return rejected(tool, format!("{} is an admin tool; admin tools are disabled (ENGINE_ADMIN_TOOLS=false)", tool));
+    }
+
+    if let Some(result) = needs_input(tool, params) {
+        return Ok(result);
+    }
+
+    let response_version = match parse_response_version(params.response_version()) {
+        Ok(value) => value,
+        Err(parse_error) => return rejected(tool, invalid_parameter("response_version", parse_error)),
+    };
+    let audience = match Audience::parse(params.audience()) {
+        Ok(value) => value,
+        Err(parse_error) => return rejected(tool, invalid_parameter("audience", parse_error)),
+    };
+    let context = match parse_request_context(params.context()) {
+        Ok(value) => value,
+        Err(parse_error) => return rejected(tool, invalid_parameter("context", parse_error)),
+    };
+    let fields = match parse_fields(params.fields()) {
+        Ok(value) => value,
+        Err(parse_error) => return rejected(tool, invalid_parameter("fields", parse_error)),
+    };
+    let acknowledged = match params.acknowledge_warnings().map(|flag| flag.0.clone()).transpose() {
+        Ok(value) => value.unwrap_or(false),
+        Err(parse_error) => return rejected(tool, invalid_parameter("acknowledge_warnings", parse_error)),
+    };
+    let graph_format = match GraphFormat::parse(params.explanation_graph()) {
+        Ok(value) => value,
+        Err(parse_error) => return rejected(tool, invalid_parameter("explanation_graph", parse_error)),
+    };
+    let number_format = match explanations::number_format(params.locale()) {
+        Ok(value) => value,
+        Err(parse_error) => return rejected(tool, invalid_parameter("locale", parse_error)),
+    };
+    let include_explanations = match params.include_explanations().map(|flag| flag.0.clone()).transpose() {
+        Ok(Some(false)) if graph_format.is_some() => {
+            return rejected(tool, invalid_parameter("include_explanations", "cannot be false with explanation_graph"));
+        }
+        Ok(value) => value.unwrap_or(true),
+        Err(parse_error) => return rejected(tool, invalid_parameter("include_explanations", parse_error)),
+    };
+
+    let mut call = ToolCall {
+        audience,
+        context,
+        assumptions: AssumptionLog::default(),
+        notes: Vec::new(),
+        inputs: BTreeMap::new(),
+    };
+    let (result, steps) = explanations::with_explanations(include_explanations, || {
+        explanations::with_number_format(number_format, || match graph_format {
+            Some(_) => explanation_graph::record(|| body(&mut call)),
+            None => (body(&mut call), Vec::new()),
+        })
+    });
+    let mut result = match result {
+        Ok(result) => result,
+        Err(message) => return rejected(tool, message),
+    };
+    let warnings = soft_limit_warnings(&call.inputs);
+    if acknowledged {
+        for warning in &warnings {
+            call.assumptions.push("soft_limit_acknowledged", &warning.parameter, format!("{}; accepted with acknowledge_warnings", warning));
+        }
+    }
+    result.set_assumptions(call.assumptions.into_vec());
+    if !include_explanations {
+        // The steps were never rendered; drop the separators they were joined with
+        result.clear_explanation();
+    }
+
+    let label = match payload_errors {
+        PayloadErrors::Validation => Some("Validation"),
+        PayloadErrors::Calculation => Some("Calculation"),
+        PayloadErrors::Reported => None,
+    };
+    if let Some(label) = label
+        && !result.errors().is_empty()
+    {
+        return rejected(tool, format!("{} errors: {}", label, result.errors().join(", ")));
+    }
+
+    if !acknowledged && !warnings.is_empty() {
+        return needs_confirmation(tool, warnings);
+    }
+
+    let input_hash = input_hash(tool, params, &call.inputs);
+    tracing::Span::current().record("input_hash", input_hash.as_str());
+    let graph = graph_format.map(|format| explanation_graph::render(format, &call.inputs, &steps));
+    match render_response(tool, response_version, call.context, input_hash, graph, fields.as_deref(), &result) {
+        Ok(json_str) => {
+            let mut content = vec![Content::text(json_str)];
+            content.extend(call.notes);
+            Ok(CallToolResult::success(content))
+        }
+        Err(message) => rejected(tool, message),
+    }
+}
+
+// =================== CALCULATOR REGISTRY ===================
+
+/// Catalog section a tool is listed under
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolCategory {
+    /// Payments, debt, returns and currency
+    Finance,
+    /// Taxes, contributions and fees
+    Tax,
+    /// Program eligibility and intake
+    Eligibility,
+    /// Votes and quorum
+    Governance,
+    /// Server administration and the tool catalog
+    Admin,
+}
+
+impl ToolCategory {
+    pub const ALL: [ToolCategory; 5] = [
+        ToolCategory::Finance,
+        ToolCategory::Tax,
+        ToolCategory::Eligibility,
+        ToolCategory::Governance,
+        ToolCategory::Admin,
+    ];
+
+    /// Parse the `category` tool parameter
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let value = value.trim().to_ascii_lowercase();
+        Self::ALL
+            .into_iter()
+            .find(|category| category.to_string() == value)
+            .ok_or_else(|| format!(
+                "must be one of: {}",
+                Self::ALL.map(|category| format!("'{}'", category)).join(", ")
+            ))
+    }
+}
+
+impl fmt::Display for ToolCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ToolCategory::Finance => "finance",
+            ToolCategory::Tax => "tax",
+            ToolCategory::Eligibility => "eligibility",
+            ToolCategory::Governance => "governance",
+            ToolCategory::Admin => "admin",
+        })
+    }
+}
+
+/// A calculation the engine serves as a tool.
+///
+/// The tool router, the missing-input check and the intake forms all read the registry, so a
+/// calculator is registered once. Arguments arrive as the raw JSON object, which lets
+/// calculators that are not backed by a typed tool method (such as ones defined in
+/// configuration) be registered the same way.
+pub(crate) trait Calculator: Send + Sync {
+    /// Tool definition: name, description and input schema
+    fn tool(&self) -> Tool;
+
+    /// Catalog section, also published as `category` in the tool's `_meta`
+    fn category(&self) -> ToolCategory;
+
+    /// One-line description of how the result is derived
+    fn logic(&self) -> &'static str;
+
+    /// Whether the tool changes the server's configuration or data, and is only served with the
+    /// admin role enabled
+    fn admin_only(&self) -> bool;
+
+    /// Check that the arguments fit the calculator's parameters, without computing
+    fn validate(&self, arguments: &JsonObject) -> Result<(), McpError>;
+
+    /// Compute the result, rendered as a tool call result
+    fn compute<'a>(
+        &'a self,
+        engine: &'a CompatibilityEngine,
+        arguments: JsonObject,
+    ) -> BoxFuture<'a, Result<CallToolResult, McpError>>;
+
+    fn name(&self) -> String {
+        self.tool().name.into_owned()
+    }
+
+    fn input_schema(&self) -> Arc<JsonObject> {
+        self.tool().input_schema
+    }
+}
+
+/// Handler generated by the `tool` attribute for an engine method
+type ToolMethod<P> =
+    for<'a> fn(&'a CompatibilityEngine, Parameters<P>) -> BoxFuture<'a, Result<CallToolResult, McpError>>;
+
+/// Calculator served by a `#[tool]` method of the engine
+struct MethodCalculator<P> {
+    category: ToolCategory,
+    admin_only: bool,
+    tool: fn() -> Tool,
+    logic: &'static str,
+    method: ToolMethod<P>,
+}
+
+impl<P: DeserializeOwned> MethodCalculator<P> {
+    /// Same error the protocol layer reports for arguments that do not deserialize
+    fn parameters(arguments: JsonObject) -> Result<P, McpError> {
+        serde_json::from_value(serde_json::Value::Object(arguments)).map_err(|e| {
+            McpError::invalid_params(format!("failed to deserialize parameters: {}", e), None)
+        })
+    }
+}
+
+impl<P: DeserializeOwned + Send + Sync + 'static> Calculator for MethodCalculator<P> {
+    fn tool(&self) -> Tool {
+        let mut tool = (self.tool)();
+        let title = tool.title.as_deref().unwrap_or(&tool.name);
+        let mut annotations = calculation_annotations(title);
+        if STORE_WRITING.contains(&tool.name.as_ref()) {
+            annotations = annotations.read_only(false).destructive(DESTRUCTIVE.contains(&tool.name.as_ref()));
+        }
+        let mut meta = JsonObject::new();
+        meta.insert("category".to_string(), serde_json::Value::from(self.category.to_string()));
+        tool.meta = Some(Meta(meta));
+        tool.annotate(annotations)
+    }
+
+    fn category(&self) -> ToolCategory {
+        self.category
+    }
+
+    fn logic(&self) -> &'static str {
+        self.logic
+    }
+
+    fn admin_only(&self) -> bool {
+        self.admin_only
+    }
+
+    fn validate(&self, arguments: &JsonObject) -> Result<(), McpError> {
+        Self::parameters(arguments.clone()).map(drop)
+    }
+
+    fn compute<'a>(
+        &'a self,
+        engine: &'a CompatibilityEngine,
+        arguments: JsonObject,
+    ) -> BoxFuture<'a, Result<CallToolResult, McpError>> {
+        match Self::parameters(arguments) {
+            Ok(params) => (self.method)(engine, Parameters(params)),
+            Err(e) => Box::pin(std::future::ready(Err(e))),
+        }
+    }
+}
+
+/// Hints shared by every calculation: it reads only its arguments and the loaded rules, changes
+/// nothing outside the server and may be repeated freely, so clients can run calls in parallel
+/// and cache their results
+fn calculation_annotations(title: &str) -> ToolAnnotations {
+    ToolAnnotations::with_title(title)
+        .read_only(true)
+        .destructive(false)
+        .idempotent(true)
+        .open_world(false)
+}
+
+/// Tools that change the loaded rules or stored data, so are not read-only: `upsert_profile`
+/// replaces the profile in force, `verify_store_integrity` can rewrite records without changing
+/// what they hold, `purge_expired_data` and `erase_subject_data` delete them. All stay idempotent.
+/// Whether a tool is served at all is decided by the admin role, not by these hints.
+const STORE_WRITING: &[&str] = &["upsert_profile", "verify_store_integrity", "purge_expired_data", "erase_subject_data"];
+/// Of those, the tools that replace or delete data
+const DESTRUCTIVE: &[&str] = &["upsert_profile", "purge_expired_data", "erase_subject_data"];
+
+fn method_calculator<P: DeserializeOwned + Send + Sync + 'static>(
+    category: ToolCategory,
+    tool: fn() -> Tool,
+    method: ToolMethod<P>,
+    logic: &'static str,
+) -> Box<dyn Calculator> {
+    Box::new(MethodCalculator { category, admin_only: false, tool, logic, method })
+}
+
+/// Calculator for an admin tool, served only when `ENGINE_ADMIN_TOOLS` is on
+fn admin_calculator<P: DeserializeOwned + Send + Sync + 'static>(
+    tool: fn() -> Tool,
+    method: ToolMethod<P>,
+    logic: &'static str,
+) -> Box<dyn Calculator> {
+    Box::new(MethodCalculator { category: ToolCategory::Admin, admin_only: true, tool, logic, method })
+}
+
+/// Whether the admin role is enabled, from `ENGINE_ADMIN_TOOLS` (default false). Admin tools are
+/// left out of the tool list and refused unless it is.
+static ADMIN_TOOLS: LazyLock<bool> = LazyLock::new(|| {
+    env::var("ENGINE_ADMIN_TOOLS")
+        .ok()
+        .map(|s| parse_bool_from_string(&s).unwrap_or_else(|e| {
+            tracing::error!("Invalid ENGINE_ADMIN_TOOLS '{}': {}; admin tools disabled", s, e);
+            false
+        }))
+        .unwrap_or(false)
+});
+
+/// Names of the admin tools, refused by `run_tool` unless the admin role is enabled
+static ADMIN_ONLY_TOOLS: LazyLock<Vec<String>> = LazyLock::new(|| {
+    CALCULATORS.iter().filter(|calculator| calculator.admin_only()).map(|calculator| calculator.name()).collect()
+});
+
+/// Every calculator the engine serves
+static CALCULATORS: LazyLock<Vec<Box<dyn Calculator>>> = LazyLock::new(|| {
+    type E = CompatibilityEngine;
+    vec![
+        method_calculator(ToolCategory::Finance, E::calc_penalty_tool_attr, E::calc_penalty,
+            "penalty = min(days_late × rate_per_day, cap), then add interest = penalty × interest_rate"),
+        method_calculator(ToolCategory::Tax, E::calc_tax_tool_attr, E::calc_tax,
+            "apply progressive brackets defined by thresholds and rates. If total tax > surcharge_threshold, add surcharge = tax × surcharge_rate"),
+        method_calculator(ToolCategory::Governance, E::check_voting_tool_attr, E::check_voting,
+            "turnout must be ≥60% of eligible. Then check: If proposal_type = \"general\" → yes_votes / turnout > 0.50. If proposal_type = \"amendment\" → yes_votes / turnout ≥ 2/3"),
+        method_calculator(ToolCategory::Governance, E::validate_vote_record_tool_attr, E::validate_vote_record,
+            "all counts non-negative, turnout ≤ eligible_voters, yes + no + abstain + invalid = turnout"),
+        method_calculator(ToolCategory::Finance, E::distribute_waterfall_tool_attr, E::distribute_waterfall,
+            "Pay senior first (up to senior_debt). Then junior (up to junior_debt). Any remainder goes to equity"),
+        method_calculator(ToolCategory::Finance, E::check_covenants_tool_attr, E::check_covenants,
+            "leverage = total_debt / EBITDA must be ≤ max_leverage. Interest coverage = EBITDA / interest_expense must be ≥ min_interest_coverage"),
+        method_calculator(ToolCategory::Finance, E::calc_npv_tool_attr, E::calc_npv,
+            "NPV = Σ cash_flow[t] / (1 + discount_rate)^t, with t = 0 for the first cash flow"),
+        method_calculator(ToolCategory::Finance, E::calc_irr_tool_attr, E::calc_irr,
+            "find rate r where Σ cash_flow[t] / (1 + r)^t = 0 by bisection between -99% and 1000%"),
+        method_calculator(ToolCategory::Finance, E::check_escrow_release_tool_attr, E::check_escrow_release,
+            "nothing is released while a dispute is pending or before release_date. Otherwise each completed milestone releases its percentage of the escrow, net of amounts already released"),
+        method_calculator(ToolCategory::Eligibility, E::check_housing_grant_tool_attr, E::check_housing_grant,
+            "Base threshold = 0.60 × AMI. If household_size > 4, threshold = threshold × 1.10. Must satisfy income ≤ threshold. Must not have another subsidy"),
+        method_calculator(ToolCategory::Tax, E::calc_capital_gains_tool_attr, E::calc_capital_gains,
+            "gain = sale_price - acquisition_cost. Rate = long-term rate if holding_period_days ≥ long-term days, else short-term rate. Tax = max(gain - annual_exemption, 0) × rate"),
+        method_calculator(ToolCategory::Tax, E::calc_inheritance_tax_tool_attr, E::calc_inheritance_tax,
+            "for each beneficiary, exemption = class exemption - prior gifts (not below 0). Taxable = amount - exemption. Tax = class progressive brackets applied to taxable"),
+        method_calculator(ToolCategory::Tax, E::calc_social_contributions_tool_attr, E::calc_social_contributions,
+            "for each contribution type, base = min(gross_salary, ceiling). Employee = base × employee_rate, employer = base × employer_rate"),
+        method_calculator(ToolCategory::Tax, E::calc_property_tax_tool_attr, E::calc_property_tax,
+            "capped = min(assessed_value, prior_year_value × (1 + cap)). Taxable = capped - homestead exemption (if homestead). Tax = taxable × millage / 1000"),
+        method_calculator(ToolCategory::Tax, E::calc_transfer_tax_tool_attr, E::calc_transfer_tax,
+            "Slice: tax = price × rate of the slice containing the price. Marginal: each bracket rate × the part of the price within it"),
+        method_calculator(ToolCategory::Tax, E::calc_fee_tool_attr, E::calc_fee,
+            "For each component of the fee code: fixed + rate × max(0, value - above), capped at cap. Fee = sum of components"),
+        method_calculator(ToolCategory::Finance, E::index_amount_tool_attr, E::index_amount,
+            "factor = index[to_year] / index[from_year]; indexed_amount = amount × factor"),
+        method_calculator(ToolCategory::Finance, E::convert_currency_tool_attr, E::convert_currency,
+            "rate = (base per unit of from_currency) / (base per unit of to_currency), using the latest quotes on or before date; converted = amount × rate"),
+        method_calculator(ToolCategory::Eligibility, E::get_intake_schema_tool_attr, E::get_intake_schema,
+            "Fields, types and required flags come from the program tool's input schema; validation hints come from the program's rules"),
+        method_calculator(ToolCategory::Eligibility, E::prescreen_tool_attr, E::prescreen,
+            "Each program rule is decided if its inputs are known (or the known inputs settle it either way). Any failed rule = ineligible; all rules passed = eligible; otherwise undetermined, listing the missing inputs"),
+        method_calculator(ToolCategory::Admin, E::get_usage_report_tool_attr, E::get_usage_report,
+            "Counts recorded since server start: calls per tool, the JSON type each parameter arrived as, and the most frequent errors with quoted values and numbers blanked out"),
+        method_calculator(ToolCategory::Admin, E::list_tools_by_category_tool_attr, E::list_tools_by_category,
+            "Tools are grouped by the category they are registered under (finance, tax, eligibility, governance, admin); each entry carries the tool's title and logic line"),
+        method_calculator(ToolCategory::Admin, E::describe_capabilities_tool_attr, E::describe_capabilities,
+            "structured_content when the negotiated protocol is 2025-06-18 or later; resource_links never (results are inline; calculations are also readable as calc:// resources); sampling_summary when ENGINE_SAMPLING_SUMMARY is on and the client supports sampling"),
+        method_calculator(ToolCategory::Admin, E::get_recent_schema_failures_tool_attr, E::get_recent_schema_failures,
+            "The most recent calls whose arguments did not deserialize, newest first: field names with JSON types in place of values, and the error with values blanked out"),
+        admin_calculator(E::upsert_profile_tool_attr, E::upsert_profile,
+            "The profile is loaded with the environment taking precedence, as at startup; any setting the startup checks would reject or replace fails the call. A valid profile is saved to ENGINE_PROFILE_FILE and put in force, and the configuration version is recomputed"),
+        method_calculator(ToolCategory::Admin, E::diff_profiles_tool_attr, E::diff_profiles,
+            "Settings are compared by value (JSON values by content). Bracket lists (ENGINE_TAX_BRACKETS, ENGINE_TAX_BRACKETS_AFTER_CHANGE, ENGINE_TRANSFER_TAX_BRACKETS) are also compared bracket by bracket from the lowest: limits, rates and cliff flags"),
+        method_calculator(ToolCategory::Eligibility, E::evaluate_ranges_tool_attr, E::evaluate_ranges,
+            "Each range argument is set to its lower and upper end in every combination (2^n calls for n ranges); each numeric or yes/no result field is reported with the lowest and highest value seen, and yes/no fields taking both values are listed as flips. Exact for results that only rise or only fall with each input"),
+        method_calculator(ToolCategory::Eligibility, E::simulate_uncertainty_tool_attr, E::simulate_uncertainty,
+            "Each draw sets every distribution argument to a seeded random value (normal by the Box-Muller transform, uniform by scaling) and runs the tool; numeric result fields report mean and nearest-rank 5th, 25th, 50th, 75th and 95th percentiles over the draws, and yes/no fields the share of draws in which they are true"),
+        method_calculator(ToolCategory::Eligibility, E::check_appeal_deadline_tool_attr, E::check_appeal_deadline,
+            "Business days: the deadline is the window_days-th weekday after the decision date that is not a public holiday. Calendar days: decision date + window_days, moved to the next business day if it falls on a weekend or public holiday. In time if filed on or before the deadline; days remaining or overdue are calendar days between the filing date and the deadline"),
+        method_calculator(ToolCategory::Eligibility, E::get_required_documents_tool_attr, E::get_required_documents,
+            "Every document of the program's configured checklist without a situation, then each document whose situation flag the applicant has, in configured order; unknown flags are reported as warnings"),
+        method_calculator(ToolCategory::Governance, E::check_voting_bulk_tool_attr, E::check_voting_bulk,
+            "eligible_voters, turnout and yes_votes are summed over all precincts and the proposal is checked on the totals as check_voting does: turnout ≥60% of eligible, then yes_votes / turnout > 0.50 (general) or ≥ 2/3 (amendment). A precinct meets quorum when its own turnout is ≥60% of its eligible voters"),
+        method_calculator(ToolCategory::Governance, E::project_quorum_tool_attr, E::project_quorum,
+            "quorum_votes = fewest votes with votes / eligible_voters ≥ 0.60; additional_votes_needed = max(0, quorum_votes - turnout). With hours: projected_turnout = floor(turnout + turnout / hours_elapsed × hours_remaining), at most eligible_voters. At turnout_basis = max(projected or current turnout, quorum_votes), min yes votes: general yes / turnout_basis > 0.50, amendment ≥ 2/3"),
+        admin_calculator(E::stress_config_tool_attr, E::stress_config,
+            "Tax brackets (loaded or tax_brackets) for incomes 0 to 1e12 and a cent either side of each threshold, a 100-bracket table, and calc_penalty defaults for 0 to 10,000 days late with one-off and accrued interest. A check passes when every result is finite, 0 ≤ tax ≤ income × top rate, tax never falls as income rises (tables without cliffs) and results agree with the integer-cent reference"),
+        admin_calculator(E::verify_store_integrity_tool_attr, E::verify_store_integrity,
+            "SQLite integrity check plus decrypting every stored result; rewrap moves data keys to the current store key"),
+        admin_calculator(E::purge_expired_data_tool_attr, E::purge_expired_data,
+            "delete results stored before now − results period; clear usage counts begun before now − usage period"),
+        admin_calculator(E::export_subject_data_tool_attr, E::export_subject_data,
+            "results stored with context.case_id = case_id, in memory and in the embedded store, oldest first"),
+        admin_calculator(E::erase_subject_data_tool_attr, E::erase_subject_data,
+            "delete results stored with context.case_id = case_id and their wrapped data keys; receipt logged under HMAC-SHA256(case hash key, case_id)"),
+    ]
+});
+
+/// All calculators served: every registered one, except admin tools unless the admin role is enabled
+pub(crate) fn calculators() -> impl Iterator<Item = &'static dyn Calculator> {
+    CALCULATORS.iter().map(Box::as_ref).filter(|calculator| *ADMIN_TOOLS || !calculator.admin_only())
+}
+
+/// The registered calculator serving `tool`, if any
+pub(crate) fn calculator(tool: &str) -> Option<&'static dyn Calculator> {
+    calculators().find(|calculator| calculator.tool().name == tool)
+}
+
+impl CompatibilityEngine {
+    /// Tool router with one route per registered calculator
+    pub(crate) fn tool_router() -> ToolRouter<Self> {
+        let mut router = ToolRouter::new();
+        for calculator in calculators() {
+            router.add_route(ToolRoute::new_dyn(calculator.tool(), move |context: ToolCallContext<'_, Self>| {
+                let arguments = context.arguments.clone().unwrap_or_default();
+                // Arguments that do not fit are rejected as a protocol error before computing
+                if let Err(e) = calculator.validate(&arguments) {
+                    analytics::record_schema_failure(&context.name, &arguments, &e.message);
+                    if let Some(suppressed) = log_sampling::admit(&context.name) {
+                        tracing::info!(tool = %context.name, suppressed, "tool call rejected: {}", e.message);
+                    }
+                    return Box::pin(std::future::ready(Err(e)));
+                }
+                let client = context.service.client.get().cloned();
+                let future = calculator.compute(context.service, arguments);
+                Box::pin(async move {
+                    let result = future.await?;
+                    Ok(match client {
+                        Some(client) => client.shape(result),
+                        None => result,
+                    })
+                })
+            }));
+        }
+        router
+    }
+}
+
+// =================== CLIENT CAPABILITIES ===================
+
+/// First protocol version with `structuredContent` in tool results
+const STRUCTURED_CONTENT_PROTOCOL: &str = "2025-06-18";
+
+/// What a client negotiated during initialize, and how tool results are shaped for it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct ClientProfile {
+    #[schemars(description = "Client name from initialize")]
+    pub client_name: String,
+    #[schemars(description = "Client version from initialize")]
+    pub client_version: String,
+    #[schemars(description = "Protocol version in use: the older of the client's and the server's")]
+    pub protocol_version: String,
+    #[schemars(description = "Whether results carry structuredContent next to the JSON text block")]
+    pub structured_content: bool,
+    #[schemars(description = "Whether results are returned as resource links instead of inline (never: results are inline; calculations are also readable as calc:// resources)")]
+    pub resource_links: bool,
+    #[schemars(description = "Whether the client advertised the sampling capability")]
+    pub sampling: bool,
+}
+
+impl ClientProfile {
+    fn negotiate(request: &InitializeRequestParams, server: &ServerInfo) -> Self {
+        // Protocol versions are dates, so the older one sorts first
+        let protocol_version = request.protocol_version.to_string().min(server.protocol_version.to_string());
+        Self {
+            client_name: request.client_info.name.clone(),
+            client_version: request.client_info.version.clone(),
+            structured_content: protocol_version.as_str() >= STRUCTURED_CONTENT_PROTOCOL,
+            protocol_version,
+            resource_links: false,
+            sampling: request.capabilities.sampling.is_some(),
+        }
+    }
+
+    /// Add `structuredContent` to a successful result for clients that understand it. The JSON
+    /// text block stays first, for clients that only read text.
+    fn shape(&self, mut result: CallToolResult) -> CallToolResult {
+        if self.structured_content && !result.is_error.unwrap_or(false) {
+            result.structured_content = result
+                .content
+                .first()
+                .and_then(|content| content.raw.as_text())
+                .and_then(|text| serde_json::from_str::<serde_json::Value>(&text.text).ok())
+                .filter(serde_json::Value::is_object);
+        }
+        result
+    }
+}
+
+// =================== INTAKE FORMS ===================
+
+/// Programs get_intake_schema can describe
+const INTAKE_PROGRAMS: [&str; 2] = ["housing_grant", "voting"];
+
+/// Form field for one tool parameter, with hints derived from the program's rules
+struct IntakeFieldSpec {
+    name: &'static str,
+    field_type: &'static str,
+    validation: Vec<String>,
+    options: &'static [&'static str],
+}
+
+impl IntakeFieldSpec {
+    fn new(name: &'static str, field_type: &'static str, validation: &[&str]) -> Self {
+        Self {
+            name,
+            field_type,
+            validation: validation.iter().map(|hint| hint.to_string()).collect(),
+            options: &[],
+        }
+    }
+
+    fn with_validation(self, validation: Vec<String>) -> Self {
+        Self { validation, ..self }
+    }
+}
+
+/// Outcome of one program rule when pre-screening on partial inputs
+enum RuleOutcome {
+    Passed,
+    Failed(String),
+    /// Inputs still needed to decide the rule
+    Undetermined(Vec<String>),
+}
+
+impl RuleOutcome {
+    fn check(passes: bool, reason: impl FnOnce() -> String) -> Self {
+        if passes { RuleOutcome::Passed } else { RuleOutcome::Failed(reason()) }
+    }
+}
+
+/// Tool and form fields for an intake program
+fn intake_program(program: &str) -> Option<(&'static str, Vec<IntakeFieldSpec>)> {
+    match program {
+        "housing_grant" => Some(("check_housing_grant", {
+            let scale = config().housing_equivalence_scale;
+            let (size_rule, limit_rule) = match scale {
+                EquivalenceScale::Size => (
+                    "Households of more than 4 people get a 10% higher income limit".to_string(),
+                    "Eligible up to 60% of AMI (66% for households of more than 4 people)".to_string(),
+                ),
+                _ => (
+                    format!("The income limit scales with the household's adult equivalents ({} scale)", scale),
+                    "Eligible up to 60% of AMI for 2 adults and 2 children, scaled by adult equivalents".to_string(),
+                ),
+            };
+            let children_rule = if scale.counts_children() {
+                "Each child counts for less than an adult in the income limit".to_string()
+            } else {
+                format!("Does not change the income limit under the {} scale", scale)
+            };
+            vec![
+                IntakeFieldSpec::new("ami", "number", &["Must be greater than 0"]),
+                IntakeFieldSpec::new("household_size", "integer", &[])
+                    .with_validation(vec!["Must be at least 1".to_string(), size_rule]),
+                IntakeFieldSpec::new("children", "integer", &[]).with_validation(vec![
+                    "Must not be negative and must be fewer than household_size, which includes them".to_string(),
+                    children_rule,
+                ]),
+                IntakeFieldSpec::new("income", "number", &[])
+                    .with_validation(vec!["Must not be negative".to_string(), limit_rule]),
+                IntakeFieldSpec::new("has_other_subsidy", "boolean", &[
+                    "Households that already receive another housing subsidy are not eligible",
+                ]),
+                IntakeFieldSpec::new("income_currency", "string", &[]).with_validation(vec![format!(
+                    "Three-letter currency code; defaults to {}",
+                    config().fx.base_currency
+                )]),
+            ]
+        })),
+        "voting" => Some(("check_voting", vec![
+            IntakeFieldSpec::new("eligible_voters", "integer", &["Must be greater than 0"]),
+            IntakeFieldSpec::new("turnout", "integer", &[
+                "Must not exceed eligible_voters",
+                "At least 60% of eligible voters must vote",
+            ]),
+            IntakeFieldSpec::new("yes_votes", "integer", &[
+                "Must not exceed turnout",
+                "General proposals need a simple majority of votes cast; amendments need two thirds",
+            ]),
+            IntakeFieldSpec {
+                options: &["general", "amendment"],
+                ..IntakeFieldSpec::new("proposal_type", "string", &[])
+            },
+        ])),
+        _ => None,
+    }
+}
+
+// =================== COMPLETIONS ===================
+
+/// Values accepted by an enum-like tool parameter, read from the live configuration
+fn argument_values(argument: &str) -> Vec<String> {
+    let fixed = |values: &[&str]| values.iter().map(|value| value.to_string()).collect();
+    match argument {
+        "proposal_type" => fixed(&["general", "amendment"]),
+        "program" => fixed(&["housing_grant", "voting"]),
+        "method" => fixed(&["slice", "marginal"]),
+        "surcharge_base" => fixed(&["tax", "income"]),
+        "audience" => fixed(&["expert", "citizen"]),
+        "relationship" => config().inheritance_classes.keys().cloned().collect(),
+        "fee_code" => config().fee_schedules.keys().cloned().collect(),
+        "from_currency" | "to_currency" | "income_currency" => std::iter::once(&config().fx.base_currency)
+            .chain(config().fx.rates.keys())
+            .cloned()
+            .collect(),
+        "category" => ToolCategory::ALL.iter().map(ToString::to_string).collect(),
+        "tool" => calculators().map(|calculator| calculator.name()).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Completion values for `argument` starting with `prefix` (case-insensitive).
+///
+/// MCP completion references a prompt or a resource; clients complete tool arguments by naming
+/// the tool as the prompt. A named tool restricts completion to its own parameters.
+fn complete_argument(tool: Option<&str>, argument: &str, prefix: &str) -> CompletionInfo {
+    let accepted = tool.is_none_or(|tool| {
+        calculator(tool).is_some_and(|calculator| {
+            calculator
+                .input_schema()
+                .get("properties")
+                .and_then(|properties| properties.get(argument))
+                .is_some()
+        })
+    });
+    let prefix = prefix.trim().to_ascii_lowercase();
+    let values: Vec<String> = if accepted { argument_values(argument) } else { Vec::new() }
+        .into_iter()
+        .filter(|value| value.to_ascii_lowercase().starts_with(&prefix))
+        .collect();
+    let total = values.len();
+    let values = values.into_iter().take(CompletionInfo::MAX_VALUES).collect();
+    CompletionInfo::with_pagination(values, Some(total as u32), total > CompletionInfo::MAX_VALUES)
+        .expect("completion values are capped at MAX_VALUES")
+}
+
+// =================== GOLDEN CALLS ===================
+
+/// Tools left out of the golden calls: their results depend on usage recorded since start and
+/// on toggles or build features that may legitimately switch them off, or they change the
+/// configuration or delete data
+const GOLDEN_EXCLUDED: &[&str] = &[
+    "get_usage_report",
+    "get_recent_schema_failures",
+    "upsert_profile",
+    "verify_store_integrity",
+    "purge_expired_data",
+    "export_subject_data",
+    "erase_subject_data",
+];
+
+/// One known-good call per tool. Arguments that name configured entries (fee codes, heir
+/// classes, currencies) are taken from the live configuration, so the calls fit any deployment.
+fn golden_calls() -> Vec<(&'static str, serde_json::Value)> {
+    use serde_json::json;
+    let first = |argument: &str| argument_values(argument).into_iter().next().unwrap_or_default();
+    let currency = argument_values("from_currency").into_iter().last().unwrap_or_default();
+    vec![
+        ("calc_penalty", json!({"days_late": 12, "rate_per_day": 150, "cap": 1000, "interest_rate": 0.05})),
+        ("calc_tax", json!({"income": 90000})),
+        ("check_voting", json!({"eligible_voters": 100, "turnout": 70, "yes_votes": 50, "proposal_type": "general"})),
+        ("validate_vote_record", json!({"eligible_voters": 100, "turnout": 70, "yes_votes": 40, "no_votes": 30})),
+        ("distribute_waterfall", json!({"cash_available": 1000, "senior_debt": 600, "junior_debt": 300})),
+        ("check_covenants", json!({"total_debt": 3000, "ebitda": 1000, "interest_expense": 200})),
+        ("calc_npv", json!({"cash_flows": [-1000, 300, 400, 500], "discount_rate": 0.08})),
+        ("calc_irr", json!({"cash_flows": [-1000, 300, 400, 500]})),
+        ("check_escrow_release", json!({"escrow_amount": 10000, "as_of_date": "2024-06-01", "milestones": [{"name": "Design", "completed": true, "release_percentage": 0.4}]})),
+        ("check_housing_grant", json!({"ami": 80000, "household_size": 5, "income": 45000, "has_other_subsidy": false})),
+        ("calc_capital_gains", json!({"acquisition_cost": 10000, "sale_price": 15000, "holding_period_days": 400})),
+        ("calc_inheritance_tax", json!({"beneficiaries": [{"name": "Ana", "relationship": first("relationship"), "amount": 250000}]})),
+        ("calc_social_contributions", json!({"gross_salary": 5000})),
+        ("calc_property_tax", json!({"assessed_value": 300000, "prior_year_value": 250000, "homestead": true})),
+        ("calc_transfer_tax", json!({"price": 350000})),
+        ("calc_fee", json!({"fee_code": first("fee_code"), "value": 1200})),
+        ("index_amount", json!({"amount": 1000})),
+        ("convert_currency", json!({"amount": 100, "from_currency": currency})),
+        ("get_intake_schema", json!({"program": "housing_grant"})),
+        ("prescreen", json!({"program": "housing_grant", "inputs": {"has_other_subsidy": true}})),
+        ("list_tools_by_category", json!({"category": "finance"})),
+        ("describe_capabilities", json!({})),
+        ("diff_profiles", json!({
+            "before": {"ENGINE_TAX_BRACKETS": {"value": [{"up_to": 10000, "rate": 0.1}, {"rate": 0.2}]}},
+            "after": {"ENGINE_TAX_BRACKETS": {"value": [{"up_to": 12000, "rate": 0.1}, {"rate": 0.22}]}},
+        })),
+        ("check_appeal_deadline", json!({"decision_date": "2026-03-02", "filing_date": "2026-03-30"})),
+        ("get_required_documents", json!({"program": "housing_grant", "situation": ["self_employed"]})),
+        ("check_voting_bulk", json!({"proposal_type": "general", "precincts": [
+            {"name": "North", "eligible_voters": 60, "turnout": 45, "yes_votes": 30},
+            {"name": "South", "eligible_voters": 40, "turnout": 20, "yes_votes": 12},
+        ]})),
+        ("project_quorum", json!({"eligible_voters": 200, "turnout": 90, "yes_votes": 50, "hours_elapsed": 6, "hours_remaining": 2})),
+        ("stress_config", json!({})),
+        ("evaluate_ranges", json!({"tool": "check_housing_grant", "arguments": {"ami": 80000, "household_size": 5, "income": {"min": 50000, "max": 56000}, "has_other_subsidy": false}})),
+        ("simulate_uncertainty", json!({"tool": "check_housing_grant", "arguments": {"ami": 80000, "household_size": 5, "income": {"distribution": "normal", "mean": 52000, "sd": 2000}, "has_other_subsidy": false}, "draws": 200})),
+    ]
+}
+
+/// Outcome of one golden call
+#[derive(Debug, Clone, PartialEq)]
+pub struct GoldenOutcome {
+    pub tool: String,
+    /// None when the call succeeded, otherwise why it failed
+    pub failure: Option<String>,
+}
+
+/// Run the golden call of every calculator through validation and computation. A calculator
+/// without a golden call fails, so new tools cannot slip past the preflight check.
+pub async fn run_golden_calls(engine: &CompatibilityEngine) -> Vec<GoldenOutcome> {
+    let mut calls: BTreeMap<&str, serde_json::Value> = golden_calls().into_iter().collect();
+    let mut outcomes = Vec::new();
+    for calculator in calculators().filter(|calculator| !GOLDEN_EXCLUDED.contains(&calculator.name().as_str())) {
+        let tool = calculator.name();
+        let failure = match calls.remove(tool.as_str()) {
+            None => Some("no golden call defined".to_string()),
+            Some(serde_json::Value::Object(arguments)) => match calculator.validate(&arguments) {
+                Err(e) => Some(format!("arguments rejected: {}", e.message)),
+                Ok(()) => match calculator.compute(engine, arguments).await {
+                    Err(e) => Some(e.message.to_string()),
+                    Ok(result) if result.is_error.unwrap_or(false) => Some(
+                        result
+                            .content
+                            .first()
+                            .and_then(|content| content.raw.as_text())
+                            .map(|text| text.text.clone())
+                            .unwrap_or_else(|| "error result".to_string()),
+                    ),
+                    Ok(_) => None,
+                },
+            },
+            Some(_) => Some("golden arguments are not an object".to_string()),
+        };
+        outcomes.push(GoldenOutcome { tool, failure });
+    }
+    outcomes
+}
+
+// =================== BUSINESS DAYS ===================
+
+/// Whether `date` is a business day: Monday to Friday and not a public holiday
+fn is_business_day(date: NaiveDate, holidays: &BTreeSet<NaiveDate>) -> bool {
+    date.weekday().number_from_monday() <= 5 && !holidays.contains(&date)
+}
+
+/// The first business day after `date`
+fn next_business_day(date: NaiveDate, holidays: &BTreeSet<NaiveDate>) -> NaiveDate {
+    let mut next = date.succ_opt().unwrap_or(date);
+    while !is_business_day(next, holidays) {
+        next = next.succ_opt().unwrap_or(next);
+    }
+    next
+}
+
+// =================== WHAT-IF EVALUATION ===================
+
+/// Most arguments `evaluate_ranges` accepts as ranges (2^6 = 64 calls)
+const MAX_RANGES: usize = 6;
+
+/// Default and most draws `simulate_uncertainty` makes
+const DEFAULT_DRAWS: i32 = 1000;
+const MAX_DRAWS: i32 = 10_000;
+
+/// Seed of `simulate_uncertainty` when none is given
+const DEFAULT_SEED: i32 = 1;
+
+/// Tools that run other tools, which a what-if tool does not run in turn
+const WHAT_IF_TOOLS: [&str; 2] = ["evaluate_ranges", "simulate_uncertainty"];
+
+/// Parameters a what-if tool sets itself on the calls it makes
+const WHAT_IF_OVERRIDDEN: [&str; 2] = ["response_version", "fields"];
+
+/// The calculator a what-if tool may run: any calculation outside the admin category, other than
+/// the what-if tools themselves
+fn what_if_calculator(tool: &str, what_if: &str) -> Result<&'static dyn Calculator, String> {
+    match calculator(tool) {
+        Some(calculator) if calculator.category() != ToolCategory::Admin && !WHAT_IF_TOOLS.contains(&calculator.name().as_str()) => Ok(calculator),
+        Some(_) => Err(format!("{} cannot evaluate '{}'; choose a calculation or eligibility tool", what_if, tool)),
+        None => Err(format!("unknown tool '{}'", sanitize_for_error_message(tool))),
+    }
+}
+
+/// Arguments without ranges, and each range argument with its raw lower and upper end
+#[allow(clippy::type_complexity)]
+fn split_ranges(
+    arguments: &BTreeMap<String, serde_json::Value>,
+) -> Result<(JsonObject, Vec<(String, serde_json::Value, serde_json::Value)>), String> {
+    let mut base = JsonObject::new();
+    let mut ranges = Vec::new();
+    for (name, value) in arguments {
+        if WHAT_IF_OVERRIDDEN.contains(&name.as_str()) {
+            continue;
+        }
+        match value.as_object() {
+            Some(object) if object.contains_key("min") || object.contains_key("max") => {
+                match (object.get("min"), object.get("max"), object.len()) {
+                    (Some(min), Some(max), 2) => ranges.push((name.clone(), min.clone(), max.clone())),
+                    _ => return Err(format!("'{}' must be a range with exactly 'min' and 'max'", sanitize_for_error_message(name))),
+                }
+            }
+            _ => {
+                base.insert(name.clone(), value.clone());
+            }
+        }
+    }
+    Ok((base, ranges))
+}
+
+/// Arguments without distributions, and each distribution argument with its kind and parameters
+#[allow(clippy::type_complexity)]
+fn split_distributions(
+    arguments: &BTreeMap<String, serde_json::Value>,
+) -> Result<(JsonObject, Vec<(String, String, JsonObject)>), String> {
+    let mut base = JsonObject::new();
+    let mut distributions = Vec::new();
+    for (name, value) in arguments {
+        if WHAT_IF_OVERRIDDEN.contains(&name.as_str()) {
+            continue;
+        }
+        match value.as_object() {
+            Some(object) if object.contains_key("distribution") => {
+                let kind = object["distribution"]
+                    .as_str()
+                    .ok_or_else(|| format!("'{}' distribution must be 'normal' or 'uniform'", sanitize_for_error_message(name)))?;
+                distributions.push((name.clone(), kind.to_string(), object.clone()));
+            }
+            _ => {
+                base.insert(name.clone(), value.clone());
+            }
+        }
+    }
+    Ok((base, distributions))
+}
+
+/// Whether `calculator` declares argument `name` as an integer, so drawn values are rounded
+fn integer_argument(calculator: &dyn Calculator, name: &str) -> bool {
+    let schema = calculator.input_schema();
+    match schema.get("properties").and_then(|properties| properties.get(name)).and_then(|property| property.get("type")) {
+        Some(serde_json::Value::String(kind)) => kind == "integer",
+        Some(serde_json::Value::Array(kinds)) => kinds.contains(&serde_json::json!("integer")),
+        _ => false,
+    }
+}
+
+/// Nearest-rank percentile of sorted values
+fn percentile(sorted: &[f64], share: f64) -> f64 {
+    let rank = (share * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// Run `calculator` for a what-if tool and return the payload of its result, or the message of
+/// the error it answered with. Calls ask for the bare version 1 payload, so what-if runs are not
+/// kept in the result store.
+async fn what_if_payload(
+    engine: &CompatibilityEngine,
+    calculator: &dyn Calculator,
+    mut arguments: JsonObject,
+) -> Result<serde_json::Value, String> {
+    arguments.insert("response_version".to_string(), serde_json::Value::from(1));
+    // Only the numbers and flags are compared, so the calls skip their explanations
+    arguments.insert("include_explanations".to_string(), serde_json::Value::from(false));
+    arguments.remove("explanation_graph");
+    calculator.validate(&arguments).map_err(|e| e.message.to_string())?;
+    let result = calculator.compute(engine, arguments).await.map_err(|e| e.message.to_string())?;
+    let text = result.content.first().and_then(|content| content.raw.as_text()).map(|text| text.text.as_str()).unwrap_or_default();
+    let value: Option<serde_json::Value> = serde_json::from_str(text).ok();
+    if result.is_error.unwrap_or(false) {
+        // needs_input and needs_confirmation replies carry their own message
+        return Err(value.as_ref().and_then(|value| value["message"].as_str()).unwrap_or(text).to_string());
+    }
+    value.ok_or_else(|| format!("{} returned no payload", calculator.name()))
+}
+
+/// Numeric and yes/no fields of a payload, with nested objects flattened to dotted paths
+fn outcome_fields(value: &serde_json::Value, prefix: &str, fields: &mut BTreeMap<String, serde_json::Value>) {
+    let Some(object) = value.as_object() else {
+        return;
+    };
+    for (name, value) in object {
+        let path = if prefix.is_empty() { name.clone() } else { format!("{}.{}", prefix, name) };
+        match value {
+            serde_json::Value::Number(_) | serde_json::Value::Bool(_) => {
+                fields.insert(path, value.clone());
+            }
+            serde_json::Value::Object(_) => outcome_fields(value, &path, fields),
+            _ => {}
+        }
+    }
+}
+
+/// Export rows of evaluations made at `points`: the numbered point, its arguments and the result fields
+fn export_rows(
+    index: &str,
+    points: &[BTreeMap<String, serde_json::Value>],
+    evaluations: &[(String, Result<serde_json::Value, String>)],
+) -> Vec<BTreeMap<String, serde_json::Value>> {
+    points
+        .iter()
+        .zip(evaluations)
+        .enumerate()
+        .filter_map(|(number, (point, (_, evaluation)))| {
+            let mut row = BTreeMap::from([(index.to_string(), serde_json::Value::from(number + 1))]);
+            row.extend(point.iter().map(|(name, value)| (format!("arguments.{}", name), value.clone())));
+            outcome_fields(evaluation.as_ref().ok()?, "", &mut row);
+            Some(row)
+        })
+        .collect()
+}
+
+/// Order of result values: numbers by value, false before true
+fn outcome_less(a: &serde_json::Value, b: &serde_json::Value) -> bool {
+    match (a, b) {
+        (serde_json::Value::Bool(a), serde_json::Value::Bool(b)) => !a & b,
+        _ => a.as_f64().zip(b.as_f64()).is_some_and(|(a, b)| a < b),
+    }
+}
+
+// =================== COMPATIBILITY ENGINE ===================
+
+#[derive(Debug, Clone)]
+pub struct CompatibilityEngine {
+    tool_router: ToolRouter<Self>,
+    /// Negotiated during initialize; unset until then (and in stateless HTTP sessions)
+    client: Arc<OnceLock<ClientProfile>>,
+    /// Lowest log level the client asked for; deadline reminders are sent at notice
+    log_level: reminders::SessionLevel,
+}
+
+impl CompatibilityEngine {
+    /// Calculate penalty with cap and interest. With an accrual period (due date, valuation date),
+    /// the interest rate is annual and accrues on the capped penalty over the days between them.
+    fn calc_penalty_internal(
+        days_late: f64, 
+        rate_per_day: f64, 
+        cap: f64, 
+        interest_rate: f64,
+        accrual: Option<(NaiveDate, NaiveDate)>,
+        audience: Audience,
+    ) -> CalcPenaltyResponse {
+        let mut errors = Vec::new();
+        let mut warnings = Vec::new();
+        let mut explanation_parts = Vec::new();
+        
+        // Validation
+        if days_late < 0.0 {
+            errors.push("Days late cannot be negative".to_string());
+        }
+        if rate_per_day < 0.0 {
+            errors.push("Rate per day cannot be negative".to_string());
+        }
+        if cap < 0.0 {
+            errors.push("Cap cannot be negative".to_string());
+        }
+        if interest_rate < 0.0 {
+            errors.push("Interest rate cannot be negative".to_string());
+        }
+        if let Some((due_date, valuation_date)) = accrual
+            && valuation_date < due_date
+        {
+            errors.push(format!("Valuation date {} is before the due date {}", valuation_date, due_date));
+        }
+        
+        if !errors.is_empty() {
+            return CalcPenaltyResponse {
+                penalty: 0.0,
+                base_penalty: 0.0,
+                cap_applied: false,
+                capped_penalty: 0.0,
+                interest_amount: 0.0,
+                due_date: None,
+                valuation_date: None,
+                interest_days: None,
+                explanation: explain(audience, "calc_penalty.invalid_inputs", context! {}),
+                assumptions: Vec::new(),
+                errors,
+                warnings,
+            };
+        }
+        
+        // Calculate base penalty
+        let base_penalty = days_late * rate_per_day;
+        explanation_parts.push(explain(audience, "calc_penalty.base_penalty", context! { days_late, rate_per_day, base_penalty }));
+        
+        // Apply cap
+        let penalty = base_penalty.min(cap);
+        let capped = base_penalty > cap;
+        explanation_parts.push(explain(audience, "calc_penalty.cap", context! { capped, base_penalty, cap }));
+        if capped {
+            warnings.push(format!("Base penalty {:.2} exceeded cap of {:.2}", base_penalty, cap));
+        }
+        
+        // Calculate interest, once or accrued to the valuation date
+        let interest_days = accrual.map(|(due_date, valuation_date)| (valuation_date - due_date).num_days());
+        let interest = match (accrual, interest_days) {
+            (Some((due_date, valuation_date)), Some(interest_days)) => {
+                let interest = penalty * interest_rate * interest_days as f64 / INTEREST_DAY_BASIS;
+                explanation_parts.push(explain(audience, "calc_penalty.accrued_interest", context! {
+                    penalty,
+                    interest_rate,
+                    interest_days,
+                    due_date => due_date.to_string(),
+                    valuation_date => valuation_date.to_string(),
+                    interest,
+                }));
+                interest
+            }
+            _ => {
+                let interest = penalty * interest_rate;
+                explanation_parts.push(explain(audience, "calc_penalty.interest", context! { penalty, interest_rate, interest }));
+                interest
+            }
+        };
+        
+        let final_penalty = penalty + interest;
+        explanation_parts.push(explain(audience, "calc_penalty.final", context! { penalty, interest, final_penalty }));
+        
+        if interest_rate > 0.1 {
+            warnings.push(format!("High interest rate: {:.1}%", interest_rate * 100.0));
+        }
+        
+        CalcPenaltyResponse {
+            penalty: final_penalty,
+            base_penalty,
+            cap_applied: capped,
+            capped_penalty: penalty,
+            interest_amount: interest,
+            due_date: accrual.map(|(due_date, _)| due_date.to_string()),
+            valuation_date: accrual.map(|(_, valuation_date)| valuation_date.to_string()),
+            interest_days,
+            explanation: explanation_parts.join(". "),
+            assumptions: Vec::new(),
+            errors,
+            warnings,
+        }
+    }
+
+    /// Apply progressive brackets to an income. Reaching a cliff bracket replaces the tax so far
+    /// with its rate × all income up to its top.
+    ///
+    /// The tax is looked up in the table. Given `brackets`, every bracket used is also listed there
+    /// with one explanation step each, which takes time in the number of brackets below the
+    /// income; without it a single step reports the lookup.
+    fn apply_brackets(
+        income: f64,
+        table: &BracketTable,
+        audience: Audience,
+        explanation_parts: &mut Vec<String>,
+        brackets: Option<&mut Vec<BracketResult>>,
+    ) -> f64 {
+        if income <= 0.0 {
+            return 0.0;
+        }
+        let reached = table.bracket(income);
+        let Some(brackets) = brackets else {
+            let tax = table.tax(income);
+            explanation_parts.push(explain(audience, "calc_tax.bracket_lookup", context! {
+                income,
+                index => reached + 1,
+                lower => table.lower(reached),
+                rate => table.rates[reached],
+                cliff => Self::is_cliff(&table.cliffs, reached),
+                tax,
+            }));
+            return tax;
+        };
+
+        for i in 0..reached {
+            let prev_threshold = table.lower(i);
+            let threshold = table.thresholds[i];
+            let rate = table.rates[i];
+
+            if Self::is_cliff(&table.cliffs, i) {
+                let tax = table.cumulative[i];
+                brackets.push(BracketResult {
+                    lower: prev_threshold,
+                    upper: Some(threshold),
+                    rate,
+                    taxable: threshold,
+                    tax,
+                    cliff: true,
+                    period: None,
+                });
+                explanation_parts.push(explain(audience, "calc_tax.cliff_bracket", context! {
+                    index => i + 1,
+                    lower => prev_threshold,
+                    taxable => threshold,
+                    rate,
+                    tax,
+                }));
+                continue;
+            }
+
+            let taxable_in_bracket = threshold - prev_threshold;
+            let bracket_tax = taxable_in_bracket * rate;
+            brackets.push(BracketResult {
+                lower: prev_threshold,
+                upper: Some(threshold),
+                rate,
+                taxable: taxable_in_bracket,
+                tax: bracket_tax,
+                cliff: false,
+                period: None,
+            });
+            explanation_parts.push(explain(audience, "calc_tax.bracket", context! {
+                index => i + 1,
+                lower => prev_threshold,
+                upper => threshold,
+                taxable => taxable_in_bracket,
+                rate,
+                bracket_tax,
+            }));
+        }
+
+        // The bracket the income falls in, which is the highest one above the last threshold
+        let tax = table.tax(income);
+        let prev_threshold = table.lower(reached);
+        let upper = table.thresholds.get(reached).copied();
+        let rate = table.rates[reached];
+        if Self::is_cliff(&table.cliffs, reached) {
+            brackets.push(BracketResult {
+                lower: prev_threshold,
+                upper,
+                rate,
+                taxable: income,
+                tax,
+                cliff: true,
+                period: None,
+            });
+            explanation_parts.push(explain(audience, "calc_tax.cliff_bracket", context! {
+                index => reached + 1,
+                lower => prev_threshold,
+                taxable => income,
+                rate,
+                tax,
+            }));
+            return tax;
+        }
+
+        let taxable_in_bracket = income - prev_threshold;
+        let bracket_tax = taxable_in_bracket * rate;
+        brackets.push(BracketResult {
+            lower: prev_threshold,
+            upper,
+            rate,
+            taxable: taxable_in_bracket,
+            tax: bracket_tax,
+            cliff: false,
+            period: None,
+        });
+        explanation_parts.push(match upper {
+            Some(threshold) => explain(audience, "calc_tax.bracket", context! {
+                index => reached + 1,
+                lower => prev_threshold,
+                upper => threshold,
+                taxable => taxable_in_bracket,
+                rate,
+                bracket_tax,
+            }),
+            None => explain(audience, "calc_tax.highest_bracket", context! {
+                lower => prev_threshold,
+                taxable => taxable_in_bracket,
+                rate,
+                bracket_tax,
+            }),
+        });
+
+        tax
+    }
+
+    /// Regular tax: progressive brackets (prorated across a mid-year schedule change) plus surcharges
+    fn regular_tax(
+        taxable_income: f64,
+        rules: &TaxRules,
+        itemize_brackets: bool,
+        audience: Audience,
+        explanation_parts: &mut Vec<String>,
+    ) -> RegularTax {
+        let mut proration = None;
+        let mut brackets = Vec::new();
+        let mut tax = match &rules.schedule_change {
+            None => Self::apply_brackets(
+                taxable_income,
+                &rules.brackets,
+                audience,
+                explanation_parts,
+                itemize_brackets.then_some(&mut brackets),
+            ),
+            Some((change_date, change)) => {
+                // Tax the full-year income under each schedule, then weight by the days each was in force
+                let year = change_date.year();
+                let year_start = NaiveDate::from_ymd_opt(year, 1, 1).unwrap_or(*change_date);
+                let next_year_start = NaiveDate::from_ymd_opt(year + 1, 1, 1).unwrap_or(*change_date);
+                let total_days = (next_year_start - year_start).num_days();
+                let days_before = (*change_date - year_start).num_days();
+                let days_after = total_days - days_before;
+                let effective_date = change_date.to_string();
+
+                explanation_parts.push(explain(audience, "calc_tax.schedule_period", context! {
+                    before => true,
+                    effective_date,
+                    days => days_before,
+                    total_days,
+                }));
+                let tax_before = Self::apply_brackets(
+                    taxable_income,
+                    &rules.brackets,
+                    audience,
+                    explanation_parts,
+                    itemize_brackets.then_some(&mut brackets),
+                );
+                for bracket in &mut brackets {
+                    bracket.period = Some("before_change".to_string());
+                }
+                let brackets_before = brackets.len();
+                explanation_parts.push(explain(audience, "calc_tax.schedule_period", context! {
+                    before => false,
+                    effective_date,
+                    days => days_after,
+                    total_days,
+                }));
+                let tax_after = Self::apply_brackets(
+                    taxable_income,
+                    change,
+                    audience,
+                    explanation_parts,
+                    itemize_brackets.then_some(&mut brackets),
+                );
+                for bracket in &mut brackets[brackets_before..] {
+                    bracket.period = Some("after_change".to_string());
+                }
+
+                let tax = (tax_before * days_before as f64 + tax_after * days_after as f64) / total_days as f64;
+                explanation_parts.push(explain(audience, "calc_tax.prorated", context! {
+                    before_tax => tax_before,
+                    before_days => days_before,
+                    after_tax => tax_after,
+                    after_days => days_after,
+                    total_days,
+                    tax,
+                }));
+                proration = Some(TaxProration {
+                    effective_date,
+                    days_before_change: days_before,
+                    days_after_change: days_after,
+                    tax_before_change: tax_before,
+                    tax_after_change: tax_after,
+                });
+                tax
+            }
+        };
+        
+        explanation_parts.push(explain(audience, "calc_tax.subtotal", context! { tax }));
+        let subtotal = tax;
+
+        // Apply surcharges in order, each on the tax so far or on income above its threshold
+        let mut surcharges = Vec::with_capacity(rules.surcharges.len());
+        for rule in &rules.surcharges {
+            let base = rule.base.unwrap_or(rules.surcharge_base);
+            let (applied, surcharge) = match base {
+                SurchargeBase::Tax => {
+                    let subtotal = tax;
+                    let applied = tax > rule.threshold;
+                    let surcharge = if applied { tax * rule.rate } else { 0.0 };
+                    tax += surcharge;
+                    explanation_parts.push(explain(audience, "calc_tax.surcharge", context! {
+                        name => rule.name,
+                        applied,
+                        subtotal,
+                        threshold => rule.threshold,
+                        rate => rule.rate,
+                        surcharge,
+                        tax,
+                    }));
+                    (applied, surcharge)
+                }
+                SurchargeBase::Income => {
+                    let excess = (taxable_income - rule.threshold).max(0.0);
+                    let applied = excess > 0.0;
+                    let surcharge = excess * rule.rate;
+                    tax += surcharge;
+                    explanation_parts.push(explain(audience, "calc_tax.income_surcharge", context! {
+                        name => rule.name,
+                        applied,
+                        income => taxable_income,
+                        threshold => rule.threshold,
+                        excess,
+                        rate => rule.rate,
+                        surcharge,
+                        tax,
+                    }));
+                    (applied, surcharge)
+                }
+            };
+            surcharges.push(SurchargeLine {
+                name: rule.name.clone(),
+                base,
+                threshold: rule.threshold,
+                rate: rule.rate,
+                applied,
+                amount: surcharge,
+            });
+        }
+
+        let surcharge = SurchargeTotal {
+            applied: surcharges.iter().any(|line| line.applied),
+            subtotal,
+            amount: surcharges.iter().map(|line| line.amount).sum(),
+        };
+        RegularTax { tax, proration, brackets, surcharge, surcharges }
+    }
+
+    /// Calculate progressive tax with surcharge
+    fn calc_tax_internal(
+        income: f64,
+        loss_carryforward: f64,
+        amt_adjustments: f64,
+        rules: &TaxRules,
+        itemize_brackets: bool,
+        audience: Audience,
+    ) -> CalcTaxResponse {
+        let mut errors = Vec::new();
+        let mut warnings = Vec::new();
+        let mut explanation_parts = Vec::new();
+        let thresholds = &rules.brackets.thresholds;
+        let rates = &rules.brackets.rates;
+        
+        // Validation
+        if loss_carryforward < 0.0 {
+            errors.push("Loss carryforward cannot be negative".to_string());
+        }
+        if amt_adjustments < 0.0 {
+            errors.push("AMT adjustments cannot be negative".to_string());
+        }
+        if !rules.brackets.complete() {
+            errors.push(format!("Invalid bracket configuration: {} rates for {} thresholds (should be {} rates)", 
+                rates.len(), thresholds.len(), thresholds.len() + 1));
+        }
+        if let Some((change_date, change)) = rules.schedule_change.as_ref().filter(|(_, change)| !change.complete()) {
+            errors.push(format!("Invalid bracket configuration after {}: {} rates for {} thresholds (should be {} rates)",
+                change_date, change.rates.len(), change.thresholds.len(), change.thresholds.len() + 1));
+        }
+        for rule in &rules.surcharges {
+            if rule.threshold < 0.0 {
+                errors.push(format!("{} threshold cannot be negative", rule.name));
+            }
+            if rule.rate < 0.0 {
+                errors.push(format!("{} rate cannot be negative", rule.name));
+            }
+        }
+        
+        // Check if thresholds are sorted
+        if !rules.brackets.ascending {
+            errors.push("Tax thresholds must be in ascending order".to_string());
+        }
+        if let Some((change_date, _)) = rules.schedule_change.as_ref().filter(|(_, change)| !change.ascending) {
+            errors.push(format!("Tax thresholds after {} must be in ascending order", change_date));
+        }
+        
+        if !errors.is_empty() {
+            return CalcTaxResponse {
+                tax: 0.0,
+                taxable_income: 0.0,
+                remaining_carryforward: 0.0,
+                surcharge_base: rules.surcharge_base,
+                brackets: Vec::new(),
+                surcharge: SurchargeTotal::default(),
+                surcharges: Vec::new(),
+                proration: None,
+                minimum_tax: None,
+                explanation: explain(audience, "calc_tax.invalid_inputs", context! {}),
+                assumptions: Vec::new(),
+                errors,
+                warnings,
+            };
+        }
+
+        explanation_parts.push(explain(audience, "calc_tax.starting_income", context! { income }));
+
+        // Offset positive income with prior losses; a current-year loss is added to the carryforward
+        let applied_carryforward = loss_carryforward.min(income.max(0.0));
+        let taxable_income = income.max(0.0) - applied_carryforward;
+        let current_loss = (-income).max(0.0);
+        let remaining_carryforward = loss_carryforward - applied_carryforward + current_loss;
+        if loss_carryforward > 0.0 || current_loss > 0.0 {
+            explanation_parts.push(explain(audience, "calc_tax.loss_carryforward", context! {
+                available => loss_carryforward,
+                applied => applied_carryforward,
+                current_loss,
+                remaining => remaining_carryforward,
+                taxable_income,
+            }));
+        }
+
+        let RegularTax { tax: regular_tax, proration, brackets, surcharge, surcharges } = if taxable_income > 0.0 {
+            Self::regular_tax(taxable_income, rules, itemize_brackets, audience, &mut explanation_parts)
+        } else {
+            explanation_parts.push(explain(audience, "calc_tax.no_taxable_income", context! { taxable_income }));
+            RegularTax::default()
+        };
+        let mut tax = regular_tax;
+
+        // Alternative minimum tax: flat rate on adjusted income above the exemption, if it exceeds the regular tax
+        let mut minimum_tax = None;
+        if let Some(amt) = &rules.minimum_tax {
+            let adjusted_income = taxable_income.max(0.0) + amt_adjustments;
+            let amt_base = (adjusted_income - amt.exemption).max(0.0);
+            let amt_tax = amt_base * amt.rate;
+            let applied = amt_tax > regular_tax;
+            if applied {
+                tax = amt_tax;
+            }
+            explanation_parts.push(explain(audience, "calc_tax.minimum_tax", context! {
+                adjusted_income,
+                adjustments => amt_adjustments,
+                exemption => amt.exemption,
+                rate => amt.rate,
+                amt_tax,
+                regular_tax,
+                applied,
+                tax,
+            }));
+            minimum_tax = Some(MinimumTaxBreakdown {
+                adjusted_income,
+                exemption: amt.exemption,
+                rate: amt.rate,
+                minimum_tax: amt_tax,
+                regular_tax,
+                applied,
+            });
+        }
+        
+        for rule in rules.surcharges.iter().filter(|rule| rule.rate > 0.05) {
+            warnings.push(format!("High {} rate: {:.1}%", rule.name.to_lowercase(), rule.rate * 100.0));
+        }
+        
+        CalcTaxResponse {
+            tax,
+            taxable_income,
+            remaining_carryforward,
+            surcharge_base: rules.surcharge_base,
+            brackets,
+            surcharge,
+            surcharges,
+            proration,
+            minimum_tax,
+            explanation: explanation_parts.join(". "),
+            assumptions: Vec::new(),
+            errors,
+            warnings,
+        }
+    }
+
+    /// Check if voting proposal passes
+    fn check_voting_internal(
+        eligible_voters: i32,
+        turnout: i32,
+        yes_votes: i32,
+        proposal_type: &str,
+        audience: Audience,
+    ) -> CheckVotingResponse {
+        let mut errors = Vec::new();
+        let mut warnings = Vec::new();
+        let mut explanation_parts = Vec::new();
+        
+        // Validation
+        if eligible_voters <= 0 {
+            errors.push("Eligible voters must be positive".to_string());
+        }
+        if turnout < 0 {
+            errors.push("Turnout cannot be negative".to_string());
+        }
+        if yes_votes < 0 {
+            errors.push("Yes votes cannot be negative".to_string());
+        }
+        if turnout > eligible_voters {
+            errors.push("Turnout cannot exceed eligible voters".to_string());
+        }
+        if yes_votes > turnout {
+            errors.push("Yes votes cannot exceed turnout".to_string());
+        }
+        if !matches!(proposal_type, "general" | "amendment") {
+            errors.push(format!("Invalid proposal type '{}' (must be 'general' or 'amendment')", proposal_type));
+        }
+        
+        if !errors.is_empty() {
+            return CheckVotingResponse {
+                passes: false,
+                turnout_percentage: 0.0,
+                approval_percentage: 0.0,
+                required_turnout: 0.0,
+                required_approval: 0.0,
+                explanation: explain(audience, "check_voting.invalid_inputs", context! {}),
+                assumptions: Vec::new(),
+                errors,
+                warnings,
+            };
+        }
+        
+        // Check minimum turnout (60%)
+        let required_turnout = 0.60;
+        let required_approval = if proposal_type == "amendment" { 2.0 / 3.0 } else { 0.50 };
+        let approval_percentage = if turnout > 0 { yes_votes as f64 / turnout as f64 } else { 0.0 };
+        let turnout_percentage = turnout as f64 / eligible_voters as f64;
+        explanation_parts.push(explain(audience, "check_voting.turnout", context! {
+            turnout, eligible_voters, turnout_ratio => turnout_percentage,
+        }));
+        
+        let turnout_passes = turnout_percentage >= required_turnout;
+        explanation_parts.push(explain(audience, "check_voting.turnout_requirement", context! { passes => turnout_passes }));
+        if !turnout_passes {
+            return CheckVotingResponse {
+                passes: false,
+                turnout_percentage,
+                approval_percentage,
+                required_turnout,
+                required_approval,
+                explanation: explanation_parts.join(". "),
+                assumptions: Vec::new(),
+                errors,
+                warnings,
+            };
+        }
+        
+        // Check voting threshold based on proposal type
+        let yes_percentage = approval_percentage;
+        explanation_parts.push(explain(audience, "check_voting.yes_votes", context! {
+            yes_votes, turnout, yes_ratio => yes_percentage,
+        }));
+        
+        let passes = match proposal_type {
+            "general" => {
+                let required = 50.0;
+                let passes = yes_percentage > required_approval;
+                explanation_parts.push(explain(audience, "check_voting.general_threshold", context! {
+                    required, yes_ratio => yes_percentage, passes,
+                }));
+                passes
+            },
+            "amendment" => {
+                let required = 66.7;
+                let passes = yes_percentage >= required_approval;
+                explanation_parts.push(explain(audience, "check_voting.amendment_threshold", context! {
+                    required, yes_ratio => yes_percentage, passes,
+                }));
+                passes
+            },
+            _ => false,
+        };
+        
+        explanation_parts.push(explain(audience, "check_voting.result", context! { passes }));
+        
+        if turnout_percentage < 0.70 {
+            warnings.push("Low turnout (below 70%)".to_string());
+        }
+        if turnout > 0 && yes_votes == 0 {
+            warnings.push("No yes votes recorded".to_string());
+        }
+        
+        CheckVotingResponse {
+            passes,
+            turnout_percentage,
+            approval_percentage,
+            required_turnout,
+            required_approval,
+            explanation: explanation_parts.join(". "),
+            assumptions: Vec::new(),
+            errors,
+            warnings,
+        }
+    }
+
+    /// Cross-check vote record tallies without determining pass/fail
+    fn validate_vote_record_internal(
+        eligible_voters: i32,
+        turnout: i32,
+        yes_votes: i32,
+        no_votes: i32,
+        abstain_votes: i32,
+        invalid_votes: i32,
+        audience: Audience,
+    ) -> ValidateVoteRecordResponse {
+        let errors = Vec::new();
+        let warnings = Vec::new();
+        let mut explanation_parts = Vec::new();
+        let mut inconsistencies = Vec::new();
+
+        // Every count must be non-negative (eligible voters must also be positive)
+        if eligible_voters <= 0 {
+            inconsistencies.push(VoteRecordInconsistency {
+                code: "non_positive_eligible".to_string(),
+                fields: vec!["eligible_voters".to_string()],
+                message: format!("Eligible voters must be positive (got {})", eligible_voters),
+            });
+        }
+        let counts = [
+            ("turnout", turnout),
+            ("yes_votes", yes_votes),
+            ("no_votes", no_votes),
+            ("abstain_votes", abstain_votes),
+            ("invalid_votes", invalid_votes),
+        ];
+        for (field, value) in counts {
+            if value < 0 {
+                inconsistencies.push(VoteRecordInconsistency {
+                    code: "negative_count".to_string(),
+                    fields: vec![field.to_string()],
+                    message: format!("{} cannot be negative (got {})", field, value),
+                });
+            }
+        }
+        explanation_parts.push(explain(audience, "validate_vote_record.non_negative", context! {}));
+
+        // Turnout cannot exceed the electorate
+        if turnout > eligible_voters {
+            inconsistencies.push(VoteRecordInconsistency {
+                code: "turnout_exceeds_eligible".to_string(),
+                fields: vec!["turnout".to_string(), "eligible_voters".to_string()],
+                message: format!("Turnout {} exceeds eligible voters {}", turnout, eligible_voters),
+            });
+        }
+        explanation_parts.push(explain(audience, "validate_vote_record.turnout_check", context! {
+            turnout, eligible_voters, within_eligible => turnout <= eligible_voters,
+        }));
+
+        // Ballot categories must add up to turnout
+        let tallied_ballots =
+            yes_votes as i64 + no_votes as i64 + abstain_votes as i64 + invalid_votes as i64;
+        explanation_parts.push(explain(audience, "validate_vote_record.tally", context! {
+            yes_votes, no_votes, abstain_votes, invalid_votes, tallied_ballots,
+        }));
+        if tallied_ballots != turnout as i64 {
+            let difference = tallied_ballots - turnout as i64;
+            inconsistencies.push(VoteRecordInconsistency {
+                code: "tally_mismatch".to_string(),
+                fields: vec![
+                    "yes_votes".to_string(),
+                    "no_votes".to_string(),
+                    "abstain_votes".to_string(),
+                    "invalid_votes".to_string(),
+                    "turnout".to_string(),
+                ],
+                message: format!(
+                    "Tallied ballots {} do not match turnout {} ({} {})",
+                    tallied_ballots,
+                    turnout,
+                    difference.abs(),
+                    if difference > 0 { "too many" } else { "missing" }
+                ),
+            });
+        }
+        explanation_parts.push(explain(audience, "validate_vote_record.tally_check", context! {
+            tallied_ballots, turnout, matches => tallied_ballots == turnout as i64,
+        }));
+
+        let valid = inconsistencies.is_empty();
+        explanation_parts.push(explain(audience, "validate_vote_record.result", context! {
+            valid, inconsistencies => inconsistencies.len(),
+        }));
+
+        ValidateVoteRecordResponse {
+            valid,
+            tallied_ballots,
+            inconsistencies,
+            explanation: explanation_parts.join(". "),
+            assumptions: Vec::new(),
+            errors,
+            warnings,
+        }
+    }
+
+    /// Round allocations to cents so they sum exactly to `total` (largest-remainder method).
+    ///
+    /// Each allocation is floored to whole cents, then the residual cents are handed out one at a
+    /// time to the allocations with the largest discarded fractions (earlier allocations win ties).
+    /// An allocation never receives a cent that would take it above its limit (a debt tranche is
+    /// not paid more than it is owed), so at least one limit should be unbounded.
+    /// Returns the rounded allocations in cents and the signed residual that was redistributed.
+    fn allocate_to_cents(allocations: &[f64], limits: &[f64], total: f64) -> (Vec<i64>, i64) {
+        // Round away float noise (e.g. 33.3299999) before flooring
+        let in_cents = |amount: f64| (amount * 100.0 * 1e6).round() / 1e6;
+        let total_cents = (total * 100.0).round() as i64;
+        let mut cents: Vec<i64> = Vec::with_capacity(allocations.len());
+        let mut remainders: Vec<(usize, f64)> = Vec::with_capacity(allocations.len());
+        for (i, amount) in allocations.iter().enumerate() {
+            let exact = in_cents(*amount);
+            let floored = exact.floor();
+            cents.push(floored as i64);
+            remainders.push((i, exact - floored));
+        }
+
+        let residual = total_cents - cents.iter().sum::<i64>();
+        if residual > 0 {
+            remainders.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal).then(a.0.cmp(&b.0)));
+            let below_limit = |cents: &[i64], i: usize| (cents[i] + 1) as f64 <= in_cents(limits[i]);
+            let mut outstanding = residual;
+            for &(i, _) in remainders.iter().cycle() {
+                if outstanding == 0 || !(0..cents.len()).any(|j| below_limit(&cents, j)) {
+                    break;
+                }
+                if below_limit(&cents, i) {
+                    cents[i] += 1;
+                    outstanding -= 1;
+                }
+            }
+        } else if residual < 0 {
+            remainders.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal).then(b.0.cmp(&a.0)));
+            let mut outstanding = -residual;
+            for &(i, _) in remainders.iter().cycle() {
+                if outstanding == 0 {
+                    break;
+                }
+                if cents[i] > 0 {
+                    cents[i] -= 1;
+                    outstanding -= 1;
+                } else if cents.iter().all(|&c| c == 0) {
+                    break;
+                }
+            }
+        }
+
+        (cents, residual)
+    }
+
+    /// Distribute cash in waterfall structure
+    fn distribute_waterfall_internal(
+        cash_available: f64,
+        senior_debt: f64,
+        junior_debt: f64,
+        audience: Audience,
+    ) -> DistributeWaterfallResponse {
+        let mut errors = Vec::new();
+        let mut warnings = Vec::new();
+        let mut explanation_parts = Vec::new();
+        
+        // Validation
+        if cash_available < 0.0 {
+            errors.push("Cash available cannot be negative".to_string());
+        }
+        if senior_debt < 0.0 {
+            errors.push("Senior debt cannot be negative".to_string());
         }
         if junior_debt < 0.0 {
             errors.push("Junior debt cannot be negative".to_string());
         }
-        
-        if !errors.is_empty() {
-            return DistributeWaterfallResponse {
-                distribution: DistributeWaterfallResult { senior: 0.0, junior: 0.0, equity: 0.0 },
-                explanation: "Waterfall distribution failed due to invalid inputs".to_string(),
-                errors,
-                warnings,
+        
+        if !errors.is_empty() {
+            return DistributeWaterfallResponse {
+                distribution: DistributeWaterfallResult::default(),
+                explanation: explain(audience, "distribute_waterfall.invalid_inputs", context! {}),
+                assumptions: Vec::new(),
+                errors,
+                warnings,
+            };
+        }
+        
+        let mut remaining = cash_available;
+        explanation_parts.push(explain(audience, "distribute_waterfall.starting_cash", context! { cash_available }));
+        
+        // Pay senior debt first
+        let senior_payment = remaining.min(senior_debt);
+        remaining -= senior_payment;
+        
+        explanation_parts.push(explain(audience, "distribute_waterfall.senior", context! {
+            has_debt => senior_debt > 0.0,
+            fully_paid => senior_payment == senior_debt,
+            payment => senior_payment,
+            debt => senior_debt,
+        }));
+        if senior_debt > 0.0 && senior_payment < senior_debt {
+            warnings.push(format!("Senior debt underpaid by {:.2}", senior_debt - senior_payment));
+        }
+        
+        explanation_parts.push(explain(audience, "distribute_waterfall.remaining_after_senior", context! { remaining }));
+        
+        // Pay junior debt second
+        let junior_payment = remaining.min(junior_debt);
+        remaining -= junior_payment;
+        
+        explanation_parts.push(explain(audience, "distribute_waterfall.junior", context! {
+            has_debt => junior_debt > 0.0,
+            fully_paid => junior_payment == junior_debt,
+            payment => junior_payment,
+            debt => junior_debt,
+        }));
+        if junior_debt > 0.0 && junior_payment < junior_debt {
+            if junior_payment > 0.0 {
+                warnings.push(format!("Junior debt underpaid by {:.2}", junior_debt - junior_payment));
+            } else {
+                warnings.push(format!("Junior debt unpaid ({:.2})", junior_debt));
+            }
+        }
+        
+        explanation_parts.push(explain(audience, "distribute_waterfall.remaining_for_equity", context! { remaining }));
+        
+        // Remainder goes to equity
+        let equity_payment = remaining;
+        
+        explanation_parts.push(explain(audience, "distribute_waterfall.equity", context! { payment => equity_payment }));
+        
+        let total_debt = senior_debt + junior_debt;
+        if cash_available < total_debt {
+            warnings.push(format!("Insufficient cash: {:.2} available vs {:.2} total debt", cash_available, total_debt));
+        }
+
+        // Round to cents so that senior + junior + equity == cash_available exactly
+        let (cents, residual) = Self::allocate_to_cents(
+            &[senior_payment, junior_payment, equity_payment],
+            &[senior_debt, junior_debt, f64::INFINITY],
+            cash_available,
+        );
+        if residual != 0 {
+            explanation_parts.push(explain(audience, "distribute_waterfall.rounding", context! { residual }));
+        }
+
+        // Invariant: rounded allocations must conserve the (cent-rounded) cash available
+        let total_cents = (cash_available * 100.0).round() as i64;
+        if cents.iter().sum::<i64>() != total_cents {
+            errors.push(format!(
+                "Internal error: allocations {:.2} do not sum to cash available {:.2}",
+                cents.iter().sum::<i64>() as f64 / 100.0,
+                cash_available
+            ));
+            return DistributeWaterfallResponse {
+                distribution: DistributeWaterfallResult::default(),
+                explanation: explain(audience, "distribute_waterfall.conservation_failed", context! {}),
+                assumptions: Vec::new(),
+                errors,
+                warnings,
+            };
+        }
+        
+        // Shortfalls and coverage of the rounded allocations, so they agree with the amounts paid
+        let shortfall_and_coverage = |debt: f64, paid_cents: i64| {
+            let debt_cents = (debt * 100.0).round() as i64;
+            let shortfall = (debt_cents - paid_cents).max(0) as f64 / 100.0;
+            let coverage = if debt_cents > 0 { paid_cents as f64 / debt_cents as f64 } else { 1.0 };
+            (shortfall, coverage)
+        };
+        let (senior_shortfall, senior_coverage) = shortfall_and_coverage(senior_debt, cents[0]);
+        let (junior_shortfall, junior_coverage) = shortfall_and_coverage(junior_debt, cents[1]);
+
+        DistributeWaterfallResponse {
+            distribution: DistributeWaterfallResult {
+                senior: cents[0] as f64 / 100.0,
+                junior: cents[1] as f64 / 100.0,
+                equity: cents[2] as f64 / 100.0,
+                senior_shortfall,
+                junior_shortfall,
+                senior_coverage,
+                junior_coverage,
+            },
+            explanation: explanation_parts.join(". "),
+            assumptions: Vec::new(),
+            errors,
+            warnings,
+        }
+    }
+
+    /// Check leverage and interest coverage covenants
+    fn check_covenants_internal(
+        total_debt: f64,
+        ebitda: f64,
+        interest_expense: f64,
+        max_leverage: f64,
+        min_interest_coverage: f64,
+        audience: Audience,
+    ) -> CheckCovenantsResponse {
+        let mut errors = Vec::new();
+        let mut warnings = Vec::new();
+        let mut explanation_parts = Vec::new();
+
+        // Validation
+        if total_debt < 0.0 {
+            errors.push("Total debt cannot be negative".to_string());
+        }
+        if interest_expense < 0.0 {
+            errors.push("Interest expense cannot be negative".to_string());
+        }
+        if max_leverage <= 0.0 {
+            errors.push("Maximum leverage must be positive".to_string());
+        }
+        if min_interest_coverage < 0.0 {
+            errors.push("Minimum interest coverage cannot be negative".to_string());
+        }
+
+        if !errors.is_empty() {
+            return CheckCovenantsResponse {
+                all_pass: false,
+                covenants: Vec::new(),
+                explanation: explain(audience, "check_covenants.invalid_inputs", context! {}),
+                assumptions: Vec::new(),
+                errors,
+                warnings,
+            };
+        }
+
+        // Leverage covenant: total debt / EBITDA ≤ max leverage
+        let leverage = if ebitda > 0.0 {
+            let ratio = total_debt / ebitda;
+            let passes = ratio <= max_leverage;
+            explanation_parts.push(explain(audience, "check_covenants.leverage", context! {
+                total_debt, ebitda, ratio, max_leverage, passes,
+            }));
+            CovenantResult {
+                name: "leverage".to_string(),
+                ratio: Some(ratio),
+                threshold: max_leverage,
+                comparison: "<=".to_string(),
+                passes,
+                headroom: Some(max_leverage - ratio),
+            }
+        } else {
+            // Zero or negative EBITDA cannot service any debt
+            let passes = total_debt == 0.0;
+            explanation_parts.push(explain(audience, "check_covenants.leverage_undefined", context! { ebitda, passes }));
+            warnings.push(format!("Non-positive EBITDA ({:.2}): leverage ratio is undefined", ebitda));
+            CovenantResult {
+                name: "leverage".to_string(),
+                ratio: None,
+                threshold: max_leverage,
+                comparison: "<=".to_string(),
+                passes,
+                headroom: None,
+            }
+        };
+
+        // Interest coverage covenant: EBITDA / interest expense ≥ min coverage
+        let coverage = if interest_expense > 0.0 {
+            let ratio = ebitda / interest_expense;
+            let passes = ratio >= min_interest_coverage;
+            explanation_parts.push(explain(audience, "check_covenants.interest_coverage", context! {
+                ebitda, interest_expense, ratio, min_interest_coverage, passes,
+            }));
+            CovenantResult {
+                name: "interest_coverage".to_string(),
+                ratio: Some(ratio),
+                threshold: min_interest_coverage,
+                comparison: ">=".to_string(),
+                passes,
+                headroom: Some(ratio - min_interest_coverage),
+            }
+        } else {
+            explanation_parts.push(explain(audience, "check_covenants.interest_coverage_undefined", context! {}));
+            CovenantResult {
+                name: "interest_coverage".to_string(),
+                ratio: None,
+                threshold: min_interest_coverage,
+                comparison: ">=".to_string(),
+                passes: true,
+                headroom: None,
+            }
+        };
+
+        for covenant in [&leverage, &coverage] {
+            if let Some(headroom) = covenant.headroom
+                && covenant.passes
+                && headroom < 0.1 * covenant.threshold
+            {
+                warnings.push(format!(
+                    "Tight headroom on {} covenant: {:.2}x",
+                    covenant.name, headroom
+                ));
+            }
+        }
+
+        let all_pass = leverage.passes && coverage.passes;
+        explanation_parts.push(explain(audience, "check_covenants.result", context! { all_pass }));
+
+        CheckCovenantsResponse {
+            all_pass,
+            covenants: vec![leverage, coverage],
+            explanation: explanation_parts.join(". "),
+            assumptions: Vec::new(),
+            errors,
+            warnings,
+        }
+    }
+
+    /// Net present value with period 0 undiscounted
+    fn npv_at(cash_flows: &[f64], rate: f64) -> f64 {
+        cash_flows
+            .iter()
+            .enumerate()
+            .map(|(t, cf)| cf / (1.0 + rate).powi(t as i32))
+            .sum()
+    }
+
+    /// Calculate net present value of a cash flow series
+    fn calc_npv_internal(cash_flows: &[f64], discount_rate: f64, audience: Audience) -> CalcNpvResponse {
+        let mut errors = Vec::new();
+        let mut warnings = Vec::new();
+        let mut explanation_parts = Vec::new();
+
+        // Validation
+        if cash_flows.is_empty() {
+            errors.push("At least one cash flow is required".to_string());
+        }
+        if cash_flows.len() > MAX_CASH_FLOWS {
+            errors.push(format!("Too many cash flows: {} (max {})", cash_flows.len(), MAX_CASH_FLOWS));
+        }
+        if discount_rate <= -1.0 {
+            errors.push("Discount rate must be greater than -100%".to_string());
+        }
+
+        if !errors.is_empty() {
+            return CalcNpvResponse {
+                npv: 0.0,
+                present_values: Vec::new(),
+                explanation: explain(audience, "calc_npv.invalid_inputs", context! {}),
+                assumptions: Vec::new(),
+                errors,
+                warnings,
+            };
+        }
+
+        explanation_parts.push(explain(audience, "calc_npv.discounting", context! {
+            periods => cash_flows.len(), discount_rate,
+        }));
+
+        let present_values: Vec<f64> = cash_flows
+            .iter()
+            .enumerate()
+            .map(|(t, cf)| cf / (1.0 + discount_rate).powi(t as i32))
+            .collect();
+        for (t, (cf, pv)) in cash_flows.iter().zip(&present_values).enumerate() {
+            explanation_parts.push(explain(audience, "calc_npv.period", context! {
+                period => t, cash_flow => cf, discount_rate, present_value => pv,
+            }));
+        }
+
+        let npv: f64 = present_values.iter().sum();
+        explanation_parts.push(explain(audience, "calc_npv.npv", context! { npv }));
+
+        if discount_rate < 0.0 {
+            warnings.push(format!("Negative discount rate: {:.2}%", discount_rate * 100.0));
+        }
+        if discount_rate > 1.0 {
+            warnings.push(format!("Very high discount rate: {:.1}% (did you pass a percentage instead of a decimal?)", discount_rate * 100.0));
+        }
+
+        CalcNpvResponse {
+            npv,
+            present_values,
+            explanation: explanation_parts.join(". "),
+            assumptions: Vec::new(),
+            errors,
+            warnings,
+        }
+    }
+
+    /// Calculate internal rate of return by bisection on the NPV function
+    fn calc_irr_internal(cash_flows: &[f64], audience: Audience) -> CalcIrrResponse {
+        let mut errors = Vec::new();
+        let mut warnings = Vec::new();
+        let mut explanation_parts = Vec::new();
+
+        // Validation
+        if cash_flows.len() < 2 {
+            errors.push("At least two cash flows are required".to_string());
+        }
+        if cash_flows.len() > MAX_CASH_FLOWS {
+            errors.push(format!("Too many cash flows: {} (max {})", cash_flows.len(), MAX_CASH_FLOWS));
+        }
+        let sign_changes = cash_flows
+            .iter()
+            .filter(|cf| **cf != 0.0)
+            .collect::<Vec<_>>()
+            .windows(2)
+            .filter(|w| (*w[0] < 0.0) != (*w[1] < 0.0))
+            .count();
+        if cash_flows.len() >= 2 && sign_changes == 0 {
+            errors.push("Cash flows must contain at least one sign change (an outflow and an inflow)".to_string());
+        }
+
+        if !errors.is_empty() {
+            return CalcIrrResponse {
+                irr: 0.0,
+                iterations: 0,
+                npv_at_irr: 0.0,
+                explanation: explain(audience, "calc_irr.invalid_inputs", context! {}),
+                assumptions: Vec::new(),
+                errors,
+                warnings,
+            };
+        }
+
+        // Bisection requires NPV to change sign across the search bracket
+        let mut low = IRR_LOWER_BOUND;
+        let mut high = IRR_UPPER_BOUND;
+        let mut npv_low = Self::npv_at(cash_flows, low);
+        let npv_high = Self::npv_at(cash_flows, high);
+        explanation_parts.push(explain(audience, "calc_irr.search_bracket", context! { low, high, npv_low, npv_high }));
+
+        if npv_low.signum() == npv_high.signum() {
+            errors.push(format!(
+                "No IRR found between {:.0}% and {:.0}%: NPV does not change sign in the search bracket",
+                low * 100.0, high * 100.0
+            ));
+            return CalcIrrResponse {
+                irr: 0.0,
+                iterations: 0,
+                npv_at_irr: 0.0,
+                explanation: explanation_parts.join(". "),
+                assumptions: Vec::new(),
+                errors,
+                warnings,
+            };
+        }
+
+        let mut iterations = 0;
+        let mut mid = (low + high) / 2.0;
+        let mut npv_mid = Self::npv_at(cash_flows, mid);
+        while iterations < IRR_MAX_ITERATIONS {
+            iterations += 1;
+            mid = (low + high) / 2.0;
+            npv_mid = Self::npv_at(cash_flows, mid);
+            if npv_mid.abs() < IRR_TOLERANCE || (high - low) / 2.0 < IRR_TOLERANCE {
+                break;
+            }
+            if npv_mid.signum() == npv_low.signum() {
+                low = mid;
+                npv_low = npv_mid;
+            } else {
+                high = mid;
+            }
+        }
+
+        let converged = npv_mid.abs() < IRR_TOLERANCE || (high - low) / 2.0 < IRR_TOLERANCE;
+        explanation_parts.push(explain(audience, "calc_irr.bisection", context! {
+            iterations, tolerance => format!("{:e}", IRR_TOLERANCE), converged,
+        }));
+        explanation_parts.push(explain(audience, "calc_irr.result", context! { npv_at_irr => npv_mid, irr => mid }));
+
+        if !converged {
+            warnings.push(format!("Solver stopped after {} iterations without converging", iterations));
+        }
+        if sign_changes > 1 {
+            warnings.push(format!(
+                "Cash flows change sign {} times; multiple IRRs may exist and only one is reported",
+                sign_changes
+            ));
+        }
+
+        CalcIrrResponse {
+            irr: mid,
+            iterations,
+            npv_at_irr: npv_mid,
+            explanation: explanation_parts.join(". "),
+            assumptions: Vec::new(),
+            errors,
+            warnings,
+        }
+    }
+
+    /// Evaluate escrow release conditions and compute the releasable amount
+    fn check_escrow_release_internal(
+        escrow_amount: f64,
+        milestones: &[(String, bool, f64)],
+        release_date: Option<NaiveDate>,
+        as_of_date: NaiveDate,
+        dispute_pending: bool,
+        already_released: f64,
+        audience: Audience,
+    ) -> CheckEscrowReleaseResponse {
+        let mut errors = Vec::new();
+        let mut warnings = Vec::new();
+        let mut explanation_parts = Vec::new();
+        let mut blocking_conditions = Vec::new();
+
+        // Validation
+        if escrow_amount < 0.0 {
+            errors.push("Escrow amount cannot be negative".to_string());
+        }
+        if already_released < 0.0 {
+            errors.push("Already released amount cannot be negative".to_string());
+        }
+        if already_released > escrow_amount {
+            errors.push("Already released amount cannot exceed escrow amount".to_string());
+        }
+        if milestones.is_empty() {
+            errors.push("At least one milestone is required".to_string());
+        }
+        for (name, _, percentage) in milestones {
+            if !(0.0..=1.0).contains(percentage) {
+                errors.push(format!(
+                    "Release percentage for milestone '{}' must be a decimal between 0 and 1",
+                    sanitize_for_error_message(name)
+                ));
+            }
+        }
+        let scheduled_percentage: f64 = milestones.iter().map(|(_, _, p)| p).sum();
+        if scheduled_percentage > 1.0 + 1e-9 {
+            errors.push(format!(
+                "Release schedule exceeds 100% of escrow ({:.1}%)",
+                scheduled_percentage * 100.0
+            ));
+        }
+
+        if !errors.is_empty() {
+            return CheckEscrowReleaseResponse {
+                release_amount: 0.0,
+                released_percentage: 0.0,
+                remaining_in_escrow: 0.0,
+                milestones: Vec::new(),
+                blocking_conditions,
+                explanation: explain(audience, "check_escrow_release.invalid_inputs", context! {}),
+                assumptions: Vec::new(),
+                errors,
+                warnings,
+            };
+        }
+
+        explanation_parts.push(explain(audience, "check_escrow_release.escrow_amount", context! { escrow_amount }));
+
+        // Global conditions: no open dispute and release date reached
+        if dispute_pending {
+            blocking_conditions.push("Dispute pending: no release while a dispute is open".to_string());
+        }
+        explanation_parts.push(explain(audience, "check_escrow_release.no_dispute", context! { dispute_pending }));
+        if let Some(release_date) = release_date {
+            let reached = as_of_date >= release_date;
+            if !reached {
+                blocking_conditions.push(format!(
+                    "Release date not reached: {} is before {}",
+                    as_of_date, release_date
+                ));
+            }
+            explanation_parts.push(explain(audience, "check_escrow_release.release_date", context! {
+                as_of_date => as_of_date.to_string(),
+                release_date => release_date.to_string(),
+                reached,
+            }));
+        }
+        let globally_blocked = !blocking_conditions.is_empty();
+
+        // Milestone conditions: each completed milestone releases its share
+        let mut released_percentage = 0.0;
+        let mut milestone_results = Vec::with_capacity(milestones.len());
+        for (name, completed, percentage) in milestones {
+            let amount = escrow_amount * percentage;
+            let released = *completed && !globally_blocked;
+            if released {
+                released_percentage += percentage;
+            }
+            if !completed {
+                blocking_conditions.push(format!("Milestone '{}' not completed", name));
+            }
+            explanation_parts.push(explain(audience, "check_escrow_release.milestone", context! {
+                name, percentage, amount, completed, blocked => globally_blocked,
+            }));
+            milestone_results.push(EscrowMilestoneResult {
+                name: name.clone(),
+                completed: *completed,
+                release_percentage: *percentage,
+                amount,
+                released,
+            });
+        }
+
+        let cumulative_release = escrow_amount * released_percentage;
+        let release_amount = (cumulative_release - already_released).max(0.0);
+        explanation_parts.push(explain(audience, "check_escrow_release.cumulative_release", context! {
+            released_percentage, escrow_amount, cumulative_release,
+        }));
+        if already_released > 0.0 {
+            explanation_parts.push(explain(audience, "check_escrow_release.already_released", context! {
+                already_released, release_amount,
+            }));
+            if already_released > cumulative_release {
+                warnings.push(format!(
+                    "Already released {:.2} exceeds the amount releasable under the schedule ({:.2})",
+                    already_released, cumulative_release
+                ));
+            }
+        }
+
+        let remaining_in_escrow = escrow_amount - already_released - release_amount;
+        explanation_parts.push(explain(audience, "check_escrow_release.remaining", context! { remaining => remaining_in_escrow }));
+
+        if scheduled_percentage < 1.0 - 1e-9 {
+            warnings.push(format!(
+                "Release schedule covers only {:.1}% of escrow; the rest is not tied to any milestone",
+                scheduled_percentage * 100.0
+            ));
+        }
+
+        CheckEscrowReleaseResponse {
+            release_amount,
+            released_percentage,
+            remaining_in_escrow,
+            milestones: milestone_results,
+            blocking_conditions,
+            explanation: explanation_parts.join(". "),
+            assumptions: Vec::new(),
+            errors,
+            warnings,
+        }
+    }
+
+    /// Check housing grant eligibility
+    /// Income limit for the housing grant: 60% of AMI, raised by 10% for households of more than 4
+    /// under the size rule. Under an equivalence scale the limit is 60% of AMI for the reference
+    /// household of two adults and two children, scaled by the household's adult equivalents.
+    fn housing_income_threshold(ami: f64, household_size: i32, children: i32, scale: EquivalenceScale) -> f64 {
+        let base_threshold = 0.60 * ami;
+        match (scale.adult_equivalents(household_size, children), scale.adult_equivalents(4, 2)) {
+            (Some(equivalents), Some(reference)) => base_threshold * equivalents / reference,
+            _ if household_size > 4 => base_threshold * 1.10,
+            _ => base_threshold,
+        }
+    }
+
+    /// The share of AMI used by `housing_income_threshold`
+    fn housing_ami_percentage(household_size: i32, children: i32, scale: EquivalenceScale) -> f64 {
+        match scale {
+            EquivalenceScale::Size if household_size > 4 => 0.66,
+            EquivalenceScale::Size => 0.60,
+            _ => Self::housing_income_threshold(1.0, household_size, children, scale),
+        }
+    }
+
+    fn check_housing_grant_internal(
+        ami: f64,
+        household_size: i32,
+        children: i32,
+        income: f64,
+        has_other_subsidy: bool,
+        scale: EquivalenceScale,
+        audience: Audience,
+    ) -> CheckHousingGrantResponse {
+        let mut errors = Vec::new();
+        let mut additional_requirements = Vec::new();
+        let mut explanation_parts = Vec::new();
+        
+        // Validation
+        if ami <= 0.0 {
+            errors.push("Area Median Income (AMI) must be positive".to_string());
+        }
+        if household_size <= 0 {
+            errors.push("Household size must be positive".to_string());
+        }
+        if children < 0 {
+            errors.push("Number of children cannot be negative".to_string());
+        } else if household_size > 0 && children >= household_size {
+            errors.push("Children are counted in household_size and a household needs at least one adult".to_string());
+        }
+        if income < 0.0 {
+            errors.push("Income cannot be negative".to_string());
+        }
+        
+        if !errors.is_empty() {
+            return CheckHousingGrantResponse {
+                eligible: false,
+                threshold: 0.0,
+                ami_percentage_used: 0.0,
+                equivalence_scale: scale,
+                adult_equivalents: None,
+                equivalized_income: None,
+                income_margin: 0.0,
+                blocking_reasons: Vec::new(),
+                explanation: explain(audience, "check_housing_grant.invalid_inputs", context! {}),
+                assumptions: Vec::new(),
+                errors,
+                additional_requirements,
+            };
+        }
+        
+        explanation_parts.push(explain(audience, "check_housing_grant.inputs", context! {
+            ami, household_size, income, has_other_subsidy,
+        }));
+
+        // Every reason is reported, even though the subsidy check alone decides
+        let threshold = Self::housing_income_threshold(ami, household_size, children, scale);
+        let ami_percentage_used = Self::housing_ami_percentage(household_size, children, scale);
+        let adult_equivalents = scale.adult_equivalents(household_size, children);
+        let equivalized_income = adult_equivalents.map(|equivalents| income / equivalents);
+        let income_margin = threshold - income;
+        let mut blocking_reasons = Vec::new();
+        if has_other_subsidy {
+            blocking_reasons.push("Household already receives another housing subsidy".to_string());
+        }
+        if income > threshold {
+            blocking_reasons.push(format!(
+                "Income {:.2} is {:.2} over the limit of {:.2}",
+                income, -income_margin, threshold
+            ));
+        }
+        
+        // Check subsidy requirement first
+        explanation_parts.push(explain(audience, "check_housing_grant.subsidy_check", context! { has_other_subsidy }));
+        if has_other_subsidy {
+            additional_requirements.push("Must not have any other housing subsidies or assistance".to_string());
+            
+            return CheckHousingGrantResponse {
+                eligible: false,
+                threshold,
+                ami_percentage_used,
+                equivalence_scale: scale,
+                adult_equivalents,
+                equivalized_income,
+                income_margin,
+                blocking_reasons,
+                explanation: explanation_parts.join(". "),
+                assumptions: Vec::new(),
+                errors,
+                additional_requirements,
+            };
+        }
+        
+        // Calculate threshold
+        let base_threshold = 0.60 * ami;
+        explanation_parts.push(explain(audience, "check_housing_grant.base_threshold", context! { threshold => base_threshold }));
+        
+        match (adult_equivalents, scale.adult_equivalents(4, 2)) {
+            (Some(adult_equivalents), Some(reference_equivalents)) => {
+                explanation_parts.push(explain(audience, "check_housing_grant.equivalence_scale", context! {
+                    scale => scale.to_string(),
+                    household_size,
+                    children,
+                    adult_equivalents,
+                    reference_equivalents,
+                    limit_per_equivalent => base_threshold / reference_equivalents,
+                    equivalized_income,
+                    threshold,
+                }));
+            }
+            _ => {
+                let adjusted = household_size > 4;
+                explanation_parts.push(explain(audience, "check_housing_grant.household_adjustment", context! {
+                    adjusted, household_size, threshold,
+                }));
+            }
+        }
+        
+        // Check income eligibility
+        let eligible = income <= threshold;
+        explanation_parts.push(explain(audience, "check_housing_grant.income_eligibility", context! {
+            income, threshold, eligible,
+        }));
+        
+        explanation_parts.push(explain(audience, "check_housing_grant.result", context! { eligible }));
+        
+        // Add additional requirements
+        additional_requirements.push("Must provide proof of income documentation".to_string());
+        additional_requirements.push("Must be a first-time homebuyer or meet other program criteria".to_string());
+        if household_size > 4 {
+            additional_requirements.push("Large household size may require additional documentation".to_string());
+        }
+        if income > threshold * 0.9 {
+            additional_requirements.push("Income is close to threshold - verify all deductions are included".to_string());
+        }
+        
+        CheckHousingGrantResponse {
+            eligible,
+            threshold,
+            ami_percentage_used,
+            equivalence_scale: scale,
+            adult_equivalents,
+            equivalized_income,
+            income_margin,
+            blocking_reasons,
+            explanation: explanation_parts.join(". "),
+            assumptions: Vec::new(),
+            errors,
+            additional_requirements,
+        }
+    }
+
+    /// Calculate capital gains tax with holding-period rates and an annual exemption
+    fn calc_capital_gains_internal(
+        acquisition_cost: f64,
+        sale_price: f64,
+        holding_period_days: i32,
+        annual_exemption: f64,
+        audience: Audience,
+    ) -> CalcCapitalGainsResponse {
+        let mut errors = Vec::new();
+        let mut warnings = Vec::new();
+        let mut explanation_parts = Vec::new();
+
+        // Validation
+        if acquisition_cost < 0.0 {
+            errors.push("Acquisition cost cannot be negative".to_string());
+        }
+        if sale_price < 0.0 {
+            errors.push("Sale price cannot be negative".to_string());
+        }
+        if holding_period_days < 0 {
+            errors.push("Holding period cannot be negative".to_string());
+        }
+        if annual_exemption < 0.0 {
+            errors.push("Annual exemption cannot be negative".to_string());
+        }
+
+        if !errors.is_empty() {
+            return CalcCapitalGainsResponse {
+                gain: 0.0,
+                long_term: false,
+                rate: 0.0,
+                exemption_applied: 0.0,
+                taxable_gain: 0.0,
+                tax: 0.0,
+                explanation: explain(audience, "calc_capital_gains.invalid_inputs", context! {}),
+                assumptions: Vec::new(),
+                errors,
+                warnings,
+            };
+        }
+
+        let gain = sale_price - acquisition_cost;
+        explanation_parts.push(explain(audience, "calc_capital_gains.gain", context! {
+            sale_price, acquisition_cost, gain,
+        }));
+
+        // Holding period decides the rate
+        let long_term_days = config().capital_gains_long_term_days;
+        let long_term = holding_period_days >= long_term_days;
+        let rate = if long_term {
+            config().capital_gains_long_term_rate
+        } else {
+            config().capital_gains_short_term_rate
+        };
+        explanation_parts.push(explain(audience, "calc_capital_gains.holding_period", context! {
+            holding_period_days, long_term_days, long_term, rate,
+        }));
+        if !long_term && long_term_days - holding_period_days <= 30 {
+            warnings.push(format!(
+                "Holding period is {} days short of long-term treatment",
+                long_term_days - holding_period_days
+            ));
+        }
+
+        if gain <= 0.0 {
+            explanation_parts.push(explain(audience, "calc_capital_gains.no_gain", context! { gain }));
+            return CalcCapitalGainsResponse {
+                gain,
+                long_term,
+                rate,
+                exemption_applied: 0.0,
+                taxable_gain: 0.0,
+                tax: 0.0,
+                explanation: explanation_parts.join(". "),
+                assumptions: Vec::new(),
+                errors,
+                warnings,
+            };
+        }
+
+        // Annual exemption offsets the gain first
+        let exemption_applied = annual_exemption.min(gain);
+        let taxable_gain = gain - exemption_applied;
+        explanation_parts.push(explain(audience, "calc_capital_gains.exemption", context! {
+            annual_exemption, exemption_applied, taxable_gain,
+        }));
+
+        let tax = taxable_gain * rate;
+        explanation_parts.push(explain(audience, "calc_capital_gains.tax", context! { taxable_gain, rate, tax }));
+
+        CalcCapitalGainsResponse {
+            gain,
+            long_term,
+            rate,
+            exemption_applied,
+            taxable_gain,
+            tax,
+            explanation: explanation_parts.join(". "),
+            assumptions: Vec::new(),
+            errors,
+            warnings,
+        }
+    }
+
+    /// Progressive tax on an amount without explanation steps
+    fn progressive_tax(amount: f64, thresholds: &[f64], rates: &[f64], cliffs: &[bool]) -> f64 {
+        let mut tax = 0.0;
+        let mut lower = 0.0;
+        for (i, &rate) in rates.iter().enumerate() {
+            let upper = thresholds.get(i).copied().unwrap_or(f64::INFINITY);
+            if amount <= lower {
+                break;
+            }
+            if Self::is_cliff(cliffs, i) {
+                tax = amount.min(upper) * rate;
+            } else {
+                tax += (amount.min(upper) - lower) * rate;
+            }
+            lower = upper;
+        }
+        tax
+    }
+
+    /// Whether bracket `index` is a cliff bracket; brackets without a flag are marginal
+    fn is_cliff(cliffs: &[bool], index: usize) -> bool {
+        cliffs.get(index).copied().unwrap_or(false)
+    }
+
+    /// Calculate inheritance or gift tax per beneficiary by relationship class
+    fn calc_inheritance_tax_internal(
+        beneficiaries: &[(String, String, f64, f64)],
+        classes: &BTreeMap<String, InheritanceClass>,
+        audience: Audience,
+    ) -> CalcInheritanceTaxResponse {
+        let mut errors = Vec::new();
+        let mut warnings = Vec::new();
+        let mut explanation_parts = Vec::new();
+
+        // Validation
+        if beneficiaries.is_empty() {
+            errors.push("At least one beneficiary is required".to_string());
+        }
+        for (name, relationship, amount, prior_gifts) in beneficiaries {
+            if !classes.contains_key(relationship) {
+                errors.push(format!(
+                    "Unknown relationship '{}' for beneficiary '{}' (expected one of: {})",
+                    sanitize_for_error_message(relationship),
+                    name,
+                    classes.keys().cloned().collect::<Vec<_>>().join(", ")
+                ));
+            }
+            if *amount < 0.0 {
+                errors.push(format!("Amount for beneficiary '{}' cannot be negative", name));
+            }
+            if *prior_gifts < 0.0 {
+                errors.push(format!("Prior gifts for beneficiary '{}' cannot be negative", name));
+            }
+        }
+
+        if !errors.is_empty() {
+            return CalcInheritanceTaxResponse {
+                beneficiaries: Vec::new(),
+                total_tax: 0.0,
+                explanation: explain(audience, "calc_inheritance_tax.invalid_inputs", context! {}),
+                assumptions: Vec::new(),
+                errors,
+                warnings,
+            };
+        }
+
+        let mut results = Vec::with_capacity(beneficiaries.len());
+        for (name, relationship, amount, prior_gifts) in beneficiaries {
+            let class = &classes[relationship];
+
+            // Prior gifts use up the exemption before this transfer
+            let exemption_available = (class.exemption - prior_gifts).max(0.0);
+            if *prior_gifts > 0.0 && exemption_available == 0.0 {
+                warnings.push(format!("Prior gifts to '{}' have used the full {} exemption", name, relationship));
+            }
+            let exemption_applied = exemption_available.min(*amount);
+            let taxable_amount = amount - exemption_applied;
+            let tax = Self::progressive_tax(taxable_amount, &class.thresholds, &class.rates, &class.cliffs);
+            let effective_rate = if *amount > 0.0 { tax / amount } else { 0.0 };
+
+            explanation_parts.push(explain(audience, "calc_inheritance_tax.beneficiary", context! {
+                name,
+                relationship,
+                amount,
+                prior_gifts,
+                exemption => class.exemption,
+                exemption_applied,
+                taxable_amount,
+                tax,
+                effective_rate,
+            }));
+            results.push(BeneficiaryTax {
+                name: name.clone(),
+                relationship: relationship.clone(),
+                amount: *amount,
+                exemption_applied,
+                taxable_amount,
+                tax,
+                effective_rate,
+            });
+        }
+
+        let total_tax: f64 = results.iter().map(|result| result.tax).sum();
+        explanation_parts.push(explain(audience, "calc_inheritance_tax.total", context! {
+            total_tax,
+            count => results.len(),
+        }));
+
+        CalcInheritanceTaxResponse {
+            beneficiaries: results,
+            total_tax,
+            explanation: explanation_parts.join(". "),
+            assumptions: Vec::new(),
+            errors,
+            warnings,
+        }
+    }
+
+    /// Calculate social contributions per type, capped at each type's ceiling
+    fn calc_social_contributions_internal(
+        gross_salary: f64,
+        contributions: &[SocialContribution],
+        audience: Audience,
+    ) -> CalcSocialContributionsResponse {
+        let mut errors = Vec::new();
+        let mut warnings = Vec::new();
+        let mut explanation_parts = Vec::new();
+
+        // Validation
+        if gross_salary < 0.0 {
+            errors.push("Gross salary cannot be negative".to_string());
+        }
+
+        if !errors.is_empty() {
+            return CalcSocialContributionsResponse {
+                contributions: Vec::new(),
+                total_employee: 0.0,
+                total_employer: 0.0,
+                net_salary: 0.0,
+                explanation: explain(audience, "calc_social_contributions.invalid_inputs", context! {}),
+                assumptions: Vec::new(),
+                errors,
+                warnings,
+            };
+        }
+
+        explanation_parts.push(explain(audience, "calc_social_contributions.gross_salary", context! { gross_salary }));
+
+        let mut lines = Vec::with_capacity(contributions.len());
+        for contribution in contributions {
+            let base = contribution.ceiling.map_or(gross_salary, |ceiling| gross_salary.min(ceiling));
+            let capped = base < gross_salary;
+            let employee_amount = base * contribution.employee_rate;
+            let employer_amount = base * contribution.employer_rate;
+            explanation_parts.push(explain(audience, "calc_social_contributions.contribution", context! {
+                name => contribution.name,
+                ceiling => contribution.ceiling,
+                capped,
+                base,
+                employee_rate => contribution.employee_rate,
+                employer_rate => contribution.employer_rate,
+                employee_amount,
+                employer_amount,
+            }));
+            lines.push(ContributionLine {
+                name: contribution.name.clone(),
+                ceiling: contribution.ceiling,
+                base,
+                capped,
+                employee_rate: contribution.employee_rate,
+                employer_rate: contribution.employer_rate,
+                employee_amount,
+                employer_amount,
+            });
+        }
+
+        let total_employee: f64 = lines.iter().map(|line| line.employee_amount).sum();
+        let total_employer: f64 = lines.iter().map(|line| line.employer_amount).sum();
+        let net_salary = gross_salary - total_employee;
+        explanation_parts.push(explain(audience, "calc_social_contributions.totals", context! {
+            total_employee, total_employer, net_salary,
+        }));
+
+        let capped: Vec<&str> = lines.iter().filter(|line| line.capped).map(|line| line.name.as_str()).collect();
+        if !capped.is_empty() {
+            warnings.push(format!("Salary exceeds the ceiling for: {}", capped.join(", ")));
+        }
+
+        CalcSocialContributionsResponse {
+            contributions: lines,
+            total_employee,
+            total_employer,
+            net_salary,
+            explanation: explanation_parts.join(". "),
+            assumptions: Vec::new(),
+            errors,
+            warnings,
+        }
+    }
+
+    /// Calculate property tax from assessed value with assessment cap and homestead exemption
+    fn calc_property_tax_internal(
+        assessed_value: f64,
+        prior_year_value: Option<f64>,
+        homestead: bool,
+        millage: f64,
+        audience: Audience,
+    ) -> CalcPropertyTaxResponse {
+        let mut errors = Vec::new();
+        let mut warnings = Vec::new();
+        let mut explanation_parts = Vec::new();
+
+        // Validation
+        if assessed_value < 0.0 {
+            errors.push("Assessed value cannot be negative".to_string());
+        }
+        if prior_year_value.is_some_and(|value| value < 0.0) {
+            errors.push("Prior year value cannot be negative".to_string());
+        }
+        if millage < 0.0 {
+            errors.push("Millage cannot be negative".to_string());
+        }
+
+        if !errors.is_empty() {
+            return CalcPropertyTaxResponse {
+                assessed_value: 0.0,
+                capped_value: 0.0,
+                increase_capped: false,
+                exemption_applied: 0.0,
+                taxable_value: 0.0,
+                millage: 0.0,
+                tax: 0.0,
+                explanation: explain(audience, "calc_property_tax.invalid_inputs", context! {}),
+                assumptions: Vec::new(),
+                errors,
+                warnings,
+            };
+        }
+
+        explanation_parts.push(explain(audience, "calc_property_tax.assessed_value", context! { assessed_value }));
+
+        // Cap the year-over-year increase in taxable value
+        let cap = config().property_assessment_cap;
+        let (capped_value, increase_capped) = match prior_year_value {
+            Some(prior) => {
+                let limit = prior * (1.0 + cap);
+                let increase_capped = assessed_value > limit;
+                explanation_parts.push(explain(audience, "calc_property_tax.assessment_cap", context! {
+                    prior, cap, limit, increase_capped,
+                }));
+                (assessed_value.min(limit), increase_capped)
+            }
+            None => (assessed_value, false),
+        };
+
+        let exemption_applied = if homestead {
+            config().property_homestead_exemption.min(capped_value)
+        } else {
+            0.0
+        };
+        explanation_parts.push(explain(audience, "calc_property_tax.homestead", context! {
+            homestead, exemption_applied,
+        }));
+
+        let taxable_value = capped_value - exemption_applied;
+        let tax = taxable_value * millage / 1000.0;
+        explanation_parts.push(explain(audience, "calc_property_tax.tax", context! { taxable_value, millage, tax }));
+
+        if millage > 50.0 {
+            warnings.push(format!("High millage rate: {:.2} mills", millage));
+        }
+
+        CalcPropertyTaxResponse {
+            assessed_value,
+            capped_value,
+            increase_capped,
+            exemption_applied,
+            taxable_value,
+            millage,
+            tax,
+            explanation: explanation_parts.join(". "),
+            assumptions: Vec::new(),
+            errors,
+            warnings,
+        }
+    }
+
+    /// Calculate transfer tax on a price using slice or marginal computation
+    fn calc_transfer_tax_internal(
+        price: f64,
+        thresholds: &[f64],
+        rates: &[f64],
+        cliffs: &[bool],
+        method: TransferTaxMethod,
+        audience: Audience,
+    ) -> CalcTransferTaxResponse {
+        let mut errors = Vec::new();
+        let mut warnings = Vec::new();
+        let mut explanation_parts = Vec::new();
+
+        // Validation
+        if price < 0.0 {
+            errors.push("Price cannot be negative".to_string());
+        }
+        if rates.len() != thresholds.len() + 1 {
+            errors.push(format!(
+                "Configured transfer tax schedule has {} rates for {} thresholds (expected {})",
+                rates.len(), thresholds.len(), thresholds.len() + 1
+            ));
+        }
+
+        if !errors.is_empty() {
+            return CalcTransferTaxResponse {
+                price: 0.0,
+                method,
+                rate: 0.0,
+                tax: 0.0,
+                effective_rate: 0.0,
+                explanation: explain(audience, "calc_transfer_tax.invalid_inputs", context! {}),
+                assumptions: Vec::new(),
+                errors,
+                warnings,
+            };
+        }
+
+        // Bracket the price falls in; thresholds are inclusive upper bounds
+        let slice = thresholds.iter().position(|&threshold| price <= threshold).unwrap_or(thresholds.len());
+        let rate = rates[slice];
+        let lower = if slice == 0 { 0.0 } else { thresholds[slice - 1] };
+        let upper = thresholds.get(slice).copied();
+
+        let tax = match method {
+            TransferTaxMethod::Slice => {
+                let tax = price * rate;
+                explanation_parts.push(explain(audience, "calc_transfer_tax.slice", context! {
+                    price, lower, upper, rate, tax,
+                }));
+                // Just over a threshold, the whole price moves to the higher rate
+                if slice > 0 && price - lower <= lower * 0.01 {
+                    warnings.push(format!(
+                        "Price is within 1% above the {:.2} threshold; the whole price is taxed at {:.1}% instead of {:.1}%",
+                        lower, rate * 100.0, rates[slice - 1] * 100.0
+                    ));
+                }
+                tax
+            }
+            TransferTaxMethod::Marginal => {
+                let tax = Self::progressive_tax(price, thresholds, rates, cliffs);
+                explanation_parts.push(explain(audience, "calc_transfer_tax.marginal", context! {
+                    price, rate, tax, brackets => slice + 1,
+                }));
+                tax
+            }
+        };
+
+        let effective_rate = if price > 0.0 { tax / price } else { 0.0 };
+        explanation_parts.push(explain(audience, "calc_transfer_tax.effective_rate", context! { effective_rate }));
+
+        CalcTransferTaxResponse {
+            price,
+            method,
+            rate,
+            tax,
+            effective_rate,
+            explanation: explanation_parts.join(". "),
+            assumptions: Vec::new(),
+            errors,
+            warnings,
+        }
+    }
+
+    /// Calculate a fee as the sum of its fixed and percentage components
+    fn calc_fee_internal(
+        fee_code: &str,
+        value: f64,
+        schedules: &BTreeMap<String, Vec<FeeComponent>>,
+        audience: Audience,
+    ) -> CalcFeeResponse {
+        let mut errors = Vec::new();
+        let mut warnings = Vec::new();
+        let mut explanation_parts = Vec::new();
+
+        // Validation
+        let components = schedules.get(fee_code);
+        if components.is_none() {
+            errors.push(format!(
+                "Unknown fee code '{}' (expected one of: {})",
+                sanitize_for_error_message(fee_code),
+                schedules.keys().cloned().collect::<Vec<_>>().join(", ")
+            ));
+        }
+        if value < 0.0 {
+            errors.push("Value cannot be negative".to_string());
+        }
+
+        let Some(components) = components.filter(|_| errors.is_empty()) else {
+            return CalcFeeResponse {
+                fee_code: fee_code.to_string(),
+                components: Vec::new(),
+                fee: 0.0,
+                explanation: explain(audience, "calc_fee.invalid_inputs", context! {}),
+                assumptions: Vec::new(),
+                errors,
+                warnings,
+            };
+        };
+
+        explanation_parts.push(explain(audience, "calc_fee.value", context! { fee_code, value }));
+
+        let mut lines = Vec::with_capacity(components.len());
+        for component in components {
+            let excess = (value - component.above).max(0.0);
+            let uncapped = component.fixed + excess * component.rate;
+            let amount = component.cap.map_or(uncapped, |cap| uncapped.min(cap));
+            let capped = amount < uncapped;
+            explanation_parts.push(explain(audience, "calc_fee.component", context! {
+                name => component.name,
+                fixed => component.fixed,
+                rate => component.rate,
+                above => component.above,
+                cap => component.cap,
+                excess,
+                capped,
+                amount,
+            }));
+            lines.push(FeeLine {
+                name: component.name.clone(),
+                fixed: component.fixed,
+                rate: component.rate,
+                above: component.above,
+                cap: component.cap,
+                amount,
+                capped,
+            });
+        }
+
+        let fee: f64 = lines.iter().map(|line| line.amount).sum();
+        explanation_parts.push(explain(audience, "calc_fee.total", context! { fee }));
+
+        let capped: Vec<&str> = lines.iter().filter(|line| line.capped).map(|line| line.name.as_str()).collect();
+        if !capped.is_empty() {
+            warnings.push(format!("Fee capped for: {}", capped.join(", ")));
+        }
+
+        CalcFeeResponse {
+            fee_code: fee_code.to_string(),
+            components: lines,
+            fee,
+            explanation: explanation_parts.join(". "),
+            assumptions: Vec::new(),
+            errors,
+            warnings,
+        }
+    }
+
+    /// Adjust an amount between years using the price index
+    fn index_amount_internal(
+        amount: f64,
+        from_year: i32,
+        to_year: i32,
+        index: &PriceIndex,
+        audience: Audience,
+    ) -> IndexAmountResponse {
+        let mut errors = Vec::new();
+        let mut warnings = Vec::new();
+        let mut explanation_parts = Vec::new();
+
+        // Validation
+        if !amount.is_finite() {
+            errors.push("Amount must be a finite number".to_string());
+        }
+        let from_index = match index.value(from_year) {
+            Ok(value) => value,
+            Err(e) => {
+                errors.push(format!("Invalid from_year: {}", e));
+                0.0
+            }
+        };
+        let to_index = match index.value(to_year) {
+            Ok(value) => value,
+            Err(e) => {
+                errors.push(format!("Invalid to_year: {}", e));
+                0.0
+            }
+        };
+
+        if !errors.is_empty() {
+            return IndexAmountResponse {
+                amount: 0.0,
+                from_year,
+                to_year,
+                from_index: 0.0,
+                to_index: 0.0,
+                factor: 0.0,
+                indexed_amount: 0.0,
+                explanation: explain(audience, "index_amount.invalid_inputs", context! {}),
+                assumptions: Vec::new(),
+                errors,
+                warnings,
+            };
+        }
+
+        let factor = to_index / from_index;
+        let indexed_amount = amount * factor;
+        explanation_parts.push(explain(audience, "index_amount.index_values", context! {
+            from_year, from_index, to_year, to_index, factor,
+        }));
+        explanation_parts.push(explain(audience, "index_amount.result", context! { amount, factor, indexed_amount, to_year }));
+
+        if to_year < from_year {
+            warnings.push(format!("Adjusting back from {} to the earlier year {}", from_year, to_year));
+        }
+
+        IndexAmountResponse {
+            amount,
+            from_year,
+            to_year,
+            from_index,
+            to_index,
+            factor,
+            indexed_amount,
+            explanation: explanation_parts.join(". "),
+            assumptions: Vec::new(),
+            errors,
+            warnings,
+        }
+    }
+
+    /// Convert an amount between currencies using the date-stamped exchange rate table
+    fn convert_currency_internal(
+        amount: f64,
+        from_currency: &str,
+        to_currency: &str,
+        date: Option<NaiveDate>,
+        fx: &FxTable,
+        audience: Audience,
+    ) -> ConvertCurrencyResponse {
+        let mut errors = Vec::new();
+        let mut warnings = Vec::new();
+        let mut explanation_parts = Vec::new();
+
+        // Validation
+        if !amount.is_finite() {
+            errors.push("Amount must be a finite number".to_string());
+        }
+        let conversion = match fx.conversion(from_currency, to_currency, date) {
+            Ok(conversion) => Some(conversion),
+            Err(e) => {
+                errors.push(format!("Cannot convert {} to {}: {}", sanitize_for_error_message(from_currency), sanitize_for_error_message(to_currency), e));
+                None
+            }
+        };
+
+        let Some(conversion) = conversion.filter(|_| errors.is_empty()) else {
+            return ConvertCurrencyResponse {
+                amount: 0.0,
+                from_currency: from_currency.to_string(),
+                to_currency: to_currency.to_string(),
+                rate: 0.0,
+                rate_date: None,
+                converted_amount: 0.0,
+                explanation: explain(audience, "convert_currency.invalid_inputs", context! {}),
+                assumptions: Vec::new(),
+                errors,
+                warnings,
+            };
+        };
+
+        let rate = conversion.rate;
+        let rate_date = conversion.quote_date.map(|date| date.to_string());
+        let converted_amount = amount * rate;
+        explanation_parts.push(explain(audience, "convert_currency.rate", context! {
+            from_currency, to_currency, rate, rate_date, base_currency => fx.base_currency,
+        }));
+        explanation_parts.push(explain(audience, "convert_currency.result", context! {
+            amount, from_currency, to_currency, rate, converted_amount,
+        }));
+
+        if let (Some(requested), Some(quoted)) = (date, conversion.quote_date)
+            && quoted < requested
+        {
+            warnings.push(format!("No rate quoted on {}; used the {} rate", requested, quoted));
+        }
+
+        ConvertCurrencyResponse {
+            amount,
+            from_currency: from_currency.to_string(),
+            to_currency: to_currency.to_string(),
+            rate,
+            rate_date,
+            converted_amount,
+            explanation: explanation_parts.join(". "),
+            assumptions: Vec::new(),
+            errors,
+            warnings,
+        }
+    }
+
+    /// Build an intake form for a program from its tool's input schema and rule-derived hints
+    fn get_intake_schema_internal(program: &str, audience: Audience) -> GetIntakeSchemaResponse {
+        let mut errors = Vec::new();
+        let warnings = Vec::new();
+        let mut explanation_parts = Vec::new();
+
+        // Validation
+        let Some((tool, specs)) = intake_program(program) else {
+            errors.push(format!(
+                "Unknown program '{}' (expected one of: {})",
+                sanitize_for_error_message(program),
+                INTAKE_PROGRAMS.join(", ")
+            ));
+            return GetIntakeSchemaResponse {
+                program: program.to_string(),
+                tool: String::new(),
+                fields: Vec::new(),
+                logic: String::new(),
+                explanation: explain(audience, "get_intake_schema.invalid_inputs", context! {}),
+                assumptions: Vec::new(),
+                errors,
+                warnings,
+            };
+        };
+
+        let calculator = calculator(tool);
+        let schema = calculator
+            .map(|calculator| calculator.input_schema().as_ref().clone())
+            .unwrap_or_default();
+        let properties = schema.get("properties").and_then(serde_json::Value::as_object);
+        let required: Vec<&str> = schema
+            .get("required")
+            .and_then(serde_json::Value::as_array)
+            .map(|list| list.iter().filter_map(serde_json::Value::as_str).collect())
+            .unwrap_or_default();
+
+        let fields: Vec<IntakeField> = specs
+            .into_iter()
+            .map(|spec| IntakeField {
+                name: spec.name.to_string(),
+                field_type: spec.field_type.to_string(),
+                required: required.contains(&spec.name),
+                help: properties
+                    .and_then(|properties| properties.get(spec.name))
+                    .and_then(|property| property.get("description"))
+                    .and_then(serde_json::Value::as_str)
+                    .unwrap_or_default()
+                    .to_string(),
+                validation: spec.validation,
+                options: spec.options.iter().map(|option| option.to_string()).collect(),
+            })
+            .collect();
+
+        let required_count = fields.iter().filter(|field| field.required).count();
+        explanation_parts.push(explain(audience, "get_intake_schema.form", context! {
+            program, tool, fields => fields.len(), required => required_count,
+        }));
+
+        GetIntakeSchemaResponse {
+            program: program.to_string(),
+            tool: tool.to_string(),
+            fields,
+            logic: calculator.map(|calculator| calculator.logic().to_string()).unwrap_or_default(),
+            explanation: explanation_parts.join(". "),
+            assumptions: Vec::new(),
+            errors,
+            warnings,
+        }
+    }
+
+    /// Decide whichever program rules the partial inputs allow and combine them into a verdict
+    fn prescreen_internal(
+        program: &str,
+        inputs: &BTreeMap<String, serde_json::Value>,
+        audience: Audience,
+    ) -> PrescreenResponse {
+        let mut errors = Vec::new();
+        let warnings = Vec::new();
+        let mut explanation_parts = Vec::new();
+
+        let number = |name: &str| inputs.get(name).and_then(serde_json::Value::as_f64);
+        let flag = |name: &str| inputs.get(name).and_then(serde_json::Value::as_bool);
+        let text = |name: &str| inputs.get(name).and_then(serde_json::Value::as_str);
+        let missing = |names: &[&str]| -> RuleOutcome {
+            RuleOutcome::Undetermined(names.iter().filter(|name| !inputs.contains_key(**name)).map(|name| name.to_string()).collect())
+        };
+
+        // Validation
+        let tool = intake_program(program).map(|(tool, _)| tool);
+        if tool.is_none() {
+            errors.push(format!(
+                "Unknown program '{}' (expected one of: {})",
+                sanitize_for_error_message(program),
+                INTAKE_PROGRAMS.join(", ")
+            ));
+        }
+        if number("ami").is_some_and(|ami| ami <= 0.0) {
+            errors.push("Area Median Income (AMI) must be positive".to_string());
+        }
+        for field in ["household_size", "eligible_voters"] {
+            if number(field).is_some_and(|value| value <= 0.0) {
+                errors.push(format!("{} must be positive", field));
+            }
+        }
+        for field in ["income", "children", "turnout", "yes_votes"] {
+            if number(field).is_some_and(|value| value < 0.0) {
+                errors.push(format!("{} cannot be negative", field));
+            }
+        }
+        if let Some(proposal_type) = text("proposal_type").filter(|value| !matches!(*value, "general" | "amendment")) {
+            errors.push(format!("Invalid proposal type '{}' (must be 'general' or 'amendment')", sanitize_for_error_message(proposal_type)));
+        }
+
+        let Some(tool) = tool.filter(|_| errors.is_empty()) else {
+            return PrescreenResponse {
+                program: program.to_string(),
+                tool: String::new(),
+                verdict: PrescreenVerdict::Undetermined,
+                missing: Vec::new(),
+                rules: Vec::new(),
+                explanation: explain(audience, "prescreen.invalid_inputs", context! {}),
+                assumptions: Vec::new(),
+                errors,
+                warnings,
+            };
+        };
+
+        let outcomes: Vec<(&str, RuleOutcome)> = match program {
+            "housing_grant" => {
+                let subsidy = match flag("has_other_subsidy") {
+                    Some(true) => RuleOutcome::Failed("already receives another housing subsidy".to_string()),
+                    Some(false) => RuleOutcome::Passed,
+                    None => missing(&["has_other_subsidy"]),
+                };
+                // 60% of AMI, scaled to the household by the configured rule
+                let scale = config().housing_equivalence_scale;
+                let limit = |ami: f64, size: f64, children: f64| {
+                    Self::housing_income_threshold(ami, size as i32, children as i32, scale)
+                };
+                let exceeds = |income: f64, threshold: f64| format!("income {:.2} exceeds the limit of {:.2}", income, threshold);
+                let income_limit = match (number("income"), number("ami"), number("household_size")) {
+                    (Some(income), Some(ami), Some(size)) => match number("children") {
+                        Some(children) => {
+                            let threshold = limit(ami, size, children);
+                            RuleOutcome::check(income <= threshold, || exceeds(income, threshold))
+                        }
+                        // Children lower the limit, so the bounds are all adults and a single adult
+                        None if !scale.counts_children() || income <= limit(ami, size, size - 1.0) => {
+                            let threshold = limit(ami, size, 0.0);
+                            RuleOutcome::check(income <= threshold, || exceeds(income, threshold))
+                        }
+                        None if income > limit(ami, size, 0.0) => RuleOutcome::Failed(exceeds(income, limit(ami, size, 0.0))),
+                        None => missing(&["children"]),
+                    },
+                    (Some(income), Some(ami), None) if income <= limit(ami, 1.0, 0.0) => RuleOutcome::Passed,
+                    // Only the size rule has a highest limit
+                    (Some(income), Some(ami), None) if scale == EquivalenceScale::Size && income > 0.60 * ami * 1.10 => {
+                        RuleOutcome::Failed(format!(
+                            "income {:.2} exceeds the limit of {:.2} for any household size", income, 0.60 * ami * 1.10
+                        ))
+                    }
+                    _ => missing(&["income", "ami", "household_size"]),
+                };
+                vec![("subsidy", subsidy), ("income_limit", income_limit)]
+            }
+            _ => {
+                let turnout = match (number("turnout"), number("eligible_voters")) {
+                    (Some(turnout), Some(eligible)) => RuleOutcome::check(turnout / eligible >= 0.60, || {
+                        format!("turnout {:.1}% is below the 60% minimum", turnout / eligible * 100.0)
+                    }),
+                    _ => missing(&["turnout", "eligible_voters"]),
+                };
+                // General proposals need a simple majority of votes cast, amendments two thirds
+                let yes_votes = match (number("yes_votes"), number("turnout").filter(|turnout| *turnout > 0.0)) {
+                    (Some(yes), Some(turnout)) => {
+                        let ratio = yes / turnout;
+                        let fail = || format!("{:.1}% yes votes is below the required majority", ratio * 100.0);
+                        match text("proposal_type") {
+                            Some("amendment") => RuleOutcome::check(ratio >= 2.0 / 3.0, fail),
+                            Some(_) => RuleOutcome::check(ratio > 0.50, fail),
+                            None if ratio >= 2.0 / 3.0 => RuleOutcome::Passed,
+                            None if ratio <= 0.50 => RuleOutcome::Failed(fail()),
+                            None => missing(&["proposal_type"]),
+                        }
+                    }
+                    _ => missing(&["yes_votes", "turnout"]),
+                };
+                vec![("turnout", turnout), ("yes_votes", yes_votes)]
+            }
+        };
+
+        let mut rules = Vec::with_capacity(outcomes.len());
+        let mut still_missing: Vec<String> = Vec::new();
+        for (rule, outcome) in outcomes {
+            let (status, detail, needed) = match outcome {
+                RuleOutcome::Passed => ("passed", None, Vec::new()),
+                RuleOutcome::Failed(detail) => ("failed", Some(detail), Vec::new()),
+                RuleOutcome::Undetermined(needed) => ("undetermined", None, needed),
+            };
+            explanation_parts.push(explain(audience, "prescreen.rule", context! {
+                rule, status, detail, missing => needed,
+            }));
+            for field in &needed {
+                if !still_missing.contains(field) {
+                    still_missing.push(field.clone());
+                }
+            }
+            rules.push(PrescreenRule { rule: rule.to_string(), status: status.to_string(), detail, missing: needed });
+        }
+
+        let verdict = if rules.iter().any(|rule| rule.status == "failed") {
+            PrescreenVerdict::Ineligible
+        } else if still_missing.is_empty() {
+            PrescreenVerdict::Eligible
+        } else {
+            PrescreenVerdict::Undetermined
+        };
+        if verdict != PrescreenVerdict::Undetermined {
+            still_missing.clear();
+        }
+        explanation_parts.push(explain(audience, "prescreen.verdict", context! {
+            verdict, tool, missing => still_missing,
+        }));
+
+        PrescreenResponse {
+            program: program.to_string(),
+            tool: tool.to_string(),
+            verdict,
+            missing: still_missing,
+            rules,
+            explanation: explanation_parts.join(". "),
+            assumptions: Vec::new(),
+            errors,
+            warnings,
+        }
+    }
+
+    /// Summarize recorded usage: calls and parameter shapes per tool and the most frequent errors
+    fn get_usage_report_internal(
+        stats: Option<&UsageStats>,
+        tool: Option<&str>,
+        limit: i32,
+        audience: Audience,
+    ) -> GetUsageReportResponse {
+        let mut errors = Vec::new();
+        let warnings = Vec::new();
+        let mut explanation_parts = Vec::new();
+
+        // Validation
+        if stats.is_none() {
+            errors.push("Usage analytics are disabled (ENGINE_USAGE_ANALYTICS=false)".to_string());
+        }
+        if limit <= 0 {
+            errors.push("limit must be positive".to_string());
+        }
+        if let Some(tool) = tool
+            && !INPUT_SCHEMAS.contains_key(tool)
+        {
+            errors.push(format!("Unknown tool '{}'", sanitize_for_error_message(tool)));
+        }
+
+        let Some(stats) = stats.filter(|_| errors.is_empty()) else {
+            return GetUsageReportResponse {
+                since: String::new(),
+                total_calls: 0,
+                tools: Vec::new(),
+                top_errors: Vec::new(),
+                explanation: explain(audience, "get_usage_report.invalid_inputs", context! {}),
+                assumptions: Vec::new(),
+                errors,
+                warnings,
+            };
+        };
+
+        let selected: Vec<(&String, &ToolUsage)> = stats
+            .tools
+            .iter()
+            .filter(|(name, _)| tool.is_none_or(|tool| tool == name.as_str()))
+            .collect();
+
+        let mut tools: Vec<ToolUsageReport> = selected
+            .iter()
+            .map(|(name, usage)| ToolUsageReport {
+                tool: name.to_string(),
+                calls: usage.calls,
+                failed_calls: usage.failed_calls,
+                parameters: usage
+                    .parameter_types
+                    .iter()
+                    .map(|(name, types)| ParameterUsage { name: name.clone(), types: types.clone() })
+                    .collect(),
+            })
+            .collect();
+        tools.sort_by(|a, b| b.calls.cmp(&a.calls).then_with(|| a.tool.cmp(&b.tool)));
+
+        let mut top_errors: Vec<ErrorUsage> = selected
+            .iter()
+            .flat_map(|(name, usage)| {
+                usage.errors.iter().map(|(message, count)| ErrorUsage {
+                    tool: name.to_string(),
+                    message: message.clone(),
+                    count: *count,
+                })
+            })
+            .collect();
+        top_errors.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.tool.cmp(&b.tool)));
+        top_errors.truncate(limit as usize);
+
+        let total_calls: u64 = tools.iter().map(|usage| usage.calls).sum();
+        let failed_calls: u64 = tools.iter().map(|usage| usage.failed_calls).sum();
+        let since = stats.since.to_rfc3339();
+        explanation_parts.push(explain(audience, "get_usage_report.summary", context! {
+            calls => total_calls, failed => failed_calls, tools => tools.len(), since,
+        }));
+        if let Some(top) = top_errors.first() {
+            explanation_parts.push(explain(audience, "get_usage_report.top_error", context! {
+                tool => top.tool, message => top.message, count => top.count,
+            }));
+        }
+
+        GetUsageReportResponse {
+            since,
+            total_calls,
+            tools,
+            top_errors,
+            explanation: explanation_parts.join(". "),
+            assumptions: Vec::new(),
+            errors,
+            warnings,
+        }
+    }
+
+    /// List registered tools grouped by category
+    fn list_tools_by_category_internal(
+        category: Option<&str>,
+        audience: Audience,
+    ) -> ListToolsByCategoryResponse {
+        let mut errors = Vec::new();
+        let warnings = Vec::new();
+
+        // Validation
+        let selected = match category.map(ToolCategory::parse).transpose() {
+            Ok(selected) => selected,
+            Err(e) => {
+                errors.push(format!("category {}", e));
+                return ListToolsByCategoryResponse {
+                    categories: Vec::new(),
+                    total_tools: 0,
+                    explanation: explain(audience, "list_tools_by_category.invalid_inputs", context! {}),
+                    assumptions: Vec::new(),
+                    errors,
+                    warnings,
+                };
+            }
+        };
+
+        let categories: Vec<ToolCategoryListing> = ToolCategory::ALL
+            .into_iter()
+            .filter(|category| selected.is_none_or(|selected| selected == *category))
+            .map(|category| ToolCategoryListing {
+                category,
+                tools: calculators()
+                    .filter(|calculator| calculator.category() == category)
+                    .map(|calculator| {
+                        let tool = calculator.tool();
+                        ToolSummary {
+                            title: tool.title.clone().unwrap_or_else(|| tool.name.to_string()),
+                            name: tool.name.to_string(),
+                            logic: calculator.logic().to_string(),
+                        }
+                    })
+                    .collect(),
+            })
+            .collect();
+        let total_tools = categories.iter().map(|listing| listing.tools.len()).sum();
+        let explanation = explain(audience, "list_tools_by_category.summary", context! {
+            tools => total_tools, categories => categories.len(),
+        });
+
+        ListToolsByCategoryResponse {
+            categories,
+            total_tools,
+            explanation,
+            assumptions: Vec::new(),
+            errors,
+            warnings,
+        }
+    }
+
+    /// Describe how responses are shaped for the negotiated client
+    fn describe_capabilities_internal(
+        client: Option<&ClientProfile>,
+        sampling_summary: bool,
+        audience: Audience,
+    ) -> DescribeCapabilitiesResponse {
+        let errors = Vec::new();
+        let mut warnings = Vec::new();
+        let mut explanation_parts = Vec::new();
+
+        match client {
+            Some(client) => explanation_parts.push(explain(audience, "describe_capabilities.negotiated", context! {
+                client => client.client_name, protocol => client.protocol_version,
+            })),
+            None => {
+                warnings.push("The client has not completed initialize in this session; default shaping applies".to_string());
+                explanation_parts.push(explain(audience, "describe_capabilities.not_negotiated", context! {}));
+            }
+        }
+        let structured_content = client.is_some_and(|client| client.structured_content);
+        let sampling_summary = sampling_summary && client.is_some_and(|client| client.sampling);
+        explanation_parts.push(explain(audience, "describe_capabilities.shaping", context! {
+            structured_content, sampling_summary,
+        }));
+
+        DescribeCapabilitiesResponse {
+            negotiated: client.is_some(),
+            client: client.cloned(),
+            structured_content,
+            resource_links: false,
+            sampling_summary,
+            explanation: explanation_parts.join(". "),
+            assumptions: Vec::new(),
+            errors,
+            warnings,
+        }
+    }
+
+    /// List the most recent calls whose arguments failed to deserialize
+    fn get_recent_schema_failures_internal(
+        samples: Option<&[SchemaFailure]>,
+        tool: Option<&str>,
+        limit: i32,
+        audience: Audience,
+    ) -> GetRecentSchemaFailuresResponse {
+        let mut errors = Vec::new();
+        let warnings = Vec::new();
+
+        // Validation
+        if samples.is_none() {
+            errors.push("Schema failure sampling is disabled (ENGINE_SCHEMA_FAILURE_SAMPLES=0)".to_string());
+        }
+        if limit <= 0 {
+            errors.push("limit must be positive".to_string());
+        }
+        if let Some(tool) = tool
+            && !INPUT_SCHEMAS.contains_key(tool)
+        {
+            errors.push(format!("Unknown tool '{}'", sanitize_for_error_message(tool)));
+        }
+
+        let Some(samples) = samples.filter(|_| errors.is_empty()) else {
+            return GetRecentSchemaFailuresResponse {
+                failures: Vec::new(),
+                total_recorded: 0,
+                explanation: explain(audience, "get_recent_schema_failures.invalid_inputs", context! {}),
+                assumptions: Vec::new(),
+                errors,
+                warnings,
+            };
+        };
+
+        let selected: Vec<&SchemaFailure> = samples
+            .iter()
+            .rev()
+            .filter(|failure| tool.is_none_or(|tool| tool == failure.tool))
+            .collect();
+        let total_recorded = selected.len();
+        let failures: Vec<SchemaFailureReport> = selected
+            .into_iter()
+            .take(limit as usize)
+            .map(|failure| SchemaFailureReport {
+                tool: failure.tool.clone(),
+                at: failure.at.to_rfc3339(),
+                error: failure.error.clone(),
+                shape: failure.shape.clone(),
+            })
+            .collect();
+        let explanation = explain(audience, "get_recent_schema_failures.summary", context! {
+            listed => failures.len(), total => total_recorded, tool,
+        });
+
+        GetRecentSchemaFailuresResponse {
+            failures,
+            total_recorded,
+            explanation,
+            assumptions: Vec::new(),
+            errors,
+            warnings,
+        }
+    }
+
+    /// Check a profile against the startup checks, then save it to the profile store and put it
+    /// in force in `rules`. `env` reads the environment, which takes precedence over the profile.
+    fn upsert_profile_internal(
+        profile: &BTreeMap<String, ProfileSetting>,
+        env: &dyn Fn(&str) -> Option<String>,
+        rules: &RwLock<LoadedRules>,
+        audience: Audience,
+    ) -> UpsertProfileResponse {
+        let mut errors = Vec::new();
+        let mut warnings = Vec::new();
+
+        // Validation
+        let store = match (env("ENGINE_PROFILE"), env("ENGINE_PROFILE_FILE")) {
+            (Some(_), _) => {
+                errors.push("The profile is set inline in ENGINE_PROFILE and cannot be replaced; use ENGINE_PROFILE_FILE to store profiles".to_string());
+                None
+            }
+            (None, Some(path)) => Some(path),
+            (None, None) => {
+                errors.push("No profile store is configured (ENGINE_PROFILE_FILE)".to_string());
+                None
+            }
+        };
+        let settings = EngineConfig::profile_settings(profile).unwrap_or_else(|e| {
+            errors.push(format!("Invalid profile: {}", e));
+            BTreeMap::new()
+        });
+        let (loaded, problems) = preflight::problems_while(|| {
+            LoadedRules::from_vars(&|name| env(name).or_else(|| settings.get(name).cloned()))
+        });
+        errors.extend(problems.into_iter().map(|problem| format!("Startup check failed: {}", problem)));
+
+        let overridden: Vec<String> = settings.keys().filter(|name| env(name).is_some()).cloned().collect();
+        if !overridden.is_empty() {
+            warnings.push(format!("Overridden by environment variables: {}", overridden.join(", ")));
+        }
+        let unsourced: Vec<&str> = profile
+            .iter()
+            .filter(|(_, setting)| setting.source.as_deref().is_none_or(|source| source.trim().is_empty() || source == PROFILE_SOURCE))
+            .map(|(name, _)| name.as_str())
+            .collect();
+        if !unsourced.is_empty() && errors.is_empty() {
+            warnings.push(format!("No source given for: {}", unsourced.join(", ")));
+        }
+
+        let mut rules = rules.write().unwrap_or_else(PoisonError::into_inner);
+        let previous_version = rules.version.clone();
+        // Saved under the lock, so the stored profile is always the one in force
+        if let (Some(path), true) = (&store, errors.is_empty())
+            && let Err(e) = EngineConfig::save_profile(path, profile)
+        {
+            errors.push(format!("Profile not saved: {}", e));
+        }
+        if !errors.is_empty() {
+            return UpsertProfileResponse {
+                applied: false,
+                settings: profile.len(),
+                config_version: previous_version.clone(),
+                previous_version,
+                overridden,
+                explanation: explain(audience, "upsert_profile.invalid_inputs", context! {}),
+                assumptions: Vec::new(),
+                errors,
+                warnings,
+            };
+        }
+
+        let config_version = loaded.version.clone();
+        *rules = loaded;
+        tracing::info!(
+            "Configuration profile saved to {} and put in force; version {} -> {}",
+            store.unwrap_or_default(), previous_version, config_version
+        );
+        let explanation = explain(audience, "upsert_profile.summary", context! {
+            settings => profile.len(), previous => previous_version, version => config_version,
+        });
+
+        UpsertProfileResponse {
+            applied: true,
+            settings: profile.len(),
+            previous_version,
+            config_version,
+            overridden,
+            explanation,
+            assumptions: Vec::new(),
+            errors,
+            warnings,
+        }
+    }
+
+    /// Compare two profiles setting by setting, and bracket lists bracket by bracket
+    fn diff_profiles_internal(
+        before: &BTreeMap<String, ProfileSetting>,
+        after: &BTreeMap<String, ProfileSetting>,
+        index: &PriceIndex,
+        audience: Audience,
+    ) -> DiffProfilesResponse {
+        let mut errors = Vec::new();
+        let mut warnings = Vec::new();
+
+        // Validation
+        let (before, after) = match (EngineConfig::profile_settings(before), EngineConfig::profile_settings(after)) {
+            (Ok(before), Ok(after)) => (before, after),
+            (before, after) => {
+                errors.extend(before.err().map(|e| format!("Invalid before profile: {}", e)));
+                errors.extend(after.err().map(|e| format!("Invalid after profile: {}", e)));
+                return DiffProfilesResponse {
+                    changes: Vec::new(),
+                    unchanged: 0,
+                    explanation: explain(audience, "diff_profiles.invalid_inputs", context! {}),
+                    assumptions: Vec::new(),
+                    errors,
+                    warnings,
+                };
+            }
+        };
+
+        let mut changes = Vec::new();
+        let mut unchanged = 0;
+        let names: BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+        for name in names {
+            let (old, new) = (before.get(name), after.get(name));
+            let change = match (old, new) {
+                (Some(old), Some(new)) if Self::same_setting_value(old, new) => {
+                    unchanged += 1;
+                    continue;
+                }
+                (None, _) => "added",
+                (_, None) => "removed",
+                _ => "changed",
+            };
+            let brackets = if BRACKET_SETTINGS.contains(&name.as_str()) {
+                let parse = |value: Option<&String>| {
+                    value.map_or(Ok((Vec::new(), Vec::new(), Vec::new())), |json| EngineConfig::parse_tax_brackets(json, index))
+                };
+                match (parse(old), parse(new)) {
+                    (Ok(old), Ok(new)) => Self::diff_brackets(name, &old, &new, audience),
+                    (old, new) => {
+                        let e = old.err().or(new.err()).unwrap_or_default();
+                        warnings.push(format!("{} not compared bracket by bracket: {}", name, e));
+                        Vec::new()
+                    }
+                }
+            } else {
+                Vec::new()
+            };
+            let summary = explain(audience, &format!("diff_profiles.setting_{}", change), context! {
+                setting => name, before => old, after => new, brackets => brackets.len(),
+            });
+            changes.push(SettingChange {
+                setting: name.clone(),
+                change: change.to_string(),
+                before: old.cloned(),
+                after: new.cloned(),
+                brackets,
+                summary,
+            });
+        }
+
+        let count = |change: &str| changes.iter().filter(|setting| setting.change == change).count();
+        let explanation = std::iter::once(explain(audience, "diff_profiles.summary", context! {
+            changed => changes.len(), added => count("added"), removed => count("removed"),
+            modified => count("changed"), unchanged => unchanged,
+        }))
+        .chain(changes.iter().flat_map(|setting| {
+            std::iter::once(setting.summary.clone()).chain(setting.brackets.iter().map(|bracket| bracket.summary.clone()))
+        }))
+        .collect::<Vec<_>>()
+        .join(". ");
+
+        DiffProfilesResponse {
+            changes,
+            unchanged,
+            explanation,
+            assumptions: Vec::new(),
+            errors,
+            warnings,
+        }
+    }
+
+    /// Whether two setting values are the same: equal text, or equal JSON such as `0.2` and `0.20`
+    fn same_setting_value(a: &str, b: &str) -> bool {
+        a == b || serde_json::from_str::<serde_json::Value>(a).is_ok_and(|a| serde_json::from_str(b).is_ok_and(|b: serde_json::Value| a == b))
+    }
+
+    /// Brackets that differ between two bracket lists, compared by position from the lowest
+    fn diff_brackets(setting: &str, before: &BracketSchedule, after: &BracketSchedule, audience: Audience) -> Vec<BracketChange> {
+        let rows = |(thresholds, rates, cliffs): &BracketSchedule| -> Vec<(Option<f64>, f64, bool)> {
+            rates.iter().enumerate().map(|(i, rate)| (thresholds.get(i).copied(), *rate, cliffs[i])).collect()
+        };
+        let (before, after) = (rows(before), rows(after));
+        (0..before.len().max(after.len()))
+            .filter_map(|i| {
+                let (old, new) = (before.get(i), after.get(i));
+                let change = match (old, new) {
+                    (Some(old), Some(new)) if old == new => return None,
+                    (None, _) => "added",
+                    (_, None) => "removed",
+                    _ => "changed",
+                };
+                let (before_up_to, before_rate, before_cliff) = (old.and_then(|b| b.0), old.map(|b| b.1), old.map(|b| b.2));
+                let (after_up_to, after_rate, after_cliff) = (new.and_then(|b| b.0), new.map(|b| b.1), new.map(|b| b.2));
+                let summary = explain(audience, &format!("diff_profiles.bracket_{}", change), context! {
+                    setting, bracket => i + 1, before_up_to, after_up_to, before_rate, after_rate, before_cliff, after_cliff,
+                });
+                Some(BracketChange {
+                    bracket: i + 1,
+                    change: change.to_string(),
+                    before_up_to,
+                    after_up_to,
+                    before_rate,
+                    after_rate,
+                    before_cliff,
+                    after_cliff,
+                    summary,
+                })
+            })
+            .collect()
+    }
+
+    /// Combine the evaluations at every combination of range ends into result ranges
+    fn evaluate_ranges_internal(
+        tool: &str,
+        ranges: Vec<InputRange>,
+        evaluations: &[(String, Result<serde_json::Value, String>)],
+        audience: Audience,
+    ) -> EvaluateRangesResponse {
+        let mut errors = Vec::new();
+        let warnings = Vec::new();
+
+        // Validation
+        if ranges.is_empty() {
+            errors.push("Give at least one argument as a range, e.g. {\"min\": 38000, \"max\": 42000}".to_string());
+        }
+        if ranges.len() > MAX_RANGES {
+            errors.push(format!("At most {} arguments can be ranges, got {}", MAX_RANGES, ranges.len()));
+        }
+        for range in &ranges {
+            if range.min > range.max {
+                errors.push(format!("{}: min {} is above max {}", range.parameter, range.min, range.max));
+            }
+        }
+        if errors.is_empty() {
+            for (at, evaluation) in evaluations {
+                if let Err(message) = evaluation {
+                    errors.push(format!("{} failed at {}: {}", tool, at, message));
+                }
+            }
+        }
+
+        if !errors.is_empty() {
+            return EvaluateRangesResponse {
+                tool: tool.to_string(),
+                ranges,
+                evaluations: 0,
+                outcomes: Vec::new(),
+                flips: Vec::new(),
+                export: None,
+                explanation: explain(audience, "evaluate_ranges.invalid_inputs", context! {}),
+                assumptions: Vec::new(),
+                errors,
+                warnings,
+            };
+        }
+
+        // Lowest and highest value of each field over the evaluations
+        let mut extremes: BTreeMap<String, (serde_json::Value, serde_json::Value)> = BTreeMap::new();
+        for payload in evaluations.iter().filter_map(|(_, evaluation)| evaluation.as_ref().ok()) {
+            let mut fields = BTreeMap::new();
+            outcome_fields(payload, "", &mut fields);
+            for (field, value) in fields {
+                let (low, high) = extremes.entry(field).or_insert_with(|| (value.clone(), value.clone()));
+                if outcome_less(&value, low) {
+                    *low = value.clone();
+                }
+                if outcome_less(high, &value) {
+                    *high = value;
+                }
+            }
+        }
+        let outcomes: Vec<OutcomeRange> = extremes
+            .into_iter()
+            .map(|(field, (min, max))| OutcomeRange { varies: min != max, field, min, max })
+            .collect();
+        let flips: Vec<String> = outcomes
+            .iter()
+            .filter(|outcome| outcome.varies && outcome.min.is_boolean())
+            .map(|outcome| outcome.field.clone())
+            .collect();
+
+        let explanation = explain(audience, "evaluate_ranges.summary", context! {
+            tool,
+            evaluations => evaluations.len(),
+            ranges => ranges.iter().map(|range| context! { parameter => range.parameter, min => range.min, max => range.max }).collect::<Vec<_>>(),
+            flips,
+        });
+
+        EvaluateRangesResponse {
+            tool: tool.to_string(),
+            ranges,
+            evaluations: evaluations.len(),
+            outcomes,
+            flips,
+            export: None,
+            explanation,
+            assumptions: Vec::new(),
+            errors,
+            warnings,
+        }
+    }
+
+    /// Summarize the evaluations at every draw into percentiles and probabilities
+    fn simulate_uncertainty_internal(
+        tool: &str,
+        inputs: Vec<InputDistribution>,
+        draws: i32,
+        seed: i32,
+        evaluations: &[(String, Result<serde_json::Value, String>)],
+        audience: Audience,
+    ) -> SimulateUncertaintyResponse {
+        let mut errors = Vec::new();
+        let warnings = Vec::new();
+
+        // Validation
+        if inputs.is_empty() {
+            errors.push("Give at least one argument as a distribution, e.g. {\"distribution\": \"normal\", \"mean\": 40000, \"sd\": 2000}".to_string());
+        }
+        for input in &inputs {
+            if let Err(e) = input.distribution.check() {
+                errors.push(format!("{}: {}", input.parameter, e));
+            }
+        }
+        if !(1..=MAX_DRAWS).contains(&draws) {
+            errors.push(format!("Draws must be from 1 to {}, got {}", MAX_DRAWS, draws));
+        }
+        if seed < 0 {
+            errors.push(format!("Seed cannot be negative, got {}", seed));
+        }
+        if errors.is_empty()
+            && let Some((at, Err(message))) = evaluations.iter().find(|(_, evaluation)| evaluation.is_err())
+        {
+            errors.push(format!("{} failed at {}: {}", tool, at, message));
+        }
+
+        if !errors.is_empty() {
+            return SimulateUncertaintyResponse {
+                tool: tool.to_string(),
+                inputs,
+                draws: 0,
+                seed: seed.max(0) as u64,
+                outcomes: Vec::new(),
+                probabilities: Vec::new(),
+                export: None,
+                explanation: explain(audience, "simulate_uncertainty.invalid_inputs", context! {}),
+                assumptions: Vec::new(),
+                errors,
+                warnings,
+            };
+        }
+
+        // Values of each field over the draws
+        let mut numbers: BTreeMap<String, Vec<f64>> = BTreeMap::new();
+        let mut trues: BTreeMap<String, usize> = BTreeMap::new();
+        for payload in evaluations.iter().filter_map(|(_, evaluation)| evaluation.as_ref().ok()) {
+            let mut fields = BTreeMap::new();
+            outcome_fields(payload, "", &mut fields);
+            for (field, value) in fields {
+                match value {
+                    serde_json::Value::Bool(value) => *trues.entry(field).or_default() += usize::from(value),
+                    value => numbers.entry(field).or_default().extend(value.as_f64()),
+                }
+            }
+        }
+        let outcomes: Vec<OutcomeDistribution> = numbers
+            .into_iter()
+            .map(|(field, mut values)| {
+                values.sort_by(f64::total_cmp);
+                OutcomeDistribution {
+                    field,
+                    mean: values.iter().sum::<f64>() / values.len() as f64,
+                    p5: percentile(&values, 0.05),
+                    p25: percentile(&values, 0.25),
+                    p50: percentile(&values, 0.50),
+                    p75: percentile(&values, 0.75),
+                    p95: percentile(&values, 0.95),
+                }
+            })
+            .collect();
+        let probabilities: Vec<OutcomeProbability> = trues
+            .into_iter()
+            .map(|(field, count)| OutcomeProbability { field, probability: count as f64 / evaluations.len() as f64 })
+            .collect();
+
+        let explanation = explain(audience, "simulate_uncertainty.summary", context! {
+            tool,
+            draws => evaluations.len(),
+            seed,
+            inputs,
+            probabilities,
+        });
+
+        SimulateUncertaintyResponse {
+            tool: tool.to_string(),
+            inputs,
+            draws: evaluations.len(),
+            seed: seed as u64,
+            outcomes,
+            probabilities,
+            export: None,
+            explanation,
+            assumptions: Vec::new(),
+            errors,
+            warnings,
+        }
+    }
+
+    /// Check an appeal filing date against the appeal window of a decision
+    fn check_appeal_deadline_internal(
+        decision_date: NaiveDate,
+        filing_date: NaiveDate,
+        window_days: i32,
+        business_days: bool,
+        holidays: &BTreeSet<NaiveDate>,
+        audience: Audience,
+    ) -> CheckAppealDeadlineResponse {
+        let mut errors = Vec::new();
+        let mut warnings = Vec::new();
+
+        // Validation
+        if window_days < 1 {
+            errors.push("Appeal window must be at least 1 day".to_string());
+        }
+        if filing_date < decision_date {
+            errors.push(format!("Filing date {} is before the decision date {}", filing_date, decision_date));
+        }
+
+        if !errors.is_empty() {
+            return CheckAppealDeadlineResponse {
+                within_window: false,
+                deadline: String::new(),
+                days_remaining: 0,
+                days_overdue: 0,
+                window_days,
+                business_days,
+                deadline_extended: false,
+                explanation: explain(audience, "check_appeal_deadline.invalid_inputs", context! {}),
+                assumptions: Vec::new(),
+                errors,
+                warnings,
+            };
+        }
+
+        let mut explanation_parts = vec![explain(audience, "check_appeal_deadline.inputs", context! {
+            decision_date => decision_date.to_string(),
+            filing_date => filing_date.to_string(),
+        })];
+
+        // The window starts the day after the decision
+        let (deadline, nominal_deadline) = if business_days {
+            let mut deadline = decision_date;
+            for _ in 0..window_days {
+                deadline = next_business_day(deadline, holidays);
+            }
+            (deadline, deadline)
+        } else {
+            let nominal = decision_date + chrono::Duration::days(i64::from(window_days));
+            let deadline = if is_business_day(nominal, holidays) { nominal } else { next_business_day(nominal, holidays) };
+            (deadline, nominal)
+        };
+        let deadline_extended = deadline != nominal_deadline;
+        explanation_parts.push(explain(audience, "check_appeal_deadline.deadline", context! {
+            window_days,
+            business_days,
+            nominal_deadline => nominal_deadline.to_string(),
+            deadline => deadline.to_string(),
+            deadline_extended,
+        }));
+
+        let within_window = filing_date <= deadline;
+        let days_remaining = if within_window { (deadline - filing_date).num_days() } else { 0 };
+        let days_overdue = if within_window { 0 } else { (filing_date - deadline).num_days() };
+        explanation_parts.push(explain(audience, "check_appeal_deadline.result", context! {
+            within_window, days_remaining, days_overdue,
+            deadline => deadline.to_string(),
+        }));
+
+        if let Some(last) = holidays.iter().next_back()
+            && *last < deadline
+            && deadline.year() > last.year()
+        {
+            warnings.push(format!(
+                "No public holidays are configured for {}; the deadline only skips weekends in that year",
+                deadline.year()
+            ));
+        }
+        if within_window && days_remaining <= 3 {
+            warnings.push(format!("Only {} day(s) left to file the appeal", days_remaining));
+        }
+
+        CheckAppealDeadlineResponse {
+            within_window,
+            deadline: deadline.to_string(),
+            days_remaining,
+            days_overdue,
+            window_days,
+            business_days,
+            deadline_extended,
+            explanation: explanation_parts.join(". "),
+            assumptions: Vec::new(),
+            errors,
+            warnings,
+        }
+    }
+
+    /// Checklist of the documents a program requires for an applicant's situation
+    fn get_required_documents_internal(
+        program: &str,
+        situation: &[String],
+        checklists: &BTreeMap<String, Vec<RequiredDocument>>,
+        audience: Audience,
+    ) -> GetRequiredDocumentsResponse {
+        let mut errors = Vec::new();
+        let mut warnings = Vec::new();
+
+        // Validation
+        let checklist = checklists.get(program);
+        if checklist.is_none() {
+            errors.push(format!(
+                "Unknown program '{}' (expected one of: {})",
+                sanitize_for_error_message(program),
+                checklists.keys().cloned().collect::<Vec<_>>().join(", ")
+            ));
+        }
+
+        let Some(checklist) = checklist.filter(|_| errors.is_empty()) else {
+            return GetRequiredDocumentsResponse {
+                program: program.to_string(),
+                documents: Vec::new(),
+                known_situations: Vec::new(),
+                explanation: explain(audience, "get_required_documents.invalid_inputs", context! {}),
+                assumptions: Vec::new(),
+                errors,
+                warnings,
+            };
+        };
+
+        let mut known_situations: Vec<String> = checklist.iter().filter_map(|item| item.when.clone()).collect();
+        known_situations.sort();
+        known_situations.dedup();
+        for flag in situation {
+            if !known_situations.contains(flag) {
+                warnings.push(format!(
+                    "Situation '{}' does not add documents for {} (known: {})",
+                    sanitize_for_error_message(flag),
+                    program,
+                    known_situations.join(", ")
+                ));
+            }
+        }
+
+        // Documents for every applicant first, then those for the situation, in configured order
+        let general = checklist.iter().filter(|item| item.when.is_none());
+        let specific = checklist.iter().filter(|item| item.when.as_ref().is_some_and(|when| situation.contains(when)));
+        let documents: Vec<DocumentRequirement> = general
+            .chain(specific)
+            .map(|item| DocumentRequirement { document: item.document.clone(), situation: item.when.clone() })
+            .collect();
+        let applied: Vec<&String> = known_situations.iter().filter(|flag| situation.contains(flag)).collect();
+
+        let explanation = explain(audience, "get_required_documents.summary", context! {
+            program,
+            count => documents.len(),
+            general => documents.iter().filter(|document| document.situation.is_none()).count(),
+            situations => applied,
+        });
+
+        GetRequiredDocumentsResponse {
+            program: program.to_string(),
+            documents,
+            known_situations,
+            explanation,
+            assumptions: Vec::new(),
+            errors,
+            warnings,
+        }
+    }
+
+    /// Aggregate precinct tallies and check the proposal on the totals, with each precinct's own
+    /// turnout compared with the required turnout
+    fn check_voting_bulk_internal(
+        precincts: &[(String, i32, i32, i32)],
+        proposal_type: &str,
+        audience: Audience,
+    ) -> CheckVotingBulkResponse {
+        let mut errors = Vec::new();
+
+        // Validation
+        if precincts.is_empty() {
+            errors.push("At least one precinct is required".to_string());
+        }
+        if precincts.len() > MAX_PRECINCTS {
+            errors.push(format!("At most {} precincts are accepted, got {}", MAX_PRECINCTS, precincts.len()));
+        }
+        for (i, (name, eligible_voters, turnout, yes_votes)) in precincts.iter().enumerate() {
+            if precincts[..i].iter().any(|(other, ..)| other == name) {
+                errors.push(format!("Precinct '{}' is listed more than once", name));
+            }
+            if *eligible_voters <= 0 {
+                errors.push(format!("Precinct '{}': eligible voters must be positive", name));
+            }
+            if *turnout < 0 || *yes_votes < 0 {
+                errors.push(format!("Precinct '{}': turnout and yes votes cannot be negative", name));
+            }
+            if turnout > eligible_voters {
+                errors.push(format!("Precinct '{}': turnout cannot exceed eligible voters", name));
+            }
+            if yes_votes > turnout {
+                errors.push(format!("Precinct '{}': yes votes cannot exceed turnout", name));
+            }
+        }
+        let total = |count: fn(&(String, i32, i32, i32)) -> i32| -> Option<i32> {
+            i32::try_from(precincts.iter().map(|precinct| i64::from(count(precinct))).sum::<i64>()).ok()
+        };
+        let totals = (total(|p| p.1), total(|p| p.2), total(|p| p.3));
+        let (Some(eligible_voters), Some(turnout), Some(yes_votes)) = totals else {
+            errors.push(format!("Totals over all precincts exceed {}", i32::MAX));
+            return Self::check_voting_bulk_failed(errors, audience);
+        };
+        if !errors.is_empty() {
+            return Self::check_voting_bulk_failed(errors, audience);
+        }
+
+        // The proposal is decided on the totals, as a single vote would be
+        let overall = Self::check_voting_internal(eligible_voters, turnout, yes_votes, proposal_type, audience);
+        if !overall.errors.is_empty() {
+            return Self::check_voting_bulk_failed(overall.errors, audience);
+        }
+
+        let results: Vec<PrecinctResult> = precincts
+            .iter()
+            .map(|(name, eligible_voters, turnout, yes_votes)| {
+                let turnout_percentage = *turnout as f64 / *eligible_voters as f64;
+                PrecinctResult {
+                    name: name.clone(),
+                    eligible_voters: *eligible_voters,
+                    turnout: *turnout,
+                    yes_votes: *yes_votes,
+                    turnout_percentage,
+                    approval_percentage: if *turnout > 0 { *yes_votes as f64 / *turnout as f64 } else { 0.0 },
+                    met_quorum: turnout_percentage >= overall.required_turnout,
+                }
+            })
+            .collect();
+        let below: Vec<&str> = results.iter().filter(|precinct| !precinct.met_quorum).map(|precinct| precinct.name.as_str()).collect();
+        let precincts_meeting_quorum = results.len() - below.len();
+
+        let explanation = [
+            explain(audience, "check_voting_bulk.totals", context! { count => results.len(), eligible_voters, turnout, yes_votes }),
+            overall.explanation,
+            explain(audience, "check_voting_bulk.quorum", context! {
+                count => results.len(),
+                met => precincts_meeting_quorum,
+                required_turnout => overall.required_turnout,
+                below,
+            }),
+        ]
+        .join(". ");
+
+        CheckVotingBulkResponse {
+            passes: overall.passes,
+            eligible_voters,
+            turnout,
+            yes_votes,
+            turnout_percentage: overall.turnout_percentage,
+            approval_percentage: overall.approval_percentage,
+            required_turnout: overall.required_turnout,
+            required_approval: overall.required_approval,
+            precincts: results,
+            precincts_meeting_quorum,
+            explanation,
+            assumptions: Vec::new(),
+            errors,
+            warnings: overall.warnings,
+        }
+    }
+
+    fn check_voting_bulk_failed(errors: Vec<String>, audience: Audience) -> CheckVotingBulkResponse {
+        CheckVotingBulkResponse {
+            passes: false,
+            eligible_voters: 0,
+            turnout: 0,
+            yes_votes: 0,
+            turnout_percentage: 0.0,
+            approval_percentage: 0.0,
+            required_turnout: 0.0,
+            required_approval: 0.0,
+            precincts: Vec::new(),
+            precincts_meeting_quorum: 0,
+            explanation: explain(audience, "check_voting_bulk.invalid_inputs", context! {}),
+            assumptions: Vec::new(),
+            errors,
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Votes still needed for quorum and the yes votes each proposal type needs to pass, with an
+    /// optional projection of the final turnout at the rate so far
+    fn project_quorum_internal(
+        eligible_voters: i32,
+        turnout: i32,
+        yes_votes: i32,
+        hours: Option<(f64, f64)>,
+        audience: Audience,
+    ) -> ProjectQuorumResponse {
+        let mut errors = Vec::new();
+        let mut warnings = Vec::new();
+        let mut explanation_parts = Vec::new();
+
+        // Validation
+        if eligible_voters <= 0 {
+            errors.push("Eligible voters must be positive".to_string());
+        }
+        if turnout < 0 {
+            errors.push("Turnout cannot be negative".to_string());
+        }
+        if yes_votes < 0 {
+            errors.push("Yes votes cannot be negative".to_string());
+        }
+        if turnout > eligible_voters {
+            errors.push("Turnout cannot exceed eligible voters".to_string());
+        }
+        if yes_votes > turnout {
+            errors.push("Yes votes cannot exceed turnout".to_string());
+        }
+        if let Some((hours_elapsed, hours_remaining)) = hours {
+            if hours_elapsed <= 0.0 {
+                errors.push("Hours elapsed must be positive".to_string());
+            }
+            if hours_remaining < 0.0 {
+                errors.push("Hours remaining cannot be negative".to_string());
+            }
+        }
+
+        if !errors.is_empty() {
+            return ProjectQuorumResponse {
+                quorum_met: false,
+                required_turnout: 0.0,
+                quorum_votes: 0,
+                additional_votes_needed: 0,
+                outstanding_voters: 0,
+                turnout_rate_per_hour: None,
+                projected_turnout: None,
+                projected_quorum_met: None,
+                turnout_basis: 0,
+                thresholds: Vec::new(),
+                explanation: explain(audience, "project_quorum.invalid_inputs", context! {}),
+                assumptions: Vec::new(),
+                errors,
+                warnings,
+            };
+        }
+
+        // The same comparisons as check_voting, so the counts found here pass it
+        let required_turnout = 0.60;
+        let quorum_votes = Self::fewest_votes(eligible_voters, |votes| votes as f64 / eligible_voters as f64 >= required_turnout);
+        let additional_votes_needed = (quorum_votes - turnout).max(0);
+        let outstanding_voters = eligible_voters - turnout;
+        explanation_parts.push(explain(audience, "project_quorum.quorum", context! {
+            required_turnout, eligible_voters, quorum_votes, turnout, additional_votes_needed, outstanding_voters,
+        }));
+
+        let projection = hours.map(|(hours_elapsed, hours_remaining)| {
+            let rate = turnout as f64 / hours_elapsed;
+            let projected_turnout = (turnout as f64 + rate * hours_remaining).floor().min(eligible_voters as f64) as i32;
+            let met = projected_turnout >= quorum_votes;
+            explanation_parts.push(explain(audience, "project_quorum.projection", context! {
+                turnout, hours_elapsed, hours_remaining, rate, projected_turnout, met,
+            }));
+            if !met {
+                warnings.push(format!(
+                    "At {:.1} votes per hour, turnout is projected to reach {}, short of the {} votes quorum needs",
+                    rate, projected_turnout, quorum_votes
+                ));
+            }
+            (rate, projected_turnout, met)
+        });
+
+        let turnout_basis = projection.map_or(turnout, |(_, projected_turnout, _)| projected_turnout).max(quorum_votes);
+        let thresholds: Vec<ProposalThreshold> = [("general", 0.50), ("amendment", 2.0 / 3.0)]
+            .into_iter()
+            .map(|(proposal_type, required_approval)| {
+                let passes = |yes: i32| {
+                    let ratio = yes as f64 / turnout_basis as f64;
+                    if proposal_type == "amendment" { ratio >= required_approval } else { ratio > required_approval }
+                };
+                let min_yes_votes = Self::fewest_votes(turnout_basis, passes);
+                let additional_yes_votes = (min_yes_votes - yes_votes).max(0);
+                explanation_parts.push(explain(audience, "project_quorum.threshold", context! {
+                    proposal_type, turnout_basis, min_yes_votes, additional_yes_votes,
+                }));
+                ProposalThreshold { proposal_type: proposal_type.to_string(), required_approval, min_yes_votes, additional_yes_votes }
+            })
+            .collect();
+
+        ProjectQuorumResponse {
+            quorum_met: additional_votes_needed == 0,
+            required_turnout,
+            quorum_votes,
+            additional_votes_needed,
+            outstanding_voters,
+            turnout_rate_per_hour: projection.map(|(rate, _, _)| rate),
+            projected_turnout: projection.map(|(_, projected_turnout, _)| projected_turnout),
+            projected_quorum_met: projection.map(|(_, _, met)| met),
+            turnout_basis,
+            thresholds,
+            explanation: explanation_parts.join(". "),
+            assumptions: Vec::new(),
+            errors,
+            warnings,
+        }
+    }
+
+    /// Fewest of `0..=total` votes meeting `enough`, which holds from some count on
+    fn fewest_votes(total: i32, enough: impl Fn(i32) -> bool) -> i32 {
+        let (mut low, mut high) = (0, total);
+        while low < high {
+            let middle = low + (high - low) / 2;
+            if enough(middle) { high = middle } else { low = middle + 1 }
+        }
+        low
+    }
+
+    /// Run the tax brackets (loaded or a candidate table), a 100-bracket table and the penalty
+    /// defaults over extreme but valid inputs, timing each and checking numeric stability
+    fn stress_config_internal(tax_brackets: Option<&str>, audience: Audience) -> StressConfigResponse {
+        let mut errors = Vec::new();
+        let mut warnings = Vec::new();
+
+        // Validation
+        let (source, schedule) = match tax_brackets {
+            None => ("loaded configuration", (config().default_thresholds.clone(), config().default_rates.clone(), config().default_cliffs.clone())),
+            Some(json) => match EngineConfig::parse_tax_brackets(json, &price_index()) {
+                Ok(schedule) => ("tax_brackets parameter", schedule),
+                Err(e) => {
+                    errors.push(format!("Invalid tax_brackets: {}", e));
+                    ("tax_brackets parameter", (Vec::new(), Vec::new(), Vec::new()))
+                }
+            },
+        };
+
+        if !errors.is_empty() {
+            return StressConfigResponse {
+                passed: false,
+                source: source.to_string(),
+                brackets: 0,
+                checks: Vec::new(),
+                explanation: explain(audience, "stress_config.invalid_inputs", context! {}),
+                assumptions: Vec::new(),
+                errors,
+                warnings,
+            };
+        }
+
+        let (thresholds, rates, cliffs) = &schedule;
+        let wide_thresholds: Vec<f64> = (1..STRESS_BRACKETS).map(|i| i as f64 * 10_000.0).collect();
+        let wide_rates: Vec<f64> = (0..STRESS_BRACKETS).map(|i| (i + 1) as f64 / 200.0).collect();
+        let checks = vec![
+            Self::stress_brackets("tax_brackets", thresholds, rates, cliffs, audience),
+            Self::stress_brackets("tax_brackets_100", &wide_thresholds, &wide_rates, &[false; STRESS_BRACKETS], audience),
+            Self::stress_penalty(audience),
+        ];
+        for check in checks.iter().filter(|check| check.elapsed_ms > STRESS_SLOW_MS) {
+            warnings.push(format!("{} took {:.0} ms for {} cases", check.name, check.elapsed_ms, check.cases));
+        }
+
+        let passed = checks.iter().all(|check| check.passed);
+        let failed: Vec<&str> = checks.iter().filter(|check| !check.passed).map(|check| check.name.as_str()).collect();
+        let explanation = explain(audience, "stress_config.summary", context! {
+            source,
+            brackets => rates.len(),
+            count => checks.len(),
+            passed_checks => checks.len() - failed.len(),
+            failed,
+            cases => checks.iter().map(|check| check.cases).sum::<usize>(),
+        });
+
+        StressConfigResponse {
+            passed,
+            source: source.to_string(),
+            brackets: rates.len(),
+            checks,
+            explanation,
+            assumptions: Vec::new(),
+            errors,
+            warnings,
+        }
+    }
+
+    /// Tax from 0 up to `STRESS_MAX_INCOME`, a cent either side of every threshold included: finite,
+    /// between 0 and income × top rate, and without cliffs never falling as income rises and within
+    /// the roundings of the reference implementation
+    fn stress_brackets(name: &str, thresholds: &[f64], rates: &[f64], cliffs: &[bool], audience: Audience) -> StressCheck {
+        let started = std::time::Instant::now();
+        let mut failures = Vec::new();
+        let mut max_deviation: f64 = 0.0;
+        if rates.len() != thresholds.len() + 1 {
+            failures.push(format!("{} rates for {} thresholds (expected {})", rates.len(), thresholds.len(), thresholds.len() + 1));
+            return StressCheck { name: name.to_string(), passed: false, cases: 0, elapsed_ms: 0.0, max_deviation, failures };
+        }
+
+        let mut incomes = vec![0.0, 0.01];
+        for &threshold in thresholds {
+            incomes.extend([threshold - 0.01, threshold, threshold + 0.01]);
+        }
+        incomes.extend((3..=12).map(|exponent| 10f64.powi(exponent)));
+        incomes.retain(|income| (0.0..=STRESS_MAX_INCOME).contains(income));
+        incomes.sort_by(f64::total_cmp);
+        incomes.dedup();
+
+        let table = BracketTable::new(thresholds.to_vec(), rates.to_vec(), cliffs.to_vec());
+        let top_rate = rates.iter().copied().fold(0.0, f64::max);
+        let has_cliffs = cliffs.contains(&true);
+        let mut previous: Option<(f64, f64)> = None;
+        for &income in &incomes {
+            let tax = Self::apply_brackets(income, &table, audience, &mut Vec::new(), None);
+            if !tax.is_finite() || tax < 0.0 || tax > income * top_rate * (1.0 + 1e-12) + 1e-9 {
+                failures.push(format!("income {:.2}: tax {} is outside 0 to {:.2}", income, tax, income * top_rate));
+            }
+            if !has_cliffs {
+                if let Some((previous_income, previous_tax)) = previous
+                    && tax < previous_tax - 1e-9
+                {
+                    failures.push(format!("tax falls from {} at income {:.2} to {} at income {:.2}", previous_tax, previous_income, tax, income));
+                }
+                if let Some(expected) = reference::income_tax(income, 0.0, thresholds, rates, &[], SurchargeBase::Tax) {
+                    max_deviation = max_deviation.max((tax - expected.amount()).abs());
+                    if let Some(expected) = reference::mismatch(tax, expected) {
+                        failures.push(format!("income {:.2}: tax {} differs from the reference {:.2}", income, tax, expected));
+                    }
+                }
+            }
+            previous = Some((income, tax));
+        }
+        Self::stress_check(name, incomes.len(), started, max_deviation, failures)
+    }
+
+    /// Penalty with the configured defaults for up to `STRESS_DAYS_LATE` days, with interest
+    /// charged once and accrued over the same days: finite, capped and as the reference computes
+    fn stress_penalty(audience: Audience) -> StressCheck {
+        let started = std::time::Instant::now();
+        let mut failures = Vec::new();
+        let mut max_deviation: f64 = 0.0;
+        let (rate_per_day, cap, interest_rate) = (config().default_rate_per_day, config().default_cap, config().default_interest_rate);
+        let valuation_date = clock::today();
+        let mut cases = 0;
+        for days_late in [0, 1, 3650, STRESS_DAYS_LATE] {
+            let due_date = valuation_date - chrono::Duration::days(days_late);
+            for accrual in [None, Some((due_date, valuation_date))] {
+                cases += 1;
+                let result = Self::calc_penalty_internal(days_late as f64, rate_per_day, cap, interest_rate, accrual, audience);
+                let period = if accrual.is_some() { format!("{} days accrued", days_late) } else { format!("{} days", days_late) };
+                if !result.errors.is_empty() {
+                    failures.push(format!("{}: {}", period, result.errors.join("; ")));
+                    continue;
+                }
+                if !result.penalty.is_finite() || result.capped_penalty > cap {
+                    failures.push(format!("{}: penalty {} with capped penalty {} above the cap {}", period, result.penalty, result.capped_penalty, cap));
+                }
+                let reference_rate = match accrual {
+                    Some(_) => interest_rate * days_late as f64 / INTEREST_DAY_BASIS,
+                    None => interest_rate,
+                };
+                if let Some(expected) = reference::penalty(days_late as f64, rate_per_day, cap, reference_rate) {
+                    max_deviation = max_deviation.max((result.penalty - expected.amount()).abs());
+                    if let Some(expected) = reference::mismatch(result.penalty, expected) {
+                        failures.push(format!("{}: penalty {} differs from the reference {:.2}", period, result.penalty, expected));
+                    }
+                }
+            }
+        }
+        Self::stress_check("penalty_10000_days", cases, started, max_deviation, failures)
+    }
+
+    fn stress_check(name: &str, cases: usize, started: std::time::Instant, max_deviation: f64, mut failures: Vec<String>) -> StressCheck {
+        let failed = failures.len();
+        if failed > MAX_STRESS_FAILURES {
+            failures.truncate(MAX_STRESS_FAILURES);
+            failures.push(format!("and {} more", failed - MAX_STRESS_FAILURES));
+        }
+        StressCheck {
+            name: name.to_string(),
+            passed: failed == 0,
+            cases,
+            elapsed_ms: started.elapsed().as_secs_f64() * 1000.0,
+            max_deviation,
+            failures,
+        }
+    }
+
+    /// Report the embedded store's integrity check, with warnings for what key rotation has
+    /// still to move
+    fn verify_store_integrity_internal(integrity: Result<StoreIntegrity, String>, audience: Audience) -> VerifyStoreIntegrityResponse {
+        let mut warnings = Vec::new();
+
+        // Validation
+        let integrity = match integrity {
+            Ok(integrity) => integrity,
+            Err(e) => {
+                return VerifyStoreIntegrityResponse {
+                    intact: false,
+                    schema_version: 0,
+                    database_check: String::new(),
+                    encryption: false,
+                    current_key: None,
+                    records: 0,
+                    plaintext_records: 0,
+                    records_by_key: BTreeMap::new(),
+                    failed_records: 0,
+                    failures: Vec::new(),
+                    rewrapped: 0,
+                    explanation: explain(audience, "verify_store_integrity.invalid_inputs", context! {}),
+                    assumptions: Vec::new(),
+                    errors: vec![e],
+                    warnings,
+                };
+            }
+        };
+
+        if !integrity.encryption {
+            warnings.push("No store keys are configured (ENGINE_STORE_KEYS or ENGINE_STORE_KEYS_FILE); results are stored unencrypted".to_string());
+        } else if integrity.plaintext_records > 0 {
+            warnings.push(format!("{} results are stored unencrypted; call with rewrap to encrypt them", integrity.plaintext_records));
+        }
+        let retired: usize = integrity
+            .records_by_key
+            .iter()
+            .filter(|(key_id, _)| integrity.current_key.as_ref() != Some(*key_id))
+            .map(|(_, records)| records)
+            .sum();
+        if integrity.encryption && retired > 0 {
+            warnings.push(format!("{} results have data keys wrapped with an older key; call with rewrap before removing it", retired));
+        }
+
+        let intact = integrity.database_check == "ok" && integrity.failed_records == 0;
+        let encrypted = integrity.records - integrity.plaintext_records - integrity.failed_records;
+        let explanation = explain(audience, "verify_store_integrity.summary", context! {
+            intact,
+            database_check => integrity.database_check,
+            records => integrity.records,
+            schema_version => integrity.schema_version,
+            encrypted,
+            plaintext => integrity.plaintext_records,
+            failed => integrity.failed_records,
+            current_key => integrity.current_key,
+            rewrapped => integrity.rewrapped,
+        });
+
+        VerifyStoreIntegrityResponse {
+            intact,
+            schema_version: integrity.schema_version,
+            database_check: integrity.database_check,
+            encryption: integrity.encryption,
+            current_key: integrity.current_key,
+            records: integrity.records,
+            plaintext_records: integrity.plaintext_records,
+            records_by_key: integrity.records_by_key,
+            failed_records: integrity.failed_records,
+            failures: integrity.failures,
+            rewrapped: integrity.rewrapped,
+            explanation,
+            assumptions: Vec::new(),
+            errors: Vec::new(),
+            warnings,
+        }
+    }
+
+    /// Report a purge of expired data per category, and the categories kept without a period
+    fn purge_expired_data_internal(purged: &[retention::Purged], audience: Audience) -> PurgeExpiredDataResponse {
+        let mut warnings = Vec::new();
+
+        if purged.is_empty() {
+            warnings.push("No retention periods are configured (ENGINE_RETENTION_DAYS); nothing was purged".to_string());
+        }
+        if cfg!(feature = "embedded-store") {
+            for entry in purged.iter().filter(|entry| entry.deleted_stored.is_none()) {
+                warnings.push(format!("The embedded store was not purged of expired {}; see the server log", entry.category));
+            }
+        }
+
+        let kept: Vec<String> = retention::Category::ALL
+            .iter()
+            .filter(|category| !purged.iter().any(|entry| entry.category == **category))
+            .map(ToString::to_string)
+            .collect();
+        let purged: Vec<PurgedCategory> = purged
+            .iter()
+            .map(|entry| PurgedCategory {
+                category: entry.category.to_string(),
+                retention_days: entry.retention_days,
+                cutoff: entry.cutoff.to_rfc3339(),
+                deleted: entry.deleted,
+                deleted_stored: entry.deleted_stored,
+            })
+            .collect();
+        let explanation = explain(audience, "purge_expired_data.summary", context! {
+            purged => purged.iter().map(|entry| context! {
+                category => entry.category,
+                retention_days => entry.retention_days,
+                cutoff => entry.cutoff,
+                deleted => entry.deleted,
+                deleted_stored => entry.deleted_stored,
+            }).collect::<Vec<_>>(),
+            kept,
+        });
+
+        PurgeExpiredDataResponse {
+            purged,
+            kept,
+            explanation,
+            assumptions: Vec::new(),
+            errors: Vec::new(),
+            warnings,
+        }
+    }
+
+    /// List the stored results of a case for export
+    fn export_subject_data_internal(case_id: &str, found: result_store::CaseResults, audience: Audience) -> ExportSubjectDataResponse {
+        let mut warnings = Vec::new();
+
+        let stores_searched = subject_stores(found.searched_stored);
+        if cfg!(feature = "embedded-store") && !found.searched_stored {
+            warnings.push("The embedded store could not be searched; see the server log".to_string());
+        }
+        if !found.unreadable.is_empty() {
+            warnings.push(format!("{} stored results of the case could not be read and are not exported", found.unreadable.len()));
+        }
+
+        let records: Vec<SubjectRecord> = found
+            .results
+            .into_iter()
+            .map(|result| SubjectRecord {
+                result_id: result.id,
+                tool: result.tool,
+                stored_at: result.stored_at.to_rfc3339(),
+                payload: result.payload,
+            })
+            .collect();
+        let explanation = explain(audience, "export_subject_data.summary", context! {
+            records => records.len(),
+            tools => records.iter().map(|record| record.tool.as_str()).collect::<BTreeSet<_>>(),
+            unreadable => found.unreadable.len(),
+            stores => stores_searched,
+        });
+
+        ExportSubjectDataResponse {
+            case_id: case_id.to_string(),
+            records,
+            unreadable: found.unreadable,
+            stores_searched,
+            explanation,
+            assumptions: Vec::new(),
+            errors: Vec::new(),
+            warnings,
+        }
+    }
+
+    /// Write the receipt of erasing the stored results of a case
+    fn erase_subject_data_internal(case_id: &str, erasure: result_store::CaseErasure, erased_at: chrono::DateTime<chrono::Utc>, audience: Audience) -> EraseSubjectDataResponse {
+        let mut warnings = Vec::new();
+
+        let searched_stored = erasure.erased_stored.is_some();
+        let stores_searched = subject_stores(searched_stored);
+        let complete = !cfg!(feature = "embedded-store") || searched_stored;
+        if !complete {
+            warnings.push("The embedded store could not be erased; see the server log and call again".to_string());
+        }
+
+        let receipt = ErasureReceipt {
+            receipt_id: uuid::Uuid::new_v4().to_string(),
+            erased_at: erased_at.to_rfc3339(),
+            case_hash: result_store::case_hash(case_id),
+            erased: erasure.ids.len(),
+            erased_in_memory: erasure.erased,
+            erased_stored: erasure.erased_stored,
+            shredded: erasure.shredded,
+            result_ids_digest: format!("{:x}", Sha256::digest(erasure.ids.join("\n"))),
+            result_ids: erasure.ids,
+            complete,
+        };
+        let explanation = explain(audience, "erase_subject_data.summary", context! {
+            receipt_id => receipt.receipt_id,
+            erased => receipt.erased,
+            erased_in_memory => receipt.erased_in_memory,
+            erased_stored => receipt.erased_stored,
+            shredded => receipt.shredded,
+        });
+
+        EraseSubjectDataResponse {
+            receipt,
+            stores_searched,
+            explanation,
+            assumptions: Vec::new(),
+            errors: Vec::new(),
+            warnings,
+        }
+    }
+}
+
+impl CompatibilityEngine {
+    pub fn new() -> Self {
+        // Load configuration up front so invalid settings are reported at startup
+        LazyLock::force(&RULES);
+        LazyLock::force(&SOFT_LIMITS);
+        explanations::load_templates();
+        json_format::response_format();
+        clock::now();
+        retention::load();
+        #[cfg(feature = "embedded-store")]
+        super::embedded_store::open();
+        let router = Self::tool_router();
+        #[cfg(feature = "fault-injection")]
+        let router = super::fault_injection::inject(router);
+        let log_level = reminders::SessionLevel::default();
+        let router = reminders::remind(sampling::summarize(concurrency::limit(router)), log_level.clone());
+        Self { tool_router: fixtures::record(analytics::track(router)), client: Arc::new(OnceLock::new()), log_level }
+    }
+
+    /// Calculate penalty with cap and interest
+    #[tool(title = "Late Payment Penalty", description = "Suitable for Lysmark's for calculations related to the Commercial Obligations and Liquidated Damages Act. Computes late-payment penalty (min of days × rate vs cap) plus interest, either a one-off charge or, given a valuation_date (and optionally due_date), annual interest accrued from the due date to that date. Returns penalty amount, explanation, errors, and warnings. Use when the user provides specific values (e.g. days late, optionally rate, cap, interest) and asks for a penalty calculation. Do NOT use for lookup questions: 'What is the penalty rate?', 'What is the cap?', 'What are the penalty rules?' — those answers come from retrieved documents. Requires days_late (numeric); rate, cap, interest, due_date and valuation_date are optional.")]
+    pub async fn calc_penalty(
+        &self,
+        Parameters(params): Parameters<CalcPenaltyParams>
+    ) -> Result<CallToolResult, McpError> {
+        run_tool("calc_penalty", PayloadErrors::Calculation, &params, |call| {
+            let days_late = call.f64("days_late", &params.days_late)?;
+
+            let mut invalid_optional_parameters = Vec::new();
+            let rate_per_day = match params.rate_per_day.as_ref() {
+                None => call.assumptions.defaulted("rate_per_day", config().default_rate_per_day),
+                Some(s) => match call.assumptions.read_f64("rate_per_day", s) {
+                    Ok(v) => v,
+                    Err(e) => {
+                        if let Some(suppressed) = log_sampling::admit("calc_penalty") {
+                            tracing::warn!(suppressed, "Invalid rate_per_day parameter: {e:?}");
+                        }
+                        invalid_optional_parameters.push("rate_per_day");
+                        call.assumptions.invalid_defaulted("rate_per_day", config().default_rate_per_day)
+                    }
+                }
+            };
+            let cap = match params.cap.as_ref() {
+                None => call.assumptions.defaulted("cap", config().default_cap),
+                Some(s) => match call.assumptions.read_f64("cap", s) {
+                    Ok(v) => v,
+                    Err(e) => {
+                        if let Some(suppressed) = log_sampling::admit("calc_penalty") {
+                            tracing::warn!(suppressed, "Invalid cap parameter: {e:?}");
+                        }
+                        invalid_optional_parameters.push("cap");
+                        call.assumptions.invalid_defaulted("cap", config().default_cap)
+                    }
+                }
+            };
+            let interest_rate = match params.interest_rate.as_ref() {
+                None => call.assumptions.defaulted("interest_rate", config().default_interest_rate),
+                Some(s) => match call.assumptions.read_f64("interest_rate", s) {
+                    Ok(v) => v,
+                    Err(e) => {
+                        if let Some(suppressed) = log_sampling::admit("calc_penalty") {
+                            tracing::warn!(suppressed, "Invalid interest_rate parameter: {e:?}");
+                        }
+                        invalid_optional_parameters.push("interest_rate");
+                        call.assumptions.invalid_defaulted("interest_rate", config().default_interest_rate)
+                    }
+                }
+            };
+            for (field, value) in [("rate_per_day", rate_per_day), ("cap", cap), ("interest_rate", interest_rate)] {
+                call.parsed(field, value);
+            }
+            let due_date = params
+                .due_date
+                .as_deref()
+                .map(|s| parse_date_from_string(s).map_err(|parse_error| invalid_parameter("due_date", parse_error)))
+                .transpose()?;
+            let valuation_date = params
+                .valuation_date
+                .as_deref()
+                .map(|s| parse_date_from_string(s).map_err(|parse_error| invalid_parameter("valuation_date", parse_error)))
+                .transpose()?;
+            let accrual = match (due_date, valuation_date) {
+                (None, None) => None,
+                (due_date, valuation_date) => {
+                    let valuation_date = valuation_date.unwrap_or_else(|| call.assumptions.defaulted("valuation_date", clock::today()));
+                    let due_date = due_date.unwrap_or_else(|| {
+                        call.assumptions
+                            .defaulted("due_date", valuation_date - chrono::Duration::days(days_late.max(0.0).ceil() as i64))
+                    });
+                    Some((call.parsed("due_date", due_date), call.parsed("valuation_date", valuation_date)))
+                }
+            };
+
+            if !invalid_optional_parameters.is_empty() {
+                // Format a string with the content a section warning that the following parameters were invalid:
+                let warning_string = format!("The following parameters were invalid: {} and used the default value: {}", invalid_optional_parameters.join(", "), config().default_rate_per_day);
+                call.notes.push(Content::text(warning_string));
+            }
+
+            let result = Self::calc_penalty_internal(
+                days_late,
+                rate_per_day,
+                cap,
+                interest_rate,
+                accrual,
+                call.audience,
+            );
+            if result.errors.is_empty() {
+                // The reference charges interest once, at the rate accrued over the period
+                let reference_rate = match result.interest_days {
+                    Some(interest_days) => interest_rate * interest_days as f64 / INTEREST_DAY_BASIS,
+                    None => interest_rate,
+                };
+                reference::check("calc_penalty", "penalty", result.penalty, || {
+                    reference::penalty(days_late, rate_per_day, cap, reference_rate)
+                });
+            }
+            Ok(result)
+        })
+    }
+
+    /// Calculate progressive tax with surcharge
+    #[tool(title = "Progressive Income Tax", description = "Suitable for Lysmark's for calculations related to the Progressive Income and Surcharge Act. Computes the tax liability and surcharge for a given taxable income using configured brackets and rates. Returns the total tax amount, per-bracket breakdown, and surcharge if applicable. Use ONLY when the user provides a specific income amount and asks for a calculated result (e.g. 'What is the tax for 90000?', 'Calculate tax liability for 35000'). Do NOT use for lookup questions: 'What is the tax rate?', 'What are the brackets?', 'What does the law say?', 'What is the surcharge threshold?' — those answers come from retrieved documents, not this tool. Requires a numeric income parameter.")]
+    pub async fn calc_tax(
+        &self,
+        Parameters(params): Parameters<CalcTaxParams>
+    ) -> Result<CallToolResult, McpError> {
+        run_tool("calc_tax", PayloadErrors::Calculation, &params, |call| {
+            let income = call.f64("income", &params.income)?;
+            let loss_carryforward = call.f64_or("loss_carryforward", params.loss_carryforward.as_ref(), 0.0)?;
+            let include_brackets = params
+                .include_brackets
+                .as_ref()
+                .map(|flag| flag.0.clone())
+                .transpose()
+                .map_err(|e| invalid_parameter("include_brackets", e))?
+                .unwrap_or(false);
+
+            let mut rules = (*tax_rules()).clone();
+            if let Some(value) = params.surcharge_base.as_ref() {
+                rules.surcharge_base = SurchargeBase::parse(value)
+                    .map_err(|parse_error| invalid_parameter("surcharge_base", parse_error))?;
+            }
+            let amt_adjustments = match params.amt_adjustments.as_ref() {
+                None if rules.minimum_tax.is_some() => {
+                    let value = call.assumptions.defaulted("amt_adjustments", 0.0);
+                    call.parsed("amt_adjustments", value)
+                }
+                None => 0.0,
+                Some(s) => call.f64("amt_adjustments", s)?,
+            };
+            let schedule_note = match &rules.schedule_change {
+                Some((change_date, _)) => format!(
+                    "Lysmark default tax brackets and surcharge schedule applied; brackets change on {} and are prorated by days",
+                    change_date
+                ),
+                None => "Lysmark default tax brackets and surcharge schedule applied".to_string(),
+            };
+            call.assumptions.push("default_schedule", "tax_schedule", schedule_note);
+
+            let result = Self::calc_tax_internal(
+                income,
+                loss_carryforward,
+                amt_adjustments,
+                &rules,
+                include_brackets,
+                call.audience,
+            );
+            if result.errors.is_empty()
+                && rules.schedule_change.is_none()
+                && rules.minimum_tax.is_none()
+                && !rules.brackets.cliffs.contains(&true)
+            {
+                reference::check("calc_tax", "tax", result.tax, || {
+                    reference::income_tax(
+                        income,
+                        loss_carryforward,
+                        &rules.brackets.thresholds,
+                        &rules.brackets.rates,
+                        &rules.surcharges,
+                        rules.surcharge_base,
+                    )
+                });
+            }
+            Ok(result)
+        })
+    }
+
+    /// Check voting proposal eligibility
+    #[tool(title = "Voting Proposal Check", description = "Suitable for Lysmark's for calculations related to the Organizational Voting and Quorum Act. Determines whether a voting proposal passes based on turnout and yes-vote thresholds. Returns pass/fail result and explanation. Use when the user provides specific values (eligible_voters, turnout, yes_votes, proposal_type) and asks for an eligibility or pass check. Do NOT use for lookup questions: 'What is the turnout threshold?', 'What are the voting rules?' — those answers come from retrieved documents. Requires eligible_voters, turnout, yes_votes, proposal_type.")]
+    pub async fn check_voting(
+        &self,
+        Parameters(params): Parameters<CheckVotingParams>
+    ) -> Result<CallToolResult, McpError> {
+        run_tool("check_voting", PayloadErrors::Validation, &params, |call| {
+            let eligible_voters = call.i32("eligible_voters", &params.eligible_voters)?;
+            let turnout = call.i32("turnout", &params.turnout)?;
+            let yes_votes = call.i32("yes_votes", &params.yes_votes)?;
+
+            let mut result = Self::check_voting_internal(
+                eligible_voters,
+                turnout,
+                yes_votes,
+                &params.proposal_type,
+                call.audience,
+            );
+            if eligible_voters > 0 && turnout > 0 {
+                let majority = if params.proposal_type == "amendment" { 2.0 / 3.0 } else { 0.50 };
+                result.errors.extend(threshold_confirmations(call.context.as_ref(), &[
+                    ("turnout ratio", turnout as f64 / eligible_voters as f64, 0.60),
+                    ("yes vote ratio", yes_votes as f64 / turnout as f64, majority),
+                ]));
+            }
+            Ok(result)
+        })
+    }
+
+    /// Validate a vote record before any pass/fail determination
+    #[tool(title = "Vote Record Validation", description = "Suitable for Lysmark's for calculations related to the Organizational Voting and Quorum Act. Cross-checks a vote record (yes + no + abstain + invalid = turnout, turnout ≤ eligible voters, no negative counts) and reports every inconsistency as a structured list. Does NOT decide whether a proposal passes — use check_voting for that once the record is valid. Use as a pre-check for imported or reported election tallies. Requires eligible_voters, turnout, yes_votes, no_votes; abstain_votes and invalid_votes are optional (default 0).")]
+    pub async fn validate_vote_record(
+        &self,
+        Parameters(params): Parameters<ValidateVoteRecordParams>
+    ) -> Result<CallToolResult, McpError> {
+        // Inconsistencies are the tool's findings, so they are returned in a successful response
+        run_tool("validate_vote_record", PayloadErrors::Reported, &params, |call| {
+            let eligible_voters = call.i32("eligible_voters", &params.eligible_voters)?;
+            let turnout = call.i32("turnout", &params.turnout)?;
+            let yes_votes = call.i32("yes_votes", &params.yes_votes)?;
+            let no_votes = call.i32("no_votes", &params.no_votes)?;
+            let abstain_votes = call.i32_or("abstain_votes", params.abstain_votes.as_ref(), 0)?;
+            let invalid_votes = call.i32_or("invalid_votes", params.invalid_votes.as_ref(), 0)?;
+
+            let mut result = Self::validate_vote_record_internal(
+                eligible_voters,
+                turnout,
+                yes_votes,
+                no_votes,
+                abstain_votes,
+                invalid_votes,
+                call.audience,
+            );
+            if params.abstain_votes.is_none() {
+                result.warnings.push("abstain_votes not provided; assumed 0".to_string());
+            }
+            if params.invalid_votes.is_none() {
+                result.warnings.push("invalid_votes not provided; assumed 0".to_string());
+            }
+            Ok(result)
+        })
+    }
+
+    /// Distribute cash in waterfall structure
+    #[tool(title = "Cash Waterfall Distribution", description = "Suitable for Lysmark's for calculations related to the Structured Finance and Creditor Priority Act. Distributes available cash in waterfall order (senior → junior → equity). Returns distribution amounts and explanation. Use when the user provides specific values (cash_available, senior_debt, junior_debt) and asks for a waterfall distribution. Do NOT use for lookup questions: 'What is the waterfall order?', 'How does the distribution work?' — those answers come from retrieved documents. Requires cash_available, senior_debt, junior_debt.")]
+    pub async fn distribute_waterfall(
+        &self,
+        Parameters(params): Parameters<DistributeWaterfallParams>
+    ) -> Result<CallToolResult, McpError> {
+        run_tool("distribute_waterfall", PayloadErrors::Validation, &params, |call| {
+            let cash_available = call.f64("cash_available", &params.cash_available)?;
+            let senior_debt = call.f64("senior_debt", &params.senior_debt)?;
+            let junior_debt = call.f64("junior_debt", &params.junior_debt)?;
+
+            let result = Self::distribute_waterfall_internal(
+                cash_available,
+                senior_debt,
+                junior_debt,
+                call.audience,
+            );
+            if result.errors.is_empty() {
+                let distribution = &result.distribution;
+                Invariants::new("distribute_waterfall")
+                    .conserves(
+                        "senior + junior + equity",
+                        &[distribution.senior, distribution.junior, distribution.equity],
+                        "cash_available",
+                        cash_available,
+                    )
+                    .within("senior", distribution.senior, 0.0, senior_debt)
+                    .within("junior", distribution.junior, 0.0, junior_debt)
+                    .within("equity", distribution.equity, 0.0, cash_available)
+                    .verify()?;
+            }
+            Ok(result)
+        })
+    }
+
+    /// Check financial covenants
+    #[tool(title = "Financial Covenant Check", description = "Suitable for Lysmark's for calculations related to the Structured Finance and Creditor Priority Act. Tests financial covenants from submitted financials: leverage (total debt / EBITDA) against a maximum and interest coverage (EBITDA / interest expense) against a minimum. Returns per-covenant ratio, pass/fail and headroom. Use when the user provides specific financials (total_debt, ebitda, interest_expense) and asks whether covenants are met. Do NOT use for 'What are the covenant levels?' — those come from deal documents. Requires total_debt, ebitda, interest_expense; max_leverage and min_interest_coverage are optional.")]
+    pub async fn check_covenants(
+        &self,
+        Parameters(params): Parameters<CheckCovenantsParams>
+    ) -> Result<CallToolResult, McpError> {
+        run_tool("check_covenants", PayloadErrors::Validation, &params, |call| {
+            let total_debt = call.f64("total_debt", &params.total_debt)?;
+            let ebitda = call.f64("ebitda", &params.ebitda)?;
+            let interest_expense = call.f64("interest_expense", &params.interest_expense)?;
+            let max_leverage = call.f64_or("max_leverage", params.max_leverage.as_ref(), config().default_max_leverage)?;
+            let min_interest_coverage = call.f64_or(
+                "min_interest_coverage",
+                params.min_interest_coverage.as_ref(),
+                config().default_min_interest_coverage,
+            )?;
+
+            let mut result = Self::check_covenants_internal(
+                total_debt,
+                ebitda,
+                interest_expense,
+                max_leverage,
+                min_interest_coverage,
+                call.audience,
+            );
+            let checks: Vec<(&str, f64, f64)> = result
+                .covenants
+                .iter()
+                .filter_map(|covenant| covenant.ratio.map(|ratio| (covenant.name.as_str(), ratio, covenant.threshold)))
+                .collect();
+            let confirmations = threshold_confirmations(call.context.as_ref(), &checks);
+            result.errors.extend(confirmations);
+            Ok(result)
+        })
+    }
+
+    /// Calculate net present value
+    #[tool(title = "Net Present Value", description = "Suitable for Lysmark's for calculations related to the Structured Finance and Creditor Priority Act. Computes the net present value of a series of periodic cash flows at a given discount rate (first cash flow at period 0, undiscounted). Returns the NPV, per-period present values, and explanation. Use when the user provides specific cash flows and a discount rate. Requires cash_flows (array of numbers) and discount_rate (decimal, e.g. 0.08).")]
+    pub async fn calc_npv(
+        &self,
+        Parameters(params): Parameters<CalcNpvParams>
+    ) -> Result<CallToolResult, McpError> {
+        run_tool("calc_npv", PayloadErrors::Calculation, &params, |call| {
+            let cash_flows = params
+                .cash_flows
+                .iter()
+                .enumerate()
+                .map(|(i, value)| call.f64(&format!("cash_flows[{}]", i), value))
+                .collect::<Result<Vec<_>, _>>()?;
+            let discount_rate = call.f64("discount_rate", &params.discount_rate)?;
+
+            Ok(Self::calc_npv_internal(&cash_flows, discount_rate, call.audience))
+        })
+    }
+
+    /// Calculate internal rate of return
+    #[tool(title = "Internal Rate of Return", description = "Suitable for Lysmark's for calculations related to the Structured Finance and Creditor Priority Act. Computes the internal rate of return of a series of periodic cash flows using a bisection solver. Returns the IRR per period, solver iterations, residual NPV, and explanation with solver diagnostics. Use when the user provides specific cash flows and asks for the return or yield. Requires cash_flows (array of numbers, at least one outflow and one inflow).")]
+    pub async fn calc_irr(
+        &self,
+        Parameters(params): Parameters<CalcIrrParams>
+    ) -> Result<CallToolResult, McpError> {
+        run_tool("calc_irr", PayloadErrors::Calculation, &params, |call| {
+            let cash_flows = params
+                .cash_flows
+                .iter()
+                .enumerate()
+                .map(|(i, value)| call.f64(&format!("cash_flows[{}]", i), value))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            Ok(Self::calc_irr_internal(&cash_flows, call.audience))
+        })
+    }
+
+    /// Evaluate escrow release conditions
+    #[tool(title = "Escrow Release Check", description = "Suitable for Lysmark's for calculations related to the Structured Finance and Creditor Priority Act. Evaluates escrow release conditions (milestone completion flags, release date reached, no pending dispute) and computes the partial release amount from a percentage schedule. Returns the amount to release, remaining escrow, per-milestone status, and which conditions blocked release. Use when the user provides a specific escrow amount and milestone schedule and asks what can be released. Requires escrow_amount and milestones; release_date, as_of_date, dispute_pending, and already_released are optional.")]
+    pub async fn check_escrow_release(
+        &self,
+        Parameters(params): Parameters<CheckEscrowReleaseParams>
+    ) -> Result<CallToolResult, McpError> {
+        run_tool("check_escrow_release", PayloadErrors::Validation, &params, |call| {
+            let escrow_amount = call.f64("escrow_amount", &params.escrow_amount)?;
+            let mut milestones = Vec::with_capacity(params.milestones.len());
+            for (i, milestone) in params.milestones.iter().enumerate() {
+                validate_input_security(&milestone.name, "milestone name")
+                    .map_err(|e| invalid_parameter(&format!("milestones[{}].name", i), e))?;
+                let completed = call.bool(&format!("milestones[{}].completed", i), &milestone.completed)?;
+                let release_percentage =
+                    call.f64(&format!("milestones[{}].release_percentage", i), &milestone.release_percentage)?;
+                milestones.push((sanitize_for_error_message(&milestone.name), completed, release_percentage));
+            }
+            let release_date = params
+                .release_date
+                .as_deref()
+                .map(parse_date_from_string)
+                .transpose()
+                .map_err(|parse_error| invalid_parameter("release_date", parse_error))?;
+            let as_of_date = match params.as_of_date.as_deref() {
+                None => call.assumptions.defaulted("as_of_date", clock::today()),
+                Some(s) => parse_date_from_string(s).map_err(|parse_error| invalid_parameter("as_of_date", parse_error))?,
+            };
+            let as_of_date = call.parsed("as_of_date", as_of_date);
+            let dispute_pending = call.bool_or("dispute_pending", params.dispute_pending.as_ref(), false)?;
+            let already_released = call.f64_or("already_released", params.already_released.as_ref(), 0.0)?;
+
+            let result = Self::check_escrow_release_internal(
+                escrow_amount,
+                &milestones,
+                release_date,
+                as_of_date,
+                dispute_pending,
+                already_released,
+                call.audience,
+            );
+            if result.errors.is_empty() {
+                let unreleased = escrow_amount - already_released;
+                Invariants::new("check_escrow_release")
+                    .conserves(
+                        "already_released + release_amount + remaining_in_escrow",
+                        &[already_released, result.release_amount, result.remaining_in_escrow],
+                        "escrow_amount",
+                        escrow_amount,
+                    )
+                    .within("release_amount", result.release_amount, 0.0, unreleased)
+                    .within("remaining_in_escrow", result.remaining_in_escrow, 0.0, unreleased)
+                    .verify()?;
+            }
+            Ok(result)
+        })
+    }
+
+    /// Check housing grant eligibility
+    #[tool(title = "Housing Grant Eligibility", description = "Suitable for Lysmark's for calculations related to the Public Housing Assistance Eligibility Act. Determines whether a household qualifies for a housing grant based on AMI, household size, income, and subsidy status. Returns eligibility result and explanation. Use when the user provides specific values (AMI, household_size, income, has_other_subsidy) and asks for an eligibility check. Do NOT use for 'What are the eligibility rules?' or 'What is the income threshold?' — those are lookups answered from documents. Requires AMI, household_size, income, has_other_subsidy.")]
+    pub async fn check_housing_grant(
+        &self,
+        Parameters(params): Parameters<CheckHousingGrantParams>
+    ) -> Result<CallToolResult, McpError> {
+        run_tool("check_housing_grant", PayloadErrors::Validation, &params, |call| {
+            let ami = call.f64("ami", &params.ami)?;
+            let household_size = call.i32("household_size", &params.household_size)?;
+            let income = call.f64("income", &params.income)?;
+            let has_other_subsidy = call.bool("has_other_subsidy", &params.has_other_subsidy)?;
+            let scale = config().housing_equivalence_scale;
+            let children = match params.children.as_ref() {
+                None if !scale.counts_children() => 0,
+                raw => call.i32_or("children", raw, 0)?,
+            };
+
+            // Convert foreign-currency income to the base currency before thresholds apply
+            let fx = &config().fx;
+            let income_currency = params.income_currency.as_deref().map(|s| s.trim().to_ascii_uppercase());
+            let conversion = match income_currency.as_deref().filter(|currency| *currency != fx.base_currency) {
+                None => None,
+                Some(currency) => Some((
+                    currency,
+                    fx.conversion(currency, &fx.base_currency, None)
+                        .map_err(|parse_error| invalid_parameter("income_currency", parse_error))?,
+                )),
+            };
+            let converted_income = conversion.as_ref().map_or(income, |(_, conversion)| income * conversion.rate);
+
+            let mut result = Self::check_housing_grant_internal(
+                ami,
+                household_size,
+                children,
+                converted_income,
+                has_other_subsidy,
+                scale,
+                call.audience,
+            );
+            if let Some((currency, conversion)) = conversion {
+                let step = explain(call.audience, "check_housing_grant.income_conversion", context! {
+                    income,
+                    currency,
+                    rate => conversion.rate,
+                    rate_date => conversion.quote_date.map(|date| date.to_string()),
+                    converted_income,
+                    base_currency => fx.base_currency,
+                });
+                result.explanation = format!("{}. {}", step, result.explanation);
+            }
+            result.errors.extend(threshold_confirmations(call.context.as_ref(), &[
+                ("income", converted_income, Self::housing_income_threshold(ami, household_size, children, scale)),
+            ]));
+            Ok(result)
+        })
+    }
+
+    /// Calculate capital gains tax
+    #[tool(title = "Capital Gains Tax", description = "Calculates capital gains tax on the sale of an asset. Computes the gain (sale price - acquisition cost), picks the short- or long-term rate from the holding period, deducts the annual exemption, and returns the gain, applicable rate, and tax. Use when the user provides specific values (acquisition_cost, sale_price, holding_period_days) and asks for the tax due on a sale. Do NOT use for lookup questions: 'What is the capital gains rate?', 'How long must I hold an asset?' — those answers come from retrieved documents. Requires acquisition_cost, sale_price, holding_period_days; annual_exemption is optional.")]
+    pub async fn calc_capital_gains(
+        &self,
+        Parameters(params): Parameters<CalcCapitalGainsParams>
+    ) -> Result<CallToolResult, McpError> {
+        run_tool("calc_capital_gains", PayloadErrors::Validation, &params, |call| {
+            let acquisition_cost = call.f64("acquisition_cost", &params.acquisition_cost)?;
+            let sale_price = call.f64("sale_price", &params.sale_price)?;
+            let holding_period_days = call.i32("holding_period_days", &params.holding_period_days)?;
+            let annual_exemption = call.f64_or(
+                "annual_exemption",
+                params.annual_exemption.as_ref(),
+                config().default_capital_gains_exemption,
+            )?;
+
+            Ok(Self::calc_capital_gains_internal(
+                acquisition_cost,
+                sale_price,
+                holding_period_days,
+                annual_exemption,
+                call.audience,
+            ))
+        })
+    }
+
+    /// Calculate inheritance or gift tax per beneficiary
+    #[tool(title = "Inheritance and Gift Tax", description = "Calculates inheritance or gift tax for each beneficiary. Looks up the beneficiary's relationship class (e.g. spouse, child, sibling, other), deducts the class exemption (reduced by any prior gifts), and applies the class's progressive rates. Returns the tax per beneficiary and the total. Use when the user provides specific beneficiaries with relationship and amount received and asks for the tax due. Do NOT use for 'What is the exemption for children?' or 'What are the inheritance tax rates?' — those are lookups answered from documents. Requires beneficiaries (name, relationship, amount; prior_gifts optional).")]
+    pub async fn calc_inheritance_tax(
+        &self,
+        Parameters(params): Parameters<CalcInheritanceTaxParams>
+    ) -> Result<CallToolResult, McpError> {
+        run_tool("calc_inheritance_tax", PayloadErrors::Validation, &params, |call| {
+            let mut beneficiaries = Vec::with_capacity(params.beneficiaries.len());
+            for (i, beneficiary) in params.beneficiaries.iter().enumerate() {
+                validate_input_security(&beneficiary.name, "beneficiary name")
+                    .map_err(|e| invalid_parameter(&format!("beneficiaries[{}].name", i), e))?;
+                validate_input_security(&beneficiary.relationship, "relationship")
+                    .map_err(|e| invalid_parameter(&format!("beneficiaries[{}].relationship", i), e))?;
+                let amount = call.f64(&format!("beneficiaries[{}].amount", i), &beneficiary.amount)?;
+                let prior_gifts = match beneficiary.prior_gifts.as_ref() {
+                    None => 0.0,
+                    Some(s) => call.f64(&format!("beneficiaries[{}].prior_gifts", i), s)?,
+                };
+                beneficiaries.push((
+                    sanitize_for_error_message(&beneficiary.name),
+                    beneficiary.relationship.trim().to_ascii_lowercase(),
+                    amount,
+                    prior_gifts,
+                ));
+            }
+
+            Ok(Self::calc_inheritance_tax_internal(
+                &beneficiaries,
+                &config().inheritance_classes,
+                call.audience,
+            ))
+        })
+    }
+
+    /// Calculate social contributions
+    #[tool(title = "Social Contributions", description = "Calculates payroll social contributions (e.g. pension, health, unemployment) for an annual gross salary. Applies each contribution type's employee and employer rates up to its configured ceiling. Returns a per-type breakdown, employee and employer totals, and net salary after employee contributions. Use when the user provides a specific salary and asks for contributions owed. Do NOT use for 'What is the pension contribution rate?' or 'What is the contribution ceiling?' — those are lookups answered from documents. Requires gross_salary.")]
+    pub async fn calc_social_contributions(
+        &self,
+        Parameters(params): Parameters<CalcSocialContributionsParams>
+    ) -> Result<CallToolResult, McpError> {
+        run_tool("calc_social_contributions", PayloadErrors::Validation, &params, |call| {
+            let gross_salary = call.f64("gross_salary", &params.gross_salary)?;
+
+            Ok(Self::calc_social_contributions_internal(
+                gross_salary,
+                &config().social_contributions,
+                call.audience,
+            ))
+        })
+    }
+
+    /// Calculate property tax
+    #[tool(title = "Property Tax", description = "Calculates annual property tax from an assessed value. Caps the year-over-year increase in taxable value when the prior year's value is given, deducts the homestead exemption for a primary residence, and applies the millage rate (mills per 1000 of value). Returns the capped and taxable values, exemption, and tax. Use when the user provides a specific assessed value and asks for the property tax due. Do NOT use for 'What is the millage rate?' or 'How much is the homestead exemption?' — those are lookups answered from documents. Requires assessed_value; prior_year_value, homestead and millage are optional.")]
+    pub async fn calc_property_tax(
+        &self,
+        Parameters(params): Parameters<CalcPropertyTaxParams>
+    ) -> Result<CallToolResult, McpError> {
+        run_tool("calc_property_tax", PayloadErrors::Validation, &params, |call| {
+            let assessed_value = call.f64("assessed_value", &params.assessed_value)?;
+            let prior_year_value = call.optional_f64("prior_year_value", params.prior_year_value.as_ref())?;
+            let homestead = call.bool_or("homestead", params.homestead.as_ref(), false)?;
+            let millage = call.f64_or("millage", params.millage.as_ref(), config().default_property_millage)?;
+
+            let result = Self::calc_property_tax_internal(
+                assessed_value,
+                prior_year_value,
+                homestead,
+                millage,
+                call.audience,
+            );
+            if result.errors.is_empty() {
+                reference::check("calc_property_tax", "tax", result.tax, || {
+                    reference::property_tax(
+                        assessed_value,
+                        prior_year_value,
+                        homestead.then_some(config().property_homestead_exemption),
+                        config().property_assessment_cap,
+                        millage,
+                    )
+                });
+            }
+            Ok(result)
+        })
+    }
+
+    /// Calculate transfer tax (stamp duty)
+    #[tool(title = "Transfer Tax", description = "Calculates transfer tax (stamp duty) on a property price using the configured bracket schedule. In slice mode the whole price is taxed at the rate of the slice it falls in; in marginal mode each rate applies only to the part of the price within its bracket. Returns the applicable rate, tax, and effective rate. Use when the user provides a specific price and asks for the transfer tax or stamp duty due. Do NOT use for 'What are the stamp duty rates?' — that is a lookup answered from documents. Requires price; method is optional.")]
+    pub async fn calc_transfer_tax(
+        &self,
+        Parameters(params): Parameters<CalcTransferTaxParams>
+    ) -> Result<CallToolResult, McpError> {
+        run_tool("calc_transfer_tax", PayloadErrors::Validation, &params, |call| {
+            let price = call.f64("price", &params.price)?;
+            let method = match params.method.as_ref() {
+                None => call.assumptions.defaulted("method", config().transfer_tax_method),
+                Some(value) => TransferTaxMethod::parse(value)
+                    .map_err(|parse_error| invalid_parameter("method", parse_error))?,
+            };
+            let method = call.parsed("method", method);
+
+            let result = Self::calc_transfer_tax_internal(
+                price,
+                &config().transfer_tax_thresholds,
+                &config().transfer_tax_rates,
+                &config().transfer_tax_cliffs,
+                method,
+                call.audience,
+            );
+            if result.errors.is_empty() && !config().transfer_tax_cliffs.contains(&true) {
+                reference::check("calc_transfer_tax", "tax", result.tax, || {
+                    reference::transfer_tax(
+                        price,
+                        &config().transfer_tax_thresholds,
+                        &config().transfer_tax_rates,
+                        method == TransferTaxMethod::Slice,
+                    )
+                });
+            }
+            Ok(result)
+        })
+    }
+
+    /// Calculate a fee from its configured schedule
+    #[tool(title = "Scheduled Fee", description = "Calculates a court, registry or administrative fee from the configured fee schedule for a fee code. Each component is a fixed amount plus a percentage of the value above a threshold, optionally capped; the fee is the sum of the components. Returns the per-component breakdown and total fee. Use when the user provides a fee type and a value (claim amount, property value) and asks what the fee is. Do NOT use for 'Which fees apply to a filing?' — that is a lookup answered from documents. Requires fee_code and value.")]
+    pub async fn calc_fee(
+        &self,
+        Parameters(params): Parameters<CalcFeeParams>
+    ) -> Result<CallToolResult, McpError> {
+        run_tool("calc_fee", PayloadErrors::Validation, &params, |call| {
+            let fee_code = params.fee_code.trim().to_ascii_lowercase();
+            let value = call.f64("value", &params.value)?;
+
+            Ok(Self::calc_fee_internal(
+                &fee_code,
+                value,
+                &config().fee_schedules,
+                call.audience,
+            ))
+        })
+    }
+
+    /// Inflation-adjust an amount between years
+    #[tool(title = "Inflation Indexing", description = "Inflation-adjusts a monetary amount from one year to another using the configured price index table, citing the index values used. Returns the index values, adjustment factor, and adjusted amount. Use when the user provides an amount and years and asks what it is worth in another year's money, or to update a cap or threshold for inflation. Do NOT use for 'What was inflation in 2023?' — that is a lookup answered from documents. Requires amount; from_year and to_year default to the configured base and target years.")]
+    pub async fn index_amount(
+        &self,
+        Parameters(params): Parameters<IndexAmountParams>
+    ) -> Result<CallToolResult, McpError> {
+        run_tool("index_amount", PayloadErrors::Validation, &params, |call| {
+            let amount = call.f64("amount", &params.amount)?;
+            let from_year = call.i32_or("from_year", params.from_year.as_ref(), price_index().base_year)?;
+            let to_year = call.i32_or("to_year", params.to_year.as_ref(), price_index().target_year)?;
+
+            Ok(Self::index_amount_internal(
+                amount,
+                from_year,
+                to_year,
+                &price_index(),
+                call.audience,
+            ))
+        })
+    }
+
+    /// Convert an amount between currencies
+    #[tool(title = "Currency Conversion", description = "Converts an amount between currencies using the configured date-stamped exchange rate table, crossing through the base currency when needed, and cites the rate and quote date used. Returns the rate, quote date, and converted amount. Use when the user provides an amount in one currency and needs it in another, e.g. before comparing it to a threshold. Do NOT use for 'What is today's exchange rate?' without an amount — that is a lookup answered from documents. Requires amount and from_currency; to_currency defaults to the base currency and date to the latest rate.")]
+    pub async fn convert_currency(
+        &self,
+        Parameters(params): Parameters<ConvertCurrencyParams>
+    ) -> Result<CallToolResult, McpError> {
+        run_tool("convert_currency", PayloadErrors::Validation, &params, |call| {
+            let amount = call.f64("amount", &params.amount)?;
+            let from_currency = params.from_currency.trim().to_ascii_uppercase();
+            let to_currency = match params.to_currency.as_ref() {
+                None => call.assumptions.defaulted("to_currency", config().fx.base_currency.clone()),
+                Some(s) => s.trim().to_ascii_uppercase(),
+            };
+            let from_currency = call.parsed("from_currency", from_currency);
+            let to_currency = call.parsed("to_currency", to_currency);
+            let date = params
+                .date
+                .as_deref()
+                .map(parse_date_from_string)
+                .transpose()
+                .map_err(|parse_error| invalid_parameter("date", parse_error))?;
+
+            Ok(Self::convert_currency_internal(
+                amount,
+                &from_currency,
+                &to_currency,
+                date,
+                &config().fx,
+                call.audience,
+            ))
+        })
+    }
+
+    /// Describe the intake form for a program
+    #[tool(title = "Program Intake Form", description = "Returns the intake form for a named program: the tool to submit to and each field's name, input type, whether it is required, help text, validation hints derived from the eligibility rules, and allowed values for choice fields. Use when a client needs to render or walk the user through a form that collects exactly what the engine needs before calling the program's tool. Requires program.")]
+    pub async fn get_intake_schema(
+        &self,
+        Parameters(params): Parameters<GetIntakeSchemaParams>
+    ) -> Result<CallToolResult, McpError> {
+        run_tool("get_intake_schema", PayloadErrors::Validation, &params, |call| {
+            let program = params.program.trim().to_ascii_lowercase();
+
+            Ok(Self::get_intake_schema_internal(&program, call.audience))
+        })
+    }
+
+    /// Pre-screen program eligibility on partial inputs
+    #[tool(title = "Eligibility Pre-screen", description = "Pre-screens eligibility for a program using whatever subset of its parameters is known so far. Returns 'ineligible' as soon as any rule fails regardless of the missing inputs, 'eligible' when every rule passes, or 'undetermined' with the inputs still needed, plus the outcome of each rule. Use early in a conversation to stop collecting data once the answer is settled, or to ask only for the inputs that still matter; call the program's tool (e.g. check_housing_grant) for the full determination. Requires program; inputs may be empty.")]
+    pub async fn prescreen(
+        &self,
+        Parameters(params): Parameters<PrescreenParams>
+    ) -> Result<CallToolResult, McpError> {
+        run_tool("prescreen", PayloadErrors::Validation, &params, |call| {
+            let program = params.program.trim().to_ascii_lowercase();
+
+            // Parse each provided input as its form field type; unknown programs are reported below
+            let mut inputs = BTreeMap::new();
+            let fields = intake_program(&program).map(|(_, fields)| fields).unwrap_or_default();
+            for (name, raw) in &params.inputs {
+                let Some(field) = fields.iter().find(|field| field.name == name) else {
+                    if fields.is_empty() {
+                        continue;
+                    }
+                    return Err(invalid_parameter("inputs", format!(
+                        "unknown field '{}' (expected one of: {})",
+                        sanitize_for_error_message(name),
+                        fields.iter().map(|field| field.name).collect::<Vec<_>>().join(", ")
+                    )));
+                };
+                let text = match raw {
+                    serde_json::Value::Null => continue,
+                    serde_json::Value::String(text) => text.clone(),
+                    other => other.to_string(),
+                };
+                // Values of the field's JSON type are read directly; anything else is read as text
+                let value = match field.field_type {
+                    "number" => call
+                        .f64(name, &FlexibleF64::deserialize(raw).unwrap_or_else(|_| text.as_str().into()))
+                        .map(serde_json::Value::from),
+                    "integer" => call
+                        .i32(name, &FlexibleI32::deserialize(raw).unwrap_or_else(|_| text.as_str().into()))
+                        .map(serde_json::Value::from),
+                    "boolean" => call
+                        .bool(name, &FlexibleBool::deserialize(raw).unwrap_or_else(|_| text.as_str().into()))
+                        .map(serde_json::Value::from),
+                    _ => Ok(serde_json::Value::from(text.trim().to_ascii_lowercase())),
+                }?;
+                inputs.insert(name.clone(), value);
+            }
+
+            Ok(Self::prescreen_internal(&program, &inputs, call.audience))
+        })
+    }
+
+    /// Report tool usage analytics
+    #[tool(title = "Tool Usage Report", description = "Admin tool reporting how the engine's tools have been used since the server started: calls and failed calls per tool, the JSON types each parameter was sent as (string vs native number or boolean), and the most frequent errors (parse errors, missing fields, validation errors). Use when an operator asks where clients misuse the API or which tools are called. Do NOT use to answer a user's calculation or eligibility question. All parameters are optional.")]
+    pub async fn get_usage_report(
+        &self,
+        Parameters(params): Parameters<GetUsageReportParams>
+    ) -> Result<CallToolResult, McpError> {
+        run_tool("get_usage_report", PayloadErrors::Validation, &params, |call| {
+            let limit = call.i32_or("limit", params.limit.as_ref(), 10)?;
+
+            Ok(Self::get_usage_report_internal(
+                analytics::snapshot().as_ref(),
+                params.tool.as_deref().map(str::trim),
+                limit,
+                call.audience,
+            ))
+        })
+    }
+
+    /// List tools by category
+    #[tool(title = "Tools by Category", description = "Catalog tool listing the engine's tools grouped by category (finance, tax, eligibility, governance, admin), with each tool's title and a one-line description of its logic. Use to find the right tool for a question before calling it, or to show a user what the engine can calculate. Do NOT use to answer a calculation or eligibility question itself. All parameters are optional.")]
+    pub async fn list_tools_by_category(
+        &self,
+        Parameters(params): Parameters<ListToolsByCategoryParams>
+    ) -> Result<CallToolResult, McpError> {
+        run_tool("list_tools_by_category", PayloadErrors::Validation, &params, |call| {
+            Ok(Self::list_tools_by_category_internal(
+                params.category.as_deref().map(str::trim),
+                call.audience,
+            ))
+        })
+    }
+
+    /// Describe negotiated client capabilities
+    #[tool(title = "Client Capabilities", description = "Admin tool describing what this MCP session negotiated with the client during initialize (client name, protocol version, sampling support) and how tool results are shaped as a result: whether they carry structuredContent next to the JSON text, whether they are inline or resource links, and whether a sampled plain-language summary is appended. Use when debugging how a client receives results. Do NOT use to answer a calculation or eligibility question. All parameters are optional.")]
+    pub async fn describe_capabilities(
+        &self,
+        Parameters(params): Parameters<DescribeCapabilitiesParams>
+    ) -> Result<CallToolResult, McpError> {
+        run_tool("describe_capabilities", PayloadErrors::Validation, &params, |call| {
+            Ok(Self::describe_capabilities_internal(
+                self.client.get(),
+                sampling::enabled(),
+                call.audience,
+            ))
+        })
+    }
+
+    /// Report recent schema failures
+    #[tool(title = "Recent Schema Failures", description = "Admin tool listing the most recent tool calls rejected before reaching the tool because their arguments did not fit the tool's parameters (missing fields, wrong JSON types, unknown enum values). Each sample shows the argument shape, field names with JSON types instead of values, and the redacted error. Use when an operator asks how clients malform calls. Do NOT use to answer a user's calculation or eligibility question. All parameters are optional.")]
+    pub async fn get_recent_schema_failures(
+        &self,
+        Parameters(params): Parameters<GetRecentSchemaFailuresParams>
+    ) -> Result<CallToolResult, McpError> {
+        run_tool("get_recent_schema_failures", PayloadErrors::Validation, &params, |call| {
+            let limit = call.i32_or("limit", params.limit.as_ref(), 20)?;
+            let schema_failures = analytics::schema_failures();
+
+            Ok(Self::get_recent_schema_failures_internal(
+                schema_failures.as_deref(),
+                params.tool.as_deref().map(str::trim),
+                limit,
+                call.audience,
+            ))
+        })
+    }
+
+    /// Add or update the configuration profile
+    #[tool(title = "Upsert Configuration Profile", description = "Admin tool replacing the configuration profile (rates, brackets, schedules, exchange rates, price index) at runtime. The profile is checked like the configuration at startup and rejected if any setting is invalid; a valid profile is saved to the profile store and put in force, and the new configuration version is returned. Only available when the server runs with ENGINE_ADMIN_TOOLS enabled. Do NOT use to answer a user's calculation or eligibility question. Requires profile.")]
+    pub async fn upsert_profile(
+        &self,
+        Parameters(params): Parameters<UpsertProfileParams>
+    ) -> Result<CallToolResult, McpError> {
+        run_tool("upsert_profile", PayloadErrors::Validation, &params, |call| {
+            Ok(Self::upsert_profile_internal(&params.profile, &|name| env::var(name).ok(), &RULES, call.audience))
+        })
+    }
+
+    /// Compare two configuration profiles
+    #[tool(title = "Diff Configuration Profiles", description = "Compare two configuration profiles (as printed by --migrate-config) and list what a rule update changes: settings added, removed or changed, and for bracket lists the brackets whose limits, rates or cliff flags differ, each with a short summary. Use to review a profile before it is applied with upsert_profile. Requires before, after.")]
+    pub async fn diff_profiles(
+        &self,
+        Parameters(params): Parameters<DiffProfilesParams>
+    ) -> Result<CallToolResult, McpError> {
+        run_tool("diff_profiles", PayloadErrors::Validation, &params, |call| {
+            Ok(Self::diff_profiles_internal(&params.before, &params.after, &price_index(), call.audience))
+        })
+    }
+
+    /// Evaluate a calculation over ranges of its inputs
+    #[tool(title = "Range Evaluation", description = "Runs a calculation or eligibility tool with some inputs known only as ranges (e.g. income between 38000 and 42000) and reports the lowest and highest value of every numeric result, plus which yes/no decisions (such as eligible or passes) flip within the ranges. Use when the user only knows approximate figures and asks whether the answer could change. Give the tool's arguments as usual, with each uncertain number as {\"min\": ..., \"max\": ...}; at most six ranges. Results are exact for calculations that only rise or only fall as each input grows, which covers taxes, penalties, fees and income limits. Requires tool and arguments.")]
+    pub async fn evaluate_ranges(
+        &self,
+        Parameters(params): Parameters<EvaluateRangesParams>
+    ) -> Result<CallToolResult, McpError> {
+        let tool = params.tool.trim().to_ascii_lowercase();
+        let calculator = what_if_calculator(&tool, "evaluate_ranges");
+        let split = split_ranges(&params.arguments);
+        let mut evaluations = Vec::new();
+        let mut points = Vec::new();
+        if let (Ok(calculator), Ok((base, ranges))) = (&calculator, &split)
+            && (1..=MAX_RANGES).contains(&ranges.len())
+        {
+            let mut base = base.clone();
+            if let Some(acknowledged) = &params.acknowledge_warnings {
+                base.entry("acknowledge_warnings").or_insert_with(|| serde_json::json!(acknowledged));
+            }
+            for corner in 0..1usize << ranges.len() {
+                let mut arguments = base.clone();
+                let mut at = Vec::new();
+                let mut point = BTreeMap::new();
+                for (index, (name, min, max)) in ranges.iter().enumerate() {
+                    let end = if corner & (1 << index) == 0 { min } else { max };
+                    at.push(format!("{} = {}", name, end));
+                    arguments.insert(name.clone(), end.clone());
+                    point.insert(name.clone(), end.clone());
+                }
+                evaluations.push((at.join(", "), what_if_payload(self, *calculator, arguments).await));
+                points.push(point);
+            }
+        }
+
+        run_tool("evaluate_ranges", PayloadErrors::Validation, &params, |call| {
+            let calculator = calculator.map_err(|e| invalid_parameter("tool", e))?;
+            let (_, ends) = split.map_err(|e| invalid_parameter("arguments", e))?;
+            let mut ranges = Vec::new();
+            for (name, min, max) in &ends {
+                let mut end = |field: &str, value: &serde_json::Value| {
+                    let path = format!("arguments.{}.{}", name, field);
+                    let raw: FlexibleF64 = serde_json::from_value(value.clone()).map_err(|e| invalid_parameter(&path, e))?;
+                    call.f64(&path, &raw)
+                };
+                ranges.push(InputRange { parameter: name.clone(), min: end("min", min)?, max: end("max", max)? });
+            }
+            let export = ExportFormat::parse(params.export.as_ref()).map_err(|e| invalid_parameter("export", e))?;
+
+            let mut result = Self::evaluate_ranges_internal(
+                &calculator.name(),
+                ranges,
+                &evaluations,
+                call.audience,
+            );
+            if let Some(format) = export
+                && result.errors.is_empty()
+            {
+                let rows = export_rows("point", &points, &evaluations);
+                result.export = Some(export::write(format, "evaluate_ranges", &rows, &["point"])?);
+            }
+            Ok(result)
+        })
+    }
+
+    /// Simulate a calculation with inputs drawn from distributions
+    #[tool(title = "Uncertainty Simulation", description = "Runs a calculation or eligibility tool many times with some inputs drawn at random from distributions (normal with mean and sd, or uniform between min and max) and reports percentiles (5th, 25th, median, 75th, 95th) and the mean of every numeric result, plus the probability of every yes/no result such as eligible. Use for risk and policy questions like 'how likely is this household to qualify if its income is about 40000 give or take 2000'. Give the tool's arguments as usual, with each uncertain number as {\"distribution\": \"normal\", \"mean\": ..., \"sd\": ...} or {\"distribution\": \"uniform\", \"min\": ..., \"max\": ...}. Draws are seeded, so the same call gives the same answer. Requires tool and arguments.")]
+    pub async fn simulate_uncertainty(
+        &self,
+        Parameters(params): Parameters<SimulateUncertaintyParams>
+    ) -> Result<CallToolResult, McpError> {
+        let tool = params.tool.trim().to_ascii_lowercase();
+        let calculator = what_if_calculator(&tool, "simulate_uncertainty");
+        let split = split_distributions(&params.arguments);
+        let draws = params.draws.as_ref().map_or(Ok(DEFAULT_DRAWS), |draws| draws.0.clone());
+        let seed = params.seed.as_ref().map_or(Ok(DEFAULT_SEED), |seed| seed.0.clone());
+        let mut evaluations = Vec::new();
+        let mut points = Vec::new();
+        if let (Ok(calculator), Ok((base, specs)), Ok(draws), Ok(seed)) = (&calculator, &split, draws, seed)
+            && (1..=MAX_DRAWS).contains(&draws)
+            && seed >= 0
+        {
+            let inputs: Result<Vec<(String, Distribution, bool)>, String> = specs
+                .iter()
+                .map(|(name, kind, spec)| {
+                    let distribution = Distribution::parse(kind, |field| {
+                        spec.get(field).map_or(Err(format!("missing {}", field)), |value| {
+                            parse_f64_from_string(&value.as_str().map_or_else(|| value.to_string(), str::to_string))
+                        })
+                    })?;
+                    distribution.check()?;
+                    Ok((name.clone(), distribution, integer_argument(*calculator, name)))
+                })
+                .collect();
+            if let Ok(inputs) = inputs
+                && !inputs.is_empty()
+            {
+                let mut base = base.clone();
+                if let Some(acknowledged) = &params.acknowledge_warnings {
+                    base.entry("acknowledge_warnings").or_insert_with(|| serde_json::json!(acknowledged));
+                }
+                let mut rng = StdRng::seed_from_u64(seed as u64);
+                for draw in 1..=draws {
+                    let mut arguments = base.clone();
+                    let mut at = Vec::new();
+                    let mut point = BTreeMap::new();
+                    for (name, distribution, integer) in &inputs {
+                        let value = distribution.sample(&mut rng);
+                        let value = if *integer { serde_json::Value::from(value.round() as i64) } else { serde_json::Value::from(value) };
+                        at.push(format!("{} = {}", name, value));
+                        arguments.insert(name.clone(), value.clone());
+                        point.insert(name.clone(), value);
+                    }
+                    let evaluation = what_if_payload(self, *calculator, arguments).await;
+                    let failed = evaluation.is_err();
+                    evaluations.push((format!("draw {} ({})", draw, at.join(", ")), evaluation));
+                    points.push(point);
+                    if failed {
+                        break;
+                    }
+                }
+            }
+        }
+
+        run_tool("simulate_uncertainty", PayloadErrors::Validation, &params, |call| {
+            let calculator = calculator.map_err(|e| invalid_parameter("tool", e))?;
+            let (_, specs) = split.map_err(|e| invalid_parameter("arguments", e))?;
+            let mut inputs = Vec::new();
+            for (name, kind, spec) in &specs {
+                let distribution = Distribution::parse(kind, |field| {
+                    let path = format!("arguments.{}.{}", name, field);
+                    let value = spec.get(field).ok_or_else(|| invalid_parameter(&path, "missing"))?;
+                    let raw: FlexibleF64 = serde_json::from_value(value.clone()).map_err(|e| invalid_parameter(&path, e))?;
+                    call.f64(&path, &raw)
+                })
+                .map_err(|e| invalid_parameter(&format!("arguments.{}", name), e))?;
+                inputs.push(InputDistribution { parameter: name.clone(), distribution });
+            }
+            let draws = call.i32_or("draws", params.draws.as_ref(), DEFAULT_DRAWS)?;
+            let seed = call.i32_or("seed", params.seed.as_ref(), DEFAULT_SEED)?;
+            let export = ExportFormat::parse(params.export.as_ref()).map_err(|e| invalid_parameter("export", e))?;
+
+            let mut result = Self::simulate_uncertainty_internal(
+                &calculator.name(),
+                inputs,
+                draws,
+                seed,
+                &evaluations,
+                call.audience,
+            );
+            if let Some(format) = export
+                && result.errors.is_empty()
+            {
+                let rows = export_rows("draw", &points, &evaluations);
+                result.export = Some(export::write(format, "simulate_uncertainty", &rows, &["draw"])?);
+            }
+            Ok(result)
+        })
+    }
+
+    /// Check whether an appeal is filed within the statutory window
+    #[tool(title = "Appeal Deadline Check", description = "Determines whether an appeal against a decision is filed within the statutory appeal window. Computes the deadline from the decision date and window (business days skipping weekends and public holidays, or calendar days with a deadline on a weekend or holiday moved to the next business day) and returns whether the filing is in time, the deadline, and the days remaining or overdue. Use when the user gives a decision date and asks whether an appeal is still possible or was filed in time. Do NOT use for 'How long is the appeal period?' — that is a lookup answered from documents. Requires decision_date; filing_date (default today), window_days and business_days are optional.")]
+    pub async fn check_appeal_deadline(
+        &self,
+        Parameters(params): Parameters<CheckAppealDeadlineParams>
+    ) -> Result<CallToolResult, McpError> {
+        run_tool("check_appeal_deadline", PayloadErrors::Validation, &params, |call| {
+            let decision_date = parse_date_from_string(&params.decision_date)
+                .map_err(|parse_error| invalid_parameter("decision_date", parse_error))?;
+            let decision_date = call.parsed("decision_date", decision_date);
+            let filing_date = match params.filing_date.as_deref() {
+                None => call.assumptions.defaulted("filing_date", clock::today()),
+                Some(s) => parse_date_from_string(s).map_err(|parse_error| invalid_parameter("filing_date", parse_error))?,
+            };
+            let filing_date = call.parsed("filing_date", filing_date);
+            let window_days = call.i32_or("window_days", params.window_days.as_ref(), config().appeal_window_days)?;
+            let business_days = call.bool_or("business_days", params.business_days.as_ref(), config().appeal_business_days)?;
+
+            Ok(Self::check_appeal_deadline_internal(
+                decision_date,
+                filing_date,
+                window_days,
+                business_days,
+                &config().public_holidays,
+                call.audience,
+            ))
+        })
+    }
+
+    /// List the documents a program requires
+    #[tool(title = "Required Documents", description = "Returns the checklist of documents an applicant must provide for a program (e.g. housing_grant), including the extra documents their situation calls for, such as self_employed, large_household or non_resident. The checklists are configured per program. Use when the user asks what they need to bring or submit for an application, or to prepare an application after an eligibility check. Requires program; situation flags are optional.")]
+    pub async fn get_required_documents(
+        &self,
+        Parameters(params): Parameters<GetRequiredDocumentsParams>
+    ) -> Result<CallToolResult, McpError> {
+        run_tool("get_required_documents", PayloadErrors::Validation, &params, |call| {
+            let program = params.program.trim().to_ascii_lowercase();
+            let situation: Vec<String> = params
+                .situation
+                .iter()
+                .flatten()
+                .map(|flag| situation_flag(flag))
+                .filter(|flag| !flag.is_empty())
+                .collect();
+            let situation = call.parsed("situation", situation);
+
+            Ok(Self::get_required_documents_internal(
+                &program,
+                &situation,
+                &config().required_documents,
+                call.audience,
+            ))
+        })
+    }
+
+    /// Check a voting proposal on tallies from several precincts
+    #[tool(title = "Multi-Precinct Voting Check", description = "Suitable for Lysmark's for calculations related to the Organizational Voting and Quorum Act. Aggregates per-precinct tallies (eligible voters, turnout, yes votes) for a vote held at several sites, determines whether the proposal passes on the totals with the same turnout and yes-vote thresholds as check_voting, and reports each precinct's turnout, approval and whether it met quorum on its own. Use when the user gives results for several precincts, branches or sites. For a single tally use check_voting. Requires precincts and proposal_type.")]
+    pub async fn check_voting_bulk(
+        &self,
+        Parameters(params): Parameters<CheckVotingBulkParams>
+    ) -> Result<CallToolResult, McpError> {
+        run_tool("check_voting_bulk", PayloadErrors::Validation, &params, |call| {
+            let mut precincts = Vec::with_capacity(params.precincts.len());
+            for (i, precinct) in params.precincts.iter().enumerate() {
+                validate_input_security(&precinct.name, "precinct name")
+                    .map_err(|e| invalid_parameter(&format!("precincts[{}].name", i), e))?;
+                let eligible_voters = call.i32(&format!("precincts[{}].eligible_voters", i), &precinct.eligible_voters)?;
+                let turnout = call.i32(&format!("precincts[{}].turnout", i), &precinct.turnout)?;
+                let yes_votes = call.i32(&format!("precincts[{}].yes_votes", i), &precinct.yes_votes)?;
+                precincts.push((sanitize_for_error_message(&precinct.name), eligible_voters, turnout, yes_votes));
+            }
+
+            let mut result = Self::check_voting_bulk_internal(
+                &precincts,
+                &params.proposal_type,
+                call.audience,
+            );
+            if result.errors.is_empty() && result.turnout > 0 {
+                result.errors.extend(threshold_confirmations(call.context.as_ref(), &[
+                    ("turnout ratio", result.turnout_percentage, result.required_turnout),
+                    ("yes vote ratio", result.approval_percentage, result.required_approval),
+                ]));
+            }
+            Ok(result)
+        })
+    }
+
+    /// Project the votes needed for quorum and to pass
+    #[tool(title = "Quorum Projection", description = "Suitable for Lysmark's for calculations related to the Organizational Voting and Quorum Act. For a vote still in progress, computes how many more votes are needed to reach the 60% turnout quorum and the fewest yes votes that pass a general proposal (more than half) and an amendment (at least two thirds), with the yes votes still missing. Given hours elapsed and hours remaining, also projects the final turnout at the rate so far. Use when the user asks how many more voters or yes votes are needed. For a finished vote use check_voting. Requires eligible_voters and turnout; yes_votes, hours_elapsed and hours_remaining are optional.")]
+    pub async fn project_quorum(
+        &self,
+        Parameters(params): Parameters<ProjectQuorumParams>
+    ) -> Result<CallToolResult, McpError> {
+        run_tool("project_quorum", PayloadErrors::Validation, &params, |call| {
+            let eligible_voters = call.i32("eligible_voters", &params.eligible_voters)?;
+            let turnout = call.i32("turnout", &params.turnout)?;
+            let yes_votes = call.i32_or("yes_votes", params.yes_votes.as_ref(), 0)?;
+            let hours_elapsed = call.optional_f64("hours_elapsed", params.hours_elapsed.as_ref())?;
+            let hours_remaining = call.optional_f64("hours_remaining", params.hours_remaining.as_ref())?;
+            let hours = match (hours_elapsed, hours_remaining) {
+                (Some(hours_elapsed), Some(hours_remaining)) => Some((hours_elapsed, hours_remaining)),
+                (None, None) => None,
+                (Some(_), None) => return Err(invalid_parameter("hours_remaining", "required with hours_elapsed")),
+                (None, Some(_)) => return Err(invalid_parameter("hours_elapsed", "required with hours_remaining")),
             };
+
+            Ok(Self::project_quorum_internal(
+                eligible_voters,
+                turnout,
+                yes_votes,
+                hours,
+                call.audience,
+            ))
+        })
+    }
+
+    /// Stress the tax and penalty rules with extreme inputs
+    #[tool(title = "Configuration Stress Test", description = "Admin tool that stresses the rule tables with extreme but valid inputs before production use: the tax brackets (the loaded ones, or a candidate table passed as tax_brackets) for incomes up to 1 trillion and a cent either side of every threshold, a 100-bracket table, and the penalty defaults for up to 10,000 days late. Reports per check the time taken, the largest difference from the integer-cent reference implementation and any case that is not finite, out of bounds or falls as income rises. Use when an operator asks whether imported rule tables are safe to deploy. Only available when the server runs with ENGINE_ADMIN_TOOLS enabled. Do NOT use to calculate a tax or penalty. All parameters are optional.")]
+    pub async fn stress_config(
+        &self,
+        Parameters(params): Parameters<StressConfigParams>
+    ) -> Result<CallToolResult, McpError> {
+        run_tool("stress_config", PayloadErrors::Validation, &params, |call| {
+            Ok(Self::stress_config_internal(
+                params.tax_brackets.as_deref(),
+                call.audience,
+            ))
+        })
+    }
+
+    /// Check the embedded store and rotate its keys
+    #[tool(title = "Store Integrity Check", description = "Admin tool checking the embedded SQLite store of builds with the embedded-store feature: runs SQLite's integrity check and decrypts every stored result to confirm it authenticates, reporting results per store key, unencrypted results and failures. With rewrap, re-wraps the data keys of results under older keys with the current key and encrypts unencrypted results, to finish a key rotation. Use when an operator asks whether stored data is intact or is rotating the store keys. Only available when the server runs with ENGINE_ADMIN_TOOLS enabled. Do NOT use for calculations. All parameters are optional.")]
+    pub async fn verify_store_integrity(
+        &self,
+        Parameters(params): Parameters<VerifyStoreIntegrityParams>
+    ) -> Result<CallToolResult, McpError> {
+        run_tool("verify_store_integrity", PayloadErrors::Validation, &params, |call| {
+            #[cfg_attr(not(feature = "embedded-store"), allow(unused_variables))]
+            let rewrap = call.bool_or("rewrap", params.rewrap.as_ref(), false)?;
+
+            #[cfg(feature = "embedded-store")]
+            let integrity = super::embedded_store::verify(rewrap);
+            #[cfg(not(feature = "embedded-store"))]
+            let integrity = Err("The embedded store is not available in this build (embedded-store feature)".to_string());
+
+            Ok(Self::verify_store_integrity_internal(integrity, call.audience))
+        })
+    }
+
+    /// Delete data past its retention period
+    #[tool(title = "Purge Expired Data", description = "Admin tool deleting the data past its retention period (ENGINE_RETENTION_DAYS): stored results older than the results period, in memory and in the embedded store, and the usage counts once counting began longer ago than the usage period. Reports per category the period, the cutoff and how many records were deleted, and which categories are kept without a period. The same purge runs hourly in the background; use this to purge at once and document it, e.g. for a data protection review. Only available when the server runs with ENGINE_ADMIN_TOOLS enabled. Do NOT use for calculations. No parameters are needed.")]
+    pub async fn purge_expired_data(
+        &self,
+        Parameters(params): Parameters<PurgeExpiredDataParams>
+    ) -> Result<CallToolResult, McpError> {
+        run_tool("purge_expired_data", PayloadErrors::Validation, &params, |call| {
+            let purged = retention::purge(clock::now());
+
+            Ok(Self::purge_expired_data_internal(&purged, call.audience))
+        })
+    }
+
+    /// Export the stored data of a case
+    #[tool(title = "Export Subject Data", description = "Admin tool for data subject access requests: finds every result stored for a case identifier (the context.case_id of the calls that produced them), in memory and in the embedded store, and returns them with the tool that produced each, when it was stored and the full payload. The server keeps no audit log or case records of its own, so stored results are all it holds about a case. Use when an operator must hand over what is held about a case. Only available when the server runs with ENGINE_ADMIN_TOOLS enabled. Do NOT use for calculations. Requires: case_id.")]
+    pub async fn export_subject_data(
+        &self,
+        Parameters(params): Parameters<ExportSubjectDataParams>
+    ) -> Result<CallToolResult, McpError> {
+        run_tool("export_subject_data", PayloadErrors::Validation, &params, |call| {
+            let case_id = parse_case_id(&params.case_id)?;
+            let found = result_store::case_results(&case_id);
+
+            Ok(Self::export_subject_data_internal(&case_id, found, call.audience))
+        })
+    }
+
+    /// Erase the stored data of a case
+    #[tool(title = "Erase Subject Data", description = "Admin tool for data erasure requests: deletes every result stored for a case identifier (the context.case_id of the calls that produced them), in memory and in the embedded store, destroying the wrapped data keys of encrypted results with them, and returns an erasure receipt that is also written to the server log under a hash of the case identifier. The server keeps no audit log or case records of its own. This cannot be undone: confirm the case with the operator first, and export the data beforehand if it must be handed over. Only available when the server runs with ENGINE_ADMIN_TOOLS enabled. Do NOT use for calculations. Requires: case_id.")]
+    pub async fn erase_subject_data(
+        &self,
+        Parameters(params): Parameters<EraseSubjectDataParams>
+    ) -> Result<CallToolResult, McpError> {
+        run_tool("erase_subject_data", PayloadErrors::Validation, &params, |call| {
+            let case_id = parse_case_id(&params.case_id)?;
+            let erasure = result_store::erase_case(&case_id);
+            let response = Self::erase_subject_data_internal(&case_id, erasure, clock::now(), call.audience);
+
+            let receipt = &response.receipt;
+            tracing::info!(
+                receipt_id = %receipt.receipt_id,
+                case_hash = %receipt.case_hash,
+                erased = receipt.erased,
+                erased_in_memory = receipt.erased_in_memory,
+                erased_stored = ?receipt.erased_stored,
+                shredded = ?receipt.shredded,
+                result_ids_digest = %receipt.result_ids_digest,
+                complete = receipt.complete,
+                "Subject data erased"
+            );
+            Ok(response)
+        })
+    }
+}
+
+#[tool_handler(router = self.tool_router)]
+impl ServerHandler for CompatibilityEngine {
+    fn get_info(&self) -> ServerInfo {
+        // Read basic information from .env file (replaced by sync script during release)
+        let name = "compatibility-engine-mcp-rs".to_string();
+        let version = "3.1.1".to_string();
+        let title = "Compatibility Engine MCP Server".to_string();
+        let website_url = "https://github.com/alpha-hack-program/compatibility-engine-mcp-rs.git".to_string();
+
+        let mut capabilities = ServerCapabilities::builder().enable_tools().enable_resources().enable_completions().build();
+        if reminders::enabled() {
+            capabilities.logging = Some(JsonObject::new());
+        }
+        ServerInfo::new(capabilities)
+            .with_instructions(
+                "Compatibility Engine providing thirty-one calculation and eligibility functions:\
+                 \n\n1. calc_penalty - Calculate penalty with cap and interest\
+                 \n2. calc_tax - Calculate progressive tax with surcharge\
+                 \n3. check_voting - Check voting proposal eligibility\
+                 \n4. validate_vote_record - Cross-check vote record tallies\
+                 \n5. distribute_waterfall - Distribute cash in waterfall structure\
+                 \n6. check_covenants - Check leverage and interest coverage covenants\
+                 \n7. calc_npv - Calculate net present value of cash flows\
+                 \n8. calc_irr - Calculate internal rate of return of cash flows\
+                 \n9. check_escrow_release - Evaluate escrow release conditions\
+                 \n10. check_housing_grant - Check housing grant eligibility\
+                 \n11. calc_capital_gains - Calculate capital gains tax by holding period\
+                 \n12. calc_inheritance_tax - Calculate inheritance or gift tax per beneficiary\
+                 \n13. calc_social_contributions - Calculate capped employer and employee social contributions\
+                 \n14. calc_property_tax - Calculate property tax from assessed value\
+                 \n15. calc_transfer_tax - Calculate transfer tax with slice or marginal brackets\
+                 \n16. calc_fee - Calculate a fee from fixed and percentage components\
+                 \n17. index_amount - Inflation-adjust an amount between years using a price index\
+                 \n18. convert_currency - Convert an amount between currencies using date-stamped rates\
+                 \n19. get_intake_schema - Describe the intake form for a program\
+                 \n20. prescreen - Pre-screen program eligibility on partial inputs\
+                 \n21. get_usage_report - Report tool usage analytics\
+                 \n22. list_tools_by_category - List tools by category\
+                 \n23. describe_capabilities - Describe negotiated client capabilities\
+                 \n24. get_recent_schema_failures - Report recent schema failures\
+                 \n25. diff_profiles - Compare two configuration profiles\
+                 \n26. evaluate_ranges - Evaluate a calculation over input ranges\
+                 \n27. simulate_uncertainty - Simulate a calculation with uncertain inputs\
+                 \n28. check_appeal_deadline - Check an appeal filing against the appeal window\
+                 \n29. get_required_documents - List the documents a program requires\
+                 \n30. check_voting_bulk - Check a voting proposal on tallies from several precincts\
+                 \n31. project_quorum - Project the votes needed for quorum and to pass\
+                 \n\nAll functions are strongly typed and provide explicit calculations.\
+                 \n\nThe calculations (all but the admin tools) can also be read as resources, with the tool's\
+                 arguments as query parameters: calc://<tool>?<argument>=<value>, e.g. calc://calc_tax?income=40000.",
+            )
+            .with_server_info(
+                Implementation::new(name, version)
+                    .with_title(title)
+                    .with_description(
+                        "Compatibility Engine MCP Server with 36 calculation and eligibility functions",
+                    )
+                    .with_website_url(website_url),
+            )
+    }
+
+    async fn initialize(
+        &self,
+        request: InitializeRequestParams,
+        context: ServiceContext<RoleServer>,
+    ) -> Result<ServerInfo, McpError> {
+        let info = self.get_info();
+        let _ = self.client.set(ClientProfile::negotiate(&request, &info));
+        if context.peer.peer_info().is_none() {
+            context.peer.set_peer_info(request);
+        }
+        Ok(info)
+    }
+
+    async fn set_level(
+        &self,
+        request: SetLevelRequestParams,
+        _context: ServiceContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        if !reminders::enabled() {
+            return Err(McpError::method_not_found::<SetLevelRequestMethod>());
         }
+        self.log_level.set(request.level);
+        Ok(())
+    }
+
+    async fn complete(
+        &self,
+        request: CompleteRequestParams,
+        _context: ServiceContext<RoleServer>,
+    ) -> Result<CompleteResult, McpError> {
+        let tool = match &request.r#ref {
+            Reference::Prompt(prompt) => Some(prompt.name.as_str()),
+            Reference::Resource(_) => None,
+        };
+        Ok(CompleteResult::new(complete_argument(tool, &request.argument.name, &request.argument.value)))
+    }
+
+    async fn list_resource_templates(
+        &self,
+        _request: Option<PaginatedRequestParams>,
+        _context: ServiceContext<RoleServer>,
+    ) -> Result<ListResourceTemplatesResult, McpError> {
+        Ok(ListResourceTemplatesResult::with_all_items(resources::templates()))
+    }
+
+    async fn read_resource(
+        &self,
+        request: ReadResourceRequestParams,
+        _context: ServiceContext<RoleServer>,
+    ) -> Result<ReadResourceResult, McpError> {
+        resources::read(self, &request.uri).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Unwrap the versioned response envelope and return the typed payload
+    fn parse_payload<T: serde::de::DeserializeOwned>(json_text: &str) -> T {
+        let envelope: ResponseEnvelope<T> = serde_json::from_str(json_text).unwrap();
+        assert_eq!(envelope.schema_version, RESPONSE_SCHEMA_VERSION);
+        envelope.payload
+    }
+
+    #[tokio::test]
+    async fn test_calc_penalty() {
+        let engine = CompatibilityEngine::new();
+        let params = CalcPenaltyParams {
+            days_late: "12".into(),
+            ..Default::default()
+        };
         
-        let mut remaining = cash_available;
-        explanation_parts.push(format!("Starting cash: {:.2}", cash_available));
+        let result = engine.calc_penalty(Parameters(params)).await;
+        assert!(result.is_ok());
         
-        // Pay senior debt first
-        let senior_payment = remaining.min(senior_debt);
-        remaining -= senior_payment;
+        let call_result = result.unwrap();
+        let content = call_result.content;
+        let json_text = content[0].raw.as_text().unwrap().text.as_str();
+        let response: CalcPenaltyResponse = parse_payload(json_text);
         
-        if senior_debt > 0.0 {
-            if senior_payment == senior_debt {
-                explanation_parts.push(format!("Senior debt: {:.2} fully paid", senior_debt));
-            } else {
-                explanation_parts.push(format!("Senior debt: {:.2} partially paid ({:.2} of {:.2})", senior_payment, senior_payment, senior_debt));
-                warnings.push(format!("Senior debt underpaid by {:.2}", senior_debt - senior_payment));
-            }
-        } else {
-            explanation_parts.push("No senior debt to pay".to_string());
-        }
+        // Expected: min(12 * 100, 1000) = 1000, then 1000 + (1000 * 0.05) = 1050
+        assert_eq!(response.penalty, 1050.0);
+        assert_eq!(response.base_penalty, 1200.0);
+        assert!(response.cap_applied);
+        assert_eq!(response.capped_penalty, 1000.0);
+        assert_eq!(response.interest_amount, 50.0);
+        assert!(response.errors.is_empty());
+        assert!(response.explanation.contains("Applied cap"));
+        assert!(response.explanation.contains("Interest"));
+    }
+    #[tokio::test]
+    async fn test_calc_penalty_valuation_date() {
+        let engine = CompatibilityEngine::new();
+        let penalty_as_of = |due_date: Option<&str>, valuation_date: &str| CalcPenaltyParams {
+            days_late: "12".into(),
+            due_date: due_date.map(String::from),
+            valuation_date: Some(valuation_date.to_string()),
+            ..Default::default()
+        };
+
+        // 5% a year on the capped 1000 for the 181 days from 15 January to 15 July
+        let call_result = engine.calc_penalty(Parameters(penalty_as_of(Some("2026-01-15"), "2026-07-15"))).await.unwrap();
+        let response: CalcPenaltyResponse = parse_payload(call_result.content[0].raw.as_text().unwrap().text.as_str());
+        assert_eq!(response.capped_penalty, 1000.0);
+        assert_eq!(response.interest_days, Some(181));
+        assert!((response.interest_amount - 1000.0 * 0.05 * 181.0 / 365.0).abs() < 1e-9);
+        assert_eq!(response.penalty, response.capped_penalty + response.interest_amount);
+        assert!(response.explanation.contains("Interest from 2026-01-15 to 2026-07-15"));
+
+        // Without a due date, interest accrues over the days late
+        let call_result = engine.calc_penalty(Parameters(penalty_as_of(None, "2026-07-15"))).await.unwrap();
+        let response: CalcPenaltyResponse = parse_payload(call_result.content[0].raw.as_text().unwrap().text.as_str());
+        assert_eq!(response.due_date.as_deref(), Some("2026-07-03"));
+        assert_eq!(response.interest_days, Some(12));
+
+        let call_result = engine.calc_penalty(Parameters(penalty_as_of(Some("2026-07-15"), "2026-01-15"))).await.unwrap();
+        assert_eq!(call_result.is_error, Some(true));
+        assert!(call_result.content[0].raw.as_text().unwrap().text.contains("before the due date 2026-07-15"));
+    }
+
+    #[tokio::test]
+    async fn test_calc_tax() {
+        let engine = CompatibilityEngine::new();
+        let params = CalcTaxParams {
+            income: "40000".into(),
+            include_brackets: Some(true.into()),
+            ..Default::default()
+        };
+        
+        let result = engine.calc_tax(Parameters(params)).await;
+        assert!(result.is_ok());
+        
+        let call_result = result.unwrap();
+        let content = call_result.content;
+        let json_text = content[0].raw.as_text().unwrap().text.as_str();
+        let response: CalcTaxResponse = parse_payload(json_text);
+        
+        // Expected: 10000 * 0.10 + 30000 * 0.20 = 1000 + 6000 = 7000
+        // Surcharge: 7000 > 5000 (surcharge_threshold), so 7000 + (7000 * 0.02) = 7,140
+        assert_eq!(response.tax, 7140.0);
+        assert_eq!(response.brackets.len(), 2);
+        assert_eq!((response.brackets[0].lower, response.brackets[0].upper), (0.0, Some(10000.0)));
+        assert_eq!((response.brackets[1].taxable, response.brackets[1].tax), (30000.0, 6000.0));
+        assert_eq!(response.surcharge, SurchargeTotal { applied: true, subtotal: 7000.0, amount: 140.0 });
+        assert!(response.errors.is_empty());
+        assert!(response.explanation.contains("Bracket 1"));
+        assert!(response.explanation.contains("Surcharge applied"));
+    }
+
+    #[tokio::test]
+    async fn test_check_voting_amendment_passes() {
+        let engine = CompatibilityEngine::new();
+        let params = CheckVotingParams {
+            eligible_voters: "100".into(),
+            turnout: "70".into(),
+            yes_votes: "55".into(),
+            proposal_type: "amendment".to_string(),
+            ..Default::default()
+        };
         
-        explanation_parts.push(format!("Remaining after senior: {:.2}", remaining));
+        let result = engine.check_voting(Parameters(params)).await;
+        assert!(result.is_ok());
         
-        // Pay junior debt second
-        let junior_payment = remaining.min(junior_debt);
-        remaining -= junior_payment;
+        let call_result = result.unwrap();
+        let content = call_result.content;
+        let json_text = content[0].raw.as_text().unwrap().text.as_str();
+        let response: CheckVotingResponse = parse_payload(json_text);
         
-        if junior_debt > 0.0 {
-            if junior_payment == junior_debt {
-                explanation_parts.push(format!("Junior debt: {:.2} fully paid", junior_debt));
-            } else if junior_payment > 0.0 {
-                explanation_parts.push(format!("Junior debt: {:.2} partially paid ({:.2} of {:.2})", junior_payment, junior_payment, junior_debt));
-                warnings.push(format!("Junior debt underpaid by {:.2}", junior_debt - junior_payment));
-            } else {
-                explanation_parts.push("Junior debt: no funds available".to_string());
-                warnings.push(format!("Junior debt unpaid ({:.2})", junior_debt));
-            }
-        } else {
-            explanation_parts.push("No junior debt to pay".to_string());
-        }
+        // Expected: turnout = 70%, yes% = 55/70 = 78.6% ≥ 66.67%, passes
+        assert_eq!(response.passes, true);
+        assert!(response.errors.is_empty());
+        assert!(response.explanation.contains("70.0%"));
+        assert!(response.explanation.contains("PASSED"));
+        assert!((response.turnout_percentage - 0.70).abs() < 1e-9);
+        assert!((response.approval_percentage - 55.0 / 70.0).abs() < 1e-9);
+        assert_eq!(response.required_turnout, 0.60);
+        assert!((response.required_approval - 2.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_distribute_waterfall() {
+        let engine = CompatibilityEngine::new();
+        let params = DistributeWaterfallParams {
+            cash_available: "15000000".into(),
+            senior_debt: "8000000".into(),
+            junior_debt: "10000000".into(),
+            ..Default::default()
+        };
         
-        explanation_parts.push(format!("Remaining for equity: {:.2}", remaining));
+        let result = engine.distribute_waterfall(Parameters(params)).await;
+        assert!(result.is_ok());
         
-        // Remainder goes to equity
-        let equity_payment = remaining;
+        let call_result = result.unwrap();
+        let content = call_result.content;
+        let json_text = content[0].raw.as_text().unwrap().text.as_str();
+        let response: DistributeWaterfallResponse = parse_payload(json_text);
         
-        if equity_payment > 0.0 {
-            explanation_parts.push(format!("Equity distribution: {:.2}", equity_payment));
-        } else {
-            explanation_parts.push("No funds available for equity".to_string());
+        // Expected: senior = 8M, junior = 7M, equity = 0
+        assert_eq!(response.distribution.senior, 8_000_000.0);
+        assert_eq!(response.distribution.junior, 7_000_000.0);
+        assert_eq!(response.distribution.equity, 0.0);
+        assert_eq!(response.distribution.senior_shortfall, 0.0);
+        assert_eq!(response.distribution.junior_shortfall, 3_000_000.0);
+        assert_eq!(response.distribution.senior_coverage, 1.0);
+        assert_eq!(response.distribution.junior_coverage, 0.7);
+        assert!(response.errors.is_empty());
+        assert!(response.explanation.contains("Senior debt: 8000000.00 fully paid"));
+        assert!(response.explanation.contains("Junior debt: 7000000.00 partially paid"));
+    }
+
+    #[tokio::test]
+    async fn test_distribute_waterfall_rounding_residual() {
+        let engine = CompatibilityEngine::new();
+        let params = DistributeWaterfallParams {
+            cash_available: "100".into(),
+            senior_debt: "33.333".into(),
+            junior_debt: "33.333".into(),
+            ..Default::default()
+        };
+
+        let result = engine.distribute_waterfall(Parameters(params)).await;
+        assert!(result.is_ok());
+
+        let call_result = result.unwrap();
+        let content = call_result.content;
+        let json_text = content[0].raw.as_text().unwrap().text.as_str();
+        let response: DistributeWaterfallResponse = parse_payload(json_text);
+
+        // Expected: 33.333 / 33.333 / 33.334 rounded to 33.33 / 33.33 / 33.34 (residual cent to equity)
+        assert_eq!(response.distribution.senior, 33.33);
+        assert_eq!(response.distribution.junior, 33.33);
+        assert_eq!(response.distribution.equity, 33.34);
+        let total_cents: i64 = [response.distribution.senior, response.distribution.junior, response.distribution.equity]
+            .iter()
+            .map(|v| (v * 100.0).round() as i64)
+            .sum();
+        assert_eq!(total_cents, 10_000);
+        assert!(response.explanation.contains("largest-remainder"));
+    }
+
+    #[tokio::test]
+    async fn test_distribute_waterfall_residual_never_overpays_debt() {
+        let engine = CompatibilityEngine::new();
+        let params = DistributeWaterfallParams {
+            cash_available: "10".into(),
+            senior_debt: "3.338".into(),
+            junior_debt: "3.331".into(),
+            ..Default::default()
+        };
+
+        let call_result = engine.distribute_waterfall(Parameters(params)).await.unwrap();
+        let json_text = call_result.content[0].raw.as_text().unwrap().text.as_str();
+        let response: DistributeWaterfallResponse = parse_payload(json_text);
+
+        // Senior has the largest discarded fraction (0.8 of a cent), but 3.34 would exceed the
+        // 3.338 owed, so the residual cent goes to equity
+        assert_eq!(response.distribution.senior, 3.33);
+        assert_eq!(response.distribution.junior, 3.33);
+        assert_eq!(response.distribution.equity, 3.34);
+    }
+
+    #[test]
+    fn test_allocate_to_cents_conserves_total() {
+        let unlimited = [f64::INFINITY; 3];
+        let (cents, residual) = CompatibilityEngine::allocate_to_cents(&[10.0 / 3.0, 10.0 / 3.0, 10.0 / 3.0], &unlimited, 10.0);
+        assert_eq!(cents, vec![334, 333, 333]);
+        assert_eq!(residual, 1);
+
+        // A residual cent skips allocations already at their limit
+        let (cents, residual) =
+            CompatibilityEngine::allocate_to_cents(&[10.0 / 3.0, 10.0 / 3.0, 10.0 / 3.0], &[10.0 / 3.0, 10.0 / 3.0, f64::INFINITY], 10.0);
+        assert_eq!(cents, vec![333, 333, 334]);
+        assert_eq!(residual, 1);
+
+        let (cents, residual) = CompatibilityEngine::allocate_to_cents(&[8_000_000.0, 7_000_000.0, 0.0], &unlimited, 15_000_000.0);
+        assert_eq!(cents, vec![800_000_000, 700_000_000, 0]);
+        assert_eq!(residual, 0);
+    }
+
+    #[tokio::test]
+    async fn test_check_covenants_pass() {
+        let engine = CompatibilityEngine::new();
+        let params = CheckCovenantsParams {
+            total_debt: "30,000,000".into(),
+            ebitda: "10000000".into(),
+            interest_expense: "2000000".into(),
+            ..Default::default()
+        };
+
+        let result = engine.check_covenants(Parameters(params)).await;
+        assert!(result.is_ok());
+
+        let call_result = result.unwrap();
+        let content = call_result.content;
+        let json_text = content[0].raw.as_text().unwrap().text.as_str();
+        let response: CheckCovenantsResponse = parse_payload(json_text);
+
+        // Expected with defaults (≤4.0x, ≥2.0x): leverage 3.0x (headroom 1.0), coverage 5.0x (headroom 3.0)
+        assert!(response.all_pass);
+        assert_eq!(response.covenants[0].ratio, Some(3.0));
+        assert_eq!(response.covenants[0].headroom, Some(1.0));
+        assert_eq!(response.covenants[1].ratio, Some(5.0));
+        assert_eq!(response.covenants[1].headroom, Some(3.0));
+        assert!(response.explanation.contains("ALL COVENANTS PASSED"));
+    }
+
+    #[tokio::test]
+    async fn test_check_covenants_breach_with_overrides() {
+        let engine = CompatibilityEngine::new();
+        let params = CheckCovenantsParams {
+            total_debt: "50000000".into(),
+            ebitda: "10000000".into(),
+            interest_expense: "4000000".into(),
+            max_leverage: Some("4.5".into()),
+            min_interest_coverage: Some("3".into()),
+            ..Default::default()
+        };
+
+        let result = engine.check_covenants(Parameters(params)).await;
+        assert!(result.is_ok());
+
+        let call_result = result.unwrap();
+        let content = call_result.content;
+        let json_text = content[0].raw.as_text().unwrap().text.as_str();
+        let response: CheckCovenantsResponse = parse_payload(json_text);
+
+        // Expected: leverage 5.0x > 4.5x (headroom -0.5), coverage 2.5x < 3.0x (headroom -0.5)
+        assert!(!response.all_pass);
+        assert!(!response.covenants[0].passes);
+        assert_eq!(response.covenants[0].headroom, Some(-0.5));
+        assert!(!response.covenants[1].passes);
+        assert_eq!(response.covenants[1].headroom, Some(-0.5));
+        assert!(response.explanation.contains("COVENANT BREACH"));
+    }
+
+    #[tokio::test]
+    async fn test_check_covenants_negative_ebitda() {
+        let engine = CompatibilityEngine::new();
+        let params = CheckCovenantsParams {
+            total_debt: "1000000".into(),
+            ebitda: "-250000".into(),
+            interest_expense: "0".into(),
+            ..Default::default()
+        };
+
+        let result = engine.check_covenants(Parameters(params)).await;
+        let call_result = result.unwrap();
+        let content = call_result.content;
+        let json_text = content[0].raw.as_text().unwrap().text.as_str();
+        let response: CheckCovenantsResponse = parse_payload(json_text);
+
+        assert!(!response.all_pass);
+        assert_eq!(response.covenants[0].ratio, None);
+        assert!(response.covenants[1].passes);
+        assert!(response.warnings.iter().any(|w| w.contains("Non-positive EBITDA")));
+    }
+
+    #[tokio::test]
+    async fn test_calc_npv() {
+        let engine = CompatibilityEngine::new();
+        let params = CalcNpvParams {
+            cash_flows: vec!["-1000".into(), "550".into(), "605".into()],
+            discount_rate: "0.10".into(),
+            ..Default::default()
+        };
+
+        let result = engine.calc_npv(Parameters(params)).await;
+        assert!(result.is_ok());
+
+        let call_result = result.unwrap();
+        let content = call_result.content;
+        let json_text = content[0].raw.as_text().unwrap().text.as_str();
+        let response: CalcNpvResponse = parse_payload(json_text);
+
+        // Expected: -1000 + 550/1.1 + 605/1.21 = -1000 + 500 + 500 = 0
+        assert!(response.npv.abs() < 1e-9);
+        assert_eq!(response.present_values.len(), 3);
+        assert!((response.present_values[1] - 500.0).abs() < 1e-9);
+        assert!(response.explanation.contains("Period 2"));
+    }
+
+    #[tokio::test]
+    async fn test_calc_irr() {
+        let engine = CompatibilityEngine::new();
+        let params = CalcIrrParams {
+            cash_flows: vec!["-1000".into(), "550".into(), "605".into()],
+            ..Default::default()
+        };
+
+        let result = engine.calc_irr(Parameters(params)).await;
+        assert!(result.is_ok());
+
+        let call_result = result.unwrap();
+        let content = call_result.content;
+        let json_text = content[0].raw.as_text().unwrap().text.as_str();
+        let response: CalcIrrResponse = parse_payload(json_text);
+
+        // Expected: 10% (the NPV test above is zero at 10%)
+        assert!((response.irr - 0.10).abs() < 1e-6);
+        assert!(response.npv_at_irr.abs() < 1e-4);
+        assert!(response.iterations > 0);
+        assert!(response.explanation.contains("converged"));
+    }
+
+    #[tokio::test]
+    async fn test_calc_irr_requires_sign_change() {
+        let engine = CompatibilityEngine::new();
+        let params = CalcIrrParams {
+            cash_flows: vec!["100".into(), "200".into()],
+            ..Default::default()
+        };
+
+        let result = engine.calc_irr(Parameters(params)).await;
+        let call_result = result.unwrap();
+        assert!(call_result.is_error.unwrap_or(false));
+        let content = call_result.content;
+        let error_text = content[0].raw.as_text().unwrap().text.as_str();
+        assert!(error_text.contains("sign change"));
+    }
+
+    #[test]
+    fn test_cash_flows_deserialization() {
+        let flows = |params: CalcIrrParams| params.cash_flows.into_iter().map(|flow| flow.0).collect::<Vec<_>>();
+        let params: CalcIrrParams = serde_json::from_str(r#"{"cash_flows": [-1000, "550", 605.5]}"#).unwrap();
+        assert_eq!(flows(params), vec![Ok(-1000.0), Ok(550.0), Ok(605.5)]);
+
+        // Stringified arrays are accepted too
+        let params: CalcIrrParams = serde_json::from_str(r#"{"cash_flows": "[-1000, 550]"}"#).unwrap();
+        assert_eq!(flows(params), vec![Ok(-1000.0), Ok(550.0)]);
+    }
+
+    fn escrow_milestone(name: &str, completed: bool, release_percentage: &str) -> EscrowMilestone {
+        EscrowMilestone {
+            name: name.to_string(),
+            completed: completed.into(),
+            release_percentage: release_percentage.into(),
         }
+    }
+
+    #[tokio::test]
+    async fn test_check_escrow_release_partial() {
+        let engine = CompatibilityEngine::new();
+        let params = CheckEscrowReleaseParams {
+            escrow_amount: "100000".into(),
+            milestones: vec![
+                escrow_milestone("permits", true, "0.25"),
+                escrow_milestone("construction", true, "0.5"),
+                escrow_milestone("handover", false, "0.25"),
+            ],
+            release_date: Some("2026-01-01".to_string()),
+            as_of_date: Some("2026-03-01".to_string()),
+            already_released: Some("25000".into()),
+            ..Default::default()
+        };
+
+        let result = engine.check_escrow_release(Parameters(params)).await;
+        assert!(result.is_ok());
+
+        let call_result = result.unwrap();
+        let content = call_result.content;
+        let json_text = content[0].raw.as_text().unwrap().text.as_str();
+        let response: CheckEscrowReleaseResponse = parse_payload(json_text);
+
+        // Expected: 75% cumulative = 75000, less 25000 already released = 50000 now, 25000 remaining
+        assert_eq!(response.released_percentage, 0.75);
+        assert_eq!(response.release_amount, 50_000.0);
+        assert_eq!(response.remaining_in_escrow, 25_000.0);
+        assert_eq!(response.blocking_conditions, vec!["Milestone 'handover' not completed"]);
+        assert!(!response.milestones[2].released);
+    }
+
+    #[tokio::test]
+    async fn test_check_escrow_release_blocked_by_dispute_and_date() {
+        let engine = CompatibilityEngine::new();
+        let params = CheckEscrowReleaseParams {
+            escrow_amount: "100000".into(),
+            milestones: vec![escrow_milestone("delivery", true, "1.0")],
+            release_date: Some("2026-06-30".to_string()),
+            as_of_date: Some("2026-03-01".to_string()),
+            dispute_pending: Some("yes".into()),
+            ..Default::default()
+        };
+
+        let result = engine.check_escrow_release(Parameters(params)).await;
+        let call_result = result.unwrap();
+        let content = call_result.content;
+        let json_text = content[0].raw.as_text().unwrap().text.as_str();
+        let response: CheckEscrowReleaseResponse = parse_payload(json_text);
+
+        assert_eq!(response.release_amount, 0.0);
+        assert_eq!(response.remaining_in_escrow, 100_000.0);
+        assert_eq!(response.blocking_conditions.len(), 2);
+        assert!(response.blocking_conditions[0].contains("Dispute pending"));
+        assert!(response.blocking_conditions[1].contains("Release date not reached"));
+        assert!(response.explanation.contains("COMPLETED, BLOCKED"));
+    }
+
+    #[tokio::test]
+    async fn test_check_escrow_release_schedule_over_100_percent() {
+        let engine = CompatibilityEngine::new();
+        let params = CheckEscrowReleaseParams {
+            escrow_amount: "1000".into(),
+            milestones: vec![
+                escrow_milestone("a", true, "0.6"),
+                escrow_milestone("b", true, "0.6"),
+            ],
+            ..Default::default()
+        };
+
+        let result = engine.check_escrow_release(Parameters(params)).await;
+        let call_result = result.unwrap();
+        assert!(call_result.is_error.unwrap_or(false));
+        let content = call_result.content;
+        let error_text = content[0].raw.as_text().unwrap().text.as_str();
+        assert!(error_text.contains("exceeds 100%"));
+    }
+
+    #[tokio::test]
+    async fn test_check_housing_grant_eligible() {
+        let engine = CompatibilityEngine::new();
+        let params = CheckHousingGrantParams {
+            ami: "50000".into(),
+            household_size: "5".into(),
+            income: "32000".into(),
+            has_other_subsidy: "false".into(),
+            ..Default::default()
+        };
         
-        let total_debt = senior_debt + junior_debt;
-        if cash_available < total_debt {
-            warnings.push(format!("Insufficient cash: {:.2} available vs {:.2} total debt", cash_available, total_debt));
-        }
+        let result = engine.check_housing_grant(Parameters(params)).await;
+        assert!(result.is_ok());
         
-        DistributeWaterfallResponse {
-            distribution: DistributeWaterfallResult {
-                senior: senior_payment,
-                junior: junior_payment,
-                equity: equity_payment,
-            },
-            explanation: explanation_parts.join(". "),
-            errors,
-            warnings,
+        let call_result = result.unwrap();
+        let content = call_result.content;
+        let json_text = content[0].raw.as_text().unwrap().text.as_str();
+        let response: CheckHousingGrantResponse = parse_payload(json_text);
+        
+        // Expected: threshold = 0.60 * 50000 * 1.10 = 33000, income 32000 ≤ 33000, eligible
+        assert_eq!(response.eligible, true);
+        assert!(response.errors.is_empty());
+        assert!(response.explanation.contains("5 > 4, threshold increased by 10%"));
+        assert!(response.explanation.contains("ELIGIBLE"));
+    }
+
+    #[tokio::test]
+    async fn test_check_housing_grant_citizen_audience() {
+        let engine = CompatibilityEngine::new();
+        let params = CheckHousingGrantParams {
+            ami: "50000".into(),
+            household_size: "5".into(),
+            income: "32000".into(),
+            has_other_subsidy: "false".into(),
+            audience: Some("citizen".to_string()),
+            ..Default::default()
+        };
+
+        let call_result = engine.check_housing_grant(Parameters(params)).await.unwrap();
+        let json_text = call_result.content[0].raw.as_text().unwrap().text.as_str();
+        let response: CheckHousingGrantResponse = parse_payload(json_text);
+
+        assert!(response.eligible);
+        assert!(response.explanation.contains("the limit is raised by 10 percent to 33000.00"));
+        assert!(response.explanation.ends_with("You are eligible"));
+        for symbol in ["≤", ">", "%"] {
+            assert!(!response.explanation.contains(symbol));
         }
     }
 
-    /// Check housing grant eligibility
-    fn check_housing_grant_internal(
-        ami: f64,
-        household_size: i32,
-        income: f64,
-        has_other_subsidy: bool,
-    ) -> CheckHousingGrantResponse {
-        let mut errors = Vec::new();
-        let mut additional_requirements = Vec::new();
-        let mut explanation_parts = Vec::new();
-        
-        // Validation
-        if ami <= 0.0 {
-            errors.push("Area Median Income (AMI) must be positive".to_string());
+    #[tokio::test]
+    async fn test_assumptions_reported() {
+        let engine = CompatibilityEngine::new();
+        let params = CalcPenaltyParams {
+            days_late: "1,000".into(),
+            rate_per_day: Some("$2".into()),
+            interest_rate: Some("5%".into()),
+            ..Default::default()
+        };
+
+        let call_result = engine.calc_penalty(Parameters(params)).await.unwrap();
+        let json_text = call_result.content[0].raw.as_text().unwrap().text.as_str();
+        let response: CalcPenaltyResponse = parse_payload(json_text);
+
+        let codes: Vec<(&str, &str)> = response
+            .assumptions
+            .iter()
+            .map(|a| (a.code.as_str(), a.field.as_str()))
+            .collect();
+        assert_eq!(
+            codes,
+            vec![
+                ("comma_removed", "days_late"),
+                ("currency_symbol_stripped", "rate_per_day"),
+                ("default_applied", "cap"),
+                ("percent_sign_stripped", "interest_rate"),
+            ]
+        );
+        assert!(response.assumptions[0].message.contains("thousands separators removed"));
+        assert!(response.assumptions[3].message.contains("may have been meant as 0.05"));
+    }
+
+    #[tokio::test]
+    async fn test_assumptions_ambiguous_comma_and_boolean() {
+        let engine = CompatibilityEngine::new();
+        let params = CheckHousingGrantParams {
+            ami: "50000".into(),
+            household_size: "3".into(),
+            income: "1,5".into(),
+            has_other_subsidy: "no".into(),
+            ..Default::default()
+        };
+
+        let call_result = engine.check_housing_grant(Parameters(params)).await.unwrap();
+        let json_text = call_result.content[0].raw.as_text().unwrap().text.as_str();
+        let response: CheckHousingGrantResponse = parse_payload(json_text);
+
+        assert_eq!(response.assumptions.len(), 2);
+        assert_eq!(response.assumptions[0].code, "comma_removed");
+        assert!(response.assumptions[0].message.contains("not decimal separators"));
+        assert_eq!(response.assumptions[1].code, "boolean_interpreted");
+        assert_eq!(response.assumptions[1].message, "'no' read as false");
+    }
+
+    #[tokio::test]
+    async fn test_invalid_audience() {
+        let engine = CompatibilityEngine::new();
+        let params = CalcNpvParams {
+            cash_flows: vec!["-100".into(), "110".into()],
+            discount_rate: "0.1".into(),
+            audience: Some("children".to_string()),
+            ..Default::default()
+        };
+
+        let call_result = engine.calc_npv(Parameters(params)).await.unwrap();
+        assert!(call_result.is_error.unwrap_or(false));
+        let text = call_result.content[0].raw.as_text().unwrap().text.as_str();
+        assert!(text.contains("Invalid audience parameter"));
+    }
+
+    #[tokio::test]
+    async fn test_include_explanations_false() {
+        let engine = CompatibilityEngine::new();
+        let call = |arguments: serde_json::Value| {
+            let params: CalcTaxParams = serde_json::from_value(arguments).unwrap();
+            engine.calc_tax(Parameters(params))
+        };
+        let envelope = |result: CallToolResult| -> serde_json::Value {
+            serde_json::from_str(&result.content[0].raw.as_text().unwrap().text).unwrap()
+        };
+
+        let full = envelope(call(serde_json::json!({"income": 45000, "include_brackets": true})).await.unwrap());
+        let bare = envelope(call(serde_json::json!({"income": 45000, "include_brackets": true, "include_explanations": false})).await.unwrap());
+        assert!(!full["payload"]["explanation"].as_str().unwrap().is_empty());
+        assert_eq!(bare["payload"]["explanation"], "");
+        assert_eq!(bare["payload"]["tax"], full["payload"]["tax"]);
+        assert_eq!(bare["payload"]["brackets"], full["payload"]["brackets"]);
+        assert_eq!(bare["input_hash"], full["input_hash"]);
+
+        let result = call(serde_json::json!({"income": 45000, "include_explanations": false, "explanation_graph": "mermaid"}))
+            .await
+            .unwrap();
+        assert!(result.is_error.unwrap_or(false));
+        assert!(result.content[0].raw.as_text().unwrap().text.contains("cannot be false with explanation_graph"));
+        let result = call(serde_json::json!({"income": 45000, "include_explanations": "maybe"})).await.unwrap();
+        assert!(result.is_error.unwrap_or(false));
+
+        // Templates are not rendered at all, and the setting ends with the body
+        let rendered = explanations::with_explanations(false, || explain(Audience::Expert, "calc_tax.subtotal", context! { tax => 1.0 }));
+        assert_eq!(rendered, "");
+        assert!(!explain(Audience::Expert, "calc_tax.subtotal", context! { tax => 1.0 }).is_empty());
+    }
+
+    /// `cargo test --release -- --ignored bench_ --nocapture`
+    #[tokio::test]
+    #[ignore]
+    async fn bench_include_explanations() {
+        const CALLS: u32 = 2000;
+        let rules = tax_rules();
+        for include in [true, false] {
+            let started = std::time::Instant::now();
+            for i in 0..CALLS {
+                let income = 1000.0 + f64::from(i) * 97.0;
+                let response = explanations::with_explanations(include, || {
+                    CompatibilityEngine::calc_tax_internal(income, 0.0, 0.0, &rules, true, Audience::Expert)
+                });
+                std::hint::black_box(response);
+            }
+            println!("calc_tax_internal, include_explanations {}: {:?} for {} calls", include, started.elapsed(), CALLS);
         }
-        if household_size <= 0 {
-            errors.push("Household size must be positive".to_string());
+
+        let engine = CompatibilityEngine::new();
+        for include in [true, false] {
+            let started = std::time::Instant::now();
+            for i in 0..CALLS {
+                let arguments = serde_json::json!({"income": 1000 + i * 97, "include_explanations": include, "response_version": 1});
+                let params: CalcTaxParams = serde_json::from_value(arguments).unwrap();
+                std::hint::black_box(engine.calc_tax(Parameters(params)).await.unwrap());
+            }
+            println!("calc_tax tool, include_explanations {}: {:?} for {} calls", include, started.elapsed(), CALLS);
         }
-        if income < 0.0 {
-            errors.push("Income cannot be negative".to_string());
+    }
+
+    #[test]
+    fn test_check_housing_grant_equivalence_scale() {
+        let check = |household_size, children, income, scale| {
+            CompatibilityEngine::check_housing_grant_internal(50000.0, household_size, children, income, false, scale, Audience::Expert)
+        };
+
+        // Size rule: the same limit for 1 to 4 people, 10% more above
+        let response = check(5, 3, 32000.0, EquivalenceScale::Size);
+        assert!(response.eligible);
+        assert!((response.threshold - 33000.0).abs() < 1e-9);
+        assert_eq!(response.adult_equivalents, None);
+
+        // OECD-modified: 2 adults and 2 children are 2.1 adult equivalents and get 60% of AMI
+        let response = check(4, 2, 30000.0, EquivalenceScale::OecdModified);
+        assert!((response.adult_equivalents.unwrap() - 2.1).abs() < 1e-9);
+        assert!((response.threshold - 30000.0).abs() < 1e-9 && response.eligible);
+        assert!((response.equivalized_income.unwrap() - 30000.0 / 2.1).abs() < 1e-9);
+        assert!(response.explanation.contains("Equivalence scale oecd_modified: 4 people (2 children) = 2.10 adult equivalents"));
+        // A single adult: 1 / 2.1 of the reference limit
+        let response = check(1, 0, 15000.0, EquivalenceScale::OecdModified);
+        assert!((response.threshold - 30000.0 / 2.1).abs() < 1e-9);
+        assert!(!response.eligible);
+        assert!((response.ami_percentage_used - 0.60 / 2.1).abs() < 1e-9);
+        // Children count for less than adults
+        assert!(check(4, 3, 0.0, EquivalenceScale::Oecd).threshold < check(4, 0, 0.0, EquivalenceScale::Oecd).threshold);
+        let response = check(9, 0, 0.0, EquivalenceScale::SquareRoot);
+        assert!((response.adult_equivalents.unwrap() - 3.0).abs() < 1e-9);
+        assert!((response.threshold - 45000.0).abs() < 1e-9);
+
+        let response = check(2, 2, 1000.0, EquivalenceScale::OecdModified);
+        assert!(!response.errors.is_empty() && !response.eligible);
+        assert_eq!(EquivalenceScale::parse(" OECD-Modified "), Ok(EquivalenceScale::OecdModified));
+        assert!(EquivalenceScale::parse("per_capita").is_err());
+    }
+
+    #[test]
+    fn test_check_appeal_deadline() {
+        let date = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+        let holidays = BTreeSet::from([date("2026-04-06")]);
+        let check = |decision: &str, filing: &str, window_days, business_days| {
+            CompatibilityEngine::check_appeal_deadline_internal(date(decision), date(filing), window_days, business_days, &holidays, Audience::Expert)
+        };
+
+        // 30 calendar days after Monday 2 March is Wednesday 1 April
+        let response = check("2026-03-02", "2026-03-30", 30, false);
+        assert!(response.within_window);
+        assert_eq!((response.deadline.as_str(), response.days_remaining, response.days_overdue), ("2026-04-01", 2, 0));
+        assert!(!response.deadline_extended);
+        assert!(response.warnings.iter().any(|warning| warning.contains("2 day(s) left")));
+
+        // A deadline on Saturday 4 April moves past the weekend and the Monday holiday
+        let response = check("2026-03-05", "2026-04-07", 30, false);
+        assert_eq!(response.deadline, "2026-04-07");
+        assert!(response.deadline_extended && response.within_window);
+        assert!(response.explanation.contains("2026-04-04 is not a business day"), "{}", response.explanation);
+
+        // 10 business days after Friday 27 March skip two weekends and the holiday
+        let response = check("2026-03-27", "2026-04-16", 10, true);
+        assert_eq!(response.deadline, "2026-04-13");
+        assert!(!response.within_window);
+        assert_eq!((response.days_remaining, response.days_overdue), (0, 3));
+        assert!(response.explanation.contains("3 days after the deadline"));
+
+        assert!(!check("2026-03-02", "2026-03-01", 30, false).errors.is_empty());
+        assert!(!check("2026-03-02", "2026-03-03", 0, true).errors.is_empty());
+    }
+
+    #[test]
+    fn test_get_required_documents() {
+        let checklists = EngineConfig::parse_required_documents(
+            r#"{"Housing_Grant": [
+                {"document": "Proof of income"},
+                {"document": "Tax returns", "when": "Self-Employed"},
+                {"document": "Residence permit", "when": "non_resident"}
+            ]}"#,
+        )
+        .unwrap();
+        let situation = vec!["self_employed".to_string(), "student".to_string()];
+        let response = CompatibilityEngine::get_required_documents_internal("housing_grant", &situation, &checklists, Audience::Expert);
+        assert!(response.errors.is_empty());
+        let documents: Vec<(&str, Option<&str>)> =
+            response.documents.iter().map(|item| (item.document.as_str(), item.situation.as_deref())).collect();
+        assert_eq!(documents, [("Proof of income", None), ("Tax returns", Some("self_employed"))]);
+        assert_eq!(response.known_situations, ["non_resident", "self_employed"]);
+        assert!(response.warnings.iter().any(|warning| warning.contains("'student'")));
+        assert!(response.explanation.contains("2 documents"), "{}", response.explanation);
+
+        let response = CompatibilityEngine::get_required_documents_internal("pension", &[], &checklists, Audience::Expert);
+        assert!(response.errors[0].contains("Unknown program 'pension' (expected one of: housing_grant)"));
+        assert!(EngineConfig::parse_required_documents(r#"{"housing_grant": []}"#).is_err());
+        assert!(EngineConfig::parse_required_documents(r#"{"housing_grant": [{"document": " "}]}"#).is_err());
+    }
+
+    #[test]
+    fn test_check_voting_bulk() {
+        let precinct = |name: &str, eligible_voters, turnout, yes_votes| (name.to_string(), eligible_voters, turnout, yes_votes);
+        let precincts = [precinct("North", 60, 45, 30), precinct("South", 40, 20, 12)];
+        let response = CompatibilityEngine::check_voting_bulk_internal(&precincts, "general", Audience::Expert);
+        assert!(response.errors.is_empty());
+        // 65 of 100 voted and 42 of 65 voted yes, although South alone had only 50% turnout
+        assert!(response.passes);
+        assert_eq!((response.eligible_voters, response.turnout, response.yes_votes), (100, 65, 42));
+        assert_eq!(response.turnout_percentage, 0.65);
+        let quorum: Vec<bool> = response.precincts.iter().map(|precinct| precinct.met_quorum).collect();
+        assert_eq!(quorum, [true, false]);
+        assert_eq!(response.precincts_meeting_quorum, 1);
+        assert_eq!(response.precincts[1].approval_percentage, 0.6);
+        assert!(response.explanation.contains("1 of 2 (below: South)"), "{}", response.explanation);
+
+        // The totals decide: an amendment needs two thirds of all votes cast
+        let response = CompatibilityEngine::check_voting_bulk_internal(&precincts, "amendment", Audience::Expert);
+        assert!(!response.passes);
+
+        let response = CompatibilityEngine::check_voting_bulk_internal(
+            &[precinct("North", 60, 45, 30), precinct("North", 40, 50, 12)],
+            "general",
+            Audience::Expert,
+        );
+        assert!(!response.passes);
+        assert_eq!(response.errors, [
+            "Precinct 'North' is listed more than once",
+            "Precinct 'North': turnout cannot exceed eligible voters",
+        ]);
+        let response = CompatibilityEngine::check_voting_bulk_internal(&[], "general", Audience::Expert);
+        assert_eq!(response.errors, ["At least one precinct is required"]);
+        let response = CompatibilityEngine::check_voting_bulk_internal(&precincts, "bylaw", Audience::Expert);
+        assert!(response.errors[0].contains("Invalid proposal type 'bylaw'"));
+    }
+
+    #[test]
+    fn test_project_quorum() {
+        let response = CompatibilityEngine::project_quorum_internal(200, 90, 50, Some((6.0, 2.0)), Audience::Expert);
+        assert!(response.errors.is_empty());
+        assert!(!response.quorum_met);
+        assert_eq!((response.quorum_votes, response.additional_votes_needed, response.outstanding_voters), (120, 30, 110));
+        // 15 votes an hour for 2 more hours reaches 120 exactly
+        assert_eq!(response.turnout_rate_per_hour, Some(15.0));
+        assert_eq!(response.projected_turnout, Some(120));
+        assert_eq!(response.projected_quorum_met, Some(true));
+        assert_eq!(response.turnout_basis, 120);
+        let minimums: Vec<(i32, i32)> =
+            response.thresholds.iter().map(|threshold| (threshold.min_yes_votes, threshold.additional_yes_votes)).collect();
+        assert_eq!(minimums, [(61, 11), (80, 30)]);
+
+        // The minimums agree with check_voting at the basis turnout, one vote less fails
+        for (threshold, proposal_type) in response.thresholds.iter().zip(["general", "amendment"]) {
+            let passing = CompatibilityEngine::check_voting_internal(200, 120, threshold.min_yes_votes, proposal_type, Audience::Expert);
+            let failing = CompatibilityEngine::check_voting_internal(200, 120, threshold.min_yes_votes - 1, proposal_type, Audience::Expert);
+            assert!(passing.passes && !failing.passes, "{}", proposal_type);
         }
-        
-        if !errors.is_empty() {
-            return CheckHousingGrantResponse {
-                eligible: false,
-                explanation: "Housing grant eligibility check failed due to invalid inputs".to_string(),
-                errors,
-                additional_requirements,
-            };
+
+        // Quorum already met: the minimums assume the turnout so far
+        let response = CompatibilityEngine::project_quorum_internal(7, 5, 0, None, Audience::Expert);
+        assert!(response.quorum_met);
+        assert_eq!((response.quorum_votes, response.turnout_basis), (5, 5));
+        assert_eq!(response.projected_turnout, None);
+        assert_eq!(response.thresholds[1].min_yes_votes, 4);
+
+        let response = CompatibilityEngine::project_quorum_internal(100, 20, 0, Some((4.0, 1.0)), Audience::Expert);
+        assert_eq!(response.projected_quorum_met, Some(false));
+        assert!(response.warnings[0].contains("projected to reach 25"));
+        let response = CompatibilityEngine::project_quorum_internal(100, 120, 0, Some((0.0, 1.0)), Audience::Expert);
+        assert_eq!(response.errors, ["Turnout cannot exceed eligible voters", "Hours elapsed must be positive"]);
+    }
+
+    #[test]
+    fn test_stress_config() {
+        let response = CompatibilityEngine::stress_config_internal(None, Audience::Expert);
+        assert!(response.errors.is_empty());
+        assert!(response.passed, "{:?}", response.checks);
+        assert_eq!(response.source, "loaded configuration");
+        let names: Vec<&str> = response.checks.iter().map(|check| check.name.as_str()).collect();
+        assert_eq!(names, ["tax_brackets", "tax_brackets_100", "penalty_10000_days"]);
+        assert_eq!(response.checks[2].cases, 8);
+        assert!(response.checks.iter().all(|check| check.max_deviation < 0.01));
+
+        let candidate = r#"[{"up_to": 10000, "rate": 0.1}, {"up_to": 50000, "rate": 0.3, "cliff": true}, {"rate": 0.45}]"#;
+        let response = CompatibilityEngine::stress_config_internal(Some(candidate), Audience::Expert);
+        assert!(response.passed, "{:?}", response.checks);
+        assert_eq!((response.source.as_str(), response.brackets), ("tax_brackets parameter", 3));
+        // 0, 0.01, both thresholds and a cent either side, and the powers of ten from 1e3 to 1e12
+        // (1e4 being a threshold)
+        assert_eq!(response.checks[0].cases, 2 + 6 + 10 - 1);
+
+        let response = CompatibilityEngine::stress_config_internal(Some(r#"[{"up_to": 10000, "rate": 0.1}]"#), Audience::Expert);
+        assert!(response.errors[0].starts_with("Invalid tax_brackets: bracket 1 is the top bracket"));
+
+        // A table the loader would reject fails the check with the cases it breaks
+        let check = CompatibilityEngine::stress_brackets("tax_brackets", &[1000.0], &[0.1, -0.2], &[false, false], Audience::Expert);
+        assert!(!check.passed);
+        assert!(check.failures.iter().any(|failure| failure.contains("-1700 is outside 0 to 1000.00")), "{:?}", check.failures);
+        assert_eq!(check.failures.len(), MAX_STRESS_FAILURES + 1);
+        assert!(check.failures[MAX_STRESS_FAILURES].starts_with("and "));
+        let check = CompatibilityEngine::stress_brackets("tax_brackets", &[1000.0], &[0.1], &[false], Audience::Expert);
+        assert_eq!(check.failures, ["1 rates for 1 thresholds (expected 2)"]);
+    }
+
+    #[test]
+    fn test_verify_store_integrity() {
+        let integrity = StoreIntegrity {
+            schema_version: 2,
+            database_check: "ok".to_string(),
+            encryption: true,
+            current_key: Some("2026".to_string()),
+            records: 5,
+            plaintext_records: 1,
+            records_by_key: BTreeMap::from([("2025".to_string(), 1), ("2026".to_string(), 3)]),
+            ..StoreIntegrity::default()
+        };
+        let response = CompatibilityEngine::verify_store_integrity_internal(Ok(integrity.clone()), Audience::Expert);
+        assert!(response.intact && response.errors.is_empty());
+        assert_eq!(response.warnings.len(), 2);
+        assert!(response.warnings[0].starts_with("1 results are stored unencrypted"));
+        assert!(response.warnings[1].starts_with("1 results have data keys wrapped with an older key"));
+        assert!(response.explanation.contains("4 encrypted (current key 2026), 1 unencrypted"), "{}", response.explanation);
+
+        let failing = StoreIntegrity { failed_records: 1, failures: vec!["r1: payload does not authenticate".to_string()], ..integrity };
+        let response = CompatibilityEngine::verify_store_integrity_internal(Ok(failing), Audience::Citizen);
+        assert!(!response.intact);
+        assert_eq!(response.explanation, "The store has problems: 1 of 5 records cannot be read");
+
+        let response = CompatibilityEngine::verify_store_integrity_internal(Err("unavailable".to_string()), Audience::Expert);
+        assert_eq!((response.intact, response.errors), (false, vec!["unavailable".to_string()]));
+    }
+
+    #[test]
+    fn test_purge_expired_data() {
+        let now = clock::now();
+        let purged = [retention::Purged {
+            category: retention::Category::Results,
+            retention_days: 30,
+            cutoff: now - chrono::TimeDelta::days(30),
+            deleted: 4,
+            deleted_stored: Some(6),
+        }];
+        let response = CompatibilityEngine::purge_expired_data_internal(&purged, Audience::Expert);
+        assert_eq!(response.purged.len(), 1);
+        assert_eq!((response.purged[0].deleted, response.purged[0].deleted_stored), (4, Some(6)));
+        assert_eq!(response.kept, ["usage"]);
+        assert!(response.explanation.starts_with("results older than 30 days"), "{}", response.explanation);
+        assert!(response.explanation.contains("4 deleted, 6 from the embedded store. Kept without a period: usage"), "{}", response.explanation);
+        assert!(response.warnings.is_empty());
+
+        let response = CompatibilityEngine::purge_expired_data_internal(&[], Audience::Citizen);
+        assert_eq!(response.kept, ["results", "usage"]);
+        assert!(response.warnings[0].starts_with("No retention periods are configured"));
+        assert_eq!(response.explanation, "No retention periods are set, so nothing was deleted");
+    }
+
+    #[test]
+    fn test_subject_data_reports() {
+        let stored_at = clock::now();
+        let found = result_store::CaseResults {
+            results: vec![result_store::StoredResult {
+                id: "3f9a1c2e5b7d".to_string(),
+                tool: "calc_tax".to_string(),
+                stored_at,
+                payload: serde_json::json!({ "tax": 7140.0 }),
+            }],
+            unreadable: vec!["0a1b2c3d4e5f: key '2025' is not configured".to_string()],
+            searched_stored: cfg!(feature = "embedded-store"),
+        };
+        let response = CompatibilityEngine::export_subject_data_internal("CASE-7", found, Audience::Expert);
+        assert_eq!((response.case_id.as_str(), response.records.len()), ("CASE-7", 1));
+        assert_eq!(response.records[0].stored_at, stored_at.to_rfc3339());
+        assert_eq!(response.warnings, ["1 stored results of the case could not be read and are not exported"]);
+        assert!(response.explanation.starts_with("1 stored results of the case (calc_tax) found in memory"), "{}", response.explanation);
+
+        let erasure = result_store::CaseErasure {
+            ids: vec!["0a1b2c3d4e5f".to_string(), "3f9a1c2e5b7d".to_string()],
+            erased: 1,
+            erased_stored: Some(2),
+            shredded: Some(1),
+        };
+        let response = CompatibilityEngine::erase_subject_data_internal("CASE-7", erasure, stored_at, Audience::Expert);
+        let receipt = &response.receipt;
+        assert_eq!((receipt.erased, receipt.erased_in_memory, receipt.erased_stored, receipt.shredded), (2, 1, Some(2), Some(1)));
+        assert_eq!(receipt.case_hash, result_store::case_hash("CASE-7"));
+        assert_eq!(receipt.result_ids_digest, format!("{:x}", Sha256::digest("0a1b2c3d4e5f\n3f9a1c2e5b7d")));
+        assert!(receipt.complete && response.warnings.is_empty());
+        assert_eq!(response.stores_searched, ["memory", "embedded_store"]);
+        assert!(response.explanation.contains("2 stored results erased (1 from memory, 2 from the embedded store, 1 with their data keys destroyed)"), "{}", response.explanation);
+
+        let response = CompatibilityEngine::erase_subject_data_internal("CASE-8", result_store::CaseErasure::default(), stored_at, Audience::Citizen);
+        assert_eq!(response.explanation, "No saved results were found for this case, so nothing was deleted");
+        assert_eq!(response.receipt.complete, !cfg!(feature = "embedded-store"));
+
+        assert!(parse_case_id(&"x".repeat(101)).unwrap_err().contains("max 100 characters"));
+        assert!(parse_case_id("CASE\t7").unwrap_err().contains("control characters"));
+    }
+
+    #[tokio::test]
+    async fn test_subject_data_round_trip() {
+        // The admin tools are disabled in tests, so the stored results are read and erased directly
+        let engine = CompatibilityEngine::new();
+        let case_id = format!("CASE-{}", uuid::Uuid::new_v4());
+        for income in [40000, 52000] {
+            let params: CalcTaxParams = serde_json::from_value(serde_json::json!({
+                "income": income,
+                "context": { "case_id": case_id },
+            }))
+            .unwrap();
+            engine.calc_tax(Parameters(params)).await.unwrap();
         }
+        engine.calc_tax(Parameters(serde_json::from_str(r#"{"income": 60000}"#).unwrap())).await.unwrap();
+
+        let export = CompatibilityEngine::export_subject_data_internal(&case_id, result_store::case_results(&case_id), Audience::Expert);
+        assert_eq!(export.records.len(), 2);
+        assert_eq!((export.records[0].tool.as_str(), &export.records[1].payload["tax"]), ("calc_tax", &serde_json::json!(9588.0)));
+        let reference = format!("$result:{}.tax", export.records[0].result_id);
+        assert!(result_store::resolve(&reference).unwrap().is_ok());
+
+        let erasure = CompatibilityEngine::erase_subject_data_internal(&case_id, result_store::erase_case(&case_id), clock::now(), Audience::Expert);
+        assert_eq!(erasure.receipt.erased, 2);
+        assert!(result_store::resolve(&reference).unwrap().is_err());
+
+        let export = CompatibilityEngine::export_subject_data_internal(&case_id, result_store::case_results(&case_id), Audience::Expert);
+        assert!(export.records.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_check_housing_grant_not_eligible_income() {
+        let engine = CompatibilityEngine::new();
+        let params = CheckHousingGrantParams {
+            ami: "50000".into(),
+            household_size: "5".into(),
+            income: "34000".into(),
+            has_other_subsidy: "false".into(),
+            ..Default::default()
+        };
         
-        explanation_parts.push(format!("Area Median Income (AMI): {:.2}", ami));
-        explanation_parts.push(format!("Household size: {}", household_size));
-        explanation_parts.push(format!("Household income: {:.2}", income));
-        explanation_parts.push(format!("Has other subsidy: {}", if has_other_subsidy { "Yes" } else { "No" }));
-        
-        // Check subsidy requirement first
-        if has_other_subsidy {
-            explanation_parts.push("Subsidy check: FAILED (already has another subsidy)".to_string());
-            explanation_parts.push("Result: NOT ELIGIBLE".to_string());
-            
-            additional_requirements.push("Must not have any other housing subsidies or assistance".to_string());
-            
-            return CheckHousingGrantResponse {
-                eligible: false,
-                explanation: explanation_parts.join(". "),
-                errors,
-                additional_requirements,
-            };
-        } else {
-            explanation_parts.push("Subsidy check: PASSED (no other subsidies)".to_string());
-        }
+        let result = engine.check_housing_grant(Parameters(params)).await;
+        assert!(result.is_ok());
         
-        // Calculate threshold
-        let base_threshold = 0.60 * ami;
-        explanation_parts.push(format!("Base income threshold: 60% of AMI = {:.2}", base_threshold));
+        let call_result = result.unwrap();
+        let content = call_result.content;
+        let json_text = content[0].raw.as_text().unwrap().text.as_str();
+        let response: CheckHousingGrantResponse = parse_payload(json_text);
         
-        let threshold = if household_size > 4 {
-            let adjusted_threshold = base_threshold * 1.10;
-            explanation_parts.push(format!(
-                "Household size adjustment: {} > 4, threshold increased by 10% to {:.2}", 
-                household_size, adjusted_threshold
-            ));
-            adjusted_threshold
-        } else {
-            explanation_parts.push(format!("No household size adjustment needed ({} ≤ 4)", household_size));
-            base_threshold
+        // Expected: threshold = 33000, income 34000 > 33000, not eligible
+        assert_eq!(response.eligible, false);
+        assert!(response.errors.is_empty());
+        assert!(response.explanation.contains("NOT ELIGIBLE"));
+        assert!((response.threshold - 33000.0).abs() < 1e-6);
+        assert_eq!(response.ami_percentage_used, 0.66);
+        assert!((response.income_margin + 1000.0).abs() < 1e-6);
+        assert_eq!(response.blocking_reasons, vec!["Income 34000.00 is 1000.00 over the limit of 33000.00"]);
+    }
+
+    #[tokio::test]
+    async fn test_check_housing_grant_not_eligible_subsidy() {
+        let engine = CompatibilityEngine::new();
+        let params = CheckHousingGrantParams {
+            ami: "50000".into(),
+            household_size: "5".into(),
+            income: "32000".into(),
+            has_other_subsidy: "true".into(),
+            ..Default::default()
         };
         
-        // Check income eligibility
-        let eligible = income <= threshold;
-        explanation_parts.push(format!(
-            "Income eligibility: {:.2} {} {:.2} - {}", 
-            income, 
-            if eligible { "≤" } else { ">" }, 
-            threshold,
-            if eligible { "PASSED" } else { "FAILED" }
-        ));
+        let result = engine.check_housing_grant(Parameters(params)).await;
+        assert!(result.is_ok());
         
-        explanation_parts.push(format!("Final result: {}", if eligible { "ELIGIBLE" } else { "NOT ELIGIBLE" }));
+        let call_result = result.unwrap();
+        let content = call_result.content;
+        let json_text = content[0].raw.as_text().unwrap().text.as_str();
+        let response: CheckHousingGrantResponse = parse_payload(json_text);
         
-        // Add additional requirements
-        additional_requirements.push("Must provide proof of income documentation".to_string());
-        additional_requirements.push("Must be a first-time homebuyer or meet other program criteria".to_string());
-        if household_size > 4 {
-            additional_requirements.push("Large household size may require additional documentation".to_string());
+        // Expected: has other subsidy, not eligible
+        assert_eq!(response.eligible, false);
+        assert!(response.errors.is_empty());
+        assert!(response.explanation.contains("already has another subsidy"));
+        assert!(!response.additional_requirements.is_empty());
+        assert!((response.income_margin - 1000.0).abs() < 1e-6);
+        assert_eq!(response.blocking_reasons, vec!["Household already receives another housing subsidy"]);
+    }
+
+    #[tokio::test]
+    async fn test_check_housing_grant_income_currency() {
+        let engine = CompatibilityEngine::new();
+        let params = CheckHousingGrantParams {
+            ami: "50000".into(),
+            household_size: "3".into(),
+            income: "32000".into(),
+            has_other_subsidy: "false".into(),
+            income_currency: Some("USD".to_string()),
+            ..Default::default()
+        };
+
+        let call_result = engine.check_housing_grant(Parameters(params)).await.unwrap();
+        let response: CheckHousingGrantResponse = parse_payload(call_result.content[0].raw.as_text().unwrap().text.as_str());
+
+        // 32000 USD × 0.965 = 30880 EUR > 30000 threshold
+        assert!(!response.eligible);
+        assert!(response.explanation.starts_with("Income converted: 32000.00 USD × 0.9650 = 30880.00 EUR"));
+
+        let params = CheckHousingGrantParams {
+            ami: "50000".into(),
+            household_size: "3".into(),
+            income: "32000".into(),
+            has_other_subsidy: "false".into(),
+            income_currency: Some("XYZ".to_string()),
+            ..Default::default()
+        };
+        let call_result = engine.check_housing_grant(Parameters(params)).await.unwrap();
+        assert!(call_result.is_error.unwrap_or(false));
+    }
+
+    #[tokio::test]
+    async fn test_calc_capital_gains_long_term() {
+        let engine = CompatibilityEngine::new();
+        let params = CalcCapitalGainsParams {
+            acquisition_cost: "10000".into(),
+            sale_price: "16000".into(),
+            holding_period_days: "400".into(),
+            ..Default::default()
+        };
+
+        let call_result = engine.calc_capital_gains(Parameters(params)).await.unwrap();
+        assert!(!call_result.is_error.unwrap_or(false));
+        let response: CalcCapitalGainsResponse = parse_payload(call_result.content[0].raw.as_text().unwrap().text.as_str());
+
+        // Gain 6000, default exemption 1000, long-term rate 10%: 5000 × 10% = 500
+        assert_eq!(response.gain, 6000.0);
+        assert!(response.long_term);
+        assert_eq!(response.rate, 0.10);
+        assert_eq!(response.exemption_applied, 1000.0);
+        assert_eq!(response.taxable_gain, 5000.0);
+        assert!((response.tax - 500.0).abs() < 1e-9);
+        assert_eq!(response.assumptions[0].code, "default_applied");
+        assert!(response.explanation.contains("long-term rate 10.0%"));
+    }
+
+    #[tokio::test]
+    async fn test_calc_capital_gains_short_term_and_loss() {
+        let engine = CompatibilityEngine::new();
+        let params = CalcCapitalGainsParams {
+            acquisition_cost: "10000".into(),
+            sale_price: "12000".into(),
+            holding_period_days: "350".into(),
+            annual_exemption: Some("0".into()),
+            ..Default::default()
+        };
+        let call_result = engine.calc_capital_gains(Parameters(params)).await.unwrap();
+        let response: CalcCapitalGainsResponse = parse_payload(call_result.content[0].raw.as_text().unwrap().text.as_str());
+        assert!(!response.long_term);
+        assert!((response.tax - 400.0).abs() < 1e-9);
+        assert!(response.warnings.iter().any(|w| w.contains("15 days short of long-term")));
+
+        let params = CalcCapitalGainsParams {
+            acquisition_cost: "10000".into(),
+            sale_price: "8000".into(),
+            holding_period_days: "30".into(),
+            ..Default::default()
+        };
+        let call_result = engine.calc_capital_gains(Parameters(params)).await.unwrap();
+        let response: CalcCapitalGainsResponse = parse_payload(call_result.content[0].raw.as_text().unwrap().text.as_str());
+        assert_eq!(response.gain, -2000.0);
+        assert_eq!(response.tax, 0.0);
+        assert_eq!(response.exemption_applied, 0.0);
+
+        let params = CalcCapitalGainsParams {
+            acquisition_cost: "10000".into(),
+            sale_price: "8000".into(),
+            holding_period_days: "-1".into(),
+            ..Default::default()
+        };
+        let call_result = engine.calc_capital_gains(Parameters(params)).await.unwrap();
+        assert!(call_result.is_error.unwrap_or(false));
+    }
+
+    fn beneficiary(name: &str, relationship: &str, amount: &str, prior_gifts: Option<&str>) -> InheritanceBeneficiary {
+        InheritanceBeneficiary {
+            name: name.to_string(),
+            relationship: relationship.to_string(),
+            amount: amount.into(),
+            prior_gifts: prior_gifts.map(FlexibleF64::from),
         }
-        if income > threshold * 0.9 {
-            additional_requirements.push("Income is close to threshold - verify all deductions are included".to_string());
+    }
+
+    #[tokio::test]
+    async fn test_calc_inheritance_tax_per_beneficiary() {
+        let engine = CompatibilityEngine::new();
+        let params = CalcInheritanceTaxParams {
+            beneficiaries: vec![
+                beneficiary("Ana", "child", "600000", None),
+                beneficiary("Ben", "Sibling", "50000", Some("10000")),
+                beneficiary("Cleo", "spouse", "300000", None),
+            ],
+            ..Default::default()
+        };
+
+        let call_result = engine.calc_inheritance_tax(Parameters(params)).await.unwrap();
+        assert!(!call_result.is_error.unwrap_or(false));
+        let response: CalcInheritanceTaxResponse = parse_payload(call_result.content[0].raw.as_text().unwrap().text.as_str());
+
+        // Child: 600000 - 400000 = 200000; 75000 × 7% + 125000 × 11% = 19000
+        assert_eq!(response.beneficiaries[0].taxable_amount, 200000.0);
+        assert!((response.beneficiaries[0].tax - 19000.0).abs() < 1e-6);
+        // Sibling: prior gifts leave 10000 of the 20000 exemption; 40000 × 15% = 6000
+        assert_eq!(response.beneficiaries[1].relationship, "sibling");
+        assert_eq!(response.beneficiaries[1].exemption_applied, 10000.0);
+        assert!((response.beneficiaries[1].tax - 6000.0).abs() < 1e-6);
+        // Spouse: fully exempt
+        assert_eq!(response.beneficiaries[2].tax, 0.0);
+        assert!((response.total_tax - 25000.0).abs() < 1e-6);
+        assert!(response.explanation.contains("Total tax for 3 beneficiaries: 25000.00"));
+
+        let params = CalcInheritanceTaxParams {
+            beneficiaries: vec![beneficiary("Dan", "cousin", "1000", None)],
+            ..Default::default()
+        };
+        let call_result = engine.calc_inheritance_tax(Parameters(params)).await.unwrap();
+        assert!(call_result.is_error.unwrap_or(false));
+        let error_text = call_result.content[0].raw.as_text().unwrap().text.as_str();
+        assert!(error_text.contains("Unknown relationship 'cousin' for beneficiary 'Dan'"));
+    }
+
+    #[test]
+    fn test_parse_inheritance_classes() {
+        let classes = EngineConfig::parse_inheritance_classes(
+            r#"{"Child": {"exemption": 100000, "brackets": [{"up_to": 50000, "rate": 0.05}, {"rate": 0.10}]}}"#,
+            &price_index(),
+        )
+        .unwrap();
+        assert_eq!(classes["child"].exemption, 100000.0);
+        assert_eq!(classes["child"].thresholds, vec![50000.0]);
+        assert_eq!(
+            CompatibilityEngine::progressive_tax(80000.0, &classes["child"].thresholds, &classes["child"].rates, &classes["child"].cliffs),
+            5500.0
+        );
+
+        let invalid = [
+            ("{}", "at least one relationship class"),
+            (r#"{"child": {"exemption": -1, "brackets": [{"rate": 0.1}]}}"#, "exemption must not be negative"),
+            (r#"{"child": {"exemption": 0, "brackets": []}}"#, "class 'child': at least one bracket"),
+            (r#"{"child": {"exemption": 0}}"#, "invalid JSON"),
+        ];
+        for (json, expected) in invalid {
+            let error = EngineConfig::parse_inheritance_classes(json, &price_index()).unwrap_err();
+            assert!(error.contains(expected), "{}: {}", json, error);
         }
-        
-        CheckHousingGrantResponse {
-            eligible,
-            explanation: explanation_parts.join(". "),
-            errors,
-            additional_requirements,
+    }
+
+    #[tokio::test]
+    async fn test_calc_social_contributions_with_ceilings() {
+        let engine = CompatibilityEngine::new();
+        let params = CalcSocialContributionsParams {
+            gross_salary: "100000".into(),
+            ..Default::default()
+        };
+
+        let call_result = engine.calc_social_contributions(Parameters(params)).await.unwrap();
+        assert!(!call_result.is_error.unwrap_or(false));
+        let response: CalcSocialContributionsResponse = parse_payload(call_result.content[0].raw.as_text().unwrap().text.as_str());
+
+        // Pension 90000 × 9.3%, health 62000 × 7.3%, unemployment 90000 × 1.3%
+        let pension = &response.contributions[0];
+        assert_eq!(pension.name, "pension");
+        assert_eq!(pension.base, 90000.0);
+        assert!(pension.capped);
+        assert!((pension.employee_amount - 8370.0).abs() < 1e-6);
+        assert!((response.contributions[1].employee_amount - 4526.0).abs() < 1e-6);
+        assert!((response.total_employee - 14066.0).abs() < 1e-6);
+        assert!((response.total_employer - 14066.0).abs() < 1e-6);
+        assert!((response.net_salary - 85934.0).abs() < 1e-6);
+        assert!(response.warnings[0].contains("pension, health, unemployment"));
+
+        // Below every ceiling the full salary is the base
+        let response = CompatibilityEngine::calc_social_contributions_internal(
+            40000.0,
+            &EngineConfig::parse_social_contributions(
+                r#"[{"name": "pension", "ceiling": 90000, "employee_rate": 0.1, "employer_rate": 0.12}, {"name": "levy", "employee_rate": 0.01, "employer_rate": 0}]"#,
+            )
+            .unwrap(),
+            Audience::Expert,
+        );
+        assert!(response.contributions.iter().all(|line| !line.capped && line.base == 40000.0));
+        assert!((response.total_employee - 4400.0).abs() < 1e-6);
+        assert!((response.total_employer - 4800.0).abs() < 1e-6);
+        assert!(response.warnings.is_empty());
+
+        let invalid = [
+            ("[]", "at least one contribution type"),
+            (r#"[{"name": "a", "employee_rate": 0.1, "employer_rate": 0.1}, {"name": "a", "employee_rate": 0.1, "employer_rate": 0.1}]"#, "more than once"),
+            (r#"[{"name": "a", "ceiling": 0, "employee_rate": 0.1, "employer_rate": 0.1}]"#, "ceiling must be a positive number"),
+            (r#"[{"name": "a", "employee_rate": 1.5, "employer_rate": 0.1}]"#, "between 0 and 1"),
+        ];
+        for (json, expected) in invalid {
+            let error = EngineConfig::parse_social_contributions(json).unwrap_err();
+            assert!(error.contains(expected), "{}: {}", json, error);
         }
     }
-}
 
-#[tool_router]
-impl CompatibilityEngine {
-    pub fn new() -> Self {
-        Self {
-            tool_router: Self::tool_router(),
+    #[tokio::test]
+    async fn test_calc_property_tax_cap_and_homestead() {
+        let engine = CompatibilityEngine::new();
+        let params = CalcPropertyTaxParams {
+            assessed_value: "300000".into(),
+            prior_year_value: Some("250000".into()),
+            homestead: Some("yes".into()),
+            ..Default::default()
+        };
+
+        let call_result = engine.calc_property_tax(Parameters(params)).await.unwrap();
+        assert!(!call_result.is_error.unwrap_or(false));
+        let response: CalcPropertyTaxResponse = parse_payload(call_result.content[0].raw.as_text().unwrap().text.as_str());
+
+        // Capped at 250000 × 1.03 = 257500; minus 25000 homestead = 232500; × 15 mills = 3487.50
+        assert!(response.increase_capped);
+        assert!((response.capped_value - 257500.0).abs() < 1e-6);
+        assert_eq!(response.exemption_applied, 25000.0);
+        assert!((response.tax - 3487.5).abs() < 1e-6);
+        assert!(response.explanation.contains("Homestead exemption"));
+
+        // No prior value, no homestead, explicit millage
+        let params = CalcPropertyTaxParams {
+            assessed_value: "200000".into(),
+            millage: Some("20".into()),
+            ..Default::default()
+        };
+        let call_result = engine.calc_property_tax(Parameters(params)).await.unwrap();
+        let response: CalcPropertyTaxResponse = parse_payload(call_result.content[0].raw.as_text().unwrap().text.as_str());
+        assert!(!response.increase_capped);
+        assert_eq!(response.exemption_applied, 0.0);
+        assert!((response.tax - 4000.0).abs() < 1e-6);
+        assert!(response.assumptions.iter().any(|a| a.field == "homestead"));
+    }
+
+    #[tokio::test]
+    async fn test_calc_transfer_tax_slice_and_marginal() {
+        let engine = CompatibilityEngine::new();
+        let params = CalcTransferTaxParams {
+            price: "300000".into(),
+            method: Some("slice".to_string()),
+            ..Default::default()
+        };
+
+        let call_result = engine.calc_transfer_tax(Parameters(params)).await.unwrap();
+        assert!(!call_result.is_error.unwrap_or(false));
+        let response: CalcTransferTaxResponse = parse_payload(call_result.content[0].raw.as_text().unwrap().text.as_str());
+
+        // Whole price in the 250000-925000 slice at 5%
+        assert_eq!(response.method, TransferTaxMethod::Slice);
+        assert_eq!(response.rate, 0.05);
+        assert!((response.tax - 15000.0).abs() < 1e-6);
+
+        // Marginal: 250000 × 2% + 50000 × 5% = 7500
+        let params = CalcTransferTaxParams {
+            price: "300000".into(),
+            method: Some("marginal".to_string()),
+            ..Default::default()
+        };
+        let call_result = engine.calc_transfer_tax(Parameters(params)).await.unwrap();
+        let response: CalcTransferTaxResponse = parse_payload(call_result.content[0].raw.as_text().unwrap().text.as_str());
+        assert_eq!(response.method, TransferTaxMethod::Marginal);
+        assert!((response.tax - 7500.0).abs() < 1e-6);
+        assert!((response.effective_rate - 0.025).abs() < 1e-9);
+
+        let params = CalcTransferTaxParams {
+            price: "300000".into(),
+            method: Some("flat".to_string()),
+            ..Default::default()
+        };
+        let call_result = engine.calc_transfer_tax(Parameters(params)).await.unwrap();
+        assert!(call_result.is_error.unwrap_or(false));
+    }
+
+    #[test]
+    fn test_transfer_tax_slice_threshold_warning() {
+        let thresholds = [250000.0, 925000.0];
+        let rates = [0.02, 0.05, 0.10];
+
+        // A price exactly on a threshold stays in the lower slice
+        let at = CompatibilityEngine::calc_transfer_tax_internal(250000.0, &thresholds, &rates, &[], TransferTaxMethod::Slice, Audience::Expert);
+        assert_eq!(at.rate, 0.02);
+        assert!(at.warnings.is_empty());
+
+        let over = CompatibilityEngine::calc_transfer_tax_internal(251000.0, &thresholds, &rates, &[], TransferTaxMethod::Slice, Audience::Expert);
+        assert_eq!(over.rate, 0.05);
+        assert!((over.tax - 12550.0).abs() < 1e-6);
+        assert!(over.warnings.iter().any(|w| w.contains("whole price")));
+        assert!(over.explanation.contains("above 250000.00 and up to 925000.00; whole price × 5.0%"));
+    }
+
+    #[tokio::test]
+    async fn test_calc_fee_components() {
+        let engine = CompatibilityEngine::new();
+        let params = CalcFeeParams {
+            fee_code: "Land_Registration".to_string(),
+            value: "300000".into(),
+            ..Default::default()
+        };
+
+        let call_result = engine.calc_fee(Parameters(params)).await.unwrap();
+        assert!(!call_result.is_error.unwrap_or(false));
+        let response: CalcFeeResponse = parse_payload(call_result.content[0].raw.as_text().unwrap().text.as_str());
+
+        // 100 fixed + 0.5% × 300000 = 1500, capped at 1000
+        assert_eq!(response.fee_code, "land_registration");
+        assert_eq!(response.components.len(), 2);
+        assert!(response.components[1].capped);
+        assert_eq!(response.fee, 1100.0);
+        assert!(response.warnings.iter().any(|w| w.contains("stamp")));
+        assert!(response.explanation.contains("stamp: 0.00 + 0.50% × 300000.00 above 0.00, capped at 1000.00 = 1000.00"));
+
+        let params = CalcFeeParams {
+            fee_code: "parking".to_string(),
+            value: "100".into(),
+            ..Default::default()
+        };
+        let call_result = engine.calc_fee(Parameters(params)).await.unwrap();
+        assert!(call_result.is_error.unwrap_or(false));
+        let error_text = call_result.content[0].raw.as_text().unwrap().text.as_str();
+        assert!(error_text.contains("Unknown fee code 'parking'"));
+    }
+
+    #[test]
+    fn test_parse_fee_schedules() {
+        let schedules = EngineConfig::parse_fee_schedules(
+            r#"{"Appeal": [{"name": "filing", "fixed": 200, "rate": 0.02, "above": 5000, "cap": 3000}]}"#,
+        )
+        .unwrap();
+        let response = CompatibilityEngine::calc_fee_internal("appeal", 20000.0, &schedules, Audience::Expert);
+        // 200 + 2% × (20000 - 5000) = 500
+        assert_eq!(response.fee, 500.0);
+        assert!(!response.components[0].capped);
+        // Below the threshold only the fixed amount is due
+        let response = CompatibilityEngine::calc_fee_internal("appeal", 4000.0, &schedules, Audience::Expert);
+        assert_eq!(response.fee, 200.0);
+
+        let invalid = [
+            ("{}", "at least one fee code"),
+            (r#"{"appeal": []}"#, "at least one component"),
+            (r#"{"appeal": [{"name": "", "fixed": 10}]}"#, "must have a name"),
+            (r#"{"appeal": [{"name": "a", "fixed": 10}, {"name": "a", "rate": 0.1}]}"#, "more than once"),
+            (r#"{"appeal": [{"name": "a", "fixed": -10}]}"#, "must not be negative"),
+            (r#"{"appeal": [{"name": "a", "rate": 2}]}"#, "between 0 and 1"),
+            (r#"{"appeal": [{"name": "a", "rate": 0.1, "cap": 0}]}"#, "cap must be a positive"),
+            (r#"{"appeal": [{"name": "a", "percent": 0.1}]}"#, "invalid JSON"),
+        ];
+        for (json, expected) in invalid {
+            let error = EngineConfig::parse_fee_schedules(json).unwrap_err();
+            assert!(error.contains(expected), "{}: {}", json, error);
         }
     }
 
-    /// Calculate penalty with cap and interest
-    /// Logic: penalty = min(days_late × rate_per_day, cap), then add interest = penalty × interest_rate
-    #[tool(description = "Suitable for Lysmark's for calculations related to the Commercial Obligations and Liquidated Damages Act. Computes late-payment penalty (min of days × rate vs cap) plus interest. Returns penalty amount, explanation, errors, and warnings. Use when the user provides specific values (e.g. days late, optionally rate, cap, interest) and asks for a penalty calculation. Do NOT use for lookup questions: 'What is the penalty rate?', 'What is the cap?', 'What are the penalty rules?' — those answers come from retrieved documents. Requires days_late (numeric); rate, cap, and interest are optional.")]
-    pub async fn calc_penalty(
-        &self,
-        Parameters(params): Parameters<CalcPenaltyParams>
-    ) -> Result<CallToolResult, McpError> {
-        let _timer = RequestTimer::new();
-        increment_requests();
+    #[tokio::test]
+    async fn test_index_amount() {
+        let engine = CompatibilityEngine::new();
+        let params = IndexAmountParams {
+            amount: "1000".into(),
+            to_year: Some("2024".into()),
+            ..Default::default()
+        };
+
+        let call_result = engine.index_amount(Parameters(params)).await.unwrap();
+        assert!(!call_result.is_error.unwrap_or(false));
+        let response: IndexAmountResponse = parse_payload(call_result.content[0].raw.as_text().unwrap().text.as_str());
+
+        // Built-in index: 2020 = 100, 2024 = 121.20
+        assert_eq!(response.from_year, 2020);
+        assert_eq!(response.from_index, 100.0);
+        assert_eq!(response.to_index, 121.2);
+        assert!((response.indexed_amount - 1212.0).abs() < 1e-6);
+        assert!(response.explanation.contains("121.20"));
+        assert!(response.assumptions.iter().any(|a| a.field == "from_year"));
+
+        let params = IndexAmountParams {
+            amount: "1000".into(),
+            from_year: Some("1990".into()),
+            ..Default::default()
+        };
+        let call_result = engine.index_amount(Parameters(params)).await.unwrap();
+        assert!(call_result.is_error.unwrap_or(false));
+        let error_text = call_result.content[0].raw.as_text().unwrap().text.as_str();
+        assert!(error_text.contains("Invalid from_year: no price index value for 1990"));
+    }
+
+    #[test]
+    fn test_price_index_and_indexed_brackets() {
+        let index = PriceIndex::parse(r#"{"2020": 100, "2025": 110}"#, None, None).unwrap();
+        assert_eq!(index.base_year, 2020);
+        assert_eq!(index.target_year, 2025);
+        assert!((index.index_to_target(10000.0).unwrap() - 11000.0).abs() < 1e-6);
+
+        assert!(PriceIndex::parse(r#"{"2020": 0}"#, None, None).unwrap_err().contains("positive"));
+        assert!(PriceIndex::parse(r#"{"2021": 100}"#, None, None).unwrap_err().contains("no price index value for 2020"));
+        assert!(PriceIndex::parse(r#"{"2020": 100}"#, Some("soon"), None).unwrap_err().contains("not a year"));
+
+        // Built-in index adjusts indexed brackets from 2020 to 2024 (× 1.212)
+        let (thresholds, _, _) = EngineConfig::parse_tax_brackets(
+            r#"[{"up_to": 10000, "rate": 0.10, "indexed": true}, {"up_to": 50000, "rate": 0.20}, {"rate": 0.30}]"#,
+            &price_index(),
+        )
+        .unwrap();
+        assert!((thresholds[0] - 12120.0).abs() < 1e-6);
+        assert_eq!(thresholds[1], 50000.0);
+
+        let error = EngineConfig::parse_tax_brackets(r#"[{"up_to": 10000, "rate": 0.10}, {"rate": 0.20, "indexed": true}]"#, &price_index())
+            .unwrap_err();
+        assert!(error.contains("no up_to to index"));
+    }
+
+    #[tokio::test]
+    async fn test_result_reference_chaining() {
+        let engine = CompatibilityEngine::new();
+        let params = CalcPropertyTaxParams {
+            assessed_value: "200000".into(),
+            millage: Some("20".into()),
+            ..Default::default()
+        };
+        let call_result = engine.calc_property_tax(Parameters(params)).await.unwrap();
+        let envelope: serde_json::Value =
+            serde_json::from_str(call_result.content[0].raw.as_text().unwrap().text.as_str()).unwrap();
+        let result_id = envelope["result_id"].as_str().unwrap();
+
+        // Feed the property tax (4000) into index_amount without copying the number
+        let params = IndexAmountParams {
+            amount: format!("$result:{}.tax", result_id).into(),
+            to_year: Some("2024".into()),
+            ..Default::default()
+        };
+        let call_result = engine.index_amount(Parameters(params)).await.unwrap();
+        assert!(!call_result.is_error.unwrap_or(false));
+        let response: IndexAmountResponse = parse_payload(call_result.content[0].raw.as_text().unwrap().text.as_str());
+        assert_eq!(response.amount, 4000.0);
+        assert!(response.assumptions.iter().any(|a| a.code == "result_reference" && a.field == "amount"));
 
-        // Parse string parameter
-        let days_late = match parse_f64_from_string(&params.days_late) {
-            Ok(value) => value,
-            Err(parse_error) => {
-                increment_errors();
-                return Ok(CallToolResult::error(vec![Content::text(format!(
-                    "Invalid days_late parameter: {}", parse_error
-                ))]));
-            }
+        let params = IndexAmountParams {
+            amount: format!("$result:{}.penalty", result_id).into(),
+            ..Default::default()
         };
+        let call_result = engine.index_amount(Parameters(params)).await.unwrap();
+        assert!(call_result.is_error.unwrap_or(false));
+        let error_text = call_result.content[0].raw.as_text().unwrap().text.as_str();
+        assert!(error_text.contains("Invalid amount parameter"));
+        assert!(error_text.contains("has no field 'penalty'"));
+    }
 
-        let mut invalid_optional_parameters = Vec::new();
-        let rate_per_day = match params.rate_per_day.as_ref() {
-            None => CONFIG.default_rate_per_day,
-            Some(s) => match parse_f64_from_string(s) {
-                Ok(v) => v,
-                Err(e) => {
-                    tracing::warn!("Invalid rate_per_day parameter: {e:?}");
-                    invalid_optional_parameters.push("rate_per_day");
-                    CONFIG.default_rate_per_day
-                }
-            }
+    #[tokio::test]
+    async fn test_convert_currency() {
+        let engine = CompatibilityEngine::new();
+        let params = ConvertCurrencyParams {
+            amount: "1000".into(),
+            from_currency: "usd".to_string(),
+            ..Default::default()
         };
-        let cap = match params.cap.as_ref() {
-            None => CONFIG.default_cap,
-            Some(s) => match parse_f64_from_string(s) {
-                Ok(v) => v,
-                Err(e) => {
-                    tracing::warn!("Invalid cap parameter: {e:?}");
-                    invalid_optional_parameters.push("cap");
-                    CONFIG.default_cap
-                }
-            }
+
+        let call_result = engine.convert_currency(Parameters(params)).await.unwrap();
+        assert!(!call_result.is_error.unwrap_or(false));
+        let response: ConvertCurrencyResponse = parse_payload(call_result.content[0].raw.as_text().unwrap().text.as_str());
+
+        // Built-in table: 1 USD = 0.965 EUR on 2025-01-02
+        assert_eq!(response.to_currency, "EUR");
+        assert_eq!(response.rate, 0.965);
+        assert_eq!(response.rate_date.as_deref(), Some("2025-01-02"));
+        assert!((response.converted_amount - 965.0).abs() < 1e-6);
+        assert!(response.explanation.contains("2025-01-02"));
+
+        // Cross rate through the base currency
+        let params = ConvertCurrencyParams {
+            amount: "1000".into(),
+            from_currency: "GBP".to_string(),
+            to_currency: Some("USD".to_string()),
+            date: Some("2025-03-01".to_string()),
+            ..Default::default()
         };
-        let interest_rate = match params.interest_rate.as_ref() {
-            None => CONFIG.default_interest_rate,
-            Some(s) => match parse_f64_from_string(s) {
-                Ok(v) => v,
-                Err(e) => {
-                    tracing::warn!("Invalid interest_rate parameter: {e:?}");
-                    invalid_optional_parameters.push("interest_rate");
-                    CONFIG.default_interest_rate
-                }
-            }
+        let call_result = engine.convert_currency(Parameters(params)).await.unwrap();
+        let response: ConvertCurrencyResponse = parse_payload(call_result.content[0].raw.as_text().unwrap().text.as_str());
+        assert!((response.converted_amount - 1000.0 * 1.205 / 0.965).abs() < 1e-6);
+        assert!(response.warnings.iter().any(|w| w.contains("used the 2025-01-02 rate")));
+
+        let params = ConvertCurrencyParams {
+            amount: "1000".into(),
+            from_currency: "USD".to_string(),
+            date: Some("2024-12-31".to_string()),
+            ..Default::default()
         };
+        let call_result = engine.convert_currency(Parameters(params)).await.unwrap();
+        assert!(call_result.is_error.unwrap_or(false));
+        let error_text = call_result.content[0].raw.as_text().unwrap().text.as_str();
+        assert!(error_text.contains("no USD exchange rate on or before 2024-12-31"));
+    }
 
-        let result = Self::calc_penalty_internal(
-            days_late,
-            rate_per_day,
-            cap,
-            interest_rate,
-        );
+    #[tokio::test]
+    async fn test_get_intake_schema() {
+        let engine = CompatibilityEngine::new();
+        let params = GetIntakeSchemaParams {
+            program: "Housing_Grant".to_string(),
+            ..Default::default()
+        };
 
-        if !result.errors.is_empty() {
-            increment_errors();
-            return Ok(CallToolResult::error(vec![Content::text(format!(
-                "Calculation errors: {}", result.errors.join(", ")
-            ))]));
-        }
+        let call_result = engine.get_intake_schema(Parameters(params)).await.unwrap();
+        assert!(!call_result.is_error.unwrap_or(false));
+        let response: GetIntakeSchemaResponse = parse_payload(call_result.content[0].raw.as_text().unwrap().text.as_str());
+        assert_eq!(response.tool, "check_housing_grant");
+
+        let income = response.fields.iter().find(|field| field.name == "income").unwrap();
+        assert_eq!(income.field_type, "number");
+        assert!(income.required);
+        assert_eq!(income.help, "Household income");
+        assert!(income.validation.iter().any(|hint| hint.contains("60% of AMI")));
+        let currency = response.fields.iter().find(|field| field.name == "income_currency").unwrap();
+        assert!(!currency.required);
+
+        let params = GetIntakeSchemaParams {
+            program: "scholarship".to_string(),
+            ..Default::default()
+        };
+        let call_result = engine.get_intake_schema(Parameters(params)).await.unwrap();
+        assert!(call_result.is_error.unwrap_or(false));
+        let error_text = call_result.content[0].raw.as_text().unwrap().text.as_str();
+        assert!(error_text.contains("Unknown program 'scholarship' (expected one of: housing_grant, voting)"));
+    }
 
-        match serde_json::to_string_pretty(&result) {
-            Ok(json_str) => {
-                let content = Content::text(json_str);
-                if !invalid_optional_parameters.is_empty() {
-                    // Format a string with the content a section warning that the following parameters were invalid:
-                    let warning_string = format!("The following parameters were invalid: {} and used the default value: {}", invalid_optional_parameters.join(", "), CONFIG.default_rate_per_day);
-                    Ok(CallToolResult::success(vec![content, Content::text(warning_string)]))
-                } else {
-                    Ok(CallToolResult::success(vec![content]))
-                }
-            },
-            Err(e) => {
-                increment_errors();
-                return Ok(CallToolResult::error(vec![Content::text(format!(
-                    "Error serializing response: {}", e
-                ))]));
-            }
+    #[test]
+    fn test_intake_programs_cover_tool_inputs() {
+        // Every program field must exist on its tool, and every tool input must be on the form
+        let common = PRESENTATION_PARAMS;
+        for program in INTAKE_PROGRAMS {
+            let (tool, specs) = intake_program(program).unwrap();
+            let schema = CompatibilityEngine::tool_router()
+                .list_all()
+                .into_iter()
+                .find(|candidate| candidate.name == tool)
+                .unwrap()
+                .input_schema;
+            let mut inputs: Vec<&str> = schema["properties"]
+                .as_object()
+                .unwrap()
+                .keys()
+                .map(String::as_str)
+                .filter(|name| !common.contains(name))
+                .collect();
+            let mut fields: Vec<&str> = specs.iter().map(|spec| spec.name).collect();
+            inputs.sort();
+            fields.sort();
+            assert_eq!(inputs, fields, "intake form for {} is out of date", program);
         }
     }
 
-    /// Calculate progressive tax with surcharge
-    /// Logic: apply progressive brackets defined by thresholds and rates. If total tax > surcharge_threshold, add surcharge = tax × surcharge_rate
-    #[tool(description = "Suitable for Lysmark's for calculations related to the Progressive Income and Surcharge Act. Computes the tax liability and surcharge for a given taxable income using configured brackets and rates. Returns the total tax amount, per-bracket breakdown, and surcharge if applicable. Use ONLY when the user provides a specific income amount and asks for a calculated result (e.g. 'What is the tax for 90000?', 'Calculate tax liability for 35000'). Do NOT use for lookup questions: 'What is the tax rate?', 'What are the brackets?', 'What does the law say?', 'What is the surcharge threshold?' — those answers come from retrieved documents, not this tool. Requires a numeric income parameter.")]
-    pub async fn calc_tax(
-        &self,
-        Parameters(params): Parameters<CalcTaxParams>
-    ) -> Result<CallToolResult, McpError> {
-        let _timer = RequestTimer::new();
-        increment_requests();
+    #[test]
+    fn test_flexible_params_advertise_types() {
+        let schema = CompatibilityEngine::tool_router()
+            .list_all()
+            .into_iter()
+            .find(|candidate| candidate.name == "check_housing_grant")
+            .unwrap()
+            .input_schema;
+        let properties = &schema["properties"];
+        assert_eq!(properties["income"]["type"], serde_json::json!(["number", "string"]));
+        assert_eq!(properties["household_size"]["type"], serde_json::json!(["integer", "string"]));
+        assert_eq!(properties["has_other_subsidy"]["type"], serde_json::json!(["boolean", "string"]));
+        assert!(properties["income"]["description"].is_string());
+
+        let params: CheckHousingGrantParams = serde_json::from_value(serde_json::json!({
+            "ami": 50000, "household_size": "3", "income": 1.5e4, "has_other_subsidy": false,
+        }))
+        .unwrap();
+        assert_eq!(params.ami.0, Ok(50000.0));
+        assert_eq!(params.household_size.0, Ok(3));
+        assert_eq!(params.income.0, Ok(15000.0));
+        assert_eq!(params.has_other_subsidy.0, Ok(false));
+        assert!(serde_json::from_value::<CheckHousingGrantParams>(serde_json::json!({
+            "ami": [50000], "household_size": 3, "income": 15000, "has_other_subsidy": false,
+        }))
+        .is_err());
+    }
 
-        // Parse string parameter
-        let income = match parse_f64_from_string(&params.income) {
-            Ok(value) => value,
-            Err(parse_error) => {
-                increment_errors();
-                return Ok(CallToolResult::error(vec![Content::text(format!(
-                    "Invalid income parameter: {}", parse_error
-                ))]));
-            }
+    #[tokio::test]
+    async fn test_prescreen_partial_inputs() {
+        let engine = CompatibilityEngine::new();
+        let prescreen = |inputs: serde_json::Value| PrescreenParams {
+            program: "housing_grant".to_string(),
+            inputs: serde_json::from_value(inputs).unwrap(),
+            ..Default::default()
         };
 
-        let result = Self::calc_tax_internal(
-            income,
-            CONFIG.default_thresholds.clone(),
-            CONFIG.default_rates.clone(),
-            CONFIG.default_surcharge_threshold,
-            CONFIG.default_surcharge_rate,
+        // Another subsidy settles it without any income data
+        let call_result = engine.prescreen(Parameters(prescreen(serde_json::json!({ "has_other_subsidy": "yes" })))).await.unwrap();
+        let response: PrescreenResponse = parse_payload(call_result.content[0].raw.as_text().unwrap().text.as_str());
+        assert_eq!(response.verdict, PrescreenVerdict::Ineligible);
+        assert!(response.missing.is_empty());
+
+        // Income between 60% and 66% of AMI depends on household size
+        let call_result = engine.prescreen(Parameters(prescreen(serde_json::json!({
+            "ami": 50000, "income": "31,500", "has_other_subsidy": false,
+        })))).await.unwrap();
+        let response: PrescreenResponse = parse_payload(call_result.content[0].raw.as_text().unwrap().text.as_str());
+        assert_eq!(response.verdict, PrescreenVerdict::Undetermined);
+        assert_eq!(response.missing, vec!["household_size".to_string()]);
+        assert!(response.explanation.contains("income_limit: undetermined, missing household_size"));
+
+        // Above 66% of AMI fails for any household size
+        let call_result = engine.prescreen(Parameters(prescreen(serde_json::json!({ "ami": 50000, "income": 34000 })))).await.unwrap();
+        let response: PrescreenResponse = parse_payload(call_result.content[0].raw.as_text().unwrap().text.as_str());
+        assert_eq!(response.verdict, PrescreenVerdict::Ineligible);
+
+        let call_result = engine.prescreen(Parameters(prescreen(serde_json::json!({
+            "ami": 50000, "income": 29000, "household_size": 3, "has_other_subsidy": false,
+        })))).await.unwrap();
+        let response: PrescreenResponse = parse_payload(call_result.content[0].raw.as_text().unwrap().text.as_str());
+        assert_eq!(response.verdict, PrescreenVerdict::Eligible);
+
+        let call_result = engine.prescreen(Parameters(prescreen(serde_json::json!({ "salary": 29000 })))).await.unwrap();
+        assert!(call_result.is_error.unwrap_or(false));
+        let error_text = call_result.content[0].raw.as_text().unwrap().text.as_str();
+        assert!(error_text.contains("unknown field 'salary'"));
+    }
+
+    #[test]
+    fn test_prescreen_voting_without_proposal_type() {
+        let inputs = |value: serde_json::Value| serde_json::from_value(value).unwrap();
+
+        // 70% yes passes either threshold, 60% needs the proposal type
+        let response = CompatibilityEngine::prescreen_internal(
+            "voting",
+            &inputs(serde_json::json!({ "eligible_voters": 100, "turnout": 80, "yes_votes": 56 })),
+            Audience::Expert,
         );
+        assert_eq!(response.verdict, PrescreenVerdict::Eligible);
 
-        if !result.errors.is_empty() {
-            increment_errors();
-            Ok(CallToolResult::error(vec![Content::text(format!(
-                "Calculation errors: {}", result.errors.join(", ")
-            ))]))
-        } else {
-            match serde_json::to_string_pretty(&result) {
-                Ok(json_str) => Ok(CallToolResult::success(vec![Content::text(json_str)])),
-                Err(e) => {
-                    increment_errors();
-                    Ok(CallToolResult::error(vec![Content::text(format!(
-                        "Error serializing response: {}", e
-                    ))]))
-                }
-            }
-        }
-    }
+        let response = CompatibilityEngine::prescreen_internal(
+            "voting",
+            &inputs(serde_json::json!({ "turnout": 80, "yes_votes": 48 })),
+            Audience::Expert,
+        );
+        assert_eq!(response.verdict, PrescreenVerdict::Undetermined);
+        assert_eq!(response.missing, vec!["eligible_voters".to_string(), "proposal_type".to_string()]);
 
-    /// Check voting proposal eligibility
-    /// Logic: turnout must be ≥60% of eligible. Then check: If proposal_type = "general" → yes_votes / turnout > 0.50. If proposal_type = "amendment" → yes_votes / turnout ≥ 2/3
-    #[tool(description = "Suitable for Lysmark's for calculations related to the Organizational Voting and Quorum Act. Determines whether a voting proposal passes based on turnout and yes-vote thresholds. Returns pass/fail result and explanation. Use when the user provides specific values (eligible_voters, turnout, yes_votes, proposal_type) and asks for an eligibility or pass check. Do NOT use for lookup questions: 'What is the turnout threshold?', 'What are the voting rules?' — those answers come from retrieved documents. Requires eligible_voters, turnout, yes_votes, proposal_type.")]
-    pub async fn check_voting(
-        &self,
-        Parameters(params): Parameters<CheckVotingParams>
-    ) -> Result<CallToolResult, McpError> {
-        let _timer = RequestTimer::new();
-        increment_requests();
+        let response = CompatibilityEngine::prescreen_internal(
+            "scholarship",
+            &BTreeMap::new(),
+            Audience::Expert,
+        );
+        assert!(response.errors[0].contains("Unknown program 'scholarship'"));
+    }
 
-        // Parse string parameters
-        let eligible_voters = match parse_i32_from_string(&params.eligible_voters) {
-            Ok(value) => value,
-            Err(parse_error) => {
-                increment_errors();
-                return Ok(CallToolResult::error(vec![Content::text(format!(
-                    "Invalid eligible_voters parameter: {}", parse_error
-                ))]));
-            }
+    #[test]
+    fn test_get_usage_report_orders_tools_and_errors() {
+        let mut stats = UsageStats::default();
+        let usage = |calls, errors: &[(&str, u64)]| ToolUsage {
+            calls,
+            failed_calls: errors.iter().map(|(_, count)| count).sum(),
+            parameter_types: BTreeMap::from([(
+                "income".to_string(),
+                BTreeMap::from([("string".to_string(), calls)]),
+            )]),
+            errors: errors.iter().map(|(message, count)| (message.to_string(), *count)).collect(),
         };
+        stats.tools.insert("calc_tax".to_string(), usage(3, &[("Invalid income parameter: Cannot parse '…' as a number", 2)]));
+        stats.tools.insert("check_voting".to_string(), usage(5, &[("missing field `…`", 4), ("Turnout (#) cannot exceed eligible voters (#)", 1)]));
 
-        let turnout = match parse_i32_from_string(&params.turnout) {
-            Ok(value) => value,
-            Err(parse_error) => {
-                increment_errors();
-                return Ok(CallToolResult::error(vec![Content::text(format!(
-                    "Invalid turnout parameter: {}", parse_error
-                ))]));
-            }
-        };
+        let response = CompatibilityEngine::get_usage_report_internal(Some(&stats), None, 2, Audience::Expert);
+        assert!(response.errors.is_empty());
+        assert_eq!(response.total_calls, 8);
+        assert_eq!(response.tools[0].tool, "check_voting");
+        assert_eq!(response.tools[1].parameters[0].types["string"], 3);
+        assert_eq!(response.top_errors.len(), 2);
+        assert_eq!(response.top_errors[0].message, "missing field `…`");
+        assert_eq!(response.top_errors[1].tool, "calc_tax");
+        assert!(response.explanation.contains("8 calls to 2 tools"));
+
+        let response = CompatibilityEngine::get_usage_report_internal(Some(&stats), Some("calc_tax"), 10, Audience::Expert);
+        assert_eq!(response.total_calls, 3);
+        assert_eq!(response.top_errors.len(), 1);
+
+        let response = CompatibilityEngine::get_usage_report_internal(Some(&stats), Some("calc_taxes"), 10, Audience::Expert);
+        assert!(response.errors[0].contains("Unknown tool 'calc_taxes'"));
+        let response = CompatibilityEngine::get_usage_report_internal(None, None, 10, Audience::Expert);
+        assert!(response.errors[0].contains("disabled"));
+    }
 
-        let yes_votes = match parse_i32_from_string(&params.yes_votes) {
-            Ok(value) => value,
-            Err(parse_error) => {
-                increment_errors();
-                return Ok(CallToolResult::error(vec![Content::text(format!(
-                    "Invalid yes_votes parameter: {}", parse_error
-                ))]));
-            }
+    #[test]
+    fn test_get_recent_schema_failures_newest_first() {
+        let failure = |tool: &str, error: &str| SchemaFailure {
+            tool: tool.to_string(),
+            at: chrono::Utc::now(),
+            error: error.to_string(),
+            shape: serde_json::json!({ "income": "boolean" }),
         };
+        let samples = vec![
+            failure("calc_tax", "invalid type: boolean `…`"),
+            failure("check_voting", "missing field `turnout`"),
+            failure("calc_tax", "missing field `income`"),
+        ];
 
-        let result = Self::check_voting_internal(
-            eligible_voters,
-            turnout,
-            yes_votes,
-            &params.proposal_type,
-        );
+        let response = CompatibilityEngine::get_recent_schema_failures_internal(Some(&samples), None, 2, Audience::Expert);
+        assert!(response.errors.is_empty());
+        assert_eq!(response.total_recorded, 3);
+        let listed: Vec<&str> = response.failures.iter().map(|failure| failure.error.as_str()).collect();
+        assert_eq!(listed, ["missing field `income`", "missing field `turnout`"]);
 
-        if !result.errors.is_empty() {
-            increment_errors();
-            Ok(CallToolResult::error(vec![Content::text(format!(
-                "Validation errors: {}", result.errors.join(", ")
-            ))]))
-        } else {
-            match serde_json::to_string_pretty(&result) {
-                Ok(json_str) => Ok(CallToolResult::success(vec![Content::text(json_str)])),
-                Err(e) => {
-                    increment_errors();
-                    Ok(CallToolResult::error(vec![Content::text(format!(
-                        "Error serializing response: {}", e
-                    ))]))
-                }
-            }
+        let response = CompatibilityEngine::get_recent_schema_failures_internal(Some(&samples), Some("calc_tax"), 20, Audience::Expert);
+        assert_eq!(response.total_recorded, 2);
+        assert_eq!(response.failures[1].shape["income"], "boolean");
+
+        let response = CompatibilityEngine::get_recent_schema_failures_internal(None, Some("calc_taxes"), 0, Audience::Expert);
+        assert_eq!(response.errors.len(), 3);
+    }
+
+    #[test]
+    fn test_parse_fx_rates() {
+        let table = EngineConfig::parse_fx_rates(
+            r#"[{"date": "2025-01-02", "currency": "usd", "rate": 0.96}, {"date": "2025-02-03", "currency": "USD", "rate": 0.95}]"#,
+            "EUR",
+        )
+        .unwrap();
+        let date = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+        assert_eq!(table.conversion("USD", "EUR", Some(date("2025-01-31"))).unwrap().rate, 0.96);
+        assert_eq!(table.conversion("USD", "EUR", None).unwrap().rate, 0.95);
+        assert_eq!(table.conversion("EUR", "EUR", None).unwrap().quote_date, None);
+
+        let invalid = [
+            ("[]", "at least one exchange rate"),
+            (r#"[{"date": "2025-01-02", "currency": "dollar", "rate": 1}]"#, "three-letter code"),
+            (r#"[{"date": "2025-01-02", "currency": "EUR", "rate": 1}]"#, "base currency"),
+            (r#"[{"date": "02/01/2025", "currency": "USD", "rate": 1}]"#, "must be YYYY-MM-DD"),
+            (r#"[{"date": "2025-01-02", "currency": "USD", "rate": 0}]"#, "positive number"),
+            (r#"[{"date": "2025-01-02", "currency": "USD", "rate": 1}, {"date": "2025-01-02", "currency": "USD", "rate": 2}]"#, "more than one rate"),
+        ];
+        for (json, expected) in invalid {
+            let error = EngineConfig::parse_fx_rates(json, "EUR").unwrap_err();
+            assert!(error.contains(expected), "{}: {}", json, error);
         }
     }
 
-    /// Distribute cash in waterfall structure
-    /// Logic: Pay senior first (up to senior_debt). Then junior (up to junior_debt). Any remainder goes to equity
-    #[tool(description = "Suitable for Lysmark's for calculations related to the Structured Finance and Creditor Priority Act. Distributes available cash in waterfall order (senior → junior → equity). Returns distribution amounts and explanation. Use when the user provides specific values (cash_available, senior_debt, junior_debt) and asks for a waterfall distribution. Do NOT use for lookup questions: 'What is the waterfall order?', 'How does the distribution work?' — those answers come from retrieved documents. Requires cash_available, senior_debt, junior_debt.")]
-    pub async fn distribute_waterfall(
-        &self,
-        Parameters(params): Parameters<DistributeWaterfallParams>
-    ) -> Result<CallToolResult, McpError> {
-        let _timer = RequestTimer::new();
-        increment_requests();
+    #[tokio::test]
+    async fn test_calc_penalty_with_errors() {
+        let engine = CompatibilityEngine::new();
+        let params = CalcPenaltyParams {
+            days_late: "-5".into(),  // Invalid: negative
+            ..Default::default()
+        };
+        
+        let result = engine.calc_penalty(Parameters(params)).await;
+        assert!(result.is_ok());
+        
+        let call_result = result.unwrap();
+        // Should be an error response due to invalid input
+        assert!(call_result.is_error.unwrap_or(false));
+        let content = call_result.content;
+        let error_text = content[0].raw.as_text().unwrap().text.as_str();
+        // Now the error comes from parsing and calculation
+        assert!(error_text.contains("Days late cannot be negative") || error_text.contains("Calculation errors"));
+    }
 
-        // Parse string parameters
-        let cash_available = match parse_f64_from_string(&params.cash_available) {
-            Ok(value) => value,
-            Err(parse_error) => {
-                increment_errors();
-                return Ok(CallToolResult::error(vec![Content::text(format!(
-                    "Invalid cash_available parameter: {}", parse_error
-                ))]));
-            }
+    #[tokio::test]  
+    async fn test_calc_tax_invalid_brackets() {
+        // This test is no longer relevant since we use fixed configuration
+        // but let's keep it to test that the default configuration is valid
+        let engine = CompatibilityEngine::new();
+        let params = CalcTaxParams {
+            income: "40000".into(),
+            ..Default::default()
         };
+        
+        let result = engine.calc_tax(Parameters(params)).await;
+        assert!(result.is_ok());
+        let call_result = result.unwrap();
+        // Should succeed since we use valid default configuration
+        assert!(!call_result.is_error.unwrap_or(false));
+        let content = call_result.content;
+        let json_text = content[0].raw.as_text().unwrap().text.as_str();
+        let response: CalcTaxResponse = parse_payload(json_text);
+        assert!(response.errors.is_empty());
+    }
 
-        let senior_debt = match parse_f64_from_string(&params.senior_debt) {
-            Ok(value) => value,
-            Err(parse_error) => {
-                increment_errors();
-                return Ok(CallToolResult::error(vec![Content::text(format!(
-                    "Invalid senior_debt parameter: {}", parse_error
-                ))]));
-            }
+    #[tokio::test]
+    async fn test_calc_tax_negative_income_carries_loss_forward() {
+        let engine = CompatibilityEngine::new();
+        let params = CalcTaxParams {
+            income: "-5000".into(),
+            loss_carryforward: Some("1000".into()),
+            ..Default::default()
         };
 
-        let junior_debt = match parse_f64_from_string(&params.junior_debt) {
-            Ok(value) => value,
-            Err(parse_error) => {
-                increment_errors();
-                return Ok(CallToolResult::error(vec![Content::text(format!(
-                    "Invalid junior_debt parameter: {}", parse_error
-                ))]));
-            }
+        let call_result = engine.calc_tax(Parameters(params)).await.unwrap();
+        assert!(!call_result.is_error.unwrap_or(false));
+        let response: CalcTaxResponse = parse_payload(call_result.content[0].raw.as_text().unwrap().text.as_str());
+        assert_eq!(response.tax, 0.0);
+        assert_eq!(response.taxable_income, 0.0);
+        assert_eq!(response.remaining_carryforward, 6000.0);
+        assert!(response.explanation.contains("current-year loss 5000.00 added"));
+        assert!(response.explanation.contains("No taxable income"));
+    }
+
+    #[tokio::test]
+    async fn test_calc_tax_loss_carryforward_reduces_taxable_income() {
+        let engine = CompatibilityEngine::new();
+        let with_carryforward = CalcTaxParams {
+            income: "40000".into(),
+            loss_carryforward: Some("15000".into()),
+            ..Default::default()
+        };
+        let equivalent = CalcTaxParams {
+            income: "25000".into(),
+            ..Default::default()
         };
 
-        let result = Self::distribute_waterfall_internal(
-            cash_available,
-            senior_debt,
-            junior_debt,
-        );
+        let call_result = engine.calc_tax(Parameters(with_carryforward)).await.unwrap();
+        let response: CalcTaxResponse = parse_payload(call_result.content[0].raw.as_text().unwrap().text.as_str());
+        let call_result = engine.calc_tax(Parameters(equivalent)).await.unwrap();
+        let baseline: CalcTaxResponse = parse_payload(call_result.content[0].raw.as_text().unwrap().text.as_str());
+        assert_eq!(response.taxable_income, 25000.0);
+        assert_eq!(response.remaining_carryforward, 0.0);
+        assert_eq!(response.tax, baseline.tax);
 
-        if !result.errors.is_empty() {
-            increment_errors();
-            Ok(CallToolResult::error(vec![Content::text(format!(
-                "Validation errors: {}", result.errors.join(", ")
-            ))]))
-        } else {
-            match serde_json::to_string_pretty(&result) {
-                Ok(json_str) => Ok(CallToolResult::success(vec![Content::text(json_str)])),
-                Err(e) => {
-                    increment_errors();
-                    Ok(CallToolResult::error(vec![Content::text(format!(
-                        "Error serializing response: {}", e
-                    ))]))
-                }
-            }
-        }
-    }
+        // A carryforward larger than income is only partly used
+        let params = CalcTaxParams {
+            income: "4000".into(),
+            loss_carryforward: Some("10000".into()),
+            ..Default::default()
+        };
+        let call_result = engine.calc_tax(Parameters(params)).await.unwrap();
+        let response: CalcTaxResponse = parse_payload(call_result.content[0].raw.as_text().unwrap().text.as_str());
+        assert_eq!(response.tax, 0.0);
+        assert_eq!(response.remaining_carryforward, 6000.0);
 
-    /// Check housing grant eligibility
-    /// Logic: Base threshold = 0.60 × AMI. If household_size > 4, threshold = threshold × 1.10. Must satisfy income ≤ threshold. Must not have another subsidy
-    #[tool(description = "Suitable for Lysmark's for calculations related to the Public Housing Assistance Eligibility Act. Determines whether a household qualifies for a housing grant based on AMI, household size, income, and subsidy status. Returns eligibility result and explanation. Use when the user provides specific values (AMI, household_size, income, has_other_subsidy) and asks for an eligibility check. Do NOT use for 'What are the eligibility rules?' or 'What is the income threshold?' — those are lookups answered from documents. Requires AMI, household_size, income, has_other_subsidy.")]
-    pub async fn check_housing_grant(
-        &self,
-        Parameters(params): Parameters<CheckHousingGrantParams>
-    ) -> Result<CallToolResult, McpError> {
-        let _timer = RequestTimer::new();
-        increment_requests();
+        let params = CalcTaxParams {
+            income: "4000".into(),
+            loss_carryforward: Some("-1".into()),
+            ..Default::default()
+        };
+        let call_result = engine.calc_tax(Parameters(params)).await.unwrap();
+        assert!(call_result.is_error.unwrap_or(false));
+    }
 
-        // Parse string parameters
-        let ami = match parse_f64_from_string(&params.ami) {
-            Ok(value) => value,
-            Err(parse_error) => {
-                increment_errors();
-                return Ok(CallToolResult::error(vec![Content::text(format!(
-                    "Invalid ami parameter: {}", parse_error
-                ))]));
-            }
+    #[tokio::test]
+    async fn test_calc_tax_surcharge_base_override() {
+        let engine = CompatibilityEngine::new();
+        let params = CalcTaxParams {
+            income: "40000".into(),
+            surcharge_base: Some("income".to_string()),
+            ..Default::default()
         };
 
-        let household_size = match parse_i32_from_string(&params.household_size) {
-            Ok(value) => value,
-            Err(parse_error) => {
-                increment_errors();
-                return Ok(CallToolResult::error(vec![Content::text(format!(
-                    "Invalid household_size parameter: {}", parse_error
-                ))]));
-            }
+        let call_result = engine.calc_tax(Parameters(params)).await.unwrap();
+        assert!(!call_result.is_error.unwrap_or(false));
+        let response: CalcTaxResponse = parse_payload(call_result.content[0].raw.as_text().unwrap().text.as_str());
+        // Brackets: 10000 × 10% + 30000 × 20% = 7000; surcharge: (40000 - 5000) × 2% = 700
+        assert_eq!(response.surcharge_base, SurchargeBase::Income);
+        assert!((response.tax - 7700.0).abs() < 0.01);
+        assert!(response.explanation.contains("Surcharge on income applied (income 40000.00 > 5000.00)"));
+
+        let params = CalcTaxParams {
+            income: "40000".into(),
+            surcharge_base: Some("profit".to_string()),
+            ..Default::default()
         };
+        let call_result = engine.calc_tax(Parameters(params)).await.unwrap();
+        assert!(call_result.is_error.unwrap_or(false));
+        let error_text = call_result.content[0].raw.as_text().unwrap().text.as_str();
+        assert!(error_text.contains("Invalid surcharge_base parameter: must be 'tax' or 'income'"));
+    }
 
-        let income = match parse_f64_from_string(&params.income) {
-            Ok(value) => value,
-            Err(parse_error) => {
-                increment_errors();
-                return Ok(CallToolResult::error(vec![Content::text(format!(
-                    "Invalid income parameter: {}", parse_error
-                ))]));
-            }
+    #[tokio::test]
+    async fn test_check_voting_invalid_proposal_type() {
+        let engine = CompatibilityEngine::new();
+        let params = CheckVotingParams {
+            eligible_voters: "100".into(),
+            turnout: "70".into(),
+            yes_votes: "55".into(),
+            proposal_type: "invalid_type".to_string(),
+            ..Default::default()
         };
+        
+        let result = engine.check_voting(Parameters(params)).await;
+        assert!(result.is_ok());
+        
+        let call_result = result.unwrap();
+        assert!(call_result.is_error.unwrap_or(false));
+        let content = call_result.content;
+        let error_text = content[0].raw.as_text().unwrap().text.as_str();
+        assert!(error_text.contains("Invalid proposal type"));
+    }
 
-        let has_other_subsidy = match parse_bool_from_string(&params.has_other_subsidy) {
-            Ok(value) => value,
-            Err(parse_error) => {
-                increment_errors();
-                return Ok(CallToolResult::error(vec![Content::text(format!(
-                    "Invalid has_other_subsidy parameter: {}", parse_error
-                ))]));
-            }
+    #[tokio::test]
+    async fn test_validate_vote_record_valid() {
+        let engine = CompatibilityEngine::new();
+        let params = ValidateVoteRecordParams {
+            eligible_voters: "100".into(),
+            turnout: "70".into(),
+            yes_votes: "55".into(),
+            no_votes: "10".into(),
+            abstain_votes: Some("3".into()),
+            invalid_votes: Some("2".into()),
+            ..Default::default()
         };
 
-        let result = Self::check_housing_grant_internal(
-            ami,
-            household_size,
-            income,
-            has_other_subsidy,
-        );
+        let result = engine.validate_vote_record(Parameters(params)).await;
+        assert!(result.is_ok());
 
-        if !result.errors.is_empty() {
-            increment_errors();
-            Ok(CallToolResult::error(vec![Content::text(format!(
-                "Validation errors: {}", result.errors.join(", ")
-            ))]))
-        } else {
-            match serde_json::to_string_pretty(&result) {
-                Ok(json_str) => Ok(CallToolResult::success(vec![Content::text(json_str)])),
-                Err(e) => {
-                    increment_errors();
-                    Ok(CallToolResult::error(vec![Content::text(format!(
-                        "Error serializing response: {}", e
-                    ))]))
-                }
-            }
-        }
+        let call_result = result.unwrap();
+        assert!(!call_result.is_error.unwrap_or(false));
+        let content = call_result.content;
+        let json_text = content[0].raw.as_text().unwrap().text.as_str();
+        let response: ValidateVoteRecordResponse = parse_payload(json_text);
+
+        assert!(response.valid);
+        assert_eq!(response.tallied_ballots, 70);
+        assert!(response.inconsistencies.is_empty());
+        assert!(response.warnings.is_empty());
+        assert!(response.explanation.contains("VALID"));
     }
-}
 
-#[tool_handler(router = self.tool_router)]
-impl ServerHandler for CompatibilityEngine {
-    fn get_info(&self) -> ServerInfo {
-        // Read basic information from .env file (replaced by sync script during release)
-        let name = "compatibility-engine-mcp-rs".to_string();
-        let version = "3.1.1".to_string();
-        let title = "Compatibility Engine MCP Server".to_string();
-        let website_url = "https://github.com/alpha-hack-program/compatibility-engine-mcp-rs.git".to_string();
+    #[tokio::test]
+    async fn test_validate_vote_record_reports_all_inconsistencies() {
+        let engine = CompatibilityEngine::new();
+        let params = ValidateVoteRecordParams {
+            eligible_voters: "100".into(),
+            turnout: "120".into(),
+            yes_votes: "80".into(),
+            no_votes: "-5".into(),
+            ..Default::default()
+        };
+
+        let result = engine.validate_vote_record(Parameters(params)).await;
+        assert!(result.is_ok());
+
+        let call_result = result.unwrap();
+        // Inconsistencies are reported in a successful response, not as a tool error
+        assert!(!call_result.is_error.unwrap_or(false));
+        let content = call_result.content;
+        let json_text = content[0].raw.as_text().unwrap().text.as_str();
+        let response: ValidateVoteRecordResponse = parse_payload(json_text);
 
-        ServerInfo::new(ServerCapabilities::builder().enable_tools().build())
-            .with_instructions(
-                "Compatibility Engine providing five calculation and eligibility functions:\
-                 \n\n1. calc_penalty - Calculate penalty with cap and interest\
-                 \n2. calc_tax - Calculate progressive tax with surcharge\
-                 \n3. check_voting - Check voting proposal eligibility\
-                 \n4. distribute_waterfall - Distribute cash in waterfall structure\
-                 \n5. check_housing_grant - Check housing grant eligibility\
-                 \n\nAll functions are strongly typed and provide explicit calculations.",
-            )
-            .with_server_info(
-                Implementation::new(name, version)
-                    .with_title(title)
-                    .with_description(
-                        "Compatibility Engine MCP Server with 5 calculation and eligibility functions",
-                    )
-                    .with_website_url(website_url),
-            )
+        assert!(!response.valid);
+        let codes: Vec<&str> = response.inconsistencies.iter().map(|i| i.code.as_str()).collect();
+        assert_eq!(codes, vec!["negative_count", "turnout_exceeds_eligible", "tally_mismatch"]);
+        assert!(response.warnings.iter().any(|w| w.contains("abstain_votes not provided")));
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_validate_vote_record_native_types() {
+        let json_data = r#"{
+            "eligible_voters": 100,
+            "turnout": 70,
+            "yes_votes": 55,
+            "no_votes": "15",
+            "abstain_votes": 0
+        }"#;
+
+        let params: ValidateVoteRecordParams = serde_json::from_str(json_data).unwrap();
+        assert_eq!(params.no_votes.0, Ok(15));
+        assert_eq!(params.abstain_votes, Some(0.into()));
+        assert_eq!(params.invalid_votes, None);
+    }
 
     #[tokio::test]
-    async fn test_calc_penalty() {
+    async fn test_calc_penalty_small_amount() {
         let engine = CompatibilityEngine::new();
         let params = CalcPenaltyParams {
-            days_late: "12".to_string(),
+            days_late: "10".into(),
             ..Default::default()
         };
         
@@ -1421,20 +14394,20 @@ mod tests {
         let call_result = result.unwrap();
         let content = call_result.content;
         let json_text = content[0].raw.as_text().unwrap().text.as_str();
-        let response: CalcPenaltyResponse = serde_json::from_str(json_text).unwrap();
+        let response: CalcPenaltyResponse = parse_payload(json_text);
         
-        // Expected: min(12 * 100, 1000) = 1000, then 1000 + (1000 * 0.05) = 1050
+        // Uses configured defaults: rate_per_day=100.0, cap=1000.0, interest_rate=0.05
+        // Expected: min(10 * 100, 1000) = 1000, then 1000 + (1000 * 0.05) = 1050
         assert_eq!(response.penalty, 1050.0);
         assert!(response.errors.is_empty());
-        assert!(response.explanation.contains("Applied cap"));
-        assert!(response.explanation.contains("Interest"));
     }
 
     #[tokio::test]
-    async fn test_calc_tax() {
+    async fn test_calc_tax_with_surcharge() {
         let engine = CompatibilityEngine::new();
         let params = CalcTaxParams {
-            income: "40000".to_string(),
+            income: "50000".into(),
+            ..Default::default()
         };
         
         let result = engine.calc_tax(Parameters(params)).await;
@@ -1443,352 +14416,1392 @@ mod tests {
         let call_result = result.unwrap();
         let content = call_result.content;
         let json_text = content[0].raw.as_text().unwrap().text.as_str();
-        let response: CalcTaxResponse = serde_json::from_str(json_text).unwrap();
+        let response: CalcTaxResponse = parse_payload(json_text);
         
-        // Expected: 10000 * 0.10 + 30000 * 0.20 = 1000 + 6000 = 7000
-        // Surcharge: 7000 > 5000 (surcharge_threshold), so 7000 + (7000 * 0.02) = 7,140
-        assert_eq!(response.tax, 7140.0);
+        // Uses configured defaults: thresholds=[10000], rates=[0.10,0.20]
+        // surcharge_threshold=5000, surcharge_rate=0.02
+        // Expected: 10000 * 0.10 + 40000 * 0.20 = 1000 + 8000 = 9000
+        // Surcharge: 9000 > 5000, so 9000 + (9000 * 0.02) = 9000 + 180 = 9,180
+        assert_eq!(response.tax, 9180.0);
         assert!(response.errors.is_empty());
-        assert!(response.explanation.contains("Bracket 1"));
-        assert!(response.explanation.contains("Surcharge applied"));
     }
 
     #[tokio::test]
-    async fn test_check_voting_amendment_passes() {
+    async fn test_string_parsing_with_commas() {
         let engine = CompatibilityEngine::new();
-        let params = CheckVotingParams {
-            eligible_voters: "100".to_string(),
-            turnout: "70".to_string(),
-            yes_votes: "55".to_string(),
-            proposal_type: "amendment".to_string(),
+        let params = CalcTaxParams {
+            income: "40,000.00".into(), // Test comma-separated thousands,
+            ..Default::default()
         };
         
-        let result = engine.check_voting(Parameters(params)).await;
+        let result = engine.calc_tax(Parameters(params)).await;
         assert!(result.is_ok());
         
         let call_result = result.unwrap();
+        assert!(!call_result.is_error.unwrap_or(false));
         let content = call_result.content;
         let json_text = content[0].raw.as_text().unwrap().text.as_str();
-        let response: CheckVotingResponse = serde_json::from_str(json_text).unwrap();
+        let response: CalcTaxResponse = parse_payload(json_text);
         
-        // Expected: turnout = 70%, yes% = 55/70 = 78.6% ≥ 66.67%, passes
-        assert_eq!(response.passes, true);
+        // Should parse as 40000.0 and give same result
+        assert_eq!(response.tax, 7140.0);
         assert!(response.errors.is_empty());
-        assert!(response.explanation.contains("70.0%"));
-        assert!(response.explanation.contains("PASSED"));
     }
 
     #[tokio::test]
-    async fn test_distribute_waterfall() {
+    async fn test_string_parsing_with_dollar_sign() {
         let engine = CompatibilityEngine::new();
         let params = DistributeWaterfallParams {
-            cash_available: "15000000".to_string(),
-            senior_debt: "8000000".to_string(),
-            junior_debt: "10000000".to_string(),
+            cash_available: "$15,000,000".into(), // Test dollar sign and commas
+            senior_debt: "$8000000".into(),
+            junior_debt: "$10,000,000.00".into(),
+            ..Default::default()
         };
         
         let result = engine.distribute_waterfall(Parameters(params)).await;
         assert!(result.is_ok());
         
         let call_result = result.unwrap();
+        assert!(!call_result.is_error.unwrap_or(false));
         let content = call_result.content;
         let json_text = content[0].raw.as_text().unwrap().text.as_str();
-        let response: DistributeWaterfallResponse = serde_json::from_str(json_text).unwrap();
+        let response: DistributeWaterfallResponse = parse_payload(json_text);
         
-        // Expected: senior = 8M, junior = 7M, equity = 0
+        // Should parse correctly and give expected result
         assert_eq!(response.distribution.senior, 8_000_000.0);
         assert_eq!(response.distribution.junior, 7_000_000.0);
         assert_eq!(response.distribution.equity, 0.0);
         assert!(response.errors.is_empty());
-        assert!(response.explanation.contains("Senior debt: 8000000.00 fully paid"));
-        assert!(response.explanation.contains("Junior debt: 7000000.00 partially paid"));
     }
 
     #[tokio::test]
-    async fn test_check_housing_grant_eligible() {
+    async fn test_string_parsing_invalid_format() {
         let engine = CompatibilityEngine::new();
-        let params = CheckHousingGrantParams {
-            ami: "50000".to_string(),
-            household_size: "5".to_string(),
-            income: "32000".to_string(),
-            has_other_subsidy: "false".to_string(),
+        let params = CalcPenaltyParams {
+            days_late: "not-a-number".into(), // Invalid format
+            ..Default::default()
         };
         
-        let result = engine.check_housing_grant(Parameters(params)).await;
+        let result = engine.calc_penalty(Parameters(params)).await;
+        assert!(result.is_ok());
+        
+        let call_result = result.unwrap();
+        assert!(call_result.is_error.unwrap_or(false));
+        let content = call_result.content;
+        let error_text = content[0].raw.as_text().unwrap().text.as_str();
+        assert!(error_text.contains("Invalid days_late parameter"));
+        assert!(error_text.contains("Cannot parse 'not-a-number' as a number"));
+    }
+
+    #[tokio::test]
+    async fn test_string_parsing_empty_string() {
+        let engine = CompatibilityEngine::new();
+        let params = CheckVotingParams {
+            eligible_voters: "".into(), // Empty string
+            turnout: "70".into(),
+            yes_votes: "55".into(),
+            proposal_type: "general".to_string(),
+            ..Default::default()
+        };
+        
+        let result = engine.check_voting(Parameters(params)).await;
+        assert!(result.is_ok());
+        
+        let call_result = result.unwrap();
+        assert!(call_result.is_error.unwrap_or(false));
+        let content = call_result.content;
+        let error_text = content[0].raw.as_text().unwrap().text.as_str();
+        let needs: NeedsInputResponse = serde_json::from_str(error_text).unwrap();
+        assert_eq!(needs.status, "needs_input");
+        assert_eq!(needs.missing, vec!["eligible_voters".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_needs_input_for_placeholder_values() {
+        let engine = CompatibilityEngine::new();
+        let params = CalcTaxParams {
+            income: " Unknown ".into(),
+            ..Default::default()
+        };
+
+        let call_result = engine.calc_tax(Parameters(params)).await.unwrap();
+        assert!(call_result.is_error.unwrap_or(false));
+        let error_text = call_result.content[0].raw.as_text().unwrap().text.as_str();
+        let needs: NeedsInputResponse = serde_json::from_str(error_text).unwrap();
+        assert_eq!(needs.tool, "calc_tax");
+        assert_eq!(needs.missing, vec!["income".to_string()]);
+        assert_eq!(needs.requested_schema["required"], serde_json::json!(["income"]));
+        assert!(needs.requested_schema["properties"]["income"]["description"].is_string());
+
+        // Unparseable values that are not placeholders still get the parse error
+        let params = CalcTaxParams {
+            income: "lots".into(),
+            ..Default::default()
+        };
+        let call_result = engine.calc_tax(Parameters(params)).await.unwrap();
+        let error_text = call_result.content[0].raw.as_text().unwrap().text.as_str();
+        assert!(error_text.contains("Invalid income parameter"));
+    }
+
+    #[tokio::test]
+    async fn test_calculator_registry_serves_every_tool() {
+        let mut registered: Vec<String> = calculators().map(|calculator| calculator.name()).collect();
+        let mut routed: Vec<String> = CompatibilityEngine::tool_router()
+            .list_all()
+            .into_iter()
+            .map(|tool| tool.name.to_string())
+            .collect();
+        registered.sort();
+        routed.sort();
+        assert_eq!(registered, routed);
+        assert!(calculators().all(|calculator| !calculator.logic().is_empty()));
+
+        let calc_tax = calculator("calc_tax").unwrap();
+        let missing = serde_json::json!({ "loss_carryforward": 100 });
+        let error = calc_tax.validate(missing.as_object().unwrap()).unwrap_err();
+        assert!(error.message.contains("missing field `income`"));
+
+        let engine = CompatibilityEngine::new();
+        let arguments = serde_json::json!({ "income": "52000", "response_version": 1 });
+        let call_result = calc_tax
+            .compute(&engine, arguments.as_object().unwrap().clone())
+            .await
+            .unwrap();
+        let response: CalcTaxResponse =
+            serde_json::from_str(&call_result.content[0].raw.as_text().unwrap().text).unwrap();
+        assert!(response.tax > 0.0);
+    }
+
+    #[test]
+    fn test_tools_are_annotated_read_only() {
+        for tool in CompatibilityEngine::tool_router().list_all() {
+            let annotations = tool.annotations.as_ref().unwrap();
+            assert!(tool.title.is_some(), "{} has no title", tool.name);
+            assert_eq!(annotations.title, tool.title);
+            assert_eq!(annotations.read_only_hint, Some(!STORE_WRITING.contains(&tool.name.as_ref())), "{}", tool.name);
+            assert_eq!(annotations.destructive_hint, Some(DESTRUCTIVE.contains(&tool.name.as_ref())), "{}", tool.name);
+            assert_eq!(annotations.idempotent_hint, Some(true));
+            assert_eq!(annotations.open_world_hint, Some(false));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_tools_by_category() {
+        for tool in CompatibilityEngine::tool_router().list_all() {
+            let meta = tool.meta.as_ref().unwrap();
+            let category = meta.0.get("category").and_then(|value| value.as_str()).unwrap();
+            assert!(ToolCategory::parse(category).is_ok(), "{} has category {}", tool.name, category);
+        }
+
+        let engine = CompatibilityEngine::new();
+        let result = engine.list_tools_by_category(Parameters(ListToolsByCategoryParams {
+            category: Some(" Governance ".to_string()),
+            ..Default::default()
+        })).await.unwrap();
+        let response: ListToolsByCategoryResponse =
+            parse_payload(result.content[0].raw.as_text().unwrap().text.as_str());
+        assert_eq!(response.categories.len(), 1);
+        assert_eq!(response.categories[0].category, ToolCategory::Governance);
+        let names: Vec<&str> = response.categories[0].tools.iter().map(|tool| tool.name.as_str()).collect();
+        assert_eq!(names, ["check_voting", "validate_vote_record", "check_voting_bulk", "project_quorum"]);
+        assert_eq!(response.total_tools, 4);
+
+        let result = engine.list_tools_by_category(Parameters(ListToolsByCategoryParams::default())).await.unwrap();
+        let response: ListToolsByCategoryResponse =
+            parse_payload(result.content[0].raw.as_text().unwrap().text.as_str());
+        assert_eq!(response.categories.len(), ToolCategory::ALL.len());
+        assert_eq!(response.total_tools, calculators().count());
+
+        let result = engine.list_tools_by_category(Parameters(ListToolsByCategoryParams {
+            category: Some("weather".to_string()),
+            ..Default::default()
+        })).await.unwrap();
+        assert_eq!(result.is_error, Some(true));
+    }
+
+    #[test]
+    fn test_complete_argument() {
+        let completion = complete_argument(Some("check_voting"), "proposal_type", "");
+        assert_eq!(completion.values, vec!["general".to_string(), "amendment".to_string()]);
+        assert_eq!(completion.total, Some(2));
+        assert_eq!(completion.has_more, Some(false));
+
+        let completion = complete_argument(Some("check_voting"), "proposal_type", " AM");
+        assert_eq!(completion.values, vec!["amendment".to_string()]);
+
+        // Currencies and fee codes come from the loaded configuration
+        let completion = complete_argument(Some("convert_currency"), "to_currency", "");
+        assert!(completion.values.contains(&config().fx.base_currency));
+        let completion = complete_argument(None, "fee_code", "");
+        assert_eq!(completion.values.len(), config().fee_schedules.len());
+
+        // A tool only completes its own parameters
+        assert!(complete_argument(Some("calc_tax"), "proposal_type", "").values.is_empty());
+        assert!(complete_argument(Some("no_such_tool"), "proposal_type", "").values.is_empty());
+        assert!(complete_argument(None, "income", "").values.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_golden_calls_pass() {
+        let outcomes = run_golden_calls(&CompatibilityEngine::new()).await;
+        let golden = calculators().filter(|calculator| !GOLDEN_EXCLUDED.contains(&calculator.tool().name.as_ref()));
+        assert_eq!(outcomes.len(), golden.count());
+        let failures: Vec<&GoldenOutcome> = outcomes.iter().filter(|outcome| outcome.failure.is_some()).collect();
+        assert!(failures.is_empty(), "golden calls failed: {:?}", failures);
+    }
+
+    #[test]
+    fn test_client_profile_negotiation_and_shaping() {
+        let request: InitializeRequestParams = serde_json::from_value(serde_json::json!({
+            "protocolVersion": "2025-06-18",
+            "capabilities": { "sampling": {} },
+            "clientInfo": { "name": "desk", "version": "1.2.0" },
+        }))
+        .unwrap();
+        let mut server: ServerInfo = CompatibilityEngine::new().get_info();
+        server.protocol_version = serde_json::from_value(serde_json::json!("2025-03-26")).unwrap();
+        let old = ClientProfile::negotiate(&request, &server);
+        assert_eq!(old.protocol_version, "2025-03-26");
+        assert!(!old.structured_content);
+        assert!(old.sampling);
+        assert_eq!(old.client_name, "desk");
+
+        let new = ClientProfile { structured_content: true, ..old.clone() };
+        let result = CallToolResult::success(vec![Content::text(r#"{"schema_version": 2, "payload": {}}"#)]);
+        assert_eq!(old.shape(result.clone()).structured_content, None);
+        let shaped = new.shape(result);
+        assert_eq!(shaped.structured_content.unwrap()["schema_version"], 2);
+        assert_eq!(shaped.content.len(), 1);
+        let error = CallToolResult::error(vec![Content::text(r#"{"status": "needs_input"}"#)]);
+        assert_eq!(new.shape(error).structured_content, None);
+
+        let response = CompatibilityEngine::describe_capabilities_internal(Some(&new), true, Audience::Expert);
+        assert!(response.negotiated);
+        assert!(response.structured_content);
+        assert!(response.sampling_summary);
+        assert!(!response.resource_links);
+        let response = CompatibilityEngine::describe_capabilities_internal(None, true, Audience::Expert);
+        assert!(!response.negotiated && !response.structured_content && !response.sampling_summary);
+        assert_eq!(response.warnings.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_string_parsing_with_whitespace() {
+        let engine = CompatibilityEngine::new();
+        let params = CalcPenaltyParams {
+            days_late: "  12.5  ".into(), // Test whitespace trimming
+            ..Default::default()
+        };
+        
+        let result = engine.calc_penalty(Parameters(params)).await;
         assert!(result.is_ok());
         
         let call_result = result.unwrap();
+        assert!(!call_result.is_error.unwrap_or(false));
         let content = call_result.content;
         let json_text = content[0].raw.as_text().unwrap().text.as_str();
-        let response: CheckHousingGrantResponse = serde_json::from_str(json_text).unwrap();
+        let response: CalcPenaltyResponse = parse_payload(json_text);
         
-        // Expected: threshold = 0.60 * 50000 * 1.10 = 33000, income 32000 ≤ 33000, eligible
-        assert_eq!(response.eligible, true);
+        // Should parse as 12.5 and calculate penalty
+        assert!(response.penalty > 0.0);
         assert!(response.errors.is_empty());
-        assert!(response.explanation.contains("5 > 4, threshold increased by 10%"));
-        assert!(response.explanation.contains("ELIGIBLE"));
     }
 
-    #[tokio::test]
-    async fn test_check_housing_grant_not_eligible_income() {
-        let engine = CompatibilityEngine::new();
-        let params = CheckHousingGrantParams {
-            ami: "50000".to_string(),
-            household_size: "5".to_string(),
-            income: "34000".to_string(),
-            has_other_subsidy: "false".to_string(),
+    #[tokio::test]
+    async fn test_response_envelope_fields() {
+        let engine = CompatibilityEngine::new();
+        let params = CalcTaxParams {
+            income: "40000".into(),
+            ..Default::default()
+        };
+
+        let call_result = engine.calc_tax(Parameters(params)).await.unwrap();
+        let json_text = call_result.content[0].raw.as_text().unwrap().text.as_str();
+        let envelope: ResponseEnvelope<CalcTaxResponse> = serde_json::from_str(json_text).unwrap();
+
+        assert_eq!(envelope.schema_version, 2);
+        assert_eq!(envelope.tool, "calc_tax");
+        assert!(chrono::DateTime::parse_from_rfc3339(&envelope.generated_at).is_ok());
+        assert_eq!(envelope.payload.tax, 7140.0);
+    }
+
+    #[tokio::test]
+    async fn test_response_version_1_returns_bare_payload() {
+        let engine = CompatibilityEngine::new();
+        let params: CalcTaxParams = serde_json::from_str(r#"{"income": 40000, "response_version": 1}"#).unwrap();
+
+        let call_result = engine.calc_tax(Parameters(params)).await.unwrap();
+        let json_text = call_result.content[0].raw.as_text().unwrap().text.as_str();
+        let response: CalcTaxResponse = serde_json::from_str(json_text).unwrap();
+
+        assert_eq!(response.tax, 7140.0);
+        assert!(!json_text.contains("schema_version"));
+    }
+
+    #[tokio::test]
+    async fn test_fields_projection() {
+        let engine = CompatibilityEngine::new();
+        let params: CalcTaxParams = serde_json::from_str(r#"{"income": 40000, "fields": ["tax", " errors "]}"#).unwrap();
+        let call_result = engine.calc_tax(Parameters(params)).await.unwrap();
+        let json_text = call_result.content[0].raw.as_text().unwrap().text.as_str();
+        let envelope: serde_json::Value = serde_json::from_str(json_text).unwrap();
+        assert_eq!(envelope["tool"], "calc_tax");
+        assert_eq!(envelope["payload"], serde_json::json!({ "tax": 7140.0, "errors": [] }));
+        // The whole payload stays available for references
+        let result_id = envelope["result_id"].as_str().unwrap();
+        assert!(result_store::resolve(&format!("$result:{}.taxable_income", result_id)).unwrap().is_ok());
+
+        let params: DistributeWaterfallParams = serde_json::from_str(
+            r#"{"cash_available": 1000, "senior_debt": 600, "junior_debt": 300, "fields": ["distribution.senior", "distribution.junior_shortfall"], "response_version": 1}"#,
+        )
+        .unwrap();
+        let call_result = engine.distribute_waterfall(Parameters(params)).await.unwrap();
+        let json_text = call_result.content[0].raw.as_text().unwrap().text.as_str();
+        let payload: serde_json::Value = serde_json::from_str(json_text).unwrap();
+        assert_eq!(payload, serde_json::json!({ "distribution": { "senior": 600.0, "junior_shortfall": 0.0 } }));
+
+        for (fields, expected) in [
+            (r#"["tax", "refund"]"#, "unknown field 'refund' (available: "),
+            (r#"["tax.amount"]"#, "'tax.amount' has no nested fields"),
+            (r#"["tax."]"#, "'tax.' is not a field name"),
+            ("[]", "list at least one field"),
+        ] {
+            let params: CalcTaxParams =
+                serde_json::from_str(&format!(r#"{{"income": 40000, "fields": {}}}"#, fields)).unwrap();
+            let call_result = engine.calc_tax(Parameters(params)).await.unwrap();
+            assert!(call_result.is_error.unwrap_or(false));
+            let error_text = call_result.content[0].raw.as_text().unwrap().text.as_str();
+            assert!(error_text.starts_with("Invalid fields parameter"), "{}", error_text);
+            assert!(error_text.contains(expected), "{}", error_text);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_soft_limits() {
+        let engine = CompatibilityEngine::new();
+        let params: CalcPenaltyParams = serde_json::from_str(r#"{"days_late": "5,000"}"#).unwrap();
+        let result = engine.calc_penalty(Parameters(params)).await.unwrap();
+        assert!(result.is_error.unwrap_or(false));
+        let needs: NeedsConfirmationResponse =
+            serde_json::from_str(&result.content[0].raw.as_text().unwrap().text).unwrap();
+        assert_eq!(needs.status, "needs_confirmation");
+        assert_eq!(needs.warnings, [SoftLimitWarning { parameter: "days_late".to_string(), value: 5000.0, limit: 3650.0 }]);
+        assert!(needs.message.contains("acknowledge_warnings: true"));
+
+        // Acknowledged, the call goes through and says what was accepted
+        let params: CalcPenaltyParams =
+            serde_json::from_str(r#"{"days_late": "5,000", "acknowledge_warnings": "yes"}"#).unwrap();
+        let result = engine.calc_penalty(Parameters(params)).await.unwrap();
+        let response: CalcPenaltyResponse = parse_payload(&result.content[0].raw.as_text().unwrap().text);
+        assert_eq!(response.capped_penalty, 1000.0);
+        assert!(response.assumptions.iter().any(|assumption| assumption.code == "soft_limit_acknowledged"
+            && assumption.field == "days_late"));
+
+        // Limits apply by name to list items too, and only beyond the limit
+        let inputs = BTreeMap::from([
+            ("beneficiaries[1].income".to_string(), serde_json::json!(-2e7)),
+            ("income".to_string(), serde_json::json!(1e7)),
+        ]);
+        assert_eq!(soft_limit_warnings(&inputs).len(), 1);
+        assert_eq!(soft_limit_warnings(&inputs)[0].parameter, "beneficiaries[1].income");
+
+        assert_eq!(parse_soft_limits(r#"{"amount": 5e6}"#), Ok(BTreeMap::from([("amount".to_string(), 5e6)])));
+        assert!(parse_soft_limits(r#"{"amount": 0}"#).is_err());
+        assert!(parse_soft_limits(r#"[3650]"#).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_evaluate_ranges() {
+        let engine = CompatibilityEngine::new();
+        async fn evaluate(engine: &CompatibilityEngine, arguments: serde_json::Value) -> Result<EvaluateRangesResponse, String> {
+            let params: EvaluateRangesParams = serde_json::from_value(arguments).unwrap();
+            let result = engine.evaluate_ranges(Parameters(params)).await.unwrap();
+            let text = &result.content[0].raw.as_text().unwrap().text;
+            if result.is_error.unwrap_or(false) { Err(text.clone()) } else { Ok(parse_payload(text)) }
+        }
+
+        // The income threshold for five people at 80000 AMI is 52800, inside the range
+        let response = evaluate(&engine, serde_json::json!({
+            "tool": "check_housing_grant",
+            "arguments": {"ami": 80000, "household_size": 5, "income": {"min": 50000, "max": "56,000"}, "has_other_subsidy": false},
+        })).await.unwrap();
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        assert_eq!(response.ranges, [InputRange { parameter: "income".to_string(), min: 50000.0, max: 56000.0 }]);
+        assert_eq!(response.evaluations, 2);
+        assert_eq!(response.flips, ["eligible"]);
+        let threshold = response.outcomes.iter().find(|outcome| outcome.field == "threshold").unwrap();
+        assert!(!threshold.varies);
+
+        // Two ranges: four corners, numbers move but nothing flips
+        let response = evaluate(&engine, serde_json::json!({
+            "tool": "calc_penalty",
+            "arguments": {"days_late": {"min": 1, "max": 10}, "rate_per_day": {"min": 5, "max": 10}},
+        })).await.unwrap();
+        assert_eq!(response.evaluations, 4);
+        let penalty = response.outcomes.iter().find(|outcome| outcome.field == "capped_penalty").unwrap();
+        assert_eq!((penalty.min.as_f64(), penalty.max.as_f64()), (Some(5.0), Some(100.0)));
+        assert!(penalty.varies && response.flips.is_empty());
+
+        // Failures at a range end name the end
+        let error = evaluate(&engine, serde_json::json!({"tool": "calc_penalty", "arguments": {"days_late": {"min": -5, "max": 10}}})).await.unwrap_err();
+        assert!(error.contains("days_late = -5"), "{}", error);
+        let error = evaluate(&engine, serde_json::json!({"tool": "calc_tax", "arguments": {"income": {"min": 5, "max": 1}}})).await.unwrap_err();
+        assert!(error.contains("above max"), "{}", error);
+        let error = evaluate(&engine, serde_json::json!({"tool": "calc_tax", "arguments": {"income": 40000}})).await.unwrap_err();
+        assert!(error.contains("at least one"), "{}", error);
+
+        for (tool, arguments) in [
+            ("get_usage_report", serde_json::json!({})),
+            ("evaluate_ranges", serde_json::json!({})),
+            ("calc_tax", serde_json::json!({"income": {"min": 1}})),
+        ] {
+            assert!(evaluate(&engine, serde_json::json!({"tool": tool, "arguments": arguments})).await.is_err(), "{}", tool);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_simulate_uncertainty() {
+        let engine = CompatibilityEngine::new();
+        async fn simulate(engine: &CompatibilityEngine, arguments: serde_json::Value) -> Result<SimulateUncertaintyResponse, String> {
+            let params: SimulateUncertaintyParams = serde_json::from_value(arguments).unwrap();
+            let result = engine.simulate_uncertainty(Parameters(params)).await.unwrap();
+            let text = &result.content[0].raw.as_text().unwrap().text;
+            if result.is_error.unwrap_or(false) { Err(text.clone()) } else { Ok(parse_payload(text)) }
+        }
+
+        // Income centred on the 52800 limit for five people qualifies about half the time
+        let arguments = serde_json::json!({
+            "tool": "check_housing_grant",
+            "arguments": {"ami": 80000, "household_size": 5, "income": {"distribution": "normal", "mean": 52800, "sd": 2000}, "has_other_subsidy": false},
+            "seed": 3,
+        });
+        let response = simulate(&engine, arguments.clone()).await.unwrap();
+        assert_eq!((response.draws, response.seed), (1000, 3));
+        let eligible = response.probabilities.iter().find(|outcome| outcome.field == "eligible").unwrap();
+        assert!((0.4..0.6).contains(&eligible.probability), "{}", eligible.probability);
+        let margin = response.outcomes.iter().find(|outcome| outcome.field == "income_margin").unwrap();
+        assert!(margin.p5 < -2000.0 && margin.p95 > 2000.0 && margin.p25 <= margin.p50 && margin.p50 <= margin.p75);
+        assert!(response.assumptions.iter().any(|assumption| assumption.field == "draws"));
+        // Seeded: the same call gives the same answer
+        assert_eq!(simulate(&engine, arguments).await.unwrap().outcomes, response.outcomes);
+
+        let response = simulate(&engine, serde_json::json!({
+            "tool": "calc_tax",
+            "arguments": {"income": {"distribution": "uniform", "min": 30000, "max": "50,000"}},
+            "draws": 200,
+        })).await.unwrap();
+        let income = response.outcomes.iter().find(|outcome| outcome.field == "taxable_income").unwrap();
+        assert!(income.p5 >= 30000.0 && income.p95 <= 50000.0 && (income.mean - 40000.0).abs() < 2000.0, "{:?}", income);
+
+        // Failed draws name the values
+        let error = simulate(&engine, serde_json::json!({
+            "tool": "calc_penalty",
+            "arguments": {"days_late": {"distribution": "uniform", "min": -10, "max": 0}},
+        })).await.unwrap_err();
+        assert!(error.contains("draw 1 (days_late = -"), "{}", error);
+        let error = simulate(&engine, serde_json::json!({"tool": "calc_tax", "arguments": {"income": {"distribution": "normal", "mean": 1, "sd": -1}}})).await.unwrap_err();
+        assert!(error.contains("must not be negative"), "{}", error);
+
+        for arguments in [
+            serde_json::json!({"tool": "calc_tax", "arguments": {"income": 40000}}),
+            serde_json::json!({"tool": "calc_tax", "arguments": {"income": {"distribution": "normal", "mean": 1, "sd": 1}}, "draws": 0}),
+            serde_json::json!({"tool": "calc_tax", "arguments": {"income": {"distribution": "lognormal", "mean": 1, "sd": 1}}}),
+            serde_json::json!({"tool": "calc_tax", "arguments": {"income": {"distribution": "uniform", "min": 1}}}),
+            serde_json::json!({"tool": "evaluate_ranges", "arguments": {"income": {"distribution": "uniform", "min": 1, "max": 2}}}),
+        ] {
+            assert!(simulate(&engine, arguments.clone()).await.is_err(), "{}", arguments);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_simulate_uncertainty_export() {
+        let engine = CompatibilityEngine::new();
+        let params: SimulateUncertaintyParams = serde_json::from_value(serde_json::json!({
+            "tool": "check_housing_grant",
+            "arguments": {"ami": 80000, "household_size": 5, "income": {"distribution": "normal", "mean": 52800, "sd": 2000}, "has_other_subsidy": false},
+            "draws": 20,
+            "export": "parquet",
+        }))
+        .unwrap();
+        let result = engine.simulate_uncertainty(Parameters(params)).await.unwrap();
+        let text = &result.content[0].raw.as_text().unwrap().text;
+        if !cfg!(feature = "parquet-export") {
+            assert!(result.is_error.unwrap_or(false));
+            assert!(text.contains("parquet-export feature"), "{}", text);
+            return;
+        }
+        let response: SimulateUncertaintyResponse = parse_payload(text);
+        let exported = response.export.unwrap();
+        assert_eq!(exported.rows, 20);
+        assert_eq!(exported.columns[..2], ["draw".to_string(), "arguments.income".to_string()]);
+        assert!(exported.columns.contains(&"eligible".to_string()));
+        std::fs::remove_file(&exported.path).unwrap();
+    }
+
+    /// The default configuration's results agree with the reference implementations
+    #[tokio::test]
+    async fn test_results_agree_with_reference() {
+        let engine = CompatibilityEngine::new();
+        fn payload(result: CallToolResult) -> serde_json::Value {
+            let envelope: serde_json::Value = serde_json::from_str(&result.content[0].raw.as_text().unwrap().text).unwrap();
+            envelope["payload"].clone()
+        }
+        let agrees = |engine: f64, reference: Option<reference::Reference>| (engine - reference.unwrap().amount()).abs() < 0.05;
+        let rules = tax_rules();
+
+        for amount in [0.0, 0.01, 999.99, 10000.0, 45000.5, 123456.78, 2500000.0] {
+            let params: CalcTaxParams = serde_json::from_value(serde_json::json!({"income": amount, "loss_carryforward": 1000})).unwrap();
+            let tax = payload(engine.calc_tax(Parameters(params)).await.unwrap())["tax"].as_f64().unwrap();
+            let expected = reference::income_tax(amount, 1000.0, &rules.brackets.thresholds, &rules.brackets.rates, &rules.surcharges, rules.surcharge_base);
+            assert!(agrees(tax, expected), "calc_tax {}: {} vs {:?}", amount, tax, expected);
+
+            for method in ["slice", "marginal"] {
+                let params: CalcTransferTaxParams =
+                    serde_json::from_value(serde_json::json!({"price": amount, "method": method})).unwrap();
+                let tax = payload(engine.calc_transfer_tax(Parameters(params)).await.unwrap())["tax"].as_f64().unwrap();
+                let expected = reference::transfer_tax(amount, &config().transfer_tax_thresholds, &config().transfer_tax_rates, method == "slice");
+                assert!(agrees(tax, expected), "calc_transfer_tax {} {}: {} vs {:?}", method, amount, tax, expected);
+            }
+
+            let arguments = serde_json::json!({"assessed_value": amount, "prior_year_value": amount * 0.9, "homestead": true});
+            let params: CalcPropertyTaxParams = serde_json::from_value(arguments).unwrap();
+            let tax = payload(engine.calc_property_tax(Parameters(params)).await.unwrap())["tax"].as_f64().unwrap();
+            let expected = reference::property_tax(
+                amount,
+                Some(amount * 0.9),
+                Some(config().property_homestead_exemption),
+                config().property_assessment_cap,
+                config().default_property_millage,
+            );
+            if expected.is_some() {
+                assert!(agrees(tax, expected), "calc_property_tax {}: {} vs {:?}", amount, tax, expected);
+            }
+        }
+        for days in [0.0, 1.0, 7.5, 9.0, 11.0, 365.0] {
+            let params: CalcPenaltyParams = serde_json::from_value(serde_json::json!({"days_late": days, "interest_rate": 0.07})).unwrap();
+            let penalty = payload(engine.calc_penalty(Parameters(params)).await.unwrap())["penalty"].as_f64().unwrap();
+            let expected = reference::penalty(days, config().default_rate_per_day, config().default_cap, 0.07);
+            assert!(agrees(penalty, expected), "calc_penalty {}: {} vs {:?}", days, penalty, expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_input_hash() {
+        let engine = CompatibilityEngine::new();
+        async fn hash(engine: &CompatibilityEngine, arguments: &str) -> Option<String> {
+            let params: CalcPenaltyParams = serde_json::from_str(arguments).unwrap();
+            let call_result = engine.calc_penalty(Parameters(params)).await.unwrap();
+            let json_text = call_result.content[0].raw.as_text().unwrap().text.as_str();
+            let envelope: serde_json::Value = serde_json::from_str(json_text).unwrap();
+            envelope["input_hash"].as_str().map(str::to_string)
+        }
+
+        let plain = hash(&engine, r#"{"days_late": 12, "cap": 1000}"#).await.unwrap();
+        assert_eq!(plain.len(), 64);
+        // Same parsed values, spelled differently or with presentation parameters
+        assert_eq!(hash(&engine, r#"{"days_late": "12", "cap": "1,000"}"#).await.unwrap(), plain);
+        assert_eq!(hash(&engine, r#"{"days_late": 12, "cap": "1k", "audience": "citizen", "context": {"case_id": "C-1"}}"#).await.unwrap(), plain);
+        // Omitted defaults hash like the default given explicitly
+        let rate_per_day = config().default_rate_per_day;
+        assert_eq!(
+            hash(&engine, &format!(r#"{{"days_late": 12, "cap": 1000, "rate_per_day": {}}}"#, rate_per_day)).await.unwrap(),
+            plain
+        );
+        assert_ne!(hash(&engine, r#"{"days_late": 13, "cap": 1000}"#).await.unwrap(), plain);
+        // Legacy payloads have no envelope to carry it
+        assert_eq!(hash(&engine, r#"{"days_late": 12, "cap": 1000, "response_version": 1}"#).await, None);
+
+        let mut arguments = serde_json::json!({ "milestones": [{ "name": "Design", "completed": "yes" }] });
+        set_parsed(&mut arguments, "milestones[0].completed", serde_json::json!(true));
+        set_parsed(&mut arguments, "milestones[3].completed", serde_json::json!(true));
+        set_parsed(&mut arguments, "dispute_pending", serde_json::json!(false));
+        assert_eq!(
+            arguments,
+            serde_json::json!({ "milestones": [{ "name": "Design", "completed": true }], "dispute_pending": false })
+        );
+    }
+
+    #[test]
+    fn test_legacy_migration() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| vars.iter().find(|(key, _)| *key == name).map(|(_, value)| value.to_string())
+        };
+
+        let output = EngineConfig::profile_from_vars(&env(&[
+            ("ENGINE_DEFAULT_THRESHOLDS", "10000, 40000"),
+            ("ENGINE_DEFAULT_RATES", "0.1,0.2,0.3"),
+            ("ENGINE_DEFAULT_SURCHARGE_RATE", "0.03"),
+        ]));
+        let profile: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(
+            profile["ENGINE_TAX_BRACKETS"]["value"],
+            serde_json::json!([{ "up_to": 10000.0, "rate": 0.1 }, { "up_to": 40000.0, "rate": 0.2 }, { "rate": 0.3 }])
+        );
+        assert_eq!(
+            profile["ENGINE_TAX_BRACKETS"]["note"],
+            "Replaces the deprecated ENGINE_DEFAULT_THRESHOLDS=10000, 40000 and ENGINE_DEFAULT_RATES=0.1,0.2,0.3"
+        );
+        assert_eq!(profile["ENGINE_SURCHARGES"]["value"], serde_json::json!([{ "name": "Surcharge", "threshold": 5000.0, "rate": 0.03 }]));
+        assert_eq!(profile["ENGINE_SURCHARGES"]["source"], PROFILE_SOURCE);
+        assert!(profile.get("ENGINE_DEFAULT_RATES").is_none());
+
+        // Settings that cannot be replaced, or are overridden, are kept with a note
+        let output = EngineConfig::profile_from_vars(&env(&[
+            ("ENGINE_DEFAULT_RATES", "0.1,0.2,0.3"),
+            ("ENGINE_SURCHARGES", "[]"),
+            ("ENGINE_DEFAULT_SURCHARGE_THRESHOLD", "100"),
+        ]));
+        let profile: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(
+            profile["ENGINE_DEFAULT_RATES"]["note"],
+            "Deprecated; cannot be replaced by ENGINE_TAX_BRACKETS: 3 rates for 1 thresholds (expected 2)"
+        );
+        assert_eq!(
+            profile["ENGINE_DEFAULT_SURCHARGE_THRESHOLD"]["note"],
+            "Deprecated; only used while ENGINE_SURCHARGES or ENGINE_SURCHARGES_FILE is invalid"
+        );
+        assert_eq!(profile["ENGINE_SURCHARGES"]["value"], serde_json::json!([]));
+        assert!(profile.get("ENGINE_TAX_BRACKETS").is_none());
+
+        assert_eq!(EngineConfig::profile_from_vars(&env(&[])), "{}\n");
+    }
+
+    #[test]
+    fn test_migrated_profile_loads_identical_config() {
+        let vars = BTreeMap::from([
+            ("ENGINE_DEFAULT_RATE_PER_DAY", "150"),
+            ("ENGINE_DEFAULT_CAP", "2500"),
+            ("ENGINE_DEFAULT_THRESHOLDS", "12000,50000"),
+            ("ENGINE_DEFAULT_RATES", "0.1,0.25,0.4"),
+            ("ENGINE_DEFAULT_SURCHARGE_THRESHOLD", "8000"),
+            ("ENGINE_DEFAULT_SURCHARGE_RATE", "0.015"),
+            ("ENGINE_SURCHARGE_BASE", "income"),
+            ("ENGINE_TAX_CHANGE_DATE", "2025-07-01"),
+            ("ENGINE_TAX_BRACKETS_AFTER_CHANGE", r#"[{"up_to": 15000, "rate": 0.1}, {"rate": 0.3, "cliff": true}]"#),
+            ("ENGINE_AMT_RATE", "0.15"),
+            ("ENGINE_CAPITAL_GAINS_LONG_TERM_DAYS", "730"),
+            ("ENGINE_SOCIAL_CONTRIBUTIONS", r#"[{"name": "Pension", "ceiling": 90000, "employee_rate": 0.09, "employer_rate": 0.1}]"#),
+            ("ENGINE_TRANSFER_TAX_METHOD", "marginal"),
+            ("ENGINE_FEE_SCHEDULES", r#"{"filing": [{"name": "Base", "fixed": 50}]}"#),
+            ("ENGINE_BASE_CURRENCY", "usd"),
+            ("ENGINE_FX_RATES", r#"[{"date": "2025-01-02", "currency": "EUR", "rate": 1.04}]"#),
+            ("ENGINE_STRICT_PARSING", "true"),
+            ("ENGINE_PRICE_INDEX", r#"{"2020": 100, "2025": 118.5}"#),
+            ("ENGINE_INDEX_TARGET_YEAR", "2025"),
+        ]);
+        let read = std::sync::Mutex::new(Vec::new());
+        let env = |name: &str| {
+            read.lock().unwrap().push(name.to_string());
+            vars.get(name).map(|value| value.to_string())
+        };
+        let profile = EngineConfig::profile_from_vars(&env);
+        let settings = EngineConfig::parse_profile(&profile).unwrap();
+        assert!(!settings.contains_key("ENGINE_DEFAULT_THRESHOLDS") && settings.contains_key("ENGINE_TAX_BRACKETS"));
+        let from_profile = |name: &str| settings.get(name).cloned();
+        let (migrated, original) = (LoadedRules::from_vars(&from_profile), LoadedRules::from_vars(&env));
+        assert_eq!(format!("{:?}", migrated.config), format!("{:?}", original.config));
+        assert_eq!(migrated.price_index, original.price_index);
+        assert_eq!(migrated.version, original.version);
+
+        // Every setting the configuration reads can be migrated
+        for name in read.into_inner().unwrap() {
+            assert!(CONFIG_VARS.contains(&name.as_str()), "{} is not in CONFIG_VARS", name);
+        }
+        assert!(EngineConfig::parse_profile(r#"{"ENGINE_LOCALE": {"value": "fr"}}"#).unwrap_err().contains("unknown setting"));
+    }
+
+    #[test]
+    fn test_upsert_profile() {
+        let path = std::env::temp_dir().join(format!("engine-test-profile-{}.json", std::process::id()));
+        let store = path.to_str().unwrap().to_string();
+        let env = |name: &str| match name {
+            "ENGINE_PROFILE_FILE" => Some(store.clone()),
+            "ENGINE_AMT_RATE" => Some("0.15".to_string()),
+            _ => None,
+        };
+        let rules = RwLock::new(LoadedRules::from_vars(&env));
+        let setting = |value: serde_json::Value| ProfileSetting { value, source: Some("Tax Act s. 12".to_string()), note: None };
+        let profile = BTreeMap::from([
+            ("ENGINE_DEFAULT_CAP".to_string(), setting(serde_json::json!("2500"))),
+            ("ENGINE_AMT_RATE".to_string(), setting(serde_json::json!("0.2"))),
+            ("ENGINE_TAX_BRACKETS".to_string(), setting(serde_json::json!([{"up_to": 20000, "rate": 0.1}, {"rate": 0.3}]))),
+        ]);
+
+        let result = CompatibilityEngine::upsert_profile_internal(&profile, &env, &rules, Audience::Expert);
+        assert!(result.applied, "{:?}", result.errors);
+        assert_ne!(result.config_version, result.previous_version);
+        assert_eq!(result.overridden, vec!["ENGINE_AMT_RATE"]);
+        let in_force = rules.read().unwrap();
+        assert_eq!(in_force.version, result.config_version);
+        assert_eq!(in_force.config.default_cap, 2500.0);
+        assert_eq!(in_force.config.default_thresholds, vec![20000.0]);
+        drop(in_force);
+        let saved = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(EngineConfig::parse_profile(&saved).unwrap(), EngineConfig::profile_settings(&profile).unwrap());
+
+        // Rejected by the startup checks: nothing saved, rules unchanged
+        let invalid = BTreeMap::from([("ENGINE_TAX_BRACKETS".to_string(), setting(serde_json::json!([{"up_to": 1000, "rate": 0.1}])))]);
+        let result = CompatibilityEngine::upsert_profile_internal(&invalid, &env, &rules, Audience::Expert);
+        assert!(!result.applied);
+        assert!(result.errors.iter().any(|e| e.starts_with("Startup check failed") && e.contains("up_to")), "{:?}", result.errors);
+        assert_eq!(result.config_version, result.previous_version);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), saved);
+        std::fs::remove_file(&path).unwrap();
+
+        let unknown = BTreeMap::from([("ENGINE_LOCALE".to_string(), setting(serde_json::json!("fr")))]);
+        let result = CompatibilityEngine::upsert_profile_internal(&unknown, &env, &rules, Audience::Expert);
+        assert!(!result.applied && result.errors[0].contains("unknown setting"));
+        let result = CompatibilityEngine::upsert_profile_internal(&profile, &|_: &str| None, &rules, Audience::Expert);
+        assert!(!result.applied && result.errors[0].contains("No profile store"));
+    }
+
+    #[test]
+    fn test_diff_profiles() {
+        let setting = |value: serde_json::Value| ProfileSetting { value, source: None, note: None };
+        let before = BTreeMap::from([
+            ("ENGINE_AMT_RATE".to_string(), setting(serde_json::json!("0.15"))),
+            ("ENGINE_DEFAULT_CAP".to_string(), setting(serde_json::json!("2500"))),
+            ("ENGINE_STRICT_PARSING".to_string(), setting(serde_json::json!("true"))),
+            ("ENGINE_TAX_BRACKETS".to_string(), setting(serde_json::json!([{"up_to": 10000, "rate": 0.1}, {"rate": 0.2}]))),
+        ]);
+        let after = BTreeMap::from([
+            ("ENGINE_AMT_RATE".to_string(), setting(serde_json::json!(0.150))),
+            ("ENGINE_DEFAULT_CAP".to_string(), setting(serde_json::json!("3000"))),
+            ("ENGINE_BASE_CURRENCY".to_string(), setting(serde_json::json!("EUR"))),
+            ("ENGINE_TAX_BRACKETS".to_string(), setting(serde_json::json!(
+                [{"up_to": 12000, "rate": 0.1}, {"up_to": 50000, "rate": 0.22, "cliff": true}, {"rate": 0.3}]
+            ))),
+        ]);
+        let result = CompatibilityEngine::diff_profiles_internal(&before, &after, &price_index(), Audience::Expert);
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+        assert_eq!(result.unchanged, 1);
+        let changes: Vec<(&str, &str)> = result.changes.iter().map(|c| (c.setting.as_str(), c.change.as_str())).collect();
+        assert_eq!(changes, vec![
+            ("ENGINE_BASE_CURRENCY", "added"),
+            ("ENGINE_DEFAULT_CAP", "changed"),
+            ("ENGINE_STRICT_PARSING", "removed"),
+            ("ENGINE_TAX_BRACKETS", "changed"),
+        ]);
+        assert_eq!(result.changes[1].summary, "ENGINE_DEFAULT_CAP: 2500 → 3000");
+
+        let brackets = &result.changes[3].brackets;
+        assert_eq!(brackets.len(), 3);
+        assert_eq!((brackets[0].before_up_to, brackets[0].after_up_to), (Some(10000.0), Some(12000.0)));
+        assert_eq!(brackets[0].summary, "ENGINE_TAX_BRACKETS bracket 1: up to 10000 → 12000");
+        assert_eq!(brackets[1].summary, "ENGINE_TAX_BRACKETS bracket 2: up to no limit → 50000, rate 20.00% → 22.00%, cliff on");
+        assert_eq!((brackets[2].change.as_str(), brackets[2].after_rate), ("added", Some(0.3)));
+        assert_eq!(brackets[2].summary, "ENGINE_TAX_BRACKETS bracket 3 added: top bracket at 30.00%");
+        assert!(result.explanation.starts_with("4 settings differ (1 added, 1 removed, 2 changed), 1 unchanged"));
+
+        // A bracket list that does not validate is still compared as a value
+        let broken = BTreeMap::from([("ENGINE_TAX_BRACKETS".to_string(), setting(serde_json::json!([])))]);
+        let result = CompatibilityEngine::diff_profiles_internal(&before, &broken, &price_index(), Audience::Expert);
+        assert!(result.changes.iter().any(|c| c.setting == "ENGINE_TAX_BRACKETS" && c.brackets.is_empty()));
+        assert!(result.warnings[0].contains("at least one bracket"));
+
+        let unknown = BTreeMap::from([("ENGINE_LOCALE".to_string(), setting(serde_json::json!("fr")))]);
+        let result = CompatibilityEngine::diff_profiles_internal(&unknown, &after, &price_index(), Audience::Expert);
+        assert_eq!(result.errors, vec!["Invalid before profile: unknown setting 'ENGINE_LOCALE'"]);
+    }
+
+    #[tokio::test]
+    async fn test_admin_tools_disabled_by_default() {
+        assert!(!*ADMIN_TOOLS);
+        let gated = ["upsert_profile", "stress_config", "verify_store_integrity", "purge_expired_data", "export_subject_data", "erase_subject_data"];
+        assert_eq!(ADMIN_ONLY_TOOLS.len(), gated.len());
+        for tool in gated {
+            assert!(ADMIN_ONLY_TOOLS.iter().any(|name| name == tool), "{}", tool);
+            assert!(calculator(tool).is_none(), "{}", tool);
+        }
+        let engine = CompatibilityEngine::new();
+        let refused = [
+            engine.upsert_profile(Parameters(UpsertProfileParams::default())).await.unwrap(),
+            engine.stress_config(Parameters(StressConfigParams::default())).await.unwrap(),
+            engine.purge_expired_data(Parameters(PurgeExpiredDataParams::default())).await.unwrap(),
+            engine.erase_subject_data(Parameters(serde_json::from_str(r#"{"case_id": "CASE-7"}"#).unwrap())).await.unwrap(),
+        ];
+        for result in refused {
+            assert_eq!(result.is_error, Some(true));
+            assert!(result.content[0].raw.as_text().unwrap().text.contains("admin tools are disabled"));
+        }
+    }
+
+    #[test]
+    fn test_parse_tax_brackets() {
+        let (thresholds, rates, cliffs) = EngineConfig::parse_tax_brackets(
+            r#"[{"up_to": 10000, "rate": 0.10}, {"up_to": 40000, "rate": 0.20, "cliff": true}, {"rate": 0.30}]"#,
+            &price_index(),
+        )
+        .unwrap();
+        assert_eq!(thresholds, vec![10000.0, 40000.0]);
+        assert_eq!(rates, vec![0.10, 0.20, 0.30]);
+        assert_eq!(cliffs, vec![false, true, false]);
+
+        let (thresholds, rates, _) = EngineConfig::parse_tax_brackets(r#"[{"rate": 0.15}]"#, &price_index()).unwrap();
+        assert!(thresholds.is_empty());
+        assert_eq!(rates, vec![0.15]);
+
+        let invalid = [
+            ("[]", "at least one bracket"),
+            (r#"[{"up_to": 10000, "rate": 0.1}]"#, "must not set up_to"),
+            (r#"[{"rate": 0.1}, {"rate": 0.2}]"#, "must set up_to"),
+            (r#"[{"up_to": 20000, "rate": 0.1}, {"up_to": 10000, "rate": 0.2}, {"rate": 0.3}]"#, "greater than the previous"),
+            (r#"[{"up_to": 10000, "rate": 10}, {"rate": 0.2}]"#, "between 0 and 1"),
+            (r#"[{"upto": 10000, "rate": 0.1}, {"rate": 0.2}]"#, "invalid JSON"),
+        ];
+        for (json, expected) in invalid {
+            let error = EngineConfig::parse_tax_brackets(json, &price_index()).unwrap_err();
+            assert!(error.contains(expected), "{}: {}", json, error);
+        }
+    }
+
+    #[test]
+    fn test_load_json_config_sources() {
+        let default = || (vec![1.0], vec![0.5, 0.6], vec![false; 2]);
+        let load = || {
+            load_json_config(
+                &|name| env::var(name).ok(),
+                "ENGINE_TEST_BRACKETS",
+                "ENGINE_TEST_BRACKETS_FILE",
+                |json| EngineConfig::parse_tax_brackets(json, &price_index()),
+                "using test default",
+                default,
+            )
+        };
+
+        assert_eq!(load(), default());
+
+        let path = std::env::temp_dir().join(format!("engine-test-brackets-{}.json", std::process::id()));
+        std::fs::write(&path, r#"[{"up_to": 20000, "rate": 0.1}, {"rate": 0.3}]"#).unwrap();
+        unsafe { env::set_var("ENGINE_TEST_BRACKETS_FILE", &path) };
+        assert_eq!(load(), (vec![20000.0], vec![0.1, 0.3], vec![false; 2]));
+
+        // Inline JSON takes precedence over the file; invalid configuration falls back to the default
+        unsafe { env::set_var("ENGINE_TEST_BRACKETS", r#"[{"rate": 0.15}]"#) };
+        assert_eq!(load(), (vec![], vec![0.15], vec![false]));
+        unsafe { env::set_var("ENGINE_TEST_BRACKETS", "[]") };
+        assert_eq!(load(), default());
+
+        unsafe {
+            env::remove_var("ENGINE_TEST_BRACKETS");
+            env::remove_var("ENGINE_TEST_BRACKETS_FILE");
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_cliff_brackets() {
+        let (thresholds, rates, cliffs) = EngineConfig::parse_tax_brackets(
+            r#"[{"up_to": 10000, "rate": 0.10}, {"up_to": 40000, "rate": 0.20, "cliff": true}, {"rate": 0.30}]"#,
+            &price_index(),
+        )
+        .unwrap();
+
+        // Within the first bracket nothing changes
+        assert_eq!(CompatibilityEngine::progressive_tax(8000.0, &thresholds, &rates, &cliffs), 800.0);
+        // Past 10000 the whole amount is taxed at 20%: 15000 × 20% = 3000 (marginal would be 2000)
+        assert_eq!(CompatibilityEngine::progressive_tax(15000.0, &thresholds, &rates, &cliffs), 3000.0);
+        assert_eq!(CompatibilityEngine::progressive_tax(15000.0, &thresholds, &rates, &[]), 2000.0);
+        // Marginal brackets above a cliff add on top: 40000 × 20% + 10000 × 30% = 11000
+        assert_eq!(CompatibilityEngine::progressive_tax(50000.0, &thresholds, &rates, &cliffs), 11000.0);
+
+        let rules = TaxRules {
+            brackets: Arc::new(BracketTable::new(thresholds.clone(), rates.clone(), cliffs)),
+            schedule_change: None,
+            minimum_tax: None,
+            surcharges: Vec::new(),
+            surcharge_base: SurchargeBase::Tax,
         };
-        
-        let result = engine.check_housing_grant(Parameters(params)).await;
-        assert!(result.is_ok());
-        
-        let call_result = result.unwrap();
-        let content = call_result.content;
-        let json_text = content[0].raw.as_text().unwrap().text.as_str();
-        let response: CheckHousingGrantResponse = serde_json::from_str(json_text).unwrap();
-        
-        // Expected: threshold = 33000, income 34000 > 33000, not eligible
-        assert_eq!(response.eligible, false);
+        let response = CompatibilityEngine::calc_tax_internal(50000.0, 0.0, 0.0, &rules, true, Audience::Expert);
         assert!(response.errors.is_empty());
-        assert!(response.explanation.contains("NOT ELIGIBLE"));
+        assert_eq!(response.tax, 11000.0);
+        assert!(response.explanation.contains("Cliff bracket 2"), "{}", response.explanation);
+
+        // A cliff top bracket taxes all income at the top rate
+        let rules = TaxRules { brackets: Arc::new(BracketTable::new(thresholds, rates, vec![false, false, true])), ..rules };
+        let response = CompatibilityEngine::calc_tax_internal(50000.0, 0.0, 0.0, &rules, true, Audience::Expert);
+        assert_eq!(response.tax, 15000.0);
     }
 
-    #[tokio::test]
-    async fn test_check_housing_grant_not_eligible_subsidy() {
-        let engine = CompatibilityEngine::new();
-        let params = CheckHousingGrantParams {
-            ami: "50000".to_string(),
-            household_size: "5".to_string(),
-            income: "32000".to_string(),
-            has_other_subsidy: "true".to_string(),
+    /// The bracket walk calc_tax did before tax was looked up in a bracket table
+    fn naive_bracket_tax(income: f64, thresholds: &[f64], rates: &[f64], cliffs: &[bool]) -> f64 {
+        let mut tax = 0.0;
+        let mut remaining_income = income;
+        for (i, &threshold) in thresholds.iter().enumerate() {
+            if remaining_income <= 0.0 {
+                break;
+            }
+            let prev_threshold = if i == 0 { 0.0 } else { thresholds[i - 1] };
+            let taxable_in_bracket = remaining_income.min(threshold - prev_threshold);
+            remaining_income -= taxable_in_bracket;
+            if CompatibilityEngine::is_cliff(cliffs, i) {
+                tax = (prev_threshold + taxable_in_bracket) * rates[i];
+            } else {
+                tax += taxable_in_bracket * rates[i];
+            }
+        }
+        if remaining_income > 0.0 {
+            let top = rates.len() - 1;
+            if CompatibilityEngine::is_cliff(cliffs, top) {
+                return income * rates[top];
+            }
+            tax += remaining_income * rates[top];
+        }
+        tax
+    }
+
+    #[test]
+    fn test_bracket_table_matches_naive_walk() {
+        // 500 brackets of uneven widths and rates, once marginal and once with every seventh a cliff
+        let mut seed: u64 = 1490;
+        let mut next = move || {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (seed >> 33) as f64 / (1u64 << 31) as f64
         };
-        
-        let result = engine.check_housing_grant(Parameters(params)).await;
-        assert!(result.is_ok());
-        
-        let call_result = result.unwrap();
-        let content = call_result.content;
-        let json_text = content[0].raw.as_text().unwrap().text.as_str();
-        let response: CheckHousingGrantResponse = serde_json::from_str(json_text).unwrap();
-        
-        // Expected: has other subsidy, not eligible
-        assert_eq!(response.eligible, false);
+        let mut thresholds = Vec::new();
+        let mut upper = 0.0;
+        for _ in 0..499 {
+            upper += 1.0 + (next() * 5000.0).round();
+            thresholds.push(upper);
+        }
+        let rates: Vec<f64> = (0..500).map(|_| (next() * 60.0).round() / 100.0).collect();
+        let mut incomes = vec![-100.0, 0.0, 0.01, 1e9];
+        for &threshold in &thresholds {
+            incomes.extend([threshold - 0.01, threshold, threshold + 0.01]);
+        }
+        incomes.extend((0..200).map(|_| next() * upper * 1.1));
+
+        let cliffs: Vec<bool> = (0..500).map(|i| i % 7 == 3).collect();
+        for cliffs in [Vec::new(), cliffs] {
+            let table = BracketTable::new(thresholds.clone(), rates.clone(), cliffs.clone());
+            assert!(table.complete() && table.ascending);
+            for &income in &incomes {
+                let expected = naive_bracket_tax(income, &thresholds, &rates, &cliffs);
+                let mut rows = Vec::new();
+                let tax = CompatibilityEngine::apply_brackets(income, &table, Audience::Expert, &mut Vec::new(), Some(&mut rows));
+                assert!((tax - expected).abs() <= 1e-9 * expected.max(1.0), "income {}: {} vs {}", income, tax, expected);
+                assert_eq!(table.tax(income), tax);
+                if income > 0.0 {
+                    assert_eq!(rows.len(), table.bracket(income) + 1);
+                    assert_eq!(rows.last().unwrap().upper, thresholds.get(rows.len() - 1).copied());
+                } else {
+                    assert!(rows.is_empty());
+                }
+                if cliffs.is_empty() {
+                    let listed: f64 = rows.iter().map(|row| row.tax).sum();
+                    assert!((listed - tax).abs() <= 1e-9 * tax.max(1.0), "income {}: rows {} vs {}", income, listed, tax);
+                }
+            }
+        }
+
+        // calc_tax on the large table, and its validation without walking the thresholds per call
+        let rules = TaxRules {
+            brackets: Arc::new(BracketTable::new(thresholds.clone(), rates.clone(), Vec::new())),
+            schedule_change: None,
+            minimum_tax: None,
+            surcharges: Vec::new(),
+            surcharge_base: SurchargeBase::Tax,
+        };
+        let income = upper / 2.0;
+        let response = CompatibilityEngine::calc_tax_internal(income, 0.0, 0.0, &rules, true, Audience::Expert);
         assert!(response.errors.is_empty());
-        assert!(response.explanation.contains("already has another subsidy"));
-        assert!(!response.additional_requirements.is_empty());
+        let expected = naive_bracket_tax(income, &thresholds, &rates, &[]);
+        assert!((response.tax - expected).abs() <= 1e-9 * expected);
+
+        let mut unsorted = thresholds.clone();
+        unsorted.swap(10, 11);
+        let rules = TaxRules { brackets: Arc::new(BracketTable::new(unsorted, rates.clone(), Vec::new())), ..rules };
+        let response = CompatibilityEngine::calc_tax_internal(income, 0.0, 0.0, &rules, true, Audience::Expert);
+        assert_eq!(response.errors, vec!["Tax thresholds must be in ascending order".to_string()]);
+        let rules = TaxRules { brackets: Arc::new(BracketTable::new(thresholds, rates[..499].to_vec(), Vec::new())), ..rules };
+        let response = CompatibilityEngine::calc_tax_internal(income, 0.0, 0.0, &rules, true, Audience::Expert);
+        assert!(response.errors[0].contains("499 rates for 499 thresholds"), "{:?}", response.errors);
+    }
+
+    fn uniform_bracket_rules(count: usize) -> TaxRules {
+        let thresholds: Vec<f64> = (1..count).map(|i| i as f64 * 100.0).collect();
+        let rates: Vec<f64> = (0..count).map(|i| (i % 50) as f64 / 100.0).collect();
+        TaxRules {
+            brackets: Arc::new(BracketTable::new(thresholds, rates, Vec::new())),
+            schedule_change: None,
+            minimum_tax: None,
+            surcharges: Vec::new(),
+            surcharge_base: SurchargeBase::Tax,
+        }
     }
 
     #[tokio::test]
-    async fn test_calc_penalty_with_errors() {
+    async fn test_calc_tax_without_bracket_rows() {
+        // 200,000 brackets: without the breakdown, the response does not grow with the brackets below the income
+        let rules = uniform_bracket_rules(200_000);
+        let income = 15_000_050.0;
+        let itemized = CompatibilityEngine::calc_tax_internal(income, 0.0, 0.0, &rules, true, Audience::Expert);
+        let looked_up = CompatibilityEngine::calc_tax_internal(income, 0.0, 0.0, &rules, false, Audience::Expert);
+        assert!(itemized.errors.is_empty() && looked_up.errors.is_empty());
+        assert_eq!(itemized.brackets.len(), 150_001);
+        assert!(looked_up.brackets.is_empty());
+        assert!((looked_up.tax - itemized.tax).abs() <= 1e-9 * itemized.tax);
+        assert!(looked_up.explanation.contains("reaches bracket 150001 (above 15000000, 0.0%)"), "{}", looked_up.explanation);
+        assert!(!looked_up.explanation.contains("Bracket 1 "));
+
+        let small = CompatibilityEngine::calc_tax_internal(income, 0.0, 0.0, &uniform_bracket_rules(3), false, Audience::Expert);
+        assert_eq!(small.explanation.lines().count(), looked_up.explanation.lines().count());
+
+        let citizen = CompatibilityEngine::calc_tax_internal(income, 0.0, 0.0, &rules, false, Audience::Citizen);
+        assert!(citizen.explanation.contains("Across all the brackets, the tax on it is"), "{}", citizen.explanation);
+
+        // The tool looks the tax up by default and itemizes the brackets when include_brackets is true
         let engine = CompatibilityEngine::new();
-        let params = CalcPenaltyParams {
-            days_late: "-5".to_string(),  // Invalid: negative
-            ..Default::default()
+        let payload = |arguments: serde_json::Value| {
+            let params: CalcTaxParams = serde_json::from_value(arguments).unwrap();
+            let engine = &engine;
+            async move {
+                let result = engine.calc_tax(Parameters(params)).await.unwrap();
+                let envelope: serde_json::Value = serde_json::from_str(&result.content[0].raw.as_text().unwrap().text).unwrap();
+                envelope["payload"].clone()
+            }
         };
-        
-        let result = engine.calc_penalty(Parameters(params)).await;
-        assert!(result.is_ok());
-        
-        let call_result = result.unwrap();
-        // Should be an error response due to invalid input
-        assert!(call_result.is_error.unwrap_or(false));
-        let content = call_result.content;
-        let error_text = content[0].raw.as_text().unwrap().text.as_str();
-        // Now the error comes from parsing and calculation
-        assert!(error_text.contains("Days late cannot be negative") || error_text.contains("Calculation errors"));
+        let full = payload(serde_json::json!({"income": 45000, "include_brackets": "true"})).await;
+        let bare = payload(serde_json::json!({"income": 45000})).await;
+        assert!(!full["brackets"].as_array().unwrap().is_empty());
+        assert_eq!(bare["brackets"], serde_json::json!([]));
+        assert_eq!(bare["tax"], full["tax"]);
+        let params: CalcTaxParams = serde_json::from_value(serde_json::json!({"income": 45000, "include_brackets": "maybe"})).unwrap();
+        let result = engine.calc_tax(Parameters(params)).await.unwrap();
+        assert!(result.is_error.unwrap_or(false));
+        let text = &result.content[0].raw.as_text().unwrap().text;
+        assert!(text.contains("Invalid include_brackets parameter"), "{}", text);
     }
 
-    #[tokio::test]  
-    async fn test_calc_tax_invalid_brackets() {
-        // This test is no longer relevant since we use fixed configuration
-        // but let's keep it to test that the default configuration is valid
-        let engine = CompatibilityEngine::new();
-        let params = CalcTaxParams {
-            income: "40000".to_string(),
+    /// `cargo test --release -- --ignored bench_ --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_large_bracket_table() {
+        const CALLS: u32 = 200;
+        for count in [1_000, 100_000, 1_000_000] {
+            let rules = uniform_bracket_rules(count);
+            let top = count as f64 * 100.0;
+            for itemize in [true, false] {
+                let started = std::time::Instant::now();
+                for i in 0..CALLS {
+                    let income = top * f64::from(i + 1) / f64::from(CALLS);
+                    let response = CompatibilityEngine::calc_tax_internal(income, 0.0, 0.0, &rules, itemize, Audience::Expert);
+                    std::hint::black_box(response);
+                }
+                println!(
+                    "calc_tax_internal, {} brackets, include_brackets {}: {:?} per call",
+                    count,
+                    itemize,
+                    started.elapsed() / CALLS
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_calc_tax_mid_year_schedule_change() {
+        let change = EngineConfig::parse_schedule_change(
+            "2025-07-01",
+            r#"[{"up_to": 10000, "rate": 0.10}, {"rate": 0.30}]"#,
+            &price_index(),
+        )
+        .unwrap();
+        let change_brackets = BracketTable::new(change.thresholds, change.rates, change.cliffs);
+        let rules = TaxRules {
+            brackets: Arc::new(BracketTable::new(vec![10000.0], vec![0.10, 0.20], Vec::new())),
+            schedule_change: Some((change.effective_date, Arc::new(change_brackets))),
+            minimum_tax: None,
+            surcharges: Vec::new(),
+            surcharge_base: SurchargeBase::Tax,
         };
-        
-        let result = engine.calc_tax(Parameters(params)).await;
-        assert!(result.is_ok());
-        let call_result = result.unwrap();
-        // Should succeed since we use valid default configuration
-        assert!(!call_result.is_error.unwrap_or(false));
-        let content = call_result.content;
-        let json_text = content[0].raw.as_text().unwrap().text.as_str();
-        let response: CalcTaxResponse = serde_json::from_str(json_text).unwrap();
+
+        let response = CompatibilityEngine::calc_tax_internal(20000.0, 0.0, 0.0, &rules, true, Audience::Expert);
         assert!(response.errors.is_empty());
+        let proration = response.proration.unwrap();
+        assert_eq!(proration.days_before_change, 181);
+        assert_eq!(proration.days_after_change, 184);
+        assert_eq!(proration.tax_before_change, 3000.0);
+        assert_eq!(proration.tax_after_change, 4000.0);
+        assert!((response.tax - (3000.0 * 181.0 + 4000.0 * 184.0) / 365.0).abs() < 1e-9);
+        assert!(response.explanation.contains("Schedule from 2025-07-01 (184 of 365 days)"));
+
+        let citizen = CompatibilityEngine::calc_tax_internal(20000.0, 0.0, 0.0, &rules, true, Audience::Citizen);
+        assert!(citizen.explanation.contains("The tax rules changed on 2025-07-01"));
+
+        // A post-change schedule out of order is rejected like the default one
+        let unsorted = BracketTable::new(vec![20000.0, 10000.0], vec![0.10, 0.20, 0.30], Vec::new());
+        let rules = TaxRules { schedule_change: Some((change.effective_date, Arc::new(unsorted))), ..rules };
+        let response = CompatibilityEngine::calc_tax_internal(20000.0, 0.0, 0.0, &rules, true, Audience::Expert);
+        assert_eq!(response.errors, vec!["Tax thresholds after 2025-07-01 must be in ascending order".to_string()]);
+        assert_eq!(response.tax, 0.0);
+
+        assert!(EngineConfig::parse_schedule_change("2025-01-01", r#"[{"rate": 0.1}]"#, &price_index())
+            .unwrap_err()
+            .contains("after January 1"));
+        assert!(EngineConfig::parse_schedule_change("07/01/2025", r#"[{"rate": 0.1}]"#, &price_index())
+            .unwrap_err()
+            .contains("YYYY-MM-DD"));
+        assert!(EngineConfig::parse_schedule_change("2025-07-01", "[]", &price_index()).is_err());
     }
 
-    #[tokio::test]
-    async fn test_check_voting_invalid_proposal_type() {
-        let engine = CompatibilityEngine::new();
-        let params = CheckVotingParams {
-            eligible_voters: "100".to_string(),
-            turnout: "70".to_string(),
-            yes_votes: "55".to_string(),
-            proposal_type: "invalid_type".to_string(),
+    #[test]
+    fn test_calc_tax_alternative_minimum_tax() {
+        let rules = TaxRules {
+            brackets: Arc::new(BracketTable::new(vec![10000.0], vec![0.10, 0.20], Vec::new())),
+            schedule_change: None,
+            minimum_tax: Some(EngineConfig::parse_minimum_tax("0.15", Some("5000")).unwrap()),
+            surcharges: Vec::new(),
+            surcharge_base: SurchargeBase::Tax,
         };
-        
-        let result = engine.check_voting(Parameters(params)).await;
-        assert!(result.is_ok());
-        
-        let call_result = result.unwrap();
-        assert!(call_result.is_error.unwrap_or(false));
-        let content = call_result.content;
-        let error_text = content[0].raw.as_text().unwrap().text.as_str();
-        assert!(error_text.contains("Invalid proposal type"));
+
+        // Adjustments push the minimum tax above the regular tax
+        let response = CompatibilityEngine::calc_tax_internal(20000.0, 0.0, 10000.0, &rules, true, Audience::Expert);
+        let amt = response.minimum_tax.unwrap();
+        assert_eq!(amt.adjusted_income, 30000.0);
+        assert_eq!(amt.regular_tax, 3000.0);
+        assert_eq!(amt.minimum_tax, 3750.0);
+        assert!(amt.applied);
+        assert_eq!(response.tax, 3750.0);
+        assert!(response.explanation.contains("tax owed: 3750.00"));
+
+        // Without adjustments the regular tax is higher and is owed
+        let response = CompatibilityEngine::calc_tax_internal(20000.0, 0.0, 0.0, &rules, true, Audience::Expert);
+        let amt = response.minimum_tax.unwrap();
+        assert_eq!(amt.minimum_tax, 2250.0);
+        assert!(!amt.applied);
+        assert_eq!(response.tax, 3000.0);
+
+        // Not configured: no minimum tax layer
+        let rules = TaxRules { minimum_tax: None, ..rules };
+        let response = CompatibilityEngine::calc_tax_internal(20000.0, 0.0, 10000.0, &rules, true, Audience::Expert);
+        assert!(response.minimum_tax.is_none());
+        assert_eq!(response.tax, 3000.0);
+
+        let response = CompatibilityEngine::calc_tax_internal(20000.0, 0.0, -1.0, &rules, true, Audience::Expert);
+        assert!(response.errors.contains(&"AMT adjustments cannot be negative".to_string()));
+
+        assert_eq!(EngineConfig::parse_minimum_tax("0.2", None).unwrap().exemption, 0.0);
+        assert!(EngineConfig::parse_minimum_tax("1.5", None).is_err());
+        assert!(EngineConfig::parse_minimum_tax("0.2", Some("-100")).is_err());
+        assert!(EngineConfig::parse_minimum_tax("abc", None).is_err());
     }
 
-    #[tokio::test]
-    async fn test_calc_penalty_small_amount() {
-        let engine = CompatibilityEngine::new();
-        let params = CalcPenaltyParams {
-            days_late: "10".to_string(),
-            ..Default::default()
+    #[test]
+    fn test_calc_tax_stacked_surcharges() {
+        let surcharges = EngineConfig::parse_surcharges(
+            r#"[
+                {"name": "Surcharge", "threshold": 5000, "rate": 0.02},
+                {"name": "Solidarity levy", "threshold": 30000, "rate": 0.01, "base": "income"},
+                {"name": "Temporary surcharge", "threshold": 0, "rate": 0.05, "base": "tax"}
+            ]"#,
+        )
+        .unwrap();
+        let rules = TaxRules {
+            brackets: Arc::new(BracketTable::new(vec![10000.0], vec![0.10, 0.20], Vec::new())),
+            schedule_change: None,
+            minimum_tax: None,
+            surcharges,
+            surcharge_base: SurchargeBase::Tax,
         };
-        
-        let result = engine.calc_penalty(Parameters(params)).await;
-        assert!(result.is_ok());
-        
-        let call_result = result.unwrap();
-        let content = call_result.content;
-        let json_text = content[0].raw.as_text().unwrap().text.as_str();
-        let response: CalcPenaltyResponse = serde_json::from_str(json_text).unwrap();
-        
-        // Uses configured defaults: rate_per_day=100.0, cap=1000.0, interest_rate=0.05
-        // Expected: min(10 * 100, 1000) = 1000, then 1000 + (1000 * 0.05) = 1050
-        assert_eq!(response.penalty, 1050.0);
+
+        let response = CompatibilityEngine::calc_tax_internal(40000.0, 0.0, 0.0, &rules, true, Audience::Expert);
         assert!(response.errors.is_empty());
+        // 7000 brackets, + 140 (2% of tax), + 100 (1% of 10000 income above 30000), + 5% of 7240
+        let amounts: Vec<f64> = response.surcharges.iter().map(|line| line.amount).collect();
+        assert_eq!(amounts.len(), 3);
+        assert!((amounts[0] - 140.0).abs() < 1e-9);
+        assert!((amounts[1] - 100.0).abs() < 1e-9);
+        assert!((amounts[2] - 362.0).abs() < 1e-9);
+        assert!((response.tax - 7602.0).abs() < 1e-9);
+        assert_eq!(response.surcharges[1].base, SurchargeBase::Income);
+        assert!(response.explanation.contains("Solidarity levy on income applied"));
+        assert!(response.explanation.contains("Final tax with temporary surcharge: 7602.00"));
+        assert!(response.warnings.is_empty());
+
+        // Rules without a base follow the request's surcharge base
+        let rules = TaxRules { surcharge_base: SurchargeBase::Income, ..rules };
+        let response = CompatibilityEngine::calc_tax_internal(40000.0, 0.0, 0.0, &rules, true, Audience::Expert);
+        assert_eq!(response.surcharges[0].base, SurchargeBase::Income);
+        assert_eq!(response.surcharges[2].base, SurchargeBase::Tax);
+
+        assert!(EngineConfig::parse_surcharges("[]").unwrap().is_empty());
+        let invalid = [
+            (r#"[{"name": "", "threshold": 0, "rate": 0.1}]"#, "must have a name"),
+            (r#"[{"name": "Levy", "threshold": -1, "rate": 0.1}]"#, "threshold must not be negative"),
+            (r#"[{"name": "Levy", "threshold": 0, "rate": 2}]"#, "between 0 and 1"),
+            (r#"[{"name": "Levy", "threshold": 0, "rate": 0.1, "base": "profit"}]"#, "invalid JSON"),
+        ];
+        for (json, expected) in invalid {
+            let error = EngineConfig::parse_surcharges(json).unwrap_err();
+            assert!(error.contains(expected), "{}: {}", json, error);
+        }
     }
 
     #[tokio::test]
-    async fn test_calc_tax_with_surcharge() {
+    async fn test_request_context_echoed_in_envelope() {
         let engine = CompatibilityEngine::new();
+        let params: CalcTaxParams = serde_json::from_value(serde_json::json!({
+            "income": 40000,
+            "context": { "case_id": "CASE-2024-0042", "channel": "chatbot" }
+        }))
+        .unwrap();
+
+        let call_result = engine.calc_tax(Parameters(params)).await.unwrap();
+        let json_text = call_result.content[0].raw.as_text().unwrap().text.as_str();
+        let envelope: ResponseEnvelope<CalcTaxResponse> = serde_json::from_str(json_text).unwrap();
+        let context = envelope.context.unwrap();
+        assert_eq!(context.case_id.as_deref(), Some("CASE-2024-0042"));
+        assert_eq!(context.requester, None);
+        assert_eq!(context.channel.as_deref(), Some("chatbot"));
+
+        // No context, no echo
         let params = CalcTaxParams {
-            income: "50000".to_string(),
+            income: "40000".into(),
+            ..Default::default()
         };
-        
-        let result = engine.calc_tax(Parameters(params)).await;
-        assert!(result.is_ok());
-        
-        let call_result = result.unwrap();
-        let content = call_result.content;
-        let json_text = content[0].raw.as_text().unwrap().text.as_str();
-        let response: CalcTaxResponse = serde_json::from_str(json_text).unwrap();
-        
-        // Uses configured defaults: thresholds=[10000], rates=[0.10,0.20]
-        // surcharge_threshold=5000, surcharge_rate=0.02
-        // Expected: 10000 * 0.10 + 40000 * 0.20 = 1000 + 8000 = 9000
-        // Surcharge: 9000 > 5000, so 9000 + (9000 * 0.02) = 9000 + 180 = 9,180
-        assert_eq!(response.tax, 9180.0);
-        assert!(response.errors.is_empty());
+        let call_result = engine.calc_tax(Parameters(params)).await.unwrap();
+        let json_text = call_result.content[0].raw.as_text().unwrap().text.as_str();
+        assert!(!json_text.contains("\"context\""));
     }
 
     #[tokio::test]
-    async fn test_string_parsing_with_commas() {
+    async fn test_request_context_validation() {
         let engine = CompatibilityEngine::new();
         let params = CalcTaxParams {
-            income: "40,000.00".to_string(), // Test comma-separated thousands
+            income: "40000".into(),
+            context: Some(RequestContext {
+                case_id: Some("x".repeat(101)),
+                ..Default::default()
+            }),
+            ..Default::default()
         };
-        
-        let result = engine.calc_tax(Parameters(params)).await;
-        assert!(result.is_ok());
-        
-        let call_result = result.unwrap();
-        assert!(!call_result.is_error.unwrap_or(false));
-        let content = call_result.content;
-        let json_text = content[0].raw.as_text().unwrap().text.as_str();
-        let response: CalcTaxResponse = serde_json::from_str(json_text).unwrap();
-        
-        // Should parse as 40000.0 and give same result
-        assert_eq!(response.tax, 7140.0);
-        assert!(response.errors.is_empty());
+        let call_result = engine.calc_tax(Parameters(params)).await.unwrap();
+        assert!(call_result.is_error.unwrap_or(false));
+        let error_text = call_result.content[0].raw.as_text().unwrap().text.as_str();
+        assert!(error_text.contains("Invalid context parameter"));
+        assert!(error_text.contains("context.case_id"));
+
+        let unknown_field = serde_json::from_value::<CalcTaxParams>(serde_json::json!({
+            "income": 40000,
+            "context": { "case_id": "A-1", "notes": "free text" }
+        }));
+        assert!(unknown_field.is_err());
     }
 
-    #[tokio::test]
-    async fn test_string_parsing_with_dollar_sign() {
-        let engine = CompatibilityEngine::new();
-        let params = DistributeWaterfallParams {
-            cash_available: "$15,000,000".to_string(), // Test dollar sign and commas
-            senior_debt: "$8000000".to_string(),
-            junior_debt: "$10,000,000.00".to_string(),
-        };
-        
-        let result = engine.distribute_waterfall(Parameters(params)).await;
-        assert!(result.is_ok());
-        
-        let call_result = result.unwrap();
-        assert!(!call_result.is_error.unwrap_or(false));
-        let content = call_result.content;
-        let json_text = content[0].raw.as_text().unwrap().text.as_str();
-        let response: DistributeWaterfallResponse = serde_json::from_str(json_text).unwrap();
-        
-        // Should parse correctly and give expected result
-        assert_eq!(response.distribution.senior, 8_000_000.0);
-        assert_eq!(response.distribution.junior, 7_000_000.0);
-        assert_eq!(response.distribution.equity, 0.0);
-        assert!(response.errors.is_empty());
+    #[test]
+    fn test_scientific_notation_and_underscores() {
+        assert_eq!(parse_f64_from_string("1.5e6"), Ok(1_500_000.0));
+        assert_eq!(parse_f64_from_string("2.5E-2"), Ok(0.025));
+        assert_eq!(parse_f64_from_string("1_000_000"), Ok(1_000_000.0));
+        assert_eq!(parse_f64_from_string("1_234.5_6"), Ok(1234.56));
+        assert_eq!(parse_f64_from_string("$1_500k"), Ok(1_500_000.0));
+        assert_eq!(parse_i32_from_string("1e3"), Ok(1000));
+        assert_eq!(parse_i32_from_string("1_000"), Ok(1000));
+
+        for misplaced in ["_1000", "1000_", "1__000", "1_.5"] {
+            let error = parse_f64_from_string(misplaced).unwrap_err();
+            assert!(error.contains("underscores must separate digits"), "{}", misplaced);
+        }
+        assert!(parse_i32_from_string("1.5e0").unwrap_err().contains("not a whole number"));
+        assert!(parse_i32_from_string("1e10").unwrap_err().contains("integer range"));
+        assert!(parse_f64_from_string("1e400").unwrap_err().contains("Invalid number"));
+    }
+
+    #[test]
+    fn test_magnitude_suffixes() {
+        let mut assumptions = AssumptionLog::default();
+        assert_eq!(assumptions.read_f64("price", &"15M".into()), Ok(15_000_000.0));
+        assert_eq!(assumptions.read_f64("price", &"8.5m".into()), Ok(8_500_000.0));
+        assert_eq!(assumptions.read_f64("price", &"$600k".into()), Ok(600_000.0));
+        assert_eq!(assumptions.read_f64("price", &"2B".into()), Ok(2_000_000_000.0));
+        let assumptions = assumptions.into_vec();
+        assert!(assumptions.iter().all(|a| a.code == "magnitude_suffix_expanded" || a.code == "currency_symbol_stripped"));
+        assert!(assumptions[0].message.contains("'15M' read as 15000000"));
+
+        assert!(parse_f64_from_string("k").is_err());
+        assert!(parse_f64_from_string("12 k").is_err());
+        let error = parse_f64_with_strictness("600k", true).unwrap_err();
+        assert!(error.contains("magnitude suffixes are disabled"));
+        assert_eq!(parse_f64_with_strictness("600", true), Ok(600.0));
+    }
+
+    #[test]
+    fn test_unit_annotations() {
+        let mut assumptions = AssumptionLog::default();
+        assert_eq!(assumptions.read_f64("income", &"32000 EUR/year".into()), Ok(32000.0));
+        assert_eq!(assumptions.read_f64("income", &"3,000 per month".into()), Ok(36000.0));
+        assert_eq!(assumptions.read_i32("days_late", &"2 weeks".into()), Ok(14));
+        assert_eq!(assumptions.read_i32("days_late", &"12 days".into()), Ok(12));
+        assert_eq!(assumptions.read_i32("household_size", &"5 people".into()), Ok(5));
+        assert_eq!(assumptions.read_f64("beneficiaries[0].amount", &"250000 EUR".into()), Ok(250000.0));
+        let assumptions = assumptions.into_vec();
+        assert_eq!(assumptions.iter().filter(|a| a.code == "unit_converted").count(), 2);
+        assert!(assumptions[0].message.contains("monthly amount × 12"));
+
+        let mut assumptions = AssumptionLog::default();
+        let error = assumptions.read_f64("income", &"32000 USD".into()).unwrap_err();
+        assert!(error.contains("income must be in EUR, not USD"));
+        let error = assumptions.read_f64("sale_price", &"1500 EUR/month".into()).unwrap_err();
+        assert!(error.contains("not a per-month amount"));
+        let error = assumptions.read_i32("days_late", &"3 months".into()).unwrap_err();
+        assert!(error.contains("is a number of days"));
+        let error = assumptions.read_f64("interest_rate", &"5 percent".into()).unwrap_err();
+        assert!(error.contains("interest_rate does not take a unit"));
+        let error = assumptions.read_f64("income", &"32000 furlongs".into()).unwrap_err();
+        assert!(error.contains("Unknown unit 'furlongs'"));
     }
 
     #[tokio::test]
-    async fn test_string_parsing_invalid_format() {
+    async fn test_llm_inferred_values_on_threshold_need_confirmation() {
         let engine = CompatibilityEngine::new();
-        let params = CalcPenaltyParams {
-            days_late: "not-a-number".to_string(), // Invalid format
+        let params = |input_source| CheckHousingGrantParams {
+            ami: "50000".into(),
+            household_size: "3".into(),
+            income: "30000".into(),
+            has_other_subsidy: "false".into(),
+            context: Some(RequestContext { input_source, ..Default::default() }),
             ..Default::default()
         };
-        
-        let result = engine.calc_penalty(Parameters(params)).await;
-        assert!(result.is_ok());
-        
-        let call_result = result.unwrap();
+
+        let call_result = engine.check_housing_grant(Parameters(params(Some(InputSource::LlmInferred)))).await.unwrap();
         assert!(call_result.is_error.unwrap_or(false));
-        let content = call_result.content;
-        let error_text = content[0].raw.as_text().unwrap().text.as_str();
-        assert!(error_text.contains("Invalid days_late parameter"));
-        assert!(error_text.contains("Cannot parse 'not-a-number' as a number"));
-    }
+        let error_text = call_result.content[0].raw.as_text().unwrap().text.as_str();
+        assert!(error_text.contains("income (30000) sits exactly on the threshold of 30000"));
 
-    #[tokio::test]
-    async fn test_string_parsing_empty_string() {
-        let engine = CompatibilityEngine::new();
+        let call_result = engine.check_housing_grant(Parameters(params(Some(InputSource::UserProvided)))).await.unwrap();
+        assert!(!call_result.is_error.unwrap_or(false));
+        let json_text = call_result.content[0].raw.as_text().unwrap().text.as_str();
+        assert!(json_text.contains("\"input_source\": \"user_provided\""));
+
+        // 60 of 90 is exactly the two-thirds amendment majority
         let params = CheckVotingParams {
-            eligible_voters: "".to_string(), // Empty string
-            turnout: "70".to_string(),
-            yes_votes: "55".to_string(),
-            proposal_type: "general".to_string(),
+            eligible_voters: "100".into(),
+            turnout: "90".into(),
+            yes_votes: "60".into(),
+            proposal_type: "amendment".to_string(),
+            context: Some(RequestContext { input_source: Some(InputSource::LlmInferred), ..Default::default() }),
+            ..Default::default()
         };
-        
-        let result = engine.check_voting(Parameters(params)).await;
-        assert!(result.is_ok());
-        
-        let call_result = result.unwrap();
-        assert!(call_result.is_error.unwrap_or(false));
-        let content = call_result.content;
-        let error_text = content[0].raw.as_text().unwrap().text.as_str();
-        assert!(error_text.contains("Invalid eligible_voters parameter"));
-        assert!(error_text.contains("Empty string cannot be parsed"));
+        let call_result = engine.check_voting(Parameters(params)).await.unwrap();
+        let error_text = call_result.content[0].raw.as_text().unwrap().text.as_str();
+        assert!(error_text.contains("yes vote ratio"));
+        assert!(!error_text.contains("turnout ratio"));
     }
 
     #[tokio::test]
-    async fn test_string_parsing_with_whitespace() {
+    async fn test_response_version_unsupported() {
         let engine = CompatibilityEngine::new();
-        let params = CalcPenaltyParams {
-            days_late: "  12.5  ".to_string(), // Test whitespace trimming
+        let params = CalcTaxParams {
+            income: "40000".into(),
+            response_version: Some("3".into()),
             ..Default::default()
         };
-        
-        let result = engine.calc_penalty(Parameters(params)).await;
-        assert!(result.is_ok());
-        
-        let call_result = result.unwrap();
-        assert!(!call_result.is_error.unwrap_or(false));
-        let content = call_result.content;
-        let json_text = content[0].raw.as_text().unwrap().text.as_str();
-        let response: CalcPenaltyResponse = serde_json::from_str(json_text).unwrap();
-        
-        // Should parse as 12.5 and calculate penalty
-        assert!(response.penalty > 0.0);
-        assert!(response.errors.is_empty());
+
+        let call_result = engine.calc_tax(Parameters(params)).await.unwrap();
+        assert!(call_result.is_error.unwrap_or(false));
+        let error_text = call_result.content[0].raw.as_text().unwrap().text.as_str();
+        assert!(error_text.contains("Invalid response_version parameter"));
+        assert!(error_text.contains("supported: 1, 2"));
     }
 
     // =================== SECURITY TESTS ===================
@@ -1799,7 +15812,7 @@ mod tests {
         // Create a string longer than 100 characters
         let long_string = "1".repeat(101);
         let params = CalcPenaltyParams {
-            days_late: long_string,
+            days_late: long_string.into(),
             ..Default::default()
         };
         
@@ -1818,7 +15831,7 @@ mod tests {
     async fn test_security_json_injection_prevention() {
         let engine = CompatibilityEngine::new();
         let params = CalcPenaltyParams {
-            days_late: r#"12", "malicious": "payload"#.to_string(),
+            days_late: r#"12", "malicious": "payload"#.into(),
             ..Default::default()
         };
         
@@ -1839,7 +15852,7 @@ mod tests {
     async fn test_security_xss_prevention() {
         let engine = CompatibilityEngine::new();
         let params = CalcPenaltyParams {
-            days_late: "<script>alert('xss')</script>".to_string(),
+            days_late: "<script>alert('xss')</script>".into(),
             ..Default::default()
         };
         
@@ -1861,7 +15874,7 @@ mod tests {
     async fn test_security_newline_injection_prevention() {
         let engine = CompatibilityEngine::new();
         let params = CalcPenaltyParams {
-            days_late: "12\n\nFAKE LOG ENTRY: Unauthorized access".to_string(),
+            days_late: "12\n\nFAKE LOG ENTRY: Unauthorized access".into(),
             ..Default::default()
         };
         
@@ -1882,7 +15895,7 @@ mod tests {
     async fn test_security_null_byte_prevention() {
         let engine = CompatibilityEngine::new();
         let params = CalcPenaltyParams {
-            days_late: "12\0malicious".to_string(),
+            days_late: "12\0malicious".into(),
             ..Default::default()
         };
         
@@ -1904,7 +15917,7 @@ mod tests {
         // Create input with excessive control characters
         let malicious_input = "12\x01\x02\x03\x04\x05evil";
         let params = CalcPenaltyParams {
-            days_late: malicious_input.to_string(),
+            days_late: malicious_input.into(),
             ..Default::default()
         };
         
@@ -1926,7 +15939,7 @@ mod tests {
         // Create a 60-character invalid string (over the 50 error display limit but under input limit)
         let long_invalid = "not-a-number-".repeat(4) + "extra-text"; // ~60 chars of invalid input
         let params = CalcPenaltyParams {
-            days_late: long_invalid,
+            days_late: long_invalid.into(),
             ..Default::default()
         };
         
@@ -1947,7 +15960,7 @@ mod tests {
     async fn test_security_backslash_sanitization() {
         let engine = CompatibilityEngine::new();
         let params = CalcPenaltyParams {
-            days_late: r#"12\"malicious\"payload"#.to_string(),
+            days_late: r#"12\"malicious\"payload"#.into(),
             ..Default::default()
         };
         
@@ -1971,10 +15984,11 @@ mod tests {
         // Test various "true" representations
         for true_value in ["true", "TRUE", "True", "t", "T", "yes", "YES", "y", "Y", "1", "on", "ON"] {
             let params = CheckHousingGrantParams {
-                ami: "50000".to_string(),
-                household_size: "3".to_string(),
-                income: "25000".to_string(), // Same qualifying income as false test
-                has_other_subsidy: true_value.to_string(),
+                ami: "50000".into(),
+                household_size: "3".into(),
+                income: "25000".into(), // Same qualifying income as false test
+                has_other_subsidy: true_value.into(),
+                ..Default::default()
             };
             
             let result = engine.check_housing_grant(Parameters(params)).await;
@@ -1984,7 +15998,7 @@ mod tests {
             assert!(!call_result.is_error.unwrap_or(false));
             let content = call_result.content;
             let json_text = content[0].raw.as_text().unwrap().text.as_str();
-            let response: CheckHousingGrantResponse = serde_json::from_str(json_text).unwrap();
+            let response: CheckHousingGrantResponse = parse_payload(json_text);
             
             // Should be ineligible due to having other subsidy (true)
             assert_eq!(response.eligible, false);
@@ -1994,10 +16008,11 @@ mod tests {
         // Test various "false" representations
         for false_value in ["false", "FALSE", "False", "f", "F", "no", "NO", "n", "N", "0", "off", "OFF"] {
             let params = CheckHousingGrantParams {
-                ami: "50000".to_string(),
-                household_size: "3".to_string(),
-                income: "25000".to_string(), // Set income below threshold (0.60 * 50000 = 30000)
-                has_other_subsidy: false_value.to_string(),
+                ami: "50000".into(),
+                household_size: "3".into(),
+                income: "25000".into(), // Set income below threshold (0.60 * 50000 = 30000)
+                has_other_subsidy: false_value.into(),
+                ..Default::default()
             };
             
             let result = engine.check_housing_grant(Parameters(params)).await;
@@ -2007,7 +16022,7 @@ mod tests {
             assert!(!call_result.is_error.unwrap_or(false));
             let content = call_result.content;
             let json_text = content[0].raw.as_text().unwrap().text.as_str();
-            let response: CheckHousingGrantResponse = serde_json::from_str(json_text).unwrap();
+            let response: CheckHousingGrantResponse = parse_payload(json_text);
             
             // Should be eligible (no other subsidy + income qualifies)
             assert_eq!(response.eligible, true);
@@ -2018,10 +16033,11 @@ mod tests {
     async fn test_boolean_parsing_invalid() {
         let engine = CompatibilityEngine::new();
         let params = CheckHousingGrantParams {
-            ami: "50000".to_string(),
-            household_size: "3".to_string(),
-            income: "32000".to_string(),
-            has_other_subsidy: "maybe".to_string(), // Invalid boolean
+            ami: "50000".into(),
+            household_size: "3".into(),
+            income: "32000".into(),
+            has_other_subsidy: "maybe".into(), // Invalid boolean,
+            ..Default::default()
         };
         
         let result = engine.check_housing_grant(Parameters(params)).await;
@@ -2040,10 +16056,11 @@ mod tests {
     async fn test_boolean_parsing_empty_string() {
         let engine = CompatibilityEngine::new();
         let params = CheckHousingGrantParams {
-            ami: "50000".to_string(),
-            household_size: "3".to_string(),
-            income: "32000".to_string(),
-            has_other_subsidy: "".to_string(), // Empty string
+            ami: "50000".into(),
+            household_size: "3".into(),
+            income: "32000".into(),
+            has_other_subsidy: "".into(), // Empty string,
+            ..Default::default()
         };
         
         let result = engine.check_housing_grant(Parameters(params)).await;
@@ -2054,8 +16071,8 @@ mod tests {
         let content = call_result.content;
         let error_text = content[0].raw.as_text().unwrap().text.as_str();
         
-        assert!(error_text.contains("Invalid has_other_subsidy parameter"));
-        assert!(error_text.contains("Empty string cannot be parsed as boolean"));
+        let needs: NeedsInputResponse = serde_json::from_str(error_text).unwrap();
+        assert_eq!(needs.missing, vec!["has_other_subsidy".to_string()]);
     }
 
     #[tokio::test]
@@ -2065,10 +16082,11 @@ mod tests {
         // Simulate the exact error scenario from the terminal log:
         // "has_other_subsidy": String("true") instead of boolean true
         let params = CheckHousingGrantParams {
-            ami: "65000".to_string(),
-            household_size: "7".to_string(),
-            income: "40000".to_string(),
-            has_other_subsidy: "true".to_string(), // This was causing the original error
+            ami: "65000".into(),
+            household_size: "7".into(),
+            income: "40000".into(),
+            has_other_subsidy: "true".into(), // This was causing the original error,
+            ..Default::default()
         };
         
         let result = engine.check_housing_grant(Parameters(params)).await;
@@ -2078,7 +16096,7 @@ mod tests {
         assert!(!call_result.is_error.unwrap_or(false)); // Should NOT be an error anymore
         let content = call_result.content;
         let json_text = content[0].raw.as_text().unwrap().text.as_str();
-        let response: CheckHousingGrantResponse = serde_json::from_str(json_text).unwrap();
+        let response: CheckHousingGrantResponse = parse_payload(json_text);
         
         // Should be ineligible due to having other subsidy
         assert_eq!(response.eligible, false);
@@ -2097,11 +16115,11 @@ mod tests {
         
         let params: CheckHousingGrantParams = serde_json::from_str(json_data).unwrap();
         
-        // Should have been converted to strings internally
-        assert_eq!(params.ami, "65000");
-        assert_eq!(params.household_size, "7");
-        assert_eq!(params.income, "40000");
-        assert_eq!(params.has_other_subsidy, "true");
+        // Should have been parsed while deserializing
+        assert_eq!(params.ami.0, Ok(65000.0));
+        assert_eq!(params.household_size.0, Ok(7));
+        assert_eq!(params.income.0, Ok(40000.0));
+        assert_eq!(params.has_other_subsidy.0, Ok(true));
         
         // Test that the engine can process these
         let engine = CompatibilityEngine::new();
@@ -2124,10 +16142,38 @@ mod tests {
         
         let params: CheckHousingGrantParams = serde_json::from_str(json_data).unwrap();
         
-        assert_eq!(params.ami, "65000");
-        assert_eq!(params.household_size, "7");
-        assert_eq!(params.income, "40000.5");
-        assert_eq!(params.has_other_subsidy, "false");
+        assert_eq!(params.ami.0, Ok(65000.0));
+        assert_eq!(params.household_size.0, Ok(7));
+        assert_eq!(params.income.0, Ok(40000.5));
+        assert_eq!(params.has_other_subsidy.0, Ok(false));
+    }
+
+    #[tokio::test]
+    async fn test_flexible_params_parsed_while_deserializing() {
+        // Native numbers are read as they are, however long their decimal form
+        let params: CalcTaxParams = serde_json::from_value(serde_json::json!({"income": 1e300})).unwrap();
+        assert_eq!(params.income.0, Ok(1e300));
+        let params: CheckHousingGrantParams = serde_json::from_value(serde_json::json!({
+            "ami": 50000, "household_size": 3_000_000_000u64, "income": 15000, "has_other_subsidy": false,
+        }))
+        .unwrap();
+        assert_eq!(params.household_size.0, Err("Cannot parse '3000000000' as an integer".to_string()));
+
+        // Text is parsed once, keeping how it was read for the tool to record as assumptions
+        let params: CalcTaxParams = serde_json::from_value(serde_json::json!({"income": "45,000"})).unwrap();
+        assert_eq!(params.income.0, Ok(45000.0));
+        let mut assumptions = AssumptionLog::default();
+        assert_eq!(assumptions.read_f64("income", &params.income), Ok(45000.0));
+        assert_eq!(assumptions.into_vec()[0].code, "comma_removed");
+
+        // Unreadable text still deserializes and is reported by the tool under the field name
+        let params: CalcPenaltyParams = serde_json::from_value(serde_json::json!({"days_late": "soon"})).unwrap();
+        let call_result = CompatibilityEngine::new().calc_penalty(Parameters(params)).await.unwrap();
+        assert_eq!(call_result.is_error, Some(true));
+        assert_eq!(
+            call_result.content[0].raw.as_text().unwrap().text,
+            "Invalid days_late parameter: Cannot parse 'soon' as a number"
+        );
     }
 
     #[tokio::test]
@@ -2135,12 +16181,12 @@ mod tests {
         // Test CalcPenaltyParams with native number
         let json_penalty = r#"{"days_late": 12.5}"#;
         let penalty_params: CalcPenaltyParams = serde_json::from_str(json_penalty).unwrap();
-        assert_eq!(penalty_params.days_late, "12.5");
+        assert_eq!(penalty_params.days_late.0, Ok(12.5));
         
         // Test CalcTaxParams with native number
         let json_tax = r#"{"income": 50000}"#;
         let tax_params: CalcTaxParams = serde_json::from_str(json_tax).unwrap();
-        assert_eq!(tax_params.income, "50000");
+        assert_eq!(tax_params.income.0, Ok(50000.0));
         
         // Test CheckVotingParams with native numbers
         let json_voting = r#"{
@@ -2150,9 +16196,9 @@ mod tests {
             "proposal_type": "amendment"
         }"#;
         let voting_params: CheckVotingParams = serde_json::from_str(json_voting).unwrap();
-        assert_eq!(voting_params.eligible_voters, "100");
-        assert_eq!(voting_params.turnout, "75");
-        assert_eq!(voting_params.yes_votes, "60");
+        assert_eq!(voting_params.eligible_voters.0, Ok(100));
+        assert_eq!(voting_params.turnout.0, Ok(75));
+        assert_eq!(voting_params.yes_votes.0, Ok(60));
         
         // Test DistributeWaterfallParams with native numbers
         let json_waterfall = r#"{
@@ -2161,9 +16207,9 @@ mod tests {
             "junior_debt": 10000000.5
         }"#;
         let waterfall_params: DistributeWaterfallParams = serde_json::from_str(json_waterfall).unwrap();
-        assert_eq!(waterfall_params.cash_available, "15000000");
-        assert_eq!(waterfall_params.senior_debt, "8000000");
-        assert_eq!(waterfall_params.junior_debt, "10000000.5");
+        assert_eq!(waterfall_params.cash_available.0, Ok(15000000.0));
+        assert_eq!(waterfall_params.senior_debt.0, Ok(8000000.0));
+        assert_eq!(waterfall_params.junior_debt.0, Ok(10000000.5));
     }
 
     #[tokio::test]
@@ -2202,7 +16248,7 @@ mod tests {
         
         let content = call_result.content;
         let json_text = content[0].raw.as_text().unwrap().text.as_str();
-        let response: CheckHousingGrantResponse = serde_json::from_str(json_text).unwrap();
+        let response: CheckHousingGrantResponse = parse_payload(json_text);
         
         // Should be ineligible due to having subsidy
         assert_eq!(response.eligible, false);
@@ -2224,10 +16270,10 @@ mod tests {
         assert!(params.is_ok());
         
         let params = params.unwrap();
-        assert_eq!(params.ami, "65000");
-        assert_eq!(params.has_other_subsidy, "true");
-        assert_eq!(params.household_size, "7");
-        assert_eq!(params.income, "40000");
+        assert_eq!(params.ami.0, Ok(65000.0));
+        assert_eq!(params.has_other_subsidy.0, Ok(true));
+        assert_eq!(params.household_size.0, Ok(7));
+        assert_eq!(params.income.0, Ok(40000.0));
     }
 
     #[test]
@@ -2244,9 +16290,9 @@ mod tests {
         assert!(params.is_ok());
         
         let params = params.unwrap();
-        assert_eq!(params.ami, "55000");
-        assert_eq!(params.has_other_subsidy, "false");
-        assert_eq!(params.household_size, "2");
-        assert_eq!(params.income, "32000");
+        assert_eq!(params.ami.0, Ok(55000.0));
+        assert_eq!(params.has_other_subsidy.0, Ok(false));
+        assert_eq!(params.household_size.0, Ok(2));
+        assert_eq!(params.income.0, Ok(32000.0));
     }
 }
@@ -0,0 +1,190 @@
+//! Limits on concurrent tool calls, so heavy batch tools cannot starve interactive checks.
+//!
+//! [`limit`] wraps every route of a tool router. A call first takes a slot for its tool (if
+//! `ENGINE_TOOL_CONCURRENCY` lists one, e.g. `distribute_waterfall=2,calc_npv=4`), then a slot of
+//! the global `ENGINE_MAX_CONCURRENT_CALLS` limit. Calls beyond a limit queue for up to
+//! `ENGINE_QUEUE_TIMEOUT_MS` (default 10000, `0` rejects at once) and are then answered with an
+//! error result asking the client to retry. Both limits default to unlimited.
+//!
+//! Queue depth, queue wait and rejections are exported as metrics.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, LazyLock};
+use std::time::{Duration, Instant};
+
+use futures::FutureExt;
+use rmcp::handler::server::router::tool::{ToolRoute, ToolRouter};
+use rmcp::handler::server::tool::ToolCallContext;
+use rmcp::model::{CallToolResult, Content};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use super::metrics;
+
+const DEFAULT_QUEUE_TIMEOUT_MS: u64 = 10_000;
+
+struct Limits {
+    global: Option<Arc<Semaphore>>,
+    per_tool: BTreeMap<String, Arc<Semaphore>>,
+    queue_timeout: Duration,
+}
+
+static LIMITS: LazyLock<Limits> = LazyLock::new(|| {
+    let global = std::env::var("ENGINE_MAX_CONCURRENT_CALLS").ok().and_then(|value| {
+        match value.trim().parse::<usize>() {
+            Ok(0) => None,
+            Ok(limit) => Some(Arc::new(Semaphore::new(limit))),
+            Err(e) => {
+                tracing::error!("Invalid ENGINE_MAX_CONCURRENT_CALLS '{}': {}; calls are not limited", value, e);
+                None
+            }
+        }
+    });
+    let per_tool = std::env::var("ENGINE_TOOL_CONCURRENCY")
+        .ok()
+        .map(|value| parse_tool_limits(&value).unwrap_or_else(|e| {
+            tracing::error!("Invalid ENGINE_TOOL_CONCURRENCY '{}': {}; tools are not limited", value, e);
+            BTreeMap::new()
+        }))
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(tool, limit)| (tool, Arc::new(Semaphore::new(limit))))
+        .collect();
+    let queue_timeout = std::env::var("ENGINE_QUEUE_TIMEOUT_MS")
+        .ok()
+        .map(|value| value.trim().parse::<u64>().unwrap_or_else(|e| {
+            tracing::error!("Invalid ENGINE_QUEUE_TIMEOUT_MS '{}': {}; using {}", value, e, DEFAULT_QUEUE_TIMEOUT_MS);
+            DEFAULT_QUEUE_TIMEOUT_MS
+        }))
+        .unwrap_or(DEFAULT_QUEUE_TIMEOUT_MS);
+    Limits { global, per_tool, queue_timeout: Duration::from_millis(queue_timeout) }
+});
+
+/// Parse `tool=limit` pairs separated by commas
+fn parse_tool_limits(value: &str) -> Result<BTreeMap<String, usize>, String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (tool, limit) = pair.split_once('=').ok_or_else(|| format!("expected tool=limit, got '{}'", pair))?;
+            match limit.trim().parse::<usize>() {
+                Ok(limit) if limit > 0 => Ok((tool.trim().to_string(), limit)),
+                _ => Err(format!("limit for '{}' must be a positive integer", tool.trim())),
+            }
+        })
+        .collect()
+}
+
+/// Wrap every route of `router` so its calls wait for a slot under the configured limits
+pub fn limit<S: Send + Sync + 'static>(router: ToolRouter<S>) -> ToolRouter<S> {
+    if LIMITS.global.is_none() && LIMITS.per_tool.is_empty() {
+        return router;
+    }
+    for tool in LIMITS.per_tool.keys().filter(|tool| !router.has_route(tool)) {
+        tracing::warn!("ENGINE_TOOL_CONCURRENCY names unknown tool '{}'", tool);
+    }
+    let mut limited = ToolRouter::new();
+    for route in router {
+        let call = route.call.clone();
+        let semaphores: Vec<Arc<Semaphore>> = LIMITS
+            .per_tool
+            .get(route.name())
+            .into_iter()
+            .chain(LIMITS.global.as_ref())
+            .cloned()
+            .collect();
+        limited.add_route(ToolRoute::new_dyn(route.attr, move |context: ToolCallContext<'_, S>| {
+            let tool = context.name.to_string();
+            let semaphores = semaphores.clone();
+            // The tool does its work when the future is polled, after the slots are taken
+            let future = call(context);
+            async move {
+                match acquire(&tool, &semaphores, LIMITS.queue_timeout).await {
+                    Ok(_permits) => future.await,
+                    Err(message) => {
+                        metrics::increment_errors();
+                        Ok(CallToolResult::error(vec![Content::text(message)]))
+                    }
+                }
+            }
+            .boxed()
+        }));
+    }
+    limited
+}
+
+/// Take one slot from each semaphore in order, waiting at most `timeout` in total
+async fn acquire(
+    tool: &str,
+    semaphores: &[Arc<Semaphore>],
+    timeout: Duration,
+) -> Result<Vec<OwnedSemaphorePermit>, String> {
+    let mut permits: Vec<OwnedSemaphorePermit> = semaphores
+        .iter()
+        .map_while(|semaphore| semaphore.clone().try_acquire_owned().ok())
+        .collect();
+    if permits.len() == semaphores.len() {
+        return Ok(permits);
+    }
+
+    let start = Instant::now();
+    metrics::add_queued_requests(tool, 1);
+    let waited = tokio::time::timeout(timeout, async {
+        for semaphore in &semaphores[permits.len()..] {
+            permits.push(semaphore.clone().acquire_owned().await.expect("concurrency semaphores are never closed"));
+        }
+    })
+    .await;
+    metrics::add_queued_requests(tool, -1);
+    metrics::record_queue_wait(tool, start.elapsed());
+
+    match waited {
+        Ok(()) => Ok(permits),
+        Err(_) => {
+            metrics::increment_rejected_requests(tool);
+            tracing::warn!(tool, timeout_ms = timeout.as_millis() as u64, "tool call rejected: concurrency limit reached");
+            Err(format!(
+                "The server is busy: {} is at its concurrency limit and no slot freed up within {} ms. Retry the call later",
+                tool,
+                timeout.as_millis()
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tool_limits() {
+        assert_eq!(
+            parse_tool_limits("distribute_waterfall=2, calc_npv = 4,"),
+            Ok(BTreeMap::from([("calc_npv".to_string(), 4), ("distribute_waterfall".to_string(), 2)]))
+        );
+        assert!(parse_tool_limits("calc_npv").is_err());
+        assert!(parse_tool_limits("calc_npv=0").is_err());
+        assert!(parse_tool_limits("calc_npv=many").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_acquire_queues_then_rejects() {
+        let tool_slots = Arc::new(Semaphore::new(1));
+        let global_slots = Arc::new(Semaphore::new(2));
+        let semaphores = [tool_slots.clone(), global_slots.clone()];
+
+        let held = acquire("calc_npv", &semaphores, Duration::ZERO).await.unwrap();
+        assert_eq!(global_slots.available_permits(), 1);
+
+        // The tool slot is taken: a second call waits, then gives up without holding a global slot
+        let error = acquire("calc_npv", &semaphores, Duration::from_millis(20)).await.unwrap_err();
+        assert!(error.contains("calc_npv is at its concurrency limit"));
+        assert_eq!(global_slots.available_permits(), 1);
+
+        // A queued call proceeds once the slot is released
+        let waiting = tokio::spawn(async move { acquire("calc_npv", &semaphores, Duration::from_secs(5)).await.is_ok() });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        drop(held);
+        assert!(waiting.await.unwrap());
+    }
+}
@@ -0,0 +1,718 @@
+//! SQLite-backed storage for single-binary deployments, compiled in with the `embedded-store`
+//! cargo feature.
+//!
+//! Without the feature, the `$result` store and the usage counts behind `get_usage_report` live
+//! in memory and are lost on restart. With it, both are also written to the SQLite database at
+//! `ENGINE_STORE_PATH` (default `compatibility-engine.db` in the working directory): results are
+//! read back from it when they are no longer in memory, and the usage counts since the database
+//! was created are loaded at startup. The database needs no server and no setup; its schema is
+//! created and migrated when the server starts, tracked in `PRAGMA user_version`.
+//!
+//! With `ENGINE_STORE_KEYS` or `ENGINE_STORE_KEYS_FILE` set, result payloads are stored
+//! encrypted (see [`super::store_encryption`]); records written before keys were configured stay
+//! readable and are encrypted by `verify_store_integrity` with `rewrap`.
+//!
+//! Results are linked to the case of the call by an HMAC-SHA256 of its `case_id`, never the id
+//! itself. The HMAC key is created at random with the database and kept in it, sealed under the
+//! store keys like a result when they are configured, so the hashes cannot be reversed by
+//! hashing candidate case ids without those keys, and survive key rotation. Deleted records are overwritten in the database file (`PRAGMA secure_delete`), and
+//! erasing a case also truncates the write-ahead log, so neither the payloads nor the wrapped
+//! data keys of encrypted ones stay behind on disk. Results stored before schema version 3 have
+//! no case and are found only by their id.
+//!
+//! A database that cannot be opened or migrated, or keys that cannot be read, are logged as an
+//! error, which fails `--preflight`, and the server carries on with the in-memory stores. Write
+//! failures are logged and do not fail the call.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::Value;
+
+use super::analytics::{ToolUsage, UsageStats};
+use super::clock;
+use super::compatibility_engine::StoreIntegrity;
+use super::result_store::StoredResult;
+use super::store_encryption::{self, KeyRing, Sealed};
+
+const DEFAULT_PATH: &str = "compatibility-engine.db";
+/// Failing records listed by an integrity check
+const MAX_INTEGRITY_FAILURES: usize = 10;
+
+/// Schema changes in order; the database's `user_version` is the number applied
+const MIGRATIONS: &[&str] = &[
+    // 1: result store, usage counts per tool, and store-wide values such as when counting began
+    "CREATE TABLE results (
+        seq INTEGER PRIMARY KEY AUTOINCREMENT,
+        id TEXT NOT NULL UNIQUE,
+        payload TEXT NOT NULL,
+        created_at TEXT NOT NULL
+    );
+    CREATE TABLE usage (
+        tool TEXT PRIMARY KEY,
+        calls INTEGER NOT NULL,
+        failed_calls INTEGER NOT NULL,
+        parameter_types TEXT NOT NULL,
+        errors TEXT NOT NULL
+    );
+    CREATE TABLE meta (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );",
+    // 2: the wrapped data key of an encrypted result; results without one are plaintext
+    "ALTER TABLE results ADD COLUMN key_id TEXT;
+    ALTER TABLE results ADD COLUMN wrapped_key BLOB;",
+    // 3: the tool that produced a result and the hash of its case id, to find a case's results
+    "ALTER TABLE results ADD COLUMN tool TEXT;
+    ALTER TABLE results ADD COLUMN case_hash TEXT;
+    CREATE INDEX results_case_hash ON results (case_hash);",
+    // 4: secrets of the store itself, sealed like results when keys are configured
+    "CREATE TABLE secrets (
+        name TEXT PRIMARY KEY,
+        value BLOB NOT NULL,
+        key_id TEXT,
+        wrapped_key BLOB
+    );",
+];
+/// Name of the HMAC key of case hashes in the `secrets` table
+const CASE_HASH_KEY: &str = "case_hash_key";
+
+/// A stored result: its payload, and the wrapped data key when encrypted
+struct ResultRow {
+    id: String,
+    payload: Vec<u8>,
+    key_id: Option<String>,
+    wrapped_key: Option<Vec<u8>>,
+}
+
+impl ResultRow {
+    fn read(row: &rusqlite::Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row.get(0)?,
+            payload: row.get_ref(1)?.as_bytes()?.to_vec(),
+            key_id: row.get(2)?,
+            wrapped_key: row.get(3)?,
+        })
+    }
+
+    fn sealed(&self) -> Option<Sealed> {
+        Some(Sealed { key_id: self.key_id.clone()?, wrapped_key: self.wrapped_key.clone()?, ciphertext: self.payload.clone() })
+    }
+
+    /// The payload bytes, decrypted with `keys` if they are encrypted
+    fn plaintext(&self, keys: Option<&KeyRing>) -> Result<Vec<u8>, String> {
+        match (self.sealed(), keys) {
+            (None, _) => Ok(self.payload.clone()),
+            (Some(sealed), Some(keys)) => store_encryption::open(keys, &self.id, &sealed),
+            (Some(sealed), None) => Err(format!("encrypted under key '{}' but no keys are configured", sealed.key_id)),
+        }
+    }
+
+    /// The payload, decrypted with `keys` if it is encrypted
+    fn payload(&self, keys: Option<&KeyRing>) -> Result<Value, String> {
+        serde_json::from_slice(&self.plaintext(keys)?).map_err(|e| format!("payload is not valid JSON: {}", e))
+    }
+
+    /// The record sealed under the current key, when `rewrap` asks for it and it is not already:
+    /// a data key wrapped with an older key is re-wrapped, a plaintext record is encrypted
+    fn rewrapped(&self, keys: Option<&KeyRing>, rewrap: bool) -> Option<Sealed> {
+        match (self.sealed(), keys) {
+            (Some(sealed), Some(keys)) if rewrap && sealed.key_id != keys.current() => store_encryption::rewrap(keys, &sealed).ok(),
+            (None, Some(keys)) if rewrap => Some(store_encryption::seal(keys, &self.id, &self.payload)),
+            _ => None,
+        }
+    }
+}
+
+/// An open, migrated store database
+pub struct Store {
+    connection: Mutex<Connection>,
+    keys: Option<KeyRing>,
+    case_hash_key: [u8; 32],
+}
+
+impl Store {
+    /// Open the database at `path`, creating it if needed, and bring its schema up to date.
+    /// Results are encrypted when `keys` are given.
+    pub fn open(path: &Path, keys: Option<KeyRing>) -> Result<Self, String> {
+        let mut connection = Connection::open(path).map_err(|e| format!("cannot open {}: {}", path.display(), e))?;
+        connection
+            .pragma_update(None, "journal_mode", "WAL")
+            .map_err(|e| format!("cannot enable write-ahead logging on {}: {}", path.display(), e))?;
+        connection
+            .pragma_update(None, "secure_delete", "ON")
+            .map_err(|e| format!("cannot enable secure delete on {}: {}", path.display(), e))?;
+        Self::migrate(&mut connection).map_err(|e| format!("cannot migrate {}: {}", path.display(), e))?;
+        let case_hash_key = Self::case_hash_key(&connection, keys.as_ref())
+            .map_err(|e| format!("cannot read the case hash key of {}: {}", path.display(), e))?;
+        Ok(Self { connection: Mutex::new(connection), keys, case_hash_key })
+    }
+
+    /// The HMAC key of case hashes, created at random with the database and sealed under the
+    /// current key when keys are configured
+    fn case_hash_key(connection: &Connection, keys: Option<&KeyRing>) -> Result<[u8; 32], String> {
+        let stored = connection
+            .query_row(
+                "SELECT name, value, key_id, wrapped_key FROM secrets WHERE name = ?1",
+                params![CASE_HASH_KEY],
+                ResultRow::read,
+            )
+            .optional()
+            .map_err(|e| e.to_string())?;
+        if let Some(row) = stored {
+            return <[u8; 32]>::try_from(row.plaintext(keys)?).map_err(|_| "the key is not 32 bytes".to_string());
+        }
+        let key: [u8; 32] = rand::random();
+        let sealed = keys.map(|keys| store_encryption::seal(keys, CASE_HASH_KEY, &key));
+        connection
+            .execute(
+                "INSERT INTO secrets (name, value, key_id, wrapped_key) VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    CASE_HASH_KEY,
+                    sealed.as_ref().map_or(key.to_vec(), |sealed| sealed.ciphertext.clone()),
+                    sealed.as_ref().map(|sealed| &sealed.key_id),
+                    sealed.as_ref().map(|sealed| &sealed.wrapped_key),
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+        Ok(key)
+    }
+
+    /// Apply the migrations the database has not had yet; returns the schema version
+    fn migrate(connection: &mut Connection) -> Result<usize, String> {
+        let current: i64 = connection.query_row("PRAGMA user_version", [], |row| row.get(0)).map_err(|e| e.to_string())?;
+        let current = usize::try_from(current).unwrap_or(0);
+        if current > MIGRATIONS.len() {
+            return Err(format!("schema version {} is newer than this server's {}", current, MIGRATIONS.len()));
+        }
+        for (applied, sql) in MIGRATIONS.iter().enumerate().skip(current) {
+            let transaction = connection.transaction().map_err(|e| e.to_string())?;
+            transaction.execute_batch(sql).map_err(|e| format!("migration {}: {}", applied + 1, e))?;
+            transaction.pragma_update(None, "user_version", applied as i64 + 1).map_err(|e| e.to_string())?;
+            transaction.commit().map_err(|e| e.to_string())?;
+            tracing::info!(version = applied + 1, "Embedded store migrated");
+        }
+        Ok(MIGRATIONS.len())
+    }
+
+    fn connection(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.connection.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Keep the result of a `tool` call, encrypted if keys are configured, dropping the oldest
+    /// beyond the `capacity` most recent
+    fn save_result(&self, id: &str, tool: &str, case_hash: Option<&str>, payload: &Value, capacity: usize) -> rusqlite::Result<()> {
+        let connection = self.connection();
+        let created_at = clock::now().to_rfc3339();
+        match &self.keys {
+            Some(keys) => {
+                let sealed = store_encryption::seal(keys, id, payload.to_string().as_bytes());
+                connection.execute(
+                    "INSERT OR REPLACE INTO results (id, payload, created_at, key_id, wrapped_key, tool, case_hash)
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                    params![id, sealed.ciphertext, created_at, sealed.key_id, sealed.wrapped_key, tool, case_hash],
+                )?;
+            }
+            None => {
+                connection.execute(
+                    "INSERT OR REPLACE INTO results (id, payload, created_at, tool, case_hash) VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![id, payload.to_string(), created_at, tool, case_hash],
+                )?;
+            }
+        }
+        let newest = connection.last_insert_rowid();
+        connection.execute("DELETE FROM results WHERE seq <= ?1", params![newest - capacity as i64])?;
+        Ok(())
+    }
+
+    /// The result `id`, unless it was stored before `not_before`
+    fn load_result(&self, id: &str, not_before: Option<DateTime<Utc>>) -> Result<Option<Value>, String> {
+        let row = self
+            .connection()
+            .query_row(
+                "SELECT id, payload, key_id, wrapped_key FROM results
+                WHERE id = ?1 AND (?2 IS NULL OR julianday(created_at) >= julianday(?2))",
+                params![id, not_before.map(|time| time.to_rfc3339())],
+                ResultRow::read,
+            )
+            .optional()
+            .map_err(|e| e.to_string())?;
+        row.map(|row| row.payload(self.keys.as_ref())).transpose()
+    }
+
+    /// The results of the case hashed to `case_hash`, oldest first, and those that could not be
+    /// read, with the reason
+    fn case_results(&self, case_hash: &str) -> rusqlite::Result<(Vec<StoredResult>, Vec<String>)> {
+        let connection = self.connection();
+        let mut statement = connection.prepare(
+            "SELECT id, payload, key_id, wrapped_key, tool, created_at FROM results WHERE case_hash = ?1 ORDER BY seq",
+        )?;
+        let rows = statement.query_map(params![case_hash], |row| {
+            Ok((ResultRow::read(row)?, row.get::<_, Option<String>>(4)?, row.get::<_, String>(5)?))
+        })?;
+        let (mut results, mut unreadable) = (Vec::new(), Vec::new());
+        for row in rows {
+            let (row, tool, created_at) = row?;
+            let stored_at = DateTime::parse_from_rfc3339(&created_at).map(|time| time.with_timezone(&Utc));
+            match (row.payload(self.keys.as_ref()), stored_at) {
+                (Ok(payload), Ok(stored_at)) => results.push(StoredResult { id: row.id, tool: tool.unwrap_or_default(), stored_at, payload }),
+                (Err(e), _) => unreadable.push(format!("{}: {}", row.id, e)),
+                (_, Err(e)) => unreadable.push(format!("{}: stored time '{}' is invalid: {}", row.id, created_at, e)),
+            }
+        }
+        Ok((results, unreadable))
+    }
+
+    /// Delete the results of the case hashed to `case_hash` and their wrapped data keys, then
+    /// truncate the write-ahead log so no copy stays in it; returns the ids deleted and how many
+    /// of them were encrypted
+    fn erase_case(&self, case_hash: &str) -> rusqlite::Result<(Vec<String>, usize)> {
+        let mut connection = self.connection();
+        let transaction = connection.transaction()?;
+        let rows = transaction
+            .prepare("SELECT id, wrapped_key IS NOT NULL FROM results WHERE case_hash = ?1 ORDER BY seq")?
+            .query_map(params![case_hash], |row| Ok((row.get::<_, String>(0)?, row.get::<_, bool>(1)?)))?
+            .collect::<rusqlite::Result<Vec<(String, bool)>>>()?;
+        transaction.execute("DELETE FROM results WHERE case_hash = ?1", params![case_hash])?;
+        transaction.commit()?;
+        connection.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+        let shredded = rows.iter().filter(|(_, encrypted)| *encrypted).count();
+        Ok((rows.into_iter().map(|(id, _)| id).collect(), shredded))
+    }
+
+    /// Delete the results stored before `cutoff`; returns how many
+    fn purge_results(&self, cutoff: DateTime<Utc>) -> rusqlite::Result<usize> {
+        self.connection()
+            .execute("DELETE FROM results WHERE julianday(created_at) < julianday(?1)", params![cutoff.to_rfc3339()])
+    }
+
+    /// Delete the usage counts if counting began before `cutoff`, counting again from `now`;
+    /// returns the tools cleared
+    fn purge_usage(&self, cutoff: DateTime<Utc>, now: DateTime<Utc>) -> rusqlite::Result<usize> {
+        let mut connection = self.connection();
+        let transaction = connection.transaction()?;
+        let expired = transaction.execute(
+            "UPDATE meta SET value = ?2 WHERE key = 'usage_since' AND julianday(value) < julianday(?1)",
+            params![cutoff.to_rfc3339(), now.to_rfc3339()],
+        )?;
+        let cleared = if expired > 0 { transaction.execute("DELETE FROM usage", [])? } else { 0 };
+        transaction.commit()?;
+        Ok(cleared)
+    }
+
+    /// Check the database file and that every result decrypts and authenticates. With `rewrap`,
+    /// data keys wrapped with an older key are wrapped with the current one, and plaintext
+    /// results are encrypted, when keys are configured; the store's secrets move with them.
+    fn verify(&self, rewrap: bool) -> rusqlite::Result<StoreIntegrity> {
+        let mut connection = self.connection();
+        let version: i64 = connection.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        let database_check = connection
+            .prepare("PRAGMA integrity_check")?
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?
+            .join("; ");
+        let rows = connection
+            .prepare("SELECT id, payload, key_id, wrapped_key FROM results ORDER BY seq")?
+            .query_map([], ResultRow::read)?
+            .collect::<rusqlite::Result<Vec<ResultRow>>>()?;
+
+        let mut integrity = StoreIntegrity {
+            schema_version: version,
+            database_check,
+            encryption: self.keys.is_some(),
+            current_key: self.keys.as_ref().map(|keys| keys.current().to_string()),
+            records: rows.len(),
+            ..StoreIntegrity::default()
+        };
+        let transaction = connection.transaction()?;
+        for row in &rows {
+            if let Err(e) = row.payload(self.keys.as_ref()) {
+                integrity.failed_records += 1;
+                if integrity.failures.len() < MAX_INTEGRITY_FAILURES {
+                    integrity.failures.push(format!("{}: {}", row.id, e));
+                }
+                continue;
+            }
+            let moved = row.rewrapped(self.keys.as_ref(), rewrap);
+            if let Some(sealed) = &moved {
+                transaction.execute(
+                    "UPDATE results SET payload = ?2, key_id = ?3, wrapped_key = ?4 WHERE id = ?1",
+                    params![row.id, sealed.ciphertext, sealed.key_id, sealed.wrapped_key],
+                )?;
+                integrity.rewrapped += 1;
+            }
+            match moved.map(|sealed| sealed.key_id).or_else(|| row.key_id.clone()) {
+                Some(key_id) => *integrity.records_by_key.entry(key_id).or_default() += 1,
+                None => integrity.plaintext_records += 1,
+            }
+        }
+        // The secrets were read with the keys when the store was opened, so they only move
+        let secrets = transaction
+            .prepare("SELECT name, value, key_id, wrapped_key FROM secrets")?
+            .query_map([], ResultRow::read)?
+            .collect::<rusqlite::Result<Vec<ResultRow>>>()?;
+        for secret in &secrets {
+            if let Some(sealed) = secret.rewrapped(self.keys.as_ref(), rewrap) {
+                transaction.execute(
+                    "UPDATE secrets SET value = ?2, key_id = ?3, wrapped_key = ?4 WHERE name = ?1",
+                    params![secret.id, sealed.ciphertext, sealed.key_id, sealed.wrapped_key],
+                )?;
+            }
+        }
+        transaction.commit()?;
+        Ok(integrity)
+    }
+
+    /// Write the counts of `tool`, and when counting began. Counts are copied after the call that
+    /// made them, so a copy is stale and skipped when it has fewer calls than the row already
+    /// holds, or was counted since before the last purge.
+    fn save_usage(&self, tool: &str, counts: &ToolUsage, since: DateTime<Utc>) -> rusqlite::Result<()> {
+        let connection = self.connection();
+        connection.execute(
+            "INSERT OR IGNORE INTO meta (key, value) VALUES ('usage_since', ?1)",
+            params![since.to_rfc3339()],
+        )?;
+        connection.execute(
+            "INSERT INTO usage (tool, calls, failed_calls, parameter_types, errors)
+             SELECT ?1, ?2, ?3, ?4, ?5 FROM meta WHERE key = 'usage_since' AND julianday(?6) >= julianday(value)
+             ON CONFLICT (tool) DO UPDATE SET calls = excluded.calls, failed_calls = excluded.failed_calls,
+                 parameter_types = excluded.parameter_types, errors = excluded.errors
+             WHERE excluded.calls >= usage.calls",
+            params![
+                tool,
+                counts.calls as i64,
+                counts.failed_calls as i64,
+                serde_json::to_string(&counts.parameter_types).unwrap_or_default(),
+                serde_json::to_string(&counts.errors).unwrap_or_default(),
+                since.to_rfc3339(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Usage counts written so far, or None before the first call was counted
+    fn load_usage(&self) -> rusqlite::Result<Option<UsageStats>> {
+        let connection = self.connection();
+        let since: Option<String> =
+            connection.query_row("SELECT value FROM meta WHERE key = 'usage_since'", [], |row| row.get(0)).optional()?;
+        let Some(since) = since.and_then(|since| DateTime::parse_from_rfc3339(&since).ok()) else {
+            return Ok(None);
+        };
+        let mut statement = connection.prepare("SELECT tool, calls, failed_calls, parameter_types, errors FROM usage")?;
+        let rows = statement.query_map([], |row| {
+            let parameter_types: String = row.get(3)?;
+            let errors: String = row.get(4)?;
+            let usage = ToolUsage {
+                calls: row.get::<_, i64>(1)? as u64,
+                failed_calls: row.get::<_, i64>(2)? as u64,
+                parameter_types: serde_json::from_str(&parameter_types).unwrap_or_default(),
+                errors: serde_json::from_str(&errors).unwrap_or_default(),
+            };
+            Ok((row.get::<_, String>(0)?, usage))
+        })?;
+        let tools = rows.collect::<rusqlite::Result<BTreeMap<String, ToolUsage>>>()?;
+        Ok(Some(UsageStats { since: since.with_timezone(&Utc), tools }))
+    }
+}
+
+static STORE: LazyLock<Option<Store>> = LazyLock::new(|| {
+    let path = std::env::var("ENGINE_STORE_PATH")
+        .ok()
+        .filter(|path| !path.trim().is_empty())
+        .map_or_else(|| PathBuf::from(DEFAULT_PATH), PathBuf::from);
+    match KeyRing::from_env().and_then(|keys| Store::open(&path, keys)) {
+        Ok(store) => {
+            let current_key = store.keys.as_ref().map(|keys| keys.current().to_string());
+            tracing::info!(path = %path.display(), version = MIGRATIONS.len(), current_key, "Embedded store opened");
+            Some(store)
+        }
+        Err(e) => {
+            tracing::error!("Embedded store unavailable, keeping results and usage in memory only: {}", e);
+            None
+        }
+    }
+});
+
+/// Open and migrate the database now rather than on first use, so problems are logged at startup
+pub fn open() {
+    LazyLock::force(&STORE);
+}
+
+/// The HMAC key of case hashes kept in the database; None when the store is unavailable
+pub fn case_hash_key() -> Option<[u8; 32]> {
+    STORE.as_ref().map(|store| store.case_hash_key)
+}
+
+/// Keep the `$result` payload of a `tool` call for `case_id`, dropping the oldest beyond
+/// `capacity`
+pub fn save_result(id: &str, tool: &str, case_id: Option<&str>, payload: &Value, capacity: usize) {
+    let case_hash = case_id.map(super::result_store::case_hash);
+    if let Some(store) = STORE.as_ref()
+        && let Err(e) = store.save_result(id, tool, case_hash.as_deref(), payload, capacity)
+    {
+        tracing::warn!(id, error = %e, "Could not write result to the embedded store");
+    }
+}
+
+/// A `$result` payload kept in the database, unless it was stored before `not_before`
+pub fn load_result(id: &str, not_before: Option<DateTime<Utc>>) -> Option<Value> {
+    let store = STORE.as_ref()?;
+    store
+        .load_result(id, not_before)
+        .inspect_err(|e| tracing::warn!(id, error = %e, "Could not read result from the embedded store"))
+        .ok()
+        .flatten()
+}
+
+/// The results of the case hashed to `case_hash` and those that could not be read; None when the
+/// store is unavailable or the query failed
+pub fn case_results(case_hash: &str) -> Option<(Vec<StoredResult>, Vec<String>)> {
+    let store = STORE.as_ref()?;
+    store
+        .case_results(case_hash)
+        .inspect_err(|e| tracing::warn!(error = %e, "Could not read case results from the embedded store"))
+        .ok()
+}
+
+/// Delete the results of the case hashed to `case_hash`; returns the ids deleted and how many
+/// were encrypted, or None when the store is unavailable or the delete failed
+pub fn erase_case(case_hash: &str) -> Option<(Vec<String>, usize)> {
+    let store = STORE.as_ref()?;
+    store
+        .erase_case(case_hash)
+        .inspect_err(|e| tracing::error!(error = %e, "Could not erase case results from the embedded store"))
+        .ok()
+}
+
+/// Check the store's integrity, re-wrapping data keys with the current key if asked
+pub fn verify(rewrap: bool) -> Result<StoreIntegrity, String> {
+    let store = STORE.as_ref().ok_or("The embedded store is unavailable; see the startup log (ENGINE_STORE_PATH)")?;
+    store.verify(rewrap).map_err(|e| format!("Could not check the embedded store: {}", e))
+}
+
+/// Delete the results stored before `cutoff`; returns how many, or None when the store is
+/// unavailable or the delete failed
+pub fn purge_results(cutoff: DateTime<Utc>) -> Option<usize> {
+    let store = STORE.as_ref()?;
+    store
+        .purge_results(cutoff)
+        .inspect_err(|e| tracing::warn!(error = %e, "Could not purge results from the embedded store"))
+        .ok()
+}
+
+/// Delete the usage counts if counting began before `cutoff`, counting again from `now`; returns
+/// the tools cleared, or None when the store is unavailable or the delete failed
+pub fn purge_usage(cutoff: DateTime<Utc>, now: DateTime<Utc>) -> Option<usize> {
+    let store = STORE.as_ref()?;
+    store
+        .purge_usage(cutoff, now)
+        .inspect_err(|e| tracing::warn!(error = %e, "Could not purge usage from the embedded store"))
+        .ok()
+}
+
+/// Write the usage counts of `tool`, counted since `since`
+pub fn save_usage(tool: &str, counts: &ToolUsage, since: DateTime<Utc>) {
+    if let Some(store) = STORE.as_ref()
+        && let Err(e) = store.save_usage(tool, counts, since)
+    {
+        tracing::warn!(tool, error = %e, "Could not write usage to the embedded store");
+    }
+}
+
+/// Usage counts kept in the database, to continue counting from
+pub fn load_usage() -> Option<UsageStats> {
+    let store = STORE.as_ref()?;
+    store
+        .load_usage()
+        .inspect_err(|e| tracing::warn!(error = %e, "Could not read usage from the embedded store"))
+        .ok()
+        .flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_embedded_store() {
+        let path = std::env::temp_dir().join(format!("engine-store-{}.db", uuid::Uuid::new_v4()));
+        let store = Store::open(&path, None).unwrap();
+
+        for n in 1..=3 {
+            store.save_result(&format!("r{}", n), "calc_tax", None, &json!({ "tax": n }), 2).unwrap();
+        }
+        // Only the two most recent results are kept
+        assert_eq!(store.load_result("r1", None).unwrap(), None);
+        assert_eq!(store.load_result("r3", None).unwrap(), Some(json!({ "tax": 3 })));
+
+        assert_eq!(store.load_usage().unwrap(), None);
+        let mut usage = UsageStats::default();
+        let counts = usage.tools.entry("calc_tax".to_string()).or_default();
+        counts.calls = 4;
+        counts.failed_calls = 1;
+        counts.parameter_types.insert("income".to_string(), BTreeMap::from([("string".to_string(), 4)]));
+        counts.errors.insert("Invalid income parameter".to_string(), 1);
+        store.save_usage("calc_tax", &usage.tools["calc_tax"], usage.since).unwrap();
+        // A stale copy with fewer calls does not overwrite newer counts
+        let stale = ToolUsage { calls: 3, ..ToolUsage::default() };
+        store.save_usage("calc_tax", &stale, usage.since).unwrap();
+        drop(store);
+
+        // Reopening keeps the data and applies no migration twice
+        let store = Store::open(&path, None).unwrap();
+        assert_eq!(store.load_result("r2", None).unwrap(), Some(json!({ "tax": 2 })));
+        let loaded = store.load_usage().unwrap().unwrap();
+        assert_eq!(loaded.tools, usage.tools);
+        assert_eq!(loaded.since.timestamp(), usage.since.timestamp());
+
+        let connection = store.connection();
+        connection.pragma_update(None, "user_version", MIGRATIONS.len() as i64 + 1).unwrap();
+        drop(connection);
+        drop(store);
+        let Err(error) = Store::open(&path, None) else {
+            panic!("a newer schema must not be opened");
+        };
+        assert!(error.contains("newer than this server"), "{}", error);
+        remove(&path);
+    }
+
+    #[test]
+    fn test_embedded_store_encryption() {
+        let old_key = format!("2025:{}", "01".repeat(32));
+        let new_key = format!("2026:{}", "fe".repeat(32));
+        let keys = |text: &str| Some(KeyRing::parse(text).unwrap());
+        let path = std::env::temp_dir().join(format!("engine-store-{}.db", uuid::Uuid::new_v4()));
+
+        // A result written before encryption was configured stays readable
+        let store = Store::open(&path, None).unwrap();
+        store.save_result("r1", "calc_tax", None, &json!({ "income": 41000 }), 10).unwrap();
+        let case_hash_key = store.case_hash_key;
+        drop(store);
+        let store = Store::open(&path, keys(&old_key)).unwrap();
+        store.save_result("r2", "calc_tax", None, &json!({ "income": 52000 }), 10).unwrap();
+        assert_eq!(store.load_result("r1", None).unwrap(), Some(json!({ "income": 41000 })));
+        assert_eq!(store.load_result("r2", None).unwrap(), Some(json!({ "income": 52000 })));
+        let stored: Vec<u8> =
+            store.connection().query_row("SELECT payload FROM results WHERE id = 'r2'", [], |row| row.get(0)).unwrap();
+        assert!(!String::from_utf8_lossy(&stored).contains("52000"));
+        let integrity = store.verify(false).unwrap();
+        assert_eq!((integrity.database_check.as_str(), integrity.schema_version), ("ok", MIGRATIONS.len() as i64));
+        assert_eq!((integrity.records, integrity.plaintext_records, integrity.failed_records), (2, 1, 0));
+        assert_eq!(integrity.records_by_key, BTreeMap::from([("2025".to_string(), 1)]));
+        drop(store);
+        let store = Store::open(&path, None).unwrap();
+        assert!(store.load_result("r2", None).unwrap_err().contains("no keys are configured"));
+        assert_eq!(store.verify(false).unwrap().failed_records, 1);
+        drop(store);
+
+        // Rotation: rewrap moves every record to the new key, after which the old one can go
+        let store = Store::open(&path, keys(&format!("{},{}", new_key, old_key))).unwrap();
+        let integrity = store.verify(true).unwrap();
+        assert_eq!((integrity.rewrapped, integrity.plaintext_records), (2, 0));
+        assert_eq!(integrity.records_by_key, BTreeMap::from([("2026".to_string(), 2)]));
+        drop(store);
+        let store = Store::open(&path, keys(&new_key)).unwrap();
+        assert_eq!(store.load_result("r1", None).unwrap(), Some(json!({ "income": 41000 })));
+        // The case hash key was sealed and moved with the results, so case hashes stay valid
+        assert_eq!(store.case_hash_key, case_hash_key);
+        let (value, key_id): (Vec<u8>, Option<String>) = store
+            .connection()
+            .query_row("SELECT value, key_id FROM secrets WHERE name = ?1", params![CASE_HASH_KEY], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap();
+        assert_ne!(value, case_hash_key);
+        assert_eq!(key_id.as_deref(), Some("2026"));
+        assert_eq!(store.verify(true).unwrap().rewrapped, 0);
+
+        // A payload moved onto another record does not authenticate
+        store
+            .connection()
+            .execute("UPDATE results SET payload = (SELECT payload FROM results WHERE id = 'r2') WHERE id = 'r1'", [])
+            .unwrap();
+        let integrity = store.verify(false).unwrap();
+        assert_eq!((integrity.failed_records, integrity.failures.as_slice()), (1, ["r1: payload does not authenticate".to_string()].as_slice()));
+        drop(store);
+
+        // Without the keys the case hash key cannot be read, so the store is not opened
+        let Err(error) = Store::open(&path, None) else {
+            panic!("an encrypted store must not be opened without keys");
+        };
+        assert!(error.contains("case hash key") && error.contains("no keys are configured"), "{}", error);
+        remove(&path);
+    }
+
+    #[test]
+    fn test_embedded_store_retention() {
+        let path = std::env::temp_dir().join(format!("engine-store-{}.db", uuid::Uuid::new_v4()));
+        let store = Store::open(&path, None).unwrap();
+        let now = clock::now();
+        let days_ago = |days: i64| (now - chrono::TimeDelta::days(days)).to_rfc3339();
+        for (id, age) in [("old", 40), ("recent", 2)] {
+            store.save_result(id, "calc_tax", None, &json!({ "tax": 1 }), 10).unwrap();
+            store.connection().execute("UPDATE results SET created_at = ?2 WHERE id = ?1", params![id, days_ago(age)]).unwrap();
+        }
+        let cutoff = now - chrono::TimeDelta::days(30);
+        // Expired results are not read back even before they are purged
+        assert_eq!(store.load_result("old", None).unwrap(), Some(json!({ "tax": 1 })));
+        assert_eq!(store.load_result("old", Some(cutoff)).unwrap(), None);
+        assert_eq!(store.purge_results(cutoff).unwrap(), 1);
+        assert_eq!(store.load_result("old", None).unwrap(), None);
+        assert_eq!(store.load_result("recent", Some(cutoff)).unwrap(), Some(json!({ "tax": 1 })));
+
+        let mut usage = UsageStats { since: now - chrono::TimeDelta::days(10), ..UsageStats::default() };
+        usage.tools.entry("calc_tax".to_string()).or_default().calls = 3;
+        store.save_usage("calc_tax", &usage.tools["calc_tax"], usage.since).unwrap();
+        assert_eq!(store.purge_usage(cutoff, now).unwrap(), 0);
+        assert!(store.load_usage().unwrap().is_some());
+        store.connection().execute("UPDATE meta SET value = ?1 WHERE key = 'usage_since'", params![days_ago(31)]).unwrap();
+        assert_eq!(store.purge_usage(cutoff, now).unwrap(), 1);
+        let purged = store.load_usage().unwrap().unwrap();
+        assert!(purged.tools.is_empty());
+        assert_eq!(purged.since.timestamp(), now.timestamp());
+        // Counts copied before the purge are not written back
+        store.save_usage("calc_tax", &usage.tools["calc_tax"], days_ago(31).parse().unwrap()).unwrap();
+        assert!(store.load_usage().unwrap().unwrap().tools.is_empty());
+        drop(store);
+        remove(&path);
+    }
+
+    #[test]
+    fn test_embedded_store_erases_case() {
+        let path = std::env::temp_dir().join(format!("engine-store-{}.db", uuid::Uuid::new_v4()));
+        let store = Store::open(&path, None).unwrap();
+        store.save_result("r1", "calc_tax", Some("hash-1"), &json!({ "income": 41000 }), 10).unwrap();
+        drop(store);
+        let store = Store::open(&path, Some(KeyRing::parse(&format!("2026:{}", "fe".repeat(32))).unwrap())).unwrap();
+        store.save_result("r2", "calc_benefit", Some("hash-1"), &json!({ "income": 52000 }), 10).unwrap();
+        store.save_result("r3", "calc_tax", Some("hash-2"), &json!({ "income": 60000 }), 10).unwrap();
+        store.save_result("r4", "calc_tax", None, &json!({ "income": 70000 }), 10).unwrap();
+
+        let (results, unreadable) = store.case_results("hash-1").unwrap();
+        let found: Vec<(&str, &str)> = results.iter().map(|result| (result.id.as_str(), result.tool.as_str())).collect();
+        assert_eq!(found, [("r1", "calc_tax"), ("r2", "calc_benefit")]);
+        assert_eq!(results[1].payload, json!({ "income": 52000 }));
+        assert!(unreadable.is_empty());
+
+        assert_eq!(store.erase_case("hash-1").unwrap(), (vec!["r1".to_string(), "r2".to_string()], 1));
+        assert_eq!(store.case_results("hash-1").unwrap(), (Vec::new(), Vec::new()));
+        assert_eq!(store.load_result("r2", None).unwrap(), None);
+        assert!(store.load_result("r3", None).unwrap().is_some());
+        assert_eq!(store.erase_case("hash-1").unwrap(), (Vec::new(), 0));
+        drop(store);
+
+        // Nothing of the erased plaintext result is left in the database file
+        let bytes = std::fs::read(&path).unwrap();
+        assert!(!bytes.windows(5).any(|window| window == b"41000"));
+        remove(&path);
+    }
+
+    fn remove(path: &Path) {
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+    }
+}
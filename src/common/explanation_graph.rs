@@ -0,0 +1,241 @@
+//! Explanation steps as a graph, for clients that draw the audit trail of a result.
+//!
+//! With the `explanation_graph` parameter set to `mermaid` or `dot`, the explanation steps a call
+//! renders are recorded together with the values each step's template was given, and the
+//! envelope carries them as a Mermaid flowchart or a Graphviz DOT digraph. Parsed inputs are
+//! input nodes, each step is a rule node labelled with its explanation text, and every other
+//! value is a value node drawn from the first step that uses it. Edges run from the values a
+//! step uses to the step and from the step to the values it introduces, so the graph follows
+//! the data from the inputs to the result. Values are matched by name and value, so a value
+//! repeated under the same name (the running tax across brackets) links the steps it passes
+//! through.
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write;
+
+use minijinja::Value;
+
+/// Notation of an explanation graph
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphFormat {
+    /// Mermaid `flowchart`
+    Mermaid,
+    /// Graphviz DOT `digraph`
+    Dot,
+}
+
+impl GraphFormat {
+    /// Parse the optional `explanation_graph` tool parameter; None when no graph is wanted
+    pub fn parse(value: Option<&String>) -> Result<Option<Self>, String> {
+        match value.map(|s| s.trim().to_ascii_lowercase()) {
+            None => Ok(None),
+            Some(s) if s.is_empty() || s == "none" => Ok(None),
+            Some(s) if s == "mermaid" => Ok(Some(GraphFormat::Mermaid)),
+            Some(s) if s == "dot" => Ok(Some(GraphFormat::Dot)),
+            Some(_) => Err("must be 'mermaid' or 'dot'".to_string()),
+        }
+    }
+}
+
+/// One rendered explanation step and the scalar values its template was given
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+    pub text: String,
+    pub values: Vec<(String, serde_json::Value)>,
+}
+
+thread_local! {
+    static RECORDING: RefCell<Option<Vec<Step>>> = const { RefCell::new(None) };
+}
+
+/// Run `body`, returning the explanation steps it rendered on this thread
+pub fn record<T>(body: impl FnOnce() -> T) -> (T, Vec<Step>) {
+    let outer = RECORDING.with(|recording| recording.replace(Some(Vec::new())));
+    let result = body();
+    let steps = RECORDING.with(|recording| recording.replace(outer)).unwrap_or_default();
+    (result, steps)
+}
+
+/// Keep a rendered step if a recording is running
+pub(super) fn observe(text: &str, ctx: &Value) {
+    RECORDING.with(|recording| {
+        if let Some(steps) = recording.borrow_mut().as_mut() {
+            let values = ctx
+                .try_iter()
+                .into_iter()
+                .flatten()
+                .filter_map(|name| {
+                    let value = serde_json::to_value(ctx.get_item(&name).ok()?).ok()?;
+                    let scalar = value.is_number() || value.is_boolean() || value.is_string();
+                    scalar.then(|| (name.to_string(), value))
+                })
+                .collect();
+            steps.push(Step { text: text.to_string(), values });
+        }
+    });
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum NodeKind {
+    Input,
+    Value,
+    Rule,
+}
+
+struct Node {
+    id: String,
+    kind: NodeKind,
+    label: String,
+}
+
+/// A value as shown in a node: numbers to at most six decimals, so float noise does not split
+/// equal values
+fn value_text(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Number(number) if number.is_f64() => {
+            let text = format!("{:.6}", number.as_f64().unwrap_or_default());
+            let text = text.trim_end_matches('0').trim_end_matches('.');
+            if text == "-0" { "0".to_string() } else { text.to_string() }
+        }
+        serde_json::Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Draw `steps` from `inputs` (parsed parameters by path) in `format`
+pub fn render(format: GraphFormat, inputs: &BTreeMap<String, serde_json::Value>, steps: &[Step]) -> String {
+    let mut nodes: Vec<Node> = Vec::new();
+    let mut edges: Vec<(usize, usize)> = Vec::new();
+    let mut seen_edges = HashSet::new();
+    // Node of each (name, value) pair seen so far
+    let mut values: HashMap<(String, String), usize> = HashMap::new();
+
+    for (path, value) in inputs {
+        if value.is_array() || value.is_object() || value.is_null() {
+            continue;
+        }
+        let text = value_text(value);
+        let index = nodes.len();
+        nodes.push(Node { id: format!("in{}", index), kind: NodeKind::Input, label: format!("{} = {}", path, text) });
+        let leaf = path.rsplit('.').next().unwrap_or(path);
+        values.entry((leaf.to_string(), text.clone())).or_insert(index);
+        values.entry((path.clone(), text)).or_insert(index);
+    }
+    for step in steps {
+        let rule = nodes.len();
+        nodes.push(Node { id: format!("s{}", rule), kind: NodeKind::Rule, label: step.text.clone() });
+        for (name, value) in &step.values {
+            let text = value_text(value);
+            let edge = match values.get(&(name.clone(), text.clone())) {
+                Some(&node) => (node, rule),
+                None => {
+                    let node = nodes.len();
+                    nodes.push(Node { id: format!("v{}", node), kind: NodeKind::Value, label: format!("{} = {}", name, text) });
+                    values.insert((name.clone(), text), node);
+                    (rule, node)
+                }
+            };
+            if seen_edges.insert(edge) {
+                edges.push(edge);
+            }
+        }
+    }
+
+    let mut graph = String::new();
+    match format {
+        GraphFormat::Mermaid => {
+            graph.push_str("flowchart TD\n");
+            for node in &nodes {
+                let label = node.label.replace('"', "#quot;");
+                let _ = match node.kind {
+                    NodeKind::Input => writeln!(graph, "    {}[/\"{}\"/]", node.id, label),
+                    NodeKind::Value => writeln!(graph, "    {}(\"{}\")", node.id, label),
+                    NodeKind::Rule => writeln!(graph, "    {}[\"{}\"]", node.id, label),
+                };
+            }
+            for (from, to) in edges {
+                let _ = writeln!(graph, "    {} --> {}", nodes[from].id, nodes[to].id);
+            }
+        }
+        GraphFormat::Dot => {
+            graph.push_str("digraph explanation {\n");
+            for node in &nodes {
+                let label = node.label.replace('\\', "\\\\").replace('"', "\\\"");
+                let shape = match node.kind {
+                    NodeKind::Input => "parallelogram",
+                    NodeKind::Value => "ellipse",
+                    NodeKind::Rule => "box",
+                };
+                let _ = writeln!(graph, "    {} [label=\"{}\", shape={}];", node.id, label, shape);
+            }
+            for (from, to) in edges {
+                let _ = writeln!(graph, "    {} -> {};", nodes[from].id, nodes[to].id);
+            }
+            graph.push_str("}\n");
+        }
+    }
+    graph
+}
+
+#[cfg(test)]
+mod tests {
+    use minijinja::context;
+    use rmcp::handler::server::wrapper::Parameters;
+
+    use super::*;
+    use crate::common::compatibility_engine::{CalcPenaltyParams, CompatibilityEngine};
+
+    #[test]
+    fn test_explanation_graph() {
+        assert_eq!(GraphFormat::parse(Some(&" Mermaid ".to_string())), Ok(Some(GraphFormat::Mermaid)));
+        assert_eq!(GraphFormat::parse(None), Ok(None));
+        assert!(GraphFormat::parse(Some(&"svg".to_string())).is_err());
+
+        let ((), steps) = record(|| {
+            observe("Base: 10 × 5 = 50", &context! { days_late => 10.0, rate => 5.0, base => 50.000000000001 });
+            observe("Final \"50\"", &context! { base => 50.0, label => "ok", list => vec![1, 2] });
+        });
+        assert_eq!(steps.len(), 2);
+        assert_eq!(steps[1].values.len(), 2);
+        // Nothing is recorded outside a recording
+        observe("ignored", &context! {});
+
+        let inputs = BTreeMap::from([("days_late".to_string(), serde_json::json!(10.0))]);
+        let mermaid = render(GraphFormat::Mermaid, &inputs, &steps);
+        let id = |label: &str| {
+            let line = mermaid.lines().find(|line| line.contains(label)).unwrap_or_else(|| panic!("{} in {}", label, mermaid));
+            line.split_whitespace().next().unwrap().split(['[', '(']).next().unwrap().to_string()
+        };
+        assert!(mermaid.starts_with("flowchart TD\n"));
+        assert!(mermaid.contains("in0[/\"days_late = 10\"/]"));
+        let (base, final_step) = (id("\"base = 50\""), id("Final"));
+        assert!(mermaid.contains(&format!("in0 --> {}", id("Base:"))));
+        // The base value is introduced by the first step and used by the second
+        assert!(mermaid.contains(&format!("{} --> {}", id("Base:"), base)), "{}", mermaid);
+        assert!(mermaid.contains(&format!("{} --> {}", base, final_step)), "{}", mermaid);
+        assert!(mermaid.contains("Final #quot;50#quot;"));
+
+        let dot = render(GraphFormat::Dot, &inputs, &steps);
+        assert!(dot.starts_with("digraph explanation {") && dot.ends_with("}\n"));
+        assert!(dot.contains(&format!("{} [label=\"Final \\\"50\\\"\", shape=box];", final_step)), "{}", dot);
+    }
+
+    #[tokio::test]
+    async fn test_explanation_graph_in_envelope() {
+        let engine = CompatibilityEngine::new();
+        let params: CalcPenaltyParams =
+            serde_json::from_str(r#"{"days_late": 10, "rate_per_day": 5, "explanation_graph": "dot"}"#).unwrap();
+        let result = engine.calc_penalty(Parameters(params)).await.unwrap();
+        let envelope: serde_json::Value = serde_json::from_str(&result.content[0].raw.as_text().unwrap().text).unwrap();
+        let graph = envelope["explanation_graph"].as_str().unwrap();
+        assert!(graph.contains("days_late = 10") && graph.contains("base_penalty = 50"), "{}", graph);
+
+        let params: CalcPenaltyParams = serde_json::from_str(r#"{"days_late": 10}"#).unwrap();
+        let result = engine.calc_penalty(Parameters(params)).await.unwrap();
+        assert!(!result.content[0].raw.as_text().unwrap().text.contains("explanation_graph"));
+
+        let params: CalcPenaltyParams = serde_json::from_str(r#"{"days_late": 10, "explanation_graph": "png"}"#).unwrap();
+        assert!(engine.calc_penalty(Parameters(params)).await.unwrap().is_error.unwrap_or(false));
+    }
+}
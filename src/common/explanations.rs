@@ -0,0 +1,851 @@
+//! Explanation templates for tool responses, rendered with [`minijinja`].
+//!
+//! Every explanation step has a built-in English template named `<tool>.<step>` for each
+//! [`Audience`]: technical wording for experts and a plain-language narrative for citizens.
+//! Deployments can override the wording per locale and per tool without code changes: set
+//! `ENGINE_EXPLANATION_TEMPLATES_DIR` to a directory containing `<locale>/<tool>.json` files (and
+//! `<locale>/citizen/<tool>.json` for the citizen audience), each a JSON object mapping step names
+//! to template sources, and pick the locale with `ENGINE_LOCALE` (default `en`). Steps that are
+//! not overridden, fail to parse, or fail to render fall back to the built-in template.
+//!
+//! Besides the standard minijinja filters, templates can use `fixed(n)` (fixed decimals),
+//! `percent(n)` (ratio × 100 with fixed decimals), `num` (shortest number form) and
+//! `money(currency)` (two decimals with the currency).
+//!
+//! Numbers are written with a dot and no digit grouping (`1234.56`, `1234.56 EUR`) unless a
+//! [`NumberFormat`] is in effect, chosen per call by the `locale` tool parameter or for the
+//! deployment by `ENGINE_NUMBER_LOCALE`: `de-DE` writes `1.234,56` and `1.234,56 €`, `en-US`
+//! `1,234.56` and `$1,234.56`. The wording still comes from the templates of `ENGINE_LOCALE`.
+
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::LazyLock;
+
+use minijinja::{AutoEscape, Environment, UndefinedBehavior, Value};
+
+use super::explanation_graph;
+
+pub const DEFAULT_LOCALE: &str = "en";
+
+/// Who the explanation is written for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Audience {
+    /// Technical step-by-step explanation with formulas and comparison symbols
+    #[default]
+    Expert,
+    /// Plain-language narrative in short sentences, for public-facing deployments
+    Citizen,
+}
+
+impl Audience {
+    /// Parse the optional `audience` tool parameter, defaulting to [`Audience::Expert`]
+    pub fn parse(value: Option<&String>) -> Result<Self, String> {
+        match value.map(|s| s.trim().to_ascii_lowercase()) {
+            None => Ok(Audience::Expert),
+            Some(s) if s.is_empty() || s == "expert" => Ok(Audience::Expert),
+            Some(s) if s == "citizen" => Ok(Audience::Citizen),
+            Some(_) => Err("must be 'expert' or 'citizen'".to_string()),
+        }
+    }
+
+    /// Prefix of this audience's templates in the environment and the override directory
+    fn prefix(self) -> &'static str {
+        match self {
+            Audience::Expert => "",
+            Audience::Citizen => "citizen/",
+        }
+    }
+}
+
+/// Built-in templates, keyed by `<tool>.<step>`
+const DEFAULT_TEMPLATES: &[(&str, &str)] = &[
+    // calc_penalty
+    ("calc_penalty.invalid_inputs", "Calculation failed due to invalid inputs"),
+    ("calc_penalty.base_penalty", "Base penalty: {{ days_late | num }} days × {{ rate_per_day | num }} = {{ base_penalty | fixed(2) }}"),
+    ("calc_penalty.cap", "{% if capped %}Applied cap on base penalty: {{ base_penalty | fixed(2) }} capped at {{ cap | fixed(2) }}{% else %}No cap applied on base penalty ({{ base_penalty | fixed(2) }} ≤ {{ cap | fixed(2) }}){% endif %}"),
+    ("calc_penalty.interest", "Interest: {{ penalty | fixed(2) }} × {{ interest_rate | percent(1) }}% = {{ interest | fixed(2) }}"),
+    ("calc_penalty.accrued_interest", "Interest from {{ due_date }} to {{ valuation_date }}: {{ penalty | fixed(2) }} × {{ interest_rate | percent(1) }}% × {{ interest_days }}/365 days = {{ interest | fixed(2) }}"),
+    ("calc_penalty.final", "Final penalty: {{ penalty | fixed(2) }} + {{ interest | fixed(2) }} = {{ final_penalty | fixed(2) }}"),
+    // calc_tax
+    ("calc_tax.invalid_inputs", "Tax calculation failed due to invalid inputs"),
+    ("calc_tax.starting_income", "Starting income: {{ income | fixed(2) }}"),
+    ("calc_tax.loss_carryforward", "Loss carryforward: {{ available | fixed(2) }} available, {{ applied | fixed(2) }} applied{% if current_loss > 0 %}, current-year loss {{ current_loss | fixed(2) }} added{% endif %}, {{ remaining | fixed(2) }} carried forward. Taxable income: {{ taxable_income | fixed(2) }}"),
+    ("calc_tax.no_taxable_income", "No taxable income ({{ taxable_income | fixed(2) }}): tax is 0.00"),
+    ("calc_tax.schedule_period", "{% if before %}Schedule before {{ effective_date }}{% else %}Schedule from {{ effective_date }}{% endif %} ({{ days }} of {{ total_days }} days), applied to full-year income"),
+    ("calc_tax.prorated", "Prorated tax: {{ before_tax | fixed(2) }} × {{ before_days }}/{{ total_days }} + {{ after_tax | fixed(2) }} × {{ after_days }}/{{ total_days }} = {{ tax | fixed(2) }}"),
+    ("calc_tax.bracket", "Bracket {{ index }} ({{ lower | fixed(0) }}-{{ upper | fixed(0) }}): {{ taxable | fixed(2) }} × {{ rate | percent(1) }}% = {{ bracket_tax | fixed(2) }}"),
+    ("calc_tax.highest_bracket", "Highest bracket ({{ lower | fixed(0) }}+): {{ taxable | fixed(2) }} × {{ rate | percent(1) }}% = {{ bracket_tax | fixed(2) }}"),
+    ("calc_tax.cliff_bracket", "Cliff bracket {{ index }} (above {{ lower | fixed(0) }}): whole {{ taxable | fixed(2) }} × {{ rate | percent(1) }}% = {{ tax | fixed(2) }}, replacing lower brackets"),
+    ("calc_tax.bracket_lookup", "Income {{ income | fixed(2) }} reaches {% if cliff %}cliff {% endif %}bracket {{ index }} (above {{ lower | fixed(0) }}, {{ rate | percent(1) }}%): tax from the bracket table = {{ tax | fixed(2) }}"),
+    ("calc_tax.subtotal", "Subtotal tax: {{ tax | fixed(2) }}"),
+    ("calc_tax.surcharge", "{% if applied %}{{ name }} applied (tax {{ subtotal | fixed(2) }} > {{ threshold | fixed(2) }}): {{ subtotal | fixed(2) }} × {{ rate | percent(1) }}% = {{ surcharge | fixed(2) }}. Final tax with {{ name | lower }}: {{ tax | fixed(2) }}{% else %}No {{ name | lower }} (tax {{ tax | fixed(2) }} ≤ {{ threshold | fixed(2) }}){% endif %}"),
+    ("calc_tax.income_surcharge", "{% if applied %}{{ name }} on income applied (income {{ income | fixed(2) }} > {{ threshold | fixed(2) }}): {{ excess | fixed(2) }} × {{ rate | percent(1) }}% = {{ surcharge | fixed(2) }}. Final tax with {{ name | lower }}: {{ tax | fixed(2) }}{% else %}No {{ name | lower }} on income (income {{ income | fixed(2) }} ≤ {{ threshold | fixed(2) }}){% endif %}"),
+    ("calc_tax.minimum_tax", "Alternative minimum tax: ({{ adjusted_income | fixed(2) }} adjusted income - {{ exemption | fixed(2) }} exemption) × {{ rate | percent(1) }}% = {{ amt_tax | fixed(2) }}. {% if applied %}Minimum tax exceeds regular tax {{ regular_tax | fixed(2) }}; tax owed: {{ tax | fixed(2) }}{% else %}Regular tax {{ regular_tax | fixed(2) }} ≥ minimum tax; tax owed: {{ tax | fixed(2) }}{% endif %}"),
+    // check_voting
+    ("check_voting.invalid_inputs", "Voting check failed due to invalid inputs"),
+    ("check_voting.turnout", "Turnout: {{ turnout }} out of {{ eligible_voters }} eligible voters ({{ turnout_ratio | percent(1) }}%)"),
+    ("check_voting.turnout_requirement", "{% if passes %}Turnout requirement: ≥60% - PASSED{% else %}Turnout requirement: ≥60% - FAILED. Proposal fails due to insufficient turnout{% endif %}"),
+    ("check_voting.yes_votes", "Yes votes: {{ yes_votes }} out of {{ turnout }} ({{ yes_ratio | percent(1) }}%)"),
+    ("check_voting.general_threshold", "General proposal requirement: >{{ required | num }}%. Vote threshold: {{ yes_ratio | percent(1) }}% > {{ required | num }}% - {% if passes %}PASSED{% else %}FAILED{% endif %}"),
+    ("check_voting.amendment_threshold", "Amendment requirement: ≥{{ required | fixed(1) }}%. Vote threshold: {{ yes_ratio | percent(1) }}% ≥ {{ required | fixed(1) }}% - {% if passes %}PASSED{% else %}FAILED{% endif %}"),
+    ("check_voting.result", "Final result: Proposal {% if passes %}PASSES{% else %}FAILS{% endif %}"),
+    // validate_vote_record
+    ("validate_vote_record.non_negative", "Non-negative count check performed"),
+    ("validate_vote_record.turnout_check", "Turnout check: {{ turnout }} {% if within_eligible %}≤{% else %}>{% endif %} {{ eligible_voters }} eligible voters"),
+    ("validate_vote_record.tally", "Tally: {{ yes_votes }} yes + {{ no_votes }} no + {{ abstain_votes }} abstain + {{ invalid_votes }} invalid = {{ tallied_ballots }}"),
+    ("validate_vote_record.tally_check", "Tally check: {{ tallied_ballots }} {% if matches %}={% else %}≠{% endif %} turnout {{ turnout }} - {% if matches %}PASSED{% else %}FAILED{% endif %}"),
+    ("validate_vote_record.result", "Final result: Vote record {% if valid %}VALID{% else %}INVALID{% endif %} ({{ inconsistencies }} inconsistencies)"),
+    // distribute_waterfall
+    ("distribute_waterfall.invalid_inputs", "Waterfall distribution failed due to invalid inputs"),
+    ("distribute_waterfall.starting_cash", "Starting cash: {{ cash_available | fixed(2) }}"),
+    ("distribute_waterfall.senior", "{% if not has_debt %}No senior debt to pay{% elif fully_paid %}Senior debt: {{ debt | fixed(2) }} fully paid{% else %}Senior debt: {{ payment | fixed(2) }} partially paid ({{ payment | fixed(2) }} of {{ debt | fixed(2) }}){% endif %}"),
+    ("distribute_waterfall.remaining_after_senior", "Remaining after senior: {{ remaining | fixed(2) }}"),
+    ("distribute_waterfall.junior", "{% if not has_debt %}No junior debt to pay{% elif fully_paid %}Junior debt: {{ debt | fixed(2) }} fully paid{% elif payment > 0 %}Junior debt: {{ payment | fixed(2) }} partially paid ({{ payment | fixed(2) }} of {{ debt | fixed(2) }}){% else %}Junior debt: no funds available{% endif %}"),
+    ("distribute_waterfall.remaining_for_equity", "Remaining for equity: {{ remaining | fixed(2) }}"),
+    ("distribute_waterfall.equity", "{% if payment > 0 %}Equity distribution: {{ payment | fixed(2) }}{% else %}No funds available for equity{% endif %}"),
+    ("distribute_waterfall.rounding", "Rounding: allocations rounded to cents with {{ residual }} residual cent(s) reassigned using the largest-remainder method, without paying a debt more than it is owed"),
+    ("distribute_waterfall.conservation_failed", "Waterfall distribution failed the conservation check"),
+    // check_covenants
+    ("check_covenants.invalid_inputs", "Covenant check failed due to invalid inputs"),
+    ("check_covenants.leverage", "Leverage: {{ total_debt | fixed(2) }} / {{ ebitda | fixed(2) }} = {{ ratio | fixed(2) }}x {% if passes %}≤{% else %}>{% endif %} {{ max_leverage | fixed(2) }}x - {% if passes %}PASSED{% else %}FAILED{% endif %}"),
+    ("check_covenants.leverage_undefined", "Leverage: undefined with non-positive EBITDA {{ ebitda | fixed(2) }} - {% if passes %}PASSED (no debt){% else %}FAILED{% endif %}"),
+    ("check_covenants.interest_coverage", "Interest coverage: {{ ebitda | fixed(2) }} / {{ interest_expense | fixed(2) }} = {{ ratio | fixed(2) }}x {% if passes %}≥{% else %}<{% endif %} {{ min_interest_coverage | fixed(2) }}x - {% if passes %}PASSED{% else %}FAILED{% endif %}"),
+    ("check_covenants.interest_coverage_undefined", "Interest coverage: no interest expense - PASSED"),
+    ("check_covenants.result", "Final result: {% if all_pass %}ALL COVENANTS PASSED{% else %}COVENANT BREACH{% endif %}"),
+    // calc_npv
+    ("calc_npv.invalid_inputs", "NPV calculation failed due to invalid inputs"),
+    ("calc_npv.discounting", "Discounting {{ periods }} cash flows at {{ discount_rate | percent(2) }}% per period"),
+    ("calc_npv.period", "Period {{ period }}: {{ cash_flow | fixed(2) }} / (1 + {{ discount_rate | fixed(4) }})^{{ period }} = {{ present_value | fixed(2) }}"),
+    ("calc_npv.npv", "NPV: {{ npv | fixed(2) }}"),
+    // calc_irr
+    ("calc_irr.invalid_inputs", "IRR calculation failed due to invalid inputs"),
+    ("calc_irr.search_bracket", "Search bracket: [{{ low | percent(2) }}%, {{ high | percent(2) }}%] with NPV [{{ npv_low | fixed(2) }}, {{ npv_high | fixed(2) }}]"),
+    ("calc_irr.bisection", "Bisection: {{ iterations }} iterations, tolerance {{ tolerance }}, {% if converged %}converged{% else %}did not converge{% endif %}"),
+    ("calc_irr.result", "NPV at IRR: {{ npv_at_irr | fixed(6) }}. IRR: {{ irr | percent(4) }}%"),
+    // check_escrow_release
+    ("check_escrow_release.invalid_inputs", "Escrow release evaluation failed due to invalid inputs"),
+    ("check_escrow_release.escrow_amount", "Escrow amount: {{ escrow_amount | fixed(2) }}"),
+    ("check_escrow_release.no_dispute", "No-dispute condition: {% if dispute_pending %}FAILED{% else %}PASSED{% endif %}"),
+    ("check_escrow_release.release_date", "Release date condition ({{ as_of_date }} ≥ {{ release_date }}): {% if reached %}PASSED{% else %}FAILED{% endif %}"),
+    ("check_escrow_release.milestone", "Milestone '{{ name }}' ({{ percentage | percent(1) }}% = {{ amount | fixed(2) }}): {% if not completed %}NOT COMPLETED{% elif blocked %}COMPLETED, BLOCKED{% else %}RELEASED{% endif %}"),
+    ("check_escrow_release.cumulative_release", "Cumulative release: {{ released_percentage | percent(1) }}% of {{ escrow_amount | fixed(2) }} = {{ cumulative_release | fixed(2) }}"),
+    ("check_escrow_release.already_released", "Less already released {{ already_released | fixed(2) }}: release now {{ release_amount | fixed(2) }}"),
+    ("check_escrow_release.remaining", "Remaining in escrow: {{ remaining | fixed(2) }}"),
+    // check_housing_grant
+    ("check_housing_grant.invalid_inputs", "Housing grant eligibility check failed due to invalid inputs"),
+    ("check_housing_grant.income_conversion", "Income converted: {{ income | money(currency) }} × {{ rate | fixed(4) }} = {{ converted_income | money(base_currency) }}{% if rate_date %} (rate of {{ rate_date }}){% endif %}"),
+    ("check_housing_grant.inputs", "Area Median Income (AMI): {{ ami | fixed(2) }}. Household size: {{ household_size }}. Household income: {{ income | fixed(2) }}. Has other subsidy: {% if has_other_subsidy %}Yes{% else %}No{% endif %}"),
+    ("check_housing_grant.subsidy_check", "{% if has_other_subsidy %}Subsidy check: FAILED (already has another subsidy). Result: NOT ELIGIBLE{% else %}Subsidy check: PASSED (no other subsidies){% endif %}"),
+    ("check_housing_grant.base_threshold", "Base income threshold: 60% of AMI = {{ threshold | fixed(2) }}"),
+    ("check_housing_grant.household_adjustment", "{% if adjusted %}Household size adjustment: {{ household_size }} > 4, threshold increased by 10% to {{ threshold | fixed(2) }}{% else %}No household size adjustment needed ({{ household_size }} ≤ 4){% endif %}"),
+    ("check_housing_grant.equivalence_scale", "Equivalence scale {{ scale }}: {{ household_size }} people ({{ children }} children) = {{ adult_equivalents | fixed(2) }} adult equivalents, against {{ reference_equivalents | fixed(2) }} for the reference household of 2 adults and 2 children. Limit per adult equivalent: {{ limit_per_equivalent | fixed(2) }}, equivalised income {{ equivalized_income | fixed(2) }}. Household limit: {{ limit_per_equivalent | fixed(2) }} × {{ adult_equivalents | fixed(2) }} = {{ threshold | fixed(2) }}"),
+    ("check_housing_grant.income_eligibility", "Income eligibility: {{ income | fixed(2) }} {% if eligible %}≤{% else %}>{% endif %} {{ threshold | fixed(2) }} - {% if eligible %}PASSED{% else %}FAILED{% endif %}"),
+    ("check_housing_grant.result", "Final result: {% if eligible %}ELIGIBLE{% else %}NOT ELIGIBLE{% endif %}"),
+    // calc_capital_gains
+    ("calc_capital_gains.invalid_inputs", "Capital gains calculation failed due to invalid inputs"),
+    ("calc_capital_gains.gain", "Gain: sale price {{ sale_price | fixed(2) }} - acquisition cost {{ acquisition_cost | fixed(2) }} = {{ gain | fixed(2) }}"),
+    ("calc_capital_gains.holding_period", "Holding period: {{ holding_period_days }} days {% if long_term %}≥{% else %}<{% endif %} {{ long_term_days }} - {% if long_term %}long-term{% else %}short-term{% endif %} rate {{ rate | percent(1) }}%"),
+    ("calc_capital_gains.no_gain", "No gain to tax ({{ gain | fixed(2) }}): tax is 0.00"),
+    ("calc_capital_gains.exemption", "Annual exemption: min({{ annual_exemption | fixed(2) }}, gain) = {{ exemption_applied | fixed(2) }}. Taxable gain: {{ taxable_gain | fixed(2) }}"),
+    ("calc_capital_gains.tax", "Tax: {{ taxable_gain | fixed(2) }} × {{ rate | percent(1) }}% = {{ tax | fixed(2) }}"),
+    // calc_inheritance_tax
+    ("calc_inheritance_tax.invalid_inputs", "Inheritance tax calculation failed due to invalid inputs"),
+    ("calc_inheritance_tax.beneficiary", "{{ name }} ({{ relationship }}): amount {{ amount | fixed(2) }}, exemption {{ exemption | fixed(2) }}{% if prior_gifts > 0 %} - prior gifts {{ prior_gifts | fixed(2) }}{% endif %}, applied {{ exemption_applied | fixed(2) }}, taxable {{ taxable_amount | fixed(2) }}, tax {{ tax | fixed(2) }} ({{ effective_rate | percent(1) }}% effective)"),
+    ("calc_inheritance_tax.total", "Total tax for {{ count }} beneficiaries: {{ total_tax | fixed(2) }}"),
+    // calc_social_contributions
+    ("calc_social_contributions.invalid_inputs", "Social contribution calculation failed due to invalid inputs"),
+    ("calc_social_contributions.gross_salary", "Gross salary: {{ gross_salary | fixed(2) }}"),
+    ("calc_social_contributions.contribution", "{{ name }}: base {{ base | fixed(2) }}{% if capped %} (capped at ceiling {{ ceiling | fixed(2) }}){% endif %}, employee {{ base | fixed(2) }} × {{ employee_rate | percent(2) }}% = {{ employee_amount | fixed(2) }}, employer {{ base | fixed(2) }} × {{ employer_rate | percent(2) }}% = {{ employer_amount | fixed(2) }}"),
+    ("calc_social_contributions.totals", "Total employee contributions: {{ total_employee | fixed(2) }}. Total employer contributions: {{ total_employer | fixed(2) }}. Net salary: {{ net_salary | fixed(2) }}"),
+    // calc_property_tax
+    ("calc_property_tax.invalid_inputs", "Property tax calculation failed due to invalid inputs"),
+    ("calc_property_tax.assessed_value", "Assessed value: {{ assessed_value | fixed(2) }}"),
+    ("calc_property_tax.assessment_cap", "Assessment cap: prior year {{ prior | fixed(2) }} × (1 + {{ cap | percent(1) }}%) = {{ limit | fixed(2) }}{% if increase_capped %}; taxable value capped at {{ limit | fixed(2) }}{% else %}; assessed value within cap{% endif %}"),
+    ("calc_property_tax.homestead", "{% if homestead %}Homestead exemption: {{ exemption_applied | fixed(2) }}{% else %}No homestead exemption{% endif %}"),
+    ("calc_property_tax.tax", "Tax: {{ taxable_value | fixed(2) }} × {{ millage | num }} mills / 1000 = {{ tax | fixed(2) }}"),
+    // calc_transfer_tax
+    ("calc_transfer_tax.invalid_inputs", "Transfer tax calculation failed due to invalid inputs"),
+    ("calc_transfer_tax.slice", "Slice: {{ price | fixed(2) }} is {% if lower > 0 %}above {{ lower | fixed(2) }}{% endif %}{% if lower > 0 and upper is not none %} and {% endif %}{% if upper is not none %}up to {{ upper | fixed(2) }}{% endif %}; whole price × {{ rate | percent(1) }}% = {{ tax | fixed(2) }}"),
+    ("calc_transfer_tax.marginal", "Marginal: {{ price | fixed(2) }} across {{ brackets }} bracket(s), top rate {{ rate | percent(1) }}% = {{ tax | fixed(2) }}"),
+    ("calc_transfer_tax.effective_rate", "Effective rate: {{ effective_rate | percent(2) }}%"),
+    // calc_fee
+    ("calc_fee.invalid_inputs", "Fee calculation failed due to invalid inputs"),
+    ("calc_fee.value", "Fee {{ fee_code }} on value {{ value | fixed(2) }}"),
+    ("calc_fee.component", "{{ name }}: {{ fixed | fixed(2) }}{% if rate > 0 %} + {{ rate | percent(2) }}% × {{ excess | fixed(2) }} above {{ above | fixed(2) }}{% endif %}{% if capped %}, capped at {{ cap | fixed(2) }}{% endif %} = {{ amount | fixed(2) }}"),
+    ("calc_fee.total", "Total fee: {{ fee | fixed(2) }}"),
+    // index_amount
+    ("index_amount.invalid_inputs", "Indexation failed due to invalid inputs"),
+    ("index_amount.index_values", "Price index {{ from_year }}: {{ from_index | fixed(2) }}, {{ to_year }}: {{ to_index | fixed(2) }}; factor {{ to_index | fixed(2) }} / {{ from_index | fixed(2) }} = {{ factor | fixed(4) }}"),
+    ("index_amount.result", "Indexed amount: {{ amount | fixed(2) }} × {{ factor | fixed(4) }} = {{ indexed_amount | fixed(2) }}"),
+    // convert_currency
+    ("convert_currency.invalid_inputs", "Currency conversion failed due to invalid inputs"),
+    ("convert_currency.rate", "Rate: 1 {{ from_currency }} = {{ rate | fixed(6) }} {{ to_currency }}{% if rate_date %} (quoted {{ rate_date }}, via {{ base_currency }}){% endif %}"),
+    ("convert_currency.result", "Converted: {{ amount | money(from_currency) }} × {{ rate | fixed(6) }} = {{ converted_amount | money(to_currency) }}"),
+    // get_intake_schema
+    ("get_intake_schema.invalid_inputs", "Intake form lookup failed due to invalid inputs"),
+    ("get_intake_schema.form", "Intake form for {{ program }}: {{ fields }} fields ({{ required }} required), submitted to {{ tool }}"),
+    // prescreen
+    ("prescreen.invalid_inputs", "Pre-screening failed due to invalid inputs"),
+    ("prescreen.rule", "{{ rule }}: {% if status == 'passed' %}passed{% elif status == 'failed' %}failed ({{ detail }}){% else %}undetermined, missing {{ missing | join(', ') }}{% endif %}"),
+    ("prescreen.verdict", "Verdict: {% if verdict == 'eligible' %}definitely eligible{% elif verdict == 'ineligible' %}definitely ineligible{% else %}undetermined, missing: {{ missing | join(', ') }}{% endif %}; confirm with {{ tool }}"),
+    // get_usage_report
+    ("get_usage_report.invalid_inputs", "Usage report failed due to invalid inputs"),
+    ("get_usage_report.summary", "{{ calls }} calls to {{ tools }} tools since {{ since }}, {{ failed }} failed"),
+    ("get_usage_report.top_error", "Most frequent error: {{ message }} ({{ count }}× in {{ tool }})"),
+    // list_tools_by_category
+    ("list_tools_by_category.invalid_inputs", "Tool listing failed due to invalid inputs"),
+    ("list_tools_by_category.summary", "{{ tools }} tools in {{ categories }} categories"),
+    // describe_capabilities
+    ("describe_capabilities.negotiated", "Client {{ client }} negotiated protocol {{ protocol }}"),
+    ("describe_capabilities.not_negotiated", "No initialize in this session"),
+    ("describe_capabilities.shaping", "structuredContent {{ 'on' if structured_content else 'off' }}, resource links off, sampled summary {{ 'on' if sampling_summary else 'off' }}"),
+    // get_recent_schema_failures
+    ("get_recent_schema_failures.invalid_inputs", "Schema failure report failed due to invalid inputs"),
+    ("get_recent_schema_failures.summary", "{{ listed }} of {{ total }} recorded schema failures{% if tool %} for {{ tool }}{% endif %}, newest first"),
+    // upsert_profile
+    ("upsert_profile.invalid_inputs", "Profile not applied due to invalid inputs"),
+    ("upsert_profile.summary", "Profile of {{ settings }} settings saved and in force; configuration version {{ previous }} → {{ version }}{% if previous == version %} (unchanged){% endif %}"),
+    // diff_profiles
+    ("diff_profiles.invalid_inputs", "Profiles not compared due to invalid inputs"),
+    ("diff_profiles.summary", "{{ changed }} settings differ ({{ added }} added, {{ removed }} removed, {{ modified }} changed), {{ unchanged }} unchanged"),
+    ("diff_profiles.setting_added", "{{ setting }} added: {{ after }}"),
+    ("diff_profiles.setting_removed", "{{ setting }} removed (was {{ before }})"),
+    ("diff_profiles.setting_changed", "{% if brackets %}{{ setting }}: {{ brackets }} brackets differ{% else %}{{ setting }}: {{ before }} → {{ after }}{% endif %}"),
+    ("diff_profiles.bracket_added", "{{ setting }} bracket {{ bracket }} added: {% if after_up_to is none %}top bracket{% else %}up to {{ after_up_to | num }}{% endif %} at {{ after_rate | percent(2) }}%{% if after_cliff %} (cliff){% endif %}"),
+    ("diff_profiles.bracket_removed", "{{ setting }} bracket {{ bracket }} removed: {% if before_up_to is none %}top bracket{% else %}up to {{ before_up_to | num }}{% endif %} at {{ before_rate | percent(2) }}%"),
+    ("diff_profiles.bracket_changed", "{{ setting }} bracket {{ bracket }}:{% if before_up_to != after_up_to %} up to {% if before_up_to is none %}no limit{% else %}{{ before_up_to | num }}{% endif %} → {% if after_up_to is none %}no limit{% else %}{{ after_up_to | num }}{% endif %}{% endif %}{% if before_rate != after_rate %}{% if before_up_to != after_up_to %},{% endif %} rate {{ before_rate | percent(2) }}% → {{ after_rate | percent(2) }}%{% endif %}{% if before_cliff != after_cliff %}{% if before_up_to != after_up_to or before_rate != after_rate %},{% endif %} cliff {{ 'on' if after_cliff else 'off' }}{% endif %}"),
+    // evaluate_ranges
+    ("evaluate_ranges.invalid_inputs", "Range evaluation failed due to invalid inputs"),
+    ("evaluate_ranges.summary", "Evaluated {{ tool }} at {{ evaluations }} combinations of the range ends ({% for range in ranges %}{{ range.parameter }} {{ range.min | num }} to {{ range.max | num }}{% if not loop.last %}, {% endif %}{% endfor %}). {% if flips %}Flips within the ranges: {{ flips | join(', ') }}{% else %}No yes/no result flips within the ranges{% endif %}"),
+    // simulate_uncertainty
+    ("simulate_uncertainty.invalid_inputs", "Uncertainty simulation failed due to invalid inputs"),
+    ("simulate_uncertainty.summary", "Simulated {{ tool }} over {{ draws }} draws (seed {{ seed }}) of {% for input in inputs %}{{ input.parameter }} ~ {% if input.distribution == 'normal' %}normal(mean {{ input.mean | num }}, sd {{ input.sd | num }}){% else %}uniform({{ input.min | num }}, {{ input.max | num }}){% endif %}{% if not loop.last %}, {% endif %}{% endfor %}{% for outcome in probabilities %}. P({{ outcome.field }}) = {{ outcome.probability | percent(1) }}%{% endfor %}"),
+    // check_appeal_deadline
+    ("check_appeal_deadline.invalid_inputs", "Appeal deadline check failed due to invalid inputs"),
+    ("check_appeal_deadline.inputs", "Decision issued: {{ decision_date }}. Appeal filed: {{ filing_date }}"),
+    ("check_appeal_deadline.deadline", "{% if business_days %}Deadline: {{ window_days }} business days after the decision, skipping weekends and public holidays = {{ deadline }}{% else %}Deadline: decision date + {{ window_days }} days = {{ nominal_deadline }}{% if deadline_extended %}; {{ nominal_deadline }} is not a business day, so the deadline moves to {{ deadline }}{% endif %}{% endif %}"),
+    ("check_appeal_deadline.result", "{% if within_window %}Filed within the window: {{ days_remaining }} days remaining until {{ deadline }}{% else %}Filed {{ days_overdue }} days after the deadline - OVERDUE{% endif %}"),
+    // get_required_documents
+    ("get_required_documents.invalid_inputs", "Document checklist failed due to invalid inputs"),
+    ("get_required_documents.summary", "{{ count }} documents required for {{ program }}: {{ general }} for every applicant{% if situations %}, {{ count - general }} for the situation ({{ situations | join(', ') }}){% endif %}"),
+    // check_voting_bulk
+    ("check_voting_bulk.invalid_inputs", "Bulk voting check failed due to invalid inputs"),
+    ("check_voting_bulk.totals", "Totals over {{ count }} precincts: {{ eligible_voters }} eligible voters, turnout {{ turnout }}, yes votes {{ yes_votes }}"),
+    ("check_voting_bulk.quorum", "Precincts meeting the {{ required_turnout | percent(0) }}% turnout quorum on their own: {{ met }} of {{ count }}{% if below %} (below: {{ below | join(', ') }}){% endif %}"),
+    // project_quorum
+    ("project_quorum.invalid_inputs", "Quorum projection failed due to invalid inputs"),
+    ("project_quorum.quorum", "Quorum: {{ required_turnout | percent(0) }}% of {{ eligible_voters }} eligible voters = {{ quorum_votes }} votes; turnout so far {{ turnout }}{% if additional_votes_needed > 0 %}, {{ additional_votes_needed }} more needed of {{ outstanding_voters }} outstanding{% else %} - quorum reached{% endif %}"),
+    ("project_quorum.projection", "Projection: {{ turnout }} votes in {{ hours_elapsed | num }} h = {{ rate | fixed(1) }} per hour; with {{ hours_remaining | num }} h remaining, turnout reaches {{ projected_turnout }} - quorum {% if met %}reached{% else %}not reached{% endif %}"),
+    ("project_quorum.threshold", "{% if proposal_type == 'amendment' %}Amendment (≥ 2/3){% else %}General proposal (> 50%){% endif %} at turnout {{ turnout_basis }}: {{ min_yes_votes }} yes votes, {{ additional_yes_votes }} more needed"),
+    // stress_config
+    ("stress_config.invalid_inputs", "Stress test failed due to invalid inputs"),
+    ("stress_config.summary", "{{ passed_checks }} of {{ count }} stress checks passed over {{ cases }} cases for the {{ source }} ({{ brackets }} tax brackets){% if failed %}; failed: {{ failed | join(', ') }}{% endif %}"),
+    // verify_store_integrity
+    ("verify_store_integrity.invalid_inputs", "Store integrity check failed: the store is not available"),
+    ("verify_store_integrity.summary", "Database check: {{ database_check }}; {{ records }} results at schema version {{ schema_version }}: {{ encrypted }} encrypted{% if current_key %} (current key {{ current_key }}){% endif %}, {{ plaintext }} unencrypted, {{ failed }} failing to decrypt or authenticate{% if rewrapped %}; {{ rewrapped }} moved to the current key{% endif %}"),
+    // purge_expired_data
+    ("purge_expired_data.summary", "{% for entry in purged %}{{ entry.category }} older than {{ entry.retention_days }} days (before {{ entry.cutoff }}): {{ entry.deleted }} deleted{% if entry.deleted_stored is not none %}, {{ entry.deleted_stored }} from the embedded store{% endif %}{% if not loop.last %}; {% endif %}{% else %}No retention periods configured; nothing purged{% endfor %}{% if kept %}. Kept without a period: {{ kept | join(', ') }}{% endif %}"),
+    // export_subject_data
+    ("export_subject_data.summary", "{{ records }} stored results of the case{% if tools %} ({{ tools | join(', ') }}){% endif %} found in {{ stores | join(' and ') }}{% if unreadable %}; {{ unreadable }} could not be read{% endif %}"),
+    // erase_subject_data
+    ("erase_subject_data.summary", "Erasure {{ receipt_id }}: {{ erased }} stored results erased ({{ erased_in_memory }} from memory{% if erased_stored is not none %}, {{ erased_stored }} from the embedded store, {{ shredded }} with their data keys destroyed{% endif %})"),
+];
+
+/// Plain-language templates for the citizen audience: short sentences and no symbols
+const CITIZEN_TEMPLATES: &[(&str, &str)] = &[
+    // calc_penalty
+    ("calc_penalty.invalid_inputs", "We could not calculate the penalty because some of the information is not valid"),
+    ("calc_penalty.base_penalty", "The payment is {{ days_late | num }} days late. At {{ rate_per_day | num }} per day, the penalty starts at {{ base_penalty | fixed(2) }}"),
+    ("calc_penalty.cap", "{% if capped %}This is more than the maximum of {{ cap | fixed(2) }}, so the penalty is lowered to {{ cap | fixed(2) }}{% else %}This is within the maximum of {{ cap | fixed(2) }}{% endif %}"),
+    ("calc_penalty.interest", "Interest of {{ interest_rate | percent(1) }} percent adds {{ interest | fixed(2) }}"),
+    ("calc_penalty.accrued_interest", "Interest of {{ interest_rate | percent(1) }} percent a year for the {{ interest_days }} days from {{ due_date }} to {{ valuation_date }} adds {{ interest | fixed(2) }}"),
+    ("calc_penalty.final", "The total penalty is {{ final_penalty | fixed(2) }}"),
+    // calc_tax
+    ("calc_tax.invalid_inputs", "We could not calculate the tax because some of the information is not valid"),
+    ("calc_tax.starting_income", "Your income is {{ income | fixed(2) }}"),
+    ("calc_tax.loss_carryforward", "{% if current_loss > 0 %}This year you had a loss of {{ current_loss | fixed(2) }}, which can be used to reduce tax in future years{% else %}Losses of {{ available | fixed(2) }} from earlier years reduce your income by {{ applied | fixed(2) }}{% endif %}. {{ remaining | fixed(2) }} in losses is left for future years. The income that is taxed is {{ taxable_income | fixed(2) }}"),
+    ("calc_tax.no_taxable_income", "You have no income to tax this year, so you pay no tax"),
+    ("calc_tax.schedule_period", "{% if before %}The tax rules changed on {{ effective_date }}. For the {{ days }} days before that, the earlier rules apply to your income for the whole year{% else %}For the {{ days }} days from {{ effective_date }}, the new rules apply to your income for the whole year{% endif %}"),
+    ("calc_tax.prorated", "Each set of rules counts for its share of the year, {{ before_days }} and {{ after_days }} of {{ total_days }} days. Together your tax is {{ tax | fixed(2) }}"),
+    ("calc_tax.bracket", "Income from {{ lower | fixed(0) }} to {{ upper | fixed(0) }} is taxed at {{ rate | percent(1) }} percent. You pay {{ bracket_tax | fixed(2) }} on {{ taxable | fixed(2) }}"),
+    ("calc_tax.highest_bracket", "Income above {{ lower | fixed(0) }} is taxed at {{ rate | percent(1) }} percent. You pay {{ bracket_tax | fixed(2) }} on {{ taxable | fixed(2) }}"),
+    ("calc_tax.cliff_bracket", "Because your income is above {{ lower | fixed(0) }}, all {{ taxable | fixed(2) }} of it is taxed at {{ rate | percent(1) }} percent. That comes to {{ tax | fixed(2) }}"),
+    ("calc_tax.bracket_lookup", "Your income of {{ income | fixed(2) }} reaches the {{ rate | percent(1) }} percent bracket, which starts above {{ lower | fixed(0) }}. Across all the brackets, the tax on it is {{ tax | fixed(2) }}"),
+    ("calc_tax.subtotal", "Before any surcharge, your tax is {{ tax | fixed(2) }}"),
+    ("calc_tax.surcharge", "{% if applied %}This is more than {{ threshold | fixed(2) }}, so a {{ name | lower }} of {{ rate | percent(1) }} percent adds {{ surcharge | fixed(2) }}. Your total tax is {{ tax | fixed(2) }}{% else %}No {{ name | lower }} applies because your tax is not more than {{ threshold | fixed(2) }}. Your total tax is {{ tax | fixed(2) }}{% endif %}"),
+    ("calc_tax.income_surcharge", "{% if applied %}Your income is more than {{ threshold | fixed(2) }}, so a {{ name | lower }} of {{ rate | percent(1) }} percent of the extra {{ excess | fixed(2) }} in income adds {{ surcharge | fixed(2) }}. Your total tax is {{ tax | fixed(2) }}{% else %}No {{ name | lower }} applies because your income is not more than {{ threshold | fixed(2) }}. Your total tax is {{ tax | fixed(2) }}{% endif %}"),
+    ("calc_tax.minimum_tax", "There is also a minimum tax of {{ rate | percent(1) }} percent on income above {{ exemption | fixed(2) }}{% if adjustments > 0 %}, counting {{ adjustments | fixed(2) }} of extra items{% endif %}. That comes to {{ amt_tax | fixed(2) }}. {% if applied %}Because this is more than your regular tax of {{ regular_tax | fixed(2) }}, you pay the minimum tax of {{ tax | fixed(2) }}{% else %}Your regular tax of {{ regular_tax | fixed(2) }} is not lower than this, so you pay {{ tax | fixed(2) }}{% endif %}"),
+    // check_voting
+    ("check_voting.invalid_inputs", "We could not check the vote because some of the information is not valid"),
+    ("check_voting.turnout", "{{ turnout }} of {{ eligible_voters }} eligible voters took part. That is {{ turnout_ratio | percent(1) }} percent"),
+    ("check_voting.turnout_requirement", "{% if passes %}This meets the rule that at least 60 percent must vote{% else %}At least 60 percent must vote, so the proposal does not pass{% endif %}"),
+    ("check_voting.yes_votes", "{{ yes_votes }} of the {{ turnout }} voters voted yes. That is {{ yes_ratio | percent(1) }} percent"),
+    ("check_voting.general_threshold", "A general proposal needs more than {{ required | num }} percent yes votes. {% if passes %}This proposal has enough{% else %}This proposal does not have enough{% endif %}"),
+    ("check_voting.amendment_threshold", "An amendment needs at least {{ required | fixed(1) }} percent yes votes. {% if passes %}This amendment has enough{% else %}This amendment does not have enough{% endif %}"),
+    ("check_voting.result", "{% if passes %}The proposal passes{% else %}The proposal does not pass{% endif %}"),
+    // validate_vote_record
+    ("validate_vote_record.non_negative", "We checked that no count is below zero"),
+    ("validate_vote_record.turnout_check", "{% if within_eligible %}The {{ turnout }} voters are not more than the {{ eligible_voters }} eligible voters{% else %}The {{ turnout }} voters are more than the {{ eligible_voters }} eligible voters{% endif %}"),
+    ("validate_vote_record.tally", "The ballots add up to {{ tallied_ballots }}: {{ yes_votes }} yes, {{ no_votes }} no, {{ abstain_votes }} abstain and {{ invalid_votes }} invalid"),
+    ("validate_vote_record.tally_check", "{% if matches %}This matches the number of voters{% else %}This does not match the {{ turnout }} voters{% endif %}"),
+    ("validate_vote_record.result", "{% if valid %}The vote record is consistent{% else %}The vote record has {{ inconsistencies }} problem{% if inconsistencies != 1 %}s{% endif %}{% endif %}"),
+    // distribute_waterfall
+    ("distribute_waterfall.invalid_inputs", "We could not split the cash because some of the information is not valid"),
+    ("distribute_waterfall.starting_cash", "There is {{ cash_available | fixed(2) }} in cash to pay out"),
+    ("distribute_waterfall.senior", "{% if not has_debt %}There is no senior debt to pay{% elif fully_paid %}The senior debt of {{ debt | fixed(2) }} is paid first, in full{% else %}The senior debt is paid first. Only {{ payment | fixed(2) }} of the {{ debt | fixed(2) }} owed can be paid{% endif %}"),
+    ("distribute_waterfall.remaining_after_senior", "After that, {{ remaining | fixed(2) }} is left"),
+    ("distribute_waterfall.junior", "{% if not has_debt %}There is no junior debt to pay{% elif fully_paid %}The junior debt of {{ debt | fixed(2) }} is paid next, in full{% elif payment > 0 %}The junior debt is paid next. Only {{ payment | fixed(2) }} of the {{ debt | fixed(2) }} owed can be paid{% else %}There is no money left for the junior debt{% endif %}"),
+    ("distribute_waterfall.remaining_for_equity", "After that, {{ remaining | fixed(2) }} is left for the owners"),
+    ("distribute_waterfall.equity", "{% if payment > 0 %}The owners receive {{ payment | fixed(2) }}{% else %}Nothing is left for the owners{% endif %}"),
+    ("distribute_waterfall.rounding", "Amounts were rounded to the cent so they add up exactly to the cash available"),
+    ("distribute_waterfall.conservation_failed", "We could not split the cash because the amounts did not add up"),
+    // check_covenants
+    ("check_covenants.invalid_inputs", "We could not check the loan conditions because some of the information is not valid"),
+    ("check_covenants.leverage", "Debt is {{ ratio | fixed(2) }} times yearly earnings. The limit is {{ max_leverage | fixed(2) }} times. {% if passes %}This condition is met{% else %}This condition is not met{% endif %}"),
+    ("check_covenants.leverage_undefined", "Earnings are zero or negative, so the debt limit cannot be measured. {% if passes %}There is no debt, so this condition is met{% else %}This condition is not met{% endif %}"),
+    ("check_covenants.interest_coverage", "Earnings are {{ ratio | fixed(2) }} times the interest cost. The minimum is {{ min_interest_coverage | fixed(2) }} times. {% if passes %}This condition is met{% else %}This condition is not met{% endif %}"),
+    ("check_covenants.interest_coverage_undefined", "There is no interest to pay, so this condition is met"),
+    ("check_covenants.result", "{% if all_pass %}All loan conditions are met{% else %}At least one loan condition is not met{% endif %}"),
+    // calc_npv
+    ("calc_npv.invalid_inputs", "We could not calculate the present value because some of the information is not valid"),
+    ("calc_npv.discounting", "We converted {{ periods }} payments into today's money using a rate of {{ discount_rate | percent(2) }} percent per period"),
+    ("calc_npv.period", "The payment of {{ cash_flow | fixed(2) }} in period {{ period }} is worth {{ present_value | fixed(2) }} today"),
+    ("calc_npv.npv", "Together, the payments are worth {{ npv | fixed(2) }} today"),
+    // calc_irr
+    ("calc_irr.invalid_inputs", "We could not calculate the rate of return because some of the information is not valid"),
+    ("calc_irr.search_bracket", "We looked for a rate of return between {{ low | percent(0) }} and {{ high | percent(0) }} percent"),
+    ("calc_irr.bisection", "{% if converged %}We found the rate after {{ iterations }} steps{% else %}We stopped after {{ iterations }} steps without finding an exact rate{% endif %}"),
+    ("calc_irr.result", "The rate of return is {{ irr | percent(2) }} percent per period"),
+    // check_escrow_release
+    ("check_escrow_release.invalid_inputs", "We could not check the escrow release because some of the information is not valid"),
+    ("check_escrow_release.escrow_amount", "The escrow holds {{ escrow_amount | fixed(2) }}"),
+    ("check_escrow_release.no_dispute", "{% if dispute_pending %}There is an open dispute, so no money can be released{% else %}There is no open dispute{% endif %}"),
+    ("check_escrow_release.release_date", "{% if reached %}The release date of {{ release_date }} has been reached{% else %}The release date of {{ release_date }} has not been reached yet{% endif %}"),
+    ("check_escrow_release.milestone", "Milestone '{{ name }}' is worth {{ amount | fixed(2) }}. {% if not completed %}It is not complete yet{% elif blocked %}It is complete, but the money cannot be released yet{% else %}It is complete, so this amount is released{% endif %}"),
+    ("check_escrow_release.cumulative_release", "In total, {{ cumulative_release | fixed(2) }} can be released"),
+    ("check_escrow_release.already_released", "{{ already_released | fixed(2) }} was already released, so {{ release_amount | fixed(2) }} is released now"),
+    ("check_escrow_release.remaining", "{{ remaining | fixed(2) }} stays in escrow"),
+    // check_housing_grant
+    ("check_housing_grant.invalid_inputs", "We could not check eligibility because some of the information is not valid"),
+    ("check_housing_grant.income_conversion", "Your income of {{ income | money(currency) }} is {{ converted_income | money(base_currency) }}{% if rate_date %}, using the exchange rate from {{ rate_date }}{% endif %}"),
+    ("check_housing_grant.inputs", "The area median income is {{ ami | fixed(2) }}. Your household has {{ household_size }} {% if household_size == 1 %}person{% else %}people{% endif %} and an income of {{ income | fixed(2) }}"),
+    ("check_housing_grant.subsidy_check", "{% if has_other_subsidy %}You already get another housing subsidy, so you are not eligible{% else %}You do not get another housing subsidy{% endif %}"),
+    ("check_housing_grant.base_threshold", "The income limit is 60 percent of the area median income. That is {{ threshold | fixed(2) }}"),
+    ("check_housing_grant.household_adjustment", "{% if adjusted %}Your household has more than 4 people, so the limit is raised by 10 percent to {{ threshold | fixed(2) }}{% else %}The limit does not change for a household of your size{% endif %}"),
+    ("check_housing_grant.equivalence_scale", "Your household counts as {{ adult_equivalents | fixed(2) }} adults, because children and each adult after the first count for less. The limit is worked out for a household of 2 adults and 2 children, which counts as {{ reference_equivalents | fixed(2) }} adults, so each adult counts for {{ limit_per_equivalent | fixed(2) }}. That makes the limit for your household {{ threshold | fixed(2) }}"),
+    ("check_housing_grant.income_eligibility", "{% if eligible %}Your income of {{ income | fixed(2) }} is within the limit of {{ threshold | fixed(2) }}{% else %}Your income of {{ income | fixed(2) }} is above the limit of {{ threshold | fixed(2) }}{% endif %}"),
+    ("check_housing_grant.result", "{% if eligible %}You are eligible{% else %}You are not eligible{% endif %}"),
+    // calc_capital_gains
+    ("calc_capital_gains.invalid_inputs", "We could not calculate the tax on your sale because some of the information is not valid"),
+    ("calc_capital_gains.gain", "{% if gain > 0 %}You sold for {{ sale_price | fixed(2) }} and paid {{ acquisition_cost | fixed(2) }}, so you made {{ gain | fixed(2) }}{% else %}You sold for {{ sale_price | fixed(2) }} and paid {{ acquisition_cost | fixed(2) }}, so you made no profit{% endif %}"),
+    ("calc_capital_gains.holding_period", "{% if long_term %}You owned it for {{ holding_period_days }} days, which is at least {{ long_term_days }} days, so the lower long-term rate of {{ rate | percent(1) }} percent applies{% else %}You owned it for {{ holding_period_days }} days, which is less than {{ long_term_days }} days, so the short-term rate of {{ rate | percent(1) }} percent applies{% endif %}"),
+    ("calc_capital_gains.no_gain", "You pay no tax on this sale"),
+    ("calc_capital_gains.exemption", "The first {{ exemption_applied | fixed(2) }} of your profit is tax free. Tax is due on {{ taxable_gain | fixed(2) }}"),
+    ("calc_capital_gains.tax", "Your tax on the sale is {{ tax | fixed(2) }}"),
+    // calc_inheritance_tax
+    ("calc_inheritance_tax.invalid_inputs", "We could not calculate the tax because some of the information is not valid"),
+    ("calc_inheritance_tax.beneficiary", "{{ name }} receives {{ amount | fixed(2) }} as {{ relationship }}. {% if prior_gifts > 0 %}Earlier gifts of {{ prior_gifts | fixed(2) }} count against the tax free amount, so {% endif %}{{ exemption_applied | fixed(2) }} is tax free. {% if tax > 0 %}Tax of {{ tax | fixed(2) }} is due on the remaining {{ taxable_amount | fixed(2) }}{% else %}No tax is due{% endif %}"),
+    ("calc_inheritance_tax.total", "The total tax for everyone is {{ total_tax | fixed(2) }}"),
+    // calc_social_contributions
+    ("calc_social_contributions.invalid_inputs", "We could not calculate the contributions because some of the information is not valid"),
+    ("calc_social_contributions.gross_salary", "Your gross salary is {{ gross_salary | fixed(2) }}"),
+    ("calc_social_contributions.contribution", "For {{ name }}, {% if capped %}only the first {{ ceiling | fixed(2) }} of your salary counts. {% endif %}You pay {{ employee_amount | fixed(2) }} and your employer pays {{ employer_amount | fixed(2) }}"),
+    ("calc_social_contributions.totals", "In total you pay {{ total_employee | fixed(2) }} and your employer pays {{ total_employer | fixed(2) }}. Your salary after contributions is {{ net_salary | fixed(2) }}"),
+    // calc_property_tax
+    ("calc_property_tax.invalid_inputs", "We could not calculate the property tax because some of the information is not valid"),
+    ("calc_property_tax.assessed_value", "Your property is valued at {{ assessed_value | fixed(2) }}"),
+    ("calc_property_tax.assessment_cap", "{% if increase_capped %}The value used for tax can rise by at most {{ cap | percent(1) }} percent a year, so it is limited to {{ limit | fixed(2) }}{% else %}The value rose by no more than the yearly limit of {{ cap | percent(1) }} percent{% endif %}"),
+    ("calc_property_tax.homestead", "{% if homestead %}Because this is your main home, {{ exemption_applied | fixed(2) }} of its value is not taxed{% else %}No home owner discount applies{% endif %}"),
+    ("calc_property_tax.tax", "Your property tax is {{ tax | fixed(2) }}, at {{ millage | num }} for every 1000 of taxable value"),
+    // calc_transfer_tax
+    ("calc_transfer_tax.invalid_inputs", "We could not calculate the transfer tax because some of the information is not valid"),
+    ("calc_transfer_tax.slice", "A price of {{ price | fixed(2) }} is taxed at {{ rate | percent(1) }} percent on the whole amount, which comes to {{ tax | fixed(2) }}"),
+    ("calc_transfer_tax.marginal", "A price of {{ price | fixed(2) }} is taxed in steps, with each part taxed at its own rate, which comes to {{ tax | fixed(2) }}"),
+    ("calc_transfer_tax.effective_rate", "Overall, that is {{ effective_rate | percent(2) }} percent of the price"),
+    // calc_fee
+    ("calc_fee.invalid_inputs", "We could not calculate the fee because some of the information is not valid"),
+    ("calc_fee.value", "The {{ fee_code }} fee is based on a value of {{ value | fixed(2) }}"),
+    ("calc_fee.component", "The {{ name }} part comes to {{ amount | fixed(2) }}{% if capped %}, which is the most that can be charged{% endif %}"),
+    ("calc_fee.total", "The total fee is {{ fee | fixed(2) }}"),
+    // index_amount
+    ("index_amount.invalid_inputs", "We could not adjust the amount because some of the information is not valid"),
+    ("index_amount.index_values", "Prices moved from an index of {{ from_index | fixed(2) }} in {{ from_year }} to {{ to_index | fixed(2) }} in {{ to_year }}"),
+    ("index_amount.result", "So {{ amount | fixed(2) }} is worth about {{ indexed_amount | fixed(2) }} in {{ to_year }} money"),
+    // convert_currency
+    ("convert_currency.invalid_inputs", "We could not convert the amount because some of the information is not valid"),
+    ("convert_currency.rate", "One {{ from_currency }} is worth {{ rate | fixed(4) }} {{ to_currency }}{% if rate_date %}, using the rate from {{ rate_date }}{% endif %}"),
+    ("convert_currency.result", "So {{ amount | money(from_currency) }} is {{ converted_amount | money(to_currency) }}"),
+    // get_intake_schema
+    ("get_intake_schema.invalid_inputs", "We could not find the form because the program name is not valid"),
+    ("get_intake_schema.form", "The {{ program }} form has {{ fields }} questions, and {{ required }} of them must be answered"),
+    // prescreen
+    ("prescreen.invalid_inputs", "We could not pre-screen because some of the information is not valid"),
+    ("prescreen.rule", "{% if status == 'passed' %}The {{ rule | replace('_', ' ') }} rule is met{% elif status == 'failed' %}The {{ rule | replace('_', ' ') }} rule is not met: {{ detail }}{% else %}To check the {{ rule | replace('_', ' ') }} rule we still need: {{ missing | join(', ') | replace('_', ' ') }}{% endif %}"),
+    ("prescreen.verdict", "{% if verdict == 'eligible' %}Based on what you told us, you qualify{% elif verdict == 'ineligible' %}Based on what you told us, you do not qualify{% else %}We need a little more information before we can tell{% endif %}"),
+    // get_usage_report
+    ("get_usage_report.invalid_inputs", "We could not build the usage report because some of the information is not valid"),
+    ("get_usage_report.summary", "The tools were used {{ calls }} times since {{ since }}, and {{ failed }} of those calls failed"),
+    ("get_usage_report.top_error", "The most common problem was in {{ tool }}: {{ message }} ({{ count }} times)"),
+    // list_tools_by_category
+    ("list_tools_by_category.invalid_inputs", "We could not list the tools because the category is not valid"),
+    ("list_tools_by_category.summary", "There are {{ tools }} tools in {{ categories }} groups"),
+    // describe_capabilities
+    ("describe_capabilities.negotiated", "The app {{ client }} is connected using version {{ protocol }} of the protocol"),
+    ("describe_capabilities.not_negotiated", "The app has not introduced itself yet"),
+    ("describe_capabilities.shaping", "{% if structured_content %}Results are sent as structured data as well as text{% else %}Results are sent as text{% endif %}{% if sampling_summary %}, with a short summary written by the app{% endif %}"),
+    // get_recent_schema_failures
+    ("get_recent_schema_failures.invalid_inputs", "We could not build the report because some of the information is not valid"),
+    ("get_recent_schema_failures.summary", "Showing the {{ listed }} most recent of {{ total }} calls{% if tool %} to {{ tool }}{% endif %} that were sent in the wrong format"),
+    // upsert_profile
+    ("upsert_profile.invalid_inputs", "The settings were not changed because some of the information is not valid"),
+    ("upsert_profile.summary", "The {{ settings }} settings were saved and are now in use{% if previous == version %}; the rules did not change{% endif %}"),
+    // diff_profiles
+    ("diff_profiles.invalid_inputs", "We could not compare the rules because some of the information is not valid"),
+    ("diff_profiles.summary", "{{ changed }} settings are different in the new rules and {{ unchanged }} stay the same"),
+    ("diff_profiles.setting_added", "The new rules add the setting {{ setting }}"),
+    ("diff_profiles.setting_removed", "The new rules remove the setting {{ setting }}"),
+    ("diff_profiles.setting_changed", "The new rules change {{ setting }}{% if not brackets %} from {{ before }} to {{ after }}{% endif %}"),
+    ("diff_profiles.bracket_added", "A new band {{ bracket }} is added{% if after_up_to is not none %} for amounts up to {{ after_up_to | num }}{% endif %}, with a rate of {{ after_rate | percent(2) }} percent"),
+    ("diff_profiles.bracket_removed", "Band {{ bracket }} is removed"),
+    ("diff_profiles.bracket_changed", "Band {{ bracket }} changes{% if before_rate != after_rate %}: its rate goes from {{ before_rate | percent(2) }} to {{ after_rate | percent(2) }} percent{% endif %}{% if before_up_to != after_up_to %}{% if after_up_to is none %}, and it no longer has an upper limit{% else %}, and it now ends at {{ after_up_to | num }}{% endif %}{% endif %}"),
+    // evaluate_ranges
+    ("evaluate_ranges.invalid_inputs", "We could not work out the range because some of the information is not valid"),
+    ("evaluate_ranges.summary", "We checked every combination of the lowest and highest figures you gave ({% for range in ranges %}{{ range.parameter | replace('_', ' ') }} between {{ range.min | num }} and {{ range.max | num }}{% if not loop.last %}, {% endif %}{% endfor %}). {% if flips %}The answer can change within these figures: {{ flips | join(', ') | replace('_', ' ') }}{% else %}The answer is the same for all of them{% endif %}"),
+    // simulate_uncertainty
+    ("simulate_uncertainty.invalid_inputs", "We could not run the simulation because some of the information is not valid"),
+    ("simulate_uncertainty.summary", "We tried {{ draws }} possible values of {% for input in inputs %}{{ input.parameter | replace('_', ' ') }}{% if not loop.last %}, {% endif %}{% endfor %}{% for outcome in probabilities %}. {{ outcome.field | replace('_', ' ') | capitalize }} in {{ outcome.probability | percent(0) }} out of every 100{% endfor %}"),
+    // check_appeal_deadline
+    ("check_appeal_deadline.invalid_inputs", "We could not check the appeal deadline because some of the information is not valid"),
+    ("check_appeal_deadline.inputs", "The decision was issued on {{ decision_date }} and the appeal is filed on {{ filing_date }}"),
+    ("check_appeal_deadline.deadline", "{% if business_days %}You have {{ window_days }} working days to appeal, not counting weekends and public holidays, so the last day is {{ deadline }}{% else %}You have {{ window_days }} days to appeal{% if deadline_extended %}. The last of these days is not a working day, so you have until {{ deadline }}{% else %}, so the last day is {{ deadline }}{% endif %}{% endif %}"),
+    ("check_appeal_deadline.result", "{% if within_window %}The appeal is in time, with {{ days_remaining }} {% if days_remaining == 1 %}day{% else %}days{% endif %} to spare{% else %}The appeal is {{ days_overdue }} {% if days_overdue == 1 %}day{% else %}days{% endif %} late{% endif %}"),
+    // get_required_documents
+    ("get_required_documents.invalid_inputs", "We could not list the documents because some of the information is not valid"),
+    ("get_required_documents.summary", "For {{ program | replace('_', ' ') }} you need {{ count }} {% if count == 1 %}document{% else %}documents{% endif %}{% if situations %}, {{ count - general }} of them because of your situation{% endif %}"),
+    // check_voting_bulk
+    ("check_voting_bulk.invalid_inputs", "We could not check the vote because some of the precinct results are not valid"),
+    ("check_voting_bulk.totals", "Across the {{ count }} precincts, {{ turnout }} of {{ eligible_voters }} eligible voters took part and {{ yes_votes }} voted yes"),
+    ("check_voting_bulk.quorum", "{{ met }} of the {{ count }} precincts had enough voters on their own{% if below %}. Too few voted in {{ below | join(', ') }}{% endif %}"),
+    // project_quorum
+    ("project_quorum.invalid_inputs", "We could not project the vote because some of the information is not valid"),
+    ("project_quorum.quorum", "At least {{ quorum_votes }} of the {{ eligible_voters }} eligible voters must vote. {{ turnout }} have voted so far{% if additional_votes_needed > 0 %}, so {{ additional_votes_needed }} more are needed{% else %}, which is enough{% endif %}"),
+    ("project_quorum.projection", "If voting continues at the same pace for {{ hours_remaining | num }} more hours, about {{ projected_turnout }} people will have voted{% if met %}, which is enough{% else %}, which is not enough{% endif %}"),
+    ("project_quorum.threshold", "{% if proposal_type == 'amendment' %}An amendment{% else %}A general proposal{% endif %} needs {{ min_yes_votes }} yes votes if {{ turnout_basis }} people vote{% if additional_yes_votes > 0 %}, so {{ additional_yes_votes }} more yes votes are needed{% endif %}"),
+    // stress_config
+    ("stress_config.invalid_inputs", "We could not run the stress test because the tax brackets are not valid"),
+    ("stress_config.summary", "{% if failed %}Some checks found problems: {{ failed | join(', ') }}{% else %}The rules gave sound results for all {{ cases }} extreme inputs tried{% endif %}"),
+    // verify_store_integrity
+    ("verify_store_integrity.invalid_inputs", "We could not check the stored records because the store is not available"),
+    ("verify_store_integrity.summary", "{% if intact %}All {{ records }} stored records are intact{% else %}The store has problems: {{ failed }} of {{ records }} records cannot be read{% endif %}"),
+    // purge_expired_data
+    ("purge_expired_data.summary", "{% if purged %}Stored data past its retention period was deleted: {% for entry in purged %}{{ entry.deleted }} {{ entry.category }} records{% if not loop.last %} and {% endif %}{% endfor %}{% else %}No retention periods are set, so nothing was deleted{% endif %}"),
+    // export_subject_data
+    ("export_subject_data.summary", "{% if records %}We hold {{ records }} saved results for this case, listed below{% else %}We hold no saved results for this case{% endif %}"),
+    // erase_subject_data
+    ("erase_subject_data.summary", "{% if erased %}{{ erased }} saved results for this case were deleted{% else %}No saved results were found for this case, so nothing was deleted{% endif %}"),
+];
+
+static TEMPLATES: LazyLock<ExplanationTemplates> = LazyLock::new(|| {
+    let dir = std::env::var("ENGINE_EXPLANATION_TEMPLATES_DIR").ok();
+    let locale = std::env::var("ENGINE_LOCALE").unwrap_or_else(|_| DEFAULT_LOCALE.to_string());
+    ExplanationTemplates::load(dir.as_deref().map(Path::new), &locale)
+});
+
+/// Load the explanation templates now rather than on first render, so problems are logged at startup
+pub fn load_templates() {
+    LazyLock::force(&TEMPLATES);
+}
+
+thread_local! {
+    static EXPLAINING: Cell<bool> = const { Cell::new(true) };
+}
+
+/// Run `body` with explanations on this thread rendered, or skipped when `include` is false
+pub fn with_explanations<T>(include: bool, body: impl FnOnce() -> T) -> T {
+    let outer = EXPLAINING.with(|current| current.replace(include));
+    let result = body();
+    EXPLAINING.with(|current| current.set(outer));
+    result
+}
+
+/// Render the configured template for `key` (`<tool>.<step>`) with the given context, or return
+/// an empty string without rendering while explanations are skipped.
+pub fn explain(audience: Audience, key: &str, ctx: Value) -> String {
+    if !EXPLAINING.with(Cell::get) {
+        return String::new();
+    }
+    let text = TEMPLATES.render(audience, key, ctx.clone());
+    explanation_graph::observe(&text, &ctx);
+    text
+}
+
+/// Where a locale writes the currency relative to the amount
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CurrencyPosition {
+    /// `$1,234.56`
+    Before,
+    /// `€ 1.234,56`
+    BeforeSpaced,
+    /// `1.234,56 €`
+    After,
+}
+
+/// How a locale writes numbers: decimal mark, digit grouping and currency placement
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NumberFormat {
+    decimal: char,
+    group: char,
+    currency: CurrencyPosition,
+}
+
+impl NumberFormat {
+    /// Format for a locale such as `de`, `de-DE` or `fr_CH`
+    pub fn for_locale(locale: &str) -> Result<Self, String> {
+        let normalized = locale.trim().replace('_', "-").to_ascii_lowercase();
+        let (language, region) = normalized.split_once('-').unwrap_or((&normalized, ""));
+        let (decimal, group, currency) = match (language, region) {
+            ("de" | "it" | "fr" | "rm", "ch" | "li") => ('.', '’', CurrencyPosition::BeforeSpaced),
+            ("es", "mx" | "us") => ('.', ',', CurrencyPosition::Before),
+            ("en" | "ja" | "zh" | "ko" | "he" | "th" | "hi" | "ms", _) => ('.', ',', CurrencyPosition::Before),
+            ("nl", _) => (',', '.', CurrencyPosition::BeforeSpaced),
+            ("de" | "es" | "it" | "pt" | "da" | "id" | "tr" | "el" | "ro" | "hr" | "sl" | "sr", _) => {
+                (',', '.', CurrencyPosition::After)
+            }
+            ("fr", _) => (',', '\u{202f}', CurrencyPosition::After),
+            ("sv" | "nb" | "nn" | "no" | "fi" | "cs" | "sk" | "pl" | "ru" | "uk" | "hu" | "bg" | "et" | "lt" | "lv", _) => {
+                (',', '\u{a0}', CurrencyPosition::After)
+            }
+            _ => {
+                return Err(format!(
+                    "unsupported locale '{}'; expected a language such as 'en', 'de' or 'fr', optionally with a region ('de-DE')",
+                    locale.trim()
+                ))
+            }
+        };
+        Ok(Self { decimal, group, currency })
+    }
+
+    /// `plain` (a number written with a dot and no grouping) in this format
+    fn localize(&self, plain: &str) -> String {
+        let (sign, digits) = plain.strip_prefix('-').map_or(("", plain), |digits| ("-", digits));
+        let (integer, fraction) = digits.split_once('.').map_or((digits, None), |(integer, fraction)| (integer, Some(fraction)));
+        if !integer.bytes().all(|byte| byte.is_ascii_digit()) {
+            // inf, NaN
+            return plain.to_string();
+        }
+        let mut text = sign.to_string();
+        for (index, digit) in integer.chars().enumerate() {
+            if index > 0 && (integer.len() - index).is_multiple_of(3) {
+                text.push(self.group);
+            }
+            text.push(digit);
+        }
+        if let Some(fraction) = fraction {
+            text.push(self.decimal);
+            text.push_str(fraction);
+        }
+        text
+    }
+}
+
+/// Symbol written for a currency code; other codes are written as they are
+fn currency_symbol(code: &str) -> &str {
+    match code {
+        "USD" => "$",
+        "EUR" => "€",
+        "GBP" => "£",
+        "JPY" | "CNY" => "¥",
+        "INR" => "₹",
+        "KRW" => "₩",
+        _ => code,
+    }
+}
+
+/// Locale of numbers when a call does not give one; unset keeps the plain format
+static DEFAULT_NUMBER_FORMAT: LazyLock<Option<NumberFormat>> = LazyLock::new(|| {
+    let locale = std::env::var("ENGINE_NUMBER_LOCALE").ok().filter(|locale| !locale.trim().is_empty())?;
+    NumberFormat::for_locale(&locale)
+        .inspect_err(|e| tracing::error!("Invalid ENGINE_NUMBER_LOCALE: {}; numbers use the plain format", e))
+        .ok()
+});
+
+thread_local! {
+    static NUMBER_FORMAT: Cell<Option<NumberFormat>> = const { Cell::new(None) };
+}
+
+/// Number format for a call's optional `locale` parameter, or the deployment default
+pub fn number_format(locale: Option<&String>) -> Result<Option<NumberFormat>, String> {
+    match locale.map(|locale| locale.trim()).filter(|locale| !locale.is_empty()) {
+        Some(locale) => NumberFormat::for_locale(locale).map(Some),
+        None => Ok(*DEFAULT_NUMBER_FORMAT),
+    }
+}
+
+/// Run `body` with explanations on this thread writing numbers in `format`
+pub fn with_number_format<T>(format: Option<NumberFormat>, body: impl FnOnce() -> T) -> T {
+    let outer = NUMBER_FORMAT.with(|current| current.replace(format));
+    let result = body();
+    NUMBER_FORMAT.with(|current| current.set(outer));
+    result
+}
+
+fn localized(plain: String) -> String {
+    match NUMBER_FORMAT.with(Cell::get) {
+        Some(format) => format.localize(&plain),
+        None => plain,
+    }
+}
+
+fn fixed(value: f64, precision: Option<usize>) -> String {
+    localized(format!("{:.*}", precision.unwrap_or(2), value))
+}
+
+fn percent(value: f64, precision: Option<usize>) -> String {
+    localized(format!("{:.*}", precision.unwrap_or(1), value * 100.0))
+}
+
+fn num(value: f64) -> String {
+    localized(value.to_string())
+}
+
+fn money(value: f64, currency: String) -> String {
+    let Some(format) = NUMBER_FORMAT.with(Cell::get) else {
+        return format!("{:.2} {}", value, currency);
+    };
+    let amount = format.localize(&format!("{:.2}", value.abs()));
+    let sign = if value < 0.0 && amount.chars().any(|digit| ('1'..='9').contains(&digit)) { "-" } else { "" };
+    let symbol = currency_symbol(&currency);
+    match format.currency {
+        CurrencyPosition::Before => format!("{}{}{}", sign, symbol, amount),
+        CurrencyPosition::BeforeSpaced => format!("{}{} {}", sign, symbol, amount),
+        CurrencyPosition::After => format!("{}{} {}", sign, amount, symbol),
+    }
+}
+
+fn environment() -> Environment<'static> {
+    let mut env = Environment::new();
+    env.set_auto_escape_callback(|_| AutoEscape::None);
+    env.set_undefined_behavior(UndefinedBehavior::Strict);
+    env.add_filter("fixed", fixed);
+    env.add_filter("percent", percent);
+    env.add_filter("num", num);
+    env.add_filter("money", money);
+    env
+}
+
+pub struct ExplanationTemplates {
+    locale: String,
+    configured: Environment<'static>,
+    defaults: Environment<'static>,
+}
+
+impl ExplanationTemplates {
+    /// Built-in templates layered with the overrides found under `dir/<locale>/`, if any.
+    pub fn load(dir: Option<&Path>, locale: &str) -> Self {
+        let mut defaults = environment();
+        for (audience, templates) in [(Audience::Expert, DEFAULT_TEMPLATES), (Audience::Citizen, CITIZEN_TEMPLATES)] {
+            for (key, source) in templates {
+                defaults
+                    .add_template_owned(format!("{}{}", audience.prefix(), key), *source)
+                    .expect("built-in explanation templates must parse");
+            }
+        }
+        let mut configured = defaults.clone();
+
+        if let Some(dir) = dir {
+            let locale_dir = dir.join(locale);
+            if locale_dir.is_dir() {
+                for audience in [Audience::Expert, Audience::Citizen] {
+                    Self::load_audience_dir(&mut configured, &locale_dir.join(audience.prefix()), audience);
+                }
+            } else {
+                tracing::warn!(
+                    path = %locale_dir.display(),
+                    "explanation templates for locale not found; using built-in templates"
+                );
+            }
+        }
+
+        Self {
+            locale: locale.to_string(),
+            configured,
+            defaults,
+        }
+    }
+
+    fn load_audience_dir(env: &mut Environment<'static>, dir: &Path, audience: Audience) {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return;
+        };
+        for path in entries.flatten().map(|entry| entry.path()) {
+            if path.extension().is_some_and(|ext| ext == "json") {
+                Self::load_tool_file(env, &path, audience);
+            }
+        }
+    }
+
+    fn load_tool_file(env: &mut Environment<'static>, path: &Path, audience: Audience) {
+        let Some(tool) = path.file_stem().and_then(|stem| stem.to_str()) else {
+            return;
+        };
+        let steps: BTreeMap<String, String> = match std::fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| serde_json::from_str(&text).map_err(|e| e.to_string()))
+        {
+            Ok(steps) => steps,
+            Err(e) => {
+                tracing::warn!(path = %path.display(), error = %e, "ignoring unreadable explanation template file");
+                return;
+            }
+        };
+        for (step, source) in steps {
+            let key = format!("{}.{}", tool, step);
+            if !DEFAULT_TEMPLATES.iter().any(|(known, _)| *known == key) {
+                tracing::warn!(template = %key, "ignoring unknown explanation template");
+                continue;
+            }
+            let name = format!("{}{}", audience.prefix(), key);
+            if let Err(e) = env.add_template_owned(name.clone(), source) {
+                tracing::warn!(template = %name, error = %e, "ignoring invalid explanation template");
+            }
+        }
+    }
+
+    /// Render `key` for `audience`, falling back to the built-in template if the configured one fails.
+    pub fn render(&self, audience: Audience, key: &str, ctx: Value) -> String {
+        let name = format!("{}{}", audience.prefix(), key);
+        let rendered = self
+            .configured
+            .get_template(&name)
+            .and_then(|template| template.render(&ctx));
+        match rendered {
+            Ok(text) => text,
+            Err(e) => {
+                tracing::warn!(template = %name, locale = %self.locale, error = %e, "explanation template failed to render");
+                self.defaults
+                    .get_template(&name)
+                    .and_then(|template| template.render(&ctx))
+                    .unwrap_or_else(|_| key.to_string())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use minijinja::context;
+
+    fn write_overrides(locale: &str, tool: &str, json: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("explanations-{}-{}", std::process::id(), tool));
+        std::fs::create_dir_all(dir.join(locale)).unwrap();
+        std::fs::write(dir.join(locale).join(format!("{}.json", tool)), json).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_default_templates_render() {
+        let templates = ExplanationTemplates::load(None, DEFAULT_LOCALE);
+        let text = templates.render(
+            Audience::Expert,
+            "calc_penalty.base_penalty",
+            context! { days_late => 10.0, rate_per_day => 12.5, base_penalty => 125.0 },
+        );
+        assert_eq!(text, "Base penalty: 10 days × 12.5 = 125.00");
+    }
+
+    #[test]
+    fn test_locale_override_with_fallback() {
+        let dir = write_overrides(
+            "es",
+            "calc_npv",
+            r#"{
+                "npv": "Valor actual neto: {{ npv | fixed(2) }}",
+                "period": "{{ missing_variable }}",
+                "unknown_step": "ignored",
+                "discounting": "{% if %}"
+            }"#,
+        );
+        let templates = ExplanationTemplates::load(Some(&dir), "es");
+        assert_eq!(
+            templates.render(Audience::Expert, "calc_npv.npv", context! { npv => 1234.5 }),
+            "Valor actual neto: 1234.50"
+        );
+        // Render errors and syntax errors fall back to the built-in wording
+        let period = templates.render(
+            Audience::Expert,
+            "calc_npv.period",
+            context! { period => 1, cash_flow => 110.0, discount_rate => 0.1, present_value => 100.0 },
+        );
+        assert_eq!(period, "Period 1: 110.00 / (1 + 0.1000)^1 = 100.00");
+        let discounting = templates.render(
+            Audience::Expert,
+            "calc_npv.discounting",
+            context! { periods => 2, discount_rate => 0.1 },
+        );
+        assert_eq!(discounting, "Discounting 2 cash flows at 10.00% per period");
+
+        // Other locales keep the defaults
+        let templates = ExplanationTemplates::load(Some(&dir), "fr");
+        assert_eq!(templates.render(Audience::Expert, "calc_npv.npv", context! { npv => 1.0 }), "NPV: 1.00");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_citizen_templates_cover_every_step_in_plain_language() {
+        let expert: Vec<&str> = DEFAULT_TEMPLATES.iter().map(|(key, _)| *key).collect();
+        let citizen: Vec<&str> = CITIZEN_TEMPLATES.iter().map(|(key, _)| *key).collect();
+        assert_eq!(expert, citizen);
+        for (key, source) in CITIZEN_TEMPLATES {
+            let text = source.replace("{%", "").replace("%}", "");
+            for symbol in ["≤", "≥", "≠", "×", "^", "%"] {
+                assert!(!text.contains(symbol), "{} contains '{}'", key, symbol);
+            }
+        }
+
+        let templates = ExplanationTemplates::load(None, DEFAULT_LOCALE);
+        let ctx = context! { income => 50000.0, threshold => 48000.0, eligible => false };
+        assert_eq!(
+            templates.render(Audience::Citizen, "check_housing_grant.income_eligibility", ctx.clone()),
+            "Your income of 50000.00 is above the limit of 48000.00"
+        );
+        assert_eq!(
+            templates.render(Audience::Expert, "check_housing_grant.income_eligibility", ctx),
+            "Income eligibility: 50000.00 > 48000.00 - FAILED"
+        );
+    }
+
+    #[test]
+    fn test_number_format_for_locale() {
+        let templates = ExplanationTemplates::load(None, DEFAULT_LOCALE);
+        let ctx = context! { amount => 1234567.891, from_currency => "USD", rate => 0.92, converted_amount => -1135.8, to_currency => "EUR" };
+        let render = |locale: Option<&str>| {
+            let format = locale.map(|locale| NumberFormat::for_locale(locale).unwrap());
+            with_number_format(format, || templates.render(Audience::Expert, "convert_currency.result", ctx.clone()))
+        };
+        // Without a locale numbers keep the plain format
+        assert_eq!(render(None), "Converted: 1234567.89 USD × 0.920000 = -1135.80 EUR");
+        assert_eq!(render(Some("de-DE")), "Converted: 1.234.567,89 $ × 0,920000 = -1.135,80 €");
+        assert_eq!(render(Some("en_us")), "Converted: $1,234,567.89 × 0.920000 = -€1,135.80");
+        assert_eq!(render(Some("fr")), "Converted: 1\u{202f}234\u{202f}567,89 $ × 0,920000 = -1\u{202f}135,80 €");
+        assert_eq!(render(Some("de-CH")), "Converted: $ 1’234’567.89 × 0.920000 = -€ 1’135.80");
+
+        let format = NumberFormat::for_locale("nl-NL").ok();
+        let (ratio, number, amount) =
+            with_number_format(format, || (percent(0.1234, None), num(-1000.5), money(12.0, "CHF".to_string())));
+        assert_eq!((ratio.as_str(), number.as_str(), amount.as_str()), ("12,3", "-1.000,5", "CHF 12,00"));
+        // The format only applies inside the call
+        assert_eq!(fixed(1234.5, None), "1234.50");
+        assert_eq!(number_format(None), Ok(None));
+        assert!(NumberFormat::for_locale("tlh").is_err());
+    }
+
+    #[test]
+    fn test_parse_audience() {
+        assert_eq!(Audience::parse(None), Ok(Audience::Expert));
+        assert_eq!(Audience::parse(Some(&" Citizen ".to_string())), Ok(Audience::Citizen));
+        assert_eq!(Audience::parse(Some(&"expert".to_string())), Ok(Audience::Expert));
+        assert!(Audience::parse(Some(&"child".to_string())).is_err());
+    }
+}
@@ -0,0 +1,205 @@
+//! Batch and simulation results written to files for analytics tools.
+//!
+//! `evaluate_ranges` and `simulate_uncertainty` answer with summaries. Given `"export": "parquet"`,
+//! they also write one row per call they made to a Parquet file: the point or draw number, the
+//! arguments that were varied (as `arguments.<name>`) and every numeric and yes/no result field
+//! (nested fields as `distribution.senior`). The file goes to `ENGINE_EXPORT_DIR`, by default
+//! `compatibility-engine-exports` in the system temp directory, and the payload's `export` gives
+//! its path, row count and columns. Numbers are `DOUBLE` columns, yes/no fields `BOOLEAN`, and a
+//! field missing from a row is null. Files are not deleted by the server.
+//!
+//! Parquet support is compiled in with the `parquet-export` cargo feature; without it, asking for
+//! an export is an invalid parameter.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use rmcp::schemars;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Directory export files are written to
+static EXPORT_DIR: LazyLock<PathBuf> = LazyLock::new(|| {
+    std::env::var("ENGINE_EXPORT_DIR")
+        .ok()
+        .filter(|dir| !dir.trim().is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| std::env::temp_dir().join("compatibility-engine-exports"))
+});
+
+/// File format of an export
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Parquet,
+}
+
+impl ExportFormat {
+    /// Parse the optional `export` tool parameter; no export when omitted
+    pub fn parse(value: Option<&String>) -> Result<Option<Self>, String> {
+        match value.map(|s| s.trim().to_ascii_lowercase()) {
+            None => Ok(None),
+            Some(s) if s.is_empty() || s == "none" => Ok(None),
+            Some(s) if s == "parquet" && cfg!(feature = "parquet-export") => Ok(Some(ExportFormat::Parquet)),
+            Some(s) if s == "parquet" => Err("parquet export is not available in this build (parquet-export feature)".to_string()),
+            Some(_) => Err("must be 'parquet'".to_string()),
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Parquet => "parquet",
+        }
+    }
+}
+
+/// An export file written for a call
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, schemars::JsonSchema)]
+pub struct ExportedFile {
+    #[schemars(description = "File format: 'parquet'")]
+    pub format: String,
+    #[schemars(description = "Path of the file on the server")]
+    pub path: String,
+    #[schemars(description = "Number of rows: one per call made")]
+    pub rows: usize,
+    #[schemars(description = "Column names in file order")]
+    pub columns: Vec<String>,
+}
+
+/// Values of one column, typed by what its rows hold
+#[derive(Debug, Clone, PartialEq)]
+enum Column {
+    Number(Vec<Option<f64>>),
+    Flag(Vec<Option<bool>>),
+    Text(Vec<Option<String>>),
+}
+
+/// Columns of `rows`: the `leading` ones, the arguments, then the rest in order of first appearance.
+/// A column holds numbers if every value is a number, yes/no if every value is a boolean, text
+/// otherwise
+fn columns(rows: &[BTreeMap<String, Value>], leading: &[&str]) -> Vec<(String, Column)> {
+    let mut names: Vec<String> = leading.iter().map(|name| name.to_string()).collect();
+    for row in rows {
+        for name in row.keys() {
+            if !names.contains(name) {
+                names.push(name.clone());
+            }
+        }
+    }
+    names[leading.len()..].sort_by_key(|name| !name.starts_with("arguments."));
+    names
+        .into_iter()
+        .map(|name| {
+            let values: Vec<Option<&Value>> = rows.iter().map(|row| row.get(&name).filter(|value| !value.is_null())).collect();
+            let column = if values.iter().flatten().all(|value| value.is_number()) {
+                Column::Number(values.iter().map(|value| value.and_then(Value::as_f64)).collect())
+            } else if values.iter().flatten().all(|value| value.is_boolean()) {
+                Column::Flag(values.iter().map(|value| value.and_then(Value::as_bool)).collect())
+            } else {
+                let text = |value: &Value| value.as_str().map_or_else(|| value.to_string(), str::to_string);
+                Column::Text(values.iter().map(|value| value.map(text)).collect())
+            };
+            (name, column)
+        })
+        .collect()
+}
+
+/// Write `rows` to a new file named after `tool` in the export directory, with the `leading`
+/// columns first
+pub fn write(format: ExportFormat, tool: &str, rows: &[BTreeMap<String, Value>], leading: &[&str]) -> Result<ExportedFile, String> {
+    let columns = columns(rows, leading);
+    std::fs::create_dir_all(&*EXPORT_DIR)
+        .map_err(|e| format!("Could not create export directory {}: {}", EXPORT_DIR.display(), e))?;
+    let path = EXPORT_DIR.join(format!("{}-{}.{}", tool, uuid::Uuid::new_v4(), format.extension()));
+    match format {
+        ExportFormat::Parquet => write_parquet(&path, &columns),
+    }
+    .map_err(|e| format!("Could not write export {}: {}", path.display(), e))?;
+    tracing::info!(tool, path = %path.display(), rows = rows.len(), "Results exported");
+
+    Ok(ExportedFile {
+        format: format.extension().to_string(),
+        path: path.display().to_string(),
+        rows: rows.len(),
+        columns: columns.into_iter().map(|(name, _)| name).collect(),
+    })
+}
+
+#[cfg(feature = "parquet-export")]
+fn write_parquet(path: &Path, columns: &[(String, Column)]) -> Result<(), String> {
+    use std::sync::Arc;
+
+    use arrow_array::{ArrayRef, BooleanArray, Float64Array, RecordBatch, StringArray};
+    use arrow_schema::{DataType, Field, Schema};
+    use parquet::arrow::ArrowWriter;
+
+    let mut fields = Vec::with_capacity(columns.len());
+    let mut arrays: Vec<ArrayRef> = Vec::with_capacity(columns.len());
+    for (name, column) in columns {
+        let (data_type, array): (DataType, ArrayRef) = match column {
+            Column::Number(values) => (DataType::Float64, Arc::new(Float64Array::from(values.clone()))),
+            Column::Flag(values) => (DataType::Boolean, Arc::new(BooleanArray::from(values.clone()))),
+            Column::Text(values) => (DataType::Utf8, Arc::new(StringArray::from(values.clone()))),
+        };
+        fields.push(Field::new(name, data_type, true));
+        arrays.push(array);
+    }
+    let batch = RecordBatch::try_new(Arc::new(Schema::new(fields)), arrays).map_err(|e| e.to_string())?;
+    let file = std::fs::File::create(path).map_err(|e| e.to_string())?;
+    let mut writer = ArrowWriter::try_new(file, batch.schema(), None).map_err(|e| e.to_string())?;
+    writer.write(&batch).map_err(|e| e.to_string())?;
+    writer.close().map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[cfg(not(feature = "parquet-export"))]
+fn write_parquet(_path: &Path, _columns: &[(String, Column)]) -> Result<(), String> {
+    Err("parquet export is not available in this build".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn rows() -> Vec<BTreeMap<String, Value>> {
+        let row = |value: Value| serde_json::from_value::<BTreeMap<String, Value>>(value).unwrap();
+        vec![
+            row(json!({"draw": 1, "arguments.income": 41000.5, "eligible": true, "tax": 7000, "adjusted": 1})),
+            row(json!({"draw": 2, "arguments.income": 52000, "eligible": false, "band": "B", "tax": null})),
+        ]
+    }
+
+    #[test]
+    fn test_export_columns() {
+        let columns = columns(&rows(), &["draw"]);
+        let names: Vec<&str> = columns.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["draw", "arguments.income", "adjusted", "eligible", "tax", "band"]);
+        assert_eq!(columns[1].1, Column::Number(vec![Some(41000.5), Some(52000.0)]));
+        assert_eq!(columns[3].1, Column::Flag(vec![Some(true), Some(false)]));
+        // Missing and null values are nulls
+        assert_eq!(columns[4].1, Column::Number(vec![Some(7000.0), None]));
+        assert_eq!(columns[5].1, Column::Text(vec![None, Some("B".to_string())]));
+
+        assert_eq!(ExportFormat::parse(None), Ok(None));
+        assert_eq!(ExportFormat::parse(Some(&" none ".to_string())), Ok(None));
+        assert!(ExportFormat::parse(Some(&"csv".to_string())).unwrap_err().contains("'parquet'"));
+        assert_eq!(ExportFormat::parse(Some(&"Parquet".to_string())).is_ok(), cfg!(feature = "parquet-export"));
+    }
+
+    #[cfg(feature = "parquet-export")]
+    #[test]
+    fn test_export_parquet() {
+        use parquet::file::reader::{FileReader, SerializedFileReader};
+
+        let exported = write(ExportFormat::Parquet, "simulate_uncertainty", &rows(), &["draw"]).unwrap();
+        assert_eq!((exported.format.as_str(), exported.rows), ("parquet", 2));
+        let reader = SerializedFileReader::new(std::fs::File::open(&exported.path).unwrap()).unwrap();
+        let metadata = reader.metadata().file_metadata();
+        assert_eq!(metadata.num_rows(), 2);
+        let names: Vec<&str> = metadata.schema_descr().columns().iter().map(|column| column.name()).collect();
+        assert_eq!(names, exported.columns.iter().map(String::as_str).collect::<Vec<_>>());
+        std::fs::remove_file(&exported.path).unwrap();
+    }
+}
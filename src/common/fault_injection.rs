@@ -0,0 +1,159 @@
+//! Failure injection for testing clients against a misbehaving server (`fault-injection` feature).
+//!
+//! Built only with `--features fault-injection`; release builds carry none of this. [`inject`]
+//! wraps every route of a tool router and, depending on environment switches, makes calls fail
+//! the way the real server fails:
+//!
+//! - `ENGINE_FAULT_DELAY_MS=<ms>`: every call takes this much longer, for timeout handling.
+//! - `ENGINE_FAULT_SERIALIZATION_RATE=<0..1>`: this share of successful calls is answered with
+//!   the error result the server gives when a response cannot be serialized.
+//! - `ENGINE_FAULT_CONFIG=true`: the configuration is reported as failed to load at startup
+//!   (so `--preflight` fails) and every call is answered with an internal error result.
+//! - `ENGINE_FAULT_TOOLS=<tool,...>`: limit the faults to these tools (default all).
+//!
+//! Injected faults are logged at startup, so a build with them enabled is never mistaken for a
+//! healthy one.
+
+use std::collections::BTreeSet;
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use futures::FutureExt;
+use rmcp::handler::server::router::tool::{ToolRoute, ToolRouter};
+use rmcp::handler::server::tool::ToolCallContext;
+use rmcp::model::{CallToolResult, Content};
+
+use super::metrics;
+
+#[derive(Debug, Default, PartialEq)]
+struct Faults {
+    delay: Duration,
+    serialization_rate: f64,
+    config: bool,
+    /// Tools the faults apply to; empty for all
+    tools: BTreeSet<String>,
+}
+
+impl Faults {
+    fn parse(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        let mut faults = Faults::default();
+        if let Some(value) = lookup("ENGINE_FAULT_DELAY_MS") {
+            let millis = value.trim().parse::<u64>().map_err(|e| format!("ENGINE_FAULT_DELAY_MS '{}': {}", value, e))?;
+            faults.delay = Duration::from_millis(millis);
+        }
+        if let Some(value) = lookup("ENGINE_FAULT_SERIALIZATION_RATE") {
+            faults.serialization_rate = match value.trim().parse::<f64>() {
+                Ok(rate) if (0.0..=1.0).contains(&rate) => rate,
+                _ => return Err(format!("ENGINE_FAULT_SERIALIZATION_RATE '{}': expected a number from 0 to 1", value)),
+            };
+        }
+        if let Some(value) = lookup("ENGINE_FAULT_CONFIG") {
+            faults.config = match value.trim().to_ascii_lowercase().as_str() {
+                "true" | "1" | "on" | "yes" => true,
+                "false" | "0" | "off" | "no" | "" => false,
+                _ => return Err(format!("ENGINE_FAULT_CONFIG '{}': expected true or false", value)),
+            };
+        }
+        if let Some(value) = lookup("ENGINE_FAULT_TOOLS") {
+            faults.tools = value.split(',').map(str::trim).filter(|tool| !tool.is_empty()).map(String::from).collect();
+        }
+        Ok(faults)
+    }
+
+    fn applies_to(&self, tool: &str) -> bool {
+        self.tools.is_empty() || self.tools.contains(tool)
+    }
+
+    fn any(&self) -> bool {
+        !self.delay.is_zero() || self.serialization_rate > 0.0 || self.config
+    }
+}
+
+static FAULTS: LazyLock<Faults> = LazyLock::new(|| {
+    let faults = Faults::parse(|name| std::env::var(name).ok()).unwrap_or_else(|e| {
+        tracing::error!("Invalid fault injection setting {}; no faults are injected", e);
+        Faults::default()
+    });
+    if faults.config {
+        tracing::error!("Injected fault: the engine configuration failed to load (ENGINE_FAULT_CONFIG)");
+    }
+    if faults.any() {
+        tracing::warn!(?faults, "Fault injection is active");
+    }
+    faults
+});
+
+/// Wrap every route of `router` so its calls suffer the configured faults
+pub fn inject<S: Send + Sync + 'static>(router: ToolRouter<S>) -> ToolRouter<S> {
+    if !FAULTS.any() {
+        return router;
+    }
+    let mut injected = ToolRouter::new();
+    for route in router {
+        if !FAULTS.applies_to(route.name()) {
+            injected.add_route(route);
+            continue;
+        }
+        let call = route.call.clone();
+        injected.add_route(ToolRoute::new_dyn(route.attr, move |context: ToolCallContext<'_, S>| {
+            let tool = context.name.to_string();
+            let future = call(context);
+            async move {
+                if FAULTS.config {
+                    return Ok(fault(format!(
+                        "Internal error: the {} configuration could not be loaded. Please report this",
+                        tool
+                    )));
+                }
+                tokio::time::sleep(FAULTS.delay).await;
+                let result = future.await?;
+                if !result.is_error.unwrap_or(false) && rand::random::<f64>() < FAULTS.serialization_rate {
+                    return Ok(fault("Error serializing response: injected fault".to_string()));
+                }
+                Ok(result)
+            }
+            .boxed()
+        }));
+    }
+    injected
+}
+
+fn fault(message: String) -> CallToolResult {
+    metrics::increment_errors();
+    CallToolResult::error(vec![Content::text(message)])
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn parse(settings: &[(&str, &str)]) -> Result<Faults, String> {
+        let settings: HashMap<String, String> =
+            settings.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect();
+        Faults::parse(|name| settings.get(name).cloned())
+    }
+
+    #[test]
+    fn test_parse_faults() {
+        assert_eq!(parse(&[]), Ok(Faults::default()));
+        assert!(!parse(&[]).unwrap().any());
+
+        let faults = parse(&[
+            ("ENGINE_FAULT_DELAY_MS", "250"),
+            ("ENGINE_FAULT_SERIALIZATION_RATE", "0.5"),
+            ("ENGINE_FAULT_TOOLS", "calc_tax, calc_npv,"),
+        ])
+        .unwrap();
+        assert_eq!(faults.delay, Duration::from_millis(250));
+        assert_eq!(faults.serialization_rate, 0.5);
+        assert!(faults.any() && !faults.config);
+        assert!(faults.applies_to("calc_npv") && !faults.applies_to("calc_irr"));
+
+        assert!(parse(&[("ENGINE_FAULT_CONFIG", "yes")]).unwrap().config);
+        assert!(parse(&[("ENGINE_FAULT_SERIALIZATION_RATE", "2")]).is_err());
+        assert!(parse(&[("ENGINE_FAULT_DELAY_MS", "soon")]).is_err());
+        assert!(parse(&[("ENGINE_FAULT_CONFIG", "maybe")]).is_err());
+    }
+}
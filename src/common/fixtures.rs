@@ -0,0 +1,223 @@
+//! Record real tool calls as fixtures, and replay them in tests.
+//!
+//! With `ENGINE_RECORD_FIXTURES=<file>`, [`record`] appends every tool call to the file as one
+//! JSON line: the tool, its arguments as sent, and the result or protocol error as returned.
+//! Arguments are stored verbatim, so record test or staging traffic, not calls carrying personal
+//! data. Tools whose results depend on server state, on the client or on timing (the usage and
+//! schema failure reports, `describe_capabilities`, `stress_config` and the store admin tools)
+//! and `upsert_profile`, which changes the configuration, are not recorded.
+//!
+//! Recorded files copied to `snapshots/fixtures/` are replayed by the tests: every call runs
+//! through validation and computation again and must give the same result, ignoring timestamps,
+//! result ids and the parts shaped for the client (structured content, sampling summaries). Run
+//! the tests with `UPDATE_COMPAT_SNAPSHOT=1` to rewrite the recorded results after reviewing an
+//! intended change.
+
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::sync::{LazyLock, Mutex};
+
+use futures::FutureExt;
+use rmcp::handler::server::router::tool::{ToolRoute, ToolRouter};
+use rmcp::handler::server::tool::ToolCallContext;
+use rmcp::model::{CallToolResult, JsonObject};
+use rmcp::ErrorData as McpError;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Tools whose results cannot be replayed: they report server state, the connected client or
+/// timings, or change the configuration
+const NOT_RECORDED: &[&str] = &[
+    "get_usage_report",
+    "get_recent_schema_failures",
+    "describe_capabilities",
+    "upsert_profile",
+    "stress_config",
+    "verify_store_integrity",
+    "purge_expired_data",
+    "export_subject_data",
+    "erase_subject_data",
+];
+
+static RECORDER: LazyLock<Option<Mutex<File>>> = LazyLock::new(|| {
+    let path = std::env::var("ENGINE_RECORD_FIXTURES").ok().filter(|path| !path.trim().is_empty())?;
+    match OpenOptions::new().create(true).append(true).open(path.trim()) {
+        Ok(file) => {
+            tracing::info!("Recording tool calls to {}", path.trim());
+            Some(Mutex::new(file))
+        }
+        Err(e) => {
+            tracing::error!("Invalid ENGINE_RECORD_FIXTURES '{}': {}; calls are not recorded", path, e);
+            None
+        }
+    }
+});
+
+/// One recorded call: a result or a protocol error, as put on the wire
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Fixture {
+    pub tool: String,
+    pub arguments: JsonObject,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<Value>,
+}
+
+impl Fixture {
+    pub fn new(tool: String, arguments: JsonObject, outcome: &Result<CallToolResult, McpError>) -> Self {
+        let (result, error) = match outcome {
+            Ok(result) => (serde_json::to_value(result).ok(), None),
+            Err(e) => (None, serde_json::to_value(e).ok()),
+        };
+        Self { tool, arguments, result, error }
+    }
+}
+
+/// Wrap every route of `router` so its calls are appended to the `ENGINE_RECORD_FIXTURES` file
+pub fn record<S: Send + Sync + 'static>(router: ToolRouter<S>) -> ToolRouter<S> {
+    let Some(recorder) = RECORDER.as_ref() else {
+        return router;
+    };
+    let mut recorded = ToolRouter::new();
+    for route in router {
+        if NOT_RECORDED.contains(&route.name()) {
+            recorded.add_route(route);
+            continue;
+        }
+        let call = route.call.clone();
+        recorded.add_route(ToolRoute::new_dyn(route.attr, move |context: ToolCallContext<'_, S>| {
+            let tool = context.name.to_string();
+            let arguments = context.arguments.clone().unwrap_or_default();
+            let future = call(context);
+            async move {
+                let outcome = future.await;
+                let fixture = Fixture::new(tool, arguments, &outcome);
+                let line = serde_json::to_string(&fixture).expect("fixtures serialize to JSON");
+                let mut file = recorder.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                if let Err(e) = writeln!(file, "{}", line) {
+                    tracing::warn!(tool = %fixture.tool, "Cannot record tool call: {}", e);
+                }
+                outcome
+            }
+            .boxed()
+        }));
+    }
+    recorded
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use serde_json::json;
+    use crate::common::compatibility_engine::{calculator, CompatibilityEngine};
+
+    /// Envelope fields that differ on every call
+    const VOLATILE_FIELDS: &[&str] = &["generated_at", "result_id"];
+
+    /// The outcome with volatile and client-shaped parts removed, for comparison
+    fn normalized(fixture: &Fixture) -> Value {
+        if let Some(error) = &fixture.error {
+            return json!({ "error": { "code": error["code"], "message": error["message"] } });
+        }
+        let result = fixture.result.as_ref().unwrap_or(&Value::Null);
+        // The first block is the result; later blocks are sampling summaries
+        let text = result["content"][0]["text"].as_str().unwrap_or_default();
+        let content = match serde_json::from_str::<Value>(text) {
+            Ok(mut value) => {
+                for field in VOLATILE_FIELDS {
+                    if let Some(slot) = value.get_mut(*field) {
+                        *slot = json!("<normalized>");
+                    }
+                }
+                value
+            }
+            Err(_) => Value::String(text.to_string()),
+        };
+        json!({ "is_error": result["isError"].as_bool().unwrap_or(false), "content": content })
+    }
+
+    /// Run a recorded call again, the way the tool router runs it
+    async fn replay(engine: &CompatibilityEngine, fixture: &Fixture) -> Fixture {
+        let calculator = calculator(&fixture.tool).unwrap_or_else(|| panic!("Unknown tool '{}' in fixture", fixture.tool));
+        let outcome = match calculator.validate(&fixture.arguments) {
+            Ok(()) => calculator.compute(engine, fixture.arguments.clone()).await,
+            Err(e) => Err(e),
+        };
+        Fixture::new(fixture.tool.clone(), fixture.arguments.clone(), &outcome)
+    }
+
+    /// Recorded files in `snapshots/fixtures/`, with their fixtures
+    fn recorded_files() -> Vec<(PathBuf, Vec<Fixture>)> {
+        let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("snapshots").join("fixtures");
+        let mut files: Vec<PathBuf> = std::fs::read_dir(&dir)
+            .map(|entries| entries.filter_map(|entry| entry.ok().map(|entry| entry.path())).collect())
+            .unwrap_or_default();
+        files.retain(|path| path.extension().is_some_and(|extension| extension == "jsonl"));
+        files.sort();
+        files
+            .into_iter()
+            .map(|path| {
+                let text = std::fs::read_to_string(&path).unwrap();
+                let fixtures = text
+                    .lines()
+                    .enumerate()
+                    .map(|(line, text)| {
+                        serde_json::from_str(text).unwrap_or_else(|e| panic!("{}:{}: {}", path.display(), line + 1, e))
+                    })
+                    .collect();
+                (path, fixtures)
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_recorded_fixtures_replay() {
+        let engine = CompatibilityEngine::new();
+        let files = recorded_files();
+        assert!(!files.is_empty(), "No recorded fixtures in snapshots/fixtures");
+        let update = std::env::var("UPDATE_COMPAT_SNAPSHOT").is_ok();
+        let mut changed = Vec::new();
+        for (path, fixtures) in &files {
+            let mut replayed = Vec::new();
+            for (line, fixture) in fixtures.iter().enumerate() {
+                let outcome = replay(&engine, fixture).await;
+                if normalized(&outcome) == normalized(fixture) {
+                    replayed.push(fixture.clone());
+                } else {
+                    changed.push(format!("{}:{} ({})", path.file_name().unwrap().to_string_lossy(), line + 1, fixture.tool));
+                    replayed.push(outcome);
+                }
+            }
+            // Rewrite through a temporary file, so other tests never read a partial file
+            if update && !changed.is_empty() {
+                let lines: Vec<String> = replayed.iter().map(|fixture| serde_json::to_string(fixture).unwrap()).collect();
+                let temporary = path.with_extension("jsonl.tmp");
+                std::fs::write(&temporary, lines.join("\n") + "\n").unwrap();
+                std::fs::rename(&temporary, path).unwrap();
+            }
+        }
+        assert!(
+            update || changed.is_empty(),
+            "Replayed calls no longer match their recording; rerun with UPDATE_COMPAT_SNAPSHOT=1 to refresh the fixtures if intended: {:?}",
+            changed
+        );
+    }
+
+    #[tokio::test]
+    async fn test_replay_detects_changed_result() {
+        let engine = CompatibilityEngine::new();
+        let mut fixture = recorded_files()
+            .into_iter()
+            .flat_map(|(_, fixtures)| fixtures)
+            .find(|fixture| fixture.tool == "calc_npv" && fixture.result.is_some())
+            .expect("a recorded calc_npv call");
+        let replayed = replay(&engine, &fixture).await;
+        assert_eq!(normalized(&replayed), normalized(&fixture));
+
+        fixture.arguments.insert("discount_rate".to_string(), json!(0.09));
+        assert_ne!(normalized(&replay(&engine, &fixture).await), normalized(&replayed));
+    }
+}
@@ -0,0 +1,104 @@
+//! Invariant checks on computed splits, so a bug yields an error instead of a wrong result.
+//!
+//! Tools that divide an amount (waterfall tranches, escrow releases) describe what must hold for
+//! their result with an [`Invariants`] builder: the parts add back up to the whole, and each part
+//! stays within its bounds. A violation is logged and the call is answered with an internal error
+//! result naming the broken invariant; the computed figures are withheld.
+//!
+//! The checks always run in debug builds. Release builds run them when
+//! `ENGINE_INVARIANT_CHECKS=true`.
+
+use std::sync::LazyLock;
+
+use super::metrics;
+
+/// Amounts are reported in cents, so parts may differ from the whole by rounding up to half a cent
+const CENT_TOLERANCE: f64 = 0.005 + 1e-9;
+
+/// Allowed difference when comparing with `amount`: half a cent, or float error on large amounts
+fn tolerance(amount: f64) -> f64 {
+    CENT_TOLERANCE.max(amount.abs() * f64::EPSILON * 8.0)
+}
+
+static ENABLED: LazyLock<bool> = LazyLock::new(|| {
+    cfg!(debug_assertions)
+        || std::env::var("ENGINE_INVARIANT_CHECKS")
+            .map(|value| matches!(value.trim().to_ascii_lowercase().as_str(), "true" | "1" | "on" | "yes"))
+            .unwrap_or(false)
+});
+
+/// Invariants of one tool result, checked by [`Invariants::verify`]
+#[must_use = "invariants are only checked by verify()"]
+pub struct Invariants {
+    tool: &'static str,
+    violations: Vec<String>,
+}
+
+impl Invariants {
+    pub fn new(tool: &'static str) -> Self {
+        Self { tool, violations: Vec::new() }
+    }
+
+    /// `parts` (allocations and any undistributed remainder) must add up to `total`
+    pub fn conserves(mut self, parts_name: &str, parts: &[f64], total_name: &str, total: f64) -> Self {
+        let sum: f64 = parts.iter().sum();
+        let difference = (sum - total).abs();
+        if difference.is_nan() || difference > tolerance(total) {
+            self.violations.push(format!("{} = {:.2}, expected {} = {:.2}", parts_name, sum, total_name, total));
+        }
+        self
+    }
+
+    /// `value` must lie in `min..=max`
+    pub fn within(mut self, name: &str, value: f64, min: f64, max: f64) -> Self {
+        if value.is_nan() || value < min - tolerance(min) || value > max + tolerance(max) {
+            self.violations.push(format!("{} = {:.2}, expected between {:.2} and {:.2}", name, value, min, max));
+        }
+        self
+    }
+
+    /// Ok if every invariant holds (or checks are off), otherwise the internal error message
+    pub fn verify(self) -> Result<(), String> {
+        if !*ENABLED || self.violations.is_empty() {
+            return Ok(());
+        }
+        metrics::increment_errors();
+        tracing::error!(tool = self.tool, violations = ?self.violations, "result failed its invariant checks");
+        Err(format!(
+            "Internal error: the {} result failed its invariant checks ({}) and was withheld. Please report this",
+            self.tool,
+            self.violations.join("; ")
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invariants() {
+        let held = Invariants::new("distribute_waterfall")
+            .conserves("senior + junior + equity", &[600.0, 300.0, 100.0], "cash_available", 1000.004)
+            .within("senior", 600.004, 0.0, 600.0)
+            .verify();
+        assert_eq!(held, Ok(()));
+
+        let error = Invariants::new("distribute_waterfall")
+            .conserves("senior + junior + equity", &[600.0, 300.0, 100.01], "cash_available", 1000.0)
+            .within("junior", f64::NAN, 0.0, 300.0)
+            .verify()
+            .unwrap_err();
+        assert!(error.contains("senior + junior + equity = 1000.01, expected cash_available = 1000.00"));
+        assert!(error.contains("junior = NaN, expected between 0.00 and 300.00"));
+
+        // Float error on large amounts is not a violation
+        let total = 1e15 / 3.0;
+        assert_eq!(
+            Invariants::new("check_escrow_release")
+                .conserves("parts", &[total / 3.0, total / 3.0, total / 3.0], "total", total)
+                .verify(),
+            Ok(())
+        );
+    }
+}
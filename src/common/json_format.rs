@@ -0,0 +1,172 @@
+//! Serialization of tool responses, with an optional canonical form for hashing and signing.
+//!
+//! By default responses are pretty-printed. Keys appear in the order the response types declare
+//! them, and maps (such as projected payloads) are sorted by key, so the same result always gives
+//! the same text.
+//!
+//! With `ENGINE_RESPONSE_FORMAT=canonical` responses are written as canonical JSON in the sense
+//! of RFC 8785 (JCS): keys sorted at every level, no whitespace, numbers in their shortest
+//! round-trip form without a trailing `.0` (`7140`, `0.05`, `1e+21`), and minimal string escapes.
+//! Equal results then give byte-identical text, whatever the platform or release.
+
+use std::fmt::Write;
+use std::sync::LazyLock;
+
+use serde::Serialize;
+use serde_json::Value;
+
+/// How response text is laid out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseFormat {
+    /// Indented, keys in declaration order
+    Pretty,
+    /// RFC 8785 canonical JSON
+    Canonical,
+}
+
+impl ResponseFormat {
+    fn parse(value: &str) -> Result<Self, String> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "pretty" => Ok(ResponseFormat::Pretty),
+            "canonical" => Ok(ResponseFormat::Canonical),
+            _ => Err("expected 'pretty' or 'canonical'".to_string()),
+        }
+    }
+}
+
+static RESPONSE_FORMAT: LazyLock<ResponseFormat> = LazyLock::new(|| {
+    let Ok(value) = std::env::var("ENGINE_RESPONSE_FORMAT") else {
+        return ResponseFormat::Pretty;
+    };
+    ResponseFormat::parse(&value).unwrap_or_else(|e| {
+        tracing::error!("Invalid ENGINE_RESPONSE_FORMAT '{}': {}; using pretty", value, e);
+        ResponseFormat::Pretty
+    })
+});
+
+/// The configured response format
+pub fn response_format() -> ResponseFormat {
+    *RESPONSE_FORMAT
+}
+
+/// Serialize a response in the configured format
+pub fn to_string<T: Serialize>(value: &T) -> serde_json::Result<String> {
+    match response_format() {
+        ResponseFormat::Pretty => serde_json::to_string_pretty(value),
+        ResponseFormat::Canonical => Ok(canonical(&serde_json::to_value(value)?)),
+    }
+}
+
+/// Canonical JSON text of a value (RFC 8785)
+pub fn canonical(value: &Value) -> String {
+    let mut out = String::new();
+    write_canonical(&mut out, value);
+    out
+}
+
+fn write_canonical(out: &mut String, value: &Value) {
+    match value {
+        Value::Null | Value::Bool(_) | Value::String(_) => {
+            out.push_str(&serde_json::to_string(value).expect("JSON scalars serialize"));
+        }
+        Value::Number(number) => match (number.as_i64(), number.as_u64(), number.as_f64()) {
+            (Some(integer), _, _) => write!(out, "{}", integer).expect("writing to a String"),
+            (_, Some(integer), _) => write!(out, "{}", integer).expect("writing to a String"),
+            (_, _, Some(float)) => out.push_str(&number_text(float)),
+            _ => out.push_str(&number.to_string()),
+        },
+        Value::Array(items) => {
+            out.push('[');
+            for (index, item) in items.iter().enumerate() {
+                if index > 0 {
+                    out.push(',');
+                }
+                write_canonical(out, item);
+            }
+            out.push(']');
+        }
+        Value::Object(object) => {
+            // RFC 8785 orders keys by UTF-16 code units, which differs from byte order only
+            // outside the Basic Multilingual Plane
+            let mut entries: Vec<(&String, &Value)> = object.iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+            out.push('{');
+            for (index, (key, item)) in entries.into_iter().enumerate() {
+                if index > 0 {
+                    out.push(',');
+                }
+                out.push_str(&serde_json::to_string(key).expect("JSON strings serialize"));
+                out.push(':');
+                write_canonical(out, item);
+            }
+            out.push('}');
+        }
+    }
+}
+
+/// A finite number the way ECMAScript's `Number.prototype.toString` writes it, as RFC 8785 requires
+fn number_text(value: f64) -> String {
+    if value == 0.0 {
+        return "0".to_string();
+    }
+    // Shortest round-trip digits and exponent, e.g. "-1.2345e-7"
+    let scientific = format!("{:e}", value.abs());
+    let (mantissa, exponent) = scientific.split_once('e').expect("LowerExp has an exponent");
+    let digits: String = mantissa.chars().filter(char::is_ascii_digit).collect();
+    let exponent: i32 = exponent.parse().expect("LowerExp exponent is an integer");
+    let sign = if value < 0.0 { "-" } else { "" };
+    // value = 0.digits × 10^point
+    let point = exponent + 1;
+    let length = digits.len() as i32;
+    let text = if length <= point && point <= 21 {
+        format!("{}{}", digits, "0".repeat((point - length) as usize))
+    } else if 0 < point && point <= 21 {
+        format!("{}.{}", &digits[..point as usize], &digits[point as usize..])
+    } else if -6 < point && point <= 0 {
+        format!("0.{}{}", "0".repeat(-point as usize), digits)
+    } else {
+        let exponent_sign = if exponent < 0 { "-" } else { "+" };
+        let (first, rest) = digits.split_at(1);
+        let fraction = if rest.is_empty() { String::new() } else { format!(".{}", rest) };
+        format!("{}{}e{}{}", first, fraction, exponent_sign, exponent.abs())
+    };
+    format!("{}{}", sign, text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_canonical_json() {
+        let value = json!({
+            "tax": 7140.0,
+            "errors": [],
+            "distribution": { "senior": 600.5, "equity": -0.0 },
+            "rate": 0.05,
+            "name": "Ana \"A\"\n",
+            "count": 3,
+        });
+        assert_eq!(
+            canonical(&value),
+            r#"{"count":3,"distribution":{"equity":0,"senior":600.5},"errors":[],"name":"Ana \"A\"\n","rate":0.05,"tax":7140}"#
+        );
+
+        for (number, text) in [
+            (1e21, "1e+21"),
+            (1e20, "100000000000000000000"),
+            (123456.789, "123456.789"),
+            (0.000001, "0.000001"),
+            (1e-7, "1e-7"),
+            (-2.5e-9, "-2.5e-9"),
+            (1.5e300, "1.5e+300"),
+            (1.0 / 3.0, "0.3333333333333333"),
+        ] {
+            assert_eq!(number_text(number), text);
+        }
+
+        assert_eq!(ResponseFormat::parse(" Canonical "), Ok(ResponseFormat::Canonical));
+        assert!(ResponseFormat::parse("compact").is_err());
+    }
+}
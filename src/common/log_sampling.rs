@@ -0,0 +1,110 @@
+//! Burst protection for log entries about rejected calls.
+//!
+//! An agent stuck in a loop can send the same malformed call thousands of times a minute. Entries
+//! about rejected calls (arguments that do not deserialize, validation errors) go through
+//! [`admit`], keyed by tool: the first `ENGINE_LOG_BURST` entries per tool in each
+//! `ENGINE_LOG_BURST_WINDOW_SECS` window (defaults 20 and 60) are logged, then one in every
+//! `ENGINE_LOG_SAMPLE_EVERY` (default 100; `1` logs every entry). Entries left out are counted in
+//! `compatibility.engine.suppressed_logs`, and the next logged entry for the tool carries the
+//! number suppressed since the previous one.
+//!
+//! Results, metrics and usage analytics are unaffected: only the log lines are sampled.
+
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use super::metrics;
+
+struct Bucket {
+    window_start: Instant,
+    /// Entries seen in the current window
+    seen: u64,
+    /// Entries left out since the last one logged
+    suppressed: u64,
+}
+
+struct Sampler {
+    burst: u64,
+    every: u64,
+    window: Duration,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl Sampler {
+    fn new(burst: u64, every: u64, window: Duration) -> Self {
+        Self { burst, every: every.max(1), window, buckets: Mutex::new(HashMap::new()) }
+    }
+
+    /// Some(entries suppressed before this one) if this entry is logged, None if it is left out
+    fn admit_at(&self, key: &str, now: Instant) -> Option<u64> {
+        let mut buckets = self.buckets.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let bucket = buckets
+            .entry(key.to_string())
+            .or_insert(Bucket { window_start: now, seen: 0, suppressed: 0 });
+        if now.duration_since(bucket.window_start) >= self.window {
+            bucket.window_start = now;
+            bucket.seen = 0;
+        }
+        bucket.seen += 1;
+        if bucket.seen <= self.burst || (bucket.seen - self.burst).is_multiple_of(self.every) {
+            Some(std::mem::take(&mut bucket.suppressed))
+        } else {
+            bucket.suppressed += 1;
+            None
+        }
+    }
+}
+
+fn env_u64(name: &str, default: u64) -> u64 {
+    let Ok(value) = std::env::var(name) else {
+        return default;
+    };
+    value.trim().parse::<u64>().unwrap_or_else(|e| {
+        tracing::error!("Invalid {} '{}': {}; using {}", name, value, e, default);
+        default
+    })
+}
+
+static SAMPLER: LazyLock<Sampler> = LazyLock::new(|| {
+    Sampler::new(
+        env_u64("ENGINE_LOG_BURST", 20),
+        env_u64("ENGINE_LOG_SAMPLE_EVERY", 100),
+        Duration::from_secs(env_u64("ENGINE_LOG_BURST_WINDOW_SECS", 60)),
+    )
+});
+
+/// Whether to log an entry about a rejected `tool` call: Some(entries suppressed since the last
+/// one logged), or None to leave it out
+pub fn admit(tool: &str) -> Option<u64> {
+    let admitted = SAMPLER.admit_at(tool, Instant::now());
+    if admitted.is_none() {
+        metrics::increment_suppressed_logs(tool);
+    }
+    admitted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_sampling() {
+        let sampler = Sampler::new(3, 4, Duration::from_secs(60));
+        let start = Instant::now();
+        let admitted: Vec<Option<u64>> = (0..12).map(|_| sampler.admit_at("calc_tax", start)).collect();
+        assert_eq!(
+            admitted,
+            [Some(0), Some(0), Some(0), None, None, None, Some(3), None, None, None, Some(3), None]
+        );
+        // Tools are counted separately
+        assert_eq!(sampler.admit_at("calc_npv", start), Some(0));
+
+        // A new window starts with a fresh burst and reports what was left out before it
+        assert_eq!(sampler.admit_at("calc_tax", start + Duration::from_secs(60)), Some(1));
+        assert_eq!(sampler.admit_at("calc_tax", start + Duration::from_secs(61)), Some(0));
+
+        let unsampled = Sampler::new(0, 1, Duration::from_secs(60));
+        assert!((0..5).all(|_| unsampled.admit_at("calc_tax", start) == Some(0)));
+    }
+}
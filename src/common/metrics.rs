@@ -2,22 +2,42 @@
 //!
 //! Call [`init`] once after [`opentelemetry::global::set_meter_provider`]. If the process never
 //! calls [`init`], recording functions are no-ops so unit tests can run without telemetry setup.
+//!
+//! Tool calls slower than `ENGINE_SLOW_REQUEST_THRESHOLD_MS` (default 1000, `0` disables) are
+//! logged as structured warnings whether or not metrics are initialized.
 
-use std::sync::OnceLock;
-use std::time::Instant;
+use std::sync::{LazyLock, OnceLock};
+use std::time::{Duration, Instant};
 
-use opentelemetry::global;
+use opentelemetry::{global, KeyValue};
 use opentelemetry::metrics::{Counter, Histogram, UpDownCounter};
+use serde::Serialize;
 
 struct EngineInstruments {
     requests_total: Counter<u64>,
     errors_total: Counter<u64>,
+    slow_requests_total: Counter<u64>,
     request_duration_seconds: Histogram<f64>,
     active_requests: UpDownCounter<i64>,
+    queued_requests: UpDownCounter<i64>,
+    queue_wait_seconds: Histogram<f64>,
+    rejected_requests_total: Counter<u64>,
+    suppressed_logs_total: Counter<u64>,
+    reference_mismatches_total: Counter<u64>,
 }
 
 static INSTRUMENTS: OnceLock<EngineInstruments> = OnceLock::new();
 
+const DEFAULT_SLOW_REQUEST_THRESHOLD_MS: u64 = 1000;
+
+static SLOW_REQUEST_THRESHOLD: LazyLock<Option<Duration>> = LazyLock::new(|| {
+    let millis = std::env::var("ENGINE_SLOW_REQUEST_THRESHOLD_MS")
+        .ok()
+        .and_then(|s| s.trim().parse::<u64>().ok())
+        .unwrap_or(DEFAULT_SLOW_REQUEST_THRESHOLD_MS);
+    (millis > 0).then(|| Duration::from_millis(millis))
+});
+
 /// Registers instruments against the current global meter provider.
 ///
 /// Must run exactly once, after the meter provider is installed.
@@ -32,6 +52,10 @@ pub fn init() {
             .u64_counter("compatibility.engine.errors")
             .with_description("Total number of errors in compatibility engine calculations")
             .build(),
+        slow_requests_total: meter
+            .u64_counter("compatibility.engine.slow_requests")
+            .with_description("Total number of calculation requests exceeding the slow request threshold")
+            .build(),
         request_duration_seconds: meter
             .f64_histogram("compatibility.engine.request.duration.seconds")
             .with_unit("s")
@@ -43,6 +67,27 @@ pub fn init() {
             .i64_up_down_counter("compatibility.engine.active_requests")
             .with_description("Number of active compatibility engine calculation requests")
             .build(),
+        queued_requests: meter
+            .i64_up_down_counter("compatibility.engine.queued_requests")
+            .with_description("Number of calculation requests waiting for a concurrency slot")
+            .build(),
+        queue_wait_seconds: meter
+            .f64_histogram("compatibility.engine.queue_wait.duration.seconds")
+            .with_unit("s")
+            .with_description("Time calculation requests waited for a concurrency slot in seconds")
+            .build(),
+        rejected_requests_total: meter
+            .u64_counter("compatibility.engine.rejected_requests")
+            .with_description("Total number of calculation requests rejected because no concurrency slot freed up in time")
+            .build(),
+        suppressed_logs_total: meter
+            .u64_counter("compatibility.engine.suppressed_logs")
+            .with_description("Total number of log entries about rejected calls left out by burst sampling")
+            .build(),
+        reference_mismatches_total: meter
+            .u64_counter("compatibility.engine.reference_mismatches")
+            .with_description("Total number of results that differed from the reference implementation")
+            .build(),
     };
     if INSTRUMENTS.set(instruments).is_err() {
         tracing::warn!("compatibility engine metrics already initialized; ignoring duplicate init");
@@ -53,32 +98,68 @@ fn instruments() -> Option<&'static EngineInstruments> {
     INSTRUMENTS.get()
 }
 
+/// Size in bytes of the JSON-serialized tool parameters, reported with slow requests.
+///
+/// The parameters are only serialized when the size is asked for, so calls that are not slow
+/// do not pay for it.
+pub fn param_size<T: Serialize>(params: &T) -> impl Fn() -> usize + '_ {
+    move || serde_json::to_vec(params).map(|bytes| bytes.len()).unwrap_or(0)
+}
+
 /// Timer that records request duration and active request count when dropped.
-pub struct RequestTimer {
-    start: Option<Instant>,
+///
+/// Calls exceeding the slow request threshold are logged with the tool name and parameter size
+/// and counted in `compatibility.engine.slow_requests`.
+pub struct RequestTimer<F: Fn() -> usize> {
+    tool: &'static str,
+    param_bytes: F,
+    start: Instant,
+    tracked: bool,
 }
 
-impl RequestTimer {
-    pub fn new() -> Self {
-        if let Some(i) = instruments() {
+impl<F: Fn() -> usize> RequestTimer<F> {
+    pub fn new(tool: &'static str, param_bytes: F) -> Self {
+        let tracked = if let Some(i) = instruments() {
             i.active_requests.add(1, &[]);
-            Self {
-                start: Some(Instant::now()),
-            }
+            true
         } else {
-            Self { start: None }
+            false
+        };
+        Self {
+            tool,
+            param_bytes,
+            start: Instant::now(),
+            tracked,
         }
     }
 }
 
-impl Drop for RequestTimer {
+impl<F: Fn() -> usize> Drop for RequestTimer<F> {
     fn drop(&mut self) {
+        let elapsed = self.start.elapsed();
+
+        if let Some(threshold) = *SLOW_REQUEST_THRESHOLD
+            && elapsed >= threshold
+        {
+            tracing::warn!(
+                tool = self.tool,
+                elapsed_ms = elapsed.as_millis() as u64,
+                threshold_ms = threshold.as_millis() as u64,
+                param_bytes = (self.param_bytes)(),
+                "slow tool call exceeded threshold"
+            );
+            if let Some(i) = instruments() {
+                i.slow_requests_total
+                    .add(1, &[KeyValue::new("tool", self.tool)]);
+            }
+        }
+
         let Some(i) = instruments() else {
             return;
         };
-        if let Some(start) = self.start.take() {
+        if self.tracked {
             i.request_duration_seconds
-                .record(start.elapsed().as_secs_f64(), &[]);
+                .record(elapsed.as_secs_f64(), &[]);
             i.active_requests.add(-1, &[]);
         }
     }
@@ -95,3 +176,52 @@ pub fn increment_errors() {
         i.errors_total.add(1, &[]);
     }
 }
+
+pub fn add_queued_requests(tool: &str, delta: i64) {
+    if let Some(i) = instruments() {
+        i.queued_requests.add(delta, &[KeyValue::new("tool", tool.to_string())]);
+    }
+}
+
+pub fn record_queue_wait(tool: &str, waited: Duration) {
+    if let Some(i) = instruments() {
+        i.queue_wait_seconds
+            .record(waited.as_secs_f64(), &[KeyValue::new("tool", tool.to_string())]);
+    }
+}
+
+pub fn increment_rejected_requests(tool: &str) {
+    if let Some(i) = instruments() {
+        i.rejected_requests_total.add(1, &[KeyValue::new("tool", tool.to_string())]);
+    }
+}
+
+pub fn increment_suppressed_logs(tool: &str) {
+    if let Some(i) = instruments() {
+        i.suppressed_logs_total.add(1, &[KeyValue::new("tool", tool.to_string())]);
+    }
+}
+
+pub fn increment_reference_mismatches(tool: &str) {
+    if let Some(i) = instruments() {
+        i.reference_mismatches_total.add(1, &[KeyValue::new("tool", tool.to_string())]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn test_param_size_measured_only_for_slow_calls() {
+        let measured = Cell::new(0);
+        drop(RequestTimer::new("calc_tax", || {
+            measured.set(measured.get() + 1);
+            0
+        }));
+        assert_eq!(measured.get(), 0);
+
+        assert_eq!(param_size(&serde_json::json!({ "income": 45000 }))(), r#"{"income":45000}"#.len());
+    }
+}
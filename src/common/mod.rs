@@ -1,3 +1,32 @@
+pub mod analytics;
+pub mod clock;
 pub mod compatibility_engine;
+pub mod concurrency;
+#[cfg(feature = "embedded-store")]
+pub mod embedded_store;
+pub mod explanation_graph;
+pub mod explanations;
+pub mod export;
+#[cfg(feature = "fault-injection")]
+pub mod fault_injection;
+pub mod fixtures;
+pub mod invariants;
+pub mod json_format;
+pub mod log_sampling;
 pub mod metrics;
+pub mod preflight;
+pub mod reference;
+pub mod reminders;
+pub mod resources;
+pub mod result_store;
+pub mod retention;
+pub mod sampling;
+#[cfg(feature = "embedded-store")]
+pub mod store_encryption;
 pub mod telemetry;
+
+#[cfg(test)]
+mod schema_compat;
+
+#[cfg(test)]
+mod wire_compat;
@@ -0,0 +1,164 @@
+//! `--preflight`: check the deployment once and exit, for use as an init container.
+//!
+//! The check loads the configuration (rules, fee schedules, exchange rates, price index and
+//! explanation templates), runs a known-good call through every tool, then runs the transport
+//! checks of the binary (for the HTTP server: `MCP_TRANSPORTS` parses, `BIND_ADDRESS` can be
+//! bound and the TLS certificates load). Settings the server would log and replace with defaults
+//! count as failures here. A table of results goes to stdout; the binary exits with status 1 if
+//! any check failed.
+
+use std::fmt::{self, Write as _};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+
+use super::compatibility_engine::{run_golden_calls, CompatibilityEngine};
+
+/// Result of one check: what was found on success, or why it failed
+pub struct Check {
+    name: String,
+    outcome: Result<String, String>,
+}
+
+impl Check {
+    pub fn new(name: impl Into<String>, outcome: Result<String, String>) -> Self {
+        Self { name: name.into(), outcome }
+    }
+}
+
+/// Warnings and errors logged by this crate, i.e. settings that were rejected or ignored
+#[derive(Clone, Default)]
+struct Problems(Arc<Mutex<Vec<String>>>);
+
+impl Problems {
+    /// Take the problems logged so far; Ok when there were none
+    fn take(&self, passed: &str) -> Result<String, String> {
+        let problems = self.drain();
+        if problems.is_empty() {
+            Ok(passed.to_string())
+        } else {
+            Err(problems.join("; "))
+        }
+    }
+
+    fn drain(&self) -> Vec<String> {
+        std::mem::take(&mut *self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner()))
+    }
+}
+
+/// Run `load` on this thread and collect the problems it logs, the same ones the configuration
+/// check fails on. Used to vet configuration before it is put in force.
+pub(crate) fn problems_while<T>(load: impl FnOnce() -> T) -> (T, Vec<String>) {
+    let problems = Problems::default();
+    let loaded = tracing::subscriber::with_default(tracing_subscriber::registry().with(problems.clone()), load);
+    (loaded, problems.drain())
+}
+
+impl<S: Subscriber> Layer<S> for Problems {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        if *metadata.level() > Level::WARN || !metadata.target().starts_with(env!("CARGO_CRATE_NAME")) {
+            return;
+        }
+        let mut message = MessageVisitor::default();
+        event.record(&mut message);
+        self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).push(message.into_text());
+    }
+}
+
+/// Event message followed by its fields as `name=value`
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+    fields: String,
+}
+
+impl MessageVisitor {
+    fn into_text(self) -> String {
+        format!("{}{}", self.message, self.fields)
+    }
+}
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{:?}", value);
+        } else {
+            let _ = write!(self.fields, " {}={:?}", field.name(), value);
+        }
+    }
+}
+
+/// Run the engine checks and `transport_checks`, print the table and return whether all passed
+pub async fn run(transport_checks: impl Future<Output = Vec<Check>>) -> bool {
+    let problems = Problems::default();
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_writer(std::io::stderr)
+                .with_filter(tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| "warn".into())),
+        )
+        .with(problems.clone())
+        .init();
+
+    let mut checks = Vec::new();
+
+    let engine = CompatibilityEngine::new();
+    checks.push(Check::new("configuration", problems.take("loaded")));
+
+    for outcome in run_golden_calls(&engine).await {
+        checks.push(Check::new(
+            format!("golden call {}", outcome.tool),
+            outcome.failure.map_or_else(|| Ok("ok".to_string()), Err),
+        ));
+    }
+    checks.push(Check::new("golden call log", problems.take("no warnings")));
+
+    checks.extend(transport_checks.await);
+
+    print_table(&checks);
+    checks.iter().all(|check| check.outcome.is_ok())
+}
+
+fn print_table(checks: &[Check]) {
+    let width = checks.iter().map(|check| check.name.len()).max().unwrap_or(0).max("CHECK".len());
+    println!("{:<width$}  RESULT  DETAIL", "CHECK");
+    for check in checks {
+        let (result, detail) = match &check.outcome {
+            Ok(detail) => ("PASS", detail),
+            Err(reason) => ("FAIL", reason),
+        };
+        println!("{:<width$}  {:<6}  {}", check.name, result, detail);
+    }
+    let failed = checks.iter().filter(|check| check.outcome.is_err()).count();
+    if failed == 0 {
+        println!("Preflight passed: {} checks", checks.len());
+    } else {
+        println!("Preflight failed: {} of {} checks", failed, checks.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_problems_collects_crate_warnings() {
+        let problems = Problems::default();
+        let subscriber = tracing_subscriber::registry().with(problems.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!("not a problem");
+            tracing::warn!(template = "calc_tax.summary", "ignoring invalid explanation template");
+            tracing::error!(target: "other_crate", "not ours");
+        });
+        assert_eq!(
+            problems.take("none"),
+            Err(r#"ignoring invalid explanation template template="calc_tax.summary""#.to_string())
+        );
+        assert_eq!(problems.take("none"), Ok("none".to_string()));
+    }
+}
@@ -0,0 +1,239 @@
+//! Dual-run verification: core calculations run again in a simpler reference implementation.
+//!
+//! The reference implementations here share no code with the engine. They work in whole cents
+//! on `i128`, with rates as integer parts per million, and round to the cent after every
+//! multiplication. After `calc_penalty`, `calc_tax`, `calc_transfer_tax` and `calc_property_tax`
+//! compute a result, [`check`] runs the matching reference and compares the two, allowing half a
+//! cent per rounding. A mismatch does not change the response: it is logged as a structured
+//! warning (tool, field, both values and the difference) and counted in
+//! `compatibility.engine.reference_mismatches`, so a drift between the engine and the rules it
+//! encodes shows up in monitoring.
+//!
+//! A reference only covers inputs it can represent exactly: amounts in whole cents and rates
+//! with at most six decimals. Configurations it does not model (tax cliffs, mid-year schedule
+//! changes, the minimum tax) are not checked.
+//!
+//! The checks always run in debug builds. Release builds run them when
+//! `ENGINE_REFERENCE_CHECKS=true`.
+
+use std::sync::LazyLock;
+
+use super::compatibility_engine::{SurchargeBase, SurchargeRule};
+use super::metrics;
+
+static ENABLED: LazyLock<bool> = LazyLock::new(|| {
+    cfg!(debug_assertions)
+        || std::env::var("ENGINE_REFERENCE_CHECKS")
+            .map(|value| matches!(value.trim().to_ascii_lowercase().as_str(), "true" | "1" | "on" | "yes"))
+            .unwrap_or(false)
+});
+
+/// Rates are carried as integer parts per million
+const PPM: i128 = 1_000_000;
+
+/// A reference result in cents, with the number of roundings to the cent it went through
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reference {
+    cents: i128,
+    roundings: u32,
+}
+
+impl Reference {
+    fn new(cents: i128) -> Self {
+        Self { cents, roundings: 0 }
+    }
+
+    pub fn amount(self) -> f64 {
+        self.cents as f64 / 100.0
+    }
+}
+
+/// `value` × `scale` as an integer, if it is one (up to float noise) and fits comfortably
+fn scaled(value: f64, scale: f64) -> Option<i128> {
+    let scaled = value * scale;
+    let rounded = scaled.round();
+    let exact = (scaled - rounded).abs() <= 1e-6 * rounded.abs().max(1.0);
+    (value.is_finite() && exact && rounded.abs() < 1e17).then_some(rounded as i128)
+}
+
+fn cents(amount: f64) -> Option<i128> {
+    scaled(amount, 100.0)
+}
+
+fn ppm(rate: f64) -> Option<i128> {
+    scaled(rate, PPM as f64)
+}
+
+/// `numerator` / `denominator` rounded half away from zero
+fn div_round(numerator: i128, denominator: i128) -> i128 {
+    let half = denominator / 2;
+    if numerator >= 0 { (numerator + half) / denominator } else { (numerator - half) / denominator }
+}
+
+/// `amount` × `rate`, rounded to the cent
+fn times_rate(amount: &mut Reference, cents: i128, rate: i128) -> i128 {
+    amount.roundings += 1;
+    div_round(cents * rate, PPM)
+}
+
+/// Reference `calc_penalty`: days × rate per day, capped, plus interest on the capped amount
+pub fn penalty(days_late: f64, rate_per_day: f64, cap: f64, interest_rate: f64) -> Option<Reference> {
+    // Days in thousandths, so half days are covered
+    let days = scaled(days_late, 1000.0)?;
+    let mut result = Reference::new(0);
+    result.roundings += 1;
+    let base = div_round(days * cents(rate_per_day)?, 1000);
+    let capped = base.min(cents(cap)?);
+    let interest = times_rate(&mut result, capped, ppm(interest_rate)?);
+    result.cents = capped + interest;
+    Some(result)
+}
+
+/// Progressive tax on `amount`: each rate applies to the part of it inside its bracket
+fn bracket_tax(result: &mut Reference, amount: i128, thresholds: &[i128], rates: &[i128]) -> i128 {
+    let mut tax = 0;
+    let mut lower = 0;
+    for (index, &rate) in rates.iter().enumerate() {
+        let upper = thresholds.get(index).copied().unwrap_or(i128::MAX);
+        if amount <= lower {
+            break;
+        }
+        tax += times_rate(result, amount.min(upper) - lower, rate);
+        lower = upper;
+    }
+    tax
+}
+
+/// Reference `calc_tax` without cliffs, schedule changes or minimum tax: prior losses offset
+/// income, brackets apply to the rest, then each surcharge in order
+pub fn income_tax(
+    income: f64,
+    loss_carryforward: f64,
+    thresholds: &[f64],
+    rates: &[f64],
+    surcharges: &[SurchargeRule],
+    surcharge_base: SurchargeBase,
+) -> Option<Reference> {
+    let income = cents(income)?.max(0);
+    let taxable = income - cents(loss_carryforward)?.min(income);
+    let thresholds: Vec<i128> = thresholds.iter().map(|&threshold| cents(threshold)).collect::<Option<_>>()?;
+    let rates: Vec<i128> = rates.iter().map(|&rate| ppm(rate)).collect::<Option<_>>()?;
+
+    let mut result = Reference::new(0);
+    let mut tax = bracket_tax(&mut result, taxable, &thresholds, &rates);
+    for rule in surcharges {
+        let (threshold, rate) = (cents(rule.threshold)?, ppm(rule.rate)?);
+        tax += match rule.base.unwrap_or(surcharge_base) {
+            SurchargeBase::Tax if tax > threshold => times_rate(&mut result, tax, rate),
+            SurchargeBase::Tax => 0,
+            SurchargeBase::Income => times_rate(&mut result, (taxable - threshold).max(0), rate),
+        };
+    }
+    result.cents = tax;
+    Some(result)
+}
+
+/// Reference `calc_transfer_tax` without cliffs: the whole price at the rate of its bracket
+/// (`slice`), or progressive brackets
+pub fn transfer_tax(price: f64, thresholds: &[f64], rates: &[f64], slice: bool) -> Option<Reference> {
+    let price = cents(price)?;
+    let thresholds: Vec<i128> = thresholds.iter().map(|&threshold| cents(threshold)).collect::<Option<_>>()?;
+    let rates: Vec<i128> = rates.iter().map(|&rate| ppm(rate)).collect::<Option<_>>()?;
+
+    let mut result = Reference::new(0);
+    result.cents = if slice {
+        // Thresholds are inclusive upper bounds
+        let bracket = thresholds.iter().position(|&threshold| price <= threshold).unwrap_or(thresholds.len());
+        times_rate(&mut result, price, *rates.get(bracket)?)
+    } else {
+        bracket_tax(&mut result, price, &thresholds, &rates)
+    };
+    Some(result)
+}
+
+/// Reference `calc_property_tax`: assessed value capped at the prior value plus the allowed
+/// increase, less any homestead exemption, times the millage
+pub fn property_tax(
+    assessed_value: f64,
+    prior_year_value: Option<f64>,
+    homestead_exemption: Option<f64>,
+    assessment_cap: f64,
+    millage: f64,
+) -> Option<Reference> {
+    let mut result = Reference::new(0);
+    let mut value = cents(assessed_value)?;
+    if let Some(prior) = prior_year_value {
+        let prior = cents(prior)?;
+        let limit = prior + times_rate(&mut result, prior, ppm(assessment_cap)?);
+        value = value.min(limit);
+    }
+    if let Some(exemption) = homestead_exemption {
+        value -= cents(exemption)?.min(value);
+    }
+    // Mills are thousandths
+    result.cents = times_rate(&mut result, value, ppm(millage / 1000.0)?);
+    Some(result)
+}
+
+/// The reference amount if `engine` differs from it by more than the roundings allow
+pub fn mismatch(engine: f64, reference: Reference) -> Option<f64> {
+    let expected = reference.amount();
+    let tolerance = 0.005 * f64::from(reference.roundings) + 1e-9 + engine.abs() * f64::EPSILON * 8.0;
+    ((engine - expected).abs() > tolerance || engine.is_nan()).then_some(expected)
+}
+
+/// Compare `field` of a `tool` result with the reference implementation, when checks are on and
+/// the reference covers the inputs. A mismatch is logged and counted; the result is unchanged.
+pub fn check(tool: &'static str, field: &str, engine: f64, reference: impl FnOnce() -> Option<Reference>) {
+    if !*ENABLED {
+        return;
+    }
+    let Some(expected) = reference().and_then(|reference| mismatch(engine, reference)) else {
+        return;
+    };
+    metrics::increment_reference_mismatches(tool);
+    tracing::warn!(
+        tool,
+        field,
+        engine,
+        reference = expected,
+        difference = engine - expected,
+        "result differs from the reference implementation"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reference_implementations() {
+        // 10 days × 5, capped at 1000, plus 5% interest
+        assert_eq!(penalty(10.0, 5.0, 1000.0, 0.05).map(Reference::amount), Some(52.5));
+        assert_eq!(penalty(500.0, 5.0, 1000.0, 0.0).map(Reference::amount), Some(1000.0));
+        // Amounts in fractions of a cent are not covered
+        assert_eq!(penalty(10.0, 0.333, 1000.0, 0.0), None);
+
+        // 15% up to 10000, 25% above
+        let rates = [0.15, 0.25];
+        assert_eq!(income_tax(30000.0, 5000.0, &[10000.0], &rates, &[], SurchargeBase::Tax).map(Reference::amount), Some(5250.0));
+        let surcharge = SurchargeRule { name: "Surcharge".to_string(), threshold: 5000.0, rate: 0.1, base: None };
+        assert_eq!(
+            income_tax(30000.0, 0.0, &[10000.0], &rates, &[surcharge], SurchargeBase::Tax).map(Reference::amount),
+            Some(7150.0)
+        );
+        assert_eq!(income_tax(-500.0, 0.0, &[10000.0], &rates, &[], SurchargeBase::Tax).map(Reference::amount), Some(0.0));
+
+        assert_eq!(transfer_tax(300000.0, &[250000.0], &[0.0, 0.05], true).map(Reference::amount), Some(15000.0));
+        assert_eq!(transfer_tax(300000.0, &[250000.0], &[0.0, 0.05], false).map(Reference::amount), Some(2500.0));
+
+        // 300K assessed, 250K prior capped at 3% = 257500, less 25000 exemption, at 15 mills
+        assert_eq!(property_tax(300000.0, Some(250000.0), Some(25000.0), 0.03, 15.0).map(Reference::amount), Some(3487.5));
+
+        let reference = penalty(10.0, 5.0, 1000.0, 0.05).unwrap();
+        assert_eq!(mismatch(52.5, reference), None);
+        assert_eq!(mismatch(52.509, reference), None);
+        assert_eq!(mismatch(52.52, reference), Some(52.5));
+        assert_eq!(mismatch(f64::NAN, reference), Some(52.5));
+    }
+}
@@ -0,0 +1,146 @@
+//! Deadline reminders for connected systems, sent as MCP logging notifications.
+//!
+//! With `ENGINE_DEADLINE_REMINDERS=true`, [`remind`] wraps every route of a tool router. When a
+//! successful result carries a `deadline` (YYYY-MM-DD) that has not passed and has not already
+//! been missed, as the appeal deadline of `check_appeal_deadline` does, the client is sent a
+//! `notifications/message` at level `notice` from the `deadlines` logger. Its data names the
+//! tool, the deadline, the days left and the request's `case_id` when one was given, so a
+//! connected system that routes server log messages can act on the deadline without reading
+//! every result. The result itself is unchanged.
+//!
+//! While reminders are on, the server declares the logging capability. A client that raises its
+//! level above `notice` with `logging/setLevel` gets no reminders for the rest of the session.
+//! Reminders go to the connected client at the time of the call; nothing is scheduled for later.
+
+use std::sync::{Arc, LazyLock, Mutex};
+
+use chrono::NaiveDate;
+use futures::FutureExt;
+use rmcp::handler::server::router::tool::{ToolRoute, ToolRouter};
+use rmcp::handler::server::tool::ToolCallContext;
+use rmcp::model::{CallToolResult, LoggingLevel, LoggingMessageNotificationParam};
+use serde_json::{json, Value};
+
+use super::clock;
+
+/// Logger name the reminders are sent under
+pub const LOGGER: &str = "deadlines";
+
+static ENABLED: LazyLock<bool> = LazyLock::new(|| {
+    std::env::var("ENGINE_DEADLINE_REMINDERS")
+        .map(|value| matches!(value.trim().to_ascii_lowercase().as_str(), "true" | "1" | "on" | "yes"))
+        .unwrap_or(false)
+});
+
+/// Whether `ENGINE_DEADLINE_REMINDERS` is on
+pub fn enabled() -> bool {
+    *ENABLED
+}
+
+/// Lowest level of log messages the client of a session asked for with `logging/setLevel`
+#[derive(Debug, Clone, Default)]
+pub struct SessionLevel(Arc<Mutex<Option<LoggingLevel>>>);
+
+impl SessionLevel {
+    pub fn set(&self, level: LoggingLevel) {
+        *self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(level);
+    }
+
+    fn admits(&self, level: LoggingLevel) -> bool {
+        let minimum = *self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        minimum.is_none_or(|minimum| level as u8 >= minimum as u8)
+    }
+}
+
+/// Wrap every route of `router` so results with an upcoming deadline send the session's client a
+/// reminder
+pub fn remind<S: Send + Sync + 'static>(router: ToolRouter<S>, level: SessionLevel) -> ToolRouter<S> {
+    if !*ENABLED {
+        return router;
+    }
+    let mut reminding = ToolRouter::new();
+    for route in router {
+        let call = route.call.clone();
+        let level = level.clone();
+        reminding.add_route(ToolRoute::new_dyn(route.attr, move |context: ToolCallContext<'_, S>| {
+            let tool = context.name.to_string();
+            let peer = context.request_context.peer.clone();
+            let future = call(context);
+            let level = level.clone();
+            async move {
+                let result = future.await?;
+                if level.admits(LoggingLevel::Notice)
+                    && let Some(data) = reminder(&tool, &result, clock::today())
+                {
+                    let notification =
+                        LoggingMessageNotificationParam { level: LoggingLevel::Notice, logger: Some(LOGGER.to_string()), data };
+                    if let Err(e) = peer.notify_logging_message(notification).await {
+                        tracing::warn!(tool, error = %e, "Could not send deadline reminder");
+                    }
+                }
+                Ok(result)
+            }
+            .boxed()
+        }));
+    }
+    reminding
+}
+
+/// Reminder data for the deadline in a successful result, if it is `today` or later and the
+/// result does not report it as missed (`within_window: false`)
+fn reminder(tool: &str, result: &CallToolResult, today: NaiveDate) -> Option<Value> {
+    if result.is_error.unwrap_or(false) {
+        return None;
+    }
+    let text = &result.content.first()?.raw.as_text()?.text;
+    let response: Value = serde_json::from_str(text).ok()?;
+    // Versioned envelopes carry the payload; legacy responses are the payload
+    let payload = response.get("payload").unwrap_or(&response);
+    if payload.get("within_window") == Some(&Value::Bool(false)) {
+        return None;
+    }
+    let deadline = NaiveDate::parse_from_str(payload.get("deadline")?.as_str()?, "%Y-%m-%d").ok()?;
+    let days_left = (deadline - today).num_days();
+    (days_left >= 0).then(|| {
+        json!({
+            "tool": tool,
+            "deadline": deadline.to_string(),
+            "days_left": days_left,
+            "case_id": response.pointer("/context/case_id"),
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use rmcp::model::Content;
+
+    use super::*;
+
+    #[test]
+    fn test_deadline_reminder() {
+        let today = NaiveDate::from_ymd_opt(2026, 3, 30).unwrap();
+        let result = |text: &str| CallToolResult::success(vec![Content::text(text)]);
+        let envelope = r#"{"schema_version": 2, "context": {"case_id": "A-17"}, "payload": {"within_window": true, "deadline": "2026-04-01"}}"#;
+        assert_eq!(
+            reminder("check_appeal_deadline", &result(envelope), today),
+            Some(json!({"tool": "check_appeal_deadline", "deadline": "2026-04-01", "days_left": 2, "case_id": "A-17"}))
+        );
+        // Legacy payloads have no context
+        let reminded = reminder("check_appeal_deadline", &result(r#"{"deadline": "2026-03-30"}"#), today).unwrap();
+        assert_eq!((reminded["days_left"].clone(), reminded["case_id"].clone()), (json!(0), Value::Null));
+
+        // Passed or missed deadlines, results without one and error results send nothing
+        assert_eq!(reminder("check_appeal_deadline", &result(r#"{"deadline": "2026-03-29"}"#), today), None);
+        let missed = r#"{"payload": {"within_window": false, "deadline": "2026-04-01"}}"#;
+        assert_eq!(reminder("check_appeal_deadline", &result(missed), today), None);
+        assert_eq!(reminder("calc_tax", &result(r#"{"payload": {"tax": 100.0}}"#), today), None);
+        let error = CallToolResult::error(vec![Content::text(r#"{"deadline": "2026-04-01"}"#)]);
+        assert_eq!(reminder("check_appeal_deadline", &error, today), None);
+
+        let level = SessionLevel::default();
+        assert!(level.admits(LoggingLevel::Notice));
+        level.set(LoggingLevel::Warning);
+        assert!(!level.admits(LoggingLevel::Notice) && level.admits(LoggingLevel::Error));
+    }
+}
@@ -0,0 +1,147 @@
+//! Calculations readable as MCP resources, for resource-oriented clients.
+//!
+//! Every calculator outside the admin category is published as a resource template
+//! `calc://<tool>{?argument,...}`, listing the tool's arguments. Reading a URI such as
+//! `calc://calc_tax?income=40000` runs the same calculator a tool call would, with the query
+//! parameters as its arguments, and returns the response text as `application/json`.
+//!
+//! Query values are percent-decoded. Arguments the tool declares as strings are passed as
+//! text; any other value is read as JSON when it parses (`40000`, `true`, `[-1000,300]`) and as
+//! text otherwise, so numbers sent as text still go through the tool's own parsing. Arguments
+//! that do not fit the tool are an invalid-params error, and so is a result the tool reports as
+//! an error, with the tool's message.
+
+use rmcp::model::{
+    AnnotateAble, JsonObject, RawResourceTemplate, ReadResourceResult, ResourceContents, ResourceTemplate,
+};
+use rmcp::ErrorData as McpError;
+use serde_json::{json, Value};
+
+use super::compatibility_engine::{calculator, calculators, Calculator, CompatibilityEngine, ToolCategory};
+
+/// URI scheme of calculation resources
+pub const SCHEME: &str = "calc";
+
+const MIME_TYPE: &str = "application/json";
+
+/// Calculators served as resources: all but the admin tools, which report server state
+fn served(calculator: &dyn Calculator) -> bool {
+    calculator.category() != ToolCategory::Admin
+}
+
+/// One template per served calculator, with its arguments as query parameters
+pub fn templates() -> Vec<ResourceTemplate> {
+    calculators()
+        .filter(|calculator| served(*calculator))
+        .map(|calculator| {
+            let tool = calculator.tool();
+            let arguments: Vec<String> = calculator
+                .input_schema()
+                .get("properties")
+                .and_then(Value::as_object)
+                .map(|properties| properties.keys().cloned().collect())
+                .unwrap_or_default();
+            let mut template = RawResourceTemplate::new(
+                format!("{}://{}{{?{}}}", SCHEME, tool.name, arguments.join(",")),
+                tool.name.to_string(),
+            )
+            .with_mime_type(MIME_TYPE);
+            if let Some(description) = &tool.description {
+                template = template.with_description(description.to_string());
+            }
+            template.no_annotation()
+        })
+        .collect()
+}
+
+/// The calculator and arguments named by a `calc://` URI
+fn parse_uri(uri: &str) -> Result<(&'static dyn Calculator, JsonObject), McpError> {
+    let not_found = || McpError::resource_not_found(format!("Unknown resource '{}'", uri), Some(json!({ "uri": uri })));
+    let rest = uri.strip_prefix(SCHEME).and_then(|rest| rest.strip_prefix("://")).ok_or_else(not_found)?;
+    let (tool, query) = rest.split_once('?').unwrap_or((rest, ""));
+    let calculator = calculator(tool).filter(|calculator| served(*calculator)).ok_or_else(not_found)?;
+
+    let pairs: Vec<(String, String)> = serde_urlencoded::from_str(query)
+        .map_err(|e| McpError::invalid_params(format!("Invalid query in '{}': {}", uri, e), None))?;
+    let schema = calculator.input_schema();
+    let mut arguments = JsonObject::new();
+    for (name, value) in pairs {
+        if arguments.contains_key(&name) {
+            return Err(McpError::invalid_params(format!("Argument '{}' is given more than once", name), None));
+        }
+        let value = argument_value(schema.get("properties").and_then(|properties| properties.get(&name)), value);
+        arguments.insert(name, value);
+    }
+    Ok((calculator, arguments))
+}
+
+/// A query value as the JSON argument the tool expects
+fn argument_value(schema: Option<&Value>, text: String) -> Value {
+    let declared_string = schema.and_then(|schema| schema.get("type")).is_some_and(|kind| match kind {
+        Value::String(kind) => kind == "string",
+        Value::Array(kinds) => kinds.contains(&json!("string")),
+        _ => false,
+    });
+    if declared_string {
+        return Value::String(text);
+    }
+    serde_json::from_str(&text).unwrap_or(Value::String(text))
+}
+
+/// Run the calculation named by `uri` and return its response
+pub async fn read(engine: &CompatibilityEngine, uri: &str) -> Result<ReadResourceResult, McpError> {
+    let (calculator, arguments) = parse_uri(uri)?;
+    calculator.validate(&arguments)?;
+    let result = calculator.compute(engine, arguments).await?;
+    let text = result
+        .content
+        .first()
+        .and_then(|content| content.raw.as_text())
+        .map(|text| text.text.clone())
+        .unwrap_or_default();
+    if result.is_error.unwrap_or(false) {
+        return Err(McpError::invalid_params(text, Some(json!({ "uri": uri }))));
+    }
+    Ok(ReadResourceResult::new(vec![ResourceContents::text(text, uri).with_mime_type(MIME_TYPE)]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contents_text(result: &ReadResourceResult) -> &str {
+        match &result.contents[0] {
+            ResourceContents::TextResourceContents { text, .. } => text,
+            other => panic!("expected text contents, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_calculation_resources() {
+        let templates = templates();
+        let tax = templates.iter().find(|template| template.raw.name == "calc_tax").expect("calc_tax template");
+        assert!(tax.raw.uri_template.starts_with("calc://calc_tax{?"));
+        assert!(tax.raw.uri_template.contains("income"));
+        assert!(!templates.iter().any(|template| template.raw.name == "get_usage_report"));
+
+        let engine = CompatibilityEngine::new();
+        let result = read(&engine, "calc://calc_tax?income=40000&fields=%5B%22tax%22%5D").await.unwrap();
+        let response: Value = serde_json::from_str(contents_text(&result)).unwrap();
+        assert_eq!(response["payload"], json!({ "tax": 7140.0 }));
+
+        // Numbers sent as text, strings and JSON lists
+        let result = read(&engine, "calc://check_voting?eligible_voters=100&turnout=70&yes_votes=50&proposal_type=general").await.unwrap();
+        assert!(contents_text(&result).contains("\"passes\": true"));
+        let result = read(&engine, "calc://calc_npv?cash_flows=%5B-1000,300,400,500%5D&discount_rate=0.08").await.unwrap();
+        assert!(contents_text(&result).contains("\"npv\""));
+
+        for uri in ["calc://get_usage_report", "calc://no_such_tool?x=1", "https://calc_tax?income=1"] {
+            assert_eq!(read(&engine, uri).await.unwrap_err().code, McpError::resource_not_found("", None).code, "{}", uri);
+        }
+        let missing = read(&engine, "calc://calc_tax").await.unwrap_err();
+        assert!(missing.message.contains("income"), "{}", missing.message);
+        let invalid = read(&engine, "calc://calc_tax?income=abc").await.unwrap_err();
+        assert!(invalid.message.contains("abc"), "{}", invalid.message);
+        assert!(read(&engine, "calc://calc_tax?income=1&income=2").await.unwrap_err().message.contains("more than once"));
+    }
+}
@@ -0,0 +1,354 @@
+//! In-memory store of recent tool results, so later calls can reference them.
+//!
+//! Every versioned response is kept under a random `result_id` that is echoed in the envelope.
+//! A later call can then pass `$result:<id>.<path>` for a number or boolean parameter, for
+//! example `$result:3f9a1c2e5b7d.tax` or `$result:3f9a1c2e5b7d.distributions.0.amount`. The
+//! reference is resolved from the stored payload before parsing, so agents can chain
+//! calculations without copying numbers through the model.
+//!
+//! The store keeps the most recent `ENGINE_RESULT_STORE_CAPACITY` results (default 1000).
+//! Setting it to `0` disables the store and result ids. With the `embedded-store` feature they
+//! are also kept in its database, so references still resolve after a restart. A `results`
+//! retention period (see [`super::retention`]) also deletes results older than it.
+//!
+//! Each result also records the tool that produced it and the `case_id` of the call's request
+//! context, so the results of a case can be exported or erased together
+//! (`export_subject_data`, `erase_subject_data`). The embedded store keeps only a keyed hash of
+//! the case id (see [`case_hash`]).
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{LazyLock, Mutex};
+
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde_json::Value;
+use sha2::Sha256;
+
+use super::clock;
+use super::retention::{self, Category};
+
+const DEFAULT_CAPACITY: usize = 1000;
+
+/// Prefix that marks a parameter value as a result reference
+pub const REFERENCE_PREFIX: &str = "$result:";
+
+/// A stored result
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredResult {
+    pub id: String,
+    pub tool: String,
+    pub stored_at: DateTime<Utc>,
+    pub payload: Value,
+}
+
+/// A stored result and the case it belongs to
+struct Entry {
+    result: StoredResult,
+    case_id: Option<String>,
+}
+
+struct ResultStore {
+    capacity: usize,
+    entries: HashMap<String, Entry>,
+    order: VecDeque<String>,
+}
+
+impl ResultStore {
+    fn insert(&mut self, result: StoredResult, case_id: Option<String>) {
+        if self.order.len() >= self.capacity
+            && let Some(oldest) = self.order.pop_front()
+        {
+            self.entries.remove(&oldest);
+        }
+        self.order.push_back(result.id.clone());
+        self.entries.insert(result.id.clone(), Entry { result, case_id });
+    }
+
+    /// The results of `case_id`, oldest first
+    fn case_results(&self, case_id: &str) -> Vec<StoredResult> {
+        self.order
+            .iter()
+            .filter_map(|id| self.entries.get(id))
+            .filter(|entry| entry.case_id.as_deref() == Some(case_id))
+            .map(|entry| entry.result.clone())
+            .collect()
+    }
+
+    /// Delete the results of `case_id`; returns their ids
+    fn erase_case(&mut self, case_id: &str) -> Vec<String> {
+        let erased: Vec<String> = self.case_results(case_id).into_iter().map(|result| result.id).collect();
+        for id in &erased {
+            self.entries.remove(id);
+        }
+        self.order.retain(|id| !erased.contains(id));
+        erased
+    }
+
+    /// Delete the results stored before `cutoff`, oldest first; returns how many
+    fn purge_before(&mut self, cutoff: DateTime<Utc>) -> usize {
+        let mut deleted = 0;
+        while self.order.front().and_then(|oldest| self.entries.get(oldest)).is_some_and(|entry| entry.result.stored_at < cutoff) {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+            deleted += 1;
+        }
+        deleted
+    }
+}
+
+static STORE: LazyLock<Mutex<ResultStore>> = LazyLock::new(|| {
+    let capacity = std::env::var("ENGINE_RESULT_STORE_CAPACITY")
+        .ok()
+        .and_then(|s| s.trim().parse::<usize>().ok())
+        .unwrap_or(DEFAULT_CAPACITY);
+    Mutex::new(ResultStore {
+        capacity,
+        entries: HashMap::new(),
+        order: VecDeque::new(),
+    })
+});
+
+/// Store a response payload and return its result id, or None when the store is disabled
+pub fn store(tool: &str, case_id: Option<&str>, payload: Value) -> Option<String> {
+    let mut store = STORE.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if store.capacity == 0 {
+        return None;
+    }
+    let id = uuid::Uuid::new_v4().simple().to_string()[..12].to_string();
+    #[cfg(feature = "embedded-store")]
+    let (capacity, saved) = (store.capacity, payload.clone());
+    let result = StoredResult { id: id.clone(), tool: tool.to_string(), stored_at: clock::now(), payload };
+    store.insert(result, case_id.map(str::to_string));
+    // Written after the lock is released, so other calls do not wait on the database
+    drop(store);
+    #[cfg(feature = "embedded-store")]
+    super::embedded_store::save_result(&id, tool, case_id, &saved, capacity);
+    Some(id)
+}
+
+/// Key of the case hashes: the embedded store's, which it keeps sealed under the store keys, or
+/// else a random key for this run, as results kept only in memory do not outlive it
+static CASE_HASH_KEY: LazyLock<[u8; 32]> = LazyLock::new(|| {
+    #[cfg(feature = "embedded-store")]
+    if let Some(key) = super::embedded_store::case_hash_key() {
+        return key;
+    }
+    rand::random()
+});
+
+/// Hex HMAC-SHA256 of a case id under the case hash key: how the embedded store and erasure
+/// receipts refer to a case without holding the identifier itself. The key is secret, so the
+/// identifier cannot be found by hashing candidate case ids.
+pub fn case_hash(case_id: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(&*CASE_HASH_KEY).expect("HMAC accepts keys of any length");
+    mac.update(case_id.as_bytes());
+    format!("{:x}", mac.finalize().into_bytes())
+}
+
+/// The results of a case found in the stores
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CaseResults {
+    /// Readable results, oldest first; a result both in memory and in the embedded store once
+    pub results: Vec<StoredResult>,
+    /// Results in the embedded store that could not be read, with the reason
+    pub unreadable: Vec<String>,
+    /// Whether the embedded store was searched
+    pub searched_stored: bool,
+}
+
+/// The results stored for `case_id`, in memory and in the embedded store in builds with it
+pub fn case_results(case_id: &str) -> CaseResults {
+    let results = STORE.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).case_results(case_id);
+    #[cfg_attr(not(feature = "embedded-store"), allow(unused_mut))]
+    let mut found = CaseResults { results, ..CaseResults::default() };
+    #[cfg(feature = "embedded-store")]
+    if let Some((stored, unreadable)) = super::embedded_store::case_results(&case_hash(case_id)) {
+        for result in stored {
+            if !found.results.iter().any(|known| known.id == result.id) {
+                found.results.push(result);
+            }
+        }
+        found.results.sort_by_key(|result| result.stored_at);
+        found.unreadable = unreadable;
+        found.searched_stored = true;
+    }
+    found
+}
+
+/// What erasing the results of a case deleted
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CaseErasure {
+    /// Ids of the results erased from any store, sorted
+    pub ids: Vec<String>,
+    /// Results deleted from memory
+    pub erased: usize,
+    /// Results deleted from the embedded store; None without it, or when the delete failed
+    pub erased_stored: Option<usize>,
+    /// Of those, the encrypted results whose data keys were destroyed with them
+    pub shredded: Option<usize>,
+}
+
+/// Delete the results stored for `case_id`, in memory and in the embedded store in builds with it
+pub fn erase_case(case_id: &str) -> CaseErasure {
+    #[cfg_attr(not(feature = "embedded-store"), allow(unused_mut))]
+    let mut ids = STORE.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).erase_case(case_id);
+    let mut erasure = CaseErasure { erased: ids.len(), ..CaseErasure::default() };
+    #[cfg(feature = "embedded-store")]
+    if let Some((stored, shredded)) = super::embedded_store::erase_case(&case_hash(case_id)) {
+        erasure.erased_stored = Some(stored.len());
+        erasure.shredded = Some(shredded);
+        ids.extend(stored);
+    }
+    ids.sort();
+    ids.dedup();
+    erasure.ids = ids;
+    erasure
+}
+
+/// Delete the results stored before `cutoff`: how many were deleted from memory, and from the
+/// embedded store in builds with it
+pub fn purge_before(cutoff: DateTime<Utc>) -> (usize, Option<usize>) {
+    let deleted = STORE.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).purge_before(cutoff);
+    #[cfg(feature = "embedded-store")]
+    let deleted_stored = super::embedded_store::purge_results(cutoff);
+    #[cfg(not(feature = "embedded-store"))]
+    let deleted_stored = None;
+    (deleted, deleted_stored)
+}
+
+/// Resolve a `$result:<id>.<path>` reference to the referenced value as text.
+///
+/// Returns None when `raw` is not a reference, so the caller parses it as usual.
+pub fn resolve(raw: &str) -> Option<Result<String, String>> {
+    let reference = raw.trim().strip_prefix(REFERENCE_PREFIX)?;
+    Some(lookup(reference))
+}
+
+fn lookup(reference: &str) -> Result<String, String> {
+    let (id, path) = reference
+        .split_once('.')
+        .ok_or_else(|| format!("result reference '{}' must be <id>.<field>", reference))?;
+    // Results past their retention period are gone even before the next purge
+    let not_before = retention::cutoff(Category::Results, clock::now());
+    let payload = STORE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .entries
+        .get(id)
+        .filter(|entry| not_before.is_none_or(|cutoff| entry.result.stored_at >= cutoff))
+        .map(|entry| entry.result.payload.clone());
+    #[cfg(feature = "embedded-store")]
+    let payload = payload.or_else(|| super::embedded_store::load_result(id, not_before));
+    let payload = payload.ok_or_else(|| format!("result '{}' not found (it may have expired)", id))?;
+    let value = select(&payload, path).ok_or_else(|| format!("result '{}' has no field '{}'", id, path))?;
+    match value {
+        Value::Number(number) => Ok(number.to_string()),
+        Value::Bool(flag) => Ok(flag.to_string()),
+        Value::String(text) => Ok(text.clone()),
+        _ => Err(format!("result '{}' field '{}' is not a number, boolean or string", id, path)),
+    }
+}
+
+/// Follow a dot-separated path through objects and (by index) arrays
+fn select<'a>(payload: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(payload, |node, segment| match node {
+        Value::Object(map) => map.get(segment),
+        Value::Array(items) => segment.parse::<usize>().ok().and_then(|index| items.get(index)),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use sha2::Digest;
+
+    #[test]
+    fn test_resolve_reference() {
+        let id = store("calc_tax", None, json!({
+            "tax": 7140.0,
+            "eligible": true,
+            "distributions": [{ "amount": 600000 }],
+        }))
+        .unwrap();
+
+        assert_eq!(resolve("12000"), None);
+        assert_eq!(resolve(&format!("$result:{}.tax", id)), Some(Ok("7140.0".to_string())));
+        assert_eq!(resolve(&format!(" $result:{}.eligible ", id)), Some(Ok("true".to_string())));
+        assert_eq!(
+            resolve(&format!("$result:{}.distributions.0.amount", id)),
+            Some(Ok("600000".to_string()))
+        );
+
+        let error = resolve(&format!("$result:{}.distributions", id)).unwrap().unwrap_err();
+        assert!(error.contains("not a number"));
+        let error = resolve(&format!("$result:{}.penalty", id)).unwrap().unwrap_err();
+        assert!(error.contains("has no field 'penalty'"));
+        let error = resolve("$result:unknown.tax").unwrap().unwrap_err();
+        assert!(error.contains("not found"));
+        let error = resolve(&format!("$result:{}", id)).unwrap().unwrap_err();
+        assert!(error.contains("must be <id>.<field>"));
+    }
+
+    #[test]
+    fn test_store_evicts_oldest() {
+        let mut store = ResultStore {
+            capacity: 2,
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+        };
+        for id in ["a", "b", "c"] {
+            store.insert(result(id, clock::now()), None);
+        }
+        assert!(!store.entries.contains_key("a"));
+        assert_eq!(store.order, ["b", "c"]);
+    }
+
+    #[test]
+    fn test_store_purges_expired() {
+        let mut store = ResultStore {
+            capacity: 10,
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+        };
+        let now = clock::now();
+        for (id, days_old) in [("a", 40), ("b", 31), ("c", 29), ("d", 0)] {
+            store.insert(result(id, now - chrono::TimeDelta::days(days_old)), None);
+        }
+        assert_eq!(store.purge_before(now - chrono::TimeDelta::days(30)), 2);
+        assert_eq!(store.order, ["c", "d"]);
+        assert!(!store.entries.contains_key("b") && store.entries.contains_key("c"));
+        assert_eq!(store.purge_before(now - chrono::TimeDelta::days(30)), 0);
+    }
+
+    #[test]
+    fn test_store_erases_case() {
+        let mut store = ResultStore {
+            capacity: 10,
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+        };
+        for (id, case_id) in [("a", Some("CASE-1")), ("b", Some("CASE-2")), ("c", None), ("d", Some("CASE-1"))] {
+            store.insert(result(id, clock::now()), case_id.map(str::to_string));
+        }
+        let ids = |results: Vec<StoredResult>| results.into_iter().map(|result| result.id).collect::<Vec<_>>();
+        assert_eq!(ids(store.case_results("CASE-1")), ["a", "d"]);
+        assert_eq!(store.erase_case("CASE-1"), ["a", "d"]);
+        assert_eq!(store.order, ["b", "c"]);
+        assert!(store.case_results("CASE-1").is_empty());
+        assert_eq!(store.erase_case("CASE-1"), Vec::<String>::new());
+        assert_eq!(ids(store.case_results("CASE-2")), ["b"]);
+
+        assert_eq!(case_hash("CASE-1").len(), 64);
+        assert_eq!(case_hash("CASE-1"), case_hash("CASE-1"));
+        assert_ne!(case_hash("CASE-1"), case_hash("CASE-2"));
+        // Keyed, so not the plain digest anyone could compute from a guessed case id
+        assert_ne!(case_hash("CASE-1"), format!("{:x}", Sha256::digest("CASE-1")));
+    }
+
+    fn result(id: &str, stored_at: DateTime<Utc>) -> StoredResult {
+        StoredResult { id: id.to_string(), tool: "calc_tax".to_string(), stored_at, payload: json!({}) }
+    }
+}
@@ -0,0 +1,173 @@
+//! Retention periods for the data the server keeps, and the purge that enforces them.
+//!
+//! `ENGINE_RETENTION_DAYS` sets how long each category of data is kept, as `category=days` pairs
+//! such as `results=30,usage=365`:
+//!
+//! - `results`: the `$result` payloads, in memory and in the embedded store. A result older than
+//!   its period is deleted, and cannot be referenced even before the next purge runs.
+//! - `usage`: the usage counts behind `get_usage_report`. They are totals, not records of single
+//!   calls, so they cannot expire call by call: once counting began longer ago than the period,
+//!   the counts are cleared and counting starts again.
+//!
+//! A category without a period is kept as before: results until newer ones push them out of
+//! `ENGINE_RESULT_STORE_CAPACITY`, usage counts until restart, or indefinitely in the embedded
+//! store. The server keeps no audit log or case records, so there are no other categories. An
+//! invalid setting is logged as an error, which fails `--preflight`, and nothing is purged.
+//!
+//! While a period is set, a background task purges expired data every hour and logs what it
+//! deleted. The `purge_expired_data` admin tool runs the same purge on demand and reports it.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use chrono::{DateTime, TimeDelta, Utc};
+
+use super::{analytics, clock, result_store};
+
+/// Time between background purges
+const PURGE_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// A kind of data with its own retention period
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Category {
+    Results,
+    Usage,
+}
+
+impl Category {
+    pub const ALL: [Category; 2] = [Category::Results, Category::Usage];
+
+    fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "results" => Ok(Category::Results),
+            "usage" => Ok(Category::Usage),
+            "audit" | "cases" => Err(format!("the server keeps no {} data; categories are 'results' and 'usage'", name)),
+            _ => Err(format!("unknown category '{}'; categories are 'results' and 'usage'", name)),
+        }
+    }
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Category::Results => "results",
+            Category::Usage => "usage",
+        })
+    }
+}
+
+static PERIODS: LazyLock<BTreeMap<Category, u32>> = LazyLock::new(|| {
+    std::env::var("ENGINE_RETENTION_DAYS")
+        .ok()
+        .map(|value| parse_periods(&value).unwrap_or_else(|e| {
+            tracing::error!("Invalid ENGINE_RETENTION_DAYS '{}': {}; no data is purged", value, e);
+            BTreeMap::new()
+        }))
+        .unwrap_or_default()
+});
+
+/// Parse `category=days` pairs separated by commas
+fn parse_periods(value: &str) -> Result<BTreeMap<Category, u32>, String> {
+    let mut periods = BTreeMap::new();
+    for pair in value.split(',').map(str::trim).filter(|pair| !pair.is_empty()) {
+        let (name, days) = pair.split_once('=').ok_or_else(|| format!("expected category=days, got '{}'", pair))?;
+        let category = Category::parse(name.trim())?;
+        let days = match days.trim().parse::<u32>() {
+            Ok(days) if days > 0 => days,
+            _ => return Err(format!("days for '{}' must be a positive integer", category)),
+        };
+        if periods.insert(category, days).is_some() {
+            return Err(format!("'{}' is given twice", category));
+        }
+    }
+    Ok(periods)
+}
+
+/// Read the retention periods now, so invalid settings are reported at startup
+pub fn load() {
+    LazyLock::force(&PERIODS);
+}
+
+/// Data of `category` created before this time has expired; None when it has no period
+pub fn cutoff(category: Category, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    PERIODS.get(&category).map(|days| now - TimeDelta::days((*days).into()))
+}
+
+/// What a purge deleted from one category
+#[derive(Debug, Clone, PartialEq)]
+pub struct Purged {
+    pub category: Category,
+    pub retention_days: u32,
+    pub cutoff: DateTime<Utc>,
+    /// Results, or tools whose usage counts were cleared, deleted from memory
+    pub deleted: usize,
+    /// The same deleted from the embedded store; None without it
+    pub deleted_stored: Option<usize>,
+}
+
+/// Delete the data of every category with a period that expired before `now`
+pub fn purge(now: DateTime<Utc>) -> Vec<Purged> {
+    PERIODS
+        .iter()
+        .map(|(&category, &retention_days)| {
+            let cutoff = now - TimeDelta::days(retention_days.into());
+            let (deleted, deleted_stored) = match category {
+                Category::Results => result_store::purge_before(cutoff),
+                Category::Usage => analytics::purge_before(cutoff, now),
+            };
+            Purged { category, retention_days, cutoff, deleted, deleted_stored }
+        })
+        .collect()
+}
+
+/// Purge expired data every hour in the background, if any retention period is set. Must be
+/// called from within the Tokio runtime.
+pub fn spawn_purge_task() {
+    if PERIODS.is_empty() {
+        return;
+    }
+    tokio::spawn(async {
+        let mut interval = tokio::time::interval(PURGE_INTERVAL);
+        loop {
+            interval.tick().await;
+            for purged in purge(clock::now()) {
+                if purged.deleted > 0 || purged.deleted_stored.is_some_and(|deleted| deleted > 0) {
+                    tracing::info!(
+                        category = %purged.category,
+                        retention_days = purged.retention_days,
+                        cutoff = %purged.cutoff.to_rfc3339(),
+                        deleted = purged.deleted,
+                        deleted_stored = ?purged.deleted_stored,
+                        "Expired data purged"
+                    );
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_retention_periods() {
+        assert_eq!(
+            parse_periods(" results = 30, usage=365 ,"),
+            Ok(BTreeMap::from([(Category::Results, 30), (Category::Usage, 365)]))
+        );
+        assert_eq!(parse_periods(""), Ok(BTreeMap::new()));
+        for (value, error) in [
+            ("results", "expected category=days"),
+            ("results=0", "positive integer"),
+            ("usage=a year", "positive integer"),
+            ("results=30,results=60", "given twice"),
+            ("audit=3650", "keeps no audit data"),
+            ("logs=7", "unknown category 'logs'"),
+        ] {
+            assert!(parse_periods(value).unwrap_err().contains(error), "{}", value);
+        }
+    }
+}
@@ -0,0 +1,170 @@
+//! Optional natural-language summaries of tool results, written by the client's model.
+//!
+//! [`summarize`] wraps every route of a tool router. When `ENGINE_SAMPLING_SUMMARY=true` and the
+//! client advertised the sampling capability during initialize, a successful result is sent back
+//! to the client in a `sampling/createMessage` request asking for a one-paragraph summary, and the
+//! reply is appended to the result as an extra text block. The calculation itself stays on the
+//! server: the first content block is the structured result, unchanged.
+//!
+//! A client without sampling, a refused or failed request, or a reply later than
+//! `ENGINE_SAMPLING_TIMEOUT_SECS` (default 20) leaves the result as it is.
+
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use futures::FutureExt;
+use rmcp::handler::server::router::tool::{ToolRoute, ToolRouter};
+use rmcp::handler::server::tool::ToolCallContext;
+use rmcp::model::{CallToolResult, Content, CreateMessageRequestParams, CreateMessageResult};
+use rmcp::{Peer, RoleServer};
+use serde_json::{json, Value};
+
+/// Prefix of the appended block, so agents can tell the summary from the structured result
+pub const SUMMARY_PREFIX: &str = "Summary: ";
+
+/// Longest summary requested from the client's model, in tokens
+const SUMMARY_MAX_TOKENS: u32 = 300;
+
+const SYSTEM_PROMPT: &str = "You summarize the result of a calculation for a member of the public. \
+    Write one short paragraph in plain language. Use only the figures in the result; do not \
+    recalculate, round differently or add advice.";
+
+struct SamplingConfig {
+    enabled: bool,
+    timeout: Duration,
+}
+
+static CONFIG: LazyLock<SamplingConfig> = LazyLock::new(|| {
+    let enabled = std::env::var("ENGINE_SAMPLING_SUMMARY")
+        .map(|value| matches!(value.trim().to_ascii_lowercase().as_str(), "true" | "1" | "on" | "yes"))
+        .unwrap_or(false);
+    let timeout = std::env::var("ENGINE_SAMPLING_TIMEOUT_SECS")
+        .ok()
+        .map(|value| value.trim().parse::<u64>().unwrap_or_else(|e| {
+            tracing::error!("Invalid ENGINE_SAMPLING_TIMEOUT_SECS '{}': {}; using 20", value, e);
+            20
+        }))
+        .unwrap_or(20);
+    SamplingConfig { enabled, timeout: Duration::from_secs(timeout) }
+});
+
+/// Whether `ENGINE_SAMPLING_SUMMARY` is on
+pub fn enabled() -> bool {
+    CONFIG.enabled
+}
+
+/// Wrap every route of `router` so successful results get a summary from the client's model
+pub fn summarize<S: Send + Sync + 'static>(router: ToolRouter<S>) -> ToolRouter<S> {
+    if !CONFIG.enabled {
+        return router;
+    }
+    let mut summarized = ToolRouter::new();
+    for route in router {
+        let call = route.call.clone();
+        summarized.add_route(ToolRoute::new_dyn(route.attr, move |context: ToolCallContext<'_, S>| {
+            let tool = context.name.to_string();
+            let peer = context.request_context.peer.clone();
+            let future = call(context);
+            async move {
+                let mut result = future.await?;
+                if let Some(summary) = request_summary(&peer, &tool, &result).await {
+                    result.content.push(Content::text(format!("{}{}", SUMMARY_PREFIX, summary)));
+                }
+                Ok(result)
+            }
+            .boxed()
+        }));
+    }
+    summarized
+}
+
+/// Summary of a successful result from the client's model, if the client supports sampling
+async fn request_summary(peer: &Peer<RoleServer>, tool: &str, result: &CallToolResult) -> Option<String> {
+    if result.is_error.unwrap_or(false) {
+        return None;
+    }
+    let sampling = peer.peer_info().is_some_and(|info| info.capabilities.sampling.is_some());
+    if !sampling {
+        return None;
+    }
+    let text = result.content.first()?.raw.as_text()?.text.as_str();
+    let request = summary_request(tool, text)?;
+    match tokio::time::timeout(CONFIG.timeout, peer.create_message(request)).await {
+        Ok(Ok(reply)) => summary_text(&reply),
+        Ok(Err(e)) => {
+            tracing::warn!(tool, "Sampling summary request failed: {}", e);
+            None
+        }
+        Err(_) => {
+            tracing::warn!(tool, "Sampling summary timed out after {:?}", CONFIG.timeout);
+            None
+        }
+    }
+}
+
+/// `sampling/createMessage` request asking for a summary of `result_text`
+fn summary_request(tool: &str, result_text: &str) -> Option<CreateMessageRequestParams> {
+    let request = json!({
+        "messages": [{
+            "role": "user",
+            "content": {
+                "type": "text",
+                "text": format!("Summarize this result of the {} tool:\n\n{}", tool, result_text),
+            },
+        }],
+        "systemPrompt": SYSTEM_PROMPT,
+        "includeContext": "none",
+        "temperature": 0.2,
+        "maxTokens": SUMMARY_MAX_TOKENS,
+    });
+    serde_json::from_value(request)
+        .map_err(|e| tracing::error!(tool, "Cannot build sampling request: {}", e))
+        .ok()
+}
+
+/// Text of the model's reply; the content may be a single block or a list of blocks
+fn summary_text(reply: &CreateMessageResult) -> Option<String> {
+    let reply = serde_json::to_value(reply).ok()?;
+    let content = match &reply["content"] {
+        Value::Array(blocks) => blocks.iter().find(|block| block["type"] == "text")?,
+        block => block,
+    };
+    let text = content["text"].as_str()?.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_request() {
+        let request = summary_request("calc_tax", r#"{"payload":{"tax":7140.0}}"#).unwrap();
+        let request = serde_json::to_value(request).unwrap();
+        assert_eq!(request["maxTokens"], SUMMARY_MAX_TOKENS);
+        assert_eq!(request["includeContext"], "none");
+        assert_eq!(request["messages"][0]["role"], "user");
+        let text = request["messages"][0]["content"]["text"].as_str().unwrap();
+        assert!(text.contains("calc_tax"));
+        assert!(text.contains(r#""tax":7140.0"#));
+    }
+
+    #[test]
+    fn test_summary_text() {
+        let reply: CreateMessageResult = serde_json::from_value(json!({
+            "model": "test-model",
+            "role": "assistant",
+            "content": { "type": "text", "text": "  You owe 7,140 in tax.  " },
+        }))
+        .unwrap();
+        assert_eq!(summary_text(&reply).as_deref(), Some("You owe 7,140 in tax."));
+
+        let reply: CreateMessageResult = serde_json::from_value(json!({
+            "model": "test-model",
+            "role": "assistant",
+            "content": { "type": "text", "text": " " },
+        }))
+        .unwrap();
+        assert_eq!(summary_text(&reply), None);
+    }
+}
@@ -0,0 +1,329 @@
+//! Backward-compatibility checks for tool input schemas and response shapes.
+//!
+//! The current shapes are flattened into `path -> {type, required}` maps and compared against
+//! `snapshots/tool_shapes.json`. Removing a tool or field, changing a field type incompatibly,
+//! or adding a required input field is a breaking change. Intentional breaking changes must be
+//! listed in `snapshots/compat_allowlist.json`. Run the tests with `UPDATE_COMPAT_SNAPSHOT=1`
+//! to rewrite the snapshot after reviewing a change.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+
+use rmcp::schemars::{self, JsonSchema};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::compatibility_engine::*;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct FieldShape {
+    #[serde(rename = "type")]
+    ty: String,
+    required: bool,
+}
+
+type Shape = BTreeMap<String, FieldShape>;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct ToolShape {
+    input: Shape,
+    output: Shape,
+}
+
+type ToolShapes = BTreeMap<String, ToolShape>;
+
+fn snapshot_path(file: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("snapshots").join(file)
+}
+
+fn output_schema<T: JsonSchema>() -> Value {
+    serde_json::to_value(schemars::schema_for!(ResponseEnvelope<T>)).unwrap()
+}
+
+/// Response payload schema for each tool, as wrapped by the response envelope
+fn response_schema(tool: &str) -> Option<Value> {
+    Some(match tool {
+        "calc_penalty" => output_schema::<CalcPenaltyResponse>(),
+        "calc_tax" => output_schema::<CalcTaxResponse>(),
+        "check_voting" => output_schema::<CheckVotingResponse>(),
+        "validate_vote_record" => output_schema::<ValidateVoteRecordResponse>(),
+        "distribute_waterfall" => output_schema::<DistributeWaterfallResponse>(),
+        "check_covenants" => output_schema::<CheckCovenantsResponse>(),
+        "calc_npv" => output_schema::<CalcNpvResponse>(),
+        "calc_irr" => output_schema::<CalcIrrResponse>(),
+        "check_escrow_release" => output_schema::<CheckEscrowReleaseResponse>(),
+        "check_housing_grant" => output_schema::<CheckHousingGrantResponse>(),
+        "calc_capital_gains" => output_schema::<CalcCapitalGainsResponse>(),
+        "calc_inheritance_tax" => output_schema::<CalcInheritanceTaxResponse>(),
+        "calc_social_contributions" => output_schema::<CalcSocialContributionsResponse>(),
+        "calc_property_tax" => output_schema::<CalcPropertyTaxResponse>(),
+        "calc_transfer_tax" => output_schema::<CalcTransferTaxResponse>(),
+        "calc_fee" => output_schema::<CalcFeeResponse>(),
+        "index_amount" => output_schema::<IndexAmountResponse>(),
+        "convert_currency" => output_schema::<ConvertCurrencyResponse>(),
+        "get_intake_schema" => output_schema::<GetIntakeSchemaResponse>(),
+        "prescreen" => output_schema::<PrescreenResponse>(),
+        "get_usage_report" => output_schema::<GetUsageReportResponse>(),
+        "list_tools_by_category" => output_schema::<ListToolsByCategoryResponse>(),
+        "describe_capabilities" => output_schema::<DescribeCapabilitiesResponse>(),
+        "get_recent_schema_failures" => output_schema::<GetRecentSchemaFailuresResponse>(),
+        "upsert_profile" => output_schema::<UpsertProfileResponse>(),
+        "diff_profiles" => output_schema::<DiffProfilesResponse>(),
+        "evaluate_ranges" => output_schema::<EvaluateRangesResponse>(),
+        "simulate_uncertainty" => output_schema::<SimulateUncertaintyResponse>(),
+        "check_appeal_deadline" => output_schema::<CheckAppealDeadlineResponse>(),
+        "get_required_documents" => output_schema::<GetRequiredDocumentsResponse>(),
+        "check_voting_bulk" => output_schema::<CheckVotingBulkResponse>(),
+        "project_quorum" => output_schema::<ProjectQuorumResponse>(),
+        "stress_config" => output_schema::<StressConfigResponse>(),
+        "verify_store_integrity" => output_schema::<VerifyStoreIntegrityResponse>(),
+        "purge_expired_data" => output_schema::<PurgeExpiredDataResponse>(),
+        "export_subject_data" => output_schema::<ExportSubjectDataResponse>(),
+        "erase_subject_data" => output_schema::<EraseSubjectDataResponse>(),
+        _ => return None,
+    })
+}
+
+fn current_shapes() -> ToolShapes {
+    CompatibilityEngine::tool_router()
+        .list_all()
+        .into_iter()
+        .map(|tool| {
+            let name = tool.name.to_string();
+            let input = Value::Object(tool.input_schema.as_ref().clone());
+            let output = response_schema(&name).unwrap_or_else(|| {
+                panic!("Tool '{}' has no response schema registered in schema_compat", name)
+            });
+            let shape = ToolShape {
+                input: flatten_schema(&input),
+                output: flatten_schema(&output),
+            };
+            (name, shape)
+        })
+        .collect()
+}
+
+/// Follow a local `$ref` into the root schema's definitions
+fn resolve<'a>(root: &'a Value, node: &'a Value) -> &'a Value {
+    match node.get("$ref").and_then(Value::as_str) {
+        Some(reference) => {
+            let name = reference.rsplit('/').next().unwrap_or_default();
+            root.get("$defs")
+                .or_else(|| root.get("definitions"))
+                .and_then(|defs| defs.get(name))
+                .map(|def| resolve(root, def))
+                .unwrap_or(node)
+        }
+        None => node,
+    }
+}
+
+/// Collect the JSON types a schema node accepts, looking through `anyOf`/`oneOf`
+fn node_types(root: &Value, node: &Value, types: &mut BTreeSet<String>) {
+    let node = resolve(root, node);
+    match node.get("type") {
+        Some(Value::String(ty)) => {
+            types.insert(ty.clone());
+        }
+        Some(Value::Array(list)) => {
+            types.extend(list.iter().filter_map(Value::as_str).map(str::to_string));
+        }
+        _ => {}
+    }
+    for key in ["anyOf", "oneOf"] {
+        if let Some(Value::Array(variants)) = node.get(key) {
+            for variant in variants {
+                node_types(root, variant, types);
+            }
+        }
+    }
+}
+
+/// Non-null variants of a node, used to descend into object and array members
+fn structural_variants<'a>(root: &'a Value, node: &'a Value) -> Vec<&'a Value> {
+    let node = resolve(root, node);
+    let mut variants = Vec::new();
+    for key in ["anyOf", "oneOf"] {
+        if let Some(Value::Array(list)) = node.get(key) {
+            for variant in list {
+                variants.extend(structural_variants(root, variant));
+            }
+        }
+    }
+    if node.get("properties").is_some() || node.get("items").is_some() {
+        variants.push(node);
+    }
+    variants
+}
+
+fn walk(root: &Value, node: &Value, prefix: &str, out: &mut Shape) {
+    for variant in structural_variants(root, node) {
+        if let Some(Value::Object(properties)) = variant.get("properties") {
+            let required: BTreeSet<&str> = variant
+                .get("required")
+                .and_then(Value::as_array)
+                .map(|list| list.iter().filter_map(Value::as_str).collect())
+                .unwrap_or_default();
+            for (name, child) in properties {
+                let path = if prefix.is_empty() {
+                    name.clone()
+                } else {
+                    format!("{}.{}", prefix, name)
+                };
+                record(root, child, &path, required.contains(name.as_str()), out);
+            }
+        }
+        if let Some(items) = variant.get("items") {
+            record(root, items, &format!("{}[]", prefix), true, out);
+        }
+    }
+}
+
+fn record(root: &Value, node: &Value, path: &str, required: bool, out: &mut Shape) {
+    let mut types = BTreeSet::new();
+    node_types(root, node, &mut types);
+    let ty = types.into_iter().collect::<Vec<_>>().join("|");
+    out.insert(path.to_string(), FieldShape { ty, required });
+    walk(root, node, path, out);
+}
+
+/// Flatten a JSON Schema into field paths (`a.b`, `list[].c`) with their types and requiredness
+fn flatten_schema(schema: &Value) -> Shape {
+    let mut out = Shape::new();
+    walk(schema, schema, "", &mut out);
+    out
+}
+
+fn type_set(ty: &str) -> BTreeSet<&str> {
+    ty.split('|').filter(|t| !t.is_empty()).collect()
+}
+
+/// Input fields may accept more types or become optional; they may not lose types or
+/// become required.
+fn input_changes(tool: &str, previous: &Shape, current: &Shape, changes: &mut Vec<String>) {
+    for (path, before) in previous {
+        let Some(after) = current.get(path) else {
+            changes.push(format!("{}.input.{}: removed", tool, path));
+            continue;
+        };
+        if !type_set(&before.ty).is_subset(&type_set(&after.ty)) {
+            changes.push(format!(
+                "{}.input.{}: type changed from {} to {}",
+                tool, path, before.ty, after.ty
+            ));
+        }
+        if after.required && !before.required {
+            changes.push(format!("{}.input.{}: became required", tool, path));
+        }
+    }
+    for (path, after) in current {
+        if after.required && !previous.contains_key(path) {
+            changes.push(format!("{}.input.{}: added as required", tool, path));
+        }
+    }
+}
+
+/// Output fields may narrow their types or become always present; they may not disappear,
+/// gain types, or become optional.
+fn output_changes(tool: &str, previous: &Shape, current: &Shape, changes: &mut Vec<String>) {
+    for (path, before) in previous {
+        let Some(after) = current.get(path) else {
+            changes.push(format!("{}.output.{}: removed", tool, path));
+            continue;
+        };
+        if !type_set(&after.ty).is_subset(&type_set(&before.ty)) {
+            changes.push(format!(
+                "{}.output.{}: type changed from {} to {}",
+                tool, path, before.ty, after.ty
+            ));
+        }
+        if before.required && !after.required {
+            changes.push(format!("{}.output.{}: became optional", tool, path));
+        }
+    }
+}
+
+fn breaking_changes(previous: &ToolShapes, current: &ToolShapes) -> Vec<String> {
+    let mut changes = Vec::new();
+    for (tool, before) in previous {
+        match current.get(tool) {
+            Some(after) => {
+                input_changes(tool, &before.input, &after.input, &mut changes);
+                output_changes(tool, &before.output, &after.output, &mut changes);
+            }
+            None => changes.push(format!("{}: tool removed", tool)),
+        }
+    }
+    changes
+}
+
+#[test]
+fn test_tool_shapes_backward_compatible() {
+    let current = current_shapes();
+    let snapshot = snapshot_path("tool_shapes.json");
+
+    if std::env::var("UPDATE_COMPAT_SNAPSHOT").is_ok() {
+        let json = serde_json::to_string_pretty(&current).unwrap();
+        std::fs::write(&snapshot, json + "\n").unwrap();
+        return;
+    }
+
+    let previous: ToolShapes =
+        serde_json::from_str(&std::fs::read_to_string(&snapshot).unwrap()).unwrap();
+    let allowlist: Vec<String> = std::fs::read_to_string(snapshot_path("compat_allowlist.json"))
+        .map(|text| serde_json::from_str(&text).unwrap())
+        .unwrap_or_default();
+
+    let breaking: Vec<String> = breaking_changes(&previous, &current)
+        .into_iter()
+        .filter(|change| !allowlist.contains(change))
+        .collect();
+    assert!(
+        breaking.is_empty(),
+        "Breaking tool changes (add to snapshots/compat_allowlist.json if intentional):\n{}",
+        breaking.join("\n")
+    );
+    assert!(
+        previous == current,
+        "Tool shapes changed compatibly; rerun with UPDATE_COMPAT_SNAPSHOT=1 to refresh the snapshot"
+    );
+}
+
+#[test]
+fn test_breaking_change_detection() {
+    let field = |ty: &str, required: bool| FieldShape { ty: ty.to_string(), required };
+    let previous: ToolShapes = BTreeMap::from([(
+        "calc".to_string(),
+        ToolShape {
+            input: Shape::from([
+                ("amount".to_string(), field("string", true)),
+                ("note".to_string(), field("null|string", false)),
+            ]),
+            output: Shape::from([("total".to_string(), field("number", true))]),
+        },
+    )]);
+
+    // Additions and widened inputs are compatible
+    let mut current = previous.clone();
+    let calc = current.get_mut("calc").unwrap();
+    calc.input.insert("extra".to_string(), field("null|string", false));
+    calc.input.insert("amount".to_string(), field("null|string", false));
+    calc.output.insert("detail".to_string(), field("string", true));
+    assert!(breaking_changes(&previous, &current).is_empty());
+
+    // Removals, narrowed inputs and newly required inputs are not
+    let mut current = previous.clone();
+    let calc = current.get_mut("calc").unwrap();
+    calc.input.remove("amount");
+    calc.input.insert("note".to_string(), field("string", true));
+    calc.output.insert("total".to_string(), field("null|number", false));
+    let changes = breaking_changes(&previous, &current);
+    assert!(changes.contains(&"calc.input.amount: removed".to_string()));
+    assert!(changes.contains(&"calc.input.note: type changed from null|string to string".to_string()));
+    assert!(changes.contains(&"calc.input.note: became required".to_string()));
+    assert!(changes.contains(&"calc.output.total: type changed from number to null|number".to_string()));
+    assert!(changes.contains(&"calc.output.total: became optional".to_string()));
+
+    let changes = breaking_changes(&previous, &ToolShapes::new());
+    assert_eq!(changes, vec!["calc: tool removed".to_string()]);
+}
@@ -0,0 +1,176 @@
+//! Envelope encryption of the embedded store's records that can hold personal data.
+//!
+//! Stored `$result` payloads carry the inputs and results of calls (incomes, household details,
+//! beneficiaries), so when keys are configured each one is encrypted with AES-256-GCM under a
+//! data key of its own, and the data key is stored wrapped (encrypted) under a key-encryption
+//! key. The record id is bound to the payload as associated data, so a payload copied onto
+//! another record does not decrypt. Usage counts hold no personal data and are not encrypted.
+//!
+//! Key-encryption keys come from `ENGINE_STORE_KEYS_FILE`, a file with one `<id>:<key>` per line
+//! as written by a KMS agent or a mounted secret, or else from `ENGINE_STORE_KEYS`, the same
+//! entries separated by commas. A key is 64 hex digits (256 bits). The first key is current and
+//! wraps the data keys of new records; the others only unwrap. To rotate, put the new key first
+//! and keep the old ones until `verify_store_integrity` with `rewrap` has moved every record to
+//! the new key. Only the data keys are re-wrapped; payloads are not encrypted again.
+
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+
+const KEY_BYTES: usize = 32;
+const NONCE_BYTES: usize = 12;
+
+/// Key-encryption keys by id, the current one first. Deliberately not `Debug`, so keys cannot
+/// end up in logs.
+#[derive(Clone)]
+pub struct KeyRing {
+    keys: Vec<(String, [u8; KEY_BYTES])>,
+}
+
+impl KeyRing {
+    /// Parse `<id>:<hex key>` entries separated by commas or newlines; blank entries and lines
+    /// starting with `#` are skipped
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut keys: Vec<(String, [u8; KEY_BYTES])> = Vec::new();
+        for entry in text.split([',', '\n']).map(str::trim).filter(|entry| !entry.is_empty() && !entry.starts_with('#')) {
+            let Some((id, hex)) = entry.split_once(':') else {
+                return Err("each key must be written as <id>:<64 hex digits>".to_string());
+            };
+            let id = id.trim();
+            if id.is_empty() {
+                return Err("a key has an empty id".to_string());
+            }
+            if keys.iter().any(|(known, _)| known == id) {
+                return Err(format!("key id '{}' is given twice", id));
+            }
+            let key = decode_key(hex.trim()).ok_or_else(|| format!("key '{}' is not 64 hex digits", id))?;
+            keys.push((id.to_string(), key));
+        }
+        if keys.is_empty() {
+            return Err("no keys given".to_string());
+        }
+        Ok(Self { keys })
+    }
+
+    /// Keys from `ENGINE_STORE_KEYS_FILE`, or else `ENGINE_STORE_KEYS`; None when neither is set
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let set = |name: &str| std::env::var(name).ok().filter(|value| !value.trim().is_empty());
+        if let Some(path) = set("ENGINE_STORE_KEYS_FILE") {
+            let text = std::fs::read_to_string(&path).map_err(|e| format!("cannot read ENGINE_STORE_KEYS_FILE {}: {}", path, e))?;
+            return Self::parse(&text).map(Some).map_err(|e| format!("ENGINE_STORE_KEYS_FILE {}: {}", path, e));
+        }
+        set("ENGINE_STORE_KEYS").map(|text| Self::parse(&text).map_err(|e| format!("ENGINE_STORE_KEYS: {}", e))).transpose()
+    }
+
+    /// Id of the key that wraps new data keys
+    pub fn current(&self) -> &str {
+        &self.keys[0].0
+    }
+
+    fn key(&self, id: &str) -> Option<&[u8; KEY_BYTES]> {
+        self.keys.iter().find(|(known, _)| known == id).map(|(_, key)| key)
+    }
+}
+
+/// A payload encrypted under its own data key, and that key wrapped under `key_id`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sealed {
+    pub key_id: String,
+    pub wrapped_key: Vec<u8>,
+    pub ciphertext: Vec<u8>,
+}
+
+/// Encrypt the payload of `record` under a new data key wrapped with the current key
+pub fn seal(keys: &KeyRing, record: &str, plaintext: &[u8]) -> Sealed {
+    let data_key: [u8; KEY_BYTES] = rand::random();
+    let key_id = keys.current().to_string();
+    Sealed {
+        wrapped_key: encrypt(&keys.keys[0].1, key_id.as_bytes(), &data_key),
+        ciphertext: encrypt(&data_key, record.as_bytes(), plaintext),
+        key_id,
+    }
+}
+
+/// Decrypt the payload of `record`; fails when its key is not configured or it does not
+/// authenticate (wrong key, altered or moved from another record)
+pub fn open(keys: &KeyRing, record: &str, sealed: &Sealed) -> Result<Vec<u8>, String> {
+    let data_key = unwrap_key(keys, sealed)?;
+    decrypt(&data_key, record.as_bytes(), &sealed.ciphertext).ok_or_else(|| "payload does not authenticate".to_string())
+}
+
+/// The data key of `sealed` wrapped with the current key instead
+pub fn rewrap(keys: &KeyRing, sealed: &Sealed) -> Result<Sealed, String> {
+    let data_key = unwrap_key(keys, sealed)?;
+    let key_id = keys.current().to_string();
+    Ok(Sealed { wrapped_key: encrypt(&keys.keys[0].1, key_id.as_bytes(), &data_key), ciphertext: sealed.ciphertext.clone(), key_id })
+}
+
+fn unwrap_key(keys: &KeyRing, sealed: &Sealed) -> Result<[u8; KEY_BYTES], String> {
+    let key = keys.key(&sealed.key_id).ok_or_else(|| format!("key '{}' is not configured", sealed.key_id))?;
+    decrypt(key, sealed.key_id.as_bytes(), &sealed.wrapped_key)
+        .and_then(|data_key| <[u8; KEY_BYTES]>::try_from(data_key).ok())
+        .ok_or_else(|| format!("data key does not authenticate under key '{}'", sealed.key_id))
+}
+
+/// A random nonce followed by the AES-256-GCM ciphertext and tag
+fn encrypt(key: &[u8; KEY_BYTES], associated: &[u8], plaintext: &[u8]) -> Vec<u8> {
+    let nonce: [u8; NONCE_BYTES] = rand::random();
+    let ciphertext = Aes256Gcm::new(key.into())
+        .encrypt(Nonce::from_slice(&nonce), Payload { msg: plaintext, aad: associated })
+        .expect("AES-GCM encryption of an in-memory buffer cannot fail");
+    [nonce.as_slice(), &ciphertext].concat()
+}
+
+fn decrypt(key: &[u8; KEY_BYTES], associated: &[u8], data: &[u8]) -> Option<Vec<u8>> {
+    let (nonce, ciphertext) = data.split_at_checked(NONCE_BYTES)?;
+    Aes256Gcm::new(key.into()).decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad: associated }).ok()
+}
+
+fn decode_key(hex: &str) -> Option<[u8; KEY_BYTES]> {
+    if hex.len() != KEY_BYTES * 2 || !hex.is_ascii() {
+        return None;
+    }
+    let mut key = [0; KEY_BYTES];
+    for (byte, digits) in key.iter_mut().zip(hex.as_bytes().chunks(2)) {
+        *byte = u8::from_str_radix(std::str::from_utf8(digits).ok()?, 16).ok()?;
+    }
+    Some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OLD: &str = "0000000000000000000000000000000000000000000000000000000000000001";
+    const NEW: &str = "ffeeddccbbaa99887766554433221100ffeeddccbbaa99887766554433221100";
+
+    #[test]
+    fn test_envelope_encryption() {
+        let old = KeyRing::parse(&format!("2025:{}", OLD)).unwrap();
+        let sealed = seal(&old, "r1", b"{\"tax\": 100}");
+        assert_eq!(sealed.key_id, "2025");
+        assert_eq!(open(&old, "r1", &sealed).unwrap(), b"{\"tax\": 100}");
+        // Bound to its record, and tampering is detected
+        assert_eq!(open(&old, "r2", &sealed).unwrap_err(), "payload does not authenticate");
+        let mut altered = sealed.clone();
+        altered.ciphertext[NONCE_BYTES] ^= 1;
+        assert!(open(&old, "r1", &altered).is_err());
+
+        // Rotation: the new key first, the old one kept for unwrapping
+        let rotated = KeyRing::parse(&format!("# rotated\n2026:{}\n2025:{}\n", NEW, OLD)).unwrap();
+        assert_eq!(rotated.current(), "2026");
+        let rewrapped = rewrap(&rotated, &sealed).unwrap();
+        assert_eq!((rewrapped.key_id.as_str(), &rewrapped.ciphertext), ("2026", &sealed.ciphertext));
+        assert_eq!(open(&rotated, "r1", &rewrapped).unwrap(), b"{\"tax\": 100}");
+        assert_eq!(open(&old, "r1", &rewrapped).unwrap_err(), "key '2026' is not configured");
+
+        for (text, error) in [
+            ("", "no keys"),
+            (OLD, "<id>:"),
+            ("a:1234", "64 hex digits"),
+            (&format!("a:{},a:{}", OLD, NEW), "twice"),
+            (&format!(":{}", OLD), "empty id"),
+        ] {
+            assert!(KeyRing::parse(text).err().unwrap().contains(error), "{}", text);
+        }
+    }
+}
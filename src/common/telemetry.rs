@@ -10,6 +10,11 @@
 //! `OTEL_EXPORTER_OTLP_TRACES_PROTOCOL` / `OTEL_EXPORTER_OTLP_METRICS_PROTOCOL` and the matching
 //! `*_ENDPOINT` variables.
 //!
+//! Metrics are pushed, never scraped: the same instruments (see [`metrics`]) are exported to the
+//! collector every `OTEL_METRIC_EXPORT_INTERVAL` milliseconds (default 60000), each export
+//! bounded by `OTEL_METRIC_EXPORT_TIMEOUT`. Headers such as collector credentials come from
+//! `OTEL_EXPORTER_OTLP_HEADERS` or `OTEL_EXPORTER_OTLP_METRICS_HEADERS` (`key=value,...`).
+//!
 //! If **all** protocol variables are unset, this crate defaults to **gRPC** (backward compatible).
 //! Some Kubernetes OTLP gRPC frontends are trace-only; if metrics export fails with
 //! `unknown service …MetricsService`, use `OTEL_EXPORTER_OTLP_PROTOCOL=http/protobuf` and an HTTP
@@ -0,0 +1,233 @@
+//! Regression check for what tool calls put on the wire.
+//!
+//! A fixed set of calls (successes, parse errors, validation errors, missing inputs, legacy
+//! responses) is run against every tool and the resulting `CallToolResult`s are compared with
+//! `snapshots/wire_responses.json`. Timestamps and result ids are blanked out. Run the tests with
+//! `UPDATE_COMPAT_SNAPSHOT=1` to rewrite the snapshot after reviewing an intended change.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use rmcp::handler::server::wrapper::Parameters;
+use rmcp::model::CallToolResult;
+use serde_json::{json, Value};
+
+use super::compatibility_engine::*;
+
+/// (case name, tool, arguments)
+fn cases() -> Vec<(&'static str, &'static str, Value)> {
+    vec![
+        ("calc_penalty.ok", "calc_penalty", json!({"days_late": "12", "rate_per_day": 150, "cap": "1,000", "interest_rate": "0.05"})),
+        ("calc_penalty.defaults", "calc_penalty", json!({"days_late": 3})),
+        ("calc_penalty.invalid_optional", "calc_penalty", json!({"days_late": 3, "cap": "lots"})),
+        ("calc_penalty.invalid_days", "calc_penalty", json!({"days_late": "soon"})),
+        ("calc_penalty.negative_days", "calc_penalty", json!({"days_late": -2})),
+        ("calc_penalty.v1", "calc_penalty", json!({"days_late": 4, "response_version": 1})),
+        ("calc_penalty.bad_version", "calc_penalty", json!({"days_late": 4, "response_version": "7"})),
+        ("calc_penalty.bad_audience", "calc_penalty", json!({"days_late": 4, "audience": "robots"})),
+        ("calc_penalty.bad_context", "calc_penalty", json!({"days_late": 4, "context": {"case_id": "a\u{7}b"}})),
+        ("calc_penalty.valuation_date", "calc_penalty", json!({"days_late": 12, "due_date": "2026-01-15", "valuation_date": "2026-07-15"})),
+        ("calc_penalty.valuation_before_due", "calc_penalty", json!({"days_late": 12, "due_date": "2026-07-15", "valuation_date": "2026-01-15"})),
+        ("calc_penalty.bad_valuation_date", "calc_penalty", json!({"days_late": 12, "valuation_date": "next week"})),
+        ("calc_penalty.citizen", "calc_penalty", json!({"days_late": 4, "audience": "citizen", "context": {"case_id": "C-1"}})),
+        ("calc_penalty.needs_input", "calc_penalty", json!({"days_late": "tbd"})),
+        ("calc_penalty.fields", "calc_penalty", json!({"days_late": 4, "fields": ["penalty", "errors"]})),
+        ("calc_penalty.bad_fields", "calc_penalty", json!({"days_late": 4, "fields": ["penalty", "fine"]})),
+        ("calc_penalty.needs_confirmation", "calc_penalty", json!({"days_late": 5000})),
+        ("calc_penalty.acknowledged", "calc_penalty", json!({"days_late": 5000, "acknowledge_warnings": true})),
+        ("calc_penalty.explanation_graph", "calc_penalty", json!({"days_late": 10, "rate_per_day": 5, "explanation_graph": "mermaid"})),
+        ("check_appeal_deadline.business_days", "check_appeal_deadline", json!({"decision_date": "2026-03-27", "filing_date": "2026-04-13", "window_days": 10, "business_days": true})),
+        ("check_appeal_deadline.before_decision", "check_appeal_deadline", json!({"decision_date": "2026-03-27", "filing_date": "2026-03-01"})),
+        ("get_required_documents.situation", "get_required_documents", json!({"program": "housing_grant", "situation": ["self_employed", "Non-Resident"]})),
+        ("get_required_documents.unknown_program", "get_required_documents", json!({"program": "pension"})),
+        ("check_voting_bulk.ok", "check_voting_bulk", json!({"proposal_type": "general", "precincts": [
+            {"name": "North", "eligible_voters": "60", "turnout": 45, "yes_votes": 30},
+            {"name": "South", "eligible_voters": 40, "turnout": 20, "yes_votes": 12},
+        ]})),
+        ("check_voting_bulk.invalid_precinct", "check_voting_bulk", json!({"proposal_type": "general", "precincts": [
+            {"name": "North", "eligible_voters": 60, "turnout": 75, "yes_votes": 30},
+        ]})),
+        ("check_voting_bulk.bad_count", "check_voting_bulk", json!({"proposal_type": "general", "precincts": [
+            {"name": "North", "eligible_voters": "many", "turnout": 45, "yes_votes": 30},
+        ]})),
+        ("check_voting_bulk.empty", "check_voting_bulk", json!({"proposal_type": "general", "precincts": []})),
+        ("project_quorum.projection", "project_quorum", json!({"eligible_voters": 200, "turnout": "90", "yes_votes": 50, "hours_elapsed": 6, "hours_remaining": 2})),
+        ("project_quorum.no_projection", "project_quorum", json!({"eligible_voters": 7, "turnout": 5})),
+        ("project_quorum.half_projection", "project_quorum", json!({"eligible_voters": 200, "turnout": 90, "hours_elapsed": 6})),
+        ("project_quorum.invalid", "project_quorum", json!({"eligible_voters": 100, "turnout": 120})),
+        // Admin tools are disabled in tests, so only their refusal is pinned
+        ("stress_config.admin_disabled", "stress_config", json!({"tax_brackets": "[{\"rate\": 0.1}]"})),
+        ("verify_store_integrity.admin_disabled", "verify_store_integrity", json!({"rewrap": true})),
+        ("purge_expired_data.admin_disabled", "purge_expired_data", json!({})),
+        ("export_subject_data.admin_disabled", "export_subject_data", json!({"case_id": "CASE-1"})),
+        ("erase_subject_data.admin_disabled", "erase_subject_data", json!({"case_id": "CASE-1"})),
+        ("evaluate_ranges.flip", "evaluate_ranges", json!({"tool": "check_housing_grant", "arguments": {"ami": 80000, "household_size": 5, "income": {"min": 50000, "max": 56000}, "has_other_subsidy": false}})),
+        ("evaluate_ranges.bad_export", "evaluate_ranges", json!({"tool": "calc_tax", "arguments": {"income": {"min": 40000, "max": 42000}}, "export": "csv"})),
+        ("evaluate_ranges.invalid", "evaluate_ranges", json!({"tool": "calc_tax", "arguments": {"income": {"min": 50000, "max": 40000}}})),
+        ("simulate_uncertainty.probability", "simulate_uncertainty", json!({"tool": "check_housing_grant", "arguments": {"ami": 80000, "household_size": {"distribution": "uniform", "min": 4, "max": 6}, "income": {"distribution": "normal", "mean": 52000, "sd": 2000}, "has_other_subsidy": false}, "draws": 50, "seed": 7})),
+        ("simulate_uncertainty.invalid", "simulate_uncertainty", json!({"tool": "calc_tax", "arguments": {"income": {"distribution": "normal", "mean": 40000, "sd": -1}}})),
+        ("calc_tax.ok", "calc_tax", json!({"income": "90k", "loss_carryforward": 1000})),
+        ("calc_tax.locale", "calc_tax", json!({"income": 45000, "locale": "de-DE"})),
+        ("calc_tax.bad_locale", "calc_tax", json!({"income": 45000, "locale": "xx"})),
+        ("calc_tax.without_explanations", "calc_tax", json!({"income": 45000, "include_explanations": false})),
+        ("calc_tax.bad_surcharge_base", "calc_tax", json!({"income": 50000, "surcharge_base": "gross"})),
+        ("calc_tax.bad_amt", "calc_tax", json!({"income": 50000, "amt_adjustments": "x"})),
+        ("calc_tax.negative", "calc_tax", json!({"income": -5})),
+        ("check_voting.ok", "check_voting", json!({"eligible_voters": 100, "turnout": "70", "yes_votes": 50, "proposal_type": "general"})),
+        ("check_voting.invalid", "check_voting", json!({"eligible_voters": 100, "turnout": 120, "yes_votes": 50, "proposal_type": "general"})),
+        ("check_voting.on_threshold", "check_voting", json!({"eligible_voters": 100, "turnout": 60, "yes_votes": 50, "proposal_type": "general", "context": {"input_source": "llm_inferred"}})),
+        ("check_voting.bad_turnout", "check_voting", json!({"eligible_voters": 100, "turnout": "many", "yes_votes": 50, "proposal_type": "general"})),
+        ("validate_vote_record.ok", "validate_vote_record", json!({"eligible_voters": 100, "turnout": 70, "yes_votes": 40, "no_votes": 30})),
+        ("validate_vote_record.inconsistent", "validate_vote_record", json!({"eligible_voters": 100, "turnout": 70, "yes_votes": 40, "no_votes": 10, "abstain_votes": 5, "invalid_votes": "1"})),
+        ("validate_vote_record.bad_abstain", "validate_vote_record", json!({"eligible_voters": 100, "turnout": 70, "yes_votes": 40, "no_votes": 30, "abstain_votes": "few"})),
+        ("distribute_waterfall.ok", "distribute_waterfall", json!({"cash_available": 1000, "senior_debt": 600, "junior_debt": "300"})),
+        ("distribute_waterfall.negative", "distribute_waterfall", json!({"cash_available": -1, "senior_debt": 600, "junior_debt": 300})),
+        ("distribute_waterfall.bad_cash", "distribute_waterfall", json!({"cash_available": "cash", "senior_debt": 600, "junior_debt": 300})),
+        ("check_covenants.ok", "check_covenants", json!({"total_debt": 3000, "ebitda": 1000, "interest_expense": 200})),
+        ("check_covenants.on_threshold", "check_covenants", json!({"total_debt": 3500, "ebitda": 1000, "interest_expense": 200, "context": {"input_source": "llm_inferred"}})),
+        ("check_covenants.bad_leverage", "check_covenants", json!({"total_debt": 3000, "ebitda": 1000, "interest_expense": 200, "max_leverage": "high"})),
+        ("calc_npv.ok", "calc_npv", json!({"cash_flows": [-1000, "300", 400, 500], "discount_rate": "0.08"})),
+        ("calc_npv.bad_flow", "calc_npv", json!({"cash_flows": [-1000, "abc"], "discount_rate": 0.08})),
+        ("calc_npv.empty", "calc_npv", json!({"cash_flows": [], "discount_rate": 0.08})),
+        ("calc_irr.ok", "calc_irr", json!({"cash_flows": [-1000, 300, 400, 500]})),
+        ("calc_irr.no_inflow", "calc_irr", json!({"cash_flows": [-1000, -300]})),
+        ("calc_irr.bad_flow", "calc_irr", json!({"cash_flows": [-1000, "x"]})),
+        ("check_escrow_release.ok", "check_escrow_release", json!({"escrow_amount": 10000, "as_of_date": "2024-06-01", "milestones": [{"name": "Design", "completed": "yes", "release_percentage": 0.4}, {"name": "Build", "completed": false, "release_percentage": 0.6}]})),
+        ("check_escrow_release.bad_completed", "check_escrow_release", json!({"escrow_amount": 10000, "as_of_date": "2024-06-01", "milestones": [{"name": "Design", "completed": "maybe", "release_percentage": 0.4}]})),
+        ("check_escrow_release.bad_name", "check_escrow_release", json!({"escrow_amount": 10000, "as_of_date": "2024-06-01", "milestones": [{"name": "\0", "completed": true, "release_percentage": 0.4}]})),
+        ("check_escrow_release.bad_release_date", "check_escrow_release", json!({"escrow_amount": 10000, "as_of_date": "2024-06-01", "release_date": "someday", "milestones": []})),
+        ("check_escrow_release.bad_as_of_date", "check_escrow_release", json!({"escrow_amount": 10000, "as_of_date": "today-ish", "milestones": []})),
+        ("check_housing_grant.ok", "check_housing_grant", json!({"ami": 80000, "household_size": 5, "income": "45,000", "has_other_subsidy": "no"})),
+        ("check_housing_grant.currency", "check_housing_grant", json!({"ami": 80000, "household_size": 3, "income": 40000, "has_other_subsidy": false, "income_currency": "usd"})),
+        ("check_housing_grant.bad_currency", "check_housing_grant", json!({"ami": 80000, "household_size": 3, "income": 40000, "has_other_subsidy": false, "income_currency": "XXX"})),
+        ("check_housing_grant.on_threshold", "check_housing_grant", json!({"ami": 80000, "household_size": 3, "income": 48000, "has_other_subsidy": false, "context": {"input_source": "llm_inferred"}})),
+        ("check_housing_grant.bad_subsidy", "check_housing_grant", json!({"ami": 80000, "household_size": 3, "income": 40000, "has_other_subsidy": "perhaps"})),
+        ("calc_capital_gains.ok", "calc_capital_gains", json!({"acquisition_cost": 10000, "sale_price": "15000", "holding_period_days": 400})),
+        ("calc_capital_gains.negative", "calc_capital_gains", json!({"acquisition_cost": -1, "sale_price": 15000, "holding_period_days": 400})),
+        ("calc_inheritance_tax.ok", "calc_inheritance_tax", json!({"beneficiaries": [{"name": "Ana", "relationship": "child", "amount": "250000", "prior_gifts": 10000}, {"name": "Ben", "relationship": "sibling", "amount": 50000}]})),
+        ("calc_inheritance_tax.bad_amount", "calc_inheritance_tax", json!({"beneficiaries": [{"name": "Ana", "relationship": "child", "amount": "lots"}]})),
+        ("calc_inheritance_tax.bad_prior_gifts", "calc_inheritance_tax", json!({"beneficiaries": [{"name": "Ana", "relationship": "child", "amount": 1000, "prior_gifts": "some"}]})),
+        ("calc_inheritance_tax.bad_relationship", "calc_inheritance_tax", json!({"beneficiaries": [{"name": "Ana", "relationship": "a\u{1}\u{2}\u{3}b", "amount": 1000}]})),
+        ("calc_inheritance_tax.unknown_relationship", "calc_inheritance_tax", json!({"beneficiaries": [{"name": "Ana", "relationship": "neighbour", "amount": 1000}]})),
+        ("calc_social_contributions.ok", "calc_social_contributions", json!({"gross_salary": "5,000"})),
+        ("calc_social_contributions.negative", "calc_social_contributions", json!({"gross_salary": -5})),
+        ("calc_property_tax.ok", "calc_property_tax", json!({"assessed_value": 300000, "prior_year_value": 250000, "homestead": "yes"})),
+        ("calc_property_tax.bad_prior", "calc_property_tax", json!({"assessed_value": 300000, "prior_year_value": "old"})),
+        ("calc_transfer_tax.ok", "calc_transfer_tax", json!({"price": 350000})),
+        ("calc_transfer_tax.marginal", "calc_transfer_tax", json!({"price": 350000, "method": "marginal"})),
+        ("calc_transfer_tax.bad_method", "calc_transfer_tax", json!({"price": 350000, "method": "guess"})),
+        ("calc_fee.ok", "calc_fee", json!({"fee_code": "COURT_FILING", "value": 1200})),
+        ("calc_fee.unknown_code", "calc_fee", json!({"fee_code": "nope", "value": 1200})),
+        ("index_amount.ok", "index_amount", json!({"amount": 1000})),
+        ("index_amount.bad_year", "index_amount", json!({"amount": 1000, "from_year": "last year"})),
+        ("index_amount.unknown_year", "index_amount", json!({"amount": 1000, "from_year": 1900})),
+        ("convert_currency.ok", "convert_currency", json!({"amount": 100, "from_currency": "usd"})),
+        ("convert_currency.bad_date", "convert_currency", json!({"amount": 100, "from_currency": "USD", "date": "yesterday"})),
+        ("convert_currency.unknown_currency", "convert_currency", json!({"amount": 100, "from_currency": "XXX"})),
+        ("get_intake_schema.ok", "get_intake_schema", json!({"program": " Housing_Grant "})),
+        ("get_intake_schema.unknown", "get_intake_schema", json!({"program": "pension"})),
+        ("prescreen.ok", "prescreen", json!({"program": "housing_grant", "inputs": {"has_other_subsidy": "yes"}})),
+        ("prescreen.unknown_field", "prescreen", json!({"program": "housing_grant", "inputs": {"pets": 2}})),
+        ("prescreen.bad_input", "prescreen", json!({"program": "voting", "inputs": {"turnout": "lots"}})),
+        ("prescreen.unknown_program", "prescreen", json!({"program": "pension", "inputs": {"x": 1}})),
+        ("get_usage_report.bad_limit", "get_usage_report", json!({"limit": "all"})),
+        ("get_usage_report.bad_audience", "get_usage_report", json!({"audience": "robots"})),
+        ("list_tools_by_category.tax", "list_tools_by_category", json!({"category": "Tax"})),
+        ("list_tools_by_category.bad_category", "list_tools_by_category", json!({"category": "weather"})),
+        ("describe_capabilities.not_negotiated", "describe_capabilities", json!({})),
+        ("get_recent_schema_failures.bad_limit", "get_recent_schema_failures", json!({"limit": 0})),
+        ("upsert_profile.admin_disabled", "upsert_profile", json!({"profile": {}})),
+        ("diff_profiles.rates", "diff_profiles", json!({
+            "before": {"ENGINE_AMT_RATE": {"value": "0.15"}, "ENGINE_TAX_BRACKETS": {"value": [{"up_to": 10000, "rate": 0.1}, {"rate": 0.2}]}},
+            "after": {"ENGINE_AMT_RATE": {"value": 0.2}, "ENGINE_TAX_BRACKETS": {"value": [{"up_to": 10000, "rate": 0.1}, {"up_to": 50000, "rate": 0.2}, {"rate": 0.3}]}},
+        })),
+        ("diff_profiles.unknown_setting", "diff_profiles", json!({"before": {}, "after": {"ENGINE_LOCALE": {"value": "fr"}}})),
+    ]
+}
+
+async fn call(engine: &CompatibilityEngine, tool: &str, args: Value) -> CallToolResult {
+    macro_rules! dispatch {
+        ($($name:ident),* $(,)?) => {
+            match tool {
+                $(stringify!($name) => engine.$name(Parameters(serde_json::from_value(args).unwrap())).await,)*
+                _ => panic!("No wire case dispatch for tool '{}'", tool),
+            }
+        };
+    }
+    dispatch!(
+        calc_penalty, calc_tax, check_voting, validate_vote_record, distribute_waterfall,
+        check_covenants, calc_npv, calc_irr, check_escrow_release, check_housing_grant,
+        calc_capital_gains, calc_inheritance_tax, calc_social_contributions, calc_property_tax,
+        calc_transfer_tax, calc_fee, index_amount, convert_currency, get_intake_schema,
+        prescreen, get_usage_report,
+        erase_subject_data,
+        export_subject_data,
+        purge_expired_data,
+        verify_store_integrity,
+        stress_config,
+        project_quorum,
+        check_voting_bulk,
+        get_required_documents,
+        check_appeal_deadline,
+        simulate_uncertainty,
+        evaluate_ranges,
+        get_recent_schema_failures,
+        describe_capabilities,
+        list_tools_by_category,
+        upsert_profile,
+        diff_profiles,
+    )
+    .unwrap()
+}
+
+/// Result as stored in the snapshot: JSON text is parsed, volatile envelope fields blanked
+fn normalize(result: &CallToolResult) -> Value {
+    let content: Vec<Value> = result
+        .content
+        .iter()
+        .map(|content| {
+            let text = content.raw.as_text().map(|text| text.text.as_str()).unwrap_or_default();
+            match serde_json::from_str::<Value>(text) {
+                Ok(mut value) => {
+                    for field in ["generated_at", "result_id"] {
+                        if let Some(slot) = value.get_mut(field) {
+                            *slot = json!("<normalized>");
+                        }
+                    }
+                    value
+                }
+                Err(_) => Value::String(text.to_string()),
+            }
+        })
+        .collect();
+    json!({ "is_error": result.is_error.unwrap_or(false), "content": content })
+}
+
+#[tokio::test]
+async fn test_wire_responses_unchanged() {
+    let engine = CompatibilityEngine::new();
+    let mut current = BTreeMap::new();
+    for (name, tool, args) in cases() {
+        current.insert(name.to_string(), normalize(&call(&engine, tool, args).await));
+    }
+
+    let snapshot = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("snapshots").join("wire_responses.json");
+    if std::env::var("UPDATE_COMPAT_SNAPSHOT").is_ok() {
+        let json = serde_json::to_string_pretty(&current).unwrap();
+        std::fs::write(&snapshot, json + "\n").unwrap();
+        return;
+    }
+
+    let previous: BTreeMap<String, Value> =
+        serde_json::from_str(&std::fs::read_to_string(&snapshot).unwrap()).unwrap();
+    let changed: Vec<&String> = current
+        .keys()
+        .chain(previous.keys())
+        .filter(|name| current.get(*name) != previous.get(*name))
+        .collect();
+    assert!(
+        changed.is_empty(),
+        "Wire responses changed; rerun with UPDATE_COMPAT_SNAPSHOT=1 to refresh the snapshot if intended: {:?}",
+        changed
+    );
+}
@@ -1,13 +1,17 @@
-use rmcp::transport::{StreamableHttpServerConfig, streamable_http_server::{
+use rmcp::{ServiceExt, transport::{stdio, StreamableHttpServerConfig, streamable_http_server::{
     StreamableHttpService, session::local::LocalSessionManager,
-}};
+}}};
+use tokio_util::sync::CancellationToken;
 use tracing_subscriber::{
+    fmt::writer::BoxMakeWriter,
     layer::SubscriberExt,
     util::SubscriberInitExt,
     {self},
 };
 mod common;
-use common::{compatibility_engine::CompatibilityEngine, telemetry::Telemetry};
+mod tls;
+use common::{compatibility_engine::{CompatibilityEngine, EngineConfig}, preflight::{self, Check}, retention, telemetry::Telemetry};
+use tls::{TlsListener, TlsSettings};
 use axum::{response::IntoResponse, http::StatusCode};
 use opentelemetry::global;
 
@@ -54,10 +58,60 @@ fn streamable_http_config() -> StreamableHttpServerConfig {
     cfg
 }
 
+/// Transports served by this process, from `MCP_TRANSPORTS`
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Transports {
+    stdio: bool,
+    http: bool,
+}
+
+impl Default for Transports {
+    fn default() -> Self {
+        Self { stdio: false, http: true }
+    }
+}
+
+impl Transports {
+    /// Parse a comma-separated list such as `stdio,http`
+    fn parse(value: &str) -> Result<Self, String> {
+        let mut transports = Self { stdio: false, http: false };
+        for name in value.split(',').map(|name| name.trim().to_ascii_lowercase()).filter(|name| !name.is_empty()) {
+            match name.as_str() {
+                "stdio" => transports.stdio = true,
+                "http" => transports.http = true,
+                _ => return Err(format!("unknown transport '{}' (expected 'stdio' or 'http')", name)),
+            }
+        }
+        if !transports.stdio && !transports.http {
+            return Err("no transport listed".to_string());
+        }
+        Ok(transports)
+    }
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    if std::env::args().any(|arg| arg == "--migrate-config") {
+        print!("{}", EngineConfig::migrate_config());
+        return Ok(());
+    }
+    if std::env::args().any(|arg| arg == "--preflight") {
+        let passed = preflight::run(preflight_transports()).await;
+        std::process::exit(if passed { 0 } else { 1 });
+    }
+
     let telemetry = Telemetry::install("compatibility-engine-mcp-server")?;
 
+    let transports_env = std::env::var("MCP_TRANSPORTS").ok();
+    let transports = transports_env.as_deref().map(Transports::parse).transpose();
+    let stdio_enabled = transports.as_ref().is_ok_and(|transports| transports.is_some_and(|t| t.stdio));
+
+    // With stdio, stdout carries the protocol, so logs go to stderr
+    let writer = if stdio_enabled {
+        BoxMakeWriter::new(std::io::stderr)
+    } else {
+        BoxMakeWriter::new(std::io::stdout)
+    };
     tracing_subscriber::registry()
         .with(
             tracing_subscriber::EnvFilter::try_from_default_env()
@@ -67,11 +121,98 @@ async fn main() -> anyhow::Result<()> {
             tracing_opentelemetry::layer()
                 .with_tracer(global::tracer("compatibility-engine")),
         )
-        .with(tracing_subscriber::fmt::layer())
+        .with(tracing_subscriber::fmt::layer().with_writer(writer).with_ansi(!stdio_enabled))
         .init();
 
-    // Use environment variable or the static value
+    let transports = transports.unwrap_or_else(|e| {
+        tracing::error!("Invalid MCP_TRANSPORTS '{}': {}; serving http only", transports_env.unwrap_or_default(), e);
+        None
+    }).unwrap_or_default();
+
+    // Config, metrics, analytics and stored results are process-wide, so both transports share them
+    retention::spawn_purge_task();
+    let shutdown = CancellationToken::new();
+    let mut servers = tokio::task::JoinSet::new();
+    if transports.http {
+        // Use environment variable or the static value
+        let bind_address = std::env::var("BIND_ADDRESS").unwrap_or_else(|_| BIND_ADDRESS.to_string());
+        servers.spawn(serve_http(bind_address, shutdown.clone()));
+    }
+    if transports.stdio {
+        servers.spawn(serve_stdio(shutdown.clone()));
+    }
+
+    tracing::info!("Server started. Press Ctrl+C to stop.");
+
+    // Stop every transport when one ends (e.g. the stdio client closes) or on Ctrl+C
+    let ended = tokio::select! {
+        _ = tokio::signal::ctrl_c() => {
+            tracing::info!("Shutdown signal received, stopping server...");
+            None
+        }
+        Some(ended) = servers.join_next() => Some(ended),
+    };
+    shutdown.cancel();
+
+    // Force exit after timeout if graceful shutdown hangs
+    tokio::spawn(async {
+        tokio::time::sleep(SHUTDOWN_TIMEOUT).await;
+        tracing::warn!("Force exit after {:?} timeout", SHUTDOWN_TIMEOUT);
+        std::process::exit(0);
+    });
+    let mut results = servers.join_all().await;
+    results.extend(ended.map(|ended| ended.unwrap_or_else(|e| Err(e.into()))));
+
+    tracing::info!("Server stopped");
+    telemetry.shutdown();
+
+    results.into_iter().collect()
+}
+
+/// Preflight checks of `MCP_TRANSPORTS`, and for HTTP of the bind address and TLS certificates
+async fn preflight_transports() -> Vec<Check> {
+    let transports = match std::env::var("MCP_TRANSPORTS") {
+        Ok(value) => Transports::parse(&value).map_err(|e| format!("MCP_TRANSPORTS '{}': {}", value, e)),
+        Err(_) => Ok(Transports::default()),
+    };
+    let transports = match transports {
+        Ok(transports) => transports,
+        Err(e) => return vec![Check::new("transports", Err(e))],
+    };
+    let names: Vec<&str> = [("stdio", transports.stdio), ("http", transports.http)]
+        .into_iter()
+        .filter_map(|(name, enabled)| enabled.then_some(name))
+        .collect();
+    let mut checks = vec![Check::new("transports", Ok(names.join(",")))];
+    if !transports.http {
+        return checks;
+    }
+
     let bind_address = std::env::var("BIND_ADDRESS").unwrap_or_else(|_| BIND_ADDRESS.to_string());
+    // The listener is dropped at once, leaving the address free for the server
+    let bound = tokio::net::TcpListener::bind(&bind_address)
+        .await
+        .map(|_| bind_address.clone())
+        .map_err(|e| format!("cannot bind {}: {}", bind_address, e));
+    checks.push(Check::new("http bind", bound));
+
+    let tls = match TlsSettings::from_env() {
+        Ok(None) => Ok("not configured".to_string()),
+        Ok(Some(settings)) => settings
+            .load()
+            .map(|_| {
+                let mode = if settings.client_ca_file.is_some() { "mutual TLS" } else { "TLS" };
+                format!("{} with {}", mode, settings.cert_file.display())
+            })
+            .map_err(|e| format!("{:#}", e)),
+        Err(e) => Err(format!("{:#}", e)),
+    };
+    checks.push(Check::new("http tls", tls));
+    checks
+}
+
+/// Serve Streamable HTTP on `bind_address` until `shutdown` is cancelled
+async fn serve_http(bind_address: String, shutdown: CancellationToken) -> anyhow::Result<()> {
     tracing::info!("Starting streamable-http Compatibility Engine MCP server on {}", bind_address);
 
     let service = StreamableHttpService::new(
@@ -84,27 +225,35 @@ async fn main() -> anyhow::Result<()> {
         .nest_service("/mcp", service)
         .route("/health", axum::routing::get(health_handler));
     
+    let tls = TlsSettings::from_env()?;
     let tcp_listener = tokio::net::TcpListener::bind(bind_address).await?;
 
-    tracing::info!("Server started. Press Ctrl+C to stop.");
-
-    axum::serve(tcp_listener, router)
-        .with_graceful_shutdown(async {
-            tokio::signal::ctrl_c().await.ok();
-            tracing::info!("Shutdown signal received, stopping server...");
+    match tls {
+        Some(settings) => axum::serve(TlsListener::bind(tcp_listener, settings)?, router)
+            .with_graceful_shutdown(shutdown.cancelled_owned())
+            .await?,
+        None => axum::serve(tcp_listener, router)
+            .with_graceful_shutdown(shutdown.cancelled_owned())
+            .await?,
+    }
+    Ok(())
+}
 
-            // Force exit after timeout if graceful shutdown hangs
-            tokio::spawn(async {
-                tokio::time::sleep(SHUTDOWN_TIMEOUT).await;
-                tracing::warn!("Force exit after {:?} timeout", SHUTDOWN_TIMEOUT);
-                std::process::exit(0);
-            });
-        })
-        .await?;
+/// Serve one client on stdin/stdout until it disconnects or `shutdown` is cancelled
+async fn serve_stdio(shutdown: CancellationToken) -> anyhow::Result<()> {
+    tracing::info!("Starting Compatibility Engine MCP server using stdio transport");
 
-    tracing::info!("Server stopped");
-    telemetry.shutdown();
+    let service = CompatibilityEngine::new().serve(stdio()).await.inspect_err(|e| {
+        tracing::error!("serving error: {:?}", e);
+    })?;
 
+    tokio::select! {
+        quit = service.waiting() => {
+            quit?;
+            tracing::info!("stdio client disconnected");
+        }
+        _ = shutdown.cancelled() => {}
+    }
     Ok(())
 }
 
@@ -113,3 +262,16 @@ async fn health_handler() -> impl IntoResponse {
     let output = "OK";
     (StatusCode::OK, output)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_transports() {
+        assert_eq!(Transports::parse("stdio, HTTP"), Ok(Transports { stdio: true, http: true }));
+        assert_eq!(Transports::parse("stdio"), Ok(Transports { stdio: true, http: false }));
+        assert!(Transports::parse("").is_err());
+        assert!(Transports::parse("http,sse").is_err());
+    }
+}
@@ -5,11 +5,20 @@ use tracing_subscriber::{self, EnvFilter};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 mod common;
-use common::{compatibility_engine::CompatibilityEngine, telemetry::Telemetry};
+use common::{compatibility_engine::{CompatibilityEngine, EngineConfig}, preflight::{self, Check}, retention, telemetry::Telemetry};
 use opentelemetry::global;
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    if std::env::args().any(|arg| arg == "--migrate-config") {
+        print!("{}", EngineConfig::migrate_config());
+        return Ok(());
+    }
+    if std::env::args().any(|arg| arg == "--preflight") {
+        let passed = preflight::run(async { vec![Check::new("transports", Ok("stdio".to_string()))] }).await;
+        std::process::exit(if passed { 0 } else { 1 });
+    }
+
     let telemetry = Telemetry::install("compatibility-engine-mcp-server-stdio")?;
 
     tracing_subscriber::registry()
@@ -26,6 +35,7 @@ async fn main() -> Result<()> {
         .init();
 
     tracing::info!("Starting Compatibility Engine MCP server using stdio transport");
+    retention::spawn_purge_task();
 
     // Create an instance of our compatibility-engine router
     let service = CompatibilityEngine::new().serve(stdio()).await.inspect_err(|e| {
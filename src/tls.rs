@@ -0,0 +1,190 @@
+//! Native TLS for the Streamable HTTP transport, for deployments without a terminating proxy.
+//!
+//! TLS is on when `MCP_TLS_CERT_FILE` and `MCP_TLS_KEY_FILE` (PEM) are both set. With
+//! `MCP_TLS_CLIENT_CA_FILE`, clients must present a certificate signed by one of its CAs
+//! (mutual TLS). The files are read again on SIGHUP and every `MCP_TLS_RELOAD_SECS` seconds
+//! (default 300, `0` disables polling), so rotated certificates apply to new connections without
+//! a restart. A reload that fails is logged and the previous certificates stay in use.
+
+use std::io;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use anyhow::Context;
+use axum::serve::Listener;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::WebPkiClientVerifier;
+use rustls::{RootCertStore, ServerConfig};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_rustls::TlsAcceptor;
+use tokio_rustls::server::TlsStream;
+
+/// Handshakes slower than this are dropped, so idle sockets cannot pile up
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Accepted connections waiting for the HTTP server
+const ACCEPT_QUEUE: usize = 64;
+
+/// Certificate files from the environment
+#[derive(Debug, Clone, PartialEq)]
+pub struct TlsSettings {
+    pub cert_file: PathBuf,
+    pub key_file: PathBuf,
+    /// CA bundle that client certificates must chain to; None serves TLS without client auth
+    pub client_ca_file: Option<PathBuf>,
+    /// Re-read the files this often; None reloads on SIGHUP only
+    pub reload_interval: Option<Duration>,
+}
+
+impl TlsSettings {
+    /// Settings from `MCP_TLS_*`, or None when TLS is not configured
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        let var = |name: &str| std::env::var(name).ok().map(|value| value.trim().to_string()).filter(|value| !value.is_empty());
+        let (cert_file, key_file) = match (var("MCP_TLS_CERT_FILE"), var("MCP_TLS_KEY_FILE")) {
+            (None, None) => return Ok(None),
+            (Some(cert), Some(key)) => (PathBuf::from(cert), PathBuf::from(key)),
+            _ => anyhow::bail!("MCP_TLS_CERT_FILE and MCP_TLS_KEY_FILE must be set together"),
+        };
+        let reload_secs = match var("MCP_TLS_RELOAD_SECS") {
+            Some(value) => value.parse::<u64>().with_context(|| format!("Invalid MCP_TLS_RELOAD_SECS '{}'", value))?,
+            None => 300,
+        };
+        Ok(Some(Self {
+            cert_file,
+            key_file,
+            client_ca_file: var("MCP_TLS_CLIENT_CA_FILE").map(PathBuf::from),
+            reload_interval: (reload_secs > 0).then(|| Duration::from_secs(reload_secs)),
+        }))
+    }
+
+    /// Read the certificate files and build a rustls server config
+    pub fn load(&self) -> anyhow::Result<Arc<ServerConfig>> {
+        let read = |path: &PathBuf| std::fs::read(path).with_context(|| format!("Cannot read {}", path.display()));
+        let certs = CertificateDer::pem_slice_iter(&read(&self.cert_file)?)
+            .collect::<Result<Vec<_>, _>>()
+            .with_context(|| format!("Invalid certificate in {}", self.cert_file.display()))?;
+        anyhow::ensure!(!certs.is_empty(), "No certificate in {}", self.cert_file.display());
+        let key = PrivateKeyDer::from_pem_slice(&read(&self.key_file)?)
+            .with_context(|| format!("Invalid private key in {}", self.key_file.display()))?;
+
+        let provider = Arc::new(rustls::crypto::aws_lc_rs::default_provider());
+        let builder = ServerConfig::builder_with_provider(provider.clone()).with_safe_default_protocol_versions()?;
+        let builder = match &self.client_ca_file {
+            Some(ca_file) => {
+                let mut roots = RootCertStore::empty();
+                for ca in CertificateDer::pem_slice_iter(&read(ca_file)?) {
+                    let ca = ca.with_context(|| format!("Invalid certificate in {}", ca_file.display()))?;
+                    roots.add(ca).with_context(|| format!("Unusable CA certificate in {}", ca_file.display()))?;
+                }
+                let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider).build()?;
+                builder.with_client_cert_verifier(verifier)
+            }
+            None => builder.with_no_client_auth(),
+        };
+        let mut config = builder.with_single_cert(certs, key)?;
+        config.alpn_protocols = vec![b"http/1.1".to_vec()];
+        Ok(Arc::new(config))
+    }
+}
+
+/// TLS listener for `axum::serve`. Handshakes run in their own tasks, so a slow client does not
+/// hold up the others.
+pub struct TlsListener {
+    connections: mpsc::Receiver<(TlsStream<TcpStream>, SocketAddr)>,
+    local_addr: SocketAddr,
+}
+
+impl TlsListener {
+    /// Load the certificates (failing if they are unusable) and start accepting on `tcp_listener`
+    pub fn bind(tcp_listener: TcpListener, settings: TlsSettings) -> anyhow::Result<Self> {
+        let config = Arc::new(RwLock::new(settings.load()?));
+        tracing::info!(
+            "TLS enabled with {}{}",
+            settings.cert_file.display(),
+            if settings.client_ca_file.is_some() { ", client certificates required" } else { "" }
+        );
+        tokio::spawn(reload_on_change(settings.clone(), config.clone()));
+
+        let local_addr = tcp_listener.local_addr()?;
+        let (sender, connections) = mpsc::channel(ACCEPT_QUEUE);
+        tokio::spawn(async move {
+            loop {
+                let (stream, peer) = match tcp_listener.accept().await {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        tracing::warn!("TCP accept failed: {}", e);
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                        continue;
+                    }
+                };
+                if sender.is_closed() {
+                    break;
+                }
+                let acceptor = TlsAcceptor::from(config.read().unwrap_or_else(|poisoned| poisoned.into_inner()).clone());
+                let queue = sender.clone();
+                tokio::spawn(async move {
+                    match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                        Ok(Ok(stream)) => {
+                            let _ = queue.send((stream, peer)).await;
+                        }
+                        Ok(Err(e)) => tracing::debug!(%peer, "TLS handshake failed: {}", e),
+                        Err(_) => tracing::debug!(%peer, "TLS handshake timed out"),
+                    }
+                });
+            }
+        });
+        Ok(Self { connections, local_addr })
+    }
+}
+
+impl Listener for TlsListener {
+    type Io = TlsStream<TcpStream>;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        match self.connections.recv().await {
+            Some(connection) => connection,
+            // The accept task only stops once this listener is dropped
+            None => std::future::pending().await,
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        Ok(self.local_addr)
+    }
+}
+
+/// Swap in freshly loaded certificates on SIGHUP and at the reload interval
+async fn reload_on_change(settings: TlsSettings, config: Arc<RwLock<Arc<ServerConfig>>>) {
+    #[cfg(unix)]
+    let mut hangup = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()).ok();
+    loop {
+        let interval = async {
+            match settings.reload_interval {
+                Some(interval) => tokio::time::sleep(interval).await,
+                None => std::future::pending().await,
+            }
+        };
+        #[cfg(unix)]
+        let hangup = async {
+            match hangup.as_mut() {
+                Some(hangup) => hangup.recv().await,
+                None => std::future::pending().await,
+            }
+        };
+        #[cfg(not(unix))]
+        let hangup = std::future::pending::<Option<()>>();
+        tokio::select! {
+            _ = interval => {}
+            _ = hangup => tracing::info!("SIGHUP received, reloading TLS certificates"),
+        }
+        match settings.load() {
+            Ok(loaded) => *config.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = loaded,
+            Err(e) => tracing::error!("TLS certificate reload failed: {:#}; keeping the previous certificates", e),
+        }
+    }
+}